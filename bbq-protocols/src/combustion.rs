@@ -0,0 +1,653 @@
+// bbq-protocols/src/combustion.rs
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::temperature::Temperature;
+use crate::ProtocolError;
+
+/// Length of a packed MeatStick temperature frame (8 × 13-bit sensors)
+pub const MEATSTICK_FRAME_LEN: usize = 13;
+
+/// Thermistors in a MeatStick/Combustion probe (T1-T8)
+pub const MEATSTICK_SENSOR_COUNT: usize = 8;
+
+/// Highest valid raw sensor value: 369°C, the top of the probe's range
+const MEATSTICK_MAX_RAW: u16 = 7780;
+
+/// MeatStick (Combustion Inc) protocol parser
+/// 
+/// Based on official Combustion Inc documentation:
+/// https://github.com/combustion-inc/combustion-documentation
+pub struct MeatStickProtocol;
+
+impl MeatStickProtocol {
+    /// Parse MeatStick temperature data
+    /// 
+    /// Format (13 bytes total - 104 bits):
+    /// - 8 temperature sensors (13 bits each)
+    /// - Little-endian packed bit fields
+    /// - Temperature = (raw_value * 0.05) - 20 (in Celsius)
+    /// - Range: -20°C to 369°C per sensor
+    /// 
+    /// Sensor layout (Combustion Predictive Probe):
+    /// - Sensors T1-T4: Core temperatures (internal)
+    /// - Sensors T5-T7: Mid-section temperatures
+    /// - Sensor T8: Ambient/surface temperature
+    /// 
+    /// A sensor decoding above the 369°C ceiling has no valid reading and
+    /// comes back as `None`, so one bad sensor never drops the whole frame.
+    pub fn parse_temperature_data(data: &[u8]) -> Result<Vec<Option<Temperature>>, ProtocolError> {
+        Self::validate_frame(data)?;
+        
+        let temperatures = (0..MEATSTICK_SENSOR_COUNT)
+            .map(|sensor_idx| {
+                let raw_temp = Self::raw_sensor_value(data, sensor_idx);
+                
+                // Convert to Celsius: Temperature = (raw_value * 0.05) - 20
+                let temp_celsius = (raw_temp as f32 * 0.05) - 20.0;
+                
+                if raw_temp <= MEATSTICK_MAX_RAW {
+                    Some(Temperature::from_celsius(temp_celsius))
+                } else {
+                    #[cfg(feature = "std")]
+                    tracing::debug!("Sensor T{} out of range: {:.1}°C", sensor_idx + 1, temp_celsius);
+                    None
+                }
+            })
+            .collect();
+        
+        Ok(temperatures)
+    }
+    
+    /// Extract the unsigned 13-bit value of one sensor from the packed frame
+    fn raw_sensor_value(data: &[u8], sensor_idx: usize) -> u16 {
+        let bit_offset = sensor_idx * 13;
+        let byte_offset = bit_offset / 8;
+        // 13 bits starting anywhere in a byte span at most 3 bytes
+        let window = data[byte_offset..(byte_offset + 3).min(MEATSTICK_FRAME_LEN)]
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &byte| (acc << 8) | byte as u32);
+        ((window >> (bit_offset % 8)) & 0x1FFF) as u16
+    }
+    
+    /// Check the length of a packed temperature frame
+    /// 
+    /// Accepts exactly 13 bytes; the frame has no checksum. Shorter packets
+    /// are `TooShort`, longer ones `UnsupportedFormat`.
+    pub fn validate_frame(data: &[u8]) -> Result<(), ProtocolError> {
+        match data.len() {
+            MEATSTICK_FRAME_LEN => Ok(()),
+            got if got < MEATSTICK_FRAME_LEN => Err(ProtocolError::TooShort {
+                needed: MEATSTICK_FRAME_LEN,
+                got,
+            }),
+            got => Err(ProtocolError::UnsupportedFormat { got }),
+        }
+    }
+    
+    /// Get the internal (meat core) temperature
+    /// For Combustion probes, T1-T4 are core sensors
+    /// Returns the deepest core sensor with a reading (typically T4)
+    pub fn get_internal_temp(temperatures: &[Option<Temperature>]) -> Option<Temperature> {
+        // T4 (index 3) is the deepest core sensor, fall back to T3, T2, T1
+        temperatures.iter().take(4).rev().find_map(|&t| t)
+    }
+    
+    /// Get the ambient temperature
+    /// For Combustion probes, T8 (index 7) is the ambient sensor
+    pub fn get_ambient_temp(temperatures: &[Option<Temperature>]) -> Option<Temperature> {
+        temperatures.get(7).copied().flatten()
+    }
+}
+
+/// Byte offset of the packed raw temperatures inside a Combustion probe status packet
+pub const PROBE_STATUS_TEMPERATURE_OFFSET: usize = 8;
+
+/// Byte offset of the mode/ID byte inside a Combustion probe status packet
+pub const PROBE_STATUS_MODE_OFFSET: usize = 21;
+
+/// Byte offset of the battery status / virtual sensors byte inside a Combustion probe status packet
+pub const PROBE_STATUS_VIRTUAL_SENSORS_OFFSET: usize = 22;
+
+/// Byte offset of the 7-byte prediction status inside a Combustion probe status packet
+pub const PROBE_STATUS_PREDICTION_OFFSET: usize = 23;
+
+/// Number of sensors (T1-T6) the probe can pick as its virtual core
+const VIRTUAL_CORE_SENSORS: usize = 6;
+
+/// Physical sensors a Combustion probe currently uses as core, surface and ambient
+///
+/// The probe picks these from how deep it is inserted, so they are more
+/// reliable than assuming T4 is in the meat and T8 is in the air.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VirtualSensors {
+    /// Sensor index (0-based) of the virtual core, T1-T6
+    pub core_idx: usize,
+    /// Sensor index of the virtual surface, T4-T7
+    pub surface_idx: usize,
+    /// Sensor index of the virtual ambient, T5-T8
+    pub ambient_idx: usize,
+}
+
+impl VirtualSensors {
+    /// Temperature of the virtual core sensor, if it has a reading
+    pub fn core_temp(&self, temperatures: &[Option<Temperature>]) -> Option<Temperature> {
+        if self.core_idx >= VIRTUAL_CORE_SENSORS {
+            return None;
+        }
+        Self::reading(temperatures, self.core_idx)
+    }
+    
+    /// Temperature of the virtual surface sensor, if it has a reading
+    pub fn surface_temp(&self, temperatures: &[Option<Temperature>]) -> Option<Temperature> {
+        Self::reading(temperatures, self.surface_idx)
+    }
+    
+    /// Temperature of the virtual ambient sensor, if it has a reading
+    pub fn ambient_temp(&self, temperatures: &[Option<Temperature>]) -> Option<Temperature> {
+        Self::reading(temperatures, self.ambient_idx)
+    }
+    
+    fn reading(temperatures: &[Option<Temperature>], index: usize) -> Option<Temperature> {
+        temperatures.get(index).copied().flatten()
+    }
+}
+
+/// Byte offset of the 4-byte serial number in Combustion advertising data (after the vendor ID)
+const ADVERTISEMENT_SERIAL_OFFSET: usize = 1;
+
+/// Byte offset of the packed raw temperatures in Combustion advertising data (after the vendor ID)
+pub const ADVERTISEMENT_TEMPERATURE_OFFSET: usize = 5;
+
+/// Byte offset of the mode/ID byte in Combustion advertising data (after the vendor ID)
+const ADVERTISEMENT_MODE_OFFSET: usize = 18;
+
+/// Color a Combustion probe is assigned in the Combustion app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum ProbeColor {
+    Yellow,
+    Grey,
+    Red,
+    Orange,
+    Blue,
+    Green,
+    Purple,
+    Pink,
+}
+
+impl ProbeColor {
+    const ALL: [Self; 8] = [
+        Self::Yellow,
+        Self::Grey,
+        Self::Red,
+        Self::Orange,
+        Self::Blue,
+        Self::Green,
+        Self::Purple,
+        Self::Pink,
+    ];
+    
+    /// Stable name used for storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Yellow => "yellow",
+            Self::Grey => "grey",
+            Self::Red => "red",
+            Self::Orange => "orange",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Purple => "purple",
+            Self::Pink => "pink",
+        }
+    }
+    
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|color| color.as_str() == name)
+    }
+    
+    /// Capitalized name for labels ("Red")
+    pub fn label(&self) -> String {
+        let name = self.as_str();
+        name[..1].to_uppercase() + &name[1..]
+    }
+}
+
+/// Which probe of a multi-probe set a packet came from
+///
+/// The ID and color are what the Combustion app shows ("1/yellow"); the serial
+/// number identifies the probe even when its MAC address rotates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeIdentity {
+    /// Factory serial number as hex, only present in advertising data
+    pub serial_number: Option<String>,
+    /// Probe ID, 1-8
+    pub probe_id: u8,
+    pub color: ProbeColor,
+}
+
+impl ProbeIdentity {
+    /// Bits 2-4 of the mode/ID byte are the color, bits 5-7 the ID (0-7 for probes 1-8)
+    fn from_mode_byte(byte: u8, serial_number: Option<String>) -> Self {
+        Self {
+            serial_number,
+            probe_id: ((byte >> 5) & 0x07) + 1,
+            color: ProbeColor::ALL[((byte >> 2) & 0x07) as usize],
+        }
+    }
+    
+    /// Dashboard label, e.g. "Probe 2 (Red)"
+    pub fn label(&self) -> String {
+        probe_label(self.probe_id, self.color)
+    }
+}
+
+/// Label for a probe with the given ID and color
+pub fn probe_label(probe_id: u8, color: ProbeColor) -> String {
+    format!("Probe {} ({})", probe_id, color.label())
+}
+
+/// Operating mode reported in a Combustion probe status packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type), sqlx(rename_all = "snake_case"))]
+pub enum ReadingMode {
+    /// Regular probe in the meat (or a thermometer without a mode byte)
+    #[default]
+    Normal,
+    /// Handheld instant-read thermometer
+    InstantRead,
+    /// The probe reported a fault; temperatures are not usable
+    Error,
+}
+
+impl ReadingMode {
+    /// Bits 0-1 of the mode/ID byte; the reserved value 2 is treated as an error
+    fn from_raw(raw: u8) -> Self {
+        match raw & 0x03 {
+            0 => Self::Normal,
+            1 => Self::InstantRead,
+            _ => Self::Error,
+        }
+    }
+    
+    /// Stable name used for storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::InstantRead => "instant_read",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Combustion prediction engine state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum PredictionState {
+    ProbeNotInserted,
+    ProbeInserted,
+    Warming,
+    Predicting,
+    RemovalPredictionDone,
+    Unknown,
+}
+
+impl PredictionState {
+    fn from_raw(raw: u8) -> Self {
+        match raw {
+            0 => Self::ProbeNotInserted,
+            1 => Self::ProbeInserted,
+            2 => Self::Warming,
+            3 => Self::Predicting,
+            4 => Self::RemovalPredictionDone,
+            _ => Self::Unknown,
+        }
+    }
+    
+    /// Stable name used for storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ProbeNotInserted => "probe_not_inserted",
+            Self::ProbeInserted => "probe_inserted",
+            Self::Warming => "warming",
+            Self::Predicting => "predicting",
+            Self::RemovalPredictionDone => "removal_prediction_done",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Prediction information from a Combustion probe status packet
+///
+/// Temperatures stay in Celsius like the rest of the protocol module.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PredictionStatus {
+    pub state: PredictionState,
+    /// Target (removal) temperature the prediction is working toward
+    pub set_point: Temperature,
+    /// Core temperature when the prediction started heating
+    pub heat_start: Temperature,
+    /// Estimated core temperature at removal
+    pub estimated_core: Temperature,
+    /// Seconds until the set point is reached (only while predicting or done)
+    pub eta_seconds: Option<u32>,
+}
+
+impl MeatStickProtocol {
+    /// Parse the operating mode from a Combustion probe status packet
+    pub fn parse_mode(data: &[u8]) -> Result<ReadingMode, ProtocolError> {
+        match data.get(PROBE_STATUS_MODE_OFFSET) {
+            Some(&byte) => Ok(ReadingMode::from_raw(byte)),
+            None => Err(ProtocolError::TooShort {
+                needed: PROBE_STATUS_MODE_OFFSET + 1,
+                got: data.len(),
+            }),
+        }
+    }
+    
+    /// Parse the probe ID and color from a Combustion probe status packet
+    ///
+    /// Status packets carry no serial number; see `parse_advertisement`.
+    pub fn parse_identity(data: &[u8]) -> Result<ProbeIdentity, ProtocolError> {
+        match data.get(PROBE_STATUS_MODE_OFFSET) {
+            Some(&byte) => Ok(ProbeIdentity::from_mode_byte(byte, None)),
+            None => Err(ProtocolError::TooShort {
+                needed: PROBE_STATUS_MODE_OFFSET + 1,
+                got: data.len(),
+            }),
+        }
+    }
+    
+    /// Parse the serial number, ID and color from Combustion manufacturer data
+    ///
+    /// Format (after the vendor ID):
+    /// - Byte 0: product type
+    /// - Bytes 1-4: serial number (little-endian)
+    /// - Bytes 5-17: packed raw temperatures
+    /// - Byte 18: mode/color/ID, as in the status packet
+    /// - Byte 19: battery status / virtual sensors
+    pub fn parse_advertisement(data: &[u8]) -> Result<ProbeIdentity, ProtocolError> {
+        let Some(&mode) = data.get(ADVERTISEMENT_MODE_OFFSET) else {
+            return Err(ProtocolError::TooShort {
+                needed: ADVERTISEMENT_MODE_OFFSET + 1,
+                got: data.len(),
+            });
+        };
+        let mut serial = [0u8; 4];
+        serial.copy_from_slice(&data[ADVERTISEMENT_SERIAL_OFFSET..ADVERTISEMENT_SERIAL_OFFSET + 4]);
+        let serial_number = format!("{:08X}", u32::from_le_bytes(serial));
+        
+        Ok(ProbeIdentity::from_mode_byte(mode, Some(serial_number)))
+    }
+    
+    /// Parse the virtual sensor selection from a Combustion probe status packet
+    /// 
+    /// Format (byte at offset 22):
+    /// - Bit 0: battery low
+    /// - Bits 1-3: virtual core, 0-5 for T1-T6 (6 and 7 are reserved)
+    /// - Bits 4-5: virtual surface, 0-3 for T4-T7
+    /// - Bits 6-7: virtual ambient, 0-3 for T5-T8
+    pub fn virtual_sensors(data: &[u8]) -> Result<VirtualSensors, ProtocolError> {
+        match data.get(PROBE_STATUS_VIRTUAL_SENSORS_OFFSET) {
+            Some(&byte) => Ok(VirtualSensors {
+                core_idx: ((byte >> 1) & 0x07) as usize,
+                surface_idx: 3 + ((byte >> 4) & 0x03) as usize,
+                ambient_idx: 4 + ((byte >> 6) & 0x03) as usize,
+            }),
+            None => Err(ProtocolError::TooShort {
+                needed: PROBE_STATUS_VIRTUAL_SENSORS_OFFSET + 1,
+                got: data.len(),
+            }),
+        }
+    }
+    
+    /// Parse the prediction status from a Combustion probe status packet
+    /// 
+    /// Format (7 bytes at offset 23 - 56 bits, little-endian packed):
+    /// - Bits 0-3: prediction state
+    /// - Bits 4-5: prediction mode, bits 6-7: prediction type
+    /// - Bits 8-17: set point temperature (raw * 0.1 °C)
+    /// - Bits 18-27: heat start temperature (raw * 0.1 °C)
+    /// - Bits 28-44: prediction value in seconds
+    /// - Bits 45-55: estimated core temperature ((raw * 0.1) - 20 °C)
+    pub fn parse_prediction(data: &[u8]) -> Result<PredictionStatus, ProtocolError> {
+        let end = PROBE_STATUS_PREDICTION_OFFSET + 7;
+        if data.len() < end {
+            return Err(ProtocolError::TooShort { needed: end, got: data.len() });
+        }
+        
+        let mut bytes = [0u8; 8];
+        bytes[..7].copy_from_slice(&data[PROBE_STATUS_PREDICTION_OFFSET..end]);
+        let bits = u64::from_le_bytes(bytes);
+        let field = |shift: u32, width: u32| (bits >> shift) & ((1u64 << width) - 1);
+        
+        let state = PredictionState::from_raw(field(0, 4) as u8);
+        let seconds = field(28, 17) as u32;
+        let eta_seconds = match state {
+            PredictionState::Predicting => Some(seconds),
+            PredictionState::RemovalPredictionDone => Some(0),
+            _ => None,
+        };
+        
+        Ok(PredictionStatus {
+            state,
+            set_point: Temperature::from_celsius(field(8, 10) as f32 * 0.1),
+            heat_start: Temperature::from_celsius(field(18, 10) as f32 * 0.1),
+            estimated_core: Temperature::from_celsius(field(45, 11) as f32 * 0.1 - 20.0),
+            eta_seconds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_meatstick_parsing() {
+        // Simulate room temperature readings (72°F = 22.2°C)
+        // Using Combustion format: (temp_c + 20) / 0.05 = raw
+        // 22.2°C: (22.2 + 20) / 0.05 = 844
+        let raw_value = 844u16;
+        
+        // Create 13-byte packed data for 8 sensors (13 bits each)
+        // Simplified: just putting same value in first few sensors
+        let mut data = vec![0u8; 13];
+        data[0] = (raw_value & 0xFF) as u8;
+        data[1] = ((raw_value >> 8) & 0x1F) as u8;
+        
+        let temps = MeatStickProtocol::parse_temperature_data(&data).unwrap();
+        assert!(!temps.is_empty());
+        
+        // Should be close to 72°F
+        let temp_f = temps[0].unwrap().fahrenheit();
+        assert!((temp_f - 72.0).abs() < 1.0, "Expected ~72°F, got {}", temp_f);
+    }
+    
+    /// Build a probe status packet with the given prediction fields (°C raw units)
+    fn probe_status_packet(state: u64, set_point_raw: u64, seconds: u64, core_raw: u64) -> Vec<u8> {
+        let bits = state | (set_point_raw << 8) | (600 << 18) | (seconds << 28) | (core_raw << 45);
+        let mut packet = vec![0u8; 40];
+        packet[PROBE_STATUS_PREDICTION_OFFSET..PROBE_STATUS_PREDICTION_OFFSET + 7]
+            .copy_from_slice(&bits.to_le_bytes()[..7]);
+        packet
+    }
+    
+    #[test]
+    fn test_prediction_states() {
+        let cases = [
+            (0, PredictionState::ProbeNotInserted, None),
+            (1, PredictionState::ProbeInserted, None),
+            (2, PredictionState::Warming, None),
+            (3, PredictionState::Predicting, Some(2580)),
+            (4, PredictionState::RemovalPredictionDone, Some(0)),
+            (15, PredictionState::Unknown, None),
+        ];
+        
+        for (raw, state, eta) in cases {
+            // Set point 63.0°C (630), 43 minutes left, estimated core 61.0°C ((810 * 0.1) - 20)
+            let packet = probe_status_packet(raw, 630, 2580, 810);
+            let prediction = MeatStickProtocol::parse_prediction(&packet).unwrap();
+            assert_eq!(prediction.state, state);
+            assert_eq!(prediction.eta_seconds, eta);
+            assert!((prediction.set_point.celsius() - 63.0).abs() < 0.01, "set point {}", prediction.set_point);
+            assert!((prediction.set_point.fahrenheit() - 145.4).abs() < 0.1);
+            assert!((prediction.heat_start.celsius() - 60.0).abs() < 0.01);
+            assert!((prediction.estimated_core.celsius() - 61.0).abs() < 0.01);
+        }
+    }
+    
+    #[test]
+    fn test_probe_status_mode() {
+        let mut packet = probe_status_packet(3, 630, 2580, 810);
+        assert_eq!(MeatStickProtocol::parse_mode(&packet).unwrap(), ReadingMode::Normal);
+        
+        // Color and probe ID bits do not affect the mode
+        packet[PROBE_STATUS_MODE_OFFSET] = 0b1010_0101;
+        assert_eq!(MeatStickProtocol::parse_mode(&packet).unwrap(), ReadingMode::InstantRead);
+        packet[PROBE_STATUS_MODE_OFFSET] = 0b0000_0011;
+        assert_eq!(MeatStickProtocol::parse_mode(&packet).unwrap(), ReadingMode::Error);
+        
+        assert!(matches!(
+            MeatStickProtocol::parse_mode(&packet[..PROBE_STATUS_MODE_OFFSET]),
+            Err(ProtocolError::TooShort { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_probe_identity() {
+        let mut packet = probe_status_packet(3, 630, 2580, 810);
+        // Instant read, color 2 (red), ID bits 1 (probe 2)
+        packet[PROBE_STATUS_MODE_OFFSET] = 0b0010_1001;
+        let identity = MeatStickProtocol::parse_identity(&packet).unwrap();
+        assert_eq!(identity, ProbeIdentity { serial_number: None, probe_id: 2, color: ProbeColor::Red });
+        assert_eq!(identity.label(), "Probe 2 (Red)");
+        
+        let mut advertisement = vec![0u8; 20];
+        advertisement[0] = 0x01;
+        advertisement[1..5].copy_from_slice(&0x1000_2A3Bu32.to_le_bytes());
+        advertisement[ADVERTISEMENT_MODE_OFFSET] = 0b1111_1100;
+        let identity = MeatStickProtocol::parse_advertisement(&advertisement).unwrap();
+        assert_eq!(identity.serial_number.as_deref(), Some("10002A3B"));
+        assert_eq!((identity.probe_id, identity.color), (8, ProbeColor::Pink));
+        
+        assert!(matches!(
+            MeatStickProtocol::parse_advertisement(&advertisement[..18]),
+            Err(ProtocolError::TooShort { needed: 19, got: 18 })
+        ));
+        assert_eq!(ProbeColor::parse("grey"), Some(ProbeColor::Grey));
+        assert_eq!(ProbeColor::parse("teal"), None);
+    }
+    
+    #[test]
+    fn test_virtual_sensors() {
+        let mut packet = probe_status_packet(3, 630, 2580, 810);
+        // Battery low, core T3, surface T6, ambient T8
+        packet[PROBE_STATUS_VIRTUAL_SENSORS_OFFSET] = 0b1110_0101;
+        let sensors = MeatStickProtocol::virtual_sensors(&packet).unwrap();
+        assert_eq!(sensors, VirtualSensors { core_idx: 2, surface_idx: 5, ambient_idx: 7 });
+        
+        // Shallow insertion: T4 is out of the meat, the probe still reports T3 as core
+        let mut temps: Vec<Option<Temperature>> = [55.0, 58.0, 61.0, 120.0, 140.0, 160.0, 180.0, 200.0]
+            .iter()
+            .map(|&c| Some(Temperature::from_celsius(c)))
+            .collect();
+        assert_eq!(sensors.core_temp(&temps), temps[2]);
+        assert_eq!(sensors.surface_temp(&temps), temps[5]);
+        assert_eq!(sensors.ambient_temp(&temps), temps[7]);
+        assert_eq!(MeatStickProtocol::get_internal_temp(&temps), temps[3]);
+        
+        // Reserved core values and disconnected sensors give no reading
+        packet[PROBE_STATUS_VIRTUAL_SENSORS_OFFSET] = 0b0000_1100;
+        let sensors = MeatStickProtocol::virtual_sensors(&packet).unwrap();
+        assert_eq!(sensors.core_idx, 6);
+        assert_eq!(sensors.core_temp(&temps), None);
+        assert_eq!(sensors.ambient_temp(&temps[..4]), None);
+        temps[sensors.ambient_idx] = None;
+        assert_eq!(sensors.ambient_temp(&temps), None);
+        temps[7] = None;
+        assert_eq!(MeatStickProtocol::get_ambient_temp(&temps), None);
+        
+        assert!(matches!(
+            MeatStickProtocol::virtual_sensors(&packet[..PROBE_STATUS_VIRTUAL_SENSORS_OFFSET]),
+            Err(ProtocolError::TooShort { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_prediction_short_packet() {
+        assert_eq!(
+            MeatStickProtocol::parse_prediction(&[0u8; 29]).unwrap_err(),
+            ProtocolError::TooShort { needed: PROBE_STATUS_PREDICTION_OFFSET + 7, got: 29 }
+        );
+    }
+    
+    fn valid_frame() -> Vec<u8> {
+        let mut data = vec![0u8; MEATSTICK_FRAME_LEN];
+        data[0] = 0x4C;
+        data[1] = 0x03;
+        data
+    }
+    
+    #[test]
+    fn test_truncated_frame_rejected() {
+        let data = valid_frame();
+        for len in [0, 6, 12] {
+            let err = MeatStickProtocol::parse_temperature_data(&data[..len]).unwrap_err();
+            assert_eq!(err, ProtocolError::TooShort { needed: MEATSTICK_FRAME_LEN, got: len });
+        }
+        // UART echo of a wake-up command
+        assert!(MeatStickProtocol::parse_temperature_data(b"status\r\n").is_err());
+    }
+    
+    #[test]
+    fn test_oversized_frame_rejected() {
+        let mut data = valid_frame();
+        data.push(0);
+        let err = MeatStickProtocol::parse_temperature_data(&data).unwrap_err();
+        assert_eq!(err, ProtocolError::UnsupportedFormat { got: 14 });
+        data.push(0);
+        let err = MeatStickProtocol::parse_temperature_data(&data).unwrap_err();
+        assert!(err.to_string().contains("got 15"), "{}", err);
+    }
+    
+    /// Pack eight raw 13-bit sensor values the way the probe does
+    fn packed_frame(raw: [u16; MEATSTICK_SENSOR_COUNT]) -> Vec<u8> {
+        let bits = raw
+            .iter()
+            .enumerate()
+            .fold(0u128, |acc, (i, &value)| acc | (value as u128 & 0x1FFF) << (i * 13));
+        bits.to_le_bytes()[..MEATSTICK_FRAME_LEN].to_vec()
+    }
+    
+    #[test]
+    fn test_meatstick_range_ends() {
+        // -20°C, 0°C, 369°C and 8191 (389.55°C, past the ceiling) across aligned and unaligned offsets
+        let data = packed_frame([0, 400, 7780, 8191, 7780, 400, 0, 8191]);
+        let temps = MeatStickProtocol::parse_temperature_data(&data).unwrap();
+        let celsius: Vec<Option<f32>> = temps.iter().map(|t| t.map(|t| t.celsius())).collect();
+        let expected = [Some(-20.0), Some(0.0), Some(369.0), None, Some(369.0), Some(0.0), Some(-20.0), None];
+        for (i, (got, want)) in celsius.iter().zip(expected).enumerate() {
+            match (got, want) {
+                (Some(got), Some(want)) => assert!((got - want).abs() < 0.01, "T{}: {} != {}", i + 1, got, want),
+                _ => assert_eq!(*got, want, "T{}", i + 1),
+            }
+        }
+        
+        // Freezer readings are real values, not missing sensors
+        assert_eq!(MeatStickProtocol::get_internal_temp(&temps), temps[2]);
+        assert_eq!(MeatStickProtocol::get_internal_temp(&temps[..2]), temps[1]);
+        assert_eq!(
+            MeatStickProtocol::get_internal_temp(&temps[..1]).map(|t| t.celsius()),
+            Some(-20.0)
+        );
+        // T8 has no reading: no ambient rather than a fake 0
+        assert_eq!(MeatStickProtocol::get_ambient_temp(&temps), None);
+    }
+}
@@ -0,0 +1,52 @@
+// bbq-protocols/src/igrill.rs
+use crate::temperature::Temperature;
+use crate::ProtocolError;
+
+/// Probe jacks on an iGrill v2/v3 (the mini has only the first)
+pub const WEBER_PROBE_COUNT: usize = 4;
+
+/// Raw value an iGrill reports for an empty probe jack
+const IGRILL_UNPLUGGED: u16 = 63536;
+
+/// Parse one iGrill probe temperature value
+///
+/// Format: little-endian i16, tenths of a degree Celsius; 63536 = no
+/// probe plugged in (None). Longer values carry trailing bytes the
+/// temperature does not depend on. `probe` only labels an out-of-range error.
+pub fn parse_igrill_temperature(probe: usize, data: &[u8]) -> Result<Option<Temperature>, ProtocolError> {
+    let Some(bytes) = data.get(..2) else {
+        return Err(ProtocolError::TooShort { needed: 2, got: data.len() });
+    };
+    let raw = u16::from_le_bytes([bytes[0], bytes[1]]);
+    if raw == IGRILL_UNPLUGGED {
+        return Ok(None);
+    }
+
+    let temp_celsius = raw as i16 as f32 / 10.0;
+    // iGrill probes are rated to 300°C (ambient 380°C); allow some margin
+    if !(-40.0..=400.0).contains(&temp_celsius) {
+        return Err(ProtocolError::OutOfRange { sensor: probe, value: temp_celsius });
+    }
+    Ok(Some(Temperature::from_celsius(temp_celsius)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_igrill_temperature_decode() {
+        // Hand-made value, not a capture: 0x0122 = 29.0°C on probe 3
+        let temperature = parse_igrill_temperature(2, &[0x22, 0x01]).unwrap().unwrap();
+        assert!((temperature.celsius() - 29.0).abs() < 0.01, "{:?}", temperature);
+
+        assert_eq!(parse_igrill_temperature(0, &[0x30, 0xF8]), Ok(None), "unplugged");
+        let below_freezing = parse_igrill_temperature(0, &(-55i16).to_le_bytes()).unwrap().unwrap();
+        assert!((below_freezing.celsius() + 5.5).abs() < 0.01);
+        assert!(matches!(parse_igrill_temperature(1, &[0x22]), Err(ProtocolError::TooShort { .. })));
+        assert!(matches!(
+            parse_igrill_temperature(1, &[0x88, 0x13]),
+            Err(ProtocolError::OutOfRange { sensor: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+// bbq-protocols/src/lib.rs
+//! Probe packet parsers: pure byte math over notification and advertisement payloads
+//!
+//! Builds without std (`default-features = false`, needs `alloc`), so firmware
+//! can decode packets at the edge. BLE UUIDs, connections and storage live in
+//! the `bbq-monitor` crate, which re-exports everything here.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub mod combustion;
+pub mod igrill;
+pub mod meater;
+pub mod temperature;
+pub mod thermopro;
+
+pub use combustion::*;
+pub use igrill::*;
+pub use meater::*;
+pub use temperature::{Temperature, TemperatureUnit};
+pub use thermopro::*;
+
+/// Errors from decoding probe packets
+///
+/// Implements `core::error::Error`, so `?` still converts it into
+/// `anyhow::Error` in functions returning `anyhow::Result`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    /// Fewer bytes than the format needs; a later notification may complete it
+    TooShort { needed: usize, got: usize },
+    /// A sensor decoded to an impossible temperature (°C)
+    OutOfRange { sensor: usize, value: f32 },
+    /// The packet length matches no known frame layout for this protocol
+    UnsupportedFormat { got: usize },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort { needed, got } => write!(f, "Frame too short: need {} bytes, got {}", needed, got),
+            Self::OutOfRange { sensor, value } => write!(f, "Sensor {} out of range: {:.1}°C", sensor, value),
+            Self::UnsupportedFormat { got } => write!(f, "Unsupported frame format: got {} bytes", got),
+        }
+    }
+}
+
+impl core::error::Error for ProtocolError {}
+
+/// Notification layouts carrying temperatures, independent of the characteristic they arrive on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Packed MeatStick temperatures (13 bytes)
+    MeatStick,
+    /// Combustion probe status packet, with the packed temperatures at `PROBE_STATUS_TEMPERATURE_OFFSET`
+    CombustionStatus,
+    /// 8-byte MEATER probe packet
+    Meater,
+    /// 8-byte ThermoPro TP25 packet
+    ThermoPro,
+    /// Value of one iGrill probe characteristic, for the jack given (0-based)
+    IGrillProbe(usize),
+}
+
+impl FrameFormat {
+    /// Format of a recorded fixture, by name
+    ///
+    /// Each parser ships `tests/fixtures/<name>.txt` (hex notifications) and
+    /// `<name>.csv` (expected Celsius values) for the replay tests.
+    pub fn for_fixture(name: &str) -> Option<Self> {
+        match name {
+            "meatstick" => Some(Self::MeatStick),
+            "combustion" => Some(Self::CombustionStatus),
+            "meater" => Some(Self::Meater),
+            "thermopro" => Some(Self::ThermoPro),
+            "weber" => Some(Self::IGrillProbe(0)),
+            _ => None,
+        }
+    }
+
+    /// Decode a notification; sensors keep their index, `None` where a sensor has no reading
+    pub fn parse(self, data: &[u8]) -> Result<Vec<Option<Temperature>>, ProtocolError> {
+        match self {
+            Self::MeatStick => MeatStickProtocol::parse_temperature_data(data),
+            Self::CombustionStatus => {
+                let end = PROBE_STATUS_TEMPERATURE_OFFSET + MEATSTICK_FRAME_LEN;
+                match data.get(PROBE_STATUS_TEMPERATURE_OFFSET..end) {
+                    Some(frame) => MeatStickProtocol::parse_temperature_data(frame),
+                    None => Err(ProtocolError::TooShort { needed: end, got: data.len() }),
+                }
+            }
+            Self::Meater if data.len() < 8 => Err(ProtocolError::TooShort { needed: 8, got: data.len() }),
+            Self::Meater => Ok(MeaterProtocol::parse_sensors(data).to_vec()),
+            Self::ThermoPro => ThermoProProtocol::parse_temperature_data(data),
+            Self::IGrillProbe(probe) => parse_igrill_temperature(probe, data).map(|temperature| {
+                let mut temperatures = vec![None; WEBER_PROBE_COUNT];
+                if let Some(slot) = temperatures.get_mut(probe) {
+                    *slot = temperature;
+                }
+                temperatures
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every fixture in tests/fixtures, by the name `FrameFormat::for_fixture` knows it as
+    const FIXTURES: &[&str] = &["meatstick", "combustion", "meater", "thermopro", "weber"];
+
+    /// One replayed notification: what the parser made of it
+    #[derive(Debug)]
+    struct ParsedReading {
+        /// 1-based position among the fixture's notifications
+        frame: usize,
+        temperatures: Result<Vec<Option<Temperature>>, ProtocolError>,
+    }
+
+    impl ParsedReading {
+        /// Same shape as a row of the expected CSV
+        fn to_csv_row(&self) -> String {
+            match &self.temperatures {
+                Ok(temperatures) => std::iter::once(self.frame.to_string())
+                    .chain(temperatures.iter().map(|t| t.map_or("-".to_string(), |t| format!("{:.1}", t.celsius()))))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                Err(_) => format!("{},error", self.frame),
+            }
+        }
+    }
+
+    fn fixture_path(file: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(file)
+    }
+
+    fn parse_hex(line: &str) -> Option<Vec<u8>> {
+        if !line.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..line.len()).step_by(2).map(|i| u8::from_str_radix(line.get(i..i + 2)?, 16).ok()).collect()
+    }
+
+    /// Feed a recorded notification stream (one hex frame per line, `#` comments) through its parser
+    fn replay_fixture(name: &str, path: &std::path::Path) -> Vec<ParsedReading> {
+        let format = FrameFormat::for_fixture(name).unwrap_or_else(|| panic!("no parser registered for fixture {}", name));
+        let contents = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .enumerate()
+            .map(|(i, line)| {
+                let data = parse_hex(line).unwrap_or_else(|| panic!("{}: bad hex on frame {}", path.display(), i + 1));
+                ParsedReading { frame: i + 1, temperatures: format.parse(&data) }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recorded_fixtures_replay() {
+        for name in FIXTURES {
+            let replayed: Vec<String> = replay_fixture(name, &fixture_path(&format!("{}.txt", name)))
+                .iter()
+                .map(ParsedReading::to_csv_row)
+                .collect();
+            let expected = std::fs::read_to_string(fixture_path(&format!("{}.csv", name))).unwrap();
+            let expected: Vec<&str> = expected.lines().skip(1).filter(|l| !l.is_empty()).collect();
+            assert_eq!(replayed, expected, "{} fixture", name);
+        }
+    }
+
+    #[test]
+    fn test_frame_format_dispatch() {
+        assert_eq!(
+            FrameFormat::Meater.parse(&[0xDE, 0x00]),
+            Err(ProtocolError::TooShort { needed: 8, got: 2 })
+        );
+        assert_eq!(
+            FrameFormat::CombustionStatus.parse(&[0; 20]),
+            Err(ProtocolError::TooShort { needed: 21, got: 20 })
+        );
+        let temps = FrameFormat::IGrillProbe(2).parse(&[0x22, 0x01]).unwrap();
+        assert_eq!(temps.len(), WEBER_PROBE_COUNT);
+        assert!((temps[2].unwrap().celsius() - 29.0).abs() < 0.01, "{:?}", temps[2]);
+        assert_eq!(FrameFormat::IGrillProbe(9).parse(&[0x22, 0x01]).unwrap(), [None; WEBER_PROBE_COUNT]);
+        assert_eq!(FrameFormat::for_fixture("kelvin"), None);
+    }
+}
@@ -0,0 +1,175 @@
+// bbq-protocols/src/meater.rs
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::temperature::Temperature;
+use crate::ProtocolError;
+
+/// Probe slots a MEATER Block relays
+pub const MEATER_BLOCK_SLOTS: usize = 4;
+
+/// One Block slot: a flags byte, then the relayed 8-byte probe packet
+const MEATER_BLOCK_SLOT_LEN: usize = 9;
+
+/// Flags bit set while a probe is docked in (or paired to) a Block slot
+const MEATER_BLOCK_PROBE_PRESENT: u8 = 0x01;
+
+/// A probe reading relayed through one MEATER Block slot
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeaterBlockProbe {
+    /// Slot number on the Block, 1-4
+    pub slot: usize,
+    /// Tip and ambient
+    pub temperatures: Vec<Option<Temperature>>,
+    /// The relayed 8-byte probe packet, for duplicate detection
+    pub payload: Vec<u8>,
+}
+
+/// MEATER protocol parser
+/// 
+/// Based on reverse engineering by Nathan Faber:
+/// https://github.com/nathanfaber/meaterble
+pub struct MeaterProtocol;
+
+impl MeaterProtocol {
+    /// Parse MEATER temperature data
+    /// 
+    /// Format (8 bytes total):
+    /// - Bytes 0-1: Tip temperature (little-endian u16)
+    /// - Bytes 2-3: RA ambient reading (little-endian u16)
+    /// - Bytes 4-5: OA ambient reading (little-endian u16)
+    /// - Bytes 6-7: Unknown/reserved
+    /// 
+    /// Temperature conversion:
+    /// - Tip: direct value / 10.0 = Celsius
+    /// - Ambient: calculated from RA and OA using formula
+    pub fn parse_temperature_data(data: &[u8]) -> Result<Vec<Temperature>, ProtocolError> {
+        if data.len() < 8 {
+            return Err(ProtocolError::TooShort { needed: 8, got: data.len() });
+        }
+        Ok(Self::parse_sensors(data).into_iter().flatten().collect())
+    }
+    
+    /// Tip and ambient of an 8-byte probe packet, `None` where out of range
+    pub(crate) fn parse_sensors(data: &[u8]) -> [Option<Temperature>; 2] {
+        // -40°F to 600°F
+        let plausible = |celsius: f32| (-40.0..=315.6).contains(&celsius).then(|| Temperature::from_celsius(celsius));
+        
+        // Parse tip temperature (bytes 0-1)
+        let tip_raw = u16::from_le_bytes([data[0], data[1]]);
+        let tip_celsius = tip_raw as f32 / 10.0;
+        
+        // Parse ambient temperature components
+        let ra_raw = u16::from_le_bytes([data[2], data[3]]);
+        let oa_raw = u16::from_le_bytes([data[4], data[5]]);
+        
+        // Calculate ambient using MEATER formula (from Nathan Faber's work)
+        // ambient = tip + max(0, ((ra - min(48, oa)) * 16 * 589) / 1487)
+        let ambient_raw = tip_raw as i32 + 
+            ((((ra_raw as i32 - oa_raw.min(48) as i32) * 16 * 589) / 1487).max(0));
+        
+        let ambient_celsius = ambient_raw as f32 / 10.0;
+        
+        [plausible(tip_celsius), plausible(ambient_celsius)]
+    }
+    
+    /// Parse the MEATER Block's aggregate notification
+    /// 
+    /// Format (36 bytes total): 4 slots of 9 bytes, one per probe
+    /// - Byte 0: flags (bit 0 = probe present)
+    /// - Bytes 1-8: that probe's temperature packet, as a probe sends it
+    /// 
+    /// Returns only the slots holding a probe. Each probe's sensors keep
+    /// their index (tip 0, ambient 1), `None` where out of range.
+    pub fn parse_block_data(data: &[u8]) -> Result<Vec<MeaterBlockProbe>, ProtocolError> {
+        let expected = MEATER_BLOCK_SLOTS * MEATER_BLOCK_SLOT_LEN;
+        match data.len() {
+            got if got < expected => return Err(ProtocolError::TooShort { needed: expected, got }),
+            got if got > expected => return Err(ProtocolError::UnsupportedFormat { got }),
+            _ => {}
+        }
+        
+        Ok(data
+            .chunks_exact(MEATER_BLOCK_SLOT_LEN)
+            .enumerate()
+            .filter(|(_, slot)| slot[0] & MEATER_BLOCK_PROBE_PRESENT != 0)
+            .map(|(slot, bytes)| MeaterBlockProbe {
+                slot: slot + 1,
+                temperatures: Self::parse_sensors(&bytes[1..]).to_vec(),
+                payload: bytes[1..].to_vec(),
+            })
+            .collect())
+    }
+    
+    /// Address a probe relayed by a Block is stored under: `<block address>#probe<slot>`
+    pub fn block_probe_address(block_address: &str, slot: usize) -> String {
+        format!("{}#probe{}", block_address, slot)
+    }
+    
+    /// Get internal/tip temperature (first sensor)
+    pub fn get_internal_temp(temperatures: &[Temperature]) -> Option<Temperature> {
+        temperatures.first().copied()
+    }
+    
+    /// Get ambient temperature (second sensor)
+    pub fn get_ambient_temp(temperatures: &[Temperature]) -> Option<Temperature> {
+        if temperatures.len() >= 2 {
+            Some(temperatures[1])
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_meater_parsing() {
+        // Simulate MEATER data: tip at 72°F (22.2°C = 222 raw)
+        // ambient at 80°F (26.7°C)
+        let data = vec![
+            0xDE, 0x00, // Tip: 222 (22.2°C = 72°F)
+            0x00, 0x01, // RA: 256
+            0x00, 0x01, // OA: 256
+            0x00, 0x00, // Reserved
+        ];
+        
+        let temps = MeaterProtocol::parse_temperature_data(&data).unwrap();
+        assert_eq!(temps.len(), 2);
+        
+        // Check tip temperature
+        assert!((temps[0].celsius() - 22.2).abs() < 0.01);
+        assert!((temps[0].fahrenheit() - 72.0).abs() < 1.0);
+    }
+    
+    #[test]
+    fn test_meater_block_slots() {
+        let mut data = vec![0u8; MEATER_BLOCK_SLOTS * 9];
+        // Slot 1: tip 22.2°C, ambient well above
+        data[0] = 0x01;
+        data[1..9].copy_from_slice(&[0xDE, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        // Slot 2 empty (flag clear) despite leftover bytes
+        data[10] = 0xDE;
+        // Slot 3: tip 61.0°C with an ambient past the 600°F ceiling
+        data[18] = 0x01;
+        data[19..27].copy_from_slice(&[0x62, 0x02, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]);
+        
+        let probes = MeaterProtocol::parse_block_data(&data).unwrap();
+        assert_eq!(probes.iter().map(|p| p.slot).collect::<Vec<_>>(), [1, 3]);
+        assert!((probes[0].temperatures[0].unwrap().celsius() - 22.2).abs() < 0.01);
+        assert!(probes[0].temperatures[1].is_some());
+        assert!((probes[1].temperatures[0].unwrap().celsius() - 61.0).abs() < 0.01);
+        assert_eq!(probes[1].temperatures[1], None, "sensor keeps its index when missing");
+        
+        assert_eq!(MeaterProtocol::block_probe_address("AA:BB", 3), "AA:BB#probe3");
+        assert_eq!(
+            MeaterProtocol::parse_block_data(&data[..8]),
+            Err(ProtocolError::TooShort { needed: 36, got: 8 })
+        );
+        data.push(0);
+        assert_eq!(MeaterProtocol::parse_block_data(&data), Err(ProtocolError::UnsupportedFormat { got: 37 }));
+    }
+}
@@ -0,0 +1,301 @@
+// bbq-protocols/src/temperature.rs
+use core::fmt;
+
+/// Unit temperatures are shown in (API, WebSocket, exports)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum TemperatureUnit {
+    Celsius,
+    #[default]
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Parse a config value ("celsius"/"c" or "fahrenheit"/"f", any case)
+    pub fn parse(unit: &str) -> Option<Self> {
+        match unit.trim().to_lowercase().as_str() {
+            "celsius" | "c" => Some(Self::Celsius),
+            "fahrenheit" | "f" => Some(Self::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Celsius => "celsius",
+            Self::Fahrenheit => "fahrenheit",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+        }
+    }
+
+    /// A temperature difference (e.g. a tolerance) in this unit, as Celsius degrees
+    pub fn delta_to_celsius(&self, delta: f32) -> f32 {
+        match self {
+            Self::Celsius => delta,
+            Self::Fahrenheit => delta * 5.0 / 9.0,
+        }
+    }
+
+    /// A temperature difference in Celsius degrees, in this unit
+    pub fn delta_from_celsius(&self, delta: f32) -> f32 {
+        match self {
+            Self::Celsius => delta,
+            Self::Fahrenheit => delta * 9.0 / 5.0,
+        }
+    }
+}
+
+/// Probe resolution, and the step canonical temperatures are kept at
+pub const TEMPERATURE_RESOLUTION_C: f32 = 0.05;
+
+/// Steps of `TEMPERATURE_RESOLUTION_C` per Celsius degree
+const STEPS_PER_DEGREE: i32 = 20;
+
+/// Slack for comparing computed Celsius values (a threshold minus an offset) against readings
+///
+/// Half a resolution step: anything closer than that is the same canonical value.
+pub const THRESHOLD_EPSILON_C: f32 = TEMPERATURE_RESOLUTION_C / 2.0;
+
+/// A temperature, stored in Celsius
+///
+/// Probes report Celsius, so parsers and the database keep that and only
+/// the display edges convert. The value is fixed-point, a whole number of
+/// 0.05°C steps, so converting to Fahrenheit and back always lands on the
+/// same value and comparisons never flap on float noise. Serializes as the
+/// bare Celsius number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(from = "f32", into = "f32"))]
+pub struct Temperature(i32);
+
+impl Temperature {
+    pub fn from_celsius(celsius: f32) -> Self {
+        Self::from_celsius_f64(celsius as f64)
+    }
+
+    fn from_celsius_f64(celsius: f64) -> Self {
+        Self(round_to_i32(celsius * STEPS_PER_DEGREE as f64))
+    }
+
+    pub fn from_fahrenheit(fahrenheit: f32) -> Self {
+        Self::from_celsius_f64((fahrenheit as f64 - 32.0) * 5.0 / 9.0)
+    }
+
+    /// A value entered in `unit` (e.g. a cook target from the API)
+    pub fn from_unit(value: f32, unit: TemperatureUnit) -> Self {
+        match unit {
+            TemperatureUnit::Celsius => Self::from_celsius(value),
+            TemperatureUnit::Fahrenheit => Self::from_fahrenheit(value),
+        }
+    }
+
+    pub fn celsius(self) -> f32 {
+        self.0 as f32 / STEPS_PER_DEGREE as f32
+    }
+
+    pub fn fahrenheit(self) -> f32 {
+        self.hundredths(TemperatureUnit::Fahrenheit) as f32 / 100.0
+    }
+
+    /// Exact value in hundredths of a degree of `unit` (one step is 5 hundredths °C, 9 hundredths °F)
+    fn hundredths(self, unit: TemperatureUnit) -> i32 {
+        match unit {
+            TemperatureUnit::Celsius => self.0 * 5,
+            TemperatureUnit::Fahrenheit => self.0 * 9 + 3200,
+        }
+    }
+
+    /// Value for display in `unit`, rounded half away from zero to one decimal
+    pub fn in_unit(self, unit: TemperatureUnit) -> f32 {
+        let hundredths = self.hundredths(unit);
+        let tenths = (hundredths + hundredths.signum() * 5) / 10;
+        tenths as f32 / 10.0
+    }
+
+    /// Whether this reading has reached `threshold`, compared in canonical steps
+    pub fn reaches(self, threshold: Temperature) -> bool {
+        self >= threshold
+    }
+}
+
+impl From<f32> for Temperature {
+    fn from(celsius: f32) -> Self {
+        Self::from_celsius(celsius)
+    }
+}
+
+impl From<Temperature> for f32 {
+    fn from(temperature: Temperature) -> Self {
+        temperature.celsius()
+    }
+}
+
+// Stored as REAL Celsius, so existing rows and SQL aggregates keep working
+#[cfg(feature = "sqlx")]
+mod sqlite {
+    use super::{Temperature, STEPS_PER_DEGREE};
+    use sqlx::encode::IsNull;
+    use sqlx::error::BoxDynError;
+    use sqlx::sqlite::{Sqlite, SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+
+    impl sqlx::Type<Sqlite> for Temperature {
+        fn type_info() -> SqliteTypeInfo {
+            <f64 as sqlx::Type<Sqlite>>::type_info()
+        }
+
+        fn compatible(ty: &SqliteTypeInfo) -> bool {
+            <f64 as sqlx::Type<Sqlite>>::compatible(ty)
+        }
+    }
+
+    impl<'q> sqlx::Encode<'q, Sqlite> for Temperature {
+        fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+            let celsius = self.0 as f64 / STEPS_PER_DEGREE as f64;
+            <f64 as sqlx::Encode<Sqlite>>::encode_by_ref(&celsius, buf)
+        }
+    }
+
+    impl<'r> sqlx::Decode<'r, Sqlite> for Temperature {
+        fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+            Ok(Self::from_celsius_f64(<f64 as sqlx::Decode<Sqlite>>::decode(value)?))
+        }
+    }
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}°C", self.in_unit(TemperatureUnit::Celsius))
+    }
+}
+
+/// `f64::round` (half away from zero) saturating into an i32, which `core` lacks
+fn round_to_i32(value: f64) -> i32 {
+    let whole = value as i64;
+    let fraction = value - whole as f64;
+    let rounded = if fraction >= 0.5 {
+        whole.saturating_add(1)
+    } else if fraction <= -0.5 {
+        whole.saturating_sub(1)
+    } else {
+        whole
+    };
+    rounded.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let boiling = Temperature::from_celsius(100.0);
+        assert_eq!(boiling.fahrenheit(), 212.0);
+        assert_eq!(Temperature::from_fahrenheit(212.0), boiling);
+        assert_eq!(Temperature::from_unit(-40.0, TemperatureUnit::Fahrenheit).celsius(), -40.0);
+        assert_eq!(boiling.in_unit(TemperatureUnit::Celsius), 100.0);
+        assert_eq!(boiling.to_string(), "100.0°C");
+    }
+
+    #[test]
+    fn test_unit_round_trips() {
+        for unit in [TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit] {
+            for value in [-40.0, 0.0, 63.5, 93.3, 225.0, 1000.0] {
+                let stored = Temperature::from_unit(value, unit);
+                assert!((stored.in_unit(unit) - value).abs() < 1e-3, "{} {:?}", value, unit);
+            }
+            assert!((unit.delta_to_celsius(unit.delta_from_celsius(5.0)) - 5.0).abs() < 1e-6);
+        }
+    }
+
+    /// Every 0.05°C step a probe can report, from -40°C to 1000°C
+    fn every_step() -> impl Iterator<Item = Temperature> {
+        (-40 * STEPS_PER_DEGREE..=1000 * STEPS_PER_DEGREE).map(Temperature)
+    }
+
+    #[test]
+    fn test_round_trips_are_stable_at_every_step() {
+        for stored in every_step() {
+            // Converting to either unit and back lands on the same canonical value
+            assert_eq!(Temperature::from_celsius(stored.celsius()), stored);
+            assert_eq!(Temperature::from_fahrenheit(stored.fahrenheit()), stored);
+            #[cfg(feature = "serde")]
+            assert_eq!(serde_json::from_str::<Temperature>(&serde_json::to_string(&stored).unwrap()).unwrap(), stored);
+            for unit in [TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit] {
+                // Display is one decimal and never more than half a tenth off
+                let shown = stored.in_unit(unit);
+                assert_eq!((shown * 10.0).round() / 10.0, shown);
+                let exact = stored.hundredths(unit) as f32 / 100.0;
+                assert!((shown - exact).abs() <= 0.05 + 1e-4, "{:?} {} {}", unit, shown, exact);
+                // Reading a shown value back is off by at most one step
+                let back = Temperature::from_unit(shown, unit);
+                assert!((back.0 - stored.0).abs() <= 1, "{:?} {:?} {:?}", unit, stored, back);
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_rounds_half_away_from_zero() {
+        assert_eq!(Temperature::from_celsius(63.45).in_unit(TemperatureUnit::Celsius), 63.5);
+        assert_eq!(Temperature::from_celsius(-0.05).in_unit(TemperatureUnit::Celsius), -0.1);
+        // 0.05°C is 32.09°F, 1.15°C is 34.07°F
+        assert_eq!(Temperature::from_celsius(0.05).in_unit(TemperatureUnit::Fahrenheit), 32.1);
+        assert_eq!(Temperature::from_celsius(1.15).in_unit(TemperatureUnit::Fahrenheit), 34.1);
+        // -38.75°C is -37.75°F
+        assert_eq!(Temperature::from_celsius(-38.75).in_unit(TemperatureUnit::Fahrenheit), -37.8);
+        assert_eq!(Temperature::from_celsius(73.85).to_string(), "73.9°C");
+    }
+
+    #[test]
+    fn test_threshold_fires_on_the_reading_shown_as_target() {
+        for target_f in (1000..=4500).map(|tenths| tenths as f32 / 10.0) {
+            let target = Temperature::from_fahrenheit(target_f);
+            let mut fired = false;
+            let (low, high) = (Temperature::from_fahrenheit(target_f - 2.0), Temperature::from_fahrenheit(target_f + 2.0));
+            for reading in (low.0..=high.0).map(Temperature) {
+                let reaches = reading.reaches(target);
+                // Never fires while the display still shows less than a tenth below the target
+                if reading.in_unit(TemperatureUnit::Fahrenheit) < target_f - 0.1 {
+                    assert!(!reaches, "{} fired at {:?}", target_f, reading);
+                }
+                // Always fires once the display is past the target
+                if reading.in_unit(TemperatureUnit::Fahrenheit) > target_f + 0.1 {
+                    assert!(reaches, "{} missed {:?}", target_f, reading);
+                }
+                assert!(!fired || reaches, "{} flapped at {:?}", target_f, reading);
+                fired |= reaches;
+            }
+            assert!(fired);
+        }
+        // 165°F is 73.89°C, held as 73.9°C: fires on that step and not the one below
+        let target = Temperature::from_fahrenheit(165.0);
+        assert!(Temperature::from_celsius(73.9).reaches(target));
+        assert!(!Temperature::from_celsius(73.85).reaches(target));
+        assert_eq!(Temperature::from_celsius(73.9).in_unit(TemperatureUnit::Fahrenheit), 165.0);
+    }
+
+    #[test]
+    fn test_rounding_matches_std() {
+        for value in [0.0, 0.5, -0.5, 1.49999, 2.5, -2.5, 844.999, -1e12, 1e12, f64::NAN, f64::INFINITY] {
+            let expected = value.round() as i32;
+            assert_eq!(round_to_i32(value), expected, "{}", value);
+        }
+        for step in -100_000..100_000 {
+            let value = step as f64 * 0.0137;
+            assert_eq!(round_to_i32(value), value.round() as i32, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_unit_parse() {
+        assert_eq!(TemperatureUnit::parse("Celsius"), Some(TemperatureUnit::Celsius));
+        assert_eq!(TemperatureUnit::parse("F"), Some(TemperatureUnit::Fahrenheit));
+        assert_eq!(TemperatureUnit::parse("kelvin"), None);
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_string(&TemperatureUnit::Celsius).unwrap(), "\"celsius\"");
+    }
+}
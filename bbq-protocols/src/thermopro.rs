@@ -0,0 +1,107 @@
+// bbq-protocols/src/thermopro.rs
+use alloc::vec::Vec;
+
+use crate::temperature::Temperature;
+use crate::ProtocolError;
+
+/// Number of probe jacks on a ThermoPro TP25
+pub const THERMOPRO_PROBE_COUNT: usize = 4;
+
+/// Raw value reported for an unplugged probe jack
+const THERMOPRO_UNPLUGGED: u16 = 0xFFFF;
+
+/// ThermoPro TP25 protocol parser
+pub struct ThermoProProtocol;
+
+impl ThermoProProtocol {
+    /// Parse a ThermoPro TP25 temperature notification
+    /// 
+    /// Format (8 bytes total):
+    /// - 4 probes, signed little-endian i16 each
+    /// - Temperature = raw / 10.0 (in Celsius)
+    /// - 0xFFFF = probe not plugged in
+    /// 
+    /// Returns one entry per probe jack (None = unplugged) so indices stay stable.
+    pub fn parse_temperature_data(data: &[u8]) -> Result<Vec<Option<Temperature>>, ProtocolError> {
+        let expected = THERMOPRO_PROBE_COUNT * 2;
+        if data.len() < expected {
+            return Err(ProtocolError::TooShort { needed: expected, got: data.len() });
+        }
+        if data.len() > expected {
+            return Err(ProtocolError::UnsupportedFormat { got: data.len() });
+        }
+        
+        let mut temperatures = Vec::with_capacity(THERMOPRO_PROBE_COUNT);
+        for (probe, chunk) in data.chunks_exact(2).enumerate() {
+            let raw = u16::from_le_bytes([chunk[0], chunk[1]]);
+            if raw == THERMOPRO_UNPLUGGED {
+                temperatures.push(None);
+                continue;
+            }
+            
+            let temp_celsius = raw as i16 as f32 / 10.0;
+            
+            // TP25 probes are rated -10°C to 300°C; allow some margin
+            if !(-40.0..=343.3).contains(&temp_celsius) {
+                return Err(ProtocolError::OutOfRange {
+                    sensor: probe,
+                    value: temp_celsius,
+                });
+            }
+            temperatures.push(Some(Temperature::from_celsius(temp_celsius)));
+        }
+        
+        Ok(temperatures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // TP25 notifications built from the documented layout (4 × i16 LE, tenths of °C)
+    const TP25_TWO_PROBES: [u8; 8] = [
+        0xDE, 0x00, // Probe 1: 22.2°C
+        0x6A, 0x09, // Probe 2: 241.0°C
+        0xFF, 0xFF, // Probe 3: unplugged
+        0xFF, 0xFF, // Probe 4: unplugged
+    ];
+    const TP25_BELOW_FREEZING: [u8; 8] = [
+        0xCE, 0xFF, // Probe 1: -5.0°C
+        0xFF, 0xFF,
+        0xFF, 0xFF,
+        0xFF, 0xFF,
+    ];
+    
+    #[test]
+    fn test_thermopro_parsing() {
+        let temps = ThermoProProtocol::parse_temperature_data(&TP25_TWO_PROBES).unwrap();
+        assert_eq!(temps.len(), THERMOPRO_PROBE_COUNT);
+        assert!((temps[0].unwrap().celsius() - 22.2).abs() < 0.01, "{:?}", temps[0]);
+        assert!((temps[1].unwrap().celsius() - 241.0).abs() < 0.01, "{:?}", temps[1]);
+        assert!((temps[1].unwrap().fahrenheit() - 465.8).abs() < 0.1, "{:?}", temps[1]);
+        assert_eq!(temps[2], None);
+        assert_eq!(temps[3], None);
+        
+        let temps = ThermoProProtocol::parse_temperature_data(&TP25_BELOW_FREEZING).unwrap();
+        assert!((temps[0].unwrap().celsius() + 5.0).abs() < 0.01, "{:?}", temps[0]);
+        assert!(temps[1..].iter().all(Option::is_none));
+    }
+    
+    #[test]
+    fn test_thermopro_invalid_frames() {
+        assert!(ThermoProProtocol::parse_temperature_data(&TP25_TWO_PROBES[..6]).is_err());
+        
+        let mut oversized = TP25_TWO_PROBES.to_vec();
+        oversized.push(0);
+        assert!(ThermoProProtocol::parse_temperature_data(&oversized).is_err());
+        
+        // 0x7FFF = 3276.7°C, not a real reading
+        let mut corrupted = TP25_TWO_PROBES;
+        corrupted[2..4].copy_from_slice(&[0xFF, 0x7F]);
+        assert!(matches!(
+            ThermoProProtocol::parse_temperature_data(&corrupted),
+            Err(ProtocolError::OutOfRange { sensor: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,32 @@
+// build.rs
+include!("src/git_hash.rs");
+include!("src/schema_fingerprint.rs");
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    println!("cargo:rustc-env=BBQ_GIT_HASH={}", git_hash(std::path::Path::new(&manifest_dir)));
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    println!("cargo:rerun-if-changed=build.rs");
+    check_query_cache(std::path::Path::new(&manifest_dir));
+}
+
+/// Fail the build when the migrations changed after the sqlx query cache was prepared
+///
+/// The query macros check against the cache, so a stale one would let a query
+/// naming a renamed column compile. Skipped when `DATABASE_URL` is set: the
+/// macros then check against that database instead.
+fn check_query_cache(manifest_dir: &std::path::Path) {
+    println!("cargo:rerun-if-changed=src/migrations.rs");
+    println!("cargo:rerun-if-changed={}", SCHEMA_FINGERPRINT_FILE);
+    println!("cargo:rerun-if-env-changed=DATABASE_URL");
+    if std::env::var_os("DATABASE_URL").is_some() {
+        return;
+    }
+    
+    let migrations = std::fs::read(manifest_dir.join("src/migrations.rs")).unwrap_or_default();
+    let prepared = std::fs::read_to_string(manifest_dir.join(SCHEMA_FINGERPRINT_FILE)).unwrap_or_default();
+    if prepared.trim() != schema_fingerprint(&migrations) {
+        panic!("src/migrations.rs changed since the sqlx query cache (.sqlx) was prepared; run `cargo xtask prepare-sqlx`");
+    }
+}
@@ -97,6 +97,19 @@ class RustBLEService extends ChangeNotifier {
     }
   }
 
+  /// Stop the Rust background monitor so it can be started again later
+  void stopBackgroundMonitor() {
+    if (!_backgroundMonitorStarted) return;
+    
+    final code = _ffi.stopBackgroundMonitor();
+    if (code == bbqOk) {
+      _backgroundMonitorStarted = false;
+      debugPrint('🛑 Background monitor stopped');
+    } else {
+      debugPrint('⚠️ Failed to stop background monitor: ${_ffi.errorMessage(code)}');
+    }
+  }
+
   String _getDefaultConfig() {
     return '''
 [device]
@@ -247,6 +260,7 @@ sync_interval_secs = 300
   void dispose() {
     _pollTimer?.cancel();
     _scanTimer?.cancel();
+    stopBackgroundMonitor();
     super.dispose();
   }
 }
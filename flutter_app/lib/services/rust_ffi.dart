@@ -5,7 +5,7 @@ import 'dart:convert';
 import 'package:ffi/ffi.dart';
 
 // Type definitions for C functions
-typedef ValidateLicenseC = ffi.Int8 Function(ffi.Pointer<Utf8>);
+typedef ValidateLicenseC = ffi.Int32 Function(ffi.Pointer<Utf8>);
 typedef ValidateLicenseDart = int Function(ffi.Pointer<Utf8>);
 
 typedef GetLicenseInfoC = ffi.Pointer<Utf8> Function(ffi.Pointer<Utf8>);
@@ -14,15 +14,21 @@ typedef GetLicenseInfoDart = ffi.Pointer<Utf8> Function(ffi.Pointer<Utf8>);
 typedef FreeLicenseJsonC = ffi.Void Function(ffi.Pointer<Utf8>);
 typedef FreeLicenseJsonDart = void Function(ffi.Pointer<Utf8>);
 
-typedef BleInitializeC = ffi.Int8 Function();
+typedef ActivateLicenseC = ffi.Int32 Function(ffi.Pointer<Utf8>, ffi.Pointer<Utf8>);
+typedef ActivateLicenseDart = int Function(ffi.Pointer<Utf8>, ffi.Pointer<Utf8>);
+
+typedef BleInitializeC = ffi.Int32 Function();
 typedef BleInitializeDart = int Function();
 
-typedef BleStartScanC = ffi.Int8 Function();
+typedef BleStartScanC = ffi.Int32 Function();
 typedef BleStartScanDart = int Function();
 
-typedef BleStopScanC = ffi.Int8 Function();
+typedef BleStopScanC = ffi.Int32 Function();
 typedef BleStopScanDart = int Function();
 
+typedef BleSelectAdapterC = ffi.Int32 Function(ffi.Int32);
+typedef BleSelectAdapterDart = int Function(int);
+
 typedef BleGetDevicesC = ffi.Pointer<Utf8> Function();
 typedef BleGetDevicesDart = ffi.Pointer<Utf8> Function();
 
@@ -44,9 +50,39 @@ typedef DbGetHistoryDart = ffi.Pointer<Utf8> Function(ffi.Pointer<Utf8>, ffi.Poi
 typedef DbFreeJsonC = ffi.Void Function(ffi.Pointer<Utf8>);
 typedef DbFreeJsonDart = void Function(ffi.Pointer<Utf8>);
 
-typedef StartBackgroundMonitorC = ffi.Int8 Function(ffi.Pointer<Utf8>, ffi.Pointer<Utf8>);
+typedef StartBackgroundMonitorC = ffi.Int32 Function(ffi.Pointer<Utf8>, ffi.Pointer<Utf8>);
 typedef StartBackgroundMonitorDart = int Function(ffi.Pointer<Utf8>, ffi.Pointer<Utf8>);
 
+typedef StopBackgroundMonitorC = ffi.Int32 Function();
+typedef StopBackgroundMonitorDart = int Function();
+
+typedef BbqShutdownRuntimeC = ffi.Void Function();
+typedef BbqShutdownRuntimeDart = void Function();
+
+typedef BbqLastErrorC = ffi.Int32 Function();
+typedef BbqLastErrorDart = int Function();
+
+typedef BbqErrorMessageC = ffi.Pointer<Utf8> Function(ffi.Int32);
+typedef BbqErrorMessageDart = ffi.Pointer<Utf8> Function(int);
+
+// Status codes returned by the Rust FFI (see src/ffi_error.rs)
+const int bbqOk = 0;
+const int bbqErrNoAdapter = -1;
+const int bbqErrBleOff = -2;
+const int bbqErrAlreadyRunning = -3;
+const int bbqErrBlePermission = -4;
+const int bbqErrBleUnsupported = -5;
+const int bbqErrBleBusy = -6;
+const int bbqErrBle = -7;
+const int bbqErrNotInitialized = -8;
+const int bbqErrInvalidArgument = -9;
+const int bbqErrRuntime = -10;
+const int bbqErrDatabase = -11;
+const int bbqErrConfig = -12;
+const int bbqErrInvalidLicense = -13;
+const int bbqErrSerialization = -14;
+const int bbqErrIo = -15;
+
 class RustFFI {
   late ffi.DynamicLibrary _lib;
   
@@ -54,11 +90,13 @@ class RustFFI {
   late ValidateLicenseDart validateLicense;
   late GetLicenseInfoDart getLicenseInfo;
   late FreeLicenseJsonDart freeLicenseJson;
+  late ActivateLicenseDart activateLicense;
   
   // BLE functions
   late BleInitializeDart bleInitialize;
   late BleStartScanDart bleStartScan;
   late BleStopScanDart bleStopScan;
+  late BleSelectAdapterDart bleSelectAdapter;
   late BleGetDevicesDart bleGetDevices;
   late BleFreeDevicesJsonDart bleFreeDevicesJson;
   
@@ -71,6 +109,12 @@ class RustFFI {
   
   // Background monitor
   late StartBackgroundMonitorDart startBackgroundMonitor;
+  late StopBackgroundMonitorDart stopBackgroundMonitor;
+  
+  // Call once on app exit, after all other calls have returned
+  late BbqShutdownRuntimeDart bbqShutdownRuntime;
+  late BbqLastErrorDart bbqLastError;
+  late BbqErrorMessageDart bbqErrorMessage;
   
   static RustFFI? _instance;
   
@@ -105,11 +149,13 @@ class RustFFI {
     validateLicense = _lib.lookupFunction<ValidateLicenseC, ValidateLicenseDart>('validate_license');
     getLicenseInfo = _lib.lookupFunction<GetLicenseInfoC, GetLicenseInfoDart>('get_license_info');
     freeLicenseJson = _lib.lookupFunction<FreeLicenseJsonC, FreeLicenseJsonDart>('free_license_json');
+    activateLicense = _lib.lookupFunction<ActivateLicenseC, ActivateLicenseDart>('activate_license');
     
     // BLE functions
     bleInitialize = _lib.lookupFunction<BleInitializeC, BleInitializeDart>('ble_initialize');
     bleStartScan = _lib.lookupFunction<BleStartScanC, BleStartScanDart>('ble_start_scan');
     bleStopScan = _lib.lookupFunction<BleStopScanC, BleStopScanDart>('ble_stop_scan');
+    bleSelectAdapter = _lib.lookupFunction<BleSelectAdapterC, BleSelectAdapterDart>('ble_select_adapter');
     bleGetDevices = _lib.lookupFunction<BleGetDevicesC, BleGetDevicesDart>('ble_get_devices');
     bleFreeDevicesJson = _lib.lookupFunction<BleFreeDevicesJsonC, BleFreeDevicesJsonDart>('ble_free_devices_json');
     
@@ -122,6 +168,21 @@ class RustFFI {
     
     // Background monitor
     startBackgroundMonitor = _lib.lookupFunction<StartBackgroundMonitorC, StartBackgroundMonitorDart>('start_background_monitor');
+    stopBackgroundMonitor = _lib.lookupFunction<StopBackgroundMonitorC, StopBackgroundMonitorDart>('stop_background_monitor');
+    bbqShutdownRuntime = _lib.lookupFunction<BbqShutdownRuntimeC, BbqShutdownRuntimeDart>('bbq_shutdown_runtime');
+    bbqLastError = _lib.lookupFunction<BbqLastErrorC, BbqLastErrorDart>('bbq_last_error');
+    bbqErrorMessage = _lib.lookupFunction<BbqErrorMessageC, BbqErrorMessageDart>('bbq_error_message');
+  }
+  
+  /// Description of a status code, or of the last null-returning call if omitted
+  String errorMessage([int? code]) {
+    final ptr = bbqErrorMessage(code ?? bbqLastError());
+    if (ptr.address == 0) return 'Unknown error';
+    try {
+      return ptr.toDartString();
+    } finally {
+      dbFreeJson(ptr);
+    }
   }
   
   // Helper methods to handle string conversion and memory management
@@ -130,7 +191,7 @@ class RustFFI {
     final keyPtr = key.toNativeUtf8();
     try {
       final result = validateLicense(keyPtr);
-      return result == 1;
+      return result == bbqOk;
     } finally {
       malloc.free(keyPtr);
     }
@@ -153,19 +214,36 @@ class RustFFI {
     }
   }
   
+  /// Validate a Premium key and save it to the config file
+  bool activateLicenseKey(String configPath, String key) {
+    final pathPtr = configPath.toNativeUtf8();
+    final keyPtr = key.toNativeUtf8();
+    try {
+      return activateLicense(pathPtr, keyPtr) == bbqOk;
+    } finally {
+      malloc.free(pathPtr);
+      malloc.free(keyPtr);
+    }
+  }
+  
   bool initializeBle() {
     final result = bleInitialize();
-    return result == 1;
+    return result == bbqOk;
   }
   
   bool startBleScan() {
     final result = bleStartScan();
-    return result == 1;
+    return result == bbqOk;
   }
   
   bool stopBleScan() {
     final result = bleStopScan();
-    return result == 1;
+    return result == bbqOk;
+  }
+  
+  bool selectBleAdapter(int index) {
+    final result = bleSelectAdapter(index);
+    return result == bbqOk;
   }
   
   List<Map<String, dynamic>> getBleDevices() {
@@ -274,10 +352,15 @@ class RustFFI {
     
     try {
       final result = startBackgroundMonitor(dbPathPtr, configPathPtr);
-      return result == 1;
+      return result == bbqOk;
     } finally {
       malloc.free(dbPathPtr);
       malloc.free(configPathPtr);
     }
   }
+  
+  /// Stop background monitoring; safe to call when it is not running
+  bool stopBackgroundMonitoring() {
+    return stopBackgroundMonitor() == bbqOk;
+  }
 }
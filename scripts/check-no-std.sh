@@ -0,0 +1,21 @@
+#!/bin/sh
+# Check that bbq-protocols builds without std, the way firmware depends on it.
+#
+# Builds for a bare-metal target when it is installed (default: the ESP32-C3's
+# riscv32imc-unknown-none-elf; add it with `rustup target add`), otherwise for
+# the host with std switched off, which still rejects any use of std.
+set -eu
+cd "$(dirname "$0")/.."
+
+TARGET="${NO_STD_TARGET:-riscv32imc-unknown-none-elf}"
+if rustup target list --installed 2>/dev/null | grep -qx "$TARGET"; then
+    set -- --target "$TARGET"
+else
+    echo "⚠️  $TARGET is not installed; checking the no_std build for the host instead" >&2
+    set --
+fi
+
+cargo check -p bbq-protocols --no-default-features "$@"
+cargo check -p bbq-protocols --no-default-features --features serde "$@"
+cargo test -p bbq-protocols --no-default-features
+echo "✅ bbq-protocols builds without std"
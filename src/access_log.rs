@@ -0,0 +1,121 @@
+// src/access_log.rs
+use anyhow::{Context, Result};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::warn;
+
+/// One API request, written as a JSON line
+#[derive(Debug, Serialize)]
+pub struct AccessLogEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Peer address, "-" when the connection info is unavailable
+    pub ip: String,
+    pub method: String,
+    /// Path without the query string, so tokens in queries stay out of the log
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+/// Append-only access log, separate from the tracing output
+///
+/// Every request is written regardless of the log level, for auditing remote access.
+pub struct AccessLog {
+    file: Mutex<File>,
+}
+
+impl AccessLog {
+    /// Open (or create) the log file for appending
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create access log directory {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open access log {}", path.display()))?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    pub fn write(&self, entry: &AccessLogEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .context("Failed to write access log")
+    }
+}
+
+/// Middleware recording each request once its response is ready
+pub async fn log_access(State(log): State<Arc<AccessLog>>, request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let timestamp = Utc::now();
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+
+    let response = next.run(request).await;
+
+    let entry = AccessLogEntry {
+        timestamp,
+        ip,
+        method,
+        path,
+        status: response.status().as_u16(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    // File writes block, so keep them off the async worker threads
+    match tokio::task::spawn_blocking(move || log.write(&entry)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("⚠️  {:#}", e),
+        Err(e) => warn!("⚠️  Access log writer failed: {}", e),
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_append_as_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs/access.log");
+        for status in [200, 404] {
+            let log = AccessLog::open(&path).unwrap();
+            log.write(&AccessLogEntry {
+                timestamp: Utc::now(),
+                ip: "192.168.1.20".to_string(),
+                method: "GET".to_string(),
+                path: "/api/devices".to_string(),
+                status,
+                duration_ms: 3,
+            })
+            .unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2, "reopening appends");
+        assert_eq!(lines[0]["ip"], "192.168.1.20");
+        assert_eq!(lines[1]["status"], 404);
+    }
+}
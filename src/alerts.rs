@@ -0,0 +1,885 @@
+// src/alerts.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+
+use crate::analytics::preset_target_f;
+use crate::database::Database;
+use crate::device_capabilities::{default_primary_sensor, sensor_layout, ProbeCapabilities, SensorRole};
+use crate::fanout::{Fanout, TargetSuggestion};
+use crate::license_store::LicenseManager;
+use crate::temperature::{Temperature, TemperatureUnit, THRESHOLD_EPSILON_C};
+use crate::web_server::TemperatureUpdate;
+
+/// Buffered alert events per subscriber
+const ALERT_CHANNEL_CAPACITY: usize = 64;
+
+/// Largest offset (°F) an approaching-target rule may fire before the target
+pub const MAX_TARGET_OFFSET_F: f32 = 50.0;
+
+/// Condition an alert rule watches for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertKind {
+    /// Sensor reached or passed the target (e.g. meat is done)
+    AboveTarget(Temperature),
+    /// Sensor dropped to or below the target (e.g. the pit is dying)
+    BelowTarget(Temperature),
+    /// Ambient reported alongside the sensor reached or passed the threshold
+    AmbientAbove(Temperature),
+    /// Sensor came within `offset` Celsius degrees of the device's cook target
+    ApproachingTarget { offset: f32 },
+    /// Sensor reached the device's cook target
+    TargetReached,
+    /// Device battery dropped to or below `percent`
+    BatteryBelow { percent: u8 },
+}
+
+/// Name of an alert kind in the API and the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKindName {
+    AboveTarget,
+    BelowTarget,
+    AmbientAbove,
+    ApproachingTarget,
+    TargetReached,
+    BatteryBelow,
+}
+
+impl AlertKindName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AboveTarget => "above_target",
+            Self::BelowTarget => "below_target",
+            Self::AmbientAbove => "ambient_above",
+            Self::ApproachingTarget => "approaching_target",
+            Self::TargetReached => "target_reached",
+            Self::BatteryBelow => "battery_below",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "above_target" => Some(Self::AboveTarget),
+            "below_target" => Some(Self::BelowTarget),
+            "ambient_above" => Some(Self::AmbientAbove),
+            "approaching_target" => Some(Self::ApproachingTarget),
+            "target_reached" => Some(Self::TargetReached),
+            "battery_below" => Some(Self::BatteryBelow),
+            _ => None,
+        }
+    }
+}
+
+impl AlertKind {
+    /// Rebuild a kind from its stored value (see `stored_value`)
+    pub fn from_stored(name: AlertKindName, value: f32) -> Self {
+        match name {
+            AlertKindName::AboveTarget => Self::AboveTarget(Temperature::from_celsius(value)),
+            AlertKindName::BelowTarget => Self::BelowTarget(Temperature::from_celsius(value)),
+            AlertKindName::AmbientAbove => Self::AmbientAbove(Temperature::from_celsius(value)),
+            AlertKindName::ApproachingTarget => Self::ApproachingTarget { offset: value },
+            AlertKindName::TargetReached => Self::TargetReached,
+            AlertKindName::BatteryBelow => Self::BatteryBelow { percent: value.clamp(0.0, 100.0).round() as u8 },
+        }
+    }
+
+    /// Threshold in Celsius, the offset in Celsius degrees for relative kinds, or a battery percentage
+    pub fn stored_value(&self) -> f32 {
+        match *self {
+            Self::AboveTarget(t) | Self::BelowTarget(t) | Self::AmbientAbove(t) => t.celsius(),
+            Self::ApproachingTarget { offset } => offset,
+            Self::TargetReached => 0.0,
+            Self::BatteryBelow { percent } => percent as f32,
+        }
+    }
+
+    pub fn name(&self) -> AlertKindName {
+        match self {
+            Self::AboveTarget(_) => AlertKindName::AboveTarget,
+            Self::BelowTarget(_) => AlertKindName::BelowTarget,
+            Self::AmbientAbove(_) => AlertKindName::AmbientAbove,
+            Self::ApproachingTarget { .. } => AlertKindName::ApproachingTarget,
+            Self::TargetReached => AlertKindName::TargetReached,
+            Self::BatteryBelow { .. } => AlertKindName::BatteryBelow,
+        }
+    }
+
+    /// Whether the threshold follows the device's cook target
+    pub fn is_relative(&self) -> bool {
+        matches!(self, Self::ApproachingTarget { .. } | Self::TargetReached)
+    }
+
+    /// Whether the rule watches the battery level rather than a temperature
+    pub fn is_battery(&self) -> bool {
+        matches!(self, Self::BatteryBelow { .. })
+    }
+
+    /// Fixed threshold (None for kinds relative to the cook target and battery kinds)
+    pub fn threshold(&self) -> Option<Temperature> {
+        match *self {
+            Self::AboveTarget(t) | Self::BelowTarget(t) | Self::AmbientAbove(t) => Some(t),
+            Self::ApproachingTarget { .. } | Self::TargetReached | Self::BatteryBelow { .. } => None,
+        }
+    }
+
+    /// Offset below the cook target, in Celsius degrees
+    pub fn offset(&self) -> Option<f32> {
+        match *self {
+            Self::ApproachingTarget { offset } => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Threshold given the device's cook target; None while a relative kind has no target
+    pub fn resolve(&self, target: Option<Temperature>) -> Option<Temperature> {
+        match *self {
+            Self::ApproachingTarget { offset } => target.map(|t| Temperature::from_celsius(t.celsius() - offset)),
+            Self::TargetReached => target,
+            _ => self.threshold(),
+        }
+    }
+}
+
+/// A threshold on one sensor of one device
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub id: i64,
+    pub device_address: String,
+    pub sensor_index: usize,
+    pub kind: AlertKind,
+    /// Degrees Celsius the value must move back past the threshold before the rule can fire again
+    pub hysteresis: f32,
+    /// Degrees Celsius an above-target rule fires early, for meat that keeps rising once pulled
+    pub carryover: f32,
+}
+
+/// A rule that crossed its threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub rule_id: i64,
+    pub device_address: String,
+    pub sensor_index: usize,
+    pub kind: AlertKindName,
+    /// Threshold crossed; a percentage for battery_below
+    pub threshold: f32,
+    /// Value that crossed the threshold; the battery percentage for battery_below
+    pub temperature: f32,
+    /// Degrees before `threshold` the alert fired to allow for carryover
+    #[serde(default)]
+    pub carryover: f32,
+    /// Unit of `threshold`, `temperature` and `carryover`
+    pub unit: TemperatureUnit,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl AlertEvent {
+    /// The same event with temperatures converted to `unit`
+    pub fn in_unit(mut self, unit: TemperatureUnit) -> Self {
+        if self.kind == AlertKindName::BatteryBelow {
+            return self;
+        }
+        let from = self.unit;
+        self.threshold = Temperature::from_unit(self.threshold, from).in_unit(unit);
+        self.temperature = Temperature::from_unit(self.temperature, from).in_unit(unit);
+        self.carryover = unit.delta_from_celsius(from.delta_to_celsius(self.carryover));
+        self.unit = unit;
+        self
+    }
+}
+
+/// Battery level (percent) the default low-battery alert fires at
+pub const DEFAULT_LOW_BATTERY_PERCENT: u8 = 15;
+
+/// Percentage points a battery must climb past the threshold (a fresh battery) before the rule re-arms
+const BATTERY_REARM_PERCENT: u8 = 5;
+
+/// How far (°F) below the probe's rated ambient limit the default protection alert fires
+pub const AMBIENT_PROTECTION_MARGIN_F: f32 = 25.0;
+
+/// Last (value, ambient) seen per (device, sensor)
+type LatestValues = HashMap<(String, usize), (Temperature, Option<Temperature>)>;
+
+/// Evaluates readings against the alert rules
+///
+/// A rule fires once when its threshold is crossed and is re-armed only after
+/// the value leaves the hysteresis band, so a probe hovering at the target
+/// does not alert on every packet. Relative rules resolve their threshold from
+/// the device's cook target each time and stay inactive while it has none.
+pub struct AlertEngine {
+    rules: RwLock<Vec<AlertRule>>,
+    /// Cook target per device, for rules relative to it
+    targets: RwLock<HashMap<String, Temperature>>,
+    /// Rules that fired and have not re-armed yet
+    fired: Mutex<HashSet<i64>>,
+    /// Last value and ambient per (device, sensor), to re-check rules when a target moves
+    latest: Mutex<LatestValues>,
+    /// Rules held quiet until the given time (not persisted)
+    snoozed: Mutex<HashMap<i64, DateTime<Utc>>>,
+    tx: broadcast::Sender<AlertEvent>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let (tx, _rx) = broadcast::channel(ALERT_CHANNEL_CAPACITY);
+        Self {
+            rules: RwLock::new(rules),
+            targets: RwLock::new(HashMap::new()),
+            fired: Mutex::new(HashSet::new()),
+            latest: Mutex::new(HashMap::new()),
+            snoozed: Mutex::new(HashMap::new()),
+            tx,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AlertEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn rules(&self) -> Vec<AlertRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn add_rule(&self, rule: AlertRule) {
+        self.rules.write().unwrap().push(rule);
+    }
+
+    /// Drop a rule; returns it, or None when there is no such rule
+    pub fn remove_rule(&self, id: i64) -> Option<AlertRule> {
+        let mut rules = self.rules.write().unwrap();
+        let index = rules.iter().position(|r| r.id == id)?;
+        self.fired.lock().unwrap().remove(&id);
+        self.snoozed.lock().unwrap().remove(&id);
+        Some(rules.remove(index))
+    }
+
+    /// Hold a rule quiet until `until` (None ends a snooze early)
+    ///
+    /// The rule is re-armed, so it fires again once the snooze ends if the
+    /// value is still past the threshold. Returns false when there is no such rule.
+    pub fn snooze(&self, id: i64, until: Option<DateTime<Utc>>) -> bool {
+        if !self.rules.read().unwrap().iter().any(|r| r.id == id) {
+            return false;
+        }
+        self.fired.lock().unwrap().remove(&id);
+        let mut snoozed = self.snoozed.lock().unwrap();
+        match until {
+            Some(until) => snoozed.insert(id, until),
+            None => snoozed.remove(&id),
+        };
+        true
+    }
+
+    /// When a rule's snooze ends, if it is snoozed at `now`
+    pub fn snoozed_until(&self, id: i64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.snoozed.lock().unwrap().get(&id).copied().filter(|until| *until > now)
+    }
+
+    /// Rules snoozed at `at`
+    fn snoozed_at(&self, at: DateTime<Utc>) -> HashSet<i64> {
+        let mut snoozed = self.snoozed.lock().unwrap();
+        snoozed.retain(|_, until| *until > at);
+        snoozed.keys().copied().collect()
+    }
+
+    pub fn target(&self, device_address: &str) -> Option<Temperature> {
+        self.targets.read().unwrap().get(device_address).copied()
+    }
+
+    /// Set or clear a device's cook target and re-check its relative rules right away
+    ///
+    /// Rules are checked against the last value seen, so moving the target can
+    /// fire a rule or re-arm one that already fired; clearing it deactivates them.
+    pub fn set_target(&self, device_address: &str, target: Option<Temperature>) -> Vec<AlertEvent> {
+        {
+            let mut targets = self.targets.write().unwrap();
+            match target {
+                Some(t) => targets.insert(device_address.to_string(), t),
+                None => targets.remove(device_address),
+            };
+        }
+
+        let now = Utc::now();
+        let snoozed = self.snoozed_at(now);
+        let rules = self.rules.read().unwrap();
+        let mut fired = self.fired.lock().unwrap();
+        let latest = self.latest.lock().unwrap();
+        let mut events = Vec::new();
+
+        for rule in rules
+            .iter()
+            .filter(|r| r.device_address == device_address && r.kind.is_relative() && !snoozed.contains(&r.id))
+        {
+            match latest.get(&(device_address.to_string(), rule.sensor_index)) {
+                Some(&(value, ambient)) => {
+                    events.extend(Self::evaluate(rule, value, ambient, target, &mut fired, now));
+                }
+                None => {
+                    fired.remove(&rule.id);
+                }
+            }
+        }
+
+        drop((rules, fired, latest));
+        self.publish(&events);
+        events
+    }
+
+    /// Check one reading against every rule for its sensor; fired alerts are also broadcast
+    pub fn process(
+        &self,
+        device_address: &str,
+        sensor_index: usize,
+        temperature: Temperature,
+        ambient: Option<Temperature>,
+        timestamp: DateTime<Utc>,
+    ) -> Vec<AlertEvent> {
+        self.latest
+            .lock()
+            .unwrap()
+            .insert((device_address.to_string(), sensor_index), (temperature, ambient));
+        let target = self.target(device_address);
+        let snoozed = self.snoozed_at(timestamp);
+        let rules = self.rules.read().unwrap();
+        let mut fired = self.fired.lock().unwrap();
+
+        let events: Vec<AlertEvent> = rules
+            .iter()
+            .filter(|r| r.device_address == device_address && r.sensor_index == sensor_index)
+            .filter(|r| !r.kind.is_battery() && !snoozed.contains(&r.id))
+            .filter_map(|rule| Self::evaluate(rule, temperature, ambient, target, &mut fired, timestamp))
+            .collect();
+
+        drop((rules, fired));
+        self.publish(&events);
+        events
+    }
+
+    /// Check a device's battery level against its battery rules; fired alerts are also broadcast
+    pub fn process_battery(&self, device_address: &str, level: u8, timestamp: DateTime<Utc>) -> Vec<AlertEvent> {
+        let snoozed = self.snoozed_at(timestamp);
+        let rules = self.rules.read().unwrap();
+        let mut fired = self.fired.lock().unwrap();
+        let mut events = Vec::new();
+
+        for rule in rules.iter().filter(|r| r.device_address == device_address && !snoozed.contains(&r.id)) {
+            let AlertKind::BatteryBelow { percent } = rule.kind else { continue };
+            if level <= percent && fired.insert(rule.id) {
+                events.push(AlertEvent {
+                    rule_id: rule.id,
+                    device_address: rule.device_address.clone(),
+                    sensor_index: rule.sensor_index,
+                    kind: AlertKindName::BatteryBelow,
+                    threshold: percent as f32,
+                    temperature: level as f32,
+                    carryover: 0.0,
+                    unit: TemperatureUnit::Celsius,
+                    timestamp,
+                });
+            } else if level > percent.saturating_add(BATTERY_REARM_PERCENT) && fired.remove(&rule.id) {
+                debug!("Alert rule {} re-armed", rule.id);
+            }
+        }
+
+        drop((rules, fired));
+        self.publish(&events);
+        events
+    }
+
+    /// Check one rule against a value, updating the fired set; returns the event if it fires
+    fn evaluate(
+        rule: &AlertRule,
+        temperature: Temperature,
+        ambient: Option<Temperature>,
+        target: Option<Temperature>,
+        fired: &mut HashSet<i64>,
+        timestamp: DateTime<Utc>,
+    ) -> Option<AlertEvent> {
+        let Some(threshold) = rule.kind.resolve(target) else {
+            // Relative rule with no target: inactive, and starts armed once one is set
+            if fired.remove(&rule.id) {
+                debug!("Alert rule {} deactivated, no cook target", rule.id);
+            }
+            return None;
+        };
+        let (value, above, carryover) = match rule.kind {
+            AlertKind::AboveTarget(_) => (Some(temperature), true, rule.carryover.max(0.0)),
+            AlertKind::BelowTarget(_) => (Some(temperature), false, 0.0),
+            AlertKind::AmbientAbove(_) => (ambient, true, 0.0),
+            AlertKind::ApproachingTarget { .. } | AlertKind::TargetReached => (Some(temperature), true, 0.0),
+            AlertKind::BatteryBelow { .. } => (None, false, 0.0),
+        };
+        let value = value?;
+
+        let (v, t, band) = (value.celsius(), threshold.celsius(), rule.hysteresis.max(0.0));
+        // Readings and thresholds sit on the 0.05°C grid; the epsilon absorbs float
+        // noise from the offsets, so a 165.0°F target fires on the reading shown as 165.0
+        let eps = THRESHOLD_EPSILON_C;
+        let (triggered, cleared) = if above {
+            // Carryover moves the trigger point down: pull now, coast to the target
+            let trigger = t - carryover;
+            (v >= trigger - eps, v < trigger - band - eps)
+        } else {
+            (v <= t + eps, v > t + band + eps)
+        };
+
+        if triggered && fired.insert(rule.id) {
+            return Some(AlertEvent {
+                rule_id: rule.id,
+                device_address: rule.device_address.clone(),
+                sensor_index: rule.sensor_index,
+                kind: rule.kind.name(),
+                threshold: t,
+                temperature: v,
+                carryover,
+                unit: TemperatureUnit::Celsius,
+                timestamp,
+            });
+        }
+        if cleared && fired.remove(&rule.id) {
+            debug!("Alert rule {} re-armed", rule.id);
+        }
+        None
+    }
+
+    fn publish(&self, events: &[AlertEvent]) {
+        for event in events {
+            if event.kind == AlertKindName::BatteryBelow {
+                info!("🪫 Alert {}: {} battery at {}%", event.rule_id, event.device_address, event.temperature);
+                let _ = self.tx.send(event.clone());
+                continue;
+            }
+            info!(
+                "🚨 Alert {}: {} sensor {} {} ({} vs {})",
+                event.rule_id,
+                event.device_address,
+                event.sensor_index,
+                event.kind.as_str(),
+                Temperature::from_celsius(event.temperature),
+                Temperature::from_celsius(event.threshold)
+            );
+            let _ = self.tx.send(event.clone());
+        }
+    }
+}
+
+/// Feed every live update into the engine until the channel closes
+pub async fn run_alert_engine(
+    engine: Arc<AlertEngine>,
+    license: Arc<LicenseManager>,
+    mut rx: broadcast::Receiver<TemperatureUpdate>,
+) {
+    loop {
+        match rx.recv().await {
+            // Checked per update, so activating Premium turns alerts on without a restart
+            Ok(_) if !license.current().features.alerts => {}
+            Ok(update) => {
+                engine.process(
+                    &update.device_address,
+                    update.sensor_index,
+                    Temperature::from_unit(update.temperature, update.unit),
+                    update.ambient_temp.map(|t| Temperature::from_unit(t, update.unit)),
+                    update.timestamp,
+                );
+                if let Some(level) = update.battery_level {
+                    engine.process_battery(&update.device_address, level, update.timestamp);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Alert engine lagged, skipped {} updates", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                debug!("Update channel closed, stopping alert engine");
+                break;
+            }
+        }
+    }
+}
+
+/// A device stored for the first time, or still waiting for its default rules, as the monitor detected it
+#[derive(Debug, Clone)]
+pub struct FirstSeenDevice {
+    pub device_address: String,
+    pub capabilities: ProbeCapabilities,
+}
+
+/// First-seen devices waiting for their defaults
+const FIRST_SEEN_CHANNEL_CAPACITY: usize = 16;
+
+/// Degrees Celsius the ambient must drop below the protection threshold before it alerts again
+const AMBIENT_PROTECTION_HYSTERESIS_C: f32 = 5.0;
+
+/// Channel the monitor reports first-seen devices on; the web server, which owns the alert engine, receives
+pub fn first_seen_channel() -> (mpsc::Sender<FirstSeenDevice>, mpsc::Receiver<FirstSeenDevice>) {
+    mpsc::channel(FIRST_SEEN_CHANNEL_CAPACITY)
+}
+
+/// Default (sensor, kind, hysteresis) rules for a device with these capabilities
+///
+/// Probes that report an ambient temperature get a rule just below their
+/// rated ambient limit; battery-powered devices get a low-battery rule.
+pub fn default_rules(capabilities: &ProbeCapabilities) -> Vec<(usize, AlertKind, f32)> {
+    let mut rules = Vec::new();
+    let sensors = capabilities.sensor_count;
+    let reports_ambient = (0..sensors).any(|i| sensor_layout(sensors, i).0 == SensorRole::Ambient);
+    if reports_ambient && capabilities.max_ambient_temp_f > AMBIENT_PROTECTION_MARGIN_F {
+        let limit = Temperature::from_fahrenheit(capabilities.max_ambient_temp_f - AMBIENT_PROTECTION_MARGIN_F);
+        rules.push((default_primary_sensor(sensors), AlertKind::AmbientAbove(limit), AMBIENT_PROTECTION_HYSTERESIS_C));
+    }
+    if capabilities.battery_life_hours.is_some() {
+        rules.push((0, AlertKind::BatteryBelow { percent: DEFAULT_LOW_BATTERY_PERCENT }, 0.0));
+    }
+    rules
+}
+
+/// What `bootstrap_defaults` set up for a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefaultsBootstrap {
+    pub rules: Vec<AlertRule>,
+    pub suggestion: Option<TargetSuggestion>,
+}
+
+/// Give a first-seen device its default alerts and a target suggestion for `default_meat`
+///
+/// Recorded per device, so it runs at most once: alerts the user deletes stay
+/// deleted. The suggestion is made on the first sighting only. Without an
+/// alert engine (no Premium) the rules stay pending and are created the first
+/// time the device is bootstrapped with one. Returns None when there was
+/// nothing left to do.
+pub async fn bootstrap_defaults(
+    db: &Database,
+    engine: Option<&AlertEngine>,
+    device: &FirstSeenDevice,
+    default_meat: Option<&str>,
+) -> anyhow::Result<Option<DefaultsBootstrap>> {
+    let first_sighting = db.record_alert_bootstrap(&device.device_address).await?;
+
+    let mut rules = Vec::new();
+    let mut rules_created = false;
+    if let Some(engine) = engine {
+        if db.alert_rules_pending(&device.device_address).await? {
+            for (sensor_index, kind, hysteresis) in default_rules(&device.capabilities) {
+                let rule = db.insert_alert_rule(&device.device_address, sensor_index, kind, hysteresis, 0.0).await?;
+                engine.add_rule(rule.clone());
+                rules.push(rule);
+            }
+            db.record_alert_rules_created(&device.device_address).await?;
+            rules_created = true;
+        }
+    }
+    if !first_sighting && !rules_created {
+        return Ok(None);
+    }
+
+    let suggestion = default_meat.filter(|_| first_sighting).and_then(|meat| {
+        let target_f = preset_target_f(meat)?;
+        Some(TargetSuggestion {
+            device_address: device.device_address.clone(),
+            meat: meat.trim().to_lowercase(),
+            target: target_f,
+            unit: TemperatureUnit::Fahrenheit,
+        })
+    });
+
+    info!(
+        "🔔 Default alerts for {}: {} rules{}{}",
+        device.device_address,
+        rules.len(),
+        if engine.is_none() { " (pending Premium)" } else { "" },
+        suggestion.as_ref().map_or(String::new(), |s| format!(", suggesting {} for {}", Temperature::from_fahrenheit(s.target), s.meat))
+    );
+    Ok(Some(DefaultsBootstrap { rules, suggestion }))
+}
+
+/// Bootstrap every first-seen device the monitor reports until the channel closes
+pub async fn run_bootstrap(
+    db: Arc<Database>,
+    engine: Option<Arc<AlertEngine>>,
+    license: Arc<LicenseManager>,
+    fanout: Arc<Fanout>,
+    default_meat: Option<String>,
+    mut first_seen: mpsc::Receiver<FirstSeenDevice>,
+) {
+    while let Some(device) = first_seen.recv().await {
+        let engine = engine.as_deref().filter(|_| license.current().features.alerts);
+        match bootstrap_defaults(&db, engine, &device, default_meat.as_deref()).await {
+            Ok(Some(bootstrap)) => {
+                if !bootstrap.rules.is_empty() {
+                    fanout.settings_changed(&device.device_address);
+                }
+                if let Some(suggestion) = bootstrap.suggestion {
+                    fanout.suggest_target(suggestion);
+                }
+            }
+            Ok(None) => debug!("{} already had its default alerts", device.device_address),
+            Err(e) => warn!("⚠️  Default alerts for {} failed: {:#}", device.device_address, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: i64, kind: AlertKind, hysteresis: f32) -> AlertRule {
+        AlertRule {
+            id,
+            device_address: "AA".to_string(),
+            sensor_index: 0,
+            kind,
+            hysteresis,
+            carryover: 0.0,
+        }
+    }
+
+    fn feed(engine: &AlertEngine, celsius: f32, ambient: Option<f32>) -> Vec<i64> {
+        engine
+            .process("AA", 0, Temperature::from_celsius(celsius), ambient.map(Temperature::from_celsius), Utc::now())
+            .iter()
+            .map(|e| e.rule_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_above_target_fires_once_until_rearmed() {
+        let engine = AlertEngine::new(vec![rule(1, AlertKind::AboveTarget(Temperature::from_celsius(90.0)), 2.0)]);
+        let mut events = engine.subscribe();
+
+        assert!(feed(&engine, 85.0, None).is_empty());
+        assert_eq!(feed(&engine, 90.0, None), vec![1]);
+        assert!(feed(&engine, 91.0, None).is_empty());
+        // Dipping inside the band does not re-arm
+        assert!(feed(&engine, 88.5, None).is_empty());
+        assert!(feed(&engine, 90.5, None).is_empty());
+        // Leaving the band does
+        assert!(feed(&engine, 87.0, None).is_empty());
+        assert_eq!(feed(&engine, 90.0, None), vec![1]);
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, AlertKindName::AboveTarget);
+        assert_eq!(event.in_unit(TemperatureUnit::Fahrenheit).threshold, 194.0);
+    }
+
+    #[test]
+    fn test_carryover_fires_before_target() {
+        // Prime rib: target 135°F, expected to coast 10°F after pulling
+        let target = Temperature::from_fahrenheit(135.0);
+        let carryover = TemperatureUnit::Fahrenheit.delta_to_celsius(10.0);
+        let engine = AlertEngine::new(vec![AlertRule {
+            carryover,
+            ..rule(1, AlertKind::AboveTarget(target), 0.5)
+        }]);
+
+        assert!(feed(&engine, Temperature::from_fahrenheit(124.9).celsius(), None).is_empty());
+        let events = engine.process("AA", 0, Temperature::from_fahrenheit(125.0), None, Utc::now());
+        assert_eq!(events.len(), 1);
+
+        let event = events[0].clone().in_unit(TemperatureUnit::Fahrenheit);
+        assert!((event.threshold - 135.0).abs() < 1e-3);
+        assert!((event.temperature - 125.0).abs() < 1e-3);
+        assert!((event.carryover - 10.0).abs() < 1e-3);
+
+        // Re-arms relative to the early trigger point
+        assert!(feed(&engine, Temperature::from_fahrenheit(124.7).celsius(), None).is_empty());
+        assert!(feed(&engine, Temperature::from_fahrenheit(124.0).celsius(), None).is_empty());
+        assert_eq!(feed(&engine, Temperature::from_fahrenheit(125.5).celsius(), None), vec![1]);
+    }
+
+    #[test]
+    fn test_below_target_and_ambient_rules() {
+        let engine = AlertEngine::new(vec![
+            rule(1, AlertKind::BelowTarget(Temperature::from_celsius(100.0)), 5.0),
+            rule(2, AlertKind::AmbientAbove(Temperature::from_celsius(150.0)), 5.0),
+        ]);
+
+        assert!(feed(&engine, 110.0, Some(140.0)).is_empty());
+        assert_eq!(feed(&engine, 99.0, Some(151.0)), vec![1, 2]);
+        // No ambient value leaves ambient rules untouched
+        assert!(feed(&engine, 106.0, None).is_empty());
+        assert_eq!(feed(&engine, 100.0, Some(151.0)), vec![1]);
+    }
+
+    #[test]
+    fn test_rules_only_match_their_sensor() {
+        let engine = AlertEngine::new(vec![rule(1, AlertKind::AboveTarget(Temperature::from_celsius(50.0)), 1.0)]);
+        let events = engine.process("AA", 1, Temperature::from_celsius(60.0), None, Utc::now());
+        assert!(events.is_empty());
+        let events = engine.process("BB", 0, Temperature::from_celsius(60.0), None, Utc::now());
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_snoozed_rule_fires_again_when_snooze_ends() {
+        let engine = AlertEngine::new(vec![rule(1, AlertKind::AboveTarget(Temperature::from_celsius(90.0)), 2.0)]);
+        assert_eq!(feed(&engine, 91.0, None), vec![1]);
+
+        let now = Utc::now();
+        assert!(engine.snooze(1, Some(now + chrono::Duration::minutes(10))));
+        assert!(!engine.snooze(7, Some(now)), "unknown rule");
+        assert!(engine.snoozed_until(1, now).is_some());
+        assert!(feed(&engine, 92.0, None).is_empty());
+
+        // Still past the threshold once the snooze is over: alert again
+        let later = now + chrono::Duration::minutes(11);
+        let events = engine.process("AA", 0, Temperature::from_celsius(92.0), None, later);
+        assert_eq!(events.len(), 1);
+        assert_eq!(engine.snoozed_until(1, later), None);
+
+        assert_eq!(engine.remove_rule(1).map(|r| r.id), Some(1));
+        assert!(engine.remove_rule(1).is_none());
+        assert!(engine.rules().is_empty());
+    }
+
+    fn relative_engine() -> AlertEngine {
+        let offset = TemperatureUnit::Fahrenheit.delta_to_celsius(5.0);
+        let band = TemperatureUnit::Fahrenheit.delta_to_celsius(2.0);
+        AlertEngine::new(vec![
+            rule(1, AlertKind::ApproachingTarget { offset }, band),
+            rule(2, AlertKind::TargetReached, band),
+        ])
+    }
+
+    fn feed_f(engine: &AlertEngine, fahrenheit: f32) -> Vec<i64> {
+        feed(engine, Temperature::from_fahrenheit(fahrenheit).celsius(), None)
+    }
+
+    #[test]
+    fn test_relative_rules_follow_target() {
+        let engine = relative_engine();
+        assert!(engine.set_target("AA", Some(Temperature::from_fahrenheit(203.0))).is_empty());
+
+        assert!(feed_f(&engine, 197.0).is_empty());
+        let events = engine.process("AA", 0, Temperature::from_fahrenheit(198.5), None, Utc::now());
+        assert_eq!(events.len(), 1);
+        let event = events[0].clone().in_unit(TemperatureUnit::Fahrenheit);
+        assert_eq!(event.kind, AlertKindName::ApproachingTarget);
+        assert!((event.threshold - 198.0).abs() < 1e-3);
+
+        // Raising the target mid-fire resolves the approaching alert, without a new event
+        assert!(engine.set_target("AA", Some(Temperature::from_fahrenheit(210.0))).is_empty());
+        assert!(feed_f(&engine, 199.0).is_empty());
+        assert_eq!(feed_f(&engine, 205.0), vec![1]);
+
+        // Lowering it below the current value fires immediately
+        let events = engine.set_target("AA", Some(Temperature::from_fahrenheit(204.0)));
+        assert_eq!(events.iter().map(|e| e.rule_id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(events[0].kind, AlertKindName::TargetReached);
+    }
+
+    #[test]
+    fn test_relative_rules_inactive_without_target() {
+        let engine = relative_engine();
+        let mut events = engine.subscribe();
+
+        assert!(feed_f(&engine, 250.0).is_empty());
+        assert!(events.try_recv().is_err());
+
+        // Setting a target checks the last reading straight away
+        let fired = engine.set_target("AA", Some(Temperature::from_fahrenheit(203.0)));
+        assert_eq!(fired.len(), 2);
+        assert_eq!(events.try_recv().unwrap().rule_id, 1);
+
+        // Clearing it deactivates the rules and re-arms them for the next target
+        assert!(engine.set_target("AA", None).is_empty());
+        assert!(feed_f(&engine, 250.0).is_empty());
+        assert_eq!(engine.set_target("AA", Some(Temperature::from_fahrenheit(203.0))).len(), 2);
+
+        // Absolute rules ignore targets entirely
+        assert_eq!(AlertKind::AboveTarget(Temperature::from_celsius(90.0)).resolve(None).unwrap().celsius(), 90.0);
+    }
+
+    #[test]
+    fn test_battery_rule_fires_once_until_recharged() {
+        let engine = AlertEngine::new(vec![rule(1, AlertKind::BatteryBelow { percent: 15 }, 0.0)]);
+        let battery = |level| engine.process_battery("AA", level, Utc::now()).iter().map(|e| e.rule_id).collect::<Vec<_>>();
+
+        assert!(battery(40).is_empty());
+        assert_eq!(battery(15), vec![1]);
+        assert!(battery(12).is_empty());
+        // A flickering reading just above the threshold does not re-arm
+        assert!(battery(17).is_empty());
+        assert!(battery(14).is_empty());
+        assert!(battery(100).is_empty());
+        assert_eq!(battery(10), vec![1]);
+
+        // Temperatures never trigger battery rules
+        assert!(feed(&engine, 500.0, Some(500.0)).is_empty());
+    }
+
+    fn first_seen(name: &str) -> FirstSeenDevice {
+        FirstSeenDevice {
+            device_address: "AA".to_string(),
+            capabilities: ProbeCapabilities::detect_from_device(name, "AA", &[], &std::collections::HashMap::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_defaults_only_for_first_seen_devices() {
+        let db = Database::new(":memory:").await.unwrap();
+        let engine = AlertEngine::new(Vec::new());
+        let device = first_seen("MEATER");
+        let caps = &device.capabilities;
+
+        let upsert = || db.upsert_device("AA", "MEATER", "MeaterOriginal", &caps.model, caps.sensor_count);
+        assert!(upsert().await.unwrap(), "first sighting");
+        assert!(!upsert().await.unwrap(), "seen again");
+
+        let bootstrap = bootstrap_defaults(&db, Some(&engine), &device, Some("Brisket")).await.unwrap().unwrap();
+        let kinds: Vec<_> = bootstrap.rules.iter().map(|r| r.kind.name()).collect();
+        assert_eq!(kinds, [AlertKindName::AmbientAbove, AlertKindName::BatteryBelow]);
+        let ambient = bootstrap.rules[0].kind.threshold().unwrap();
+        assert!((ambient.fahrenheit() - (527.0 - AMBIENT_PROTECTION_MARGIN_F)).abs() < 0.1);
+        let suggestion = bootstrap.suggestion.unwrap();
+        assert_eq!((suggestion.meat.as_str(), suggestion.target), ("brisket", 203.0));
+        assert_eq!(db.get_alert_rules().await.unwrap().len(), 2);
+        assert_eq!(engine.process_battery("AA", 10, Utc::now()).len(), 1, "live without a restart");
+
+        // Deleted defaults stay deleted
+        for rule in &bootstrap.rules {
+            db.delete_alert_rule(rule.id).await.unwrap();
+        }
+        assert!(bootstrap_defaults(&db, Some(&engine), &device, Some("brisket")).await.unwrap().is_none());
+        assert!(db.get_alert_rules().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_defaults_follow_capabilities() {
+        let db = Database::new(":memory:").await.unwrap();
+        // A mains-powered multi-probe thermometer has no ambient sensor or battery
+        let mut device = first_seen("MEATER");
+        device.capabilities.sensor_count = 4;
+        device.capabilities.battery_life_hours = None;
+        assert!(default_rules(&device.capabilities).is_empty());
+
+        // Without Premium no rules are stored; a meat without a preset gets no suggestion
+        let bootstrap = bootstrap_defaults(&db, None, &first_seen("MEATER"), Some("tofu")).await.unwrap().unwrap();
+        assert_eq!(bootstrap, DefaultsBootstrap { rules: Vec::new(), suggestion: None });
+        assert!(db.get_alert_rules().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rules_wait_for_premium() {
+        let db = Database::new(":memory:").await.unwrap();
+        let device = first_seen("MEATER");
+        db.upsert_device("AA", "MEATER", "MeaterOriginal", &device.capabilities.model, device.capabilities.sensor_count).await.unwrap();
+
+        // Seen without Premium: only the suggestion, the rules stay pending
+        let bootstrap = bootstrap_defaults(&db, None, &device, Some("brisket")).await.unwrap().unwrap();
+        assert!(bootstrap.rules.is_empty());
+        assert!(bootstrap.suggestion.is_some());
+        assert!(db.alert_rules_pending("AA").await.unwrap());
+        assert!(bootstrap_defaults(&db, None, &device, Some("brisket")).await.unwrap().is_none());
+
+        // After the upgrade the rules are created once, without a second suggestion
+        let engine = AlertEngine::new(Vec::new());
+        let bootstrap = bootstrap_defaults(&db, Some(&engine), &device, Some("brisket")).await.unwrap().unwrap();
+        assert_eq!(bootstrap.rules.len(), 2);
+        assert_eq!(bootstrap.suggestion, None);
+        assert!(!db.alert_rules_pending("AA").await.unwrap());
+        assert!(bootstrap_defaults(&db, Some(&engine), &device, Some("brisket")).await.unwrap().is_none());
+        assert_eq!(db.get_alert_rules().await.unwrap().len(), 2);
+    }
+}
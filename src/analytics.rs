@@ -0,0 +1,819 @@
+// src/analytics.rs
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::database::ReadingRecord;
+use crate::temperature::Temperature;
+
+/// Outdoor temperature the pit is assumed to be heating up from (°F)
+pub const REFERENCE_OUTDOOR_F: f32 = 70.0;
+
+/// Drop in ambient temperature (°F) that counts as a lid opening
+pub const LID_DROP_F: f32 = 25.0;
+
+/// A drop must happen within this many seconds to count as a lid opening
+pub const LID_DROP_WINDOW_SECS: i64 = 120;
+
+/// A lid opening is recovered once ambient is back within this margin (°F)
+const RECOVERED_MARGIN_F: f32 = 5.0;
+
+/// Fuel units per 100°F·h of holding the setpoint above the outdoor reference,
+/// or of the pit straying from its setpoint
+const HOLDING_WEIGHT: f64 = 1.0;
+
+/// Fuel units per 100°F regained after a lid opening
+const RECOVERY_WEIGHT: f64 = 1.0;
+
+/// Internal temperatures (°F) where a stall can happen
+pub const STALL_BAND_F: (f32, f32) = (150.0, 170.0);
+
+/// A stall is a slope below this many °F per hour...
+pub const STALL_MAX_SLOPE_F_PER_HOUR: f32 = 1.0;
+
+/// ...held for at least this many minutes
+pub const STALL_MIN_MINUTES: i64 = 30;
+
+/// Minutes of readings the time-to-target slope is fitted over
+pub const ETA_WINDOW_MINUTES: i64 = 30;
+
+/// Fewest readings in the window worth extrapolating from
+const ETA_MIN_READINGS: usize = 5;
+
+/// Largest slope standard error, relative to the slope, that still gives an estimate
+const ETA_MAX_RELATIVE_ERROR: f64 = 0.5;
+
+/// Default for the longest span between two readings that is interpolated across
+pub const DEFAULT_MAX_INTERPOLATION_GAP_SECS: i64 = 15 * 60;
+
+/// Characteristics of the smoker a device is used in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmokerProfile {
+    /// Free-form multiplier: >1.0 for a smoker that burns more fuel for the same work
+    pub efficiency: f32,
+    pub notes: Option<String>,
+}
+
+impl Default for SmokerProfile {
+    fn default() -> Self {
+        Self {
+            efficiency: 1.0,
+            notes: None,
+        }
+    }
+}
+
+/// Relative fuel consumption of a cook
+///
+/// Fuel units are not pounds of pellets or kWh. They only compare cooks on
+/// the same smoker (with the same efficiency factor): a cook scoring 12 used
+/// roughly twice the fuel of one scoring 6.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FuelEstimate {
+    pub fuel_units: f64,
+    /// Part of the estimate from holding temperature
+    pub holding_units: f64,
+    /// Part of the estimate from recovering after lid openings
+    pub recovery_units: f64,
+    pub lid_openings: u32,
+    /// Average recovery rate after lid openings (°F per minute)
+    pub avg_recovery_slope: Option<f64>,
+    /// Pit setpoint the holding work was measured against (°F)
+    pub setpoint: f32,
+    pub efficiency: f32,
+}
+
+/// Estimate relative fuel use from a cook's ambient readings
+///
+/// Readings must be sorted by timestamp and carry `ambient_temp`; others are
+/// skipped. Holding work is the time spent keeping `setpoint` above
+/// `REFERENCE_OUTDOOR_F`, plus the time-weighted deviation of the ambient
+/// reading from the setpoint. Without a setpoint the cook's median ambient
+/// stands in for it. Each lid opening (ambient falling `LID_DROP_F` within
+/// `LID_DROP_WINDOW_SECS`) adds the degrees the pit had to regain.
+/// The analysis works in °F whatever the display unit.
+pub fn fuel_estimate(readings: &[ReadingRecord], profile: &SmokerProfile, setpoint: Option<Temperature>) -> FuelEstimate {
+    let samples: Vec<(i64, f32)> = readings
+        .iter()
+        .filter_map(|r| r.ambient_temp.map(|a| (r.timestamp.timestamp(), a.fahrenheit())))
+        .collect();
+    let setpoint = setpoint.map(Temperature::fahrenheit).unwrap_or_else(|| median_ambient(&samples));
+
+    // Trapezoidal integral of the setpoint's lift above outdoors and the pit's deviation from it
+    let holding_degree_hours: f64 = samples
+        .windows(2)
+        .map(|pair| {
+            let hours = (pair[1].0 - pair[0].0) as f64 / 3600.0;
+            let avg = (pair[0].1 + pair[1].1) as f64 / 2.0;
+            let lift = (setpoint - REFERENCE_OUTDOOR_F).max(0.0) as f64;
+            hours * (lift + (avg - setpoint as f64).abs())
+        })
+        .sum();
+
+    let recoveries = lid_recoveries(&samples);
+    let recovered_degrees: f64 = recoveries.iter().map(|r| r.degrees).sum();
+    let avg_recovery_slope = if recoveries.is_empty() {
+        None
+    } else {
+        Some(recoveries.iter().map(|r| r.slope_per_min).sum::<f64>() / recoveries.len() as f64)
+    };
+
+    let efficiency = profile.efficiency as f64;
+    let holding_units = holding_degree_hours / 100.0 * HOLDING_WEIGHT * efficiency;
+    let recovery_units = recovered_degrees / 100.0 * RECOVERY_WEIGHT * efficiency;
+
+    FuelEstimate {
+        fuel_units: holding_units + recovery_units,
+        holding_units,
+        recovery_units,
+        lid_openings: recoveries.len() as u32,
+        avg_recovery_slope,
+        setpoint,
+        efficiency: profile.efficiency,
+    }
+}
+
+/// Median ambient temperature of a cook (°F), or the outdoor reference without samples
+fn median_ambient(samples: &[(i64, f32)]) -> f32 {
+    let mut temps: Vec<f32> = samples.iter().map(|&(_, t)| t).collect();
+    temps.sort_by(f32::total_cmp);
+    temps.get(temps.len() / 2).copied().unwrap_or(REFERENCE_OUTDOOR_F)
+}
+
+/// An ongoing stall: the internal temperature stopped climbing in the stall band
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StallInfo {
+    pub started_at: DateTime<Utc>,
+    pub duration_mins: i64,
+    /// Slope over the whole stall (°F per hour)
+    pub slope_per_hour: f32,
+}
+
+/// Outcome of a time-to-target estimate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EtaStatus {
+    /// The latest reading is already at or past the target
+    Reached,
+    Estimated,
+    /// Flat, falling, too noisy or too few readings; see `EtaEstimate::reason`
+    CannotEstimate,
+}
+
+/// When the internal temperature is expected to reach a target
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EtaEstimate {
+    pub status: EtaStatus,
+    /// Time from the last reading to the target (zero once reached)
+    #[serde(skip)]
+    pub duration: Option<Duration>,
+    /// Smoothed slope the estimate extrapolates (°F per hour)
+    pub slope_per_hour: Option<f32>,
+    /// 0-1, lower the more the readings scatter around the fitted slope
+    pub confidence: f32,
+    /// Why no estimate could be made
+    pub reason: Option<&'static str>,
+}
+
+impl EtaEstimate {
+    fn cannot_estimate(reason: &'static str, slope_per_hour: Option<f64>) -> Self {
+        Self {
+            status: EtaStatus::CannotEstimate,
+            duration: None,
+            slope_per_hour: slope_per_hour.map(|s| s as f32),
+            confidence: 0.0,
+            reason: Some(reason),
+        }
+    }
+}
+
+/// Time until the internal temperature reaches `target_f`
+///
+/// Zero if the target is already reached; None when no sensible estimate
+/// exists (see `eta_estimate`).
+pub fn estimate_time_to_target(readings: &[ReadingRecord], target_f: f32) -> Option<Duration> {
+    eta_estimate(readings, target_f).duration
+}
+
+/// Extrapolate a least-squares line over the last `ETA_WINDOW_MINUTES` to `target_f`
+///
+/// Readings must be one sensor, sorted by timestamp. The fitted line, not the
+/// last (noisy) sample, gives the current temperature. Confidence falls with
+/// the slope's standard error; a flat or falling line, or one whose standard
+/// error exceeds half the slope, gives `CannotEstimate` instead of a wild number.
+pub fn eta_estimate(readings: &[ReadingRecord], target_f: f32) -> EtaEstimate {
+    let Some(last) = readings.last() else {
+        return EtaEstimate::cannot_estimate("no readings", None);
+    };
+    if last.temperature.reaches(Temperature::from_fahrenheit(target_f)) {
+        return EtaEstimate {
+            status: EtaStatus::Reached,
+            duration: Some(Duration::zero()),
+            slope_per_hour: None,
+            confidence: 1.0,
+            reason: None,
+        };
+    }
+
+    let end = last.timestamp;
+    let samples: Vec<(f64, f64)> = readings
+        .iter()
+        .filter(|r| end - r.timestamp <= Duration::minutes(ETA_WINDOW_MINUTES))
+        .map(|r| regression_point(r, end))
+        .collect();
+    if samples.len() < ETA_MIN_READINGS {
+        return EtaEstimate::cannot_estimate("not enough recent readings", None);
+    }
+
+    let fit = Fit::of(&samples);
+    let (Some(slope), Some(error)) = (fit.slope(), fit.slope_error()) else {
+        return EtaEstimate::cannot_estimate("not enough recent readings", None);
+    };
+    if slope <= 0.0 {
+        return EtaEstimate::cannot_estimate("temperature is flat or falling", Some(slope));
+    }
+    let relative_error = error / slope;
+    if relative_error > ETA_MAX_RELATIVE_ERROR {
+        return EtaEstimate::cannot_estimate("readings are too noisy", Some(slope));
+    }
+
+    let current = fit.intercept().unwrap_or(last.temperature.fahrenheit() as f64);
+    let hours = ((target_f as f64 - current) / slope).max(0.0);
+    EtaEstimate {
+        status: EtaStatus::Estimated,
+        duration: Some(Duration::seconds((hours * 3600.0).round() as i64)),
+        slope_per_hour: Some(slope as f32),
+        confidence: (1.0 - relative_error / ETA_MAX_RELATIVE_ERROR).clamp(0.0, 1.0) as f32,
+        reason: None,
+    }
+}
+
+/// Typical rise (°F) after a cut comes off the heat, for meats that coast noticeably
+pub fn default_carryover_f(meat: &str) -> Option<f32> {
+    match meat.to_lowercase().as_str() {
+        "prime_rib" | "roast" | "beef_roast" | "lamb" | "leg_of_lamb" => Some(10.0),
+        "brisket" | "pork_shoulder" | "turkey" | "poultry" | "chicken" | "pork_loin" => Some(5.0),
+        "steak" | "ribs" | "pork" | "beef" => Some(3.0),
+        _ => None,
+    }
+}
+
+/// Finished internal temperature (°F) of the built-in cook profile for a meat
+pub fn preset_target_f(meat: &str) -> Option<f32> {
+    match meat.trim().to_lowercase().as_str() {
+        "brisket" | "ribs" => Some(203.0),
+        "pork_shoulder" => Some(200.0),
+        "poultry" | "chicken" | "turkey" => Some(165.0),
+        "pork" | "pork_loin" | "fish" => Some(145.0),
+        "beef_roast" | "roast" | "lamb" | "leg_of_lamb" => Some(135.0),
+        "steak" | "prime_rib" => Some(130.0),
+        _ => None,
+    }
+}
+
+/// Internal temperature (°F) to pull the meat at so carryover finishes it at `target_f`
+pub fn removal_temp_f(target_f: f32, carryover_f: f32) -> f32 {
+    target_f - carryover_f.max(0.0)
+}
+
+/// Slope of the internal temperature over the last `window` (°F per hour)
+///
+/// Readings must be one sensor, sorted by timestamp. Uses a least-squares
+/// fit, so single noisy samples barely move it. None with fewer than two
+/// readings in the window.
+pub fn rate_of_change(readings: &[ReadingRecord], window: Duration) -> Option<f32> {
+    let end = readings.last()?.timestamp;
+    let samples: Vec<(f64, f64)> = readings
+        .iter()
+        .filter(|r| end - r.timestamp <= window)
+        .map(|r| regression_point(r, end))
+        .collect();
+    Fit::of(&samples).slope().map(|s| s as f32)
+}
+
+/// Detect a stall that is still going on at the last reading
+///
+/// Slides a `STALL_MIN_MINUTES` window back from the last reading over the
+/// trailing readings inside `STALL_BAND_F`. The stall starts at the earliest
+/// window whose slope, and that of every window after it, is below
+/// `STALL_MAX_SLOPE_F_PER_HOUR`. None if the latest window is still climbing
+/// or does not cover `STALL_MIN_MINUTES` yet.
+pub fn detect_stall(readings: &[ReadingRecord]) -> Option<StallInfo> {
+    let end = readings.last()?.timestamp;
+    let min_duration = Duration::minutes(STALL_MIN_MINUTES);
+    let in_band_count = readings
+        .iter()
+        .rev()
+        .take_while(|r| {
+            let f = r.temperature.fahrenheit();
+            f >= STALL_BAND_F.0 && f <= STALL_BAND_F.1
+        })
+        .count();
+    let run = &readings[readings.len() - in_band_count..];
+
+    let mut window = Fit::default();
+    let mut start = run.len();
+    let mut started_at = None;
+    for last in (0..run.len()).rev() {
+        while start > 0 && run[last].timestamp - run[start - 1].timestamp <= min_duration {
+            start -= 1;
+            window.add(regression_point(&run[start], end));
+        }
+        if start == 0 && run[last].timestamp - run[0].timestamp < min_duration {
+            break;
+        }
+        match window.slope() {
+            Some(slope) if slope < STALL_MAX_SLOPE_F_PER_HOUR as f64 => started_at = Some(run[start].timestamp),
+            _ => break,
+        }
+        window.remove(regression_point(&run[last], end));
+    }
+
+    let started_at = started_at?;
+    let stall: Vec<(f64, f64)> = run
+        .iter()
+        .filter(|r| r.timestamp >= started_at)
+        .map(|r| regression_point(r, end))
+        .collect();
+    Some(StallInfo {
+        started_at,
+        duration_mins: (end - started_at).num_minutes(),
+        slope_per_hour: Fit::of(&stall).slope().unwrap_or_default() as f32,
+    })
+}
+
+/// A reading interpolated onto a fixed time grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterpolatedReading {
+    pub timestamp: DateTime<Utc>,
+    pub temperature: Temperature,
+    /// None if either neighbouring reading has no ambient temperature
+    pub ambient_temp: Option<Temperature>,
+}
+
+/// Linearly interpolate one sensor's readings onto grid points `step` apart
+///
+/// Grid points are multiples of `step` since the Unix epoch, so the same
+/// readings always land on the same times. Points before the first or after
+/// the last reading are not extrapolated, and points between two readings
+/// more than `max_gap` apart are left out so a real gap stays visible.
+/// `readings` must be in time order.
+pub fn interpolate_readings(readings: &[ReadingRecord], step: Duration, max_gap: Duration) -> Vec<InterpolatedReading> {
+    let step_ms = step.num_milliseconds();
+    let (Some(first), Some(last)) = (readings.first(), readings.last()) else {
+        return Vec::new();
+    };
+    if step_ms <= 0 {
+        return Vec::new();
+    }
+
+    let lerp = |a: f32, b: f32, f: f64| a + ((b - a) as f64 * f) as f32;
+    let mut points = Vec::new();
+    let mut time = first.timestamp.timestamp_millis().div_euclid(step_ms) * step_ms;
+    if time < first.timestamp.timestamp_millis() {
+        time += step_ms;
+    }
+    // `readings[i]` is the latest reading at or before `time`
+    let mut i = 0;
+    while time <= last.timestamp.timestamp_millis() {
+        while readings.get(i + 1).is_some_and(|r| r.timestamp.timestamp_millis() <= time) {
+            i += 1;
+        }
+        let before = &readings[i];
+        let Some(timestamp) = DateTime::from_timestamp_millis(time) else {
+            break;
+        };
+        if before.timestamp == timestamp {
+            points.push(InterpolatedReading {
+                timestamp,
+                temperature: before.temperature,
+                ambient_temp: before.ambient_temp,
+            });
+        } else if let Some(after) = readings.get(i + 1).filter(|after| after.timestamp - before.timestamp <= max_gap) {
+            let span = (after.timestamp - before.timestamp).num_milliseconds() as f64;
+            let f = (time - before.timestamp.timestamp_millis()) as f64 / span;
+            points.push(InterpolatedReading {
+                timestamp,
+                temperature: Temperature::from_celsius(lerp(before.temperature.celsius(), after.temperature.celsius(), f)),
+                ambient_temp: before
+                    .ambient_temp
+                    .zip(after.ambient_temp)
+                    .map(|(a, b)| Temperature::from_celsius(lerp(a.celsius(), b.celsius(), f))),
+            });
+        }
+        time += step_ms;
+    }
+    points
+}
+
+/// (hours relative to `end`, °F) for a regression
+fn regression_point(reading: &ReadingRecord, end: DateTime<Utc>) -> (f64, f64) {
+    let hours = (reading.timestamp - end).num_milliseconds() as f64 / 3_600_000.0;
+    (hours, reading.temperature.fahrenheit() as f64)
+}
+
+/// Running sums for a least-squares line
+#[derive(Default)]
+struct Fit {
+    n: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+    sum_yy: f64,
+}
+
+impl Fit {
+    fn of(samples: &[(f64, f64)]) -> Self {
+        let mut fit = Self::default();
+        for &sample in samples {
+            fit.add(sample);
+        }
+        fit
+    }
+
+    fn add(&mut self, (x, y): (f64, f64)) {
+        self.n += 1.0;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+        self.sum_yy += y * y;
+    }
+
+    fn remove(&mut self, (x, y): (f64, f64)) {
+        self.n -= 1.0;
+        self.sum_x -= x;
+        self.sum_y -= y;
+        self.sum_xx -= x * x;
+        self.sum_xy -= x * y;
+        self.sum_yy -= y * y;
+    }
+
+    fn slope(&self) -> Option<f64> {
+        let denominator = self.n * self.sum_xx - self.sum_x * self.sum_x;
+        if self.n < 2.0 || denominator.abs() < f64::EPSILON {
+            return None;
+        }
+        Some((self.n * self.sum_xy - self.sum_x * self.sum_y) / denominator)
+    }
+
+    /// Value of the line at x = 0
+    fn intercept(&self) -> Option<f64> {
+        Some((self.sum_y - self.slope()? * self.sum_x) / self.n)
+    }
+
+    /// Standard error of the slope; needs at least three points
+    fn slope_error(&self) -> Option<f64> {
+        let slope = self.slope()?;
+        if self.n < 3.0 {
+            return None;
+        }
+        let sxx = self.sum_xx - self.sum_x * self.sum_x / self.n;
+        let sxy = self.sum_xy - self.sum_x * self.sum_y / self.n;
+        let syy = self.sum_yy - self.sum_y * self.sum_y / self.n;
+        let residual = (syy - slope * sxy).max(0.0) / (self.n - 2.0);
+        Some((residual / sxx).sqrt())
+    }
+}
+
+struct Recovery {
+    degrees: f64,
+    slope_per_min: f64,
+}
+
+/// Find lid openings and how far/fast the pit climbed back afterwards
+///
+/// Openings the pit never recovers from (e.g. pulling the meat) are ignored.
+fn lid_recoveries(samples: &[(i64, f32)]) -> Vec<Recovery> {
+    let mut recoveries = Vec::new();
+    // (temperature before the drop, lowest point, time of lowest point)
+    let mut dip: Option<(f32, f32, i64)> = None;
+
+    for (i, &(time, temp)) in samples.iter().enumerate() {
+        match dip.as_mut() {
+            Some((before, low, low_time)) => {
+                if temp < *low {
+                    *low = temp;
+                    *low_time = time;
+                } else if temp >= *before - RECOVERED_MARGIN_F {
+                    let minutes = ((time - *low_time) as f64 / 60.0).max(1.0 / 60.0);
+                    let degrees = (temp - *low) as f64;
+                    recoveries.push(Recovery {
+                        degrees,
+                        slope_per_min: degrees / minutes,
+                    });
+                    dip = None;
+                }
+            }
+            None => {
+                let recent_peak = samples[..i]
+                    .iter()
+                    .rev()
+                    .take_while(|(t, _)| time - t <= LID_DROP_WINDOW_SECS)
+                    .map(|&(_, a)| a)
+                    .fold(f32::MIN, f32::max);
+                if recent_peak - temp >= LID_DROP_F {
+                    dip = Some((recent_peak, temp, time));
+                }
+            }
+        }
+    }
+
+    recoveries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ReadingMode;
+    use crate::test_support::fixtures;
+    use chrono::{Duration, TimeZone, Utc};
+
+    /// One ambient reading per minute (°F)
+    fn session(ambient: &[f32]) -> Vec<ReadingRecord> {
+        let start = Utc.with_ymd_and_hms(2026, 7, 4, 10, 0, 0).unwrap();
+        ambient
+            .iter()
+            .enumerate()
+            .map(|(minute, &a)| ReadingRecord {
+                device_address: "AA:BB".to_string(),
+                timestamp: start + Duration::minutes(minute as i64),
+                sensor_index: 0,
+                temperature: Temperature::from_fahrenheit(150.0),
+                ambient_temp: Some(Temperature::from_fahrenheit(a)),
+                battery_level: None,
+                signal_strength: 0,
+                reading_mode: ReadingMode::Normal,
+                source: Default::default(),
+            })
+            .collect()
+    }
+
+    /// One internal reading per minute (°F)
+    fn internal(temps: &[f32]) -> Vec<ReadingRecord> {
+        let mut readings = session(&vec![225.0; temps.len()]);
+        for (reading, &t) in readings.iter_mut().zip(temps) {
+            reading.temperature = Temperature::from_fahrenheit(t);
+        }
+        readings
+    }
+
+    /// Climb at 1°F/min to 160°F, then hold with ±0.3°F noise
+    fn brisket_stall(hold_minutes: usize) -> Vec<f32> {
+        let mut temps: Vec<f32> = (0..=40).map(|m| 120.0 + m as f32).collect();
+        temps.extend((0..hold_minutes).map(|m| if m % 2 == 0 { 160.3 } else { 159.7 }));
+        temps
+    }
+
+    fn steady(temp: f32, minutes: usize) -> Vec<f32> {
+        vec![temp; minutes]
+    }
+
+    #[test]
+    fn test_longer_and_hotter_cooks_score_higher() {
+        let profile = SmokerProfile::default();
+        let short = fuel_estimate(&session(&steady(225.0, 120)), &profile, None);
+        let long = fuel_estimate(&session(&steady(225.0, 360)), &profile, None);
+        let hot = fuel_estimate(&session(&steady(300.0, 120)), &profile, None);
+
+        assert!(long.fuel_units > short.fuel_units);
+        assert!(hot.fuel_units > short.fuel_units);
+        assert_eq!(short.lid_openings, 0);
+        // 155°F above reference for 119 minutes (225°F is held as 107.2°C, 224.96°F)
+        assert!((short.fuel_units - 1.55 * 119.0 / 60.0).abs() < 1e-3, "{}", short.fuel_units);
+    }
+
+    #[test]
+    fn test_lid_openings_add_recovery_cost() {
+        let profile = SmokerProfile::default();
+        let closed = steady(250.0, 60);
+
+        let mut opened = closed.clone();
+        // Lid open at minute 20: 250 → 190, back up over 6 minutes
+        opened[20..27].copy_from_slice(&[190.0, 200.0, 210.0, 220.0, 235.0, 245.0, 250.0]);
+
+        let baseline = fuel_estimate(&session(&closed), &profile, None);
+        let with_lid = fuel_estimate(&session(&opened), &profile, None);
+
+        assert_eq!(with_lid.lid_openings, 1);
+        assert!(with_lid.recovery_units > 0.5);
+        assert!(with_lid.fuel_units > baseline.fuel_units);
+        assert!((with_lid.avg_recovery_slope.unwrap() - 55.0 / 5.0).abs() < 0.02);
+
+        // A second opening costs more again
+        let mut twice = opened.clone();
+        twice[40..47].copy_from_slice(&[190.0, 200.0, 210.0, 220.0, 235.0, 245.0, 250.0]);
+        let with_two = fuel_estimate(&session(&twice), &profile, None);
+        assert_eq!(with_two.lid_openings, 2);
+        assert!(with_two.fuel_units > with_lid.fuel_units);
+    }
+
+    #[test]
+    fn test_slow_cooldown_is_not_a_lid_opening() {
+        // Fire dying down by 1°F per minute
+        let cooling: Vec<f32> = (0..90).map(|m| 250.0 - m as f32).collect();
+        let estimate = fuel_estimate(&session(&cooling), &SmokerProfile::default(), None);
+        assert_eq!(estimate.lid_openings, 0);
+    }
+
+    #[test]
+    fn test_efficiency_scales_estimate() {
+        let readings = session(&steady(225.0, 120));
+        let base = fuel_estimate(&readings, &SmokerProfile::default(), None);
+        let thirsty = fuel_estimate(
+            &readings,
+            &SmokerProfile { efficiency: 1.5, notes: Some("offset, leaky door".to_string()) },
+            None,
+        );
+        assert!((thirsty.fuel_units - base.fuel_units * 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_holding_work_is_measured_against_the_setpoint() {
+        let profile = SmokerProfile::default();
+        let readings = session(&steady(225.0, 121));
+        let at = |setpoint: f32| fuel_estimate(&readings, &profile, Some(Temperature::from_fahrenheit(setpoint)));
+
+        // Held right at its setpoint: only the lift above outdoors counts, as when the setpoint is inferred
+        let held = at(225.0);
+        assert!((held.holding_units - fuel_estimate(&readings, &profile, None).holding_units).abs() < 1e-3);
+        assert!((held.holding_units - 1.55 * 2.0).abs() < 1e-3, "{:?}", held);
+        assert!((held.setpoint - 225.0).abs() < 0.05);
+
+        // Set to 250°F but running 25°F cool: a higher setpoint plus the deviation
+        let cool = at(250.0);
+        assert!((cool.holding_units - (1.80 + 0.25) * 2.0).abs() < 1e-3, "{:?}", cool);
+
+        // A pit swinging ±15°F around the same setpoint works harder than a steady one
+        let swinging: Vec<f32> = (0..121).map(|m| if m / 10 % 2 == 0 { 210.0 } else { 240.0 }).collect();
+        let swings = fuel_estimate(&session(&swinging), &profile, Some(Temperature::from_fahrenheit(225.0)));
+        assert!(swings.holding_units > held.holding_units);
+    }
+
+    #[test]
+    fn test_rate_of_change_on_rising_curve() {
+        // 0.5°F per minute = 30°F per hour
+        let rising: Vec<f32> = (0..120).map(|m| 100.0 + m as f32 * 0.5).collect();
+        let readings = internal(&rising);
+        let rate = rate_of_change(&readings, Duration::minutes(30)).unwrap();
+        // Off by the 0.05°C storage resolution at most
+        assert!((rate - 30.0).abs() < 0.01, "{}", rate);
+
+        assert_eq!(rate_of_change(&readings[..1], Duration::minutes(30)), None);
+        assert_eq!(rate_of_change(&[], Duration::minutes(30)), None);
+    }
+
+    #[test]
+    fn test_time_to_target_on_steady_climb() {
+        // 0.5°F per minute with ±0.5°F noise, at about 160°F after two hours
+        let climbing: Vec<f32> = (0..120)
+            .map(|m| 100.0 + m as f32 * 0.5 + if m % 2 == 0 { 0.5 } else { -0.5 })
+            .collect();
+        let readings = internal(&climbing);
+
+        let estimate = eta_estimate(&readings, 203.0);
+        assert_eq!(estimate.status, EtaStatus::Estimated);
+        let minutes = estimate.duration.unwrap().num_minutes();
+        // 203 - 159.5 = 43.5°F at 30°F/h is 87 minutes
+        assert!((85..=89).contains(&minutes), "{}", minutes);
+        assert!(estimate.confidence > 0.8, "{:?}", estimate);
+        assert_eq!(estimate_time_to_target(&readings, 203.0), estimate.duration);
+
+        // Already past the target
+        assert_eq!(estimate_time_to_target(&readings, 150.0), Some(Duration::zero()));
+        assert_eq!(eta_estimate(&readings, 150.0).status, EtaStatus::Reached);
+    }
+
+    #[test]
+    fn test_carryover_moves_removal_earlier() {
+        assert_eq!(removal_temp_f(135.0, 10.0), 125.0);
+        assert_eq!(removal_temp_f(203.0, -3.0), 203.0);
+        assert_eq!(default_carryover_f("Prime_Rib"), Some(10.0));
+        assert_eq!(default_carryover_f("tofu"), None);
+
+        // Pulling a brisket 5°F early at 30°F/h is 10 minutes sooner
+        let climbing: Vec<f32> = (0..120).map(|m| 100.0 + m as f32 * 0.5).collect();
+        let readings = internal(&climbing);
+        let to_target = estimate_time_to_target(&readings, 203.0).unwrap();
+        let to_removal = estimate_time_to_target(&readings, removal_temp_f(203.0, 5.0)).unwrap();
+        assert_eq!((to_target - to_removal).num_minutes(), 10);
+    }
+
+    #[test]
+    fn test_time_to_target_refuses_flat_noisy_or_sparse_data() {
+        let stalled = eta_estimate(&internal(&brisket_stall(60)), 203.0);
+        assert_eq!(stalled.status, EtaStatus::CannotEstimate);
+        assert_eq!(stalled.duration, None);
+
+        let cooling: Vec<f32> = (0..60).map(|m| 180.0 - m as f32 * 0.2).collect();
+        let falling = eta_estimate(&internal(&cooling), 203.0);
+        assert_eq!(falling.reason, Some("temperature is flat or falling"));
+
+        // Barely rising under ±5°F jitter
+        let noisy: Vec<f32> = (0..30).map(|m| 150.0 + m as f32 * 0.02 + if m % 2 == 0 { 5.0 } else { -5.0 }).collect();
+        let noisy = eta_estimate(&internal(&noisy), 203.0);
+        assert_eq!(noisy.status, EtaStatus::CannotEstimate);
+        assert_eq!(noisy.confidence, 0.0);
+
+        assert_eq!(estimate_time_to_target(&internal(&[150.0, 151.0, 152.0]), 203.0), None);
+        assert_eq!(estimate_time_to_target(&[], 203.0), None);
+    }
+
+    #[tokio::test]
+    async fn test_stall_detected_on_plateau() {
+        // Simulated brisket: the meat holds at 154.7°F from minute 117 to 267
+        let (db, session) = fixtures::load("brisket_stall").await;
+        let readings = fixtures::sensor_readings(&db, &session, 0).await;
+        let mid_stall = &readings[..240];
+        let stall = detect_stall(mid_stall).unwrap();
+
+        assert!(stall.slope_per_hour.abs() < STALL_MAX_SLOPE_F_PER_HOUR);
+        let started = (stall.started_at - session.started_at).num_minutes();
+        assert!((112..=122).contains(&started), "{:?}", stall);
+        assert!(stall.duration_mins >= 115);
+        assert!(rate_of_change(mid_stall, Duration::minutes(30)).unwrap().abs() < 1.0);
+        assert_eq!(detect_stall(&readings), None, "the stall is over by the end of the cook");
+    }
+
+    #[tokio::test]
+    async fn test_recorded_cook_estimates() {
+        let (db, session) = fixtures::load("brisket_stall").await;
+        let readings = fixtures::sensor_readings(&db, &session, 0).await;
+
+        assert_eq!(eta_estimate(&readings[..200], 203.0).status, EtaStatus::CannotEstimate);
+        // Past the stall at 187°F, the last half hour climbed at about 22°F/h; the
+        // meat reached 203°F 70 minutes later as it slowed toward the pit temperature
+        let estimate = eta_estimate(&readings[..340], 203.0);
+        assert_eq!(estimate.status, EtaStatus::Estimated);
+        let minutes = estimate.duration.unwrap().num_minutes();
+        assert!((40..=70).contains(&minutes), "{:?}", estimate);
+
+        // Pit at 225°F for 7 hours, with the lid open once at 75 minutes
+        let pit = fixtures::sensor_readings(&db, &session, 1).await;
+        let fuel = fuel_estimate(&pit, &SmokerProfile::default(), None);
+        assert_eq!(fuel.lid_openings, 1);
+        assert!((fuel.holding_units - 1.55 * 7.0).abs() < 0.1, "{:?}", fuel);
+    }
+
+    #[test]
+    fn test_no_stall_while_climbing_or_outside_band() {
+        // Climbing steadily through the band at 10°F/h
+        let climbing: Vec<f32> = (0..150).map(|m| 145.0 + m as f32 / 6.0).collect();
+        assert_eq!(detect_stall(&internal(&climbing)), None);
+
+        // Flat, but too short to count yet
+        assert_eq!(detect_stall(&internal(&brisket_stall(20))), None);
+
+        // Flat, but below the stall band (resting, or probe not in meat)
+        assert_eq!(detect_stall(&internal(&steady(120.0, 90))), None);
+    }
+
+    #[test]
+    fn test_interpolation_onto_grid() {
+        // Readings at 10:00:00, 10:01:00 and 10:03:00 (decimated in between)
+        let mut readings = internal(&[100.0, 110.0, 0.0, 130.0]);
+        readings.remove(2);
+        readings[2].ambient_temp = None;
+
+        let points = interpolate_readings(&readings, Duration::seconds(30), Duration::minutes(15));
+        let start = readings[0].timestamp;
+        let times: Vec<i64> = points.iter().map(|p| (p.timestamp - start).num_seconds()).collect();
+        assert_eq!(times, [0, 30, 60, 90, 120, 150, 180], "no points beyond the last reading");
+        let expected = [100.0, 105.0, 110.0, 115.0, 120.0, 125.0, 130.0];
+        for (point, expected) in points.iter().zip(expected) {
+            assert!((point.temperature.fahrenheit() - expected).abs() < 0.2, "{:?} != {}", point, expected);
+        }
+        assert_eq!(points[1].ambient_temp.map(|t| t.fahrenheit().round()), Some(225.0));
+        assert_eq!(points[3].ambient_temp, None, "no ambient to interpolate towards");
+
+        // Grid points are aligned to the step, not to the first reading
+        let mut offset = readings.clone();
+        for reading in &mut offset {
+            reading.timestamp += Duration::seconds(10);
+        }
+        let points = interpolate_readings(&offset, Duration::seconds(30), Duration::minutes(15));
+        assert_eq!(points[0].timestamp, start + Duration::seconds(30));
+        assert!((points[0].temperature.fahrenheit() - 103.3).abs() < 0.2, "{:?}", points[0]);
+    }
+
+    #[test]
+    fn test_interpolation_keeps_gaps() {
+        // Readings every minute for 5 minutes, nothing for 40 minutes, then 5 more
+        let mut readings = internal(&steady(200.0, 10));
+        for reading in &mut readings[5..] {
+            reading.timestamp += Duration::minutes(40);
+        }
+        let start = readings[0].timestamp;
+
+        let points = interpolate_readings(&readings, Duration::minutes(1), Duration::minutes(15));
+        let minutes: Vec<i64> = points.iter().map(|p| (p.timestamp - start).num_minutes()).collect();
+        assert_eq!(minutes, [0, 1, 2, 3, 4, 45, 46, 47, 48, 49], "nothing invented inside the gap");
+
+        // A larger threshold bridges it
+        let points = interpolate_readings(&readings, Duration::minutes(1), Duration::hours(1));
+        assert_eq!(points.len(), 50);
+        assert!(points.iter().all(|p| p.temperature.fahrenheit().round() == 200.0));
+
+        assert!(interpolate_readings(&[], Duration::minutes(1), Duration::minutes(15)).is_empty());
+    }
+}
@@ -1,15 +1,35 @@
 use anyhow::{Context, Result};
 use aws_config::BehaviorVersion;
-use aws_sdk_dynamodb::{types::AttributeValue, Client as DynamoClient};
+use aws_sdk_dynamodb::types::{AttributeValue, PutRequest, WriteRequest};
+use aws_sdk_dynamodb::Client as DynamoClient;
 use aws_sdk_iotdataplane::Client as IoTDataClient;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
-use crate::database::{Database, ReadingRecord};
+pub use crate::cloud_backfill::{CloudReading, SyncBatch};
+use crate::cloud_backfill::{backfill_from_cloud, import_cloud_readings, CloudHistory};
+use crate::cloud_batch::{chunk_by_limits, write_in_batches, BatchOutcome, BatchWrite};
+pub use crate::cloud_upload::{SYNC_FROM_CLOUD, SYNC_TO_CLOUD};
+use crate::cloud_upload::{CloudUploader, ReadingUpload};
+use crate::database::{Database, OutboundReading};
+use crate::telemetry::{TelemetryReport, TelemetryUpload};
+use crate::temperature::TemperatureUnit;
+
+/// Upper bound on the final upload when the sync task shuts down
+const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// IoT Core rejects messages larger than 128 KB
+const IOT_MAX_PAYLOAD_BYTES: usize = 128 * 1024;
+
+/// IoT topic shared by every install, so telemetry carries no thing name
+const TELEMETRY_TOPIC: &str = "bbq-monitor/telemetry/parse-failures";
+
+/// DynamoDB attribute holding the `device_address#millis` sort key
+const SORT_KEY_ATTRIBUTE: &str = "timestamp_key";
 
 /// Configuration for AWS IoT and DynamoDB
 #[derive(Debug, Clone)]
@@ -18,19 +38,73 @@ pub struct AwsConfig {
     pub thing_name: String,
     pub table_name: String,
     pub sync_interval_secs: u64,
+    /// Backfill window used when no sync cursor is stored yet
+    pub initial_lookback_hours: u64,
+    /// Most readings kept in the pending sync queue (0 = unbounded)
+    pub max_pending_sync: usize,
+}
+
+/// A stored reading ready for `BatchWriteItem`
+struct CloudItem {
+    outbound: OutboundReading,
+    reading: CloudReading,
+    key: String,
+    item: HashMap<String, AttributeValue>,
+}
+
+impl CloudItem {
+    fn new(outbound: OutboundReading) -> Self {
+        let reading = CloudReading {
+            device_address: outbound.reading.device_address.clone(),
+            device_name: outbound.device_name.clone(),
+            temperature: outbound.reading.temperature.celsius() as f64,
+            ambient_temp: outbound.reading.ambient_temp.map(|t| t.celsius() as f64),
+            unit: TemperatureUnit::Celsius,
+            battery_level: outbound.reading.battery_level,
+            signal_strength: outbound.reading.signal_strength,
+            timestamp: outbound.reading.timestamp,
+            source: "local".to_string(),
+        };
+        Self { key: sort_key(&reading), item: reading_item(&reading), outbound, reading }
+    }
+}
+
+/// Composite key: device_address#timestamp
+fn sort_key(reading: &CloudReading) -> String {
+    format!("{}#{}", reading.device_address, reading.timestamp.timestamp_millis())
+}
+
+/// DynamoDB item for a reading
+fn reading_item(reading: &CloudReading) -> HashMap<String, AttributeValue> {
+    let mut item = HashMap::new();
+    item.insert("device_address".to_string(), AttributeValue::S(reading.device_address.clone()));
+    item.insert(SORT_KEY_ATTRIBUTE.to_string(), AttributeValue::S(sort_key(reading)));
+    item.insert("device_name".to_string(), AttributeValue::S(reading.device_name.clone()));
+    item.insert("temperature".to_string(), AttributeValue::N(reading.temperature.to_string()));
+    item.insert("unit".to_string(), AttributeValue::S(reading.unit.as_str().to_string()));
+    item.insert("signal_strength".to_string(), AttributeValue::N(reading.signal_strength.to_string()));
+    item.insert("timestamp".to_string(), AttributeValue::S(reading.timestamp.to_rfc3339()));
+    item.insert("source".to_string(), AttributeValue::S(reading.source.clone()));
+    if let Some(ambient) = reading.ambient_temp {
+        item.insert("ambient_temp".to_string(), AttributeValue::N(ambient.to_string()));
+    }
+    if let Some(battery) = reading.battery_level {
+        item.insert("battery_level".to_string(), AttributeValue::N(battery.to_string()));
+    }
+    item
 }
 
-/// Temperature reading for cloud sync
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CloudReading {
-    pub device_address: String,
-    pub device_name: String,
-    pub temperature: f64,
-    pub ambient_temp: Option<f64>,
-    pub battery_level: Option<u8>,
-    pub signal_strength: i16,
-    pub timestamp: DateTime<Utc>,
-    pub source: String, // "local" or "cloud"
+/// Approximate DynamoDB size of an item: attribute names plus values
+fn item_size(item: &HashMap<String, AttributeValue>) -> usize {
+    item.iter()
+        .map(|(name, value)| {
+            name.len()
+                + match value {
+                    AttributeValue::S(s) | AttributeValue::N(s) => s.len(),
+                    _ => 16,
+                }
+        })
+        .sum()
 }
 
 /// AWS client for IoT and DynamoDB operations
@@ -39,6 +113,7 @@ pub struct AwsClient {
     dynamo: DynamoClient,
     config: AwsConfig,
     database: Arc<Database>,
+    uploader: CloudUploader,
 }
 
 impl AwsClient {
@@ -56,11 +131,17 @@ impl AwsClient {
 
         info!("AWS client initialized successfully");
         
+        let uploader = CloudUploader::new(
+            Duration::from_secs(config.sync_interval_secs.max(1)),
+            config.initial_lookback_hours,
+            config.max_pending_sync,
+        );
         Ok(Self {
             iot_data,
             dynamo,
             config,
             database,
+            uploader,
         })
     }
 
@@ -87,63 +168,12 @@ impl AwsClient {
 
     /// Store a reading in DynamoDB
     pub async fn store_reading(&self, reading: &CloudReading) -> Result<()> {
-        let mut item = HashMap::new();
-        
-        // Composite key: device_address#timestamp
-        let sort_key = format!("{}#{}", 
-            reading.device_address, 
-            reading.timestamp.timestamp_millis()
-        );
-        
-        item.insert(
-            "device_address".to_string(),
-            AttributeValue::S(reading.device_address.clone()),
-        );
-        item.insert(
-            "timestamp_key".to_string(),
-            AttributeValue::S(sort_key),
-        );
-        item.insert(
-            "device_name".to_string(),
-            AttributeValue::S(reading.device_name.clone()),
-        );
-        item.insert(
-            "temperature".to_string(),
-            AttributeValue::N(reading.temperature.to_string()),
-        );
-        item.insert(
-            "signal_strength".to_string(),
-            AttributeValue::N(reading.signal_strength.to_string()),
-        );
-        item.insert(
-            "timestamp".to_string(),
-            AttributeValue::S(reading.timestamp.to_rfc3339()),
-        );
-        item.insert(
-            "source".to_string(),
-            AttributeValue::S(reading.source.clone()),
-        );
-
-        if let Some(ambient) = reading.ambient_temp {
-            item.insert(
-                "ambient_temp".to_string(),
-                AttributeValue::N(ambient.to_string()),
-            );
-        }
-
-        if let Some(battery) = reading.battery_level {
-            item.insert(
-                "battery_level".to_string(),
-                AttributeValue::N(battery.to_string()),
-            );
-        }
-
         debug!("Storing reading in DynamoDB table: {}", self.config.table_name);
         
         self.dynamo
             .put_item()
             .table_name(&self.config.table_name)
-            .set_item(Some(item))
+            .set_item(Some(reading_item(reading)))
             .send()
             .await
             .context("Failed to store reading in DynamoDB")?;
@@ -152,7 +182,72 @@ impl AwsClient {
         Ok(())
     }
 
-    /// Query recent readings from DynamoDB for a device
+    /// Store readings with `BatchWriteItem`, 25 at a time, and publish the stored ones to IoT Core
+    ///
+    /// Readings sharing a DynamoDB key (sensors of one packet) would overwrite
+    /// each other, and a batch may not write a key twice, so only the last of
+    /// them is sent; the others count as persisted along with it.
+    pub async fn store_readings_batch(&self, readings: Vec<OutboundReading>) -> BatchOutcome<OutboundReading> {
+        let mut items: Vec<CloudItem> = Vec::new();
+        let mut by_key = HashMap::new();
+        let mut superseded = Vec::new();
+        for outbound in readings {
+            let item = CloudItem::new(outbound);
+            match by_key.get(&item.key) {
+                Some(&i) => superseded.push(std::mem::replace(&mut items[i], item).outbound),
+                None => {
+                    by_key.insert(item.key.clone(), items.len());
+                    items.push(item);
+                }
+            }
+        }
+
+        let outcome = write_in_batches(self, items, |c| item_size(&c.item)).await;
+        let stored: Vec<&CloudReading> = outcome.persisted.iter().map(|c| &c.reading).collect();
+        self.publish_readings_batch(&stored).await;
+
+        debug!("Stored {} of {} readings in DynamoDB", outcome.persisted.len(), outcome.persisted.len() + outcome.failed.len());
+        BatchOutcome {
+            persisted: outcome.persisted.into_iter().map(|c| c.outbound).chain(superseded).collect(),
+            failed: outcome.failed.into_iter().map(|c| c.outbound).collect(),
+            error: outcome.error,
+        }
+    }
+
+    /// Publish readings to IoT Core as JSON arrays, one device per message (best effort)
+    async fn publish_readings_batch(&self, readings: &[&CloudReading]) {
+        let topic = format!("bbq-monitor/{}/readings/batch", self.config.thing_name);
+        let mut by_device: HashMap<&str, Vec<&CloudReading>> = HashMap::new();
+        for reading in readings {
+            by_device.entry(reading.device_address.as_str()).or_default().push(*reading);
+        }
+
+        for (device, readings) in by_device {
+            let sized = readings.into_iter().map(|r| (r, serde_json::to_vec(r).map_or(0, |v| v.len() + 1)));
+            let chunks = chunk_by_limits(sized.collect(), usize::MAX, IOT_MAX_PAYLOAD_BYTES, |(_, len)| *len);
+            for chunk in chunks {
+                let readings: Vec<&CloudReading> = chunk.into_iter().map(|(r, _)| r).collect();
+                let result = async {
+                    let payload = serde_json::to_vec(&readings).context("Failed to serialize readings")?;
+                    self.iot_data
+                        .publish()
+                        .topic(&topic)
+                        .payload(aws_sdk_iotdataplane::primitives::Blob::new(payload))
+                        .qos(1)
+                        .send()
+                        .await
+                        .context("Failed to publish to IoT Core")?;
+                    anyhow::Ok(())
+                }
+                .await;
+                if let Err(e) = result {
+                    error!("Failed to publish {} readings for {} to IoT Core: {:#}", readings.len(), device, e);
+                }
+            }
+        }
+    }
+
+    /// Query readings from DynamoDB for a device, following pagination
     pub async fn query_device_readings(
         &self,
         device_address: &str,
@@ -166,38 +261,77 @@ impl AwsClient {
             since.to_rfc3339()
         );
 
-        let result = self.dynamo
-            .query()
-            .table_name(&self.config.table_name)
-            .key_condition_expression(
-                "device_address = :addr AND timestamp_key >= :since"
-            )
-            .expression_attribute_values(
-                ":addr",
-                AttributeValue::S(device_address.to_string()),
-            )
-            .expression_attribute_values(
-                ":since",
-                AttributeValue::S(since_key),
-            )
-            .send()
-            .await
-            .context("Failed to query DynamoDB")?;
-
         let mut readings = Vec::new();
-        
-        if let Some(items) = result.items {
-            for item in items {
+        let mut start_key = None;
+        loop {
+            let result = self.dynamo
+                .query()
+                .table_name(&self.config.table_name)
+                .key_condition_expression(
+                    "device_address = :addr AND timestamp_key >= :since"
+                )
+                .expression_attribute_values(
+                    ":addr",
+                    AttributeValue::S(device_address.to_string()),
+                )
+                .expression_attribute_values(
+                    ":since",
+                    AttributeValue::S(since_key.clone()),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .context("Failed to query DynamoDB")?;
+
+            for item in result.items.unwrap_or_default() {
                 if let Ok(reading) = self.parse_dynamo_item(item) {
                     readings.push(reading);
                 }
             }
+            // Each page holds at most 1 MB; a backfill can span many
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
         }
 
         debug!("Retrieved {} readings from DynamoDB", readings.len());
         Ok(readings)
     }
 
+    /// Address and name of every device with readings since `since`
+    ///
+    /// Scans the whole table, so it is only used for the startup backfill.
+    pub async fn scan_devices(&self, since: DateTime<Utc>) -> Result<Vec<(String, String)>> {
+        let mut devices = HashMap::new();
+        let mut start_key = None;
+        loop {
+            let result = self.dynamo
+                .scan()
+                .table_name(&self.config.table_name)
+                .projection_expression("device_address, device_name")
+                .filter_expression("#ts >= :since")
+                .expression_attribute_names("#ts", "timestamp")
+                .expression_attribute_values(":since", AttributeValue::S(since.to_rfc3339()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .context("Failed to scan DynamoDB for devices")?;
+
+            for item in result.items.unwrap_or_default() {
+                let text = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).cloned();
+                if let (Some(address), Some(name)) = (text("device_address"), text("device_name")) {
+                    devices.insert(address, name);
+                }
+            }
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        Ok(devices.into_iter().collect())
+    }
+
     /// Parse a DynamoDB item into a CloudReading
     fn parse_dynamo_item(&self, item: HashMap<String, AttributeValue>) -> Result<CloudReading> {
         let device_address = item
@@ -249,11 +383,18 @@ impl AwsClient {
             .and_then(|v| v.as_n().ok())
             .and_then(|s| s.parse::<u8>().ok());
 
+        // Older items carry no unit and were written in Fahrenheit
+        let unit = item
+            .get("unit")
+            .and_then(|v| TemperatureUnit::parse(v.as_s().ok()?))
+            .unwrap_or(TemperatureUnit::Fahrenheit);
+
         Ok(CloudReading {
             device_address,
             device_name,
             temperature,
             ambient_temp,
+            unit,
             battery_level,
             signal_strength,
             timestamp,
@@ -261,114 +402,41 @@ impl AwsClient {
         })
     }
 
-    /// Sync local readings to cloud
-    pub async fn sync_to_cloud(&self, since: DateTime<Utc>) -> Result<usize> {
-        info!("Starting sync to cloud since {}", since.to_rfc3339());
-        
-        let devices = self.database.get_all_devices().await?;
-        let mut synced_count = 0;
-
-        for device in devices {
-            let readings = self.database
-                .get_readings_since(&device.address, since)
-                .await?;
-
-            debug!(
-                "Syncing {} readings for device {}", 
-                readings.len(), 
-                device.address
-            );
-
-            for reading in readings {
-                let cloud_reading = CloudReading {
-                    device_address: reading.device_address.clone(),
-                    device_name: device.name.clone(),
-                    temperature: reading.temperature,
-                    ambient_temp: reading.ambient_temp,
-                    battery_level: reading.battery_level,
-                    signal_strength: reading.signal_strength,
-                    timestamp: reading.timestamp,
-                    source: "local".to_string(),
-                };
-
-                // Store in DynamoDB
-                if let Err(e) = self.store_reading(&cloud_reading).await {
-                    error!("Failed to store reading in DynamoDB: {}", e);
-                    continue;
-                }
-
-                // Publish to IoT Core
-                if let Err(e) = self.publish_reading(&cloud_reading).await {
-                    error!("Failed to publish reading to IoT Core: {}", e);
-                    continue;
-                }
-
-                synced_count += 1;
-            }
-        }
+    /// Retry queued readings, oldest first; returns how many were uploaded
+    pub async fn drain_pending(&self) -> Result<usize> {
+        self.uploader.drain_pending(self, &self.database).await
+    }
 
-        info!("Synced {} readings to cloud", synced_count);
-        Ok(synced_count)
+    /// Sync local readings to cloud, queueing those DynamoDB did not take
+    pub async fn sync_to_cloud(&self, since: DateTime<Utc>) -> Result<SyncBatch> {
+        self.uploader.sync_to_cloud(self, &self.database, since).await
     }
 
-    /// Sync cloud readings to local database
-    pub async fn sync_from_cloud(&self, since: DateTime<Utc>) -> Result<usize> {
+    /// Sync cloud readings of known devices to the local database
+    pub async fn sync_from_cloud(&self, since: DateTime<Utc>) -> Result<SyncBatch> {
         info!("Starting sync from cloud since {}", since.to_rfc3339());
         
-        let devices = self.database.get_all_devices().await?;
-        let mut synced_count = 0;
-
-        for device in devices {
-            let cloud_readings = self
-                .query_device_readings(&device.address, since)
-                .await?;
-
-            debug!(
-                "Retrieved {} cloud readings for device {}", 
-                cloud_readings.len(), 
-                device.address
-            );
-
-            for reading in cloud_readings {
-                // Skip if this reading originated from this instance
-                if reading.source == "local" {
-                    continue;
-                }
-
-                // Check if we already have this reading
-                let existing = self.database
-                    .get_readings_since(&reading.device_address, reading.timestamp)
-                    .await?;
-
-                let has_reading = existing.iter().any(|r| {
-                    (r.timestamp - reading.timestamp).num_seconds().abs() < 5
-                });
+        let devices: Vec<String> = self.database.get_all_devices().await?.into_iter().map(|d| d.device_address).collect();
+        let batch = import_cloud_readings(self, &self.database, &devices, since, false).await?;
 
-                if has_reading {
-                    continue;
-                }
+        info!("Synced {} readings from cloud", batch.count);
+        Ok(batch)
+    }
 
-                // Insert cloud reading into local database
-                self.database
-                    .insert_reading(
-                        &reading.device_address,
-                        reading.temperature,
-                        reading.ambient_temp,
-                        reading.battery_level,
-                        reading.signal_strength,
-                        reading.timestamp,
-                    )
-                    .await?;
-
-                synced_count += 1;
-            }
-        }
+    /// Pull the last `hours` of cloud history, including devices not seen locally yet
+    pub async fn backfill(&self, hours: u64) -> Result<SyncBatch> {
+        backfill_from_cloud(self, &self.database, hours).await
+    }
 
-        info!("Synced {} readings from cloud", synced_count);
-        Ok(synced_count)
+    /// Retry queued readings, then upload readings recorded since the stored cursor and advance it
+    pub async fn flush_to_cloud(&self) -> Result<usize> {
+        self.uploader.flush(self, &self.database).await
     }
 
     /// Start background sync task
+    ///
+    /// The first pass runs immediately (backfilling from the stored cursor);
+    /// on shutdown a final upload is attempted before the task exits.
     pub async fn start_sync_task(
         self: Arc<Self>,
         mut shutdown: broadcast::Receiver<()>,
@@ -385,25 +453,101 @@ impl AwsClient {
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    let since = Utc::now() - chrono::Duration::hours(1);
-                    
                     // Sync to cloud
-                    match self.sync_to_cloud(since).await {
+                    match self.flush_to_cloud().await {
                         Ok(count) => debug!("Synced {} readings to cloud", count),
                         Err(e) => error!("Cloud sync to failed: {}", e),
                     }
 
                     // Sync from cloud
+                    let since = self.uploader.resume_point(&self.database, SYNC_FROM_CLOUD).await;
                     match self.sync_from_cloud(since).await {
-                        Ok(count) => debug!("Synced {} readings from cloud", count),
+                        Ok(batch) => {
+                            self.uploader.advance_cursor(&self.database, SYNC_FROM_CLOUD, &batch).await;
+                            debug!("Synced {} readings from cloud", batch.count);
+                        }
                         Err(e) => error!("Cloud sync from failed: {}", e),
                     }
                 }
                 _ = shutdown.recv() => {
-                    info!("Shutting down background sync task");
+                    info!("Shutting down background sync task, uploading pending readings");
+                    match tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, self.flush_to_cloud()).await {
+                        Ok(Ok(count)) => info!("Uploaded {} pending readings before shutdown", count),
+                        Ok(Err(e)) => warn!("Final cloud sync failed, will resume on next start: {}", e),
+                        Err(_) => warn!("Final cloud sync timed out, will resume on next start"),
+                    }
                     break;
                 }
             }
         }
     }
 }
+
+impl TelemetryUpload for AwsClient {
+    async fn upload_telemetry(&self, report: &TelemetryReport) -> Result<()> {
+        let payload = serde_json::to_vec(report).context("Failed to serialize telemetry")?;
+        self.iot_data
+            .publish()
+            .topic(TELEMETRY_TOPIC)
+            .payload(aws_sdk_iotdataplane::primitives::Blob::new(payload))
+            .qos(1)
+            .send()
+            .await
+            .context("Failed to publish telemetry to IoT Core")?;
+        Ok(())
+    }
+}
+
+impl CloudHistory for AwsClient {
+    async fn device_readings(&self, device_address: &str, since: DateTime<Utc>) -> Result<Vec<CloudReading>> {
+        self.query_device_readings(device_address, since).await
+    }
+
+    async fn devices_since(&self, since: DateTime<Utc>) -> Result<Vec<(String, String)>> {
+        self.scan_devices(since).await
+    }
+}
+
+impl ReadingUpload for AwsClient {
+    async fn upload_readings(&self, readings: Vec<OutboundReading>) -> BatchOutcome<OutboundReading> {
+        self.store_readings_batch(readings).await
+    }
+}
+
+impl BatchWrite<CloudItem> for AwsClient {
+    async fn write_batch(&self, batch: &[CloudItem]) -> Result<Vec<usize>> {
+        let requests = batch
+            .iter()
+            .map(|c| -> Result<WriteRequest> {
+                let put = PutRequest::builder().set_item(Some(c.item.clone())).build()?;
+                Ok(WriteRequest::builder().put_request(put).build())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let output = self.dynamo
+            .batch_write_item()
+            .request_items(&self.config.table_name, requests)
+            .send()
+            .await
+            .context("Failed to batch write readings to DynamoDB")?;
+
+        let unprocessed: HashSet<&str> = output
+            .unprocessed_items()
+            .and_then(|tables| tables.get(&self.config.table_name))
+            .into_iter()
+            .flatten()
+            .filter_map(|request| request.put_request())
+            .filter_map(|put| match put.item().get(SORT_KEY_ATTRIBUTE) {
+                Some(AttributeValue::S(key)) => Some(key.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        Ok(batch
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| unprocessed.contains(c.key.as_str()))
+            .map(|(i, _)| i)
+            .collect())
+    }
+}
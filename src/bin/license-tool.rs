@@ -1,8 +1,14 @@
-use anyhow::Result;
-use bbq_monitor::{generate_license_key, PremiumTier};
+use anyhow::{Context, Result};
+use bbq_monitor::{generate_license_key, parse_signing_key, PremiumTier};
 use chrono::{Duration, Utc};
+use ed25519_dalek::SigningKey;
 use std::env;
 
+/// Environment variable holding the base64 Ed25519 signing key
+const SIGNING_KEY_ENV: &str = "BBQ_LICENSE_SIGNING_KEY";
+/// Environment variable pointing at a file containing the signing key
+const SIGNING_KEY_FILE_ENV: &str = "BBQ_LICENSE_SIGNING_KEY_FILE";
+
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     
@@ -28,7 +34,8 @@ fn main() -> Result<()> {
                 None // Default: lifetime license
             };
 
-            let key = generate_license_key(tier, expires_at)?;
+            let signing_key = load_signing_key()?;
+            let key = generate_license_key(tier, expires_at, &signing_key)?;
             
             println!("╔══════════════════════════════════════════════════════╗");
             println!("║           BBQ Monitor License Generator             ║");
@@ -99,6 +106,24 @@ fn main() -> Result<()> {
                 }
             }
         }
+        "keygen" => {
+            use base64::Engine;
+            let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+            let encoded = base64::engine::general_purpose::STANDARD.encode(signing_key.to_bytes());
+            let public_key = signing_key
+                .verifying_key()
+                .to_bytes()
+                .iter()
+                .map(|b| format!("0x{:02x}", b))
+                .collect::<Vec<String>>()
+                .join(", ");
+            
+            println!("Private signing key (keep SECRET, set as {}):", SIGNING_KEY_ENV);
+            println!("{}", encoded);
+            println!();
+            println!("Public key (replace LICENSE_PUBLIC_KEY in src/premium.rs):");
+            println!("[{}]", public_key);
+        }
         "examples" => {
             print_examples();
         }
@@ -111,6 +136,24 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn load_signing_key() -> Result<SigningKey> {
+    if let Ok(encoded) = env::var(SIGNING_KEY_ENV) {
+        return parse_signing_key(&encoded);
+    }
+    
+    if let Ok(path) = env::var(SIGNING_KEY_FILE_ENV) {
+        let encoded = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read signing key file {}", path))?;
+        return parse_signing_key(&encoded);
+    }
+    
+    anyhow::bail!(
+        "No signing key configured. Set {} (base64) or {} (path to key file)",
+        SIGNING_KEY_ENV,
+        SIGNING_KEY_FILE_ENV
+    )
+}
+
 fn print_usage() {
     println!("BBQ Monitor License Tool");
     println!();
@@ -124,8 +167,14 @@ fn print_usage() {
     println!();
     println!("    validate <key>            Validate an existing license key");
     println!();
+    println!("    keygen                    Generate a new Ed25519 signing keypair");
+    println!();
     println!("    examples                  Show usage examples");
     println!();
+    println!("SIGNING:");
+    println!("    generate reads the private key from ${} (base64)", SIGNING_KEY_ENV);
+    println!("    or from the file named by ${}", SIGNING_KEY_FILE_ENV);
+    println!();
     println!("EXAMPLES:");
     println!("    license-tool generate premium          # Lifetime Premium");
     println!("    license-tool generate premium 365      # Premium for 1 year");
@@ -152,7 +201,11 @@ fn print_examples() {
     println!();
     
     println!("4. Validate a license key:");
-    println!("   $ cargo run --bin license-tool validate \"UExFTUlVTXxORVZFUnwyMDI2LTAxLTIwVDE5OjUzOjE5LjQzMzM5NjcwMCswMDowMA==\"");
+    println!("   $ cargo run --bin license-tool validate \"KEY-HERE\"");
+    println!();
+    
+    println!("5. Create a new signing keypair (one-time setup):");
+    println!("   $ cargo run --bin license-tool keygen");
     println!();
     
     println!("PRICING SUGGESTIONS:");
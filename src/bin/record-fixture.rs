@@ -0,0 +1,79 @@
+// src/bin/record-fixture.rs
+//! Record a device's temperature notifications as a protocol test fixture
+//!
+//! `cargo run --features fixture-recorder --bin record-fixture -- <fixture> <address> [seconds]`
+//! writes `bbq-protocols/tests/fixtures/<fixture>.txt`; fill in the matching `.csv` by hand
+//! from the device's display, not from the parser under test.
+use anyhow::{bail, Context, Result};
+use bbq_monitor::ble;
+use bbq_monitor::ble_debug::to_hex;
+use bbq_monitor::ProtocolRegistry;
+use btleplug::api::{Central, Peripheral as _, ScanFilter};
+use chrono::Utc;
+use std::io::Write;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+const DEFAULT_RECORD_SECS: u64 = 60;
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(fixture), Some(address)) = (args.first(), args.get(1)) else {
+        bail!("Usage: record-fixture <meatstick|combustion|meater|thermopro> <address> [seconds]");
+    };
+    let characteristic = ProtocolRegistry::fixture_characteristic(fixture)
+        .with_context(|| format!("No parser registered for fixture '{}'", fixture))?;
+    let seconds = match args.get(2) {
+        Some(secs) => secs.parse().with_context(|| format!("Invalid duration '{}'", secs))?,
+        None => DEFAULT_RECORD_SECS,
+    };
+
+    let (_manager, adapter) = ble::first_adapter().await?;
+    adapter.start_scan(ScanFilter::default()).await?;
+    eprintln!("🔍 Looking for {}...", address);
+    let deadline = tokio::time::Instant::now() + SCAN_TIMEOUT;
+    let peripheral = loop {
+        let mut found = None;
+        for peripheral in adapter.peripherals().await? {
+            if peripheral.address().to_string().eq_ignore_ascii_case(address) {
+                found = Some(peripheral);
+            }
+        }
+        match found {
+            Some(peripheral) => break peripheral,
+            None if tokio::time::Instant::now() >= deadline => bail!("{} not found within {:?}", address, SCAN_TIMEOUT),
+            None => tokio::time::sleep(Duration::from_secs(1)).await,
+        }
+    };
+    adapter.stop_scan().await?;
+
+    peripheral.connect().await?;
+    peripheral.discover_services().await?;
+    let target = peripheral
+        .characteristics()
+        .into_iter()
+        .find(|c| c.uuid == characteristic)
+        .with_context(|| format!("{} has no characteristic {}", address, characteristic))?;
+    let name = peripheral.properties().await?.and_then(|p| p.local_name).unwrap_or_default();
+    let mut notifications = peripheral.notifications().await?;
+    peripheral.subscribe(&target).await?;
+
+    let path = format!("bbq-protocols/tests/fixtures/{}.txt", fixture);
+    let mut file = std::fs::File::create(&path).with_context(|| format!("Failed to create {}", path))?;
+    writeln!(file, "# {} ({}) on {}, recorded {}", name, address, characteristic, Utc::now().format("%Y-%m-%d"))?;
+    eprintln!("⏺️  Recording {} for {}s into {}", name, seconds, path);
+
+    let mut frames = 0;
+    let stop = tokio::time::Instant::now() + Duration::from_secs(seconds);
+    while let Ok(Some(notification)) = tokio::time::timeout_at(stop, notifications.next()).await {
+        if notification.uuid == characteristic {
+            writeln!(file, "{}", to_hex(&notification.value))?;
+            frames += 1;
+        }
+    }
+    let _ = peripheral.disconnect().await;
+    eprintln!("✅ {} frames written; add the expected values to bbq-protocols/tests/fixtures/{}.csv", frames, fixture);
+    Ok(())
+}
@@ -0,0 +1,508 @@
+// src/ble.rs
+use btleplug::api::{Central as _, Manager as _, ScanFilter};
+use btleplug::platform::{Adapter, Manager};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// Phase changes kept for WebSocket clients that fall behind
+const PHASE_CHANNEL_CAPACITY: usize = 16;
+
+/// How far the Bluetooth subsystem has come since launch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum BlePhase {
+    Starting,
+    Scanning { duration_secs: u64 },
+    Connecting { devices: usize },
+    Monitoring { devices: usize },
+    /// No usable adapter; the error says why
+    Failed,
+    Stopped,
+}
+
+impl BlePhase {
+    /// Short progress line for the dashboard
+    pub fn message(&self) -> String {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        match self {
+            Self::Starting => "initializing Bluetooth...".to_string(),
+            Self::Scanning { .. } => "scanning...".to_string(),
+            Self::Connecting { devices } => format!("connecting to {} device{}...", devices, plural(*devices)),
+            Self::Monitoring { devices } => format!("monitoring {} device{}", devices, plural(*devices)),
+            Self::Failed => "Bluetooth unavailable".to_string(),
+            Self::Stopped => "stopped".to_string(),
+        }
+    }
+}
+
+/// A phase change with its message, as sent to WebSocket clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BleStatus {
+    #[serde(flatten)]
+    pub phase: BlePhase,
+    pub message: String,
+    /// Devices that stopped answering and are no longer polled
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub offline_devices: Vec<String>,
+}
+
+impl From<BlePhase> for BleStatus {
+    fn from(phase: BlePhase) -> Self {
+        Self { message: phase.message(), phase, offline_devices: Vec::new() }
+    }
+}
+
+struct BleState {
+    phase: BlePhase,
+    error: Option<BleEnvironmentError>,
+    offline: BTreeSet<String>,
+}
+
+impl BleState {
+    fn status(&self) -> BleStatus {
+        let mut status = BleStatus::from(self.phase.clone());
+        if !self.offline.is_empty() {
+            status.message = format!("{}, {} offline", status.message, self.offline.len());
+            status.offline_devices = self.offline.iter().cloned().collect();
+        }
+        status
+    }
+}
+
+/// Shared, last-known state of the Bluetooth subsystem: startup phase and environment error
+///
+/// The monitor updates it while the web server is already serving, so
+/// `/api/health` and WebSocket clients can follow a slow scan.
+#[derive(Clone)]
+pub struct BleHealth {
+    state: Arc<RwLock<BleState>>,
+    changes: broadcast::Sender<BleStatus>,
+}
+
+impl Default for BleHealth {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(BleState { phase: BlePhase::Starting, error: None, offline: BTreeSet::new() })),
+            changes: broadcast::channel(PHASE_CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl BleHealth {
+    pub fn phase(&self) -> BlePhase {
+        self.state.read().unwrap().phase.clone()
+    }
+
+    /// Phase, message and offline devices, as sent to WebSocket clients
+    pub fn status(&self) -> BleStatus {
+        self.state.read().unwrap().status()
+    }
+
+    /// The environment error behind the last failure (None = healthy)
+    pub fn error(&self) -> Option<BleEnvironmentError> {
+        self.state.read().unwrap().error.clone()
+    }
+
+    /// Move to `phase`, clearing any earlier error, and tell subscribers
+    pub fn set_phase(&self, phase: BlePhase) {
+        let mut state = self.state.write().unwrap();
+        if state.phase == phase && state.error.is_none() {
+            return;
+        }
+        state.phase = phase;
+        state.error = None;
+        let _ = self.changes.send(state.status());
+    }
+
+    /// A device ran out of reconnect attempts; `still_monitoring` devices are left
+    pub fn device_offline(&self, address: &str, still_monitoring: usize) {
+        let mut state = self.state.write().unwrap();
+        state.offline.insert(address.to_string());
+        state.phase = BlePhase::Monitoring { devices: still_monitoring };
+        let _ = self.changes.send(state.status());
+    }
+
+    /// A device connected again, so it is no longer listed as offline
+    pub fn device_online(&self, address: &str) {
+        let mut state = self.state.write().unwrap();
+        if state.offline.remove(address) {
+            let _ = self.changes.send(state.status());
+        }
+    }
+
+    /// Record a failure classified from the environment
+    pub fn set_error(&self, error: BleEnvironmentError) {
+        let mut state = self.state.write().unwrap();
+        state.phase = BlePhase::Failed;
+        state.error = Some(error);
+        let _ = self.changes.send(state.status());
+    }
+
+    /// Phase changes from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<BleStatus> {
+        self.changes.subscribe()
+    }
+}
+
+/// Host platform, used to pick the right error patterns and remediation text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Linux,
+    MacOs,
+    Windows,
+    Other,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "linux") {
+            Platform::Linux
+        } else if cfg!(target_os = "macos") || cfg!(target_os = "ios") {
+            Platform::MacOs
+        } else if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else {
+            Platform::Other
+        }
+    }
+}
+
+/// Actionable classification of Bluetooth environment failures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BleEnvironmentError {
+    #[error("Missing Bluetooth permission: {hint}")]
+    MissingPermission { hint: String },
+    #[error("Bluetooth adapter is powered off")]
+    AdapterOff,
+    #[error("Bluetooth is not supported or the Bluetooth service is not running")]
+    NotSupported,
+    #[error("Bluetooth adapter is busy")]
+    Busy,
+    #[error("No Bluetooth adapters found")]
+    NoAdapter,
+    #[error("Bluetooth error: {message}")]
+    Other { message: String },
+}
+
+impl BleEnvironmentError {
+    /// Classify a btleplug error for the current platform
+    pub fn classify(error: &btleplug::Error) -> Self {
+        match error {
+            btleplug::Error::PermissionDenied => Self::MissingPermission {
+                hint: permission_hint(Platform::current()).to_string(),
+            },
+            btleplug::Error::NotSupported(_) => Self::NotSupported,
+            other => Self::classify_message(Platform::current(), &other.to_string()),
+        }
+    }
+
+    /// Classify a raw platform error string
+    pub fn classify_message(platform: Platform, message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        let (permission, off, unsupported, busy): (&[&str], &[&str], &[&str], &[&str]) = match platform {
+            Platform::Linux => (
+                &["accessdenied", "permission denied", "operation not permitted", "not authorized"],
+                &["org.bluez.error.notready", "not powered", "resource not ready", "rfkill"],
+                &["serviceunknown", "org.bluez was not provided", "no such file or directory"],
+                &["org.bluez.error.inprogress", "already in progress", "resource busy"],
+            ),
+            Platform::MacOs => (
+                &["unauthorized", "not authorized", "tcc"],
+                &["poweredoff", "powered off"],
+                &["unsupported"],
+                &["resetting"],
+            ),
+            Platform::Windows => (
+                &["0x80070005", "access is denied"],
+                &["0x800710df", "radio is off", "radiostate::off", "device is not ready"],
+                &["not supported", "0x80004001"],
+                &["0x800700aa", "resource is in use"],
+            ),
+            Platform::Other => (&["permission"], &["powered off"], &["not supported"], &["busy"]),
+        };
+
+        if has(permission) {
+            Self::MissingPermission {
+                hint: permission_hint(platform).to_string(),
+            }
+        } else if has(off) {
+            Self::AdapterOff
+        } else if has(unsupported) {
+            Self::NotSupported
+        } else if has(busy) {
+            Self::Busy
+        } else {
+            Self::Other {
+                message: message.to_string(),
+            }
+        }
+    }
+
+    /// Exact steps the user can take to fix the problem
+    pub fn remediation(&self, platform: Platform) -> String {
+        match self {
+            Self::MissingPermission { hint } => hint.clone(),
+            Self::AdapterOff => match platform {
+                Platform::Linux => "Power the adapter on: `bluetoothctl power on` (and `rfkill unblock bluetooth` if blocked)".to_string(),
+                Platform::MacOs => "Turn Bluetooth on in Control Center or System Settings → Bluetooth".to_string(),
+                Platform::Windows => "Turn Bluetooth on in Settings → Bluetooth & devices".to_string(),
+                Platform::Other => "Turn the Bluetooth adapter on".to_string(),
+            },
+            Self::NotSupported => match platform {
+                Platform::Linux => "Start BlueZ: `sudo systemctl enable --now bluetooth`".to_string(),
+                _ => "This system has no usable Bluetooth LE support".to_string(),
+            },
+            Self::Busy => "Another scan or connection is in progress; stop other Bluetooth apps and retry".to_string(),
+            Self::NoAdapter => "Plug in a Bluetooth LE adapter or enable the built-in radio".to_string(),
+            Self::Other { .. } => "Re-run with RUST_LOG=debug for details".to_string(),
+        }
+    }
+
+    /// JSON document describing this error (kind, message, remediation)
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("message".to_string(), self.to_string().into());
+            obj.insert(
+                "remediation".to_string(),
+                self.remediation(Platform::current()).into(),
+            );
+        }
+        value
+    }
+}
+
+fn permission_hint(platform: Platform) -> &'static str {
+    match platform {
+        Platform::Linux => "Run `sudo setcap 'cap_net_raw,cap_net_admin+eip' $(which bbq-monitor)` or add your user to the `bluetooth` group",
+        Platform::MacOs => "Allow Bluetooth access in System Settings → Privacy & Security → Bluetooth",
+        Platform::Windows => "Enable 'Let apps access Bluetooth' in Settings → Privacy & security",
+        Platform::Other => "Grant this process permission to use Bluetooth",
+    }
+}
+
+/// Pick one of the adapters described by `infos` (their `adapter_info` strings)
+///
+/// `address` is matched case-insensitively within each description, e.g.
+/// "hci1" on Linux, and wins over `index`. A requested adapter that is not
+/// present falls back to the first one with a warning.
+pub fn choose_adapter(infos: &[String], index: Option<usize>, address: Option<&str>) -> Result<usize, BleEnvironmentError> {
+    if infos.is_empty() {
+        return Err(BleEnvironmentError::NoAdapter);
+    }
+    if let Some(address) = address.map(str::trim).filter(|a| !a.is_empty()) {
+        let wanted = address.to_lowercase();
+        match infos.iter().position(|info| info.to_lowercase().contains(&wanted)) {
+            Some(found) => return Ok(found),
+            None => warn!("⚠️  No Bluetooth adapter matches \"{}\"; using adapter 0", address),
+        }
+    } else if let Some(index) = index {
+        if index < infos.len() {
+            return Ok(index);
+        }
+        warn!("⚠️  Bluetooth adapter {} not present ({} found); using adapter 0", index, infos.len());
+    }
+    Ok(0)
+}
+
+/// Info string of each adapter, as `choose_adapter` expects
+pub async fn adapter_infos(adapters: &[Adapter]) -> Vec<String> {
+    let mut infos = Vec::with_capacity(adapters.len());
+    for adapter in adapters {
+        infos.push(adapter.adapter_info().await.unwrap_or_default());
+    }
+    infos
+}
+
+/// Create the platform BLE manager and return the adapter chosen by `index`/`address`
+///
+/// Every adapter found is logged with its info string, so the right value for
+/// `device.adapter_address` can be read off the startup log.
+pub async fn select_adapter(index: Option<usize>, address: Option<&str>) -> Result<(Manager, Adapter), BleEnvironmentError> {
+    let manager = Manager::new()
+        .await
+        .map_err(|e| BleEnvironmentError::classify(&e))?;
+    let mut adapters = manager
+        .adapters()
+        .await
+        .map_err(|e| BleEnvironmentError::classify(&e))?;
+
+    let infos = adapter_infos(&adapters).await;
+    for (i, info) in infos.iter().enumerate() {
+        info!("📡 Bluetooth adapter {}: {}", i, info);
+    }
+
+    let chosen = choose_adapter(&infos, index, address)?;
+    Ok((manager, adapters.swap_remove(chosen)))
+}
+
+/// Create the platform BLE manager and return the first adapter
+pub async fn first_adapter() -> Result<(Manager, Adapter), BleEnvironmentError> {
+    select_adapter(None, None).await
+}
+
+/// What the startup scan needs from the radio; the adapter in the binary, a script in tests
+pub trait ScanCentral {
+    fn start_scan(&self) -> impl Future<Output = Result<(), BleEnvironmentError>> + Send;
+
+    fn stop_scan(&self) -> impl Future<Output = Result<(), BleEnvironmentError>> + Send;
+
+    /// Resolves once the scan has collected advertisements for `duration`
+    fn scan_window(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+impl ScanCentral for Adapter {
+    async fn start_scan(&self) -> Result<(), BleEnvironmentError> {
+        btleplug::api::Central::start_scan(self, ScanFilter::default())
+            .await
+            .map_err(|e| BleEnvironmentError::classify(&e))
+    }
+
+    async fn stop_scan(&self) -> Result<(), BleEnvironmentError> {
+        btleplug::api::Central::stop_scan(self).await.map_err(|e| BleEnvironmentError::classify(&e))
+    }
+}
+
+/// Scan for `duration`, reporting the scanning phase through `health` meanwhile
+///
+/// Returns false if shutdown came first; the scan is stopped then.
+pub async fn run_scan<C: ScanCentral>(
+    central: &C,
+    health: &BleHealth,
+    duration: Duration,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<bool, BleEnvironmentError> {
+    central.start_scan().await?;
+    health.set_phase(BlePhase::Scanning { duration_secs: duration.as_secs() });
+    tokio::select! {
+        _ = central.scan_window(duration) => Ok(true),
+        _ = shutdown.recv() => {
+            if let Err(e) = central.stop_scan().await {
+                warn!("Could not stop the scan: {}", e);
+            }
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_adapter() {
+        let infos = vec!["hci0 (usb:v1D6Bp0246d0548)".to_string(), "hci1 (usb:v0A12p0001d8891)".to_string()];
+        assert_eq!(choose_adapter(&infos, None, None), Ok(0));
+        assert_eq!(choose_adapter(&infos, Some(1), None), Ok(1));
+        assert_eq!(choose_adapter(&infos, Some(5), None), Ok(0), "missing index falls back");
+        assert_eq!(choose_adapter(&infos, Some(0), Some("HCI1")), Ok(1), "address wins");
+        assert_eq!(choose_adapter(&infos, Some(1), Some("hci7")), Ok(0), "missing address falls back");
+        assert_eq!(choose_adapter(&infos, Some(1), Some(" ")), Ok(1), "blank address is unset");
+        assert_eq!(choose_adapter(&[], Some(0), None), Err(BleEnvironmentError::NoAdapter));
+    }
+
+    #[test]
+    fn test_health_tracks_phase_and_error() {
+        let health = BleHealth::default();
+        let mut changes = health.subscribe();
+        assert_eq!(health.phase(), BlePhase::Starting);
+
+        health.set_phase(BlePhase::Connecting { devices: 2 });
+        health.set_phase(BlePhase::Connecting { devices: 2 });
+        let status = changes.try_recv().unwrap();
+        assert_eq!(status.message, "connecting to 2 devices...");
+        assert!(changes.try_recv().is_err(), "unchanged phase is not re-sent");
+
+        health.set_error(BleEnvironmentError::AdapterOff);
+        assert_eq!((health.phase(), health.error()), (BlePhase::Failed, Some(BleEnvironmentError::AdapterOff)));
+        assert_eq!(changes.try_recv().unwrap().phase, BlePhase::Failed);
+
+        health.set_phase(BlePhase::Monitoring { devices: 1 });
+        assert_eq!(health.error(), None);
+        let json = serde_json::to_value(health.status()).unwrap();
+        assert_eq!(json, serde_json::json!({ "phase": "monitoring", "devices": 1, "message": "monitoring 1 device" }));
+    }
+
+    #[test]
+    fn test_linux_errors() {
+        let err = BleEnvironmentError::classify_message(
+            Platform::Linux,
+            "D-Bus error: org.freedesktop.DBus.Error.AccessDenied: Rejected send message",
+        );
+        match &err {
+            BleEnvironmentError::MissingPermission { hint } => assert!(hint.contains("setcap")),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert_eq!(
+            BleEnvironmentError::classify_message(Platform::Linux, "org.bluez.Error.NotReady: Resource Not Ready"),
+            BleEnvironmentError::AdapterOff
+        );
+        assert_eq!(
+            BleEnvironmentError::classify_message(
+                Platform::Linux,
+                "org.freedesktop.DBus.Error.ServiceUnknown: The name org.bluez was not provided by any .service files"
+            ),
+            BleEnvironmentError::NotSupported
+        );
+        assert_eq!(
+            BleEnvironmentError::classify_message(Platform::Linux, "org.bluez.Error.InProgress: Operation already in progress"),
+            BleEnvironmentError::Busy
+        );
+    }
+
+    #[test]
+    fn test_macos_errors() {
+        assert!(matches!(
+            BleEnvironmentError::classify_message(Platform::MacOs, "CBManagerStateUnauthorized"),
+            BleEnvironmentError::MissingPermission { .. }
+        ));
+        assert_eq!(
+            BleEnvironmentError::classify_message(Platform::MacOs, "CBManagerStatePoweredOff"),
+            BleEnvironmentError::AdapterOff
+        );
+        assert_eq!(
+            BleEnvironmentError::classify_message(Platform::MacOs, "CBManagerStateUnsupported"),
+            BleEnvironmentError::NotSupported
+        );
+    }
+
+    #[test]
+    fn test_windows_errors() {
+        assert!(matches!(
+            BleEnvironmentError::classify_message(Platform::Windows, "Access is denied. (0x80070005)"),
+            BleEnvironmentError::MissingPermission { .. }
+        ));
+        assert_eq!(
+            BleEnvironmentError::classify_message(Platform::Windows, "The device is not ready for use. (0x800710DF)"),
+            BleEnvironmentError::AdapterOff
+        );
+        assert_eq!(
+            BleEnvironmentError::classify_message(Platform::Windows, "The requested resource is in use. (0x800700AA)"),
+            BleEnvironmentError::Busy
+        );
+    }
+
+    #[test]
+    fn test_unknown_error_kept_verbatim() {
+        let err = BleEnvironmentError::classify_message(Platform::Linux, "something odd");
+        assert_eq!(err, BleEnvironmentError::Other { message: "something odd".to_string() });
+    }
+
+    #[test]
+    fn test_json_includes_remediation() {
+        let json = BleEnvironmentError::AdapterOff.to_json();
+        assert_eq!(json["kind"], "adapter_off");
+        assert!(json["remediation"].as_str().unwrap().len() > 10);
+    }
+}
@@ -0,0 +1,167 @@
+// src/ble_debug.rs
+use btleplug::api::{Peripheral, WriteType};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::StreamExt;
+use tracing::info;
+use uuid::Uuid;
+
+/// Debug traffic is logged under the UART target, next to probe command tracing
+pub const DEBUG_TRACE_TARGET: &str = "bbq_monitor::uart";
+
+/// How long a debug operation collects notifications unless the request says otherwise
+pub const DEFAULT_DEBUG_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest a debug operation may hold a request open
+pub const MAX_DEBUG_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pending debug operations queued for the monitor
+const DEBUG_CHANNEL_CAPACITY: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DebugError {
+    #[error("{0} is not connected")]
+    NotConnected(String),
+    #[error("No characteristic {characteristic} in service {service}")]
+    CharacteristicNotFound { service: Uuid, characteristic: Uuid },
+    #[error("BLE error: {0}")]
+    Ble(String),
+    #[error("No response within {0:?}")]
+    Timeout(Duration),
+    #[error("Device monitor is not running")]
+    MonitorUnavailable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugOperation {
+    Write { payload: Vec<u8>, with_response: bool },
+    Read,
+}
+
+/// A raw GATT operation for the monitor to run on a live connection
+#[derive(Debug)]
+pub struct DebugCommand {
+    pub device_address: String,
+    pub service: Uuid,
+    pub characteristic: Uuid,
+    pub operation: DebugOperation,
+    /// How long to collect notifications (writes) or wait for the value (reads)
+    pub timeout: Duration,
+    pub reply: oneshot::Sender<Result<DebugResponse, DebugError>>,
+}
+
+/// What came back from the peripheral, as hex
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DebugResponse {
+    /// Value read, for reads
+    pub value: Option<String>,
+    /// Notifications received within the timeout, from any characteristic
+    pub notifications: Vec<CapturedNotification>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CapturedNotification {
+    pub characteristic: String,
+    pub value: String,
+}
+
+/// Channel the web server sends debug commands on; the monitor owns the receiver
+pub fn debug_channel() -> (mpsc::Sender<DebugCommand>, mpsc::Receiver<DebugCommand>) {
+    mpsc::channel(DEBUG_CHANNEL_CAPACITY)
+}
+
+/// Parse a hex payload; spaces and colons between bytes are allowed
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace() && *b != b':').collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Run `command` on its own task so a slow peripheral never stalls the monitor loop
+pub fn spawn_command<P: Peripheral + 'static>(peripheral: Option<P>, command: DebugCommand) {
+    tokio::spawn(async move {
+        let result = match &peripheral {
+            Some(peripheral) => execute(peripheral, &command).await,
+            None => Err(DebugError::NotConnected(command.device_address.clone())),
+        };
+        if let Err(e) = &result {
+            info!(target: DEBUG_TRACE_TARGET, "🧪 {} debug operation failed: {}", command.device_address, e);
+        }
+        let _ = command.reply.send(result);
+    });
+}
+
+async fn execute<P: Peripheral>(peripheral: &P, command: &DebugCommand) -> Result<DebugResponse, DebugError> {
+    let characteristic = peripheral
+        .characteristics()
+        .into_iter()
+        .find(|c| c.service_uuid == command.service && c.uuid == command.characteristic)
+        .ok_or(DebugError::CharacteristicNotFound {
+            service: command.service,
+            characteristic: command.characteristic,
+        })?;
+    let address = &command.device_address;
+
+    match &command.operation {
+        DebugOperation::Read => {
+            info!(target: DEBUG_TRACE_TARGET, "🧪 {} read {}", address, characteristic.uuid);
+            let value = tokio::time::timeout(command.timeout, peripheral.read(&characteristic))
+                .await
+                .map_err(|_| DebugError::Timeout(command.timeout))?
+                .map_err(|e| DebugError::Ble(e.to_string()))?;
+            info!(target: DEBUG_TRACE_TARGET, "🧪 {} {} = {}", address, characteristic.uuid, to_hex(&value));
+            Ok(DebugResponse { value: Some(to_hex(&value)), notifications: Vec::new() })
+        }
+        DebugOperation::Write { payload, with_response } => {
+            // Listen before writing so a fast reply is not missed
+            let mut notifications = peripheral.notifications().await.map_err(|e| DebugError::Ble(e.to_string()))?;
+            let write_type = if *with_response { WriteType::WithResponse } else { WriteType::WithoutResponse };
+            info!(
+                target: DEBUG_TRACE_TARGET,
+                "🧪 {} write {} ({:?}): {}", address, characteristic.uuid, write_type, to_hex(payload)
+            );
+            peripheral
+                .write(&characteristic, payload, write_type)
+                .await
+                .map_err(|e| DebugError::Ble(e.to_string()))?;
+
+            let deadline = tokio::time::Instant::now() + command.timeout;
+            let mut captured = Vec::new();
+            while let Ok(Some(notification)) = tokio::time::timeout_at(deadline, notifications.next()).await {
+                info!(
+                    target: DEBUG_TRACE_TARGET,
+                    "🧪 {} notify {}: {}", address, notification.uuid, to_hex(&notification.value)
+                );
+                captured.push(CapturedNotification {
+                    characteristic: notification.uuid.to_string(),
+                    value: to_hex(&notification.value),
+                });
+            }
+            Ok(DebugResponse { value: None, notifications: captured })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(parse_hex("01 aB:ff"), Some(vec![0x01, 0xab, 0xff]));
+        assert_eq!(parse_hex(""), Some(vec![]));
+        assert_eq!(parse_hex("abc"), None);
+        assert_eq!(parse_hex("zz"), None);
+        assert_eq!(to_hex(&[0x00, 0x7f, 0xa0]), "007fa0");
+    }
+}
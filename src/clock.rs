@@ -0,0 +1,159 @@
+// src/clock.rs
+use chrono::{DateTime, Utc};
+use std::time::Instant;
+
+/// Source of wall-clock and monotonic time
+///
+/// Ages (freshness, confidence) come from `monotonic`, which never jumps when
+/// NTP steps the system clock; `wall` is only for storing and showing times.
+/// Tests swap in a clock they can step.
+pub trait Clock {
+    fn wall(&self) -> DateTime<Utc>;
+    fn monotonic(&self) -> Instant;
+}
+
+/// The system clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn wall(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn monotonic(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Kernel id of the current boot (Linux); monotonic offsets only compare within one boot
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+/// Seconds since boot, suspend included (Linux)
+const UPTIME_PATH: &str = "/proc/uptime";
+
+/// Wall and monotonic elapsed times may differ by this much before the wall clock is distrusted
+pub const CLOCK_DISAGREEMENT_SECS: i64 = 120;
+
+/// Where the monotonic clock stood when a wall timestamp was written
+///
+/// Stored next to cook session timestamps so durations survive the wall
+/// clock being reset, e.g. a Pi without a battery-backed clock booting after
+/// a power cut with a stale time until NTP syncs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonotonicAnchor {
+    pub boot_id: String,
+    /// Milliseconds since that boot
+    pub uptime_ms: i64,
+}
+
+impl MonotonicAnchor {
+    /// The anchor for this instant; None where the system exposes no boot id
+    pub fn now() -> Option<Self> {
+        let boot_id = std::fs::read_to_string(BOOT_ID_PATH).ok()?;
+        let uptime = std::fs::read_to_string(UPTIME_PATH).ok()?;
+        let uptime_secs: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+        Some(Self { boot_id: boot_id.trim().to_string(), uptime_ms: (uptime_secs * 1000.0) as i64 })
+    }
+
+    /// Rebuild a stored anchor; None unless both halves were stored
+    pub fn from_parts(boot_id: Option<String>, uptime_ms: Option<i64>) -> Option<Self> {
+        Some(Self { boot_id: boot_id?, uptime_ms: uptime_ms? })
+    }
+}
+
+/// How long a session lasted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionDuration {
+    pub secs: i64,
+    /// The wall timestamps were contradicted by the anchors, so this comes from the monotonic clock
+    pub estimated: bool,
+}
+
+/// Duration between two wall timestamps, checked against the anchors written with them
+///
+/// Within one boot the monotonic delta is exact, and replaces the wall delta
+/// when the two differ by more than `CLOCK_DISAGREEMENT_SECS`. Across a
+/// reboot only the time since the new boot is known, so that is the least the
+/// session can have lasted. Without anchors the wall delta stands, though
+/// never below zero.
+pub fn reconcile_duration(
+    started_at: DateTime<Utc>,
+    start: Option<&MonotonicAnchor>,
+    ended_at: DateTime<Utc>,
+    end: Option<&MonotonicAnchor>,
+) -> SessionDuration {
+    let wall_secs = (ended_at - started_at).num_seconds();
+    let estimated = |secs: i64| SessionDuration { secs, estimated: true };
+    match (start, end) {
+        (Some(start), Some(end)) if start.boot_id == end.boot_id => {
+            let monotonic_secs = (end.uptime_ms - start.uptime_ms).max(0) / 1000;
+            if (wall_secs - monotonic_secs).abs() > CLOCK_DISAGREEMENT_SECS {
+                return estimated(monotonic_secs);
+            }
+        }
+        (Some(_), Some(end)) => {
+            let since_reboot_secs = end.uptime_ms.max(0) / 1000;
+            if wall_secs < since_reboot_secs {
+                return estimated(since_reboot_secs);
+            }
+        }
+        _ if wall_secs < 0 => return estimated(0),
+        _ => {}
+    }
+    SessionDuration { secs: wall_secs, estimated: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn anchor(boot_id: &str, uptime_secs: i64) -> MonotonicAnchor {
+        MonotonicAnchor { boot_id: boot_id.to_string(), uptime_ms: uptime_secs * 1000 }
+    }
+
+    fn wall(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 5, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_agreeing_clocks_keep_the_wall_duration() {
+        let duration = reconcile_duration(wall(10, 0), Some(&anchor("a", 600)), wall(12, 0), Some(&anchor("a", 600 + 7260)));
+        assert_eq!(duration, SessionDuration { secs: 7200, estimated: false }, "a minute of drift is tolerated");
+        assert_eq!(reconcile_duration(wall(10, 0), None, wall(12, 0), None).secs, 7200);
+    }
+
+    #[test]
+    fn test_stepped_wall_clock_within_one_boot() {
+        // NTP synced mid-cook and moved the clock forward a day
+        let start = anchor("a", 60);
+        let duration = reconcile_duration(wall(10, 0) - chrono::Duration::days(1), Some(&start), wall(12, 0), Some(&anchor("a", 60 + 7200)));
+        assert_eq!(duration, SessionDuration { secs: 7200, estimated: true });
+
+        // Clock stepped back: the wall delta is negative
+        let duration = reconcile_duration(wall(12, 0), Some(&start), wall(10, 0), Some(&anchor("a", 60 + 1800)));
+        assert_eq!(duration, SessionDuration { secs: 1800, estimated: true });
+    }
+
+    #[test]
+    fn test_reboot_mid_session() {
+        // Power cut: the Pi came back believing it was earlier than the start
+        let duration = reconcile_duration(wall(12, 0), Some(&anchor("a", 5000)), wall(9, 0), Some(&anchor("b", 1500)));
+        assert_eq!(duration, SessionDuration { secs: 1500, estimated: true }, "at least the time since the reboot");
+
+        // After NTP fixed the clock the wall delta is plausible again
+        let duration = reconcile_duration(wall(9, 0), Some(&anchor("a", 5000)), wall(12, 0), Some(&anchor("b", 1500)));
+        assert_eq!(duration, SessionDuration { secs: 3 * 3600, estimated: false });
+    }
+
+    #[test]
+    fn test_missing_anchors_fall_back_to_the_wall_clock() {
+        // Sessions from before anchors were stored, or on systems without a boot id
+        assert_eq!(reconcile_duration(wall(12, 0), None, wall(11, 0), None), SessionDuration { secs: 0, estimated: true });
+        let duration = reconcile_duration(wall(10, 0), None, wall(11, 0), Some(&anchor("b", 30)));
+        assert_eq!(duration, SessionDuration { secs: 3600, estimated: false }, "no start anchor, no bound");
+        assert_eq!(MonotonicAnchor::from_parts(Some("a".into()), None), None);
+        assert_eq!(MonotonicAnchor::from_parts(Some("a".into()), Some(5)), Some(MonotonicAnchor { boot_id: "a".into(), uptime_ms: 5 }));
+    }
+}
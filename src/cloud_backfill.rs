@@ -0,0 +1,195 @@
+// src/cloud_backfill.rs
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use tracing::{debug, info};
+
+use crate::database::{Database, ReadingSource};
+use crate::device_capabilities::ProbeCapabilities;
+use crate::temperature::{Temperature, TemperatureUnit};
+
+/// A local reading within this long of a cloud reading means it is already stored
+const DUPLICATE_WINDOW_SECS: i64 = 5;
+
+/// Result of one sync pass
+#[derive(Debug, Clone, Default)]
+pub struct SyncBatch {
+    /// Readings written
+    pub count: usize,
+    /// Newest timestamp confirmed synced or queued for retry (None = nothing new)
+    pub cursor: Option<DateTime<Utc>>,
+    /// Readings that failed to upload and went to the pending sync queue
+    pub queued: usize,
+}
+
+/// Temperature reading for cloud sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudReading {
+    pub device_address: String,
+    pub device_name: String,
+    pub temperature: f64,
+    pub ambient_temp: Option<f64>,
+    /// Unit of `temperature`/`ambient_temp`; items written before Celsius storage have none and are Fahrenheit
+    #[serde(default)]
+    pub unit: TemperatureUnit,
+    pub battery_level: Option<u8>,
+    pub signal_strength: i16,
+    pub timestamp: DateTime<Utc>,
+    pub source: String, // "local" or "cloud"
+}
+
+/// Readings stored in the cloud, behind a trait so tests can stub it
+pub trait CloudHistory {
+    /// Every reading of a device since `since`, oldest first
+    fn device_readings(&self, device_address: &str, since: DateTime<Utc>) -> impl Future<Output = Result<Vec<CloudReading>>> + Send;
+
+    /// Address and name of every device with readings since `since`
+    fn devices_since(&self, since: DateTime<Utc>) -> impl Future<Output = Result<Vec<(String, String)>>> + Send;
+}
+
+/// Store cloud readings of `devices` since `since` that are not stored locally yet
+///
+/// Any reading with a local one for the device within a few seconds of it is
+/// skipped. Unless `include_uploads` is set, so is every reading uploaded from
+/// a monitor (`source == "local"`), which is all of them on a fresh install.
+pub async fn import_cloud_readings<H: CloudHistory>(
+    history: &H,
+    db: &Database,
+    devices: &[String],
+    since: DateTime<Utc>,
+    include_uploads: bool,
+) -> Result<SyncBatch> {
+    let mut batch = SyncBatch::default();
+    let window = chrono::Duration::seconds(DUPLICATE_WINDOW_SECS);
+
+    for device_address in devices {
+        let cloud_readings = history.device_readings(device_address, since).await?;
+        debug!("Retrieved {} cloud readings for device {}", cloud_readings.len(), device_address);
+
+        for reading in cloud_readings {
+            batch.cursor = batch.cursor.max(Some(reading.timestamp));
+            if reading.source == "local" && !include_uploads {
+                continue;
+            }
+            let existing = db
+                .get_readings_in_range(&reading.device_address, reading.timestamp - window, reading.timestamp + window)
+                .await?;
+            if !existing.is_empty() {
+                continue;
+            }
+
+            db.insert_reading_from(
+                ReadingSource::Cloud,
+                &reading.device_address,
+                reading.timestamp,
+                0,
+                Temperature::from_unit(reading.temperature as f32, reading.unit),
+                reading.ambient_temp.map(|t| Temperature::from_unit(t as f32, reading.unit)),
+                reading.battery_level,
+                reading.signal_strength,
+            )
+            .await?;
+            batch.count += 1;
+        }
+    }
+    Ok(batch)
+}
+
+/// Pull the last `hours` of cloud history, adding devices this instance has never seen
+///
+/// Meant for a fresh install, once at startup; leaves the sync cursors alone,
+/// so the regular sync carries on as before.
+pub async fn backfill_from_cloud<H: CloudHistory>(history: &H, db: &Database, hours: u64) -> Result<SyncBatch> {
+    let since = Utc::now() - chrono::Duration::hours(hours as i64);
+    info!("☁️  Backfilling {}h of cloud history since {}", hours, since.to_rfc3339());
+
+    let known: HashSet<String> = db.get_all_devices().await?.into_iter().map(|d| d.device_address).collect();
+    let cloud_devices: HashMap<String, String> = history.devices_since(since).await?.into_iter().collect();
+    for (address, name) in cloud_devices.iter().filter(|(address, _)| !known.contains(*address)) {
+        let capabilities = ProbeCapabilities::detect_from_device(name, address, &[], &HashMap::new());
+        db.upsert_device(address, name, &format!("{:?}", capabilities.brand), &capabilities.model, capabilities.sensor_count)
+            .await?;
+    }
+
+    let mut devices: Vec<String> = known.into_iter().chain(cloud_devices.into_keys()).collect();
+    devices.sort();
+    devices.dedup();
+    // The duplicate window keeps readings that are still stored locally from coming back twice
+    let batch = import_cloud_readings(history, db, &devices, since, true).await?;
+    info!("☁️  Backfilled {} readings from the cloud for {} devices", batch.count, devices.len());
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cloud table held in memory
+    struct StubCloud {
+        readings: Vec<CloudReading>,
+    }
+
+    impl CloudHistory for StubCloud {
+        async fn device_readings(&self, device_address: &str, since: DateTime<Utc>) -> Result<Vec<CloudReading>> {
+            Ok(self.readings.iter().filter(|r| r.device_address == device_address && r.timestamp >= since).cloned().collect())
+        }
+
+        async fn devices_since(&self, since: DateTime<Utc>) -> Result<Vec<(String, String)>> {
+            Ok(self
+                .readings
+                .iter()
+                .filter(|r| r.timestamp >= since)
+                .map(|r| (r.device_address.clone(), r.device_name.clone()))
+                .collect())
+        }
+    }
+
+    fn cloud_reading(address: &str, minutes_ago: i64, celsius: f64, source: &str) -> CloudReading {
+        CloudReading {
+            device_address: address.to_string(),
+            device_name: "MEATER".to_string(),
+            temperature: celsius,
+            ambient_temp: None,
+            unit: TemperatureUnit::Celsius,
+            battery_level: Some(80),
+            signal_strength: -60,
+            timestamp: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            source: source.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_inserts_cloud_history() {
+        let db = Database::new(":memory:").await.unwrap();
+        let cloud = StubCloud {
+            readings: vec![
+                cloud_reading("AA", 30, 60.0, "local"),
+                cloud_reading("AA", 20, 62.0, "local"),
+                cloud_reading("AA", 10, 64.0, "local"),
+                cloud_reading("BB", 5, 70.0, "local"),
+                cloud_reading("BB", 48 * 60, 20.0, "local"),
+            ],
+        };
+        // AA's latest reading is still stored here
+        db.upsert_device("AA", "MEATER", "Meater", "MEATER", 2).await.unwrap();
+        let kept = &cloud.readings[2];
+        db.insert_reading("AA", kept.timestamp, 0, Temperature::from_celsius(64.0), None, None, -60).await.unwrap();
+
+        let batch = backfill_from_cloud(&cloud, &db, 24).await.unwrap();
+        assert_eq!(batch.count, 3, "readings stored locally and outside the window are skipped");
+        assert_eq!(db.get_device("BB").await.unwrap().device_name, "MEATER", "unknown devices are added");
+
+        let since = Utc::now() - chrono::Duration::hours(24);
+        let stored = db.get_readings_since("AA", since).await.unwrap();
+        let temperatures: Vec<f32> = stored.iter().map(|r| r.temperature.celsius()).collect();
+        assert_eq!(temperatures, [60.0, 62.0, 64.0]);
+        let sources: Vec<ReadingSource> = stored.iter().map(|r| r.source).collect();
+        assert_eq!(sources, [ReadingSource::Cloud, ReadingSource::Cloud, ReadingSource::Ble]);
+
+        // Running it again finds everything already stored
+        assert_eq!(backfill_from_cloud(&cloud, &db, 24).await.unwrap().count, 0);
+        assert_eq!(db.get_readings_since("BB", since).await.unwrap().len(), 1);
+    }
+}
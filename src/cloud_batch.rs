@@ -0,0 +1,182 @@
+// src/cloud_batch.rs
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// DynamoDB `BatchWriteItem` takes at most 25 put requests...
+pub const MAX_BATCH_ITEMS: usize = 25;
+
+/// ...totalling at most 16 MB
+pub const MAX_BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+/// Retries of the items a batch left unprocessed, before they count as failed
+const UNPROCESSED_RETRIES: u32 = 4;
+
+/// Wait before the first retry of unprocessed items; doubles with every retry
+const UNPROCESSED_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// A store that writes several items per request, behind a trait so tests can stub it
+pub trait BatchWrite<T> {
+    /// Write one batch; returns the positions of items the store left unprocessed
+    fn write_batch(&self, batch: &[T]) -> impl Future<Output = Result<Vec<usize>>> + Send;
+}
+
+/// Which items a batched write persisted
+#[derive(Debug)]
+pub struct BatchOutcome<T> {
+    pub persisted: Vec<T>,
+    /// Items not written, in their original order
+    pub failed: Vec<T>,
+    /// Why `failed` is not empty
+    pub error: Option<anyhow::Error>,
+}
+
+/// Split `items` into batches of at most `max_items` whose `size` adds up to at most `max_bytes`
+///
+/// Order is kept. An item larger than `max_bytes` gets a batch of its own.
+pub fn chunk_by_limits<T>(items: Vec<T>, max_items: usize, max_bytes: usize, size: impl Fn(&T) -> usize) -> Vec<Vec<T>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    for item in items {
+        let item_bytes = size(&item);
+        if !batch.is_empty() && (batch.len() >= max_items || batch_bytes + item_bytes > max_bytes) {
+            batches.push(std::mem::take(&mut batch));
+            batch_bytes = 0;
+        }
+        batch_bytes += item_bytes;
+        batch.push(item);
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Write `items` in batches within the DynamoDB limits, retrying unprocessed items with backoff
+///
+/// Stops at the first request error; everything not yet written is then
+/// returned as failed.
+pub async fn write_in_batches<T, W: BatchWrite<T>>(
+    writer: &W,
+    items: Vec<T>,
+    size: impl Fn(&T) -> usize,
+) -> BatchOutcome<T> {
+    let mut outcome = BatchOutcome { persisted: Vec::new(), failed: Vec::new(), error: None };
+    let mut batches = chunk_by_limits(items, MAX_BATCH_ITEMS, MAX_BATCH_BYTES, size).into_iter();
+
+    for batch in batches.by_ref() {
+        let mut pending = batch;
+        for retry in 0..=UNPROCESSED_RETRIES {
+            if retry > 0 {
+                tokio::time::sleep(UNPROCESSED_RETRY_DELAY.saturating_mul(1 << (retry - 1))).await;
+            }
+            let unprocessed = match writer.write_batch(&pending).await {
+                Ok(unprocessed) => unprocessed,
+                Err(e) => {
+                    outcome.error = Some(e);
+                    break;
+                }
+            };
+            let (retry_items, written): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .enumerate()
+                .partition(|(i, _)| unprocessed.contains(i));
+            outcome.persisted.extend(written.into_iter().map(|(_, item)| item));
+            pending = retry_items.into_iter().map(|(_, item)| item).collect();
+            if pending.is_empty() {
+                break;
+            }
+        }
+
+        if !pending.is_empty() {
+            if outcome.error.is_none() {
+                warn!("⚠️  {} items still unprocessed after {} retries", pending.len(), UNPROCESSED_RETRIES);
+            }
+            outcome.failed.extend(pending);
+        }
+        if outcome.error.is_some() {
+            break;
+        }
+    }
+
+    outcome.failed.extend(batches.flatten());
+    if !outcome.failed.is_empty() && outcome.error.is_none() {
+        outcome.error = Some(anyhow!("{} items left unprocessed", outcome.failed.len()));
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records every batch; leaves the first item of the first `unprocessed` calls unwritten
+    #[derive(Default)]
+    struct StubWriter {
+        batches: Mutex<Vec<Vec<u32>>>,
+        unprocessed: usize,
+        fail_on_call: Option<usize>,
+    }
+
+    impl BatchWrite<u32> for StubWriter {
+        async fn write_batch(&self, batch: &[u32]) -> Result<Vec<usize>> {
+            let mut batches = self.batches.lock().unwrap();
+            let call = batches.len();
+            batches.push(batch.to_vec());
+            if self.fail_on_call == Some(call) {
+                return Err(anyhow!("connection reset"));
+            }
+            Ok(if call < self.unprocessed { vec![0] } else { Vec::new() })
+        }
+    }
+
+    fn sizes(writer: &StubWriter) -> Vec<usize> {
+        writer.batches.lock().unwrap().iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn test_chunking_boundaries() {
+        let lens = |n: u32, max_bytes: usize, size: usize| -> Vec<usize> {
+            chunk_by_limits((0..n).collect(), MAX_BATCH_ITEMS, max_bytes, |_| size).iter().map(Vec::len).collect()
+        };
+        assert!(lens(0, MAX_BATCH_BYTES, 1).is_empty());
+        assert_eq!(lens(25, MAX_BATCH_BYTES, 1), [25]);
+        assert_eq!(lens(26, MAX_BATCH_BYTES, 1), [25, 1]);
+        assert_eq!(lens(51, MAX_BATCH_BYTES, 1), [25, 25, 1]);
+
+        // The byte limit splits before the item limit does
+        assert_eq!(lens(10, 1000, 300), [3, 3, 3, 1]);
+        assert_eq!(lens(3, 1000, 5000), [1, 1, 1], "oversized items go alone");
+        let batches = chunk_by_limits((0..30).collect(), MAX_BATCH_ITEMS, MAX_BATCH_BYTES, |_| 1);
+        assert_eq!(batches.concat(), (0..30).collect::<Vec<u32>>(), "order kept");
+    }
+
+    #[tokio::test]
+    async fn test_unprocessed_items_are_retried() {
+        let writer = StubWriter { unprocessed: 2, ..Default::default() };
+        let outcome = write_in_batches(&writer, (0..30).collect(), |_| 100).await;
+        assert_eq!(outcome.persisted.len(), 30);
+        assert!(outcome.failed.is_empty() && outcome.error.is_none());
+        // Item 0 is retried alone twice before the second chunk goes out
+        assert_eq!(sizes(&writer), [25, 1, 1, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_request_error_fails_the_rest() {
+        let writer = StubWriter { fail_on_call: Some(1), ..Default::default() };
+        let outcome = write_in_batches(&writer, (0..60).collect(), |_| 100).await;
+        assert_eq!(outcome.persisted, (0..25).collect::<Vec<u32>>());
+        assert_eq!(outcome.failed, (25..60).collect::<Vec<u32>>());
+        assert!(outcome.error.unwrap().to_string().contains("reset"));
+        assert_eq!(sizes(&writer), [25, 25], "no writes after the error");
+
+        // Items the store keeps rejecting end up failed
+        let writer = StubWriter { unprocessed: usize::MAX, ..Default::default() };
+        let outcome = write_in_batches(&writer, (0..3).collect(), |_| 100).await;
+        assert_eq!((outcome.persisted, outcome.failed), (vec![1, 2], vec![0]));
+        assert!(outcome.error.is_some());
+    }
+}
@@ -0,0 +1,283 @@
+// src/cloud_upload.rs
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::cloud_backfill::SyncBatch;
+use crate::cloud_batch::BatchOutcome;
+use crate::database::{Database, OutboundReading};
+
+/// sync_state keys for each sync direction
+pub const SYNC_TO_CLOUD: &str = "to_cloud";
+pub const SYNC_FROM_CLOUD: &str = "from_cloud";
+
+/// Longest wait between upload retries while the cloud is unreachable
+const MAX_SYNC_BACKOFF: Duration = Duration::from_secs(3600);
+
+/// Pending readings fetched per drain query
+const PENDING_SYNC_BATCH: usize = 500;
+
+/// A cloud store for local readings, behind a trait so tests can stub it
+pub trait ReadingUpload {
+    /// Write `readings`; reports which ones the cloud persisted
+    fn upload_readings(&self, readings: Vec<OutboundReading>) -> impl Future<Output = BatchOutcome<OutboundReading>> + Send;
+}
+
+/// Exponential backoff shared by all uploads, since a failure usually means the link is down
+#[derive(Debug)]
+struct SyncBackoff {
+    base: Duration,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl SyncBackoff {
+    fn new(base: Duration) -> Self {
+        Self { base, failures: 0, retry_at: None }
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        self.retry_at.is_none_or(|at| now >= at)
+    }
+
+    /// Double the wait after each consecutive failure, up to `MAX_SYNC_BACKOFF`
+    fn record_failure(&mut self, now: Instant) -> Duration {
+        let delay = self.base.saturating_mul(1 << self.failures.min(16)).min(MAX_SYNC_BACKOFF);
+        self.failures += 1;
+        self.retry_at = Some(now + delay);
+        delay
+    }
+
+    fn record_success(&mut self) {
+        self.failures = 0;
+        self.retry_at = None;
+    }
+}
+
+/// Uploads local readings, tracking the sync cursor, the retry queue and the backoff between failures
+#[derive(Debug)]
+pub struct CloudUploader {
+    backoff: Mutex<SyncBackoff>,
+    /// Backfill window used when no sync cursor is stored yet
+    initial_lookback_hours: u64,
+    /// Most readings kept in the pending sync queue (0 = unbounded)
+    max_pending_sync: usize,
+}
+
+impl CloudUploader {
+    /// `retry_delay` is the first wait after a failed upload; it doubles with every further failure
+    pub fn new(retry_delay: Duration, initial_lookback_hours: u64, max_pending_sync: usize) -> Self {
+        Self {
+            backoff: Mutex::new(SyncBackoff::new(retry_delay)),
+            initial_lookback_hours,
+            max_pending_sync,
+        }
+    }
+
+    fn sync_due(&self) -> bool {
+        self.backoff.lock().unwrap().is_due(Instant::now())
+    }
+
+    fn record_sync_failure(&self, e: &anyhow::Error) {
+        let delay = self.backoff.lock().unwrap().record_failure(Instant::now());
+        warn!("☁️  Cloud upload failed, retrying in {}s: {:#}", delay.as_secs(), e);
+    }
+
+    /// Queue readings for retry, warning when the queue limit drops old ones
+    async fn enqueue(&self, db: &Database, readings: &[OutboundReading]) -> Result<()> {
+        let ids: Vec<i64> = readings.iter().map(|r| r.id).collect();
+        let dropped = db.enqueue_pending_sync(&ids, self.max_pending_sync).await?;
+        if dropped > 0 {
+            warn!(
+                "⚠️  Pending sync queue is full ({} readings), dropped the {} oldest",
+                self.max_pending_sync, dropped
+            );
+        }
+        Ok(())
+    }
+
+    /// Retry queued readings, oldest first; returns how many were uploaded
+    ///
+    /// Stops at the first failed batch and backs off, leaving the rest queued.
+    pub async fn drain_pending<U: ReadingUpload>(&self, cloud: &U, db: &Database) -> Result<usize> {
+        let mut uploaded = 0;
+        while self.sync_due() {
+            let pending = db.get_pending_sync(PENDING_SYNC_BATCH).await?;
+            if pending.is_empty() {
+                break;
+            }
+            let more = pending.len() == PENDING_SYNC_BATCH;
+
+            let outcome = cloud.upload_readings(pending).await;
+            let done: Vec<i64> = outcome.persisted.iter().map(|r| r.id).collect();
+            db.clear_pending_sync(&done).await?;
+            uploaded += done.len();
+
+            if let Some(e) = &outcome.error {
+                self.record_sync_failure(e);
+                break;
+            }
+            self.backoff.lock().unwrap().record_success();
+            if !more {
+                break;
+            }
+        }
+
+        if uploaded > 0 {
+            info!("☁️  Uploaded {} queued readings", uploaded);
+        }
+        Ok(uploaded)
+    }
+
+    /// Upload local readings recorded since `since`
+    ///
+    /// Readings the cloud did not take (or all of them, while backing off) go
+    /// to the pending sync queue, so the returned cursor covers every reading
+    /// either written or queued.
+    pub async fn sync_to_cloud<U: ReadingUpload>(&self, cloud: &U, db: &Database, since: DateTime<Utc>) -> Result<SyncBatch> {
+        info!("Starting sync to cloud since {}", since.to_rfc3339());
+
+        let readings = db.get_outbound_readings_since(since).await?;
+        debug!("Syncing {} readings", readings.len());
+
+        let mut batch = SyncBatch {
+            cursor: readings.last().map(|r| r.reading.timestamp),
+            ..SyncBatch::default()
+        };
+        if readings.is_empty() {
+            return Ok(batch);
+        }
+
+        let failed = if self.sync_due() {
+            let outcome = cloud.upload_readings(readings).await;
+            batch.count = outcome.persisted.len();
+            match &outcome.error {
+                Some(e) => self.record_sync_failure(e),
+                None => self.backoff.lock().unwrap().record_success(),
+            }
+            outcome.failed
+        } else {
+            readings
+        };
+
+        if !failed.is_empty() {
+            self.enqueue(db, &failed).await?;
+            batch.queued = failed.len();
+        }
+        info!("Synced {} readings to cloud, queued {} for retry", batch.count, batch.queued);
+        Ok(batch)
+    }
+
+    /// Where a sync direction should resume: the stored cursor, or the initial lookback
+    pub async fn resume_point(&self, db: &Database, direction: &str) -> DateTime<Utc> {
+        let lookback = Utc::now() - chrono::Duration::hours(self.initial_lookback_hours as i64);
+
+        match db.get_sync_cursor(direction).await {
+            Ok(Some(cursor)) => cursor,
+            Ok(None) => {
+                info!("No {} sync cursor yet, backfilling from {}", direction, lookback.to_rfc3339());
+                lookback
+            }
+            Err(e) => {
+                warn!("Failed to read {} sync cursor: {}", direction, e);
+                lookback
+            }
+        }
+    }
+
+    /// Persist the cursor reached by a successful batch
+    pub async fn advance_cursor(&self, db: &Database, direction: &str, batch: &SyncBatch) {
+        if let Some(cursor) = batch.cursor {
+            if let Err(e) = db.set_sync_cursor(direction, cursor).await {
+                error!("Failed to save {} sync cursor: {}", direction, e);
+            }
+        }
+    }
+
+    /// Retry queued readings, then upload readings recorded since the stored cursor and advance it
+    ///
+    /// The cursor only moves past readings the cloud confirmed or the pending
+    /// sync queue holds, so nothing is skipped however long the app or the
+    /// connection was down.
+    pub async fn flush<U: ReadingUpload>(&self, cloud: &U, db: &Database) -> Result<usize> {
+        let drained = self.drain_pending(cloud, db).await?;
+        let since = self.resume_point(db, SYNC_TO_CLOUD).await;
+        let batch = self.sync_to_cloud(cloud, db, since).await?;
+        self.advance_cursor(db, SYNC_TO_CLOUD, &batch).await;
+        Ok(drained + batch.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temperature::Temperature;
+    use anyhow::anyhow;
+
+    /// Records every uploaded temperature; fails the first `failures` calls outright
+    #[derive(Default)]
+    struct StubCloud {
+        uploaded: Mutex<Vec<f32>>,
+        calls: Mutex<usize>,
+        failures: usize,
+    }
+
+    impl ReadingUpload for StubCloud {
+        async fn upload_readings(&self, readings: Vec<OutboundReading>) -> BatchOutcome<OutboundReading> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            if *calls <= self.failures {
+                return BatchOutcome { persisted: Vec::new(), failed: readings, error: Some(anyhow!("connection reset")) };
+            }
+            self.uploaded.lock().unwrap().extend(readings.iter().map(|r| r.reading.temperature.celsius()));
+            BatchOutcome { persisted: readings, failed: Vec::new(), error: None }
+        }
+    }
+
+    /// A database whose last upload was confirmed 5h ago, with two readings taken after it
+    async fn db_after_downtime() -> (Database, DateTime<Utc>) {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        let synced = DateTime::from_timestamp(Utc::now().timestamp() - 5 * 3600, 0).unwrap();
+        for (minutes, celsius) in [(-1, 140.0), (0, 141.0), (1, 142.0), (2, 143.0)] {
+            let at = synced + chrono::Duration::minutes(minutes);
+            db.insert_reading("AA", at, 0, Temperature::from_celsius(celsius), None, None, -60).await.unwrap();
+        }
+        db.set_sync_cursor(SYNC_TO_CLOUD, synced).await.unwrap();
+        (db, synced)
+    }
+
+    #[tokio::test]
+    async fn test_flush_resumes_from_cursor_after_long_downtime() {
+        let (db, synced) = db_after_downtime().await;
+        // A one-hour lookback would miss everything; the stored cursor must win
+        let uploader = CloudUploader::new(Duration::ZERO, 1, 0);
+        let cloud = StubCloud::default();
+
+        // The cursor row itself is re-sent; cloud writes are keyed by timestamp
+        assert_eq!(uploader.flush(&cloud, &db).await.unwrap(), 3);
+        assert_eq!(*cloud.uploaded.lock().unwrap(), [141.0, 142.0, 143.0]);
+        assert_eq!(db.get_sync_cursor(SYNC_TO_CLOUD).await.unwrap(), Some(synced + chrono::Duration::minutes(2)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_queues_readings_for_the_next_one() {
+        let (db, synced) = db_after_downtime().await;
+        let uploader = CloudUploader::new(Duration::ZERO, 1, 0);
+        let cloud = StubCloud { failures: 1, ..StubCloud::default() };
+
+        // The link is down: nothing uploads, but the readings are queued so the cursor may move on
+        assert_eq!(uploader.flush(&cloud, &db).await.unwrap(), 0);
+        assert!(cloud.uploaded.lock().unwrap().is_empty());
+        assert_eq!(db.get_pending_sync(10).await.unwrap().len(), 3);
+        assert_eq!(db.get_sync_cursor(SYNC_TO_CLOUD).await.unwrap(), Some(synced + chrono::Duration::minutes(2)));
+
+        // Once it is back, the queue drains before the newest reading is re-sent
+        assert_eq!(uploader.flush(&cloud, &db).await.unwrap(), 4);
+        assert_eq!(*cloud.uploaded.lock().unwrap(), [141.0, 142.0, 143.0, 143.0]);
+        assert!(db.get_pending_sync(10).await.unwrap().is_empty());
+    }
+}
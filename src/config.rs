@@ -1,10 +1,27 @@
 // src/config.rs
 use anyhow::{Context, Result};
+use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::telemetry::TelemetryMode;
+use crate::temperature::TemperatureUnit;
+
+/// Version of the config.toml layout
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+
+/// `database.retention_days` shipped before layout 2, when Premium ignored it
+const LEGACY_RETENTION_DAYS: u32 = 30;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Layout the file was written for; files from before it was added are layout 1
+    #[serde(default = "legacy_config_version")]
+    pub config_version: u32,
     pub device: DeviceConfig,
     pub filters: FilterConfig,
     pub temperature: TemperatureConfig,
@@ -13,6 +30,26 @@ pub struct Config {
     pub web: Option<WebConfig>,
     pub premium: PremiumConfig,
     pub aws: AwsConfig,
+    pub export_schedule: Option<ExportScheduleConfig>,
+    #[serde(default)]
+    pub instant_read: InstantReadConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default)]
+    pub ingest: IngestConfig,
+    #[serde(default)]
+    pub simulator: SimulatorConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub pit_controller: PitControllerConfig,
+    /// Keys in the config file that no setting reads; logged once logging is up
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +57,62 @@ pub struct DeviceConfig {
     pub scan_duration: u64,
     pub monitor_duration: u64,
     pub reconnect_attempts: u32,
+    /// Give up on a connection attempt after this long and move on to the next device
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Pause after each wake-up command unless the command sets its own
+    #[serde(default)]
+    pub wake_command_delay_ms: u64,
+    /// Wake-up sequences replacing the brand defaults, keyed by MAC (or OUI) or brand name
+    #[serde(default)]
+    pub wake_commands: HashMap<String, Vec<WakeCommand>>,
+    /// Position of the Bluetooth adapter to use when there are several (None = the first)
+    #[serde(default)]
+    pub adapter_index: Option<usize>,
+    /// Adapter to use, matched within its info string (e.g. "hci1"); wins over `adapter_index`
+    #[serde(default)]
+    pub adapter_address: Option<String>,
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+/// One command of a configured wake-up sequence
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakeCommand {
+    /// Sent byte for byte; write line endings as TOML escapes ("status\r\n")
+    pub command: String,
+    /// Pause after this command, overriding `wake_command_delay_ms`
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+}
+
+impl DeviceConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs.max(1))
+    }
+    
+    /// Commands to send a device after connecting, each with the pause that follows it
+    ///
+    /// A MAC entry wins over a brand entry (the `{:?}` brand name, e.g. "ThermoProTP25"),
+    /// and either replaces `defaults`; an empty list sends nothing.
+    pub fn wake_sequence(&self, brand: &str, address: &str, defaults: &[Vec<u8>]) -> Vec<(Vec<u8>, Duration)> {
+        let default_delay = Duration::from_millis(self.wake_command_delay_ms);
+        let configured = self
+            .wake_commands
+            .iter()
+            .find(|(key, _)| mac_matches(key, address))
+            .or_else(|| self.wake_commands.iter().find(|(key, _)| key.eq_ignore_ascii_case(brand)));
+        
+        match configured {
+            Some((_, commands)) => commands
+                .iter()
+                .map(|c| (c.command.as_bytes().to_vec(), c.delay_ms.map_or(default_delay, Duration::from_millis)))
+                .collect(),
+            None => defaults.iter().map(|command| (command.clone(), default_delay)).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,21 +120,253 @@ pub struct FilterConfig {
     pub device_prefixes: Vec<String>,
     pub mac_filters: Vec<String>,
     pub min_rssi: i16,
+    /// Only connect to these addresses (empty = any); full MACs or 3-octet OUI prefixes
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+    /// Never connect to these addresses, even when allowlisted
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+}
+
+impl FilterConfig {
+    /// Apply the blocklist, then the allowlist, to a device address
+    pub fn allows_address(&self, address: &str) -> bool {
+        if self.blocklist.iter().any(|entry| mac_matches(entry, address)) {
+            return false;
+        }
+        self.allowlist.is_empty() || self.allowlist.iter().any(|entry| mac_matches(entry, address))
+    }
+}
+
+/// Match a list entry against an address: `AA:BB:CC:DD:EE:FF` exactly or `AA:BB:CC` as an OUI prefix
+///
+/// Case-insensitive; `-` is accepted as a separator. Malformed entries never match.
+fn mac_matches(entry: &str, address: &str) -> bool {
+    let normalize = |mac: &str| mac.trim().to_ascii_uppercase().replace('-', ":");
+    let entry = normalize(entry);
+    let address = normalize(address);
+    
+    match entry.split(':').count() {
+        6 => address == entry,
+        3 => address.starts_with(&format!("{}:", entry)),
+        _ => false,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemperatureConfig {
+    /// Display unit: "fahrenheit" or "celsius" (storage is always Celsius)
     pub unit: String,
     pub max_internal_temp: f32,
     pub max_ambient_temp: f32,
     pub warning_threshold_percent: f32,
 }
 
+impl TemperatureConfig {
+    /// Unit for the API, WebSocket and exports; unknown values fall back to Fahrenheit
+    pub fn display_unit(&self) -> TemperatureUnit {
+        TemperatureUnit::parse(&self.unit).unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub path: String,
     pub retention_days: u32,
     pub batch_size: usize,
+    /// Minimum seconds between stored readings per device (0 = store every change)
+    #[serde(default)]
+    pub min_store_interval_secs: u64,
+    /// Precision of stored reading timestamps ("micros" or "seconds")
+    #[serde(default)]
+    pub timestamp_precision: TimestampPrecision,
+    /// Vacuum after the startup cleanup removed readings
+    #[serde(default)]
+    pub vacuum_after_cleanup: bool,
+    /// Hours between scheduled vacuums (0 = never); skipped while readings are being written
+    #[serde(default)]
+    pub vacuum_interval_hours: u64,
+    /// Hours between retention cleanups after the one at startup (0 = startup only)
+    #[serde(default = "default_cleanup_interval_hours")]
+    pub cleanup_interval_hours: u64,
+}
+
+fn default_cleanup_interval_hours() -> u64 {
+    6
+}
+
+/// How much of a reading timestamp is kept when it is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampPrecision {
+    #[default]
+    Micros,
+    Seconds,
+}
+
+impl TimestampPrecision {
+    /// Drop sub-unit precision from a timestamp
+    pub fn truncate(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let truncated = match self {
+            Self::Micros => timestamp.with_nanosecond(timestamp.nanosecond() / 1_000 * 1_000),
+            Self::Seconds => timestamp.with_nanosecond(0),
+        };
+        truncated.unwrap_or(timestamp)
+    }
+}
+
+/// Storage of readings from instant-read (handheld) thermometers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstantReadConfig {
+    /// Store instant-read readings at all (they are always shown live)
+    pub enabled: bool,
+    /// Consecutive samples that must agree before one is stored
+    pub stable_samples: usize,
+    /// Maximum spread (°F) between those samples
+    pub stable_tolerance: f32,
+}
+
+impl Default for InstantReadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stable_samples: 5,
+            stable_tolerance: 1.0,
+        }
+    }
+}
+
+/// Cap on how fast one device's readings are stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestConfig {
+    /// Packets stored per device per minute (0 = no limit); excess ones are merged into the latest
+    pub max_readings_per_min: u32,
+    /// Packets that may be stored back to back before the limit applies
+    pub burst: u32,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            max_readings_per_min: 120,
+            burst: 10,
+        }
+    }
+}
+
+/// Simulated probes for demos and testing alert rules without hardware
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulatorConfig {
+    pub enabled: bool,
+    /// Simulated probes, `sim-1` to `sim-N`
+    pub devices: usize,
+    /// Milliseconds between simulated readings
+    pub tick_ms: u64,
+    /// Simulated seconds per real second, to run a cook faster than real time
+    pub speed: f32,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            devices: 1,
+            tick_ms: 1000,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Reminders driven by the device maintenance log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Warn when a probe's last calibration note is older than this many days (0 = off)
+    pub calibration_reminder_days: u32,
+}
+
+/// Alert defaults for new devices
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// Give a device seen for the first time an ambient-protection and a low-battery alert
+    pub bootstrap_defaults: bool,
+    /// Meat usually cooked; its cook profile target is offered for new devices
+    pub default_meat: Option<String>,
+}
+
+/// Opt-in counts of frames the parsers rejected (see `telemetry.rs`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// `off` (default), `local` to only log the counts, or `upload` to also send them via the cloud backend
+    pub mode: TelemetryMode,
+    /// How often the counts are reported
+    pub report_interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { mode: TelemetryMode::Off, report_interval_secs: 3600 }
+    }
+}
+
+/// Fan/damper PID loop holding the pit at a setpoint (needs the `pit-controller` feature)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PitControllerConfig {
+    pub enabled: bool,
+    /// Device whose sensor measures the pit
+    pub device_address: String,
+    /// Sensor of that device measuring the pit, usually its ambient sensor
+    pub sensor_index: usize,
+    /// Pit temperature to hold, in `temperature.unit`
+    pub setpoint: f32,
+    /// Percent output per degree of error
+    pub kp: f32,
+    /// Percent output per degree-second of accumulated error
+    pub ki: f32,
+    /// Percent output per degree/second the pit is falling
+    pub kd: f32,
+    /// Program and arguments run with each new output; `{output}` becomes the percent (empty = API only)
+    pub output_command: Vec<String>,
+}
+
+impl Default for PitControllerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device_address: String::new(),
+            sensor_index: 1,
+            setpoint: 225.0,
+            kp: 4.0,
+            ki: 0.02,
+            kd: 5.0,
+            output_command: Vec::new(),
+        }
+    }
+}
+
+/// Cook analytics tuning
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    /// Carryover rise (°F) by meat, overriding the built-in estimates
+    pub carryover_f: HashMap<String, f32>,
+}
+
+impl AnalyticsConfig {
+    /// Expected carryover (°F) for a meat: the configured value, else the built-in one
+    pub fn carryover_f(&self, meat: &str) -> Option<f32> {
+        let meat = meat.trim().to_lowercase();
+        self.carryover_f
+            .get(&meat)
+            .copied()
+            .or_else(|| crate::analytics::default_carryover_f(&meat))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,11 +381,47 @@ pub struct WebConfig {
     pub enabled: bool,
     pub host: String,
     pub port: u16,
+    /// JSON-lines file recording every API request (timestamp, IP, method, path, status)
+    #[serde(default)]
+    pub access_log_path: Option<String>,
+    /// Bearer token for admin-only endpoints (None = those endpoints refuse every request)
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Key required on `/api/*` and `/ws` (None = open, for local use)
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Expose raw BLE read/write endpoints for protocol work (admin token required)
+    #[serde(default)]
+    pub developer_mode: bool,
+    /// Seconds a device may stay quiet before WebSocket clients get a freshness heartbeat (0 = off)
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u64,
+    /// Requests handled at once; further ones get 503 (WebSocket streams do not count)
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+    /// Minutes of each device's history sent to a WebSocket client on connect (0 = latest readings only)
+    #[serde(default = "default_history_on_connect_mins")]
+    pub history_on_connect_mins: u32,
+}
+
+fn default_heartbeat_interval_secs() -> u64 {
+    15
+}
+
+fn default_max_concurrent_requests() -> usize {
+    256
+}
+
+fn default_history_on_connect_mins() -> u32 {
+    30
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PremiumConfig {
     pub license_key: String,
+    /// When `license_key` was last activated; decides against a different key stored in the database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,44 +431,421 @@ pub struct AwsConfig {
     pub thing_name: String,
     pub table_name: String,
     pub sync_interval_secs: u64,
+    /// How far back the first sync reaches when no cursor is stored yet
+    #[serde(default = "default_initial_lookback_hours")]
+    pub initial_lookback_hours: u64,
+    /// Most readings held for retry while the cloud is unreachable (0 = unbounded)
+    #[serde(default = "default_max_pending_sync")]
+    pub max_pending_sync: usize,
+    /// Hours of cloud history pulled once at startup, for a fresh install (0 = off)
+    #[serde(default)]
+    pub backfill_hours: u64,
+}
+
+fn default_initial_lookback_hours() -> u64 {
+    1
+}
+
+fn legacy_config_version() -> u32 {
+    1
 }
 
+fn default_max_pending_sync() -> usize {
+    100_000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportScheduleConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub format: String,
+    pub path: String,
+    pub keep_days: u32,
+}
+
+/// Environment variables starting with this override config values, e.g. `BBQ_MONITOR__WEB__PORT=9090`
+pub const CONFIG_ENV_PREFIX: &str = "BBQ_MONITOR__";
+
 impl Config {
     pub fn load() -> Result<Self> {
         Self::load_from_path("config.toml")
     }
     
+    /// Load a config file (defaults when there is none), apply environment overrides and validate
     pub fn load_from_path(config_path: &str) -> Result<Self> {
-        if !Path::new(config_path).exists() {
+        let config = Self::load_file(config_path)?.with_env_overrides(utf8_env_vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Check ranges and enumerated values, naming the offending setting and what it allows
+    pub fn validate(&self) -> Result<()> {
+        let device = &self.device;
+        ensure_range("device.scan_duration", device.scan_duration, 1, 3600)?;
+        ensure_range("device.monitor_duration", device.monitor_duration, 1, 7 * 24 * 3600)?;
+        ensure_range("filters.min_rssi", self.filters.min_rssi, -127, 0)?;
+        
+        let temperature = &self.temperature;
+        if TemperatureUnit::parse(&temperature.unit).is_none() {
+            anyhow::bail!("temperature.unit must be \"fahrenheit\" or \"celsius\" (got {:?})", temperature.unit);
+        }
+        ensure_range("temperature.max_internal_temp", temperature.max_internal_temp, 1.0, 2000.0)?;
+        ensure_range("temperature.max_ambient_temp", temperature.max_ambient_temp, 1.0, 2000.0)?;
+        ensure_range("temperature.warning_threshold_percent", temperature.warning_threshold_percent, 1.0, 100.0)?;
+        
+        ensure_not_empty("database.path", &self.database.path)?;
+        ensure_range("database.batch_size", self.database.batch_size, 1, 100_000)?;
+        ensure_range("database.cleanup_interval_hours", self.database.cleanup_interval_hours, 0, 30 * 24)?;
+        
+        if !LOG_LEVELS.contains(&self.logging.level.trim().to_lowercase().as_str()) {
+            anyhow::bail!("logging.level must be one of {} (got {:?})", LOG_LEVELS.join(", "), self.logging.level);
+        }
+        if self.logging.file_enabled {
+            ensure_not_empty("logging.file_path", &self.logging.file_path)?;
+        }
+        
+        if let Some(web) = self.web.as_ref().filter(|web| web.enabled) {
+            ensure_range("web.port", web.port, 1, u16::MAX)?;
+            ensure_not_empty("web.host", &web.host)?;
+            ensure_range("web.max_concurrent_requests", web.max_concurrent_requests, 1, 65536)?;
+            ensure_range("web.history_on_connect_mins", web.history_on_connect_mins, 0, 24 * 60)?;
+        }
+        if self.telemetry.mode != TelemetryMode::Off {
+            ensure_range("telemetry.report_interval_secs", self.telemetry.report_interval_secs, 60, 7 * 24 * 3600)?;
+        }
+        if self.pit_controller.enabled {
+            let pit = &self.pit_controller;
+            ensure_not_empty("pit_controller.device_address", &pit.device_address)?;
+            ensure_range("pit_controller.setpoint", pit.setpoint, 1.0, 2000.0)?;
+            for (field, gain) in [("pit_controller.kp", pit.kp), ("pit_controller.ki", pit.ki), ("pit_controller.kd", pit.kd)] {
+                ensure_range(field, gain, 0.0, 1000.0)?;
+            }
+        }
+        if self.aws.enabled {
+            ensure_not_empty("aws.region", &self.aws.region)?;
+            ensure_range("aws.sync_interval_secs", self.aws.sync_interval_secs, 1, 24 * 3600)?;
+        }
+        if let Some(export) = self.export_schedule.as_ref().filter(|export| export.enabled) {
+            crate::export::ExportFormat::parse(&export.format).context("Invalid export_schedule.format")?;
+            if export.interval_secs == 0 {
+                anyhow::bail!("export_schedule.interval_secs must be at least 1");
+            }
+            ensure_not_empty("export_schedule.path", &export.path)?;
+        }
+        ensure_range("instant_read.stable_samples", self.instant_read.stable_samples, 1, 100)?;
+        if self.simulator.enabled {
+            ensure_range("simulator.devices", self.simulator.devices, 1, 64)?;
+            ensure_range("simulator.tick_ms", self.simulator.tick_ms, 1, 60_000)?;
+            ensure_range("simulator.speed", self.simulator.speed, 0.01, 1000.0)?;
+        }
+        Ok(())
+    }
+    
+    /// Apply `BBQ_MONITOR__<SECTION>__<FIELD>` variables over this config
+    ///
+    /// Values are read as TOML (`9090`, `true`, `["cA00"]`); anything else is a
+    /// string, and a string setting always stays one, even an unset optional one. The
+    /// result goes through deserialization again, so a wrong type, a partial
+    /// optional section or a variable naming no setting is an error.
+    pub fn with_env_overrides(self, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let overrides: Vec<(String, Vec<String>, String)> = vars
+            .into_iter()
+            .filter_map(|(name, raw)| {
+                let path = name.strip_prefix(CONFIG_ENV_PREFIX)?.split("__").map(str::to_lowercase).collect();
+                Some((name, path, raw))
+            })
+            .collect();
+        if overrides.is_empty() {
+            return Ok(self);
+        }
+        
+        let mut root = toml::Value::try_from(&self).context("Failed to serialize config")?;
+        let mut guessed = Vec::new();
+        for (name, path, raw) in &overrides {
+            if set_env_value(&mut root, path, raw, false).with_context(|| format!("Invalid {}", name))? {
+                guessed.push((path, raw));
+            }
+        }
+        // An unset `Option<String>` (e.g. web.api_key) is missing from the tree, so its type was
+        // guessed from the value; when `123456` is rejected there, it was meant as a string
+        let mut config: Config = loop {
+            let error = match root.clone().try_into() {
+                Ok(config) => break config,
+                Err(e) => e,
+            };
+            let message = error.to_string();
+            let Some(index) = guessed.iter().position(|(path, _)| message.contains(&format!("`{}`", path.join(".")))) else {
+                return Err(error).context("Invalid configuration from environment");
+            };
+            let (path, raw) = guessed.remove(index);
+            set_env_value(&mut root, path, raw, true)?;
+        };
+        
+        // Unknown keys are dropped by deserialization; catch typos instead of ignoring them
+        let applied = toml::Value::try_from(&config).context("Failed to serialize config")?;
+        for (name, path, _) in &overrides {
+            if path.iter().try_fold(&applied, |value, key| value.get(key)).is_none() {
+                anyhow::bail!("{} does not name a config setting", name);
+            }
+        }
+        config.unknown_keys = self.unknown_keys;
+        Ok(config)
+    }
+    
+    /// Load a config file, recovering from `<path>.bak` if the file is empty or corrupt
+    fn load_file(config_path: &str) -> Result<Self> {
+        let path = Path::new(config_path);
+        if !path.exists() {
             return Ok(Self::default());
         }
 
-        let contents = std::fs::read_to_string(config_path)
+        let error = match Self::read(path) {
+            Ok(config) => return Ok(config),
+            Err(e) => e,
+        };
+        
+        let backup = backup_path(path);
+        match Self::read(&backup) {
+            Ok(config) => {
+                warn!(
+                    "Config file {} is unreadable ({:#}); recovered previous version from {}",
+                    path.display(),
+                    error,
+                    backup.display()
+                );
+                config.save_atomic(path)?;
+                Ok(config)
+            }
+            Err(_) => Err(error),
+        }
+    }
+    
+    fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
             .context("Failed to read config file")?;
+        if contents.trim().is_empty() {
+            anyhow::bail!("Config file {} is empty", path.display());
+        }
         
-        let config: Config = toml::from_str(&contents)
+        let mut config: Config = toml::from_str(&contents)
             .context("Failed to parse config file")?;
+        config.migrate(path);
+        
+        // Deserialization drops keys it does not know; compare against what it kept
+        let raw: toml::Value = toml::from_str(&contents).context("Failed to parse config file")?;
+        let known = toml::Value::try_from(&config).context("Failed to serialize config")?;
+        collect_unknown_keys(&raw, &known, "", &mut config.unknown_keys);
         
         Ok(config)
     }
+    
+    /// Bring a config written for an older layout up to date, in memory only
+    ///
+    /// Layout 1 shipped `retention_days = 30`, which Premium ignored. Honoring
+    /// it now would delete a Premium install's old history, so that value is
+    /// read as 0 unless the file says `config_version = 2`.
+    fn migrate(&mut self, path: &Path) {
+        if self.config_version >= CONFIG_SCHEMA_VERSION {
+            return;
+        }
+        if self.database.retention_days == LEGACY_RETENTION_DAYS {
+            warn!(
+                "{} predates config_version {}: reading its retention_days = {} (the old default) as 0. \
+                 Add config_version = {} to keep only {} days",
+                path.display(),
+                CONFIG_SCHEMA_VERSION,
+                LEGACY_RETENTION_DAYS,
+                CONFIG_SCHEMA_VERSION,
+                LEGACY_RETENTION_DAYS
+            );
+            self.database.retention_days = 0;
+        }
+        self.config_version = CONFIG_SCHEMA_VERSION;
+    }
+    
+    /// Store a newly activated license key in the config file at `path`
+    ///
+    /// Reads the file itself rather than the loaded config, so environment
+    /// overrides are not written back into it.
+    pub fn store_license_key(path: &Path, key: &str) -> Result<()> {
+        let mut config = Self::read(path)?;
+        config.premium.license_key = key.to_string();
+        config.premium.activated_at = Some(Utc::now());
+        config.save_atomic(path)
+    }
+    
+    /// Write the config so a crash never leaves a truncated file behind
+    ///
+    /// Writes and fsyncs a temp file in the same directory, copies the current
+    /// (valid) file to `<path>.bak`, then renames the temp file over `path`.
+    /// All config writes must go through this.
+    pub fn save_atomic(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.save_atomic_with(path.as_ref(), |_| Ok(()))
+    }
+    
+    fn save_atomic_with(&self, path: &Path, fail_point: impl Fn(SaveStep) -> Result<()>) -> Result<()> {
+        let contents = toml::to_string_pretty(self).context("Failed to serialize config")?;
+        
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let file_name = path
+            .file_name()
+            .context("Config path has no file name")?
+            .to_string_lossy();
+        let temp_path = dir.join(format!(".{}.tmp", file_name));
+        
+        {
+            let mut file = std::fs::File::create(&temp_path)
+                .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+            file.write_all(contents.as_bytes())
+                .context("Failed to write temporary config")?;
+            file.sync_all().context("Failed to sync temporary config")?;
+        }
+        fail_point(SaveStep::TempWritten)?;
+        
+        // Only back up a file worth going back to
+        if Self::read(path).is_ok() {
+            std::fs::copy(path, backup_path(path)).context("Failed to back up config")?;
+        }
+        fail_point(SaveStep::BackedUp)?;
+        
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        // Persist the rename itself; not supported on every platform
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+        
+        Ok(())
+    }
+}
+
+/// Environment variables as strings; non-UTF-8 ones are skipped, with a warning for ours
+fn utf8_env_vars() -> Vec<(String, String)> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| match (name.into_string(), value.into_string()) {
+            (Ok(name), Ok(value)) => Some((name, value)),
+            (name, _) => {
+                let name = name.unwrap_or_else(|name| name.to_string_lossy().into_owned());
+                if name.starts_with(CONFIG_ENV_PREFIX) {
+                    warn!("⚠️ Ignoring {}: not valid UTF-8", name);
+                }
+                None
+            }
+        })
+        .collect()
+}
+
+/// Set `path` in a config tree from an environment value, creating tables on the way
+///
+/// Returns whether the value's type was guessed, i.e. the key was unset and the
+/// value did not read as a string; `as_string` forces a string instead.
+fn set_env_value(root: &mut toml::Value, path: &[String], raw: &str, as_string: bool) -> Result<bool> {
+    let Some((key, parents)) = path.split_last().filter(|(key, _)| !key.is_empty()) else {
+        anyhow::bail!("no setting named");
+    };
+    let mut table = root;
+    for parent in parents {
+        table = table
+            .as_table_mut()
+            .context("not a section")?
+            .entry(parent.as_str())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    }
+    let table = table.as_table_mut().context("not a section")?;
+    
+    let existing = table.get(key);
+    let value = match existing {
+        _ if as_string => toml::Value::String(raw.to_string()),
+        Some(toml::Value::String(_)) => toml::Value::String(raw.to_string()),
+        _ => toml::from_str::<toml::Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(raw.to_string())),
+    };
+    let guessed = existing.is_none() && !value.is_str();
+    table.insert(key.clone(), value);
+    Ok(guessed)
+}
+
+/// Points in `save_atomic` where tests inject a crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaveStep {
+    TempWritten,
+    BackedUp,
+}
+
+/// Values `logging.level` accepts
+const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+fn ensure_range<T: PartialOrd + Copy + std::fmt::Display>(field: &str, value: T, min: T, max: T) -> Result<()> {
+    if !(min..=max).contains(&value) {
+        anyhow::bail!("{} must be between {} and {} (got {})", field, min, max, value);
+    }
+    Ok(())
+}
+
+fn ensure_not_empty(field: &str, value: &str) -> Result<()> {
+    if value.trim().is_empty() {
+        anyhow::bail!("{} must not be empty", field);
+    }
+    Ok(())
+}
+
+/// Dotted paths of keys in `raw` that `known` lacks
+fn collect_unknown_keys(raw: &toml::Value, known: &toml::Value, prefix: &str, found: &mut Vec<String>) {
+    match (raw, known) {
+        (toml::Value::Table(raw), toml::Value::Table(known)) => {
+            for (key, value) in raw {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                match known.get(key) {
+                    Some(known) => collect_unknown_keys(value, known, &path, found),
+                    None => found.push(path),
+                }
+            }
+        }
+        (toml::Value::Array(raw), toml::Value::Array(known)) => {
+            for (i, (raw, known)) in raw.iter().zip(known).enumerate() {
+                collect_unknown_keys(raw, known, &format!("{}[{}]", prefix, i), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_SCHEMA_VERSION,
             device: DeviceConfig {
                 scan_duration: 5,
                 monitor_duration: 300,
                 reconnect_attempts: 3,
+                connect_timeout_secs: default_connect_timeout_secs(),
+                wake_command_delay_ms: 0,
+                wake_commands: HashMap::new(),
+                adapter_index: None,
+                adapter_address: None,
             },
             filters: FilterConfig {
                 device_prefixes: vec![
                     "cA00".to_string(),
                     "cA02".to_string(),
                     "Y0C".to_string(),
+                    "TP25".to_string(),
                 ],
                 mac_filters: vec![],
                 min_rssi: -80,
+                allowlist: vec![],
+                blocklist: vec![],
             },
             temperature: TemperatureConfig {
                 unit: "fahrenheit".to_string(),
@@ -117,8 +855,13 @@ impl Default for Config {
             },
             database: DatabaseConfig {
                 path: "bbq_monitor.db".to_string(),
-                retention_days: 30,
+                retention_days: 0,
                 batch_size: 100,
+                min_store_interval_secs: 0,
+                timestamp_precision: TimestampPrecision::default(),
+                vacuum_after_cleanup: false,
+                vacuum_interval_hours: 0,
+                cleanup_interval_hours: default_cleanup_interval_hours(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -129,9 +872,17 @@ impl Default for Config {
                 enabled: true,
                 host: "127.0.0.1".to_string(),
                 port: 8080,
+                access_log_path: None,
+                admin_token: None,
+                api_key: None,
+                developer_mode: false,
+                heartbeat_interval_secs: default_heartbeat_interval_secs(),
+                max_concurrent_requests: default_max_concurrent_requests(),
+                history_on_connect_mins: default_history_on_connect_mins(),
             }),
             premium: PremiumConfig {
                 license_key: String::new(),
+                activated_at: None,
             },
             aws: AwsConfig {
                 enabled: false,
@@ -139,7 +890,346 @@ impl Default for Config {
                 thing_name: String::new(),
                 table_name: "bbq-monitor-readings".to_string(),
                 sync_interval_secs: 300,
+                initial_lookback_hours: default_initial_lookback_hours(),
+                max_pending_sync: default_max_pending_sync(),
+                backfill_hours: 0,
             },
+            export_schedule: None,
+            instant_read: InstantReadConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            ingest: IngestConfig::default(),
+            simulator: SimulatorConfig::default(),
+            alerts: AlertsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            pit_controller: PitControllerConfig::default(),
+            unknown_keys: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_key(key: &str) -> Config {
+        let mut config = Config::default();
+        config.premium.license_key = key.to_string();
+        config
+    }
+
+    fn load(path: &Path) -> Config {
+        Config::load_from_path(path.to_str().unwrap()).unwrap()
+    }
+
+    fn filter_lists(allowlist: &[&str], blocklist: &[&str]) -> FilterConfig {
+        FilterConfig {
+            allowlist: allowlist.iter().map(|s| s.to_string()).collect(),
+            blocklist: blocklist.iter().map(|s| s.to_string()).collect(),
+            ..Config::default().filters
+        }
+    }
+
+    #[test]
+    fn test_empty_lists_allow_everything() {
+        assert!(filter_lists(&[], &[]).allows_address("AA:BB:CC:DD:EE:FF"));
+    }
+
+    #[test]
+    fn test_allowlist_exact_and_oui_matching() {
+        let filters = filter_lists(&["aa:bb:cc:dd:ee:ff", "40-51-6C"], &[]);
+        assert!(filters.allows_address("AA:BB:CC:DD:EE:FF"));
+        assert!(filters.allows_address("40:51:6C:01:02:03"));
+        assert!(!filters.allows_address("AA:BB:CC:DD:EE:00"));
+        // An OUI only matches whole octets
+        assert!(!filters.allows_address("40:51:6C0:01:02"));
+        assert!(!filters.allows_address("11:40:51:6C:01:02"));
+    }
+
+    #[test]
+    fn test_blocklist_takes_precedence() {
+        let filters = filter_lists(&["40:51:6C"], &["40:51:6C:01:02:03"]);
+        assert!(!filters.allows_address("40:51:6C:01:02:03"));
+        assert!(filters.allows_address("40:51:6C:01:02:04"));
+
+        let filters = filter_lists(&["AA:BB:CC:DD:EE:FF"], &["AA:BB:CC"]);
+        assert!(!filters.allows_address("AA:BB:CC:DD:EE:FF"));
+
+        let filters = filter_lists(&[], &["AA:BB:CC"]);
+        assert!(!filters.allows_address("aa:bb:cc:00:00:01"));
+        assert!(filters.allows_address("AA:BB:CD:00:00:01"));
+    }
+
+    #[test]
+    fn test_malformed_entries_never_match() {
+        let filters = filter_lists(&["AA:BB"], &["AA:BB:CC:DD"]);
+        assert!(!filters.allows_address("AA:BB:CC:DD:EE:FF"));
+        assert!(filter_lists(&[], &["AA:BB:CC:DD"]).allows_address("AA:BB:CC:DD:EE:FF"));
+    }
+
+    #[test]
+    fn test_lists_default_to_empty_in_old_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut old = toml::to_string(&Config::default()).unwrap();
+        old = old.replace("allowlist = []\n", "").replace("blocklist = []\n", "");
+        assert!(!old.contains("allowlist"));
+        std::fs::write(&path, old).unwrap();
+        assert!(load(&path).filters.allowlist.is_empty());
+    }
+
+    #[test]
+    fn test_wake_sequence_overrides() {
+        let defaults = [b"status\r\n".to_vec()];
+        let toml = r#"
+            scan_duration = 5
+            monitor_duration = 300
+            reconnect_attempts = 3
+            wake_command_delay_ms = 50
+
+            [wake_commands]
+            "AA:BB:CC:DD:EE:FF" = []
+            MeatStickV = [{ command = "temp\r\n", delay_ms = 200 }, { command = "status\r\n" }]
+        "#;
+        let device: DeviceConfig = toml::from_str(toml).unwrap();
+
+        assert!(device.wake_sequence("MeatStickV", "aa:bb:cc:dd:ee:ff", &defaults).is_empty(), "MAC wins");
+        assert_eq!(
+            device.wake_sequence("MeatStickV", "11:22:33:44:55:66", &defaults),
+            [
+                (b"temp\r\n".to_vec(), Duration::from_millis(200)),
+                (b"status\r\n".to_vec(), Duration::from_millis(50)),
+            ]
+        );
+        assert_eq!(
+            device.wake_sequence("ThermoProTP25", "11:22:33:44:55:66", &defaults),
+            [(b"status\r\n".to_vec(), Duration::from_millis(50))]
+        );
+        assert!(Config::default().device.wake_sequence("Unknown", "AA", &[]).is_empty());
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_env_only_config_without_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("config.toml");
+        let vars = env(&[
+            ("BBQ_MONITOR__DEVICE__SCAN_DURATION", "10"),
+            ("BBQ_MONITOR__DEVICE__RECONNECT_ATTEMPTS", "5"),
+            ("BBQ_MONITOR__FILTERS__DEVICE_PREFIXES", r#"["cA00", "TP25"]"#),
+            ("BBQ_MONITOR__FILTERS__MIN_RSSI", "-90"),
+            ("BBQ_MONITOR__TEMPERATURE__UNIT", "celsius"),
+            ("BBQ_MONITOR__DATABASE__PATH", "/data/bbq.db"),
+            ("BBQ_MONITOR__LOGGING__LEVEL", "debug"),
+            ("BBQ_MONITOR__LOGGING__FILE_ENABLED", "false"),
+            ("BBQ_MONITOR__WEB__HOST", "0.0.0.0"),
+            ("BBQ_MONITOR__WEB__PORT", "9090"),
+            ("BBQ_MONITOR__WEB__API_KEY", "k3y"),
+            ("BBQ_MONITOR__WEB__ADMIN_TOKEN", r#""12345""#),
+            ("BBQ_MONITOR__PREMIUM__LICENSE_KEY", "12345"),
+            ("BBQ_MONITOR__AWS__ENABLED", "true"),
+            ("BBQ_MONITOR__AWS__REGION", "eu-west-1"),
+            ("BBQ_MONITOR__EXPORT_SCHEDULE__ENABLED", "true"),
+            ("BBQ_MONITOR__EXPORT_SCHEDULE__INTERVAL_SECS", "3600"),
+            ("BBQ_MONITOR__EXPORT_SCHEDULE__FORMAT", "csv"),
+            ("BBQ_MONITOR__EXPORT_SCHEDULE__PATH", "/data/exports"),
+            ("BBQ_MONITOR__EXPORT_SCHEDULE__KEEP_DAYS", "7"),
+            ("BBQ_MONITOR__INGEST__MAX_READINGS_PER_MIN", "30"),
+            ("HOME", "/root"),
+        ]);
+
+        let config = Config::load_file(missing.to_str().unwrap()).unwrap().with_env_overrides(vars).unwrap();
+        assert!(!missing.exists(), "nothing written");
+        assert_eq!(config.device.scan_duration, 10);
+        assert_eq!(config.device.reconnect_attempts, 5);
+        assert_eq!(config.device.monitor_duration, 300, "untouched values keep their default");
+        assert_eq!(config.filters.device_prefixes, ["cA00", "TP25"]);
+        assert_eq!(config.filters.min_rssi, -90);
+        assert_eq!(config.temperature.unit, "celsius");
+        assert_eq!(config.database.path, "/data/bbq.db");
+        assert_eq!(config.logging.level, "debug");
+        assert!(!config.logging.file_enabled);
+        let web = config.web.as_ref().unwrap();
+        assert_eq!((web.host.as_str(), web.port), ("0.0.0.0", 9090));
+        assert_eq!(web.api_key.as_deref(), Some("k3y"));
+        assert_eq!(web.admin_token.as_deref(), Some("12345"));
+        assert_eq!(config.premium.license_key, "12345", "strings stay strings");
+        assert!(config.aws.enabled);
+        assert_eq!(config.aws.region, "eu-west-1");
+        let export = config.export_schedule.as_ref().unwrap();
+        assert_eq!((export.interval_secs, export.keep_days), (3600, 7));
+        assert_eq!(config.ingest.max_readings_per_min, 30);
+    }
+
+    #[test]
+    fn test_invalid_env_overrides_are_errors() {
+        let apply = |vars: &[(&str, &str)]| Config::default().with_env_overrides(env(vars));
+        assert!(apply(&[("BBQ_MONITOR__WEB__PORT", "eighty")]).is_err());
+        assert!(apply(&[("BBQ_MONITOR__WEB__PROT", "80")]).unwrap_err().to_string().contains("WEB__PROT"));
+        assert!(apply(&[("BBQ_MONITOR__DEVICE", "1")]).is_err());
+        assert!(apply(&[("BBQ_MONITOR__EXPORT_SCHEDULE__ENABLED", "true")]).is_err(), "partial section");
+        assert_eq!(apply(&[("OTHER__WEB__PORT", "1")]).unwrap().web.unwrap().port, 8080);
+    }
+
+    #[test]
+    fn test_unset_string_settings_stay_strings() {
+        let config = Config::default()
+            .with_env_overrides(env(&[
+                ("BBQ_MONITOR__WEB__API_KEY", "123456"),
+                ("BBQ_MONITOR__WEB__ADMIN_TOKEN", "true"),
+                ("BBQ_MONITOR__WEB__PORT", "9090"),
+            ]))
+            .unwrap();
+        let web = config.web.unwrap();
+        assert_eq!(web.api_key.as_deref(), Some("123456"));
+        assert_eq!(web.admin_token.as_deref(), Some("true"));
+        assert_eq!(web.port, 9090);
+    }
+
+    #[test]
+    fn test_validate_names_the_offending_setting() {
+        assert!(Config::default().validate().is_ok());
+        let error = |change: fn(&mut Config)| {
+            let mut config = Config::default();
+            change(&mut config);
+            config.validate().unwrap_err().to_string()
+        };
+
+        assert_eq!(error(|c| c.device.scan_duration = 0), "device.scan_duration must be between 1 and 3600 (got 0)");
+        assert_eq!(error(|c| c.temperature.unit = "kelvin".into()), r#"temperature.unit must be "fahrenheit" or "celsius" (got "kelvin")"#);
+        assert_eq!(error(|c| c.filters.min_rssi = 50), "filters.min_rssi must be between -127 and 0 (got 50)");
+        assert_eq!(error(|c| c.database.path = " ".into()), "database.path must not be empty");
+        assert_eq!(error(|c| c.web.as_mut().unwrap().port = 0), "web.port must be between 1 and 65535 (got 0)");
+        assert_eq!(
+            error(|c| c.logging.level = "verbose".into()),
+            r#"logging.level must be one of trace, debug, info, warn, error (got "verbose")"#
+        );
+        assert_eq!(
+            error(|c| c.temperature.warning_threshold_percent = f32::NAN),
+            "temperature.warning_threshold_percent must be between 1 and 100 (got NaN)"
+        );
+
+        // Disabled sections are not checked
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().enabled = false;
+        config.web.as_mut().unwrap().port = 0;
+        config.simulator.speed = 0.0;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_malformed_config_files_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let error = |from: &str, to: &str| {
+            let contents = toml::to_string(&Config::default()).unwrap();
+            assert!(contents.contains(from), "{}", from);
+            std::fs::write(&path, contents.replace(from, to)).unwrap();
+            format!("{:#}", Config::load_from_path(path.to_str().unwrap()).unwrap_err())
+        };
+
+        assert!(error("scan_duration = 5", "scan_duration = -5").contains("Failed to parse config file"));
+        assert!(error("monitor_duration = 300", "monitor_duration = 0").contains("device.monitor_duration must be between 1"));
+        assert!(error(r#"unit = "fahrenheit""#, r#"unit = "kelvin""#).contains("temperature.unit"));
+        assert!(error("port = 8080", "port = 0").contains("web.port"));
+        assert!(error(r#"path = "bbq_monitor.db""#, r#"path = """#).contains("database.path must not be empty"));
+    }
+
+    #[test]
+    fn test_legacy_retention_default_is_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let retention = |version: &str, days: u32| {
+            let contents = toml::to_string(&Config::default())
+                .unwrap()
+                .replace("config_version = 2\n", version)
+                .replace("retention_days = 0", &format!("retention_days = {}", days));
+            std::fs::write(&path, contents).unwrap();
+            let config = load(&path);
+            assert_eq!(config.config_version, CONFIG_SCHEMA_VERSION);
+            config.database.retention_days
+        };
+
+        assert_eq!(retention("", 30), 0, "the old shipped default no longer deletes Premium history");
+        assert_eq!(retention("", 14), 14, "a value the user chose is kept");
+        assert_eq!(retention("config_version = 2\n", 30), 30, "opted in under the new layout");
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let contents = toml::to_string(&Config::default()).unwrap()
+            .replace("scan_duration = 5", "scan_duration = 5\nscan_durration = 10")
+            .replace("[database]", "colour = \"red\"\n\n[database]");
+        std::fs::write(&path, contents).unwrap();
+
+        let config = load(&path);
+        assert_eq!(config.unknown_keys, ["device.scan_durration", "temperature.colour"]);
+        assert!(Config::default().with_env_overrides(env(&[])).unwrap().unknown_keys.is_empty());
+        let overridden = config.with_env_overrides(env(&[("BBQ_MONITOR__WEB__PORT", "9090")])).unwrap();
+        assert_eq!(overridden.unknown_keys.len(), 2, "kept through environment overrides");
+    }
+
+    #[test]
+    fn test_save_atomic_roundtrip_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        config_with_key("first").save_atomic(&path).unwrap();
+        assert!(!backup_path(&path).exists(), "nothing to back up on first save");
+
+        config_with_key("second").save_atomic(&path).unwrap();
+        assert_eq!(load(&path).premium.license_key, "second");
+        assert_eq!(load(&backup_path(&path)).premium.license_key, "first");
+    }
+
+    #[test]
+    fn test_crash_before_rename_keeps_old_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        config_with_key("licensed").save_atomic(&path).unwrap();
+
+        for step in [SaveStep::TempWritten, SaveStep::BackedUp] {
+            let crash = |at: SaveStep| {
+                if at == step {
+                    anyhow::bail!("simulated crash at {:?}", at)
+                }
+                Ok(())
+            };
+            assert!(config_with_key("new").save_atomic_with(&path, crash).is_err());
+            assert_eq!(load(&path).premium.license_key, "licensed", "crash at {:?}", step);
+        }
+
+        // The next save after a crash replaces the leftover temp file
+        config_with_key("new").save_atomic(&path).unwrap();
+        assert_eq!(load(&path).premium.license_key, "new");
+    }
+
+    #[test]
+    fn test_recovers_truncated_config_from_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        config_with_key("old").save_atomic(&path).unwrap();
+        config_with_key("current").save_atomic(&path).unwrap();
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(load(&path).premium.license_key, "old");
+        // Recovery rewrites the main file
+        assert_eq!(Config::read(&path).unwrap().premium.license_key, "old");
+
+        std::fs::write(&path, "[device]\nscan_duration = ").unwrap();
+        assert_eq!(load(&path).premium.license_key, "old");
+    }
+
+    #[test]
+    fn test_corrupt_config_without_backup_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "").unwrap();
+        assert!(Config::load_from_path(path.to_str().unwrap()).is_err());
+    }
+}
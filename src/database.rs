@@ -2,10 +2,45 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-use tracing::info;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::{debug, error, info, warn};
+
+use crate::alerts::{AlertKind, AlertKindName, AlertRule};
+use crate::food_safety;
+use crate::analytics::{self, FuelEstimate, SmokerProfile};
+use crate::device_capabilities::default_primary_sensor;
+use crate::config::TimestampPrecision;
+use crate::clock::{reconcile_duration, MonotonicAnchor};
+use crate::maintenance::{DeviceNote, NoteCategory};
+use crate::export::ExportFormat;
+use crate::license_store::LicenseManager;
+use crate::migrations::{run_migrations, schema_version, MIGRATIONS};
+use crate::protocol::{probe_label, DeviceInfo, PredictionStatus, ProbeColor, ProbeIdentity, ReadingMode};
+use crate::temperature::{Temperature, TemperatureUnit};
+
+pub use crate::migrations::DB_SCHEMA_VERSION;
+
+/// Streamed exports hand over text in pieces of about this many bytes
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+
+/// SQL condition: `table.timestamp` falls inside a cook session of the same device (open sessions run until now)
+fn in_cook_session(table: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM cook_sessions s \
+         WHERE s.device_address = {table}.device_address AND {table}.timestamp >= s.started_at \
+         AND (s.ended_at IS NULL OR {table}.timestamp <= s.ended_at))"
+    )
+}
 
 pub struct Database {
     pool: SqlitePool,
+    timestamp_precision: TimestampPrecision,
+    /// When a reading was last inserted, so vacuum can stay out of the way of live writes
+    last_write: Mutex<Option<Instant>>,
 }
 
 impl Database {
@@ -18,74 +53,41 @@ impl Database {
             .await
             .context("Failed to connect to database")?;
         
-        let db = Self { pool };
+        let db = Self {
+            pool,
+            timestamp_precision: TimestampPrecision::default(),
+            last_write: Mutex::new(None),
+        };
         db.initialize().await?;
         
         info!("Database initialized at {}", database_path);
         Ok(db)
     }
     
+    /// Store reading timestamps at the given precision (truncated before insert)
+    pub fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+    
     async fn initialize(&self) -> Result<()> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS devices (
-                device_address TEXT PRIMARY KEY,
-                device_name TEXT NOT NULL,
-                brand TEXT NOT NULL,
-                model TEXT NOT NULL,
-                sensor_count INTEGER NOT NULL,
-                first_seen DATETIME NOT NULL,
-                last_seen DATETIME NOT NULL
-            )
-            "#
-        )
-        .execute(&self.pool)
-        .await
-        .context("Failed to create devices table")?;
-        
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS readings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                device_address TEXT NOT NULL,
-                timestamp DATETIME NOT NULL,
-                sensor_index INTEGER NOT NULL,
-                temperature REAL NOT NULL,
-                ambient_temp REAL,
-                battery_level INTEGER,
-                signal_strength INTEGER NOT NULL,
-                FOREIGN KEY (device_address) REFERENCES devices(device_address)
-            )
-            "#
-        )
-        .execute(&self.pool)
-        .await
-        .context("Failed to create readings table")?;
-        
-        // Create index for faster queries
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_readings_timestamp 
-            ON readings(timestamp DESC)
-            "#
-        )
-        .execute(&self.pool)
-        .await
-        .context("Failed to create timestamp index")?;
-        
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_readings_device 
-            ON readings(device_address, timestamp DESC)
-            "#
-        )
-        .execute(&self.pool)
-        .await
-        .context("Failed to create device index")?;
+        // Only takes effect on a new, empty file; `vacuum` converts older databases
+        sqlx::query("PRAGMA auto_vacuum = INCREMENTAL")
+            .execute(&self.pool)
+            .await
+            .context("Failed to enable auto_vacuum")?;
         
-        Ok(())
+        run_migrations(&self.pool, MIGRATIONS).await
+    }
+    
+    /// Newest migration applied to this database
+    pub async fn schema_version(&self) -> Result<u32> {
+        schema_version(&self.pool).await
     }
     
+    /// Insert a device or refresh its name, model, sensor count and last_seen
+    ///
+    /// Returns true when the device was seen for the first time.
     pub async fn upsert_device(
         &self,
         device_address: &str,
@@ -93,10 +95,12 @@ impl Database {
         brand: &str,
         model: &str,
         sensor_count: usize,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         let now = Utc::now();
         
-        sqlx::query(
+        // An update moves last_seen past the stored first_seen; only a new row has them equal
+        let sensor_count = sensor_count as i64;
+        let is_new = sqlx::query_scalar!(
             r#"
             INSERT INTO devices (device_address, device_name, brand, model, sensor_count, first_seen, last_seen)
             VALUES (?, ?, ?, ?, ?, ?, ?)
@@ -105,18 +109,148 @@ impl Database {
                 model = excluded.model,
                 sensor_count = excluded.sensor_count,
                 last_seen = excluded.last_seen
+            RETURNING first_seen = last_seen AS "is_new!: bool"
+            "#,
+            device_address,
+            device_name,
+            brand,
+            model,
+            sensor_count,
+            now,
+            now
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to upsert device")?;
+        
+        Ok(is_new)
+    }
+    
+    /// Record a Combustion probe, returning the address its readings are stored
+    /// under and whether the probe was seen for the first time
+    ///
+    /// Probes rotate their MAC address, so when the serial number is known the
+    /// row already holding that serial is updated (and its address kept)
+    /// rather than adding a new device for every new MAC.
+    pub async fn upsert_device_with_identity(
+        &self,
+        device_address: &str,
+        device_name: &str,
+        brand: &str,
+        model: &str,
+        sensor_count: usize,
+        identity: &ProbeIdentity,
+    ) -> Result<(String, bool)> {
+        let known_address = match &identity.serial_number {
+            Some(serial) => sqlx::query_scalar!(
+                r#"SELECT device_address AS "device_address!" FROM devices WHERE serial_number = ?"#,
+                serial
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to look up device by serial number")?,
+            None => None,
+        };
+        
+        let (address, is_new) = match known_address {
+            Some(address) => {
+                if address != device_address {
+                    info!("Probe {} moved from {} to {}", identity.label(), address, device_address);
+                }
+                let (sensor_count, now) = (sensor_count as i64, Utc::now());
+                sqlx::query!(
+                    r#"
+                    UPDATE devices SET device_name = ?, model = ?, sensor_count = ?, last_seen = ?
+                    WHERE device_address = ?
+                    "#,
+                    device_name,
+                    model,
+                    sensor_count,
+                    now,
+                    address
+                )
+                .execute(&self.pool)
+                .await
+                .context("Failed to update device")?;
+                (address, false)
+            }
+            None => {
+                let is_new = self.upsert_device(device_address, device_name, brand, model, sensor_count).await?;
+                (device_address.to_string(), is_new)
+            }
+        };
+        
+        let (probe_id, probe_color) = (identity.probe_id as i64, identity.color.as_str());
+        sqlx::query!(
+            r#"
+            UPDATE devices SET
+                serial_number = COALESCE(?, serial_number),
+                probe_id = ?,
+                probe_color = ?
+            WHERE device_address = ?
+            "#,
+            identity.serial_number,
+            probe_id,
+            probe_color,
+            address
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to store probe identity")?;
+        
+        Ok((address, is_new))
+    }
+    
+    /// Record the firmware version a device reported
+    pub async fn set_firmware_version(&self, device_address: &str, firmware_version: &str) -> Result<()> {
+        sqlx::query("UPDATE devices SET firmware_version = ? WHERE device_address = ?")
+            .bind(firmware_version)
+            .bind(device_address)
+            .execute(&self.pool)
+            .await
+            .context("Failed to store firmware version")?;
+        
+        Ok(())
+    }
+    
+    /// Store what a device reported in its Device Information Service
+    ///
+    /// Fields it did not report keep their stored value. The serial number
+    /// only fills an empty one, and never one another device already has,
+    /// since it is what rotating Combustion MACs are matched by.
+    pub async fn set_device_info(&self, device_address: &str, info: &DeviceInfo) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE devices SET
+                firmware_version = COALESCE(?, firmware_version),
+                hardware_revision = COALESCE(?, hardware_revision),
+                manufacturer = COALESCE(?, manufacturer)
+            WHERE device_address = ?
             "#
         )
+        .bind(&info.firmware_revision)
+        .bind(&info.hardware_revision)
+        .bind(&info.manufacturer)
         .bind(device_address)
-        .bind(device_name)
-        .bind(brand)
-        .bind(model)
-        .bind(sensor_count as i64)
-        .bind(now)
-        .bind(now)
         .execute(&self.pool)
         .await
-        .context("Failed to upsert device")?;
+        .context("Failed to store device information")?;
+        
+        if let Some(serial) = &info.serial_number {
+            sqlx::query(
+                r#"
+                UPDATE devices SET serial_number = ?
+                WHERE device_address = ? AND serial_number IS NULL
+                  AND NOT EXISTS (SELECT 1 FROM devices WHERE serial_number = ?)
+                "#
+            )
+            .bind(serial)
+            .bind(device_address)
+            .bind(serial)
+            .execute(&self.pool)
+            .await
+            .context("Failed to store device serial number")?;
+        }
         
         Ok(())
     }
@@ -127,10 +261,62 @@ impl Database {
         device_address: &str,
         timestamp: DateTime<Utc>,
         sensor_index: usize,
-        temperature: f32,
-        ambient_temp: Option<f32>,
+        temperature: Temperature,
+        ambient_temp: Option<Temperature>,
+        battery_level: Option<u8>,
+        signal_strength: i16,
+    ) -> Result<()> {
+        self.insert_reading_impl(
+            device_address,
+            timestamp,
+            sensor_index,
+            temperature,
+            ambient_temp,
+            battery_level,
+            signal_strength,
+            ReadingMode::Normal,
+            ReadingSource::Ble,
+        ).await
+    }
+    
+    /// Store a reading that did not come from a live BLE connection (cloud sync, simulator, ...)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_reading_from(
+        &self,
+        source: ReadingSource,
+        device_address: &str,
+        timestamp: DateTime<Utc>,
+        sensor_index: usize,
+        temperature: Temperature,
+        ambient_temp: Option<Temperature>,
+        battery_level: Option<u8>,
+        signal_strength: i16,
+    ) -> Result<()> {
+        self.insert_reading_impl(
+            device_address,
+            timestamp,
+            sensor_index,
+            temperature,
+            ambient_temp,
+            battery_level,
+            signal_strength,
+            ReadingMode::Normal,
+            source,
+        ).await
+    }
+    
+    /// Store a reading taken in a specific probe mode (e.g. instant read)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_reading_with_mode(
+        &self,
+        device_address: &str,
+        timestamp: DateTime<Utc>,
+        sensor_index: usize,
+        temperature: Temperature,
+        ambient_temp: Option<Temperature>,
         battery_level: Option<u8>,
         signal_strength: i16,
+        mode: ReadingMode,
     ) -> Result<()> {
         self.insert_reading_impl(
             device_address,
@@ -140,6 +326,8 @@ impl Database {
             ambient_temp,
             battery_level,
             signal_strength,
+            mode,
+            ReadingSource::Ble,
         ).await
     }
     
@@ -149,212 +337,1992 @@ impl Database {
         device_address: &str,
         timestamp: DateTime<Utc>,
         sensor_index: usize,
-        temperature: f32,
-        ambient_temp: Option<f32>,
+        temperature: Temperature,
+        ambient_temp: Option<Temperature>,
         battery_level: Option<u8>,
         signal_strength: i16,
+        mode: ReadingMode,
+        source: ReadingSource,
     ) -> Result<()> {
-        sqlx::query(
+        let timestamp = self.timestamp_precision.truncate(timestamp);
+        let (sensor_index, battery_level, signal_strength) =
+            (sensor_index as i64, battery_level.map(|b| b as i64), signal_strength as i64);
+        let (mode, source) = (mode.as_str(), source.as_str());
+        sqlx::query!(
             r#"
             INSERT INTO readings (device_address, timestamp, sensor_index, temperature, 
-                                ambient_temp, battery_level, signal_strength)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            "#
+                                ambient_temp, battery_level, signal_strength, reading_mode, source)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            device_address,
+            timestamp,
+            sensor_index,
+            temperature,
+            ambient_temp,
+            battery_level,
+            signal_strength,
+            mode,
+            source
         )
-        .bind(device_address)
-        .bind(timestamp)
-        .bind(sensor_index as i64)
-        .bind(temperature)
-        .bind(ambient_temp)
-        .bind(battery_level.map(|b| b as i64))
-        .bind(signal_strength as i64)
         .execute(&self.pool)
         .await
         .context("Failed to insert reading")?;
         
+        *self.last_write.lock().unwrap() = Some(Instant::now());
         Ok(())
     }
     
-    pub async fn cleanup_old_readings(&self, retention_days: u32) -> Result<u64> {
-        if retention_days == 0 {
-            return Ok(0);
-        }
-        
-        let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
-        
-        let result = sqlx::query(
+    /// Store a prediction engine status received from a probe
+    pub async fn insert_prediction(
+        &self,
+        device_address: &str,
+        timestamp: DateTime<Utc>,
+        prediction: &PredictionStatus,
+    ) -> Result<()> {
+        sqlx::query(
             r#"
-            DELETE FROM readings WHERE timestamp < ?
+            INSERT INTO predictions (device_address, timestamp, state, set_point, estimated_core, eta_seconds)
+            VALUES (?, ?, ?, ?, ?, ?)
             "#
         )
-        .bind(cutoff)
+        .bind(device_address)
+        .bind(self.timestamp_precision.truncate(timestamp))
+        .bind(prediction.state.as_str())
+        .bind(prediction.set_point)
+        .bind(prediction.estimated_core)
+        .bind(prediction.eta_seconds.map(|s| s as i64))
         .execute(&self.pool)
         .await
-        .context("Failed to cleanup old readings")?;
-        
-        let rows_deleted = result.rows_affected();
-        if rows_deleted > 0 {
-            info!("Cleaned up {} old readings", rows_deleted);
-        }
+        .context("Failed to insert prediction")?;
         
-        Ok(rows_deleted)
+        Ok(())
     }
     
-    pub async fn get_latest_reading(&self, device_address: &str) -> Result<ReadingRecord> {
-        let result = sqlx::query_as::<_, ReadingRecord>(
-            r#"
-            SELECT device_address, timestamp, sensor_index, temperature, 
-                   ambient_temp, battery_level, signal_strength
-            FROM readings
-            WHERE device_address = ?
-            ORDER BY timestamp DESC
-            LIMIT 1
-            "#
+    /// Sensor chosen as the headline temperature for a device (None = brand default)
+    pub async fn get_primary_sensor(&self, device_address: &str) -> Result<Option<usize>> {
+        let index: Option<Option<i64>> = sqlx::query_scalar(
+            "SELECT primary_sensor_index FROM device_settings WHERE device_address = ?"
         )
         .bind(device_address)
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await
-        .context("Failed to fetch latest reading")?;
+        .context("Failed to fetch primary sensor")?;
         
-        Ok(result)
+        Ok(index.flatten().map(|i| i as usize))
     }
     
-    /// Get all devices
-    pub async fn get_all_devices(&self) -> Result<Vec<DeviceRecord>> {
-        let devices = sqlx::query_as::<_, DeviceRecord>(
+    /// Choose the headline sensor for a device (None restores the brand default)
+    pub async fn set_primary_sensor(&self, device_address: &str, sensor_index: Option<usize>) -> Result<()> {
+        sqlx::query(
             r#"
-            SELECT device_address, device_name, brand, model, sensor_count, 
-                   first_seen, last_seen
-            FROM devices
-            ORDER BY last_seen DESC
+            INSERT INTO device_settings (device_address, primary_sensor_index)
+            VALUES (?, ?)
+            ON CONFLICT(device_address) DO UPDATE SET
+                primary_sensor_index = excluded.primary_sensor_index
             "#
         )
-        .fetch_all(&self.pool)
+        .bind(device_address)
+        .bind(sensor_index.map(|i| i as i64))
+        .execute(&self.pool)
         .await
-        .context("Failed to fetch devices")?;
+        .context("Failed to update primary sensor")?;
         
-        Ok(devices)
+        Ok(())
     }
     
-    /// Get a specific device
-    pub async fn get_device(&self, device_address: &str) -> Result<DeviceRecord> {
-        let device = sqlx::query_as::<_, DeviceRecord>(
+    /// Count a frame from this device that failed validation; returns the new total
+    pub async fn record_rejected_frame(&self, device_address: &str, reason: &str) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
             r#"
-            SELECT device_address, device_name, brand, model, sensor_count,
-                   first_seen, last_seen
-            FROM devices
-            WHERE device_address = ?
+            INSERT INTO frame_stats (device_address, rejected_count, last_rejected_at, last_reason)
+            VALUES (?, 1, ?, ?)
+            ON CONFLICT(device_address) DO UPDATE SET
+                rejected_count = rejected_count + 1,
+                last_rejected_at = excluded.last_rejected_at,
+                last_reason = excluded.last_reason
+            RETURNING rejected_count
             "#
         )
         .bind(device_address)
+        .bind(self.timestamp_precision.truncate(Utc::now()))
+        .bind(reason)
         .fetch_one(&self.pool)
         .await
-        .context("Failed to fetch device")?;
+        .context("Failed to record rejected frame")?;
         
-        Ok(device)
+        Ok(count)
     }
     
-    /// Get readings since a specific time
-    pub async fn get_readings_since(
-        &self,
-        device_address: &str,
-        since: DateTime<Utc>,
-    ) -> Result<Vec<ReadingRecord>> {
-        let readings = sqlx::query_as::<_, ReadingRecord>(
-            r#"
-            SELECT device_address, timestamp, sensor_index, temperature,
-                   ambient_temp, battery_level, signal_strength
-            FROM readings
-            WHERE device_address = ? AND timestamp >= ?
-            ORDER BY timestamp ASC
-            "#
+    /// Number of frames from this device that failed validation
+    pub async fn get_rejected_frames(&self, device_address: &str) -> Result<i64> {
+        let count: Option<i64> = sqlx::query_scalar(
+            "SELECT rejected_count FROM frame_stats WHERE device_address = ?"
         )
         .bind(device_address)
-        .bind(since)
-        .fetch_all(&self.pool)
+        .fetch_optional(&self.pool)
         .await
-        .context("Failed to fetch readings")?;
+        .context("Failed to fetch rejected frame count")?;
         
-        Ok(readings)
+        Ok(count.unwrap_or(0))
     }
     
-    /// Get recent readings for a device
-    pub async fn get_device_readings(
-        &self,
-        device_address: &str,
-        limit: usize,
-    ) -> Result<Vec<ReadingRecord>> {
-        let query = if limit == 0 {
-            sqlx::query_as::<_, ReadingRecord>(
-                r#"
-                SELECT device_address, timestamp, sensor_index, temperature,
-                       ambient_temp, battery_level, signal_strength
-                FROM readings
-                WHERE device_address = ?
-                ORDER BY timestamp DESC
-                "#
-            )
-            .bind(device_address)
-        } else {
-            sqlx::query_as::<_, ReadingRecord>(
+    /// Two-phase retention cleanup
+    ///
+    /// Phase 1 thins readings outside cook sessions that are older than the
+    /// full-resolution window to one per sensor per bucket. Phase 2 deletes
+    /// idle readings and session readings past their retention periods.
+    /// Cook sessions themselves are never deleted.
+    pub async fn cleanup_old_readings(&self, policy: &RetentionPolicy) -> Result<CleanupReport> {
+        let now = Utc::now();
+        let mut report = CleanupReport::default();
+        
+        if policy.idle_full_resolution_hours > 0 {
+            let cutoff = now - chrono::Duration::hours(policy.idle_full_resolution_hours as i64);
+            report.decimated = sqlx::query(&format!(
                 r#"
-                SELECT device_address, timestamp, sensor_index, temperature,
-                       ambient_temp, battery_level, signal_strength
-                FROM readings
-                WHERE device_address = ?
-                ORDER BY timestamp DESC
-                LIMIT ?
-                "#
-            )
-            .bind(device_address)
-            .bind(limit as i64)
-        };
+                DELETE FROM readings
+                WHERE timestamp < ? AND NOT {in_session}
+                  AND id NOT IN (
+                    SELECT MIN(id) FROM readings
+                    WHERE timestamp < ? AND NOT {in_session}
+                    GROUP BY device_address, sensor_index,
+                             CAST(strftime('%s', timestamp) AS INTEGER) / ?
+                  )
+                "#,
+                in_session = in_cook_session("readings"),
+            ))
+            .bind(cutoff)
+            .bind(cutoff)
+            .bind(policy.decimate_interval_secs.max(1) as i64)
+            .execute(&self.pool)
+            .await
+            .context("Failed to decimate idle readings")?
+            .rows_affected();
+        }
         
-        let readings = query
-            .fetch_all(&self.pool)
+        for (days, session_clause) in [(policy.idle_days, "NOT"), (policy.session_days, "")] {
+            if days == 0 {
+                continue;
+            }
+            let cutoff = now - chrono::Duration::days(days as i64);
+            
+            report.deleted += sqlx::query(&format!(
+                "DELETE FROM readings WHERE timestamp < ? AND {} {}",
+                session_clause, in_cook_session("readings"),
+            ))
+            .bind(cutoff)
+            .execute(&self.pool)
             .await
-            .context("Failed to fetch device readings")?;
+            .context("Failed to cleanup old readings")?
+            .rows_affected();
+            
+            sqlx::query(&format!(
+                "DELETE FROM predictions WHERE timestamp < ? AND {} {}",
+                session_clause,
+                in_cook_session("predictions"),
+            ))
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .context("Failed to cleanup old predictions")?;
+        }
         
-        Ok(readings)
+        if report.decimated > 0 || report.deleted > 0 {
+            // Readings deleted before they could be uploaded leave nothing to retry
+            sqlx::query("DELETE FROM pending_sync WHERE reading_id NOT IN (SELECT id FROM readings)")
+                .execute(&self.pool)
+                .await
+                .context("Failed to cleanup pending sync queue")?;
+            info!(
+                "Cleaned up readings: {} idle readings decimated, {} deleted",
+                report.decimated, report.deleted
+            );
+        }
+        
+        Ok(report)
     }
     
-    /// Get readings within a time range
-    pub async fn get_readings_in_range(
-        &self,
-        device_address: &str,
-        start: DateTime<Utc>,
-        end: DateTime<Utc>,
-    ) -> Result<Vec<ReadingRecord>> {
-        let readings = sqlx::query_as::<_, ReadingRecord>(
+    /// Rebuild the database file so space freed by deletes is returned to the OS
+    ///
+    /// Holds an exclusive lock while it runs, so inserts wait; prefer
+    /// `vacuum_if_idle` while devices are being monitored.
+    pub async fn vacuum(&self) -> Result<VacuumReport> {
+        let bytes_before = self.file_size().await?;
+        sqlx::query("VACUUM")
+            .execute(&self.pool)
+            .await
+            .context("Failed to vacuum database")?;
+        let bytes_after = self.file_size().await?;
+        
+        info!("🧹 Vacuumed database: {} KiB -> {} KiB", bytes_before / 1024, bytes_after / 1024);
+        Ok(VacuumReport { bytes_before, bytes_after })
+    }
+    
+    /// Return free pages to the OS without rebuilding the file; returns the bytes freed
+    ///
+    /// Only shrinks databases created with incremental auto_vacuum (see `initialize`);
+    /// a full `vacuum` converts older ones.
+    pub async fn incremental_vacuum(&self) -> Result<i64> {
+        let bytes_before = self.file_size().await?;
+        sqlx::query("PRAGMA incremental_vacuum")
+            .execute(&self.pool)
+            .await
+            .context("Failed to run incremental vacuum")?;
+        Ok(bytes_before - self.file_size().await?)
+    }
+    
+    /// Vacuum unless a reading was written within `quiet_period` (returns None when skipped)
+    pub async fn vacuum_if_idle(&self, quiet_period: Duration) -> Result<Option<VacuumReport>> {
+        let last_write = *self.last_write.lock().unwrap();
+        if let Some(elapsed) = last_write.map(|t| t.elapsed()).filter(|e| *e < quiet_period) {
+            warn!(
+                "Skipping vacuum: readings written {}s ago, waiting for {}s without writes",
+                elapsed.as_secs(),
+                quiet_period.as_secs()
+            );
+            return Ok(None);
+        }
+        
+        self.vacuum().await.map(Some)
+    }
+    
+    /// Database size in bytes according to SQLite's page counts
+    async fn file_size(&self) -> Result<i64> {
+        let pages = sqlx::query_scalar::<_, i64>("PRAGMA page_count")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to read page count")?;
+        let page_size = sqlx::query_scalar::<_, i64>("PRAGMA page_size")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to read page size")?;
+        Ok(pages * page_size)
+    }
+    
+    /// Headline reading of a device: the primary sensor at the newest timestamp
+    pub async fn get_latest_reading(&self, device_address: &str) -> Result<ReadingRecord> {
+        Ok(self.get_latest_snapshot(device_address).await?.primary)
+    }
+    
+    /// Every sensor's reading at the newest timestamp of a device
+    ///
+    /// All sensors of a packet share one timestamp, so this is the last packet.
+    /// `primary` is the configured (or brand default) headline sensor, or the
+    /// lowest sensor index if the primary sensor is missing from that packet.
+    pub async fn get_latest_snapshot(&self, device_address: &str) -> Result<LatestReading> {
+        let mut sensors = sqlx::query_as!(
+            ReadingRecord,
             r#"
-            SELECT device_address, timestamp, sensor_index, temperature,
-                   ambient_temp, battery_level, signal_strength
+            SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                   temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                   battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                   reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
             FROM readings
-            WHERE device_address = ? AND timestamp >= ? AND timestamp <= ?
-            ORDER BY timestamp ASC
-            "#
+            WHERE device_address = ?1
+              AND timestamp = (SELECT MAX(timestamp) FROM readings WHERE device_address = ?1)
+            ORDER BY sensor_index ASC, id DESC
+            "#,
+            device_address
         )
-        .bind(device_address)
-        .bind(start)
-        .bind(end)
         .fetch_all(&self.pool)
         .await
-        .context("Failed to fetch readings in range")?;
+        .context("Failed to fetch latest reading")?;
+        // A duplicate row for a sensor keeps the last one inserted
+        sensors.dedup_by_key(|r| r.sensor_index);
         
-        Ok(readings)
-    }
-}
-
+        if sensors.is_empty() {
+            anyhow::bail!("No readings for device {}", device_address);
+        }
+        
+        let primary_index = match self.get_primary_sensor(device_address).await? {
+            Some(index) => index,
+            None => default_primary_sensor(self.get_device(device_address).await?.sensor_count as usize),
+        };
+        let primary = sensors
+            .iter()
+            .find(|r| r.sensor_index == primary_index as i64)
+            .unwrap_or(&sensors[0])
+            .clone();
+        
+        Ok(LatestReading { primary, sensors })
+    }
+    
+    /// Get the most recent reading of each sensor on a device, ordered by sensor index
+    pub async fn get_latest_per_sensor(&self, device_address: &str) -> Result<Vec<ReadingRecord>> {
+        let readings = sqlx::query_as!(
+            ReadingRecord,
+            r#"
+            SELECT r.device_address AS "device_address!", r.timestamp AS "timestamp!: DateTime<Utc>",
+                   r.sensor_index AS "sensor_index!", r.temperature AS "temperature!: Temperature",
+                   r.ambient_temp AS "ambient_temp: Temperature", r.battery_level AS "battery_level: u8",
+                   r.signal_strength AS "signal_strength!: i16", r.reading_mode AS "reading_mode!: ReadingMode",
+                   r.source AS "source!: ReadingSource"
+            FROM readings r
+            JOIN (
+                SELECT sensor_index, MAX(timestamp) AS latest
+                FROM readings
+                WHERE device_address = ?1
+                GROUP BY sensor_index
+            ) l ON r.sensor_index = l.sensor_index AND r.timestamp = l.latest
+            WHERE r.device_address = ?1
+            GROUP BY r.sensor_index
+            ORDER BY r.sensor_index ASC
+            "#,
+            device_address
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch latest readings per sensor")?;
+        
+        Ok(readings)
+    }
+    
+    /// Get all devices, most recently seen first
+    pub async fn get_all_devices(&self) -> Result<Vec<DeviceRecord>> {
+        self.get_devices(None, DeviceSort::default()).await
+    }
+    
+    /// Get all devices of one brand family (a brand prefix, any case)
+    pub async fn get_devices_by_brand(&self, brand: &str) -> Result<Vec<DeviceRecord>> {
+        self.get_devices(Some(brand), DeviceSort::default()).await
+    }
+    
+    /// Get devices, optionally of one brand family, in `sort` order
+    ///
+    /// `brand` matches as a case-insensitive prefix, so `meater` lists
+    /// MeaterOriginal, MeaterPlus and MeaterBlock. Ties are broken by
+    /// address, so devices seen in the same scan keep their order between calls.
+    pub async fn get_devices(&self, brand: Option<&str>, sort: DeviceSort) -> Result<Vec<DeviceRecord>> {
+        // LIKE wildcards in the filter are taken literally
+        let brand = brand.map(|b| b.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let sort = sort.as_str();
+        // One static statement for every filter and order, so it is checked at compile time
+        let devices = sqlx::query_as!(
+            DeviceRecord,
+            r#"
+            SELECT device_address AS "device_address!", device_name, brand, model, sensor_count,
+                   first_seen AS "first_seen: DateTime<Utc>", last_seen AS "last_seen: DateTime<Utc>",
+                   serial_number, probe_id, probe_color, firmware_version,
+                   hardware_revision, manufacturer, alias,
+                   damage_risk_at AS "damage_risk_at: DateTime<Utc>", damage_risk_peak AS "damage_risk_peak: Temperature",
+                   target_temp AS "target_temp: Temperature", target_sensor_index
+            FROM devices
+            WHERE ?1 IS NULL OR brand LIKE ?1 || '%' ESCAPE '\'
+            ORDER BY
+                CASE WHEN ?2 = 'name' THEN device_name END COLLATE NOCASE,
+                CASE WHEN ?2 = 'last_seen' THEN last_seen END DESC,
+                CASE WHEN ?2 = 'first_seen' THEN first_seen END,
+                device_address
+            "#,
+            brand,
+            sort
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch devices")?;
+        
+        Ok(devices)
+    }
+    
+    /// Highest sensor index and newest reading of every device that has readings
+    pub async fn observed_readings(&self) -> Result<Vec<ObservedReadings>> {
+        let observed = sqlx::query_as::<_, ObservedReadings>(
+            r#"
+            SELECT device_address, MAX(sensor_index) AS max_sensor_index, MAX(timestamp) AS last_reading
+            FROM readings
+            GROUP BY device_address
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to summarize readings per device")?;
+        
+        Ok(observed)
+    }
+    
+    /// Overwrite the device fields that are derived from detection and readings
+    pub async fn set_derived_device_fields(
+        &self,
+        device_address: &str,
+        brand: &str,
+        sensor_count: i64,
+        last_seen: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE devices SET brand = ?, sensor_count = ?, last_seen = ? WHERE device_address = ?")
+            .bind(brand)
+            .bind(sensor_count)
+            .bind(last_seen)
+            .bind(device_address)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update derived device fields")?;
+        
+        Ok(())
+    }
+    
+    /// Get a specific device
+    pub async fn get_device(&self, device_address: &str) -> Result<DeviceRecord> {
+        self.find_device(device_address)
+            .await?
+            .with_context(|| format!("Unknown device {}", device_address))
+    }
+    
+    /// A specific device, or None if it was never seen
+    pub async fn find_device(&self, device_address: &str) -> Result<Option<DeviceRecord>> {
+        let device = sqlx::query_as!(
+            DeviceRecord,
+            r#"
+            SELECT device_address AS "device_address!", device_name, brand, model, sensor_count,
+                   first_seen AS "first_seen: DateTime<Utc>", last_seen AS "last_seen: DateTime<Utc>",
+                   serial_number, probe_id, probe_color, firmware_version,
+                   hardware_revision, manufacturer, alias,
+                   damage_risk_at AS "damage_risk_at: DateTime<Utc>", damage_risk_peak AS "damage_risk_peak: Temperature",
+                   target_temp AS "target_temp: Temperature", target_sensor_index
+            FROM devices
+            WHERE device_address = ?
+            "#,
+            device_address
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch device")?;
+        
+        Ok(device)
+    }
+    
+    /// Get readings since a specific time
+    pub async fn get_readings_since(
+        &self,
+        device_address: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ReadingRecord>> {
+        let since = self.timestamp_precision.truncate(since);
+        let readings = sqlx::query_as!(
+            ReadingRecord,
+            r#"
+            SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                   temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                   battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                   reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
+            FROM readings
+            WHERE device_address = ? AND timestamp >= ?
+            ORDER BY timestamp ASC, id ASC
+            "#,
+            device_address,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch readings")?;
+        
+        Ok(readings)
+    }
+    
+    /// Stream readings since a specific time without buffering the result set
+    ///
+    /// Rows are fetched by a background task and handed over through a small
+    /// bounded channel, so memory stays flat however many rows match.
+    pub fn stream_readings_since(
+        &self,
+        device_address: &str,
+        since: DateTime<Utc>,
+    ) -> ReceiverStream<Result<ReadingRecord>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        let pool = self.pool.clone();
+        let device_address = device_address.to_string();
+        let since = self.timestamp_precision.truncate(since);
+        
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as!(
+                ReadingRecord,
+                r#"
+                SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                       temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                       battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                       reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
+                FROM readings
+                WHERE device_address = ? AND timestamp >= ?
+                ORDER BY timestamp ASC, id ASC
+                "#,
+                device_address,
+                since
+            )
+            .fetch(&pool);
+            
+            while let Some(row) = rows.next().await {
+                let row = row.context("Failed to stream readings");
+                let failed = row.is_err();
+                // Stop when the consumer hangs up or after reporting an error
+                if tx.send(row).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        
+        ReceiverStream::new(rx)
+    }
+    
+    /// Stream one device's readings (every device's when None) in a time range, formatted for download
+    ///
+    /// Like `stream_readings_since`, rows come from a background task through a
+    /// bounded channel; they are formatted as they arrive and handed over in
+    /// chunks of about `EXPORT_CHUNK_BYTES`. An error ends the stream.
+    pub fn export_readings(
+        &self,
+        device_address: Option<&str>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        format: ExportFormat,
+        unit: TemperatureUnit,
+    ) -> ReceiverStream<Result<String>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let pool = self.pool.clone();
+        let device_address = device_address.map(str::to_string);
+        let start = self.timestamp_precision.truncate(start);
+        
+        tokio::spawn(async move {
+            // Separate queries so each can use its index
+            let mut rows = match device_address {
+                Some(_) => sqlx::query_as!(
+                    ReadingRecord,
+                    r#"
+                    SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                           temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                           battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                           reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
+                    FROM readings
+                    WHERE device_address = ? AND timestamp >= ? AND timestamp <= ?
+                    ORDER BY timestamp ASC, id ASC
+                    "#,
+                    device_address,
+                    start,
+                    end
+                )
+                .fetch(&pool),
+                None => sqlx::query_as!(
+                    ReadingRecord,
+                    r#"
+                    SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                           temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                           battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                           reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
+                    FROM readings
+                    WHERE timestamp >= ? AND timestamp <= ?
+                    ORDER BY timestamp ASC, id ASC
+                    "#,
+                    start,
+                    end
+                )
+                .fetch(&pool),
+            };
+            
+            let mut chunk = format.stream_prefix();
+            let mut first = true;
+            while let Some(row) = rows.next().await {
+                let piece = row
+                    .context("Failed to stream readings")
+                    .and_then(|reading| format.stream_row(&reading, unit, first));
+                match piece {
+                    Ok(piece) => chunk.push_str(&piece),
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+                first = false;
+                if chunk.len() >= EXPORT_CHUNK_BYTES {
+                    let full = std::mem::replace(&mut chunk, String::with_capacity(EXPORT_CHUNK_BYTES));
+                    // Stop when the consumer hangs up
+                    if tx.send(Ok(full)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            chunk.push_str(format.stream_suffix());
+            if !chunk.is_empty() {
+                let _ = tx.send(Ok(chunk)).await;
+            }
+        });
+        
+        ReceiverStream::new(rx)
+    }
+    
+    /// Get recent readings for a device
+    pub async fn get_device_readings(
+        &self,
+        device_address: &str,
+        limit: usize,
+    ) -> Result<Vec<ReadingRecord>> {
+        // LIMIT -1 is no limit in SQLite
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        let readings = sqlx::query_as!(
+            ReadingRecord,
+            r#"
+            SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                   temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                   battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                   reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
+            FROM readings
+            WHERE device_address = ?
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ?
+            "#,
+            device_address,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch device readings")?;
+        
+        Ok(readings)
+    }
+    
+    /// Get readings within a time range
+    pub async fn get_readings_in_range(
+        &self,
+        device_address: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ReadingRecord>> {
+        let start = self.timestamp_precision.truncate(start);
+        let readings = sqlx::query_as!(
+            ReadingRecord,
+            r#"
+            SELECT device_address, timestamp AS "timestamp: DateTime<Utc>", sensor_index,
+                   temperature AS "temperature: Temperature", ambient_temp AS "ambient_temp: Temperature",
+                   battery_level AS "battery_level: u8", signal_strength AS "signal_strength: i16",
+                   reading_mode AS "reading_mode: ReadingMode", source AS "source: ReadingSource"
+            FROM readings
+            WHERE device_address = ? AND timestamp >= ? AND timestamp <= ?
+            ORDER BY timestamp ASC, id ASC
+            "#,
+            device_address,
+            start,
+            end
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch readings in range")?;
+        
+        Ok(readings)
+    }
+    
+    /// Start a cook session on a device; fails if one is already open
+    pub async fn start_cook(
+        &self,
+        device_address: &str,
+        name: &str,
+        target_temp: Option<Temperature>,
+    ) -> Result<CookSession> {
+        let started_at = self.timestamp_precision.truncate(Utc::now());
+        let anchor = MonotonicAnchor::now();
+        let (boot_id, uptime_ms) = (anchor.as_ref().map(|a| a.boot_id.as_str()), anchor.as_ref().map(|a| a.uptime_ms));
+        let session = sqlx::query_as!(
+            CookSession,
+            r#"
+            INSERT INTO cook_sessions (device_address, name, target_temp, started_at, start_boot_id, start_uptime_ms)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id AS "id!", device_address, name, target_temp AS "target_temp: Temperature",
+                      started_at AS "started_at: DateTime<Utc>", ended_at AS "ended_at: DateTime<Utc>"
+            "#,
+            device_address,
+            name,
+            target_temp,
+            started_at,
+            boot_id,
+            uptime_ms
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to start cook session")?;
+        
+        Ok(session)
+    }
+    
+    /// Cook sessions on a device that overlap a time range, oldest first
+    pub async fn get_cooks_in_range(
+        &self,
+        device_address: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CookSession>> {
+        let start = self.timestamp_precision.truncate(start);
+        let sessions = sqlx::query_as!(
+            CookSession,
+            r#"
+            SELECT id AS "id!", device_address, name, target_temp AS "target_temp: Temperature",
+                   started_at AS "started_at: DateTime<Utc>", ended_at AS "ended_at: DateTime<Utc>"
+            FROM cook_sessions
+            WHERE device_address = ? AND started_at <= ?
+              AND (ended_at IS NULL OR ended_at >= ?)
+            ORDER BY started_at ASC, id ASC
+            "#,
+            device_address,
+            end,
+            start
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch cook sessions in range")?;
+        
+        Ok(sessions)
+    }
+    
+    /// The open cook session for a device, if any
+    pub async fn get_open_cook(&self, device_address: &str) -> Result<Option<CookSession>> {
+        let session = sqlx::query_as!(
+            CookSession,
+            r#"
+            SELECT id AS "id!", device_address, name, target_temp AS "target_temp: Temperature",
+                   started_at AS "started_at: DateTime<Utc>", ended_at AS "ended_at: DateTime<Utc>"
+            FROM cook_sessions
+            WHERE device_address = ? AND ended_at IS NULL
+            "#,
+            device_address
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch open cook session")?;
+        
+        Ok(session)
+    }
+    
+    /// Open cook sessions across all devices
+    pub async fn get_open_cooks(&self) -> Result<Vec<CookSession>> {
+        let sessions = sqlx::query_as!(
+            CookSession,
+            r#"
+            SELECT id AS "id!", device_address, name, target_temp AS "target_temp: Temperature",
+                   started_at AS "started_at: DateTime<Utc>", ended_at AS "ended_at: DateTime<Utc>"
+            FROM cook_sessions
+            WHERE ended_at IS NULL
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch open cook sessions")?;
+        
+        Ok(sessions)
+    }
+    
+    /// Change the target of the open cook on a device (None if no cook is open)
+    pub async fn set_cook_target(
+        &self,
+        device_address: &str,
+        target_temp: Option<Temperature>,
+    ) -> Result<Option<CookSession>> {
+        let session = sqlx::query_as::<_, CookSession>(
+            r#"
+            UPDATE cook_sessions SET target_temp = ?
+            WHERE device_address = ? AND ended_at IS NULL
+            RETURNING id, device_address, name, target_temp, started_at, ended_at
+            "#
+        )
+        .bind(target_temp)
+        .bind(device_address)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to update cook target")?;
+        
+        Ok(session)
+    }
+    
+    /// Record the pit temperature the open cook on a device is run at; false if no cook is open
+    pub async fn set_cook_pit_setpoint(&self, device_address: &str, setpoint: Option<Temperature>) -> Result<bool> {
+        let updated = sqlx::query("UPDATE cook_sessions SET pit_setpoint = ? WHERE device_address = ? AND ended_at IS NULL")
+            .bind(setpoint)
+            .bind(device_address)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update cook pit setpoint")?
+            .rows_affected();
+        
+        Ok(updated > 0)
+    }
+    
+    /// Store a cook session with known start and end times, e.g. one loaded from a fixture
+    pub async fn insert_cook_session(
+        &self,
+        device_address: &str,
+        name: &str,
+        target_temp: Option<Temperature>,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+    ) -> Result<CookSession> {
+        let session = sqlx::query_as::<_, CookSession>(
+            r#"
+            INSERT INTO cook_sessions (device_address, name, target_temp, started_at, ended_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING id, device_address, name, target_temp, started_at, ended_at
+            "#
+        )
+        .bind(device_address)
+        .bind(name)
+        .bind(target_temp)
+        .bind(self.timestamp_precision.truncate(started_at))
+        .bind(ended_at.map(|t| self.timestamp_precision.truncate(t)))
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to insert cook session")?;
+
+        Ok(session)
+    }
+
+    /// End the open cook session on a device and return its stats (None if no cook is open)
+    pub async fn end_cook(&self, device_address: &str) -> Result<Option<CookStats>> {
+        let anchor = MonotonicAnchor::now();
+        let ended = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE cook_sessions SET ended_at = ?, end_boot_id = ?, end_uptime_ms = ?
+            WHERE device_address = ? AND ended_at IS NULL
+            RETURNING id
+            "#
+        )
+        .bind(self.timestamp_precision.truncate(Utc::now()))
+        .bind(anchor.as_ref().map(|a| a.boot_id.as_str()))
+        .bind(anchor.as_ref().map(|a| a.uptime_ms))
+        .bind(device_address)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to end cook session")?;
+        
+        match ended {
+            Some(id) => Ok(Some(self.get_cook_stats(id).await?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Aggregate primary-sensor stats for a cook session
+    ///
+    /// Readings belong to a session by timestamp range; an open session runs
+    /// until now. The aggregation happens in a single SQL query.
+    pub async fn get_cook_stats(&self, session_id: i64) -> Result<CookStats> {
+        let now = Utc::now();
+        
+        let row = sqlx::query_as::<_, CookStatsRow>(
+            r#"
+            SELECT s.id, s.device_address, s.name, s.target_temp, s.started_at, s.ended_at,
+                   s.start_boot_id, s.start_uptime_ms, s.end_boot_id, s.end_uptime_ms, s.pit_setpoint,
+                   COUNT(r.id) AS reading_count,
+                   MIN(r.temperature) AS min_temp,
+                   MAX(r.temperature) AS max_temp,
+                   AVG(r.temperature) AS avg_temp,
+                   MIN(CASE WHEN r.temperature >= s.target_temp THEN r.timestamp END) AS target_reached_at,
+                   COALESCE(
+                       ds.primary_sensor_index,
+                       CASE WHEN d.sensor_count = 8 THEN 3 ELSE 0 END
+                   ) AS primary_sensor_index
+            FROM cook_sessions s
+            JOIN devices d ON d.device_address = s.device_address
+            LEFT JOIN device_settings ds ON ds.device_address = s.device_address
+            LEFT JOIN readings r
+                   ON r.device_address = s.device_address
+                  AND r.timestamp >= s.started_at
+                  AND r.timestamp <= COALESCE(s.ended_at, ?)
+                  -- Chosen primary sensor, else the default from
+                  -- device_capabilities::default_primary_sensor
+                  AND r.sensor_index = COALESCE(
+                      ds.primary_sensor_index,
+                      CASE WHEN d.sensor_count = 8 THEN 3 ELSE 0 END
+                  )
+            WHERE s.id = ?
+            GROUP BY s.id
+            "#
+        )
+        .bind(now)
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to compute cook stats")?;
+        
+        let end = row.ended_at.unwrap_or(now);
+        // An open session runs until now, on this boot
+        let end_anchor = match row.ended_at {
+            Some(_) => MonotonicAnchor::from_parts(row.end_boot_id, row.end_uptime_ms),
+            None => MonotonicAnchor::now(),
+        };
+        let duration = reconcile_duration(
+            row.started_at,
+            MonotonicAnchor::from_parts(row.start_boot_id, row.start_uptime_ms).as_ref(),
+            end,
+            end_anchor.as_ref(),
+        );
+        let ambient = self
+            .get_ambient_readings(&row.device_address, row.started_at, end)
+            .await?;
+        let fuel = if ambient.len() >= 2 {
+            let profile = self.get_smoker_profile(&row.device_address).await?;
+            Some(analytics::fuel_estimate(&ambient, &profile, row.pit_setpoint))
+        } else {
+            None
+        };
+        let primary = self
+            .get_sensor_readings(&row.device_address, row.primary_sensor_index, row.started_at, end)
+            .await?;
+        
+        Ok(CookStats {
+            session: CookSession {
+                id: row.id,
+                device_address: row.device_address,
+                name: row.name,
+                target_temp: row.target_temp,
+                started_at: row.started_at,
+                ended_at: row.ended_at,
+            },
+            reading_count: row.reading_count,
+            min_temp: row.min_temp.map(|t| Temperature::from_celsius(t as f32)),
+            max_temp: row.max_temp.map(|t| Temperature::from_celsius(t as f32)),
+            avg_temp: row.avg_temp.map(|t| Temperature::from_celsius(t as f32)),
+            duration_secs: duration.secs,
+            duration_estimated: duration.estimated,
+            time_to_target_secs: row
+                .target_reached_at
+                .map(|reached| (reached - row.started_at).num_seconds()),
+            danger_zone_secs: food_safety::time_in_danger_zone(&primary).num_seconds(),
+            fuel,
+        })
+    }
+    
+    /// One sensor's readings in a time range, oldest first
+    async fn get_sensor_readings(
+        &self,
+        device_address: &str,
+        sensor_index: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ReadingRecord>> {
+        let readings = sqlx::query_as::<_, ReadingRecord>(
+            r#"
+            SELECT device_address, timestamp, sensor_index, temperature,
+                   ambient_temp, battery_level, signal_strength, reading_mode, source
+            FROM readings
+            WHERE device_address = ? AND sensor_index = ? AND timestamp >= ? AND timestamp <= ?
+            ORDER BY timestamp ASC
+            "#
+        )
+        .bind(device_address)
+        .bind(sensor_index)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch sensor readings")?;
+        
+        Ok(readings)
+    }
+    
+    /// One ambient reading per packet in a time range, oldest first
+    async fn get_ambient_readings(
+        &self,
+        device_address: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ReadingRecord>> {
+        // The packet's lowest-id row stands for it; every sensor carries the same ambient value
+        let readings = sqlx::query_as::<_, ReadingRecord>(
+            r#"
+            SELECT r.device_address, r.timestamp, r.sensor_index, r.temperature,
+                   r.ambient_temp, r.battery_level, r.signal_strength, r.reading_mode, r.source
+            FROM readings r
+            JOIN (
+                SELECT MIN(id) AS id FROM readings
+                WHERE device_address = ? AND timestamp >= ? AND timestamp <= ?
+                  AND ambient_temp IS NOT NULL
+                GROUP BY timestamp
+            ) packet ON packet.id = r.id
+            ORDER BY r.timestamp ASC
+            "#
+        )
+        .bind(device_address)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch ambient readings")?;
+        
+        Ok(readings)
+    }
+    
+    /// Primary-sensor and ambient stats of a device between `start` and `end`
+    ///
+    /// Aggregated in SQL, so a night of readings never leaves the database.
+    /// With `above`, also counts the seconds the primary sensor spent at or
+    /// above it: each reading at the threshold holds until the next one.
+    pub async fn get_device_stats(
+        &self,
+        device_address: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        above: Option<Temperature>,
+    ) -> Result<DeviceStats> {
+        let row = sqlx::query_as!(
+            DeviceStatsRow,
+            r#"
+            WITH primary_readings AS (
+                SELECT r.timestamp, r.temperature,
+                       LEAD(r.timestamp) OVER (ORDER BY r.timestamp, r.id) AS next_timestamp
+                FROM readings r
+                JOIN devices d ON d.device_address = r.device_address
+                LEFT JOIN device_settings ds ON ds.device_address = r.device_address
+                WHERE r.device_address = ?1 AND r.timestamp >= ?2 AND r.timestamp <= ?3
+                  -- Same primary sensor choice as get_cook_stats
+                  AND r.sensor_index = COALESCE(
+                      ds.primary_sensor_index,
+                      CASE WHEN d.sensor_count = 8 THEN 3 ELSE 0 END
+                  )
+            ),
+            ambient AS (
+                SELECT MIN(ambient_temp) AS min_ambient, MAX(ambient_temp) AS max_ambient
+                FROM readings
+                WHERE device_address = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+            )
+            SELECT COUNT(p.timestamp) AS "reading_count!: i64",
+                   MIN(p.temperature) AS "min_temp?: f64",
+                   MAX(p.temperature) AS "max_temp?: f64",
+                   AVG(p.temperature) AS "avg_temp?: f64",
+                   a.min_ambient AS "min_ambient?: f64", a.max_ambient AS "max_ambient?: f64",
+                   MIN(p.timestamp) AS "first_reading_at?: DateTime<Utc>",
+                   MAX(p.timestamp) AS "last_reading_at?: DateTime<Utc>",
+                   SUM(CASE WHEN p.temperature >= ?4 AND p.next_timestamp IS NOT NULL
+                            THEN (julianday(p.next_timestamp) - julianday(p.timestamp)) * 86400
+                            ELSE 0.0 END) AS "secs_above?: f64"
+            FROM ambient a
+            LEFT JOIN primary_readings p ON 1
+            "#,
+            device_address,
+            start,
+            end,
+            above
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to compute device stats")?;
+        
+        let temperature = |celsius: Option<f64>| celsius.map(|t| Temperature::from_celsius(t as f32));
+        Ok(DeviceStats {
+            device_address: device_address.to_string(),
+            start,
+            end,
+            reading_count: row.reading_count,
+            min_temp: temperature(row.min_temp),
+            max_temp: temperature(row.max_temp),
+            avg_temp: temperature(row.avg_temp),
+            min_ambient: temperature(row.min_ambient),
+            max_ambient: temperature(row.max_ambient),
+            first_reading_at: row.first_reading_at,
+            last_reading_at: row.last_reading_at,
+            secs_above: above.map(|_| row.secs_above.unwrap_or(0.0).round() as i64),
+        })
+    }
+    
+    /// Smoker characteristics for a device (defaults if never set)
+    pub async fn get_smoker_profile(&self, device_address: &str) -> Result<SmokerProfile> {
+        let row: Option<(f32, Option<String>)> = sqlx::query_as(
+            "SELECT efficiency, notes FROM smoker_profiles WHERE device_address = ?"
+        )
+        .bind(device_address)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch smoker profile")?;
+        
+        Ok(row
+            .map(|(efficiency, notes)| SmokerProfile { efficiency, notes })
+            .unwrap_or_default())
+    }
+    
+    /// Record a reading above the probe's rated internal maximum; returns true when it newly flags the device
+    ///
+    /// The first exceedance sets when the warning started; later ones only
+    /// raise the peak. The flag stays until `clear_probe_damage`.
+    pub async fn flag_probe_damage(&self, device_address: &str, temperature: Temperature, at: DateTime<Utc>) -> Result<bool> {
+        let at = self.timestamp_precision.truncate(at);
+        let was_flagged = sqlx::query_scalar!(
+            r#"
+            UPDATE devices SET
+                damage_risk_at = COALESCE(damage_risk_at, ?1),
+                damage_risk_peak = MAX(COALESCE(damage_risk_peak, ?2), ?2)
+            WHERE device_address = ?3
+            RETURNING damage_risk_at <> ?1 AS "was_flagged!: bool"
+            "#,
+            at,
+            temperature,
+            device_address
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to flag probe damage")?;
+        
+        Ok(was_flagged == Some(false))
+    }
+    
+    /// Set or clear (None) the target kept on a device; returns false for an unknown device
+    pub async fn set_target(
+        &self,
+        device_address: &str,
+        sensor_index: usize,
+        temperature: Option<Temperature>,
+    ) -> Result<bool> {
+        let sensor_index = temperature.map(|_| sensor_index as i64);
+        let updated = sqlx::query!(
+            "UPDATE devices SET target_temp = ?, target_sensor_index = ? WHERE device_address = ?",
+            temperature,
+            sensor_index,
+            device_address
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to set device target")?
+        .rows_affected();
+        
+        Ok(updated > 0)
+    }
+    
+    /// The target kept on a device (None when unset or the device is unknown)
+    pub async fn get_target(&self, device_address: &str) -> Result<Option<DeviceTarget>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT target_temp AS "target_temp: Temperature", target_sensor_index
+            FROM devices
+            WHERE device_address = ?
+            "#,
+            device_address
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch device target")?;
+        
+        Ok(row.and_then(|row| {
+            row.target_temp.map(|temperature| DeviceTarget {
+                sensor_index: row.target_sensor_index.unwrap_or(0).max(0) as usize,
+                temperature,
+            })
+        }))
+    }
+    
+    /// Target relative alert rules follow: the open cook's, else the device's own
+    pub async fn active_target(&self, device_address: &str) -> Result<Option<Temperature>> {
+        let cook_target = self.get_open_cook(device_address).await?.and_then(|cook| cook.target_temp);
+        match cook_target {
+            Some(target) => Ok(Some(target)),
+            None => Ok(self.get_target(device_address).await?.map(|t| t.temperature)),
+        }
+    }
+    
+    /// Clear a device's possible-damage warning; returns false when there was none
+    pub async fn clear_probe_damage(&self, device_address: &str) -> Result<bool> {
+        let cleared = sqlx::query!(
+            "UPDATE devices SET damage_risk_at = NULL, damage_risk_peak = NULL WHERE device_address = ? AND damage_risk_at IS NOT NULL",
+            device_address
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to clear probe damage warning")?
+        .rows_affected();
+        
+        Ok(cleared > 0)
+    }
+    
+    /// Delete a device and everything stored for it, in one transaction
+    ///
+    /// Readings (and their queued cloud uploads), predictions, cook sessions
+    /// (an open one included), settings, alert rules, notes and frame stats all
+    /// go. Returns the number of readings deleted, or None for an unknown device.
+    /// A device still in range is added again with its next reading.
+    pub async fn delete_device(&self, device_address: &str) -> Result<Option<u64>> {
+        let mut tx = self.pool.begin().await.context("Failed to start device delete")?;
+        let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM devices WHERE device_address = ?")
+            .bind(device_address)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to look up device")?;
+        if exists == 0 {
+            return Ok(None);
+        }
+        
+        sqlx::query("DELETE FROM pending_sync WHERE reading_id IN (SELECT id FROM readings WHERE device_address = ?)")
+            .bind(device_address)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete queued uploads")?;
+        let readings = sqlx::query("DELETE FROM readings WHERE device_address = ?")
+            .bind(device_address)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete readings")?
+            .rows_affected();
+        for table in DEVICE_TABLES {
+            sqlx::query(&format!("DELETE FROM {} WHERE device_address = ?", table))
+                .bind(device_address)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to delete from {}", table))?;
+        }
+        
+        tx.commit().await.context("Failed to commit device delete")?;
+        Ok(Some(readings))
+    }
+    
+    /// Give a device a name of its own (None or blank goes back to the detected name)
+    ///
+    /// Returns false when the device is unknown.
+    pub async fn set_device_alias(&self, device_address: &str, alias: Option<&str>) -> Result<bool> {
+        let alias = alias.map(str::trim).filter(|a| !a.is_empty());
+        let updated = sqlx::query("UPDATE devices SET alias = ? WHERE device_address = ?")
+            .bind(alias)
+            .bind(device_address)
+            .execute(&self.pool)
+            .await
+            .context("Failed to rename device")?
+            .rows_affected();
+        
+        Ok(updated > 0)
+    }
+    
+    pub async fn set_smoker_profile(&self, device_address: &str, profile: &SmokerProfile) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO smoker_profiles (device_address, efficiency, notes)
+            VALUES (?, ?, ?)
+            ON CONFLICT(device_address) DO UPDATE SET
+                efficiency = excluded.efficiency,
+                notes = excluded.notes
+            "#
+        )
+        .bind(device_address)
+        .bind(profile.efficiency)
+        .bind(&profile.notes)
+        .execute(&self.pool)
+        .await
+        .context("Failed to update smoker profile")?;
+        
+        Ok(())
+    }
+    
+    /// Add a maintenance note to a device
+    pub async fn insert_device_note(
+        &self,
+        device_address: &str,
+        timestamp: DateTime<Utc>,
+        category: NoteCategory,
+        text: &str,
+    ) -> Result<DeviceNote> {
+        let id = sqlx::query(
+            "INSERT INTO device_notes (device_address, timestamp, category, text) VALUES (?, ?, ?, ?)"
+        )
+        .bind(device_address)
+        .bind(timestamp)
+        .bind(category)
+        .bind(text)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert device note")?
+        .last_insert_rowid();
+        
+        Ok(DeviceNote {
+            id,
+            device_address: device_address.to_string(),
+            timestamp,
+            category,
+            text: text.to_string(),
+        })
+    }
+    
+    /// Notes for a device, newest first, optionally only one category
+    pub async fn get_device_notes(
+        &self,
+        device_address: &str,
+        category: Option<NoteCategory>,
+    ) -> Result<Vec<DeviceNote>> {
+        let notes = sqlx::query_as::<_, DeviceNote>(
+            r#"
+            SELECT id, device_address, timestamp, category, text
+            FROM device_notes
+            WHERE device_address = ? AND (? IS NULL OR category = ?)
+            ORDER BY timestamp DESC, id DESC
+            "#
+        )
+        .bind(device_address)
+        .bind(category)
+        .bind(category)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch device notes")?;
+        
+        Ok(notes)
+    }
+    
+    /// Replace a note's category and text, and its date when given; None if the device has no such note
+    pub async fn update_device_note(
+        &self,
+        device_address: &str,
+        id: i64,
+        timestamp: Option<DateTime<Utc>>,
+        category: NoteCategory,
+        text: &str,
+    ) -> Result<Option<DeviceNote>> {
+        let note = sqlx::query_as::<_, DeviceNote>(
+            r#"
+            UPDATE device_notes SET timestamp = COALESCE(?, timestamp), category = ?, text = ?
+            WHERE id = ? AND device_address = ?
+            RETURNING id, device_address, timestamp, category, text
+            "#
+        )
+        .bind(timestamp)
+        .bind(category)
+        .bind(text)
+        .bind(id)
+        .bind(device_address)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to update device note")?;
+        
+        Ok(note)
+    }
+    
+    /// Delete a note; false if the device has no such note
+    pub async fn delete_device_note(&self, device_address: &str, id: i64) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM device_notes WHERE id = ? AND device_address = ?")
+            .bind(id)
+            .bind(device_address)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete device note")?
+            .rows_affected();
+        
+        Ok(deleted > 0)
+    }
+    
+    /// When the most recent note of a category was dated (e.g. the last battery change)
+    pub async fn latest_note_time(
+        &self,
+        device_address: &str,
+        category: NoteCategory,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let timestamp = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+            "SELECT MAX(timestamp) FROM device_notes WHERE device_address = ? AND category = ?"
+        )
+        .bind(device_address)
+        .bind(category)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to fetch latest device note")?;
+        
+        Ok(timestamp)
+    }
+    
+    /// Record that a device was offered its defaults; false when it was before
+    ///
+    /// Its default rules are recorded separately by `record_alert_rules_created`.
+    pub async fn record_alert_bootstrap(&self, device_address: &str) -> Result<bool> {
+        let inserted = sqlx::query("INSERT OR IGNORE INTO alert_bootstraps (device_address, bootstrapped_at) VALUES (?, ?)")
+            .bind(device_address)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .context("Failed to record alert bootstrap")?
+            .rows_affected();
+        
+        Ok(inserted > 0)
+    }
+    
+    /// Whether a bootstrapped device is still waiting for its default rules (seen without Premium)
+    pub async fn alert_rules_pending(&self, device_address: &str) -> Result<bool> {
+        let pending = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM alert_bootstraps WHERE device_address = ? AND rules_created_at IS NULL)"
+        )
+        .bind(device_address)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to check pending default alerts")?;
+        
+        Ok(pending)
+    }
+    
+    /// Record that a device's default rules were stored, so they are never created twice
+    pub async fn record_alert_rules_created(&self, device_address: &str) -> Result<()> {
+        sqlx::query("UPDATE alert_bootstraps SET rules_created_at = ? WHERE device_address = ?")
+            .bind(Utc::now())
+            .bind(device_address)
+            .execute(&self.pool)
+            .await
+            .context("Failed to record default alert rules")?;
+        
+        Ok(())
+    }
+    
+    /// Store a new alert rule; the returned rule carries its id
+    pub async fn insert_alert_rule(
+        &self,
+        device_address: &str,
+        sensor_index: usize,
+        kind: AlertKind,
+        hysteresis: f32,
+        carryover: f32,
+    ) -> Result<AlertRule> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO alert_rules (device_address, sensor_index, kind, threshold, hysteresis, carryover)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(device_address)
+        .bind(sensor_index as i64)
+        .bind(kind.name().as_str())
+        .bind(kind.stored_value())
+        .bind(hysteresis)
+        .bind(carryover)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert alert rule")?
+        .last_insert_rowid();
+        
+        Ok(AlertRule {
+            id,
+            device_address: device_address.to_string(),
+            sensor_index,
+            kind,
+            hysteresis,
+            carryover,
+        })
+    }
+    
+    /// All alert rules, oldest first (rows with an unknown kind are skipped)
+    pub async fn get_alert_rules(&self) -> Result<Vec<AlertRule>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!", device_address, sensor_index, kind,
+                   threshold AS "threshold: f32", hysteresis AS "hysteresis: f32", carryover AS "carryover: f32"
+            FROM alert_rules
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch alert rules")?;
+        
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(AlertRule {
+                    id: row.id,
+                    device_address: row.device_address,
+                    sensor_index: row.sensor_index as usize,
+                    kind: AlertKind::from_stored(AlertKindName::parse(&row.kind)?, row.threshold),
+                    hysteresis: row.hysteresis,
+                    carryover: row.carryover,
+                })
+            })
+            .collect())
+    }
+    
+    /// Remove an alert rule; returns false when there is no such rule
+    pub async fn delete_alert_rule(&self, id: i64) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM alert_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete alert rule")?
+            .rows_affected();
+        
+        Ok(deleted > 0)
+    }
+    
+    /// Last successfully synced timestamp for a sync direction (e.g. "to_cloud")
+    pub async fn get_sync_cursor(&self, direction: &str) -> Result<Option<DateTime<Utc>>> {
+        let cursor = sqlx::query_scalar!(
+            r#"SELECT last_synced AS "last_synced: DateTime<Utc>" FROM sync_state WHERE direction = ?"#,
+            direction
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch sync cursor")?;
+        
+        Ok(cursor)
+    }
+    
+    /// A stored setting, None if it was never set
+    pub async fn get_setting(&self, key: &str) -> Result<Option<Setting>> {
+        sqlx::query_as!(
+            Setting,
+            r#"SELECT value, updated_at AS "updated_at: DateTime<Utc>" FROM settings WHERE key = ?"#,
+            key
+        )
+        .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to read setting {}", key))
+    }
+    
+    /// Store a setting, replacing any earlier value
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO settings (key, value, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+            key,
+            value,
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Failed to store setting {}", key))?;
+        
+        Ok(())
+    }
+    
+    /// Readings of all devices recorded since `since`, oldest first, for upload
+    ///
+    /// Readings that came down from the cloud are left out, so they are never pushed back up.
+    pub async fn get_outbound_readings_since(&self, since: DateTime<Utc>) -> Result<Vec<OutboundReading>> {
+        let since = self.timestamp_precision.truncate(since);
+        let rows = sqlx::query_as!(
+            OutboundRow,
+            r#"
+            SELECT r.id AS "id!", d.device_name, r.device_address, r.timestamp AS "timestamp: DateTime<Utc>",
+                   r.sensor_index, r.temperature AS "temperature: Temperature",
+                   r.ambient_temp AS "ambient_temp: Temperature", r.battery_level AS "battery_level: u8",
+                   r.signal_strength AS "signal_strength: i16", r.reading_mode AS "reading_mode: ReadingMode",
+                   r.source AS "source: ReadingSource"
+            FROM readings r
+            JOIN devices d ON d.device_address = r.device_address
+            WHERE r.source <> 'cloud' AND r.timestamp >= ?
+            ORDER BY r.timestamp ASC, r.id ASC
+            "#,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch readings to sync")?;
+        
+        Ok(rows.into_iter().map(OutboundReading::from).collect())
+    }
+    
+    /// Oldest readings in the pending sync queue (never ones that came from the cloud)
+    pub async fn get_pending_sync(&self, limit: usize) -> Result<Vec<OutboundReading>> {
+        let limit = limit as i64;
+        let rows = sqlx::query_as!(
+            OutboundRow,
+            r#"
+            SELECT r.id AS "id!", d.device_name, r.device_address, r.timestamp AS "timestamp: DateTime<Utc>",
+                   r.sensor_index, r.temperature AS "temperature: Temperature",
+                   r.ambient_temp AS "ambient_temp: Temperature", r.battery_level AS "battery_level: u8",
+                   r.signal_strength AS "signal_strength: i16", r.reading_mode AS "reading_mode: ReadingMode",
+                   r.source AS "source: ReadingSource"
+            FROM readings r
+            JOIN devices d ON d.device_address = r.device_address
+            JOIN pending_sync p ON p.reading_id = r.id
+            WHERE r.source <> 'cloud'
+            ORDER BY r.timestamp ASC, r.id ASC
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch pending sync queue")?;
+        
+        Ok(rows.into_iter().map(OutboundReading::from).collect())
+    }
+    
+    /// Readings waiting in the pending sync queue
+    pub async fn count_pending_sync(&self) -> Result<u64> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM pending_sync")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count pending sync queue")?;
+        Ok(count as u64)
+    }
+    
+    /// Queue readings for a later upload attempt
+    ///
+    /// Keeps at most `max_len` entries (0 = unbounded) by dropping the oldest
+    /// readings; returns how many were dropped.
+    pub async fn enqueue_pending_sync(&self, reading_ids: &[i64], max_len: usize) -> Result<u64> {
+        let mut tx = self.pool.begin().await.context("Failed to start pending sync update")?;
+        let now = Utc::now();
+        for id in reading_ids {
+            sqlx::query("INSERT OR IGNORE INTO pending_sync (reading_id, queued_at) VALUES (?, ?)")
+                .bind(id)
+                .bind(now)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to queue reading for sync")?;
+        }
+        
+        let dropped = if max_len > 0 {
+            sqlx::query(
+                r#"
+                DELETE FROM pending_sync WHERE reading_id IN (
+                    SELECT reading_id FROM pending_sync ORDER BY reading_id DESC LIMIT -1 OFFSET ?
+                )
+                "#
+            )
+            .bind(max_len as i64)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to trim pending sync queue")?
+            .rows_affected()
+        } else {
+            0
+        };
+        
+        tx.commit().await.context("Failed to commit pending sync update")?;
+        Ok(dropped)
+    }
+    
+    /// Remove uploaded readings from the pending sync queue
+    pub async fn clear_pending_sync(&self, reading_ids: &[i64]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start pending sync update")?;
+        for id in reading_ids {
+            sqlx::query("DELETE FROM pending_sync WHERE reading_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to clear synced reading")?;
+        }
+        tx.commit().await.context("Failed to commit pending sync update")?;
+        Ok(())
+    }
+    
+    /// Persist the sync cursor for a direction; call only after the batch is confirmed written
+    pub async fn set_sync_cursor(&self, direction: &str, last_synced: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_state (direction, last_synced)
+            VALUES (?, ?)
+            ON CONFLICT(direction) DO UPDATE SET last_synced = excluded.last_synced
+            "#
+        )
+        .bind(direction)
+        .bind(last_synced)
+        .execute(&self.pool)
+        .await
+        .context("Failed to update sync cursor")?;
+        
+        Ok(())
+    }
+}
+
+/// A stored reading on its way to the cloud
+#[derive(Debug, Clone)]
+pub struct OutboundReading {
+    /// Row id, the key of the pending sync queue
+    pub id: i64,
+    pub device_name: String,
+    pub reading: ReadingRecord,
+}
+
+/// Flat row of `OutboundReading`, as `query_as!` fills it
+struct OutboundRow {
+    id: i64,
+    device_name: String,
+    device_address: String,
+    timestamp: DateTime<Utc>,
+    sensor_index: i64,
+    temperature: Temperature,
+    ambient_temp: Option<Temperature>,
+    battery_level: Option<u8>,
+    signal_strength: i16,
+    reading_mode: ReadingMode,
+    source: ReadingSource,
+}
+
+impl From<OutboundRow> for OutboundReading {
+    fn from(row: OutboundRow) -> Self {
+        Self {
+            id: row.id,
+            device_name: row.device_name,
+            reading: ReadingRecord {
+                device_address: row.device_address,
+                timestamp: row.timestamp,
+                sensor_index: row.sensor_index,
+                temperature: row.temperature,
+                ambient_temp: row.ambient_temp,
+                battery_level: row.battery_level,
+                signal_strength: row.signal_strength,
+                reading_mode: row.reading_mode,
+                source: row.source,
+            },
+        }
+    }
+}
+
+/// A value from the `settings` table
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct Setting {
+    pub value: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Tables keyed by device besides `readings`, in the order `delete_device` empties them (`devices` last)
+const DEVICE_TABLES: &[&str] = &[
+    "predictions",
+    "cook_sessions",
+    "frame_stats",
+    "device_settings",
+    "smoker_profiles",
+    "alert_rules",
+    "device_notes",
+    "alert_bootstraps",
+    "devices",
+];
+
+/// Longest the free tier keeps readings outside cook sessions, in days
+pub const FREE_IDLE_RETENTION_DAYS: u32 = 7;
+
+/// Longest the free tier keeps cook session readings, in days
+pub const FREE_SESSION_RETENTION_DAYS: u32 = 30;
+
+/// Where the retention in effect comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionSource {
+    /// `database.retention_days` as configured
+    Configured,
+    /// `database.retention_days` asks for more than the free tier keeps
+    FreeTierCap,
+}
+
+/// How long readings are kept; 0 disables a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Readings inside a cook session are kept at full resolution for this many days
+    pub session_days: u32,
+    /// Readings outside sessions keep full resolution for this many hours, then get decimated
+    pub idle_full_resolution_hours: u32,
+    /// Decimated readings keep one value per sensor per this many seconds
+    pub decimate_interval_secs: u32,
+    /// Readings outside sessions are deleted after this many days
+    pub idle_days: u32,
+}
+
+impl RetentionPolicy {
+    /// Free tier: a week of idle history, cooks kept for a month
+    pub fn free() -> Self {
+        Self {
+            session_days: 30,
+            idle_full_resolution_hours: 24,
+            decimate_interval_secs: 300,
+            idle_days: 7,
+        }
+    }
+    
+    /// Premium tier (unlimited history): nothing is thinned or deleted
+    pub fn unlimited() -> Self {
+        Self {
+            session_days: 0,
+            idle_full_resolution_hours: 0,
+            decimate_interval_secs: 300,
+            idle_days: 0,
+        }
+    }
+    
+    /// Policy for `database.retention_days` (0 = forever)
+    ///
+    /// With unlimited history the configured days apply to every reading. The
+    /// free tier keeps at most `FREE_IDLE_RETENTION_DAYS` of idle readings and
+    /// `FREE_SESSION_RETENTION_DAYS` of cook readings, so a longer setting (or
+    /// forever) is cut down to those while a shorter one is honored.
+    pub fn from_config(retention_days: u32, unlimited_history: bool) -> (Self, RetentionSource) {
+        if unlimited_history {
+            let policy = Self { session_days: retention_days, idle_days: retention_days, ..Self::unlimited() };
+            return (policy, RetentionSource::Configured);
+        }
+        let cap = |limit: u32| if retention_days == 0 { limit } else { retention_days.min(limit) };
+        let policy = Self {
+            session_days: cap(FREE_SESSION_RETENTION_DAYS),
+            idle_days: cap(FREE_IDLE_RETENTION_DAYS),
+            ..Self::free()
+        };
+        let source = if retention_days == 0 || retention_days > FREE_IDLE_RETENTION_DAYS {
+            RetentionSource::FreeTierCap
+        } else {
+            RetentionSource::Configured
+        };
+        (policy, source)
+    }
+}
+
+impl std::fmt::Display for RetentionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days = |days: u32| if days == 0 { "forever".to_string() } else { format!("{} days", days) };
+        write!(f, "idle readings kept {}, cook readings {}", days(self.idle_days), days(self.session_days))
+    }
+}
+
+/// Database size before and after a vacuum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VacuumReport {
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+}
+
+/// Rows removed by `cleanup_old_readings`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    /// Idle readings dropped while thinning to the decimation interval
+    pub decimated: u64,
+    /// Readings deleted for being past retention
+    pub deleted: u64,
+}
+
 /// Device record from database
 #[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
-pub struct DeviceRecord {
+pub struct DeviceRecord {
+    pub device_address: String,
+    pub device_name: String,
+    pub brand: String,
+    pub model: String,
+    pub sensor_count: i64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Factory serial number (Combustion advertisement or Device Information Service)
+    pub serial_number: Option<String>,
+    /// Probe ID, 1-8 (Combustion probes)
+    pub probe_id: Option<i64>,
+    /// Probe color name (Combustion probes)
+    pub probe_color: Option<String>,
+    /// Firmware version reported over the UART or the Device Information Service
+    pub firmware_version: Option<String>,
+    /// Hardware revision from the Device Information Service
+    pub hardware_revision: Option<String>,
+    /// Manufacturer name from the Device Information Service
+    pub manufacturer: Option<String>,
+    /// Name the user gave the device
+    pub alias: Option<String>,
+    /// When a sensor first went above the probe's rated internal maximum (None = never, or cleared)
+    pub damage_risk_at: Option<DateTime<Utc>>,
+    /// Hottest reading above that maximum since then
+    pub damage_risk_peak: Option<Temperature>,
+    /// Target set on the device itself (see `Database::set_target`)
+    pub target_temp: Option<Temperature>,
+    /// Sensor `target_temp` applies to
+    pub target_sensor_index: Option<i64>,
+}
+
+/// Target temperature kept on a device, independent of cook sessions
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct DeviceTarget {
+    pub sensor_index: usize,
+    pub temperature: Temperature,
+}
+
+/// What a device's stored readings say about it
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ObservedReadings {
+    pub device_address: String,
+    pub max_sensor_index: i64,
+    pub last_reading: DateTime<Utc>,
+}
+
+impl DeviceRecord {
+    /// The user's name for the device, else "Probe 2 (Red)" for probes that
+    /// reported an ID and color, otherwise the BLE name
+    pub fn display_name(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        let color = self.probe_color.as_deref().and_then(ProbeColor::parse);
+        match (self.probe_id, color) {
+            (Some(id), Some(color)) => probe_label(id as u8, color),
+            _ => self.device_name.clone(),
+        }
+    }
+}
+
+/// Cook session record from database
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct CookSession {
+    pub id: i64,
     pub device_address: String,
-    pub device_name: String,
-    pub brand: String,
-    pub model: String,
-    pub sensor_count: i64,
-    pub first_seen: DateTime<Utc>,
-    pub last_seen: DateTime<Utc>,
+    pub name: String,
+    pub target_temp: Option<Temperature>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// Internal-temperature stats for a cook session
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CookStats {
+    #[serde(flatten)]
+    pub session: CookSession,
+    pub reading_count: i64,
+    pub min_temp: Option<Temperature>,
+    pub max_temp: Option<Temperature>,
+    pub avg_temp: Option<Temperature>,
+    pub duration_secs: i64,
+    /// The wall clock was reset during the session, so `duration_secs` comes from the monotonic clock
+    pub duration_estimated: bool,
+    /// Seconds from start until the internal temp first reached the target
+    pub time_to_target_secs: Option<i64>,
+    /// Seconds the primary sensor spent in the 40–140°F danger zone
+    pub danger_zone_secs: i64,
+    /// Relative fuel use (None without ambient readings)
+    pub fuel: Option<FuelEstimate>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CookStatsRow {
+    id: i64,
+    device_address: String,
+    name: String,
+    target_temp: Option<Temperature>,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    start_boot_id: Option<String>,
+    start_uptime_ms: Option<i64>,
+    end_boot_id: Option<String>,
+    end_uptime_ms: Option<i64>,
+    pit_setpoint: Option<Temperature>,
+    reading_count: i64,
+    min_temp: Option<f64>,
+    max_temp: Option<f64>,
+    avg_temp: Option<f64>,
+    target_reached_at: Option<DateTime<Utc>>,
+    primary_sensor_index: i64,
+}
+
+/// Temperature stats of a device over a time range (see `Database::get_device_stats`)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeviceStats {
+    pub device_address: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Primary-sensor readings in the range
+    pub reading_count: i64,
+    pub min_temp: Option<Temperature>,
+    pub max_temp: Option<Temperature>,
+    pub avg_temp: Option<Temperature>,
+    pub min_ambient: Option<Temperature>,
+    pub max_ambient: Option<Temperature>,
+    pub first_reading_at: Option<DateTime<Utc>>,
+    pub last_reading_at: Option<DateTime<Utc>>,
+    /// Seconds the primary sensor spent at or above the requested threshold (None without one)
+    pub secs_above: Option<i64>,
+}
+
+struct DeviceStatsRow {
+    reading_count: i64,
+    min_temp: Option<f64>,
+    max_temp: Option<f64>,
+    avg_temp: Option<f64>,
+    min_ambient: Option<f64>,
+    max_ambient: Option<f64>,
+    first_reading_at: Option<DateTime<Utc>>,
+    last_reading_at: Option<DateTime<Utc>>,
+    secs_above: Option<f64>,
+}
+
+/// Latest packet of a device (see `Database::get_latest_snapshot`)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LatestReading {
+    /// Headline sensor's reading, serialized flat so it reads like a `ReadingRecord`
+    #[serde(flatten)]
+    pub primary: ReadingRecord,
+    /// All sensors in that packet, ordered by sensor index
+    pub sensors: Vec<ReadingRecord>,
 }
 
 /// Reading record from database
@@ -363,9 +2331,1054 @@ pub struct ReadingRecord {
     pub device_address: String,
     pub timestamp: DateTime<Utc>,
     pub sensor_index: i64,
-    pub temperature: f32,
-    pub ambient_temp: Option<f32>,
+    pub temperature: Temperature,
+    pub ambient_temp: Option<Temperature>,
     pub battery_level: Option<u8>,
     pub signal_strength: i16,
+    /// Probe mode the reading was taken in
+    pub reading_mode: ReadingMode,
+    /// Ingestion path that stored the reading
+    pub source: ReadingSource,
+}
+
+/// Order of device lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceSort {
+    /// By name, A to Z
+    Name,
+    /// Most recently seen first
+    #[default]
+    LastSeen,
+    /// In the order they were discovered
+    FirstSeen,
+}
+
+impl DeviceSort {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::LastSeen => "last_seen",
+            Self::FirstSeen => "first_seen",
+        }
+    }
+}
+
+/// Where a stored reading came from, for telling sync and merge results apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReadingSource {
+    /// Received from a probe over BLE (including one-shot reads)
+    #[default]
+    Ble,
+    /// Pulled down from the cloud by sync
+    Cloud,
+    /// Loaded from an exported file
+    Import,
+    /// Re-ingested from a recorded capture
+    Replay,
+    /// Produced by the built-in simulator
+    Simulator,
+}
+
+impl ReadingSource {
+    pub const ALL: [ReadingSource; 5] = [Self::Ble, Self::Cloud, Self::Import, Self::Replay, Self::Simulator];
+    
+    /// Stable name used for storage and query parameters
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ble => "ble",
+            Self::Cloud => "cloud",
+            Self::Import => "import",
+            Self::Replay => "replay",
+            Self::Simulator => "simulator",
+        }
+    }
+    
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|source| source.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+
+/// Readings must have paused this long before a scheduled vacuum runs
+pub const VACUUM_QUIET_PERIOD: Duration = Duration::from_secs(60);
+
+/// Vacuum every `period`, skipping runs while readings are being written
+pub async fn start_vacuum_task(db: Arc<Database>, period: Duration, mut shutdown: broadcast::Receiver<()>) {
+    let mut interval = tokio::time::interval(period);
+    // The first tick completes immediately; startup cleanup already had its chance
+    interval.tick().await;
+    
+    info!("Scheduled vacuum every {}h", period.as_secs() / 3600);
+    
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = db.vacuum_if_idle(VACUUM_QUIET_PERIOD).await {
+                    error!("Scheduled vacuum failed: {}", e);
+                }
+            }
+            _ = shutdown.recv() => {
+                info!("Shutting down scheduled vacuum task");
+                break;
+            }
+        }
+    }
+}
+
+/// One scheduled retention run: cleanup under the current license, then an incremental vacuum
+pub async fn run_retention_cleanup(db: &Database, license: &LicenseManager, retention_days: u32) -> Result<CleanupReport> {
+    let (policy, _) = RetentionPolicy::from_config(retention_days, license.current().features.unlimited_history);
+    let report = db.cleanup_old_readings(&policy).await?;
+    if report.decimated + report.deleted > 0 {
+        let freed = db.incremental_vacuum().await?;
+        info!("🧹 Retention cleanup freed {} KiB", freed / 1024);
+    } else {
+        debug!("Retention cleanup: nothing past retention");
+    }
+    Ok(report)
+}
+
+/// Apply retention every `period` until shutdown
+///
+/// The license is read on each run, so activating Premium stops the free-tier
+/// thinning without a restart.
+pub async fn start_retention_task(
+    db: Arc<Database>,
+    license: Arc<LicenseManager>,
+    retention_days: u32,
+    period: Duration,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(period);
+    // The first tick completes immediately; the startup cleanup covers it
+    interval.tick().await;
+    
+    info!("Scheduled retention cleanup every {}h", period.as_secs() / 3600);
+    
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = run_retention_cleanup(&db, &license, retention_days).await {
+                    error!("Scheduled retention cleanup failed: {:#}", e);
+                }
+            }
+            _ = shutdown.recv() => {
+                info!("Shutting down retention cleanup task");
+                break;
+            }
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::{Migration, MigrationFuture};
+    use sqlx::sqlite::SqliteConnection;
+
+    #[tokio::test]
+    async fn test_sync_cursor_roundtrip() {
+        let db = Database::new(":memory:").await.unwrap();
+        assert_eq!(db.get_sync_cursor("to_cloud").await.unwrap(), None);
+
+        let first = Utc::now() - chrono::Duration::minutes(10);
+        let second = Utc::now();
+        db.set_sync_cursor("to_cloud", first).await.unwrap();
+        db.set_sync_cursor("to_cloud", second).await.unwrap();
+
+        assert_eq!(db.get_sync_cursor("to_cloud").await.unwrap(), Some(second));
+        assert_eq!(db.get_sync_cursor("from_cloud").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pending_sync_queue_is_bounded() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "Brisket", "Unknown", "probe", 1).await.unwrap();
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        for i in 0..5 {
+            db.insert_reading("AA", start + chrono::Duration::minutes(i), 0, Temperature::from_celsius(90.0), None, None, -60)
+                .await
+                .unwrap();
+        }
+        let readings = db.get_outbound_readings_since(start).await.unwrap();
+        assert_eq!(readings.len(), 5);
+        assert_eq!(readings[0].device_name, "Brisket");
+        let ids: Vec<i64> = readings.iter().map(|r| r.id).collect();
+
+        // Re-queueing is a no-op; the oldest are dropped past the limit
+        assert_eq!(db.enqueue_pending_sync(&ids[..2], 3).await.unwrap(), 0);
+        assert_eq!(db.enqueue_pending_sync(&ids, 3).await.unwrap(), 2);
+        let pending: Vec<i64> = db.get_pending_sync(10).await.unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(pending, ids[2..]);
+        assert_eq!(db.get_pending_sync(1).await.unwrap()[0].reading.timestamp, readings[2].reading.timestamp);
+
+        db.clear_pending_sync(&ids[2..4]).await.unwrap();
+        assert_eq!(db.count_pending_sync().await.unwrap(), 1);
+        assert_eq!(db.enqueue_pending_sync(&ids, 0).await.unwrap(), 0, "0 keeps everything");
+        assert_eq!(db.count_pending_sync().await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_cloud_readings_are_never_uploaded() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "Brisket", "Unknown", "probe", 2).await.unwrap();
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let at = |minute| start + chrono::Duration::minutes(minute);
+        db.insert_reading("AA", at(1), 0, Temperature::from_celsius(90.0), None, None, -60).await.unwrap();
+        db.insert_reading_from(ReadingSource::Cloud, "AA", at(2), 1, Temperature::from_celsius(91.0), None, None, 0)
+            .await
+            .unwrap();
+        db.insert_reading_from(ReadingSource::Simulator, "AA", at(3), 0, Temperature::from_celsius(92.0), None, None, 0)
+            .await
+            .unwrap();
+
+        let outbound = db.get_outbound_readings_since(start).await.unwrap();
+        let sources: Vec<ReadingSource> = outbound.iter().map(|r| r.reading.source).collect();
+        assert_eq!(sources, [ReadingSource::Ble, ReadingSource::Simulator]);
+
+        // Not even when a cloud row ends up queued
+        let all: Vec<i64> = sqlx::query_scalar("SELECT id FROM readings").fetch_all(&db.pool).await.unwrap();
+        db.enqueue_pending_sync(&all, 0).await.unwrap();
+        let pending: Vec<ReadingSource> = db.get_pending_sync(10).await.unwrap().iter().map(|r| r.reading.source).collect();
+        assert_eq!(pending, [ReadingSource::Ble, ReadingSource::Simulator]);
+    }
+
+    #[tokio::test]
+    async fn test_reading_mode_column_added_to_old_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.db");
+        std::fs::File::create(&path).unwrap();
+        let path = path.to_str().unwrap();
+
+        // Readings table as created by schema version 1
+        {
+            let pool = SqlitePool::connect(&format!("sqlite:{}", path)).await.unwrap();
+            sqlx::query(
+                "CREATE TABLE readings (id INTEGER PRIMARY KEY AUTOINCREMENT, device_address TEXT NOT NULL, \
+                 timestamp DATETIME NOT NULL, sensor_index INTEGER NOT NULL, temperature REAL NOT NULL, \
+                 ambient_temp REAL, battery_level INTEGER, signal_strength INTEGER NOT NULL)"
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            pool.close().await;
+        }
+
+        let db = Database::new(path).await.unwrap();
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        db.insert_reading("AA", Utc::now() - chrono::Duration::seconds(1), 0, Temperature::from_celsius(150.0), None, None, -60).await.unwrap();
+        db.insert_reading_with_mode("AA", Utc::now(), 0, Temperature::from_celsius(72.0), None, None, -60, ReadingMode::InstantRead)
+            .await
+            .unwrap();
+
+        let readings = db.get_device_readings("AA", 0).await.unwrap();
+        assert_eq!(readings[0].reading_mode, ReadingMode::InstantRead);
+        assert_eq!(readings[1].reading_mode, ReadingMode::Normal);
+
+        // Reopening does not try to add the column again
+        drop(db);
+        Database::new(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_keeps_sessions_and_decimates_idle_readings() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        // Whole 5-minute buckets so decimation results are exact
+        let aligned = |t: DateTime<Utc>| DateTime::from_timestamp(t.timestamp() / 300 * 300, 0).unwrap();
+        let now = Utc::now();
+        let minutes = |base: DateTime<Utc>, count: i64| -> Vec<DateTime<Utc>> {
+            (0..count).map(|m| aligned(base) + chrono::Duration::minutes(m)).collect()
+        };
+
+        let expired_cook = minutes(now - chrono::Duration::days(40), 10);
+        let kept_cook = minutes(now - chrono::Duration::days(10), 30);
+        let expired_idle = minutes(now - chrono::Duration::days(9), 10);
+        let decimated_idle = minutes(now - chrono::Duration::days(3), 20);
+        let open_cook = minutes(now - chrono::Duration::days(2), 10);
+        let recent_idle = minutes(now - chrono::Duration::hours(2), 10);
+
+        for (range, open) in [(&expired_cook, false), (&kept_cook, false), (&open_cook, true)] {
+            let session = db.start_cook("AA", "Cook", None).await.unwrap();
+            if !open {
+                db.end_cook("AA").await.unwrap();
+            }
+            sqlx::query("UPDATE cook_sessions SET started_at = ?, ended_at = CASE WHEN ended_at IS NULL THEN NULL ELSE ? END WHERE id = ?")
+                .bind(range[0])
+                .bind(*range.last().unwrap())
+                .bind(session.id)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+        for range in [&expired_cook, &kept_cook, &expired_idle, &decimated_idle, &open_cook, &recent_idle] {
+            for &timestamp in range.iter() {
+                db.insert_reading("AA", timestamp, 0, Temperature::from_celsius(100.0), None, None, -60).await.unwrap();
+            }
+        }
+
+        let report = db.cleanup_old_readings(&RetentionPolicy::free()).await.unwrap();
+        // 20 idle minutes become one reading per 5-minute bucket
+        assert_eq!(report.decimated, 16 + 8);
+        assert_eq!(report.deleted, 2 + 10);
+
+        let remaining: Vec<DateTime<Utc>> = db
+            .get_device_readings("AA", 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.timestamp)
+            .rev()
+            .collect();
+        let expected: Vec<DateTime<Utc>> = kept_cook
+            .iter()
+            .chain(decimated_idle.iter().step_by(5))
+            .chain(open_cook.iter())
+            .chain(recent_idle.iter())
+            .copied()
+            .collect();
+        assert_eq!(remaining, expected);
+
+        // Premium keeps everything; the cook sessions survive either way
+        let report = db.cleanup_old_readings(&RetentionPolicy::unlimited()).await.unwrap();
+        assert_eq!(report, CleanupReport::default());
+        assert_eq!(db.get_cooks_in_range("AA", now - chrono::Duration::days(60), now).await.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_retention_from_config() {
+        let days = |(policy, source): (RetentionPolicy, RetentionSource)| (policy.idle_days, policy.session_days, source);
+
+        // Free tier: asking for more than it keeps (or forever) is capped
+        assert_eq!(days(RetentionPolicy::from_config(30, false)), (7, 30, RetentionSource::FreeTierCap));
+        assert_eq!(days(RetentionPolicy::from_config(0, false)), (7, 30, RetentionSource::FreeTierCap));
+        assert_eq!(RetentionPolicy::from_config(0, false).0, RetentionPolicy::free());
+        // ...while asking for less is honored, cooks included
+        assert_eq!(days(RetentionPolicy::from_config(3, false)), (3, 3, RetentionSource::Configured));
+        assert_eq!(RetentionPolicy::from_config(3, false).0.idle_full_resolution_hours, 24);
+
+        // Premium: any value, 0 keeping everything at full resolution
+        assert_eq!(days(RetentionPolicy::from_config(90, true)), (90, 90, RetentionSource::Configured));
+        assert_eq!(RetentionPolicy::from_config(90, true).0.idle_full_resolution_hours, 0);
+        assert_eq!(RetentionPolicy::from_config(0, true).0, RetentionPolicy::unlimited());
+
+        assert_eq!(
+            RetentionPolicy::from_config(3, false).0.to_string(),
+            "idle readings kept 3 days, cook readings 3 days"
+        );
+        assert_eq!(RetentionPolicy::unlimited().to_string(), "idle readings kept forever, cook readings forever");
+    }
+
+    #[tokio::test]
+    async fn test_delete_device_removes_everything_stored_for_it() {
+        let db = Database::new(":memory:").await.unwrap();
+        let now = Utc::now();
+        for address in ["AA", "BB"] {
+            db.upsert_device(address, "probe", "Unknown", "probe", 2).await.unwrap();
+            for minute in 0..5 {
+                let timestamp = now - chrono::Duration::minutes(minute);
+                db.insert_reading(address, timestamp, 0, Temperature::from_celsius(60.0), None, None, -60).await.unwrap();
+            }
+            db.start_cook(address, "Ribs", None).await.unwrap();
+            db.end_cook(address).await.unwrap();
+            db.set_primary_sensor(address, Some(1)).await.unwrap();
+            db.set_smoker_profile(address, &SmokerProfile::default()).await.unwrap();
+            db.insert_device_note(address, now, NoteCategory::Calibration, "ice bath").await.unwrap();
+            db.insert_alert_rule(address, 0, AlertKind::BatteryBelow { percent: 20 }, 0.0, 0.0).await.unwrap();
+            db.record_alert_bootstrap(address).await.unwrap();
+            db.record_rejected_frame(address, "too_short").await.unwrap();
+        }
+        let reading_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM readings").fetch_all(&db.pool).await.unwrap();
+        db.enqueue_pending_sync(&reading_ids, 0).await.unwrap();
+
+        assert_eq!(db.delete_device("AA").await.unwrap(), Some(5));
+        assert_eq!(db.delete_device("AA").await.unwrap(), None, "already gone");
+
+        let tables = ["readings"].iter().chain(DEVICE_TABLES);
+        for table in tables {
+            let left: Vec<String> = sqlx::query_scalar(&format!("SELECT DISTINCT device_address FROM {}", table))
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+            assert!(!left.contains(&"AA".to_string()), "{} still has AA rows", table);
+        }
+        let pending = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM pending_sync").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(pending, 5, "only BB's uploads stay queued");
+        let devices: Vec<String> = db.get_all_devices().await.unwrap().into_iter().map(|d| d.device_address).collect();
+        assert_eq!(devices, ["BB"]);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_honors_premium_retention() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        let now = Utc::now();
+        for age_days in [1, 20, 100] {
+            let timestamp = now - chrono::Duration::days(age_days);
+            db.insert_reading("AA", timestamp, 0, Temperature::from_celsius(100.0), None, None, -60).await.unwrap();
+        }
+
+        let (policy, _) = RetentionPolicy::from_config(30, true);
+        assert_eq!(db.cleanup_old_readings(&policy).await.unwrap().deleted, 1);
+        assert_eq!(db.get_device_readings("AA", 0).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_shrinks_file_and_keeps_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bbq.db");
+        std::fs::File::create(&path).unwrap();
+        let db = Database::new(path.to_str().unwrap()).await.unwrap();
+
+        let mode = sqlx::query_scalar::<_, i64>("PRAGMA auto_vacuum").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(mode, 2, "new databases use incremental auto_vacuum");
+
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        let start = Utc::now() - chrono::Duration::hours(1);
+        for i in 0..2000 {
+            db.insert_reading("AA", start + chrono::Duration::seconds(i), 0, Temperature::from_celsius(100.0), None, None, -60)
+                .await
+                .unwrap();
+        }
+        sqlx::query("DELETE FROM readings WHERE id > 10").execute(&db.pool).await.unwrap();
+
+        // Readings were just written
+        assert!(db.vacuum_if_idle(Duration::from_secs(60)).await.unwrap().is_none());
+
+        let report = db.vacuum().await.unwrap();
+        assert!(report.bytes_after < report.bytes_before, "{:?}", report);
+        assert_eq!(db.get_device_readings("AA", 0).await.unwrap().len(), 10);
+        assert!(db.vacuum_if_idle(Duration::ZERO).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_retention_cleanup() {
+        use crate::premium::{License, LicenseValidator};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bbq.db");
+        std::fs::File::create(&path).unwrap();
+        let db = Arc::new(Database::new(path.to_str().unwrap()).await.unwrap());
+        let license = Arc::new(LicenseManager::with_license(License::free(), LicenseValidator::new(), db.clone()));
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+
+        let (shutdown, shutdown_rx) = broadcast::channel(1);
+        let task = tokio::spawn(start_retention_task(db.clone(), license, 0, Duration::from_millis(20), shutdown_rx));
+
+        // Written after startup: only the schedule can remove them
+        let old = Utc::now() - chrono::Duration::days(10);
+        for i in 0..2000 {
+            db.insert_reading("AA", old + chrono::Duration::seconds(i), 0, Temperature::from_celsius(100.0), None, None, -60)
+                .await
+                .unwrap();
+        }
+        db.insert_reading("AA", Utc::now(), 0, Temperature::from_celsius(100.0), None, None, -60).await.unwrap();
+        let free_pages = || async { sqlx::query_scalar::<_, i64>("PRAGMA freelist_count").fetch_one(&db.pool).await.unwrap() };
+        for _ in 0..200 {
+            if db.get_device_readings("AA", 0).await.unwrap().len() == 1 && free_pages().await == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(db.get_device_readings("AA", 0).await.unwrap().len(), 1, "free tier drops idle readings past 7 days");
+        assert_eq!(free_pages().await, 0, "freed pages returned by the incremental vacuum");
+
+        shutdown.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+    }
+
+    async fn column_exists(db: &Database, table: &str, column: &str) -> bool {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap()
+            > 0
+    }
+
+    #[tokio::test]
+    async fn test_migrations_upgrade_unversioned_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("v0.db");
+        std::fs::File::create(&path).unwrap();
+        let path = path.to_str().unwrap();
+
+        // The original schema: no identity columns, no reading mode, no version at all
+        {
+            let pool = SqlitePool::connect(&format!("sqlite:{}", path)).await.unwrap();
+            for statement in [
+                "CREATE TABLE devices (device_address TEXT PRIMARY KEY, device_name TEXT NOT NULL, \
+                 brand TEXT NOT NULL, model TEXT NOT NULL, sensor_count INTEGER NOT NULL, \
+                 first_seen DATETIME NOT NULL, last_seen DATETIME NOT NULL)",
+                "CREATE TABLE readings (id INTEGER PRIMARY KEY AUTOINCREMENT, device_address TEXT NOT NULL, \
+                 timestamp DATETIME NOT NULL, sensor_index INTEGER NOT NULL, temperature REAL NOT NULL, \
+                 ambient_temp REAL, battery_level INTEGER, signal_strength INTEGER NOT NULL)",
+                "INSERT INTO devices VALUES ('AA', 'probe', 'Unknown', 'probe', 1, \
+                 '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z')",
+                "INSERT INTO readings (device_address, timestamp, sensor_index, temperature, signal_strength) \
+                 VALUES ('AA', '2025-01-01T00:00:00Z', 0, 212.0, -60)",
+            ] {
+                sqlx::query(statement).execute(&pool).await.unwrap();
+            }
+            pool.close().await;
+        }
+
+        for _ in 0..2 {
+            let db = Database::new(path).await.unwrap();
+            assert_eq!(db.schema_version().await.unwrap(), DB_SCHEMA_VERSION);
+            for (table, column) in [("devices", "serial_number"), ("devices", "firmware_version"), ("readings", "reading_mode"), ("readings", "source")] {
+                assert!(column_exists(&db, table, column).await, "{}.{}", table, column);
+            }
+            let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_version")
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+            assert_eq!(applied, DB_SCHEMA_VERSION as i64, "reopening applies nothing twice");
+            let reading = db.get_latest_reading("AA").await.unwrap();
+            assert!((reading.temperature.celsius() - 100.0).abs() < 1e-3);
+            assert_eq!(reading.source, ReadingSource::Ble, "existing rows count as BLE");
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_reading_source_recorded_per_path() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        let at = |secs: i64| Utc::now() - chrono::Duration::seconds(60 - secs);
+        let temperature = Temperature::from_celsius(60.0);
+        
+        db.insert_reading("AA", at(0), 0, temperature, None, None, -60).await.unwrap();
+        db.insert_reading_with_mode("AA", at(1), 0, temperature, None, None, -60, ReadingMode::InstantRead)
+            .await
+            .unwrap();
+        for (secs, source) in [(2, ReadingSource::Cloud), (3, ReadingSource::Import), (4, ReadingSource::Replay), (5, ReadingSource::Simulator)] {
+            db.insert_reading_from(source, "AA", at(secs), 0, temperature, None, None, -60).await.unwrap();
+        }
+        
+        let sources: Vec<ReadingSource> = db
+            .get_readings_since("AA", at(-1))
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.source)
+            .collect();
+        assert_eq!(sources, [ReadingSource::Ble, ReadingSource::Ble, ReadingSource::Cloud, ReadingSource::Import, ReadingSource::Replay, ReadingSource::Simulator]);
+        assert!(ReadingSource::ALL.iter().all(|source| ReadingSource::parse(source.as_str()) == Some(*source)));
+        assert_eq!(ReadingSource::parse(" Cloud"), Some(ReadingSource::Cloud));
+        assert_eq!(ReadingSource::parse("usb"), None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back_and_names_its_version() {
+        fn broken(conn: &mut SqliteConnection) -> MigrationFuture<'_> {
+            Box::pin(async move {
+                sqlx::query("CREATE TABLE half_done (id INTEGER)").execute(&mut *conn).await?;
+                sqlx::query("ALTER TABLE no_such_table ADD COLUMN x TEXT").execute(&mut *conn).await?;
+                Ok(())
+            })
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.db");
+        std::fs::File::create(&path).unwrap();
+        let db = Database::new(path.to_str().unwrap()).await.unwrap();
+        let mut migrations = MIGRATIONS.to_vec();
+        migrations.push(Migration { description: "broken", apply: broken });
+        let err = run_migrations(&db.pool, &migrations).await.unwrap_err();
+        let expected = format!("migration {} (broken)", DB_SCHEMA_VERSION + 1);
+        assert!(format!("{:#}", err).contains(&expected), "{:#}", err);
+
+        assert_eq!(db.schema_version().await.unwrap(), DB_SCHEMA_VERSION);
+        assert!(!column_exists(&db, "half_done", "id").await, "failed migration left a table behind");
+    }
+
+    #[tokio::test]
+    async fn test_fahrenheit_databases_converted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.db");
+        std::fs::File::create(&path).unwrap();
+        let path = path.to_str().unwrap();
+
+        // user_version 2, before migrations, stored raw Fahrenheit values
+        {
+            let db = Database::new(path).await.unwrap();
+            db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+            db.insert_reading("AA", Utc::now(), 0, Temperature::from_celsius(212.0), Some(Temperature::from_celsius(-40.0)), None, -60)
+                .await
+                .unwrap();
+            db.start_cook("AA", "Brisket", Some(Temperature::from_celsius(203.0))).await.unwrap();
+            sqlx::query("PRAGMA user_version = 2").execute(&db.pool).await.unwrap();
+            sqlx::query("DROP TABLE schema_version").execute(&db.pool).await.unwrap();
+        }
+
+        for _ in 0..2 {
+            let db = Database::new(path).await.unwrap();
+            let reading = db.get_latest_reading("AA").await.unwrap();
+            assert!((reading.temperature.celsius() - 100.0).abs() < 1e-3);
+            assert_eq!(reading.ambient_temp, Some(Temperature::from_celsius(-40.0)));
+            let cook = db.get_open_cook("AA").await.unwrap().unwrap();
+            assert!((cook.target_temp.unwrap().fahrenheit() - 203.0).abs() < 1e-3);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotating_mac_keeps_one_device_row() {
+        let db = Database::new(":memory:").await.unwrap();
+        let identity = ProbeIdentity {
+            serial_number: Some("10002A3B".to_string()),
+            probe_id: 2,
+            color: ProbeColor::Red,
+        };
+
+        let first = db
+            .upsert_device_with_identity("AA", "cA00probe", "MeatStickV", "cA00probe", 8, &identity)
+            .await
+            .unwrap();
+        assert_eq!(first, ("AA".to_string(), true));
+        // Same serial under a new MAC, now set to probe 3
+        let moved = ProbeIdentity { probe_id: 3, ..identity.clone() };
+        let second = db
+            .upsert_device_with_identity("BB", "cA00probe", "MeatStickV", "cA00probe", 8, &moved)
+            .await
+            .unwrap();
+        assert_eq!(second, ("AA".to_string(), false), "a moved probe is not new");
+
+        let devices = db.get_all_devices().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].serial_number.as_deref(), Some("10002A3B"));
+        assert_eq!(devices[0].display_name(), "Probe 3 (Red)");
+        db.set_firmware_version("AA", "1.4.2").await.unwrap();
+        assert_eq!(db.get_device("AA").await.unwrap().firmware_version.as_deref(), Some("1.4.2"));
+
+        // Without a serial the MAC is all there is to go on
+        let status_only = ProbeIdentity { serial_number: None, ..identity };
+        let third = db
+            .upsert_device_with_identity("CC", "cA00probe", "MeatStickV", "cA00probe", 8, &status_only)
+            .await
+            .unwrap();
+        assert_eq!(third, ("CC".to_string(), true));
+        assert_eq!(db.get_all_devices().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_device_order_is_stable_on_ties() {
+        let db = Database::new(":memory:").await.unwrap();
+        for (address, name) in [("CC", "beta"), ("AA", "Charlie"), ("BB", "alpha"), ("DD", "delta")] {
+            db.upsert_device(address, name, "MeaterPlus", "MEATER", 2).await.unwrap();
+        }
+        // Seen in the same scan cycle; DD was discovered first and seen last
+        let scan = Utc::now();
+        sqlx::query("UPDATE devices SET last_seen = ?, first_seen = ?")
+            .bind(scan)
+            .bind(scan - chrono::Duration::minutes(5))
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE devices SET last_seen = ?, first_seen = ? WHERE device_address = 'DD'")
+            .bind(scan + chrono::Duration::seconds(1))
+            .bind(scan - chrono::Duration::minutes(10))
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let order = |devices: Vec<DeviceRecord>| devices.into_iter().map(|d| d.device_address).collect::<Vec<_>>();
+        for _ in 0..3 {
+            assert_eq!(order(db.get_all_devices().await.unwrap()), ["DD", "AA", "BB", "CC"]);
+        }
+        assert_eq!(order(db.get_devices(None, DeviceSort::FirstSeen).await.unwrap()), ["DD", "AA", "BB", "CC"]);
+        assert_eq!(order(db.get_devices(None, DeviceSort::Name).await.unwrap()), ["BB", "CC", "AA", "DD"]);
+        assert_eq!(order(db.get_devices(Some("meaterplus"), DeviceSort::Name).await.unwrap()).len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_device_info_is_stored() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "TP25", "ThermoProTP25", "TP25", 4).await.unwrap();
+        db.upsert_device("BB", "TP25", "ThermoProTP25", "TP25", 4).await.unwrap();
+        let info = DeviceInfo {
+            serial_number: Some("123456".to_string()),
+            firmware_revision: Some("2.0.1".to_string()),
+            hardware_revision: Some("B".to_string()),
+            manufacturer: Some("ThermoPro".to_string()),
+        };
+        db.set_device_info("AA", &info).await.unwrap();
+        let device = db.get_device("AA").await.unwrap();
+        assert_eq!(device.serial_number.as_deref(), Some("123456"));
+        assert_eq!(device.firmware_version.as_deref(), Some("2.0.1"));
+        assert_eq!(device.hardware_revision.as_deref(), Some("B"));
+        assert_eq!(device.manufacturer.as_deref(), Some("ThermoPro"));
+
+        // Unreported fields keep their value; a serial another device holds is not copied
+        db.set_device_info("AA", &DeviceInfo { firmware_revision: Some("2.0.2".to_string()), ..Default::default() })
+            .await
+            .unwrap();
+        db.set_device_info("BB", &info).await.unwrap();
+        let device = db.get_device("AA").await.unwrap();
+        assert_eq!((device.firmware_version.as_deref(), device.manufacturer.as_deref()), (Some("2.0.2"), Some("ThermoPro")));
+        let clone = db.get_device("BB").await.unwrap();
+        assert_eq!((clone.serial_number, clone.hardware_revision.as_deref()), (None, Some("B")));
+    }
+
+    #[tokio::test]
+    async fn test_unsynced_readings_survive_long_downtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bbq.db");
+        std::fs::File::create(&path).unwrap();
+        let path = path.to_str().unwrap();
+
+        // Last upload confirmed 5h ago; two readings after it were still pending at exit
+        let synced = Utc::now() - chrono::Duration::hours(5);
+        {
+            let db = Database::new(path).await.unwrap();
+            db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+            db.insert_reading("AA", synced - chrono::Duration::minutes(1), 0, Temperature::from_celsius(140.0), None, None, -60).await.unwrap();
+            db.insert_reading("AA", synced, 0, Temperature::from_celsius(141.0), None, None, -60).await.unwrap();
+            db.set_sync_cursor("to_cloud", synced).await.unwrap();
+            db.insert_reading("AA", synced + chrono::Duration::minutes(1), 0, Temperature::from_celsius(142.0), None, None, -60).await.unwrap();
+            db.insert_reading("AA", synced + chrono::Duration::minutes(2), 0, Temperature::from_celsius(143.0), None, None, -60).await.unwrap();
+        }
+
+        // Restart 4h later: resume from the stored cursor, not a fixed lookback window
+        let db = Database::new(path).await.unwrap();
+        let since = db.get_sync_cursor("to_cloud").await.unwrap().unwrap();
+        assert_eq!(since, synced);
+        assert!(since < Utc::now() - chrono::Duration::hours(1));
+
+        let pending: Vec<f32> = db
+            .get_readings_since("AA", since)
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.temperature.celsius())
+            .collect();
+        // The cursor row itself is re-sent; cloud writes are keyed by timestamp
+        assert_eq!(pending, vec![141.0, 142.0, 143.0]);
+    }
+
+    #[tokio::test]
+    async fn test_seconds_precision_truncates_and_still_matches_ranges() {
+        use chrono::TimeZone;
+
+        let db = Database::new(":memory:")
+            .await
+            .unwrap()
+            .with_timestamp_precision(TimestampPrecision::Seconds);
+        let second = Utc.with_ymd_and_hms(2026, 1, 20, 12, 0, 5).unwrap();
+        let early = second + chrono::Duration::milliseconds(250);
+        let late = second + chrono::Duration::milliseconds(750);
+
+        db.upsert_device("AA", "probe", "Unknown", "probe", 1).await.unwrap();
+        db.insert_reading("AA", early, 0, Temperature::from_celsius(150.0), None, None, -60).await.unwrap();
+        db.insert_reading("AA", late, 0, Temperature::from_celsius(151.0), None, None, -60).await.unwrap();
+
+        let readings = db.get_readings_since("AA", late).await.unwrap();
+        assert_eq!(readings.len(), 2, "cutoff inside the stored second still matches");
+        assert!(readings.iter().all(|r| r.timestamp == second));
+        // Same-second rows keep insertion order
+        assert_eq!(readings[0].temperature.celsius(), 150.0);
+        assert_eq!(readings[1].temperature.celsius(), 151.0);
+
+        let in_range = db.get_readings_in_range("AA", early, late).await.unwrap();
+        assert_eq!(in_range.len(), 2);
+
+        let latest = db.get_latest_reading("AA").await.unwrap();
+        assert_eq!(latest.temperature.celsius(), 151.0);
+    }
+
+    #[tokio::test]
+    async fn test_latest_reading_uses_primary_sensor() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        use chrono::TimeZone;
+        let earlier = Utc.with_ymd_and_hms(2026, 1, 20, 12, 0, 0).unwrap();
+        let latest = earlier + chrono::Duration::seconds(5);
+
+        // An older packet with a hotter sensor, then the last packet inserted out of sensor order
+        db.insert_reading("AA", earlier, 7, Temperature::from_celsius(300.0), None, None, -60).await.unwrap();
+        for index in [5, 0, 3, 1] {
+            db.insert_reading("AA", latest, index, Temperature::from_celsius(50.0 + index as f32), None, None, -60)
+                .await
+                .unwrap();
+        }
+
+        for _ in 0..3 {
+            let snapshot = db.get_latest_snapshot("AA").await.unwrap();
+            assert_eq!(snapshot.primary.sensor_index, 3, "8-sensor default is T4");
+            assert_eq!(snapshot.primary.timestamp, latest);
+            let indices: Vec<i64> = snapshot.sensors.iter().map(|r| r.sensor_index).collect();
+            assert_eq!(indices, vec![0, 1, 3, 5]);
+        }
+
+        db.set_primary_sensor("AA", Some(5)).await.unwrap();
+        assert_eq!(db.get_latest_reading("AA").await.unwrap().temperature.celsius(), 55.0);
+
+        // Primary missing from the last packet falls back to the lowest sensor
+        db.set_primary_sensor("AA", Some(7)).await.unwrap();
+        assert_eq!(db.get_latest_reading("AA").await.unwrap().sensor_index, 0);
+
+        let json = serde_json::to_value(db.get_latest_snapshot("AA").await.unwrap()).unwrap();
+        assert_eq!(json["sensor_index"], 0);
+        assert_eq!(json["sensors"].as_array().unwrap().len(), 4);
+
+        assert!(db.get_latest_reading("BB").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cook_session_stats() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+
+        // Reading before the cook must not count
+        db.insert_reading("AA", Utc::now() - chrono::Duration::hours(1), 3, Temperature::from_celsius(40.0), None, None, -60)
+            .await
+            .unwrap();
+
+        let session = db.start_cook("AA", "Brisket", Some(Temperature::from_celsius(200.0))).await.unwrap();
+        assert!(db.start_cook("AA", "Second", None).await.is_err(), "only one open cook per device");
+        assert_eq!(db.get_open_cook("AA").await.unwrap().unwrap().id, session.id);
+
+        // Backdate the session so the readings below fall between start and now
+        let start = TimestampPrecision::Seconds.truncate(Utc::now() - chrono::Duration::minutes(10));
+        sqlx::query("UPDATE cook_sessions SET started_at = ?, start_uptime_ms = start_uptime_ms - 600000 WHERE id = ?")
+            .bind(start)
+            .bind(session.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        for (minutes, temp) in [(1, 150.0), (2, 190.0), (3, 201.0), (4, 205.0)] {
+            db.insert_reading("AA", start + chrono::Duration::minutes(minutes), 3, Temperature::from_celsius(temp), None, None, -60)
+                .await
+                .unwrap();
+        }
+        // Ambient sensor is not the internal temp
+        db.insert_reading("AA", start + chrono::Duration::minutes(2), 7, Temperature::from_celsius(275.0), None, None, -60)
+            .await
+            .unwrap();
+
+        let moved = db.set_cook_target("AA", Some(Temperature::from_celsius(95.0))).await.unwrap().unwrap();
+        assert_eq!(moved.target_temp, Some(Temperature::from_celsius(95.0)));
+        let open: Vec<i64> = db.get_open_cooks().await.unwrap().iter().map(|c| c.id).collect();
+        assert_eq!(open, vec![moved.id]);
+        db.set_cook_target("AA", Some(Temperature::from_celsius(200.0))).await.unwrap();
+
+        let stats = db.get_cook_stats(session.id).await.unwrap();
+        assert_eq!(stats.reading_count, 4);
+        assert_eq!(stats.min_temp.map(Temperature::celsius), Some(150.0));
+        assert_eq!(stats.max_temp.map(Temperature::celsius), Some(205.0));
+        assert_eq!(stats.avg_temp.map(Temperature::celsius), Some(186.5));
+        assert_eq!(stats.time_to_target_secs, Some(180));
+        assert_eq!(stats.danger_zone_secs, 0, "never below 140°F");
+        assert!(stats.fuel.is_none(), "no ambient readings, no fuel estimate");
+        assert!((600..=610).contains(&stats.duration_secs));
+        assert!(!stats.duration_estimated);
+
+        let ended = db.end_cook("AA").await.unwrap().unwrap();
+        assert!(ended.session.ended_at.is_some());
+        assert!(db.get_open_cook("AA").await.unwrap().is_none());
+        assert!(db.end_cook("AA").await.unwrap().is_none());
+        assert!(db.set_cook_target("AA", None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_device_stats_over_a_range() {
+        use chrono::TimeZone;
+
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        db.upsert_device("BB", "MEATER", "MeaterOriginal", "MEATER", 2).await.unwrap();
+        let start = Utc.with_ymd_and_hms(2026, 1, 20, 22, 0, 0).unwrap();
+
+        // Internal on T4, ambient carried on every row of a packet
+        for (minutes, internal, ambient) in [(0, 60.0, 110.0), (10, 70.0, 125.0), (20, 80.0, 121.0), (30, 75.0, 118.0), (40, 90.0, 115.0)] {
+            let at = start + chrono::Duration::minutes(minutes);
+            let ambient = Some(Temperature::from_celsius(ambient));
+            db.insert_reading("AA", at, 3, Temperature::from_celsius(internal), ambient, None, -60).await.unwrap();
+            db.insert_reading("AA", at, 0, Temperature::from_celsius(20.0), ambient, None, -60).await.unwrap();
+        }
+        // Outside the range, and another device
+        db.insert_reading("AA", start - chrono::Duration::hours(1), 3, Temperature::from_celsius(300.0), None, None, -60)
+            .await
+            .unwrap();
+        db.insert_reading("BB", start, 0, Temperature::from_celsius(10.0), None, None, -60).await.unwrap();
+
+        let end = start + chrono::Duration::hours(1);
+        let stats = db.get_device_stats("AA", start, end, Some(Temperature::from_celsius(75.0))).await.unwrap();
+        assert_eq!(stats.reading_count, 5);
+        assert_eq!(stats.min_temp.map(Temperature::celsius), Some(60.0));
+        assert_eq!(stats.max_temp.map(Temperature::celsius), Some(90.0));
+        assert_eq!(stats.avg_temp.map(Temperature::celsius), Some(75.0));
+        assert_eq!(stats.min_ambient.map(Temperature::celsius), Some(110.0));
+        assert_eq!(stats.max_ambient.map(Temperature::celsius), Some(125.0), "overnight pit peak");
+        assert_eq!(stats.first_reading_at, Some(start));
+        assert_eq!(stats.last_reading_at, Some(start + chrono::Duration::minutes(40)));
+        // 80°C and 75°C each held for 10 minutes; the last reading has no next one
+        assert_eq!(stats.secs_above, Some(1200));
+
+        db.set_primary_sensor("AA", Some(0)).await.unwrap();
+        let stats = db.get_device_stats("AA", start, end, None).await.unwrap();
+        assert_eq!(stats.max_temp.map(Temperature::celsius), Some(20.0), "follows the chosen primary sensor");
+        assert_eq!(stats.secs_above, None);
+
+        let empty = db.get_device_stats("AA", end, end + chrono::Duration::hours(1), None).await.unwrap();
+        assert_eq!(empty.reading_count, 0);
+        assert!(empty.max_temp.is_none() && empty.max_ambient.is_none() && empty.first_reading_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cook_duration_survives_clock_reset() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "MEATER", "MeaterOriginal", "MEATER", 2).await.unwrap();
+        let session = db.start_cook("AA", "Overnight", None).await.unwrap();
+
+        // Power cut an hour into the new boot's uptime: the Pi came back believing it was a day earlier
+        sqlx::query(
+            r#"
+            UPDATE cook_sessions SET start_boot_id = 'before', start_uptime_ms = 1000,
+                ended_at = ?, end_boot_id = 'after', end_uptime_ms = 3600000
+            WHERE id = ?
+            "#
+        )
+        .bind(session.started_at - chrono::Duration::days(1))
+        .bind(session.id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let stats = db.get_cook_stats(session.id).await.unwrap();
+        assert_eq!((stats.duration_secs, stats.duration_estimated), (3600, true));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_frame_count() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        assert_eq!(db.get_rejected_frames("AA").await.unwrap(), 0);
+
+        assert_eq!(db.record_rejected_frame("AA", "expected 13 or 14 bytes, got 6").await.unwrap(), 1);
+        assert_eq!(db.record_rejected_frame("AA", "Sensor 1 out of range").await.unwrap(), 2);
+        assert_eq!(db.get_rejected_frames("AA").await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cook_stats_include_fuel_estimate() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        let session = db.start_cook("AA", "Ribs", None).await.unwrap();
+
+        let start = TimestampPrecision::Seconds.truncate(Utc::now() - chrono::Duration::minutes(30));
+        sqlx::query("UPDATE cook_sessions SET started_at = ? WHERE id = ?")
+            .bind(start)
+            .bind(session.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        for minute in 0..20 {
+            let timestamp = start + chrono::Duration::minutes(minute);
+            // Every sensor in a packet carries the same ambient value
+            for index in [3, 7] {
+                db.insert_reading("AA", timestamp, index, Temperature::from_fahrenheit(160.0), Some(Temperature::from_fahrenheit(270.0)), None, -60)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let base = db.get_cook_stats(session.id).await.unwrap().fuel.unwrap();
+        assert_eq!(base.lid_openings, 0);
+        // 200°F above the outdoor reference for 19 minutes
+        assert!((base.fuel_units - 2.0 * 19.0 / 60.0).abs() < 1e-3, "{}", base.fuel_units);
+
+        // Run 20°F under its setpoint: the lift to 290°F plus the shortfall
+        assert!(db.set_cook_pit_setpoint("AA", Some(Temperature::from_fahrenheit(290.0))).await.unwrap());
+        let under = db.get_cook_stats(session.id).await.unwrap().fuel.unwrap();
+        assert!((under.fuel_units - (2.2 + 0.2) * 19.0 / 60.0).abs() < 1e-3, "{}", under.fuel_units);
+        db.set_cook_pit_setpoint("AA", None).await.unwrap();
+
+        db.set_smoker_profile("AA", &SmokerProfile { efficiency: 2.0, notes: None }).await.unwrap();
+        let doubled = db.get_cook_stats(session.id).await.unwrap().fuel.unwrap();
+        assert!((doubled.fuel_units - base.fuel_units * 2.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_cook_stats_time_in_danger_zone() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        let session = db.start_cook("AA", "Chicken", None).await.unwrap();
+
+        let start = TimestampPrecision::Seconds.truncate(Utc::now() - chrono::Duration::minutes(30));
+        sqlx::query("UPDATE cook_sessions SET started_at = ? WHERE id = ?")
+            .bind(start)
+            .bind(session.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        for (minute, fahrenheit) in [(0, 100.0), (1, 120.0), (2, 135.0), (3, 145.0), (4, 150.0)] {
+            let timestamp = start + chrono::Duration::minutes(minute);
+            db.insert_reading("AA", timestamp, 3, Temperature::from_fahrenheit(fahrenheit), None, None, -60)
+                .await
+                .unwrap();
+            // A cool handle sensor sits in the zone the whole time but is not the meat
+            db.insert_reading("AA", timestamp, 7, Temperature::from_fahrenheit(90.0), None, None, -60)
+                .await
+                .unwrap();
+        }
+
+        let stats = db.get_cook_stats(session.id).await.unwrap();
+        assert_eq!(stats.danger_zone_secs, 3 * 60, "until the primary sensor passed 140°F");
+    }
+
+    #[tokio::test]
+    async fn test_cook_report_from_recorded_session() {
+        let (db, session) = crate::test_support::fixtures::load("brisket_stall").await;
+        let stats = db.get_cook_stats(session.id).await.unwrap();
+
+        assert_eq!(stats.reading_count, 421, "one meat reading a minute for 7 hours");
+        assert_eq!(stats.duration_secs, 7 * 3600);
+        assert!(!stats.duration_estimated);
+        assert_eq!(stats.time_to_target_secs, Some(410 * 60));
+        assert!(stats.max_temp.unwrap().celsius() > 95.0);
+        let fuel = stats.fuel.unwrap();
+        assert_eq!(fuel.lid_openings, 1);
+    }
+
+
+    #[tokio::test]
+    async fn test_export_readings_streams_in_chunks() {
+        let db = Database::new(":memory:").await.unwrap();
+        let start = Utc::now() - chrono::Duration::hours(2);
+        for address in ["AA", "BB"] {
+            db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        }
+        // Enough CSV to fill more than one chunk
+        for second in 0..1200 {
+            let address = if second % 2 == 0 { "AA" } else { "BB" };
+            db.insert_reading(address, start + chrono::Duration::seconds(second), 0, Temperature::from_celsius(70.0), None, None, -60)
+                .await
+                .unwrap();
+        }
+        let end = start + chrono::Duration::hours(1);
+        let collect = |device, format, from| {
+            db.export_readings(device, from, end, format, TemperatureUnit::Celsius)
+                .collect::<Vec<Result<String>>>()
+        };
+
+        let chunks: Vec<String> = collect(None, ExportFormat::Csv, start).await.into_iter().map(Result::unwrap).collect();
+        assert!(chunks.len() > 1, "{} chunks", chunks.len());
+        let csv = chunks.concat();
+        assert_eq!(csv.lines().count(), 1201);
+        assert!(csv.starts_with(crate::export::DEVICES_CSV_HEADER));
+
+        let json: String = collect(Some("BB"), ExportFormat::Json, start).await.into_iter().map(Result::unwrap).collect();
+        let json: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(json.len(), 600);
+        assert!(json.iter().all(|r| r["device_address"] == "BB"));
+
+        // Nothing after the last reading
+        let empty: String = collect(Some("AA"), ExportFormat::Json, end).await.into_iter().map(Result::unwrap).collect();
+        assert!(serde_json::from_str::<Vec<serde_json::Value>>(&empty).unwrap().is_empty(), "{}", empty);
+    }
+
+}
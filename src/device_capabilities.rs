@@ -2,9 +2,15 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::clock::{Clock, SystemClock};
 
 // Import service UUIDs from protocol module
-use crate::protocol::{MEATSTICK_SERVICE, COMBUSTION_UART_SERVICE};
+use crate::protocol::{
+    COMBUSTION_PRODUCT_PROBE, COMBUSTION_UART_SERVICE, COMBUSTION_VENDOR_ID, MEATER_VENDOR_ID, MEATSTICK_SERVICE,
+    THERMOPRO_PROBE_COUNT, WEBER_PROBE_COUNT,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProbeBrand {
@@ -15,6 +21,7 @@ pub enum ProbeBrand {
     MeaterPlus,
     MeaterBlock,
     WeberIGrill,
+    ThermoProTP25,
     Unknown(String),
 }
 
@@ -29,6 +36,46 @@ pub struct ProbeCapabilities {
     pub range_feet: Option<u32>,
     pub has_repeater: bool,
     pub service_uuids: Vec<String>,
+    /// Sent over the Nordic UART after connecting; empty = leave the UART alone
+    ///
+    /// Empty for every brand: no probe's UART command set is documented, so
+    /// commands only go out when `[device.wake_commands]` configures them.
+    #[serde(default)]
+    pub wake_commands: Vec<Vec<u8>>,
+}
+
+/// What a single sensor on a probe measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorRole {
+    Internal,
+    Surface,
+    Ambient,
+}
+
+/// Role and display label for sensor `index` on a device with `sensor_count` sensors
+pub fn sensor_layout(sensor_count: usize, index: usize) -> (SensorRole, String) {
+    match sensor_count {
+        // MeatStick V / Combustion: T1-T4 in the meat, T5-T7 toward the handle, T8 ambient
+        8 => match index {
+            0..=3 => (SensorRole::Internal, format!("Internal T{}", index + 1)),
+            7 => (SensorRole::Ambient, "Ambient".to_string()),
+            _ => (SensorRole::Surface, format!("Surface T{}", index + 1)),
+        },
+        // Tip + ambient probes (MEATER, MeatStick V1)
+        2 if index == 0 => (SensorRole::Internal, "Internal".to_string()),
+        2 => (SensorRole::Ambient, "Ambient".to_string()),
+        _ => (SensorRole::Internal, format!("Probe {}", index + 1)),
+    }
+}
+
+/// Sensor used for the headline temperature by default (T4 is the deepest core sensor)
+pub fn default_primary_sensor(sensor_count: usize) -> usize {
+    if sensor_count == 8 {
+        3
+    } else {
+        0
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,7 +100,11 @@ pub enum DataFreshness {
 pub struct ProbeReading {
     pub probe_id: String,
     pub device_address: String,
+    /// Wall-clock time of the reading, for storage and display
     pub timestamp: DateTime<Utc>,
+    /// Monotonic arrival time; ages are measured from this, not `timestamp`
+    #[serde(skip, default = "Instant::now")]
+    pub received_at: Instant,
     pub temperatures: Vec<f32>,  // Multiple sensors for MeatStick V
     pub ambient_temp: Option<f32>,
     pub battery_level: Option<u8>,
@@ -63,8 +114,104 @@ pub struct ProbeReading {
     pub safety_status: SafetyStatus,
 }
 
+/// Whether the advertisement carries a company ID of a supported probe maker
+pub fn has_probe_vendor_id(manufacturer_data: &HashMap<u16, Vec<u8>>) -> bool {
+    manufacturer_data.contains_key(&COMBUSTION_VENDOR_ID) || manufacturer_data.contains_key(&MEATER_VENDOR_ID)
+}
+
 impl ProbeCapabilities {
-    pub fn detect_from_device(device_name: &str, _mac_address: &str, services: &[String]) -> Self {
+    /// Classify a device from its advertisement
+    ///
+    /// Manufacturer data identifies probes that advertise a generic or empty
+    /// name, and wins over the name when both are present; name prefixes are
+    /// the fallback for devices without a known company ID.
+    pub fn detect_from_device(
+        device_name: &str,
+        _mac_address: &str,
+        services: &[String],
+        manufacturer_data: &HashMap<u16, Vec<u8>>,
+    ) -> Self {
+        Self::detect_from_manufacturer_data(device_name, services, manufacturer_data)
+            .unwrap_or_else(|| Self::detect_from_name(device_name, services))
+    }
+
+    fn detect_from_manufacturer_data(
+        device_name: &str,
+        services: &[String],
+        manufacturer_data: &HashMap<u16, Vec<u8>>,
+    ) -> Option<Self> {
+        let model = |fallback: &str| {
+            if device_name.is_empty() {
+                fallback.to_string()
+            } else {
+                device_name.to_string()
+            }
+        };
+
+        if let Some(data) = manufacturer_data.get(&COMBUSTION_VENDOR_ID) {
+            return Some(match data.first() {
+                Some(&COMBUSTION_PRODUCT_PROBE) => Self::meatstick_v(model("Combustion Probe"), services),
+                _ => Self::meatstick_base(model("Combustion MeatNet Node"), services),
+            });
+        }
+        if manufacturer_data.contains_key(&MEATER_VENDOR_ID) {
+            // The vendor bytes do not tell the models apart; a name can still refine it
+            let detected = Self::detect_from_name(device_name, services);
+            return Some(match detected.brand {
+                ProbeBrand::MeaterOriginal | ProbeBrand::MeaterPlus | ProbeBrand::MeaterBlock => detected,
+                _ => Self::meater_original(model("MEATER"), services),
+            });
+        }
+        None
+    }
+
+    fn meatstick_v(model: String, services: &[String]) -> Self {
+        // MeatStick V has 6 sensors (or 8 for Combustion models)
+        Self {
+            brand: ProbeBrand::MeatStickV,
+            model,
+            sensor_count: 8, // Updated to 8 for Combustion protocol
+            max_ambient_temp_f: 1000.0,
+            max_internal_temp_f: 200.0,
+            battery_life_hours: Some(24),
+            range_feet: Some(650),
+            has_repeater: false,
+            service_uuids: services.to_vec(),
+            wake_commands: Vec::new(),
+        }
+    }
+
+    fn meatstick_base(model: String, services: &[String]) -> Self {
+        Self {
+            brand: ProbeBrand::MeatStickV,
+            model,
+            sensor_count: 0,
+            max_ambient_temp_f: 0.0,
+            max_internal_temp_f: 0.0,
+            battery_life_hours: None, // Plugged in
+            range_feet: Some(650),
+            has_repeater: true,
+            service_uuids: services.to_vec(),
+            wake_commands: Vec::new(),
+        }
+    }
+
+    fn meater_original(model: String, services: &[String]) -> Self {
+        Self {
+            brand: ProbeBrand::MeaterOriginal,
+            model,
+            sensor_count: 2,
+            max_ambient_temp_f: 527.0,
+            max_internal_temp_f: 212.0,
+            battery_life_hours: Some(8),
+            range_feet: Some(33),
+            has_repeater: false,
+            service_uuids: services.to_vec(),
+            wake_commands: Vec::new(),
+        }
+    }
+
+    fn detect_from_name(device_name: &str, services: &[String]) -> Self {
         // Convert service strings to lowercase for comparison
         let has_meatstick_service = services.iter().any(|s| {
             s.to_lowercase() == MEATSTICK_SERVICE.to_string().to_lowercase()
@@ -77,18 +224,7 @@ impl ProbeCapabilities {
             // MeatStick device detection
             name if name.starts_with("cA00") => {
                 if has_meatstick_service || has_uart_service {
-                    // MeatStick V has 6 sensors (or 8 for Combustion models)
-                    Self {
-                        brand: ProbeBrand::MeatStickV,
-                        model: name.to_string(),
-                        sensor_count: 8, // Updated to 8 for Combustion protocol
-                        max_ambient_temp_f: 1000.0,
-                        max_internal_temp_f: 200.0,
-                        battery_life_hours: Some(24),
-                        range_feet: Some(650),
-                        has_repeater: false,
-                        service_uuids: services.to_vec(),
-                    }
+                    Self::meatstick_v(name.to_string(), services)
                 } else {
                     // Older MeatStick models
                     Self {
@@ -101,24 +237,13 @@ impl ProbeCapabilities {
                         range_feet: Some(165),
                         has_repeater: false,
                         service_uuids: services.to_vec(),
+                        wake_commands: Vec::new(),
                     }
                 }
             }
             
             // MeatStick base stations
-            name if name.starts_with("cA02") => {
-                Self {
-                    brand: ProbeBrand::MeatStickV,
-                    model: format!("{}_BASE", name),
-                    sensor_count: 0,
-                    max_ambient_temp_f: 0.0,
-                    max_internal_temp_f: 0.0,
-                    battery_life_hours: None, // Plugged in
-                    range_feet: Some(650),
-                    has_repeater: true,
-                    service_uuids: services.to_vec(),
-                }
-            }
+            name if name.starts_with("cA02") => Self::meatstick_base(format!("{}_BASE", name), services),
             
             // Meater devices
             name if name.to_uppercase().contains("MEATER") => {
@@ -133,6 +258,7 @@ impl ProbeCapabilities {
                         range_feet: Some(165),
                         has_repeater: true,
                         service_uuids: services.to_vec(),
+                        wake_commands: Vec::new(),
                     }
                 } else if name.contains("PLUS") || name.contains("Plus") {
                     Self {
@@ -145,19 +271,42 @@ impl ProbeCapabilities {
                         range_feet: Some(165),
                         has_repeater: false,
                         service_uuids: services.to_vec(),
+                        wake_commands: Vec::new(),
                     }
                 } else {
-                    Self {
-                        brand: ProbeBrand::MeaterOriginal,
-                        model: name.to_string(),
-                        sensor_count: 2,
-                        max_ambient_temp_f: 527.0,
-                        max_internal_temp_f: 212.0,
-                        battery_life_hours: Some(8),
-                        range_feet: Some(33),
-                        has_repeater: false,
-                        service_uuids: services.to_vec(),
-                    }
+                    Self::meater_original(name.to_string(), services)
+                }
+            }
+            
+            // Weber iGrill thermometers (the mini has a single probe jack)
+            name if name.to_lowercase().contains("igrill") || name.to_lowercase().contains("weber") => {
+                Self {
+                    brand: ProbeBrand::WeberIGrill,
+                    model: name.to_string(),
+                    sensor_count: if name.to_lowercase().contains("mini") { 1 } else { WEBER_PROBE_COUNT },
+                    max_ambient_temp_f: 716.0,
+                    max_internal_temp_f: 572.0,
+                    battery_life_hours: Some(150),
+                    range_feet: Some(150),
+                    has_repeater: false,
+                    service_uuids: services.to_vec(),
+                    wake_commands: Vec::new(),
+                }
+            }
+            
+            // ThermoPro 4-probe thermometers
+            name if name.starts_with("TP25") || name.to_lowercase().contains("thermopro") => {
+                Self {
+                    brand: ProbeBrand::ThermoProTP25,
+                    model: name.to_string(),
+                    sensor_count: THERMOPRO_PROBE_COUNT,
+                    max_ambient_temp_f: 572.0,
+                    max_internal_temp_f: 572.0,
+                    battery_life_hours: Some(150),
+                    range_feet: Some(500),
+                    has_repeater: false,
+                    service_uuids: services.to_vec(),
+                    wake_commands: Vec::new(),
                 }
             }
             
@@ -171,6 +320,7 @@ impl ProbeCapabilities {
                 range_feet: Some(30),
                 has_repeater: false,
                 service_uuids: services.to_vec(),
+                wake_commands: Vec::new(),
             }
         }
     }
@@ -178,10 +328,20 @@ impl ProbeCapabilities {
 
 impl ProbeReading {
     pub fn new(probe_id: String, device_address: String, capabilities: &ProbeCapabilities) -> Self {
+        Self::new_with_clock(probe_id, device_address, capabilities, &SystemClock)
+    }
+    
+    pub fn new_with_clock(
+        probe_id: String,
+        device_address: String,
+        capabilities: &ProbeCapabilities,
+        clock: &impl Clock,
+    ) -> Self {
         Self {
             probe_id,
             device_address,
-            timestamp: Utc::now(),
+            timestamp: clock.wall(),
+            received_at: clock.monotonic(),
             temperatures: vec![0.0; capabilities.sensor_count.max(1)],
             ambient_temp: None,
             battery_level: None,
@@ -224,7 +384,12 @@ impl ProbeReading {
     }
     
     pub fn update_confidence(&mut self) {
-        let age_seconds = (Utc::now() - self.timestamp).num_seconds() as u64;
+        self.update_confidence_with(&SystemClock);
+    }
+    
+    /// Recompute freshness and confidence from the monotonic age, immune to wall-clock steps
+    pub fn update_confidence_with(&mut self, clock: &impl Clock) {
+        let age_seconds = clock.monotonic().saturating_duration_since(self.received_at).as_secs();
         
         self.confidence = match age_seconds {
             0..=30 => 1.0,           // Live data: 100% confidence
@@ -299,4 +464,115 @@ impl NetworkTopology {
             .filter(|reading| matches!(reading.safety_status, SafetyStatus::DangerousAmbient | SafetyStatus::DangerousInternal))
             .collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combustion_probe_data() -> HashMap<u16, Vec<u8>> {
+        HashMap::from([(COMBUSTION_VENDOR_ID, vec![COMBUSTION_PRODUCT_PROBE, 0x78, 0x56, 0x34, 0x12])])
+    }
+
+    #[test]
+    fn test_unnamed_probe_detected_from_manufacturer_data() {
+        let probe = ProbeCapabilities::detect_from_device("", "AA", &[], &combustion_probe_data());
+        assert!(matches!(probe.brand, ProbeBrand::MeatStickV));
+        assert_eq!(probe.sensor_count, 8);
+        assert_eq!(probe.model, "Combustion Probe");
+
+        let node = HashMap::from([(COMBUSTION_VENDOR_ID, vec![0x02])]);
+        let node = ProbeCapabilities::detect_from_device("", "AA", &[], &node);
+        assert_eq!(node.sensor_count, 0);
+        assert!(node.has_repeater);
+
+        let meater = HashMap::from([(MEATER_VENDOR_ID, vec![0x01, 0x02])]);
+        let meater = ProbeCapabilities::detect_from_device("", "AA", &[], &meater);
+        assert!(matches!(meater.brand, ProbeBrand::MeaterOriginal));
+        assert!(has_probe_vendor_id(&combustion_probe_data()));
+    }
+
+    #[test]
+    fn test_manufacturer_data_wins_over_name() {
+        // Named like a MEATER but advertising Combustion's company ID
+        let probe = ProbeCapabilities::detect_from_device("MEATER+", "AA", &[], &combustion_probe_data());
+        assert!(matches!(probe.brand, ProbeBrand::MeatStickV));
+        assert_eq!(probe.sensor_count, 8);
+
+        // A MEATER name still picks the model under MEATER's company ID
+        let meater = HashMap::from([(MEATER_VENDOR_ID, vec![])]);
+        let plus = ProbeCapabilities::detect_from_device("MEATER Plus", "AA", &[], &meater);
+        assert!(matches!(plus.brand, ProbeBrand::MeaterPlus));
+    }
+
+    #[test]
+    fn test_name_is_the_fallback() {
+        let unknown_vendor = HashMap::from([(0x004C, vec![0x02, 0x15])]);
+        let probe = ProbeCapabilities::detect_from_device("cA00probe", "AA", &[], &unknown_vendor);
+        assert!(matches!(probe.brand, ProbeBrand::MeatStickV1));
+        assert!(!has_probe_vendor_id(&unknown_vendor));
+
+        let nothing = ProbeCapabilities::detect_from_device("", "AA", &[], &HashMap::new());
+        assert!(matches!(nothing.brand, ProbeBrand::Unknown(_)));
+        assert_eq!(nothing.sensor_count, 1);
+    }
+
+    #[test]
+    fn test_no_wake_commands_by_default() {
+        // The UART command format is unverified, so nothing is sent unless configured
+        let probe = ProbeCapabilities::detect_from_device("", "AA", &[], &combustion_probe_data());
+        assert!(probe.wake_commands.is_empty());
+
+        // A DIY thermometer with a Nordic UART service gets nothing
+        let uart = [COMBUSTION_UART_SERVICE.to_string()];
+        let diy = ProbeCapabilities::detect_from_device("ESP32-Thermo", "AA", &uart, &HashMap::new());
+        assert!(diy.wake_commands.is_empty());
+        let tp25 = ProbeCapabilities::detect_from_device("TP25", "AA", &uart, &HashMap::new());
+        assert!(tp25.wake_commands.is_empty());
+    }
+
+    /// Clock whose wall and monotonic times are stepped independently
+    struct SteppedClock {
+        wall: std::cell::Cell<DateTime<Utc>>,
+        monotonic: std::cell::Cell<Instant>,
+    }
+
+    impl SteppedClock {
+        fn advance(&self, wall_secs: i64, monotonic_secs: u64) {
+            self.wall.set(self.wall.get() + chrono::Duration::seconds(wall_secs));
+            self.monotonic.set(self.monotonic.get() + std::time::Duration::from_secs(monotonic_secs));
+        }
+    }
+
+    impl Clock for SteppedClock {
+        fn wall(&self) -> DateTime<Utc> {
+            self.wall.get()
+        }
+
+        fn monotonic(&self) -> Instant {
+            self.monotonic.get()
+        }
+    }
+
+    #[test]
+    fn test_freshness_ignores_wall_clock_steps() {
+        let clock = SteppedClock {
+            wall: std::cell::Cell::new(Utc::now()),
+            monotonic: std::cell::Cell::new(Instant::now()),
+        };
+        let capabilities = ProbeCapabilities::detect_from_device("", "AA", &[], &combustion_probe_data());
+        let mut reading = ProbeReading::new_with_clock("p1".to_string(), "AA".to_string(), &capabilities, &clock);
+
+        // NTP steps the clock an hour forward 10 seconds after the reading
+        clock.advance(3600 + 10, 10);
+        reading.update_confidence_with(&clock);
+        assert!(matches!(reading.freshness, DataFreshness::Live(10)), "{:?}", reading.freshness);
+        assert_eq!(reading.confidence, 1.0);
+
+        // ...and back again: ages still only move forward
+        clock.advance(-7200, 400);
+        reading.update_confidence_with(&clock);
+        assert!(matches!(reading.freshness, DataFreshness::Stale(410)), "{:?}", reading.freshness);
+        assert_eq!(reading.confidence, 0.2);
+    }
+}
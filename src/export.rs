@@ -0,0 +1,419 @@
+// src/export.rs
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use crate::config::ExportScheduleConfig;
+use crate::database::{Database, ReadingRecord, ReadingSource};
+use crate::protocol::ReadingMode;
+use crate::temperature::TemperatureUnit;
+
+/// Prefix for files written by the scheduled exporter (pruning only touches these)
+const EXPORT_FILE_PREFIX: &str = "bbq-export-";
+
+/// UTC time stamped into exporter file names
+const EXPORT_STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// CSV header of single-device exports
+pub const CSV_HEADER: &str = "timestamp,sensor_index,temperature,ambient_temp,battery_level,signal_strength,unit,source";
+
+/// CSV header of streamed exports, which may span several devices
+pub const DEVICES_CSV_HEADER: &str =
+    "device_address,timestamp,sensor_index,temperature,ambient_temp,battery_level,signal_strength,unit,source";
+
+/// Supported export file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            other => Err(anyhow!("Unsupported export format '{}' (expected csv, json or ndjson)", other)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// Text a streamed export starts with
+    pub fn stream_prefix(&self) -> String {
+        match self {
+            Self::Csv => format!("{}\n", DEVICES_CSV_HEADER),
+            Self::Json => "[".to_string(),
+            Self::Ndjson => String::new(),
+        }
+    }
+
+    /// One reading of a streamed export; `first` for the first reading written
+    pub fn stream_row(&self, reading: &ReadingRecord, unit: TemperatureUnit, first: bool) -> Result<String> {
+        let json = || serde_json::to_string(&ExportedReading::new(reading, unit)).context("Failed to serialize reading");
+        Ok(match self {
+            Self::Csv => format!("{},{}\n", reading.device_address, csv_row(reading, unit)),
+            Self::Json => format!("{}\n{}", if first { "" } else { "," }, json()?),
+            Self::Ndjson => format!("{}\n", json()?),
+        })
+    }
+
+    /// Text a streamed export ends with
+    pub fn stream_suffix(&self) -> &'static str {
+        match self {
+            Self::Json => "\n]\n",
+            Self::Csv | Self::Ndjson => "",
+        }
+    }
+}
+
+/// A reading as written to JSON exports, temperatures in the display unit
+#[derive(Debug, serde::Serialize)]
+struct ExportedReading<'a> {
+    device_address: &'a str,
+    timestamp: DateTime<Utc>,
+    sensor_index: i64,
+    temperature: f32,
+    ambient_temp: Option<f32>,
+    battery_level: Option<u8>,
+    signal_strength: i16,
+    reading_mode: ReadingMode,
+    source: ReadingSource,
+    unit: TemperatureUnit,
+}
+
+impl<'a> ExportedReading<'a> {
+    fn new(reading: &'a ReadingRecord, unit: TemperatureUnit) -> Self {
+        Self {
+            device_address: &reading.device_address,
+            timestamp: reading.timestamp,
+            sensor_index: reading.sensor_index,
+            temperature: reading.temperature.in_unit(unit),
+            ambient_temp: reading.ambient_temp.map(|t| t.in_unit(unit)),
+            battery_level: reading.battery_level,
+            signal_strength: reading.signal_strength,
+            reading_mode: reading.reading_mode,
+            source: reading.source,
+            unit,
+        }
+    }
+}
+
+/// Format one reading as a CSV row in `unit` (no trailing newline)
+pub fn csv_row(reading: &ReadingRecord, unit: TemperatureUnit) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{}",
+        reading.timestamp.to_rfc3339(),
+        reading.sensor_index,
+        reading.temperature.in_unit(unit),
+        reading.ambient_temp.map(|t| t.in_unit(unit).to_string()).unwrap_or_default(),
+        reading.battery_level.map(|b| b.to_string()).unwrap_or_default(),
+        reading.signal_strength,
+        unit.as_str(),
+        reading.source.as_str(),
+    )
+}
+
+/// Write readings in the requested format and unit
+pub fn write_readings<W: Write>(
+    writer: &mut W,
+    readings: &[ReadingRecord],
+    format: ExportFormat,
+    unit: TemperatureUnit,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(writer, "{}", CSV_HEADER)?;
+            for reading in readings {
+                writeln!(writer, "{}", csv_row(reading, unit))?;
+            }
+        }
+        ExportFormat::Json => {
+            let exported: Vec<ExportedReading> = readings
+                .iter()
+                .map(|reading| ExportedReading::new(reading, unit))
+                .collect();
+            serde_json::to_writer_pretty(&mut *writer, &exported)
+                .context("Failed to serialize readings")?;
+        }
+        ExportFormat::Ndjson => {
+            for reading in readings {
+                serde_json::to_writer(&mut *writer, &ExportedReading::new(reading, unit))
+                    .context("Failed to serialize readings")?;
+                writeln!(writer)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Export every device's readings since `since` into `dir`, one file per device
+///
+/// Returns the paths of the files written. Devices without new readings are skipped.
+pub async fn run_export_cycle(
+    db: &Database,
+    dir: &Path,
+    format: ExportFormat,
+    unit: TemperatureUnit,
+    since: DateTime<Utc>,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create export directory {}", dir.display()))?;
+
+    let stamp = Utc::now().format(EXPORT_STAMP_FORMAT);
+    let mut written = Vec::new();
+
+    for device in db.get_all_devices().await? {
+        let readings = db.get_readings_since(&device.device_address, since).await?;
+        if readings.is_empty() {
+            continue;
+        }
+
+        let file_name = format!(
+            "{}{}-{}.{}",
+            EXPORT_FILE_PREFIX,
+            device.device_address.replace(':', ""),
+            stamp,
+            format.extension()
+        );
+        let path = dir.join(file_name);
+
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?,
+        );
+        write_readings(&mut file, &readings, format, unit)?;
+        file.flush()?;
+
+        debug!("Exported {} readings to {}", readings.len(), path.display());
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+/// Delete exporter-created files older than `keep_days` (0 = keep forever)
+pub fn prune_exports(dir: &Path, keep_days: u32) -> Result<usize> {
+    if keep_days == 0 || !dir.exists() {
+        return Ok(0);
+    }
+
+    let max_age = Duration::from_secs(keep_days as u64 * 24 * 60 * 60);
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let is_export = entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(EXPORT_FILE_PREFIX);
+        if !is_export {
+            continue;
+        }
+
+        let modified = entry.metadata()?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() > max_age {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Stamp of the newest exporter file in `dir`, if there is one
+pub fn last_export_time(dir: &Path) -> Option<DateTime<Utc>> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let (_, stamp) = name.strip_prefix(EXPORT_FILE_PREFIX)?.rsplit_once('-')?;
+            let (stamp, _) = stamp.split_once('.')?;
+            let stamp = chrono::NaiveDateTime::parse_from_str(stamp, EXPORT_STAMP_FORMAT).ok()?;
+            Some(stamp.and_utc())
+        })
+        .max()
+}
+
+/// Run the exporter on a timer until shutdown
+pub async fn start_export_task(
+    db: Arc<Database>,
+    config: ExportScheduleConfig,
+    unit: TemperatureUnit,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let format = match ExportFormat::parse(&config.format) {
+        Ok(format) => format,
+        Err(e) => {
+            error!("Scheduled export disabled: {}", e);
+            return;
+        }
+    };
+
+    let dir = PathBuf::from(&config.path);
+    let period = Duration::from_secs(config.interval_secs.max(1));
+    let mut interval = tokio::time::interval(period);
+    // The first tick completes immediately; skip it so the first export covers a full period
+    interval.tick().await;
+    // Carry on after the last export, so readings taken while stopped are not skipped.
+    // Stamps are taken after the cycle started and truncated to the second; a second
+    // of overlap beats a gap.
+    let mut since = match last_export_time(&dir) {
+        Some(last) => {
+            info!("Scheduled export resuming after the export of {}", last);
+            last - chrono::Duration::seconds(1)
+        }
+        None => Utc::now(),
+    };
+
+    info!("Scheduled export every {}s to {}", config.interval_secs, dir.display());
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let cycle_start = Utc::now();
+                match run_export_cycle(&db, &dir, format, unit, since).await {
+                    Ok(files) => {
+                        info!("Scheduled export wrote {} files", files.len());
+                        since = cycle_start;
+                    }
+                    Err(e) => error!("Scheduled export failed: {}", e),
+                }
+
+                match prune_exports(&dir, config.keep_days) {
+                    Ok(removed) if removed > 0 => info!("Pruned {} old exports", removed),
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to prune exports: {}", e),
+                }
+            }
+            _ = shutdown.recv() => {
+                info!("Shutting down scheduled export task");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temperature::Temperature;
+
+    #[tokio::test]
+    async fn test_scheduled_export_cycle() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA:BB:CC:DD:EE:FF", "cA00probe", "MeatStickV", "cA00probe", 8)
+            .await
+            .unwrap();
+        db.upsert_device("11:22:33:44:55:66", "MEATER", "MeaterPlus", "MEATER", 2)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        for i in 0..3 {
+            db.insert_reading(
+                "AA:BB:CC:DD:EE:FF",
+                now,
+                i,
+                Temperature::from_celsius(65.0),
+                Some(Temperature::from_celsius(107.5)),
+                None,
+                -60,
+            )
+                .await
+                .unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let stale = dir.path().join("bbq-export-old.csv");
+        let unrelated = dir.path().join("notes.txt");
+        std::fs::write(&stale, "old").unwrap();
+        std::fs::write(&unrelated, "keep me").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60);
+        std::fs::File::options().write(true).open(&stale).unwrap().set_modified(old).unwrap();
+        std::fs::File::options().write(true).open(&unrelated).unwrap().set_modified(old).unwrap();
+
+        let since = now - chrono::Duration::minutes(5);
+        let files = run_export_cycle(&db, dir.path(), ExportFormat::Csv, TemperatureUnit::Fahrenheit, since)
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 1, "device without readings is skipped");
+
+        let contents = std::fs::read_to_string(&files[0]).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].ends_with(",149,225.5,,-60,fahrenheit,ble"), "{}", lines[1]);
+
+        assert_eq!(prune_exports(dir.path(), 7).unwrap(), 1);
+        assert!(!stale.exists());
+        assert!(unrelated.exists());
+        assert!(files[0].exists());
+    }
+
+    #[test]
+    fn test_last_export_time_is_the_newest_stamp() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(last_export_time(dir.path()), None);
+        assert_eq!(last_export_time(&dir.path().join("missing")), None);
+
+        for name in [
+            "bbq-export-AABBCCDDEEFF-20261015T120000Z.csv",
+            "bbq-export-112233445566-20261016T083015Z.ndjson",
+            "bbq-export-old.csv",
+            "notes-20991231T000000Z.txt",
+        ] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let expected = chrono::NaiveDate::from_ymd_opt(2026, 10, 16).unwrap().and_hms_opt(8, 30, 15).unwrap().and_utc();
+        assert_eq!(last_export_time(dir.path()), Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_json_export_uses_display_unit() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        db.insert_reading("AA", Utc::now(), 0, Temperature::from_celsius(100.0), None, None, -60)
+            .await
+            .unwrap();
+        let readings = db.get_device_readings("AA", 0).await.unwrap();
+
+        for (unit, expected) in [(TemperatureUnit::Celsius, 100.0), (TemperatureUnit::Fahrenheit, 212.0)] {
+            let mut out = Vec::new();
+            write_readings(&mut out, &readings, ExportFormat::Json, unit).unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+            assert_eq!(json[0]["temperature"], expected);
+            assert_eq!(json[0]["unit"], unit.as_str());
+        }
+    }
+
+    #[test]
+    fn test_export_format_parse() {
+        assert_eq!(ExportFormat::parse("CSV").unwrap(), ExportFormat::Csv);
+        assert_eq!(ExportFormat::parse("json").unwrap(), ExportFormat::Json);
+        assert!(ExportFormat::parse("xml").is_err());
+    }
+}
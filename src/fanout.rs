@@ -0,0 +1,545 @@
+// src/fanout.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::ble::BleStatus;
+use crate::temperature::{Temperature, TemperatureUnit};
+use crate::web_server::{ComparePoint, TemperatureUpdate};
+
+/// Version of the WebSocket message format (2 = sequenced updates with resume)
+pub const WS_PROTOCOL_VERSION: u32 = 2;
+
+/// Number of recent updates kept for reconnecting WebSocket clients
+pub const REPLAY_BUFFER_CAPACITY: usize = 2048;
+
+/// Update tagged with the sequence number assigned by the fan-out layer
+#[derive(Debug, Clone, Serialize)]
+pub struct SequencedUpdate {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub update: TemperatureUpdate,
+}
+
+impl SequencedUpdate {
+    /// The same update with temperatures converted to `unit`
+    pub fn in_unit(self, unit: TemperatureUnit) -> Self {
+        Self {
+            seq: self.seq,
+            update: self.update.in_unit(unit),
+        }
+    }
+}
+
+/// Messages sent by WebSocket clients
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Resume { last_seq: u64 },
+}
+
+/// Control messages sent to WebSocket clients
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    /// The requested range is no longer buffered; reload state over HTTP
+    Resync { latest_seq: u64 },
+    /// No new reading, but the device's last one is still this fresh
+    Heartbeat(Heartbeat),
+    /// Bluetooth startup progress ("scanning...", "connecting to 2 devices...")
+    System(BleStatus),
+    /// A device's name, cook target or alert rules changed; reload its settings
+    Settings { device_address: String },
+    /// A cook target offered for a new device; applied only if the user accepts
+    TargetSuggestion(TargetSuggestion),
+    /// Recent readings of a device, sent once on connect to fill its chart
+    History(DeviceHistory),
+}
+
+/// A device's primary sensor over the last `minutes`, averaged into `step_secs` buckets
+///
+/// A backfill, not live readings: unsequenced, and never replayed on resume.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceHistory {
+    pub device_address: String,
+    pub device_name: String,
+    pub sensor_index: usize,
+    pub unit: TemperatureUnit,
+    pub minutes: u32,
+    pub step_secs: u32,
+    pub points: Vec<ComparePoint>,
+}
+
+/// Cook profile target offered for a device seen for the first time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TargetSuggestion {
+    pub device_address: String,
+    pub meat: String,
+    pub target: f32,
+    /// Unit of `target`
+    pub unit: TemperatureUnit,
+}
+
+impl TargetSuggestion {
+    /// The same suggestion with the target converted to `unit`
+    pub fn in_unit(mut self, unit: TemperatureUnit) -> Self {
+        self.target = Temperature::from_unit(self.target, self.unit).in_unit(unit);
+        self.unit = unit;
+        self
+    }
+}
+
+/// Freshness ping for a quiet device: its last value and how long ago it arrived
+///
+/// Not a reading: it carries no sequence number and is never stored or replayed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Heartbeat {
+    pub device_address: String,
+    pub device_name: String,
+    pub sensor_index: usize,
+    pub temperature: f32,
+    pub unit: TemperatureUnit,
+    /// When the last reading was taken
+    pub timestamp: DateTime<Utc>,
+    /// Seconds since the last reading arrived, on the monotonic clock
+    pub age_secs: u64,
+}
+
+/// Outcome of a resume request
+#[derive(Debug)]
+pub enum Replay {
+    Messages(Vec<SequencedUpdate>),
+    Resync,
+}
+
+struct Buffer {
+    next_seq: u64,
+    recent: VecDeque<SequencedUpdate>,
+}
+
+/// Assigns sequence numbers to updates, keeps a bounded replay buffer and
+/// fans sequenced updates out to live subscribers
+pub struct Fanout {
+    buffer: Mutex<Buffer>,
+    capacity: usize,
+    live: broadcast::Sender<SequencedUpdate>,
+    /// Newest update per device sensor and when it was published, for heartbeats
+    latest: Mutex<HashMap<(String, usize), (TemperatureUpdate, Instant)>>,
+    /// Addresses of devices whose settings changed
+    settings: broadcast::Sender<String>,
+    suggestions: broadcast::Sender<TargetSuggestion>,
+    /// Suggestions nobody applied or dismissed yet, sent to clients as they connect
+    pending_suggestions: Mutex<HashMap<String, TargetSuggestion>>,
+}
+
+impl Fanout {
+    pub fn new(capacity: usize) -> Self {
+        let (live, _rx) = broadcast::channel(256);
+        let (settings, _rx) = broadcast::channel(32);
+        let (suggestions, _rx) = broadcast::channel(32);
+        Self {
+            buffer: Mutex::new(Buffer {
+                next_seq: 1,
+                recent: VecDeque::with_capacity(capacity),
+            }),
+            capacity,
+            live,
+            latest: Mutex::new(HashMap::new()),
+            settings,
+            suggestions,
+            pending_suggestions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sequence, buffer and broadcast an update; returns its sequence number
+    pub fn publish(&self, update: TemperatureUpdate) -> u64 {
+        self.publish_at(update, Instant::now())
+    }
+
+    fn publish_at(&self, update: TemperatureUpdate, now: Instant) -> u64 {
+        self.latest
+            .lock()
+            .unwrap()
+            .insert((update.device_address.clone(), update.sensor_index), (update.clone(), now));
+        let mut buffer = self.buffer.lock().unwrap();
+        let sequenced = SequencedUpdate {
+            seq: buffer.next_seq,
+            update,
+        };
+        buffer.next_seq += 1;
+
+        if buffer.recent.len() == self.capacity {
+            buffer.recent.pop_front();
+        }
+        buffer.recent.push_back(sequenced.clone());
+
+        // Sent under the lock so live order always matches sequence order
+        let _ = self.live.send(sequenced.clone());
+        sequenced.seq
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SequencedUpdate> {
+        self.live.subscribe()
+    }
+
+    /// Tell WebSocket clients a device's settings changed, confirming an edit made over REST
+    pub fn settings_changed(&self, device_address: &str) {
+        let _ = self.settings.send(device_address.to_string());
+    }
+
+    pub fn subscribe_settings(&self) -> broadcast::Receiver<String> {
+        self.settings.subscribe()
+    }
+
+    /// Offer dashboards a cook target; nothing is applied until one accepts it
+    ///
+    /// Kept until `clear_suggestion`, so dashboards that connect later see it too.
+    pub fn suggest_target(&self, suggestion: TargetSuggestion) {
+        self.pending_suggestions
+            .lock()
+            .unwrap()
+            .insert(suggestion.device_address.clone(), suggestion.clone());
+        let _ = self.suggestions.send(suggestion);
+    }
+
+    /// Suggestions still waiting for an answer, by device address
+    pub fn pending_suggestions(&self) -> Vec<TargetSuggestion> {
+        let mut pending: Vec<TargetSuggestion> = self.pending_suggestions.lock().unwrap().values().cloned().collect();
+        pending.sort_by(|a, b| a.device_address.cmp(&b.device_address));
+        pending
+    }
+
+    /// Forget a device's suggestion once it was applied or dismissed; false when there was none
+    pub fn clear_suggestion(&self, device_address: &str) -> bool {
+        self.pending_suggestions.lock().unwrap().remove(device_address).is_some()
+    }
+
+    /// Drop everything kept for a deleted device, so it gets no more heartbeats or suggestion
+    pub fn forget(&self, device_address: &str) {
+        self.latest.lock().unwrap().retain(|(address, _), _| address != device_address);
+        self.clear_suggestion(device_address);
+    }
+
+    pub fn subscribe_suggestions(&self) -> broadcast::Receiver<TargetSuggestion> {
+        self.suggestions.subscribe()
+    }
+
+    /// Sequence number of the most recent update (0 = none yet)
+    pub fn latest_seq(&self) -> u64 {
+        self.buffer.lock().unwrap().next_seq - 1
+    }
+
+    /// Heartbeats for every device sensor that has published nothing for at least `quiet_for`
+    pub fn heartbeats(&self, quiet_for: Duration, unit: TemperatureUnit) -> Vec<Heartbeat> {
+        self.heartbeats_at(quiet_for, unit, Instant::now())
+    }
+
+    fn heartbeats_at(&self, quiet_for: Duration, unit: TemperatureUnit, now: Instant) -> Vec<Heartbeat> {
+        let latest = self.latest.lock().unwrap();
+        let mut heartbeats: Vec<Heartbeat> = latest
+            .values()
+            .filter_map(|(update, published)| {
+                let age = now.saturating_duration_since(*published);
+                (age >= quiet_for).then(|| {
+                    let update = update.clone().in_unit(unit);
+                    Heartbeat {
+                        device_address: update.device_address,
+                        device_name: update.device_name,
+                        sensor_index: update.sensor_index,
+                        temperature: update.temperature,
+                        unit: update.unit,
+                        timestamp: update.timestamp,
+                        age_secs: age.as_secs(),
+                    }
+                })
+            })
+            .collect();
+        heartbeats.sort_by(|a, b| (&a.device_address, a.sensor_index).cmp(&(&b.device_address, b.sensor_index)));
+        heartbeats
+    }
+
+    /// Buffered updates after `last_seq`, or Resync if some were already evicted
+    pub fn replay_since(&self, last_seq: u64) -> Replay {
+        let buffer = self.buffer.lock().unwrap();
+        let latest = buffer.next_seq - 1;
+
+        // A client ahead of us saw a previous server instance
+        if last_seq > latest {
+            return Replay::Resync;
+        }
+        if last_seq == latest {
+            return Replay::Messages(Vec::new());
+        }
+
+        match buffer.recent.front() {
+            Some(oldest) if oldest.seq <= last_seq + 1 => Replay::Messages(
+                buffer
+                    .recent
+                    .iter()
+                    .filter(|m| m.seq > last_seq)
+                    .cloned()
+                    .collect(),
+            ),
+            _ => Replay::Resync,
+        }
+    }
+}
+
+/// Per-connection bookkeeping so replayed and live updates are never sent twice
+#[derive(Debug, Default)]
+pub struct ClientCursor {
+    first_live: Option<u64>,
+    last_sent: u64,
+}
+
+impl ClientCursor {
+    /// Returns true if a live update should be forwarded to the client
+    pub fn admit_live(&mut self, seq: u64) -> bool {
+        if seq <= self.last_sent {
+            return false;
+        }
+        self.first_live.get_or_insert(seq);
+        self.last_sent = seq;
+        true
+    }
+
+    /// The part of a replay this connection has not sent yet
+    ///
+    /// Updates are only ever sent in sequence order: a replay reaching back
+    /// before live updates already streamed becomes a Resync instead.
+    pub fn admit_replay(&mut self, replay: Replay) -> Replay {
+        let Replay::Messages(messages) = replay else {
+            return Replay::Resync;
+        };
+        if self.first_live.is_some_and(|first_live| messages.first().is_some_and(|m| m.seq < first_live)) {
+            return Replay::Resync;
+        }
+        let replay: Vec<SequencedUpdate> = messages.into_iter().filter(|m| m.seq > self.last_sent).collect();
+        if let Some(last) = replay.last() {
+            self.last_sent = last.seq;
+        }
+        Replay::Messages(replay)
+    }
+}
+
+/// Forward raw updates from producers into the fan-out until the channel closes
+pub async fn run_sequencer(fanout: Arc<Fanout>, mut rx: broadcast::Receiver<TemperatureUpdate>) {
+    loop {
+        match rx.recv().await {
+            Ok(update) => {
+                fanout.publish(update);
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Fan-out sequencer lagged, dropped {} updates", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                debug!("Update channel closed, stopping fan-out sequencer");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn update(temperature: f32) -> TemperatureUpdate {
+        TemperatureUpdate {
+            device_address: "AA:BB".to_string(),
+            device_name: "probe".to_string(),
+            timestamp: Utc::now(),
+            sensor_index: 0,
+            temperature,
+            ambient_temp: None,
+            unit: TemperatureUnit::Celsius,
+            battery_level: None,
+            signal_strength: -60,
+            eta_seconds: None,
+            reading_mode: Default::default(),
+            virtual_sensors: None,
+        }
+    }
+
+    fn seqs(messages: &[SequencedUpdate]) -> Vec<u64> {
+        messages.iter().map(|m| m.seq).collect()
+    }
+
+    #[test]
+    fn test_replay_since_last_seen() {
+        let fanout = Fanout::new(16);
+        for i in 0..5 {
+            fanout.publish(update(100.0 + i as f32));
+        }
+
+        match fanout.replay_since(2) {
+            Replay::Messages(messages) => {
+                assert_eq!(seqs(&messages), vec![3, 4, 5]);
+                assert_eq!(messages[0].update.temperature, 102.0);
+            }
+            Replay::Resync => panic!("expected replay"),
+        }
+        assert!(matches!(fanout.replay_since(5), Replay::Messages(m) if m.is_empty()));
+    }
+
+    #[test]
+    fn test_resync_when_buffer_overflowed() {
+        let fanout = Fanout::new(3);
+        for i in 0..10 {
+            fanout.publish(update(i as f32));
+        }
+
+        // 3..=7 were evicted
+        assert!(matches!(fanout.replay_since(2), Replay::Resync));
+        // Oldest buffered is 8, so resuming from 7 is still complete
+        assert!(matches!(fanout.replay_since(7), Replay::Messages(m) if seqs(&m) == vec![8, 9, 10]));
+        // Client from a previous server run
+        assert!(matches!(fanout.replay_since(50), Replay::Resync));
+    }
+
+    #[test]
+    fn test_replay_interleaved_with_live_is_sent_in_order() {
+        let fanout = Fanout::new(16);
+        for i in 0..3 {
+            fanout.publish(update(i as f32));
+        }
+
+        // Client reconnects: subscribes first, then more updates arrive before the replay
+        let mut live = fanout.subscribe();
+        let mut cursor = ClientCursor::default();
+        fanout.publish(update(3.0)); // seq 4, queued on the live channel
+        let mut sent = Vec::new();
+
+        // Resume from seq 1 before streaming live: replay covers 2..=4
+        let Replay::Messages(replay) = cursor.admit_replay(fanout.replay_since(1)) else {
+            panic!("expected replay");
+        };
+        sent.extend(seqs(&replay));
+
+        fanout.publish(update(4.0)); // seq 5
+        while let Ok(message) = live.try_recv() {
+            if cursor.admit_live(message.seq) {
+                sent.push(message.seq);
+            }
+        }
+        assert_eq!(sent, vec![2, 3, 4, 5], "4 is not sent again");
+    }
+
+    #[test]
+    fn test_late_resume_resyncs_instead_of_going_back() {
+        let fanout = Fanout::new(16);
+        for i in 0..3 {
+            fanout.publish(update(i as f32));
+        }
+        let mut live = fanout.subscribe();
+        let mut cursor = ClientCursor::default();
+        fanout.publish(update(3.0)); // seq 4
+        assert!(cursor.admit_live(live.try_recv().unwrap().seq));
+
+        // 2 and 3 would now arrive after 4
+        assert!(matches!(cursor.admit_replay(fanout.replay_since(1)), Replay::Resync));
+        // Nothing older than what was streamed: fine
+        assert!(matches!(cursor.admit_replay(fanout.replay_since(3)), Replay::Messages(m) if m.is_empty()));
+    }
+
+    #[test]
+    fn test_sequenced_update_wire_format() {
+        let json = serde_json::to_value(SequencedUpdate { seq: 7, update: update(150.0) }).unwrap();
+        assert_eq!(json["seq"], 7);
+        assert_eq!(json["temperature"], 150.0);
+
+        let resume: ClientMessage = serde_json::from_str(r#"{"type":"resume","last_seq":42}"#).unwrap();
+        assert!(matches!(resume, ClientMessage::Resume { last_seq: 42 }));
+    }
+
+    #[test]
+    fn test_heartbeats_only_for_quiet_devices() {
+        let fanout = Fanout::new(8);
+        let start = Instant::now();
+        let secs = |n| start + Duration::from_secs(n);
+        let quiet_for = Duration::from_secs(15);
+        fanout.publish_at(update(150.0), start);
+        let mut ambient = update(90.0);
+        ambient.sensor_index = 7;
+        fanout.publish_at(ambient, start);
+        let mut other = update(60.0);
+        other.device_address = "CC:DD".to_string();
+        fanout.publish_at(other.clone(), start);
+
+        // Both just reported: nothing to say yet
+        assert!(fanout.heartbeats_at(quiet_for, TemperatureUnit::Celsius, secs(10)).is_empty());
+
+        // A quiet stretch: one heartbeat per sensor with its last value and age,
+        // so a later ambient reading does not stand in for the probe's tip
+        let heartbeats = fanout.heartbeats_at(quiet_for, TemperatureUnit::Fahrenheit, secs(20));
+        let sensors: Vec<(&str, usize, f32)> =
+            heartbeats.iter().map(|h| (h.device_address.as_str(), h.sensor_index, h.temperature)).collect();
+        assert_eq!(sensors, [("AA:BB", 0, 302.0), ("AA:BB", 7, 194.0), ("CC:DD", 0, 140.0)]);
+        assert_eq!(heartbeats[0].age_secs, 20);
+
+        // A fresh reading from one device silences its heartbeat
+        other.temperature = 61.0;
+        fanout.publish_at(other, secs(25));
+        let heartbeats = fanout.heartbeats_at(quiet_for, TemperatureUnit::Celsius, secs(30));
+        assert_eq!(heartbeats.iter().map(|h| h.device_address.as_str()).collect::<Vec<_>>(), ["AA:BB", "AA:BB"]);
+        assert_eq!(heartbeats[0].age_secs, 30);
+
+        // Tagged on the wire so clients never mistake it for a reading
+        let json = serde_json::to_value(ControlMessage::Heartbeat(heartbeats[0].clone())).unwrap();
+        assert_eq!(json["type"], "heartbeat");
+        assert!(json.get("seq").is_none());
+    }
+
+    #[test]
+    fn test_pending_suggestions_until_cleared() {
+        let fanout = Fanout::new(8);
+        let suggestion = |address: &str| TargetSuggestion {
+            device_address: address.to_string(),
+            meat: "brisket".to_string(),
+            target: 203.0,
+            unit: TemperatureUnit::Fahrenheit,
+        };
+        fanout.suggest_target(suggestion("BB"));
+        fanout.suggest_target(suggestion("AA"));
+
+        // Made before anyone subscribed, still there for the next client
+        assert_eq!(fanout.pending_suggestions(), [suggestion("AA"), suggestion("BB")]);
+
+        assert!(fanout.clear_suggestion("AA"));
+        assert!(!fanout.clear_suggestion("AA"));
+        assert_eq!(fanout.pending_suggestions(), [suggestion("BB")]);
+    }
+
+    #[test]
+    fn test_forget_stops_heartbeats_and_suggestion() {
+        let fanout = Fanout::new(8);
+        let start = Instant::now();
+        fanout.publish_at(update(150.0), start);
+        let mut ambient = update(90.0);
+        ambient.sensor_index = 7;
+        fanout.publish_at(ambient, start);
+        let mut other = update(60.0);
+        other.device_address = "CC:DD".to_string();
+        fanout.publish_at(other, start);
+        let deleted = update(0.0).device_address;
+        fanout.suggest_target(TargetSuggestion {
+            device_address: deleted.clone(),
+            meat: "brisket".to_string(),
+            target: 203.0,
+            unit: TemperatureUnit::Fahrenheit,
+        });
+
+        fanout.forget(&deleted);
+
+        let later = start + Duration::from_secs(60);
+        let heartbeats = fanout.heartbeats_at(Duration::from_secs(15), TemperatureUnit::Celsius, later);
+        let addresses: Vec<&str> = heartbeats.iter().map(|h| h.device_address.as_str()).collect();
+        assert_eq!(addresses, ["CC:DD"], "every sensor of the deleted device is gone");
+        assert!(fanout.pending_suggestions().is_empty());
+    }
+}
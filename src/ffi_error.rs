@@ -0,0 +1,145 @@
+// src/ffi_error.rs
+use std::cell::Cell;
+
+use crate::ble::BleEnvironmentError;
+
+/// Status codes returned by the FFI exports
+///
+/// Values are part of the C ABI: never renumber a released code, only add new ones.
+#[allow(non_camel_case_types)]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BbqError {
+    BBQ_OK = 0,
+    BBQ_ERR_NO_ADAPTER = -1,
+    BBQ_ERR_BLE_OFF = -2,
+    BBQ_ERR_ALREADY_RUNNING = -3,
+    BBQ_ERR_BLE_PERMISSION = -4,
+    BBQ_ERR_BLE_UNSUPPORTED = -5,
+    BBQ_ERR_BLE_BUSY = -6,
+    BBQ_ERR_BLE = -7,
+    BBQ_ERR_NOT_INITIALIZED = -8,
+    BBQ_ERR_INVALID_ARGUMENT = -9,
+    BBQ_ERR_RUNTIME = -10,
+    BBQ_ERR_DATABASE = -11,
+    BBQ_ERR_CONFIG = -12,
+    BBQ_ERR_INVALID_LICENSE = -13,
+    BBQ_ERR_SERIALIZATION = -14,
+    BBQ_ERR_IO = -15,
+}
+
+impl BbqError {
+    pub const ALL: [BbqError; 16] = [
+        Self::BBQ_OK,
+        Self::BBQ_ERR_NO_ADAPTER,
+        Self::BBQ_ERR_BLE_OFF,
+        Self::BBQ_ERR_ALREADY_RUNNING,
+        Self::BBQ_ERR_BLE_PERMISSION,
+        Self::BBQ_ERR_BLE_UNSUPPORTED,
+        Self::BBQ_ERR_BLE_BUSY,
+        Self::BBQ_ERR_BLE,
+        Self::BBQ_ERR_NOT_INITIALIZED,
+        Self::BBQ_ERR_INVALID_ARGUMENT,
+        Self::BBQ_ERR_RUNTIME,
+        Self::BBQ_ERR_DATABASE,
+        Self::BBQ_ERR_CONFIG,
+        Self::BBQ_ERR_INVALID_LICENSE,
+        Self::BBQ_ERR_SERIALIZATION,
+        Self::BBQ_ERR_IO,
+    ];
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|e| e.code() == code)
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::BBQ_OK => "Success",
+            Self::BBQ_ERR_NO_ADAPTER => "No Bluetooth adapters found",
+            Self::BBQ_ERR_BLE_OFF => "Bluetooth adapter is powered off",
+            Self::BBQ_ERR_ALREADY_RUNNING => "Background monitor is already running",
+            Self::BBQ_ERR_BLE_PERMISSION => "Missing Bluetooth permission",
+            Self::BBQ_ERR_BLE_UNSUPPORTED => "Bluetooth is not supported on this device",
+            Self::BBQ_ERR_BLE_BUSY => "Bluetooth adapter is busy",
+            Self::BBQ_ERR_BLE => "Bluetooth error (see ble_last_error for details)",
+            Self::BBQ_ERR_NOT_INITIALIZED => "BLE manager not initialized; call ble_initialize first",
+            Self::BBQ_ERR_INVALID_ARGUMENT => "Invalid argument (null pointer, bad UTF-8 or bad timestamp)",
+            Self::BBQ_ERR_RUNTIME => "Failed to start the async runtime",
+            Self::BBQ_ERR_DATABASE => "Database error",
+            Self::BBQ_ERR_CONFIG => "Failed to load or save the config file",
+            Self::BBQ_ERR_INVALID_LICENSE => "License key is not a valid premium license",
+            Self::BBQ_ERR_SERIALIZATION => "Failed to encode the result as JSON",
+            Self::BBQ_ERR_IO => "Failed to write the output file",
+        }
+    }
+}
+
+impl From<&BleEnvironmentError> for BbqError {
+    fn from(error: &BleEnvironmentError) -> Self {
+        match error {
+            BleEnvironmentError::MissingPermission { .. } => Self::BBQ_ERR_BLE_PERMISSION,
+            BleEnvironmentError::AdapterOff => Self::BBQ_ERR_BLE_OFF,
+            BleEnvironmentError::NotSupported => Self::BBQ_ERR_BLE_UNSUPPORTED,
+            BleEnvironmentError::Busy => Self::BBQ_ERR_BLE_BUSY,
+            BleEnvironmentError::NoAdapter => Self::BBQ_ERR_NO_ADAPTER,
+            BleEnvironmentError::Other { .. } => Self::BBQ_ERR_BLE,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: Cell<BbqError> = const { Cell::new(BbqError::BBQ_OK) };
+}
+
+/// Record the outcome of a pointer-returning FFI call on this thread
+pub(crate) fn set_last_error(error: BbqError) {
+    LAST_ERROR.with(|last| last.set(error));
+}
+
+/// Outcome of the last pointer-returning FFI call made on this thread
+pub fn last_error() -> BbqError {
+    LAST_ERROR.with(|last| last.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_stable_and_unique() {
+        assert_eq!(BbqError::BBQ_OK.code(), 0);
+        assert_eq!(BbqError::BBQ_ERR_NO_ADAPTER.code(), -1);
+        assert_eq!(BbqError::BBQ_ERR_BLE_OFF.code(), -2);
+        assert_eq!(BbqError::BBQ_ERR_ALREADY_RUNNING.code(), -3);
+
+        for error in BbqError::ALL {
+            assert_eq!(BbqError::from_code(error.code()), Some(error));
+            assert!(!error.message().is_empty());
+        }
+        assert_eq!(BbqError::from_code(1), None);
+        assert_eq!(BbqError::from_code(-99), None);
+    }
+
+    #[test]
+    fn test_ble_errors_map_to_codes() {
+        assert_eq!(BbqError::from(&BleEnvironmentError::AdapterOff), BbqError::BBQ_ERR_BLE_OFF);
+        assert_eq!(BbqError::from(&BleEnvironmentError::NoAdapter), BbqError::BBQ_ERR_NO_ADAPTER);
+        assert_eq!(
+            BbqError::from(&BleEnvironmentError::MissingPermission { hint: String::new() }),
+            BbqError::BBQ_ERR_BLE_PERMISSION
+        );
+    }
+
+    #[test]
+    fn test_last_error_is_per_thread() {
+        set_last_error(BbqError::BBQ_ERR_DATABASE);
+        let other = std::thread::spawn(last_error).join().unwrap();
+        assert_eq!(other, BbqError::BBQ_OK);
+        assert_eq!(last_error(), BbqError::BBQ_ERR_DATABASE);
+        set_last_error(BbqError::BBQ_OK);
+    }
+}
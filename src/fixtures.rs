@@ -0,0 +1,404 @@
+// src/fixtures.rs
+//! Recorded cook sessions, stored as gzipped JSON for tests to replay
+//!
+//! `bbq-monitor db export-fixture` writes one session — readings, device
+//! notes, what the device is and how it was set up — with every timestamp
+//! as an offset from the session start. Exports are anonymized: the address,
+//! names, note texts and the date are replaced before anything is written.
+//!
+//! Readers ignore fields they do not know, so adding a field does not need a
+//! new format version; `FIXTURE_FORMAT_VERSION` only changes when an existing
+//! field changes meaning, and older readers refuse such files.
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::analytics::SmokerProfile;
+use crate::database::{CookSession, Database};
+use crate::maintenance::NoteCategory;
+use crate::protocol::ReadingMode;
+use crate::temperature::Temperature;
+
+/// Newest fixture format this build reads and the one it writes
+pub const FIXTURE_FORMAT_VERSION: u32 = 1;
+
+/// Address every anonymized fixture device gets
+pub const FIXTURE_DEVICE_ADDRESS: &str = "00:00:00:00:00:01";
+
+pub const EXPORT_FIXTURE_USAGE: &str = "Usage: bbq-monitor db export-fixture --session <id> --out <file.json.gz>";
+
+/// One exported cook session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionFixture {
+    pub format_version: u32,
+    pub metadata: FixtureMetadata,
+    pub device: FixtureDevice,
+    pub session: FixtureSession,
+    /// Every sensor's readings, oldest first
+    pub readings: Vec<FixtureReading>,
+    pub events: Vec<FixtureEvent>,
+}
+
+impl Default for SessionFixture {
+    fn default() -> Self {
+        Self {
+            format_version: FIXTURE_FORMAT_VERSION,
+            metadata: FixtureMetadata::default(),
+            device: FixtureDevice::default(),
+            session: FixtureSession::default(),
+            readings: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+/// Where a fixture came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureMetadata {
+    /// Version of bbq-monitor that exported it
+    pub app_version: String,
+    pub exported_at: Option<DateTime<Utc>>,
+}
+
+/// The device a session was cooked on, and how it was set up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureDevice {
+    pub address: String,
+    pub name: String,
+    pub brand: String,
+    pub model: String,
+    pub sensor_count: usize,
+    /// Sensor chosen as primary (None = the model's default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_sensor: Option<usize>,
+    /// Smoker profile efficiency, when set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoker_efficiency: Option<f32>,
+}
+
+impl Default for FixtureDevice {
+    fn default() -> Self {
+        Self {
+            address: FIXTURE_DEVICE_ADDRESS.to_string(),
+            name: String::new(),
+            brand: String::new(),
+            model: String::new(),
+            sensor_count: 1,
+            primary_sensor: None,
+            smoker_efficiency: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureSession {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_c: Option<f32>,
+    pub started_at: DateTime<Utc>,
+    /// None while the session was still open at export
+    pub duration_ms: Option<i64>,
+}
+
+impl Default for FixtureSession {
+    fn default() -> Self {
+        Self { name: String::new(), target_c: None, started_at: fixture_epoch(), duration_ms: None }
+    }
+}
+
+/// One stored reading; optional fields are left out when empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureReading {
+    /// Milliseconds after the session start
+    pub offset_ms: i64,
+    pub sensor: usize,
+    pub temp_c: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient_c: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery: Option<u8>,
+    pub rssi: i16,
+    #[serde(skip_serializing_if = "is_normal_mode")]
+    pub mode: ReadingMode,
+}
+
+fn is_normal_mode(mode: &ReadingMode) -> bool {
+    *mode == ReadingMode::Normal
+}
+
+/// A device note written during the session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureEvent {
+    pub offset_ms: i64,
+    pub category: NoteCategory,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+impl Default for FixtureEvent {
+    fn default() -> Self {
+        Self { offset_ms: 0, category: NoteCategory::Note, text: String::new() }
+    }
+}
+
+/// Start date of anonymized sessions: only offsets from it mean anything
+fn fixture_epoch() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()
+}
+
+impl SessionFixture {
+    /// Read a session and everything recorded on its device while it ran
+    pub async fn export(db: &Database, session_id: i64) -> Result<Self> {
+        let session = db
+            .get_cook_stats(session_id)
+            .await
+            .with_context(|| format!("Unknown cook session {}", session_id))?
+            .session;
+        let device = db.get_device(&session.device_address).await?;
+        let start = session.started_at;
+        let end = session.ended_at.unwrap_or_else(Utc::now);
+        let offset_ms = |t: DateTime<Utc>| (t - start).num_milliseconds();
+
+        let readings = db
+            .get_readings_in_range(&session.device_address, start, end)
+            .await?
+            .into_iter()
+            .map(|r| FixtureReading {
+                offset_ms: offset_ms(r.timestamp),
+                sensor: r.sensor_index as usize,
+                temp_c: r.temperature.celsius(),
+                ambient_c: r.ambient_temp.map(|t| t.celsius()),
+                battery: r.battery_level,
+                rssi: r.signal_strength,
+                mode: r.reading_mode,
+            })
+            .collect();
+        let mut events: Vec<FixtureEvent> = db
+            .get_device_notes(&session.device_address, None)
+            .await?
+            .into_iter()
+            .filter(|note| note.timestamp >= start && note.timestamp <= end)
+            .map(|note| FixtureEvent { offset_ms: offset_ms(note.timestamp), category: note.category, text: note.text })
+            .collect();
+        events.sort_by_key(|e| e.offset_ms);
+
+        let profile = db.get_smoker_profile(&session.device_address).await?;
+        Ok(Self {
+            format_version: FIXTURE_FORMAT_VERSION,
+            metadata: FixtureMetadata {
+                app_version: env!("CARGO_PKG_VERSION").to_string(),
+                exported_at: Some(Utc::now()),
+            },
+            device: FixtureDevice {
+                address: device.device_address,
+                name: device.device_name,
+                brand: device.brand,
+                model: device.model,
+                sensor_count: device.sensor_count as usize,
+                primary_sensor: db.get_primary_sensor(&session.device_address).await?,
+                smoker_efficiency: (profile != SmokerProfile::default()).then_some(profile.efficiency),
+            },
+            session: FixtureSession {
+                name: session.name,
+                target_c: session.target_temp.map(|t| t.celsius()),
+                started_at: start,
+                duration_ms: session.ended_at.map(offset_ms),
+            },
+            readings,
+            events,
+        })
+    }
+
+    /// Strip what identifies the cook: address, names, note texts and the date
+    pub fn anonymized(mut self) -> Self {
+        self.device.address = FIXTURE_DEVICE_ADDRESS.to_string();
+        self.device.name = self.device.model.clone();
+        self.session.name = "Cook".to_string();
+        self.session.started_at = fixture_epoch();
+        self.metadata.exported_at = None;
+        for event in &mut self.events {
+            event.text.clear();
+        }
+        self
+    }
+
+    pub fn write_gz(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = GzEncoder::new(file, Compression::best());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish().with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn read_gz(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let fixture: Self = serde_json::from_reader(GzDecoder::new(file))
+            .with_context(|| format!("{} is not a session fixture", path.display()))?;
+        if fixture.format_version > FIXTURE_FORMAT_VERSION {
+            bail!(
+                "{} is fixture format {}; this build reads up to {}",
+                path.display(),
+                fixture.format_version,
+                FIXTURE_FORMAT_VERSION
+            );
+        }
+        Ok(fixture)
+    }
+
+    /// Recreate the device, its readings, notes and the session in `db`
+    pub async fn load_into(&self, db: &Database) -> Result<CookSession> {
+        let (device, start) = (&self.device, self.session.started_at);
+        let at = |offset_ms: i64| start + Duration::milliseconds(offset_ms);
+
+        db.upsert_device(&device.address, &device.name, &device.brand, &device.model, device.sensor_count)
+            .await?;
+        if device.primary_sensor.is_some() {
+            db.set_primary_sensor(&device.address, device.primary_sensor).await?;
+        }
+        if let Some(efficiency) = device.smoker_efficiency {
+            db.set_smoker_profile(&device.address, &SmokerProfile { efficiency, notes: None }).await?;
+        }
+        for r in &self.readings {
+            db.insert_reading_with_mode(
+                &device.address,
+                at(r.offset_ms),
+                r.sensor,
+                Temperature::from_celsius(r.temp_c),
+                r.ambient_c.map(Temperature::from_celsius),
+                r.battery,
+                r.rssi,
+                r.mode,
+            )
+            .await?;
+        }
+        for event in &self.events {
+            db.insert_device_note(&device.address, at(event.offset_ms), event.category, &event.text).await?;
+        }
+        db.insert_cook_session(
+            &device.address,
+            &self.session.name,
+            self.session.target_c.map(Temperature::from_celsius),
+            start,
+            self.session.duration_ms.map(at),
+        )
+        .await
+    }
+}
+
+/// Options of `bbq-monitor db export-fixture`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportFixtureArgs {
+    pub session_id: i64,
+    pub out: PathBuf,
+}
+
+impl ExportFixtureArgs {
+    /// Parse the arguments following `export-fixture`
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let (mut session_id, mut out) = (None, None);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--session" => {
+                    let value = args.next().context("--session needs a cook session id")?;
+                    session_id = Some(value.parse().with_context(|| format!("Invalid --session '{}'", value))?);
+                }
+                "--out" => out = Some(PathBuf::from(args.next().context("--out needs a file name")?)),
+                other => bail!("Unknown option '{}'\n{}", other, EXPORT_FIXTURE_USAGE),
+            }
+        }
+        match (session_id, out) {
+            (Some(session_id), Some(out)) => Ok(Self { session_id, out }),
+            _ => bail!("{}", EXPORT_FIXTURE_USAGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_export_anonymizes_and_round_trips() {
+        let db = Database::new(":memory:").await.unwrap();
+        let address = "C8:1F:66:0A:12:34";
+        db.upsert_device(address, "Dad's MEATER 7", "Meater", "MEATER", 2).await.unwrap();
+        let start = Utc::now() - Duration::hours(2);
+        for minute in 0..30 {
+            let t = start + Duration::minutes(minute);
+            db.insert_reading(address, t, 0, Temperature::from_celsius(20.0 + minute as f32), Some(Temperature::from_celsius(110.0)), Some(90), -58)
+                .await
+                .unwrap();
+        }
+        db.insert_device_note(address, start + Duration::minutes(10), NoteCategory::Battery, "swapped at Sam's")
+            .await
+            .unwrap();
+        let session = db
+            .insert_cook_session(address, "Sam's birthday ribs", None, start, Some(start + Duration::minutes(29)))
+            .await
+            .unwrap();
+
+        let fixture = SessionFixture::export(&db, session.id).await.unwrap().anonymized();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ribs.json.gz");
+        fixture.write_gz(&path).unwrap();
+
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut GzDecoder::new(std::fs::File::open(&path).unwrap()), &mut json).unwrap();
+        for private in [address, "Dad", "Sam", &start.date_naive().to_string()] {
+            assert!(!json.contains(private), "{} leaked into {}", private, json);
+        }
+
+        let loaded = SessionFixture::read_gz(&path).unwrap();
+        assert_eq!(loaded, fixture);
+        assert_eq!(loaded.readings.len(), 30);
+        assert_eq!(loaded.readings[29].offset_ms, 29 * 60_000);
+        assert_eq!(loaded.events[0].category, NoteCategory::Battery);
+
+        let replay = Database::new(":memory:").await.unwrap();
+        let replayed = loaded.load_into(&replay).await.unwrap();
+        let stats = replay.get_cook_stats(replayed.id).await.unwrap();
+        assert_eq!(stats.reading_count, 30);
+        assert_eq!(stats.duration_secs, 29 * 60);
+        assert_eq!(stats.max_temp, Some(Temperature::from_celsius(49.0)));
+    }
+
+    #[test]
+    fn test_unknown_fields_ignored_and_newer_versions_refused() {
+        let json = r#"{"format_version":1,"device":{"model":"TP25","sensor_count":4,"probe_color":"red"},
+                       "readings":[{"offset_ms":1000,"sensor":2,"temp_c":51.5,"humidity":40}],
+                       "annotations":[]}"#;
+        let fixture: SessionFixture = serde_json::from_str(json).unwrap();
+        assert_eq!(fixture.device.sensor_count, 4);
+        assert_eq!(fixture.device.address, FIXTURE_DEVICE_ADDRESS);
+        assert_eq!(fixture.readings[0].sensor, 2);
+        assert_eq!(fixture.readings[0].mode, ReadingMode::Normal);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("future.json.gz");
+        SessionFixture { format_version: FIXTURE_FORMAT_VERSION + 1, ..fixture }.write_gz(&path).unwrap();
+        let error = SessionFixture::read_gz(&path).unwrap_err();
+        assert!(format!("{:#}", error).contains("this build reads up to"), "{:#}", error);
+    }
+
+    #[test]
+    fn test_export_fixture_args() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            ExportFixtureArgs::parse(&args(&["--session", "12", "--out", "tests/fixtures/ribs.json.gz"])).unwrap(),
+            ExportFixtureArgs { session_id: 12, out: PathBuf::from("tests/fixtures/ribs.json.gz") }
+        );
+        assert!(ExportFixtureArgs::parse(&args(&["--session", "12"])).is_err());
+        assert!(ExportFixtureArgs::parse(&args(&["--session", "twelve", "--out", "x"])).is_err());
+    }
+}
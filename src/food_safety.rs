@@ -0,0 +1,138 @@
+// src/food_safety.rs
+use chrono::Duration;
+use serde::Serialize;
+
+use crate::database::ReadingRecord;
+use crate::temperature::TemperatureUnit;
+
+/// USDA "danger zone" where bacteria multiply fastest (°F)
+pub const DANGER_ZONE_LOW_F: f32 = 40.0;
+pub const DANGER_ZONE_HIGH_F: f32 = 140.0;
+
+/// Shaded region or marker line for temperature charts
+///
+/// Values are in the unit named by `unit`. A band with `from == to`
+/// is a marker line rather than a shaded region.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChartBand {
+    pub label: String,
+    pub from: f32,
+    pub to: f32,
+    /// Semantic color hint ("danger", "safe") for the client to map to its palette
+    pub color: String,
+}
+
+/// USDA minimum safe internal temperature for a meat type (°F)
+pub fn usda_minimum_f(meat: &str) -> Option<f32> {
+    match meat.to_lowercase().as_str() {
+        "poultry" | "chicken" | "turkey" | "duck" => Some(165.0),
+        "ground" | "ground_beef" | "ground_pork" | "sausage" | "burger" => Some(160.0),
+        "beef" | "brisket" | "steak" | "pork" | "pork_shoulder" | "ribs" | "lamb" | "veal"
+        | "ham" | "fish" => Some(145.0),
+        _ => None,
+    }
+}
+
+/// Build chart bands for the configured unit and optional meat type
+pub fn chart_bands(unit: &str, meat: Option<&str>) -> Vec<ChartBand> {
+    let unit = TemperatureUnit::parse(unit).unwrap_or_default();
+    // Straight from °F with one rounding; a Temperature would first snap to 0.05°C
+    let convert = |f: f32| match unit {
+        TemperatureUnit::Fahrenheit => f,
+        TemperatureUnit::Celsius => ((f - 32.0) * 5.0 / 9.0 * 10.0).round() / 10.0,
+    };
+
+    let mut bands = vec![ChartBand {
+        label: "Danger zone".to_string(),
+        from: convert(DANGER_ZONE_LOW_F),
+        to: convert(DANGER_ZONE_HIGH_F),
+        color: "danger".to_string(),
+    }];
+
+    if let Some(meat) = meat {
+        if let Some(minimum) = usda_minimum_f(meat) {
+            bands.push(ChartBand {
+                label: format!("USDA minimum ({})", meat),
+                from: convert(minimum),
+                to: convert(minimum),
+                color: "safe".to_string(),
+            });
+        }
+    }
+
+    bands
+}
+
+/// Total time a single sensor's readings spent inside the danger zone
+///
+/// Readings must be for one sensor and sorted by timestamp. Each interval
+/// between consecutive readings counts if it starts inside the zone.
+pub fn time_in_danger_zone(readings: &[ReadingRecord]) -> Duration {
+    readings
+        .windows(2)
+        .filter(|pair| {
+            (DANGER_ZONE_LOW_F..=DANGER_ZONE_HIGH_F).contains(&pair[0].temperature.fahrenheit())
+        })
+        .map(|pair| pair[1].timestamp - pair[0].timestamp)
+        .fold(Duration::zero(), |total, span| total + span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temperature::Temperature;
+    use chrono::{TimeZone, Utc};
+
+    /// Reading at `temperature` °F
+    fn reading(minutes: i64, temperature: f32) -> ReadingRecord {
+        ReadingRecord {
+            device_address: "AA:BB".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 20, 12, 0, 0).unwrap() + Duration::minutes(minutes),
+            sensor_index: 0,
+            temperature: Temperature::from_fahrenheit(temperature),
+            ambient_temp: None,
+            battery_level: None,
+            signal_strength: 0,
+            reading_mode: crate::protocol::ReadingMode::Normal,
+            source: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_bands_fahrenheit() {
+        let bands = chart_bands("fahrenheit", Some("poultry"));
+        assert_eq!(bands.len(), 2);
+        assert_eq!(bands[0].from, 40.0);
+        assert_eq!(bands[0].to, 140.0);
+        assert_eq!(bands[1].from, 165.0);
+        assert_eq!(bands[1].to, 165.0);
+    }
+
+    #[test]
+    fn test_bands_celsius() {
+        let bands = chart_bands("celsius", Some("brisket"));
+        // 40°F is 4.44°C
+        assert_eq!(bands[0].from, 4.4);
+        assert_eq!(bands[0].to, 60.0);
+        assert_eq!(bands[1].from, 62.8);
+    }
+
+    #[test]
+    fn test_unknown_meat_only_danger_zone() {
+        assert_eq!(chart_bands("fahrenheit", Some("tofu")).len(), 1);
+        assert_eq!(chart_bands("fahrenheit", None).len(), 1);
+    }
+
+    #[test]
+    fn test_time_in_danger_zone() {
+        let readings = vec![
+            reading(0, 38.0),   // below zone
+            reading(10, 60.0),  // in zone for 20 minutes
+            reading(30, 139.0), // in zone for 15 minutes
+            reading(45, 150.0), // above zone
+            reading(60, 155.0),
+        ];
+        assert_eq!(time_in_danger_zone(&readings), Duration::minutes(35));
+        assert_eq!(time_in_danger_zone(&readings[..1]), Duration::zero());
+    }
+}
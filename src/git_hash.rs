@@ -0,0 +1,16 @@
+// src/git_hash.rs
+// Shared by build.rs (via include!) and the version tests; keep it std-only.
+
+/// Short git commit hash for the checkout at `dir`, or "unknown" outside a git checkout
+pub fn git_hash(dir: &std::path::Path) -> String {
+    std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
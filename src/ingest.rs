@@ -0,0 +1,175 @@
+// src/ingest.rs
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Per-device token bucket capping how often readings are stored
+///
+/// Each stored sample (one packet, all its sensors) takes a token; tokens
+/// refill at `max_per_min`. A sample arriving with no token left is held
+/// back, replacing any sample held before it, so a burst collapses into its
+/// newest reading and `flush` stores that once a token is free again.
+pub struct IngestThrottle<T> {
+    max_per_min: u32,
+    burst: f64,
+    buckets: HashMap<String, Bucket<T>>,
+}
+
+struct Bucket<T> {
+    tokens: f64,
+    refilled_at: Instant,
+    /// Newest sample held back while out of tokens
+    pending: Option<T>,
+    /// Samples merged away since throttling kicked in
+    dropped: u64,
+}
+
+impl<T> Bucket<T> {
+    fn refill(&mut self, now: Instant, per_sec: f64, burst: f64) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(burst);
+        self.refilled_at = now;
+    }
+
+    fn take(&mut self) -> bool {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<T> IngestThrottle<T> {
+    /// `max_per_min` of 0 disables throttling; `burst` samples may be stored back to back
+    pub fn new(max_per_min: u32, burst: u32) -> Self {
+        Self {
+            max_per_min,
+            burst: burst.max(1) as f64,
+            buckets: HashMap::new(),
+        }
+    }
+
+    fn per_sec(&self) -> f64 {
+        self.max_per_min as f64 / 60.0
+    }
+
+    /// Offer a sample; returns it if it should be stored now, otherwise holds it back
+    pub fn offer(&mut self, device_address: &str, sample: T) -> Option<T> {
+        self.offer_at(device_address, sample, Instant::now())
+    }
+
+    fn offer_at(&mut self, device_address: &str, sample: T, now: Instant) -> Option<T> {
+        if self.max_per_min == 0 {
+            return Some(sample);
+        }
+        let (per_sec, burst) = (self.per_sec(), self.burst);
+        let bucket = self.buckets.entry(device_address.to_string()).or_insert_with(|| Bucket {
+            tokens: burst,
+            refilled_at: now,
+            pending: None,
+            dropped: 0,
+        });
+        bucket.refill(now, per_sec, burst);
+
+        if bucket.take() {
+            // This sample is newer than anything held back
+            if bucket.pending.take().is_some() {
+                bucket.dropped += 1;
+            }
+            if bucket.dropped > 0 {
+                info!("🚦 {} throttled: merged {} readings", device_address, bucket.dropped);
+                bucket.dropped = 0;
+            }
+            return Some(sample);
+        }
+
+        if bucket.pending.replace(sample).is_some() {
+            bucket.dropped += 1;
+        } else if bucket.dropped == 0 {
+            warn!(
+                "🚦 {} is sending faster than {} readings/min, keeping only the latest",
+                device_address,
+                self.max_per_min
+            );
+        }
+        None
+    }
+
+    /// Held-back samples whose device has a token again
+    pub fn flush(&mut self) -> Vec<(String, T)> {
+        self.flush_at(Instant::now())
+    }
+
+    fn flush_at(&mut self, now: Instant) -> Vec<(String, T)> {
+        let (per_sec, burst) = (self.per_sec(), self.burst);
+        let mut ready = Vec::new();
+        for (address, bucket) in &mut self.buckets {
+            if bucket.pending.is_none() {
+                continue;
+            }
+            bucket.refill(now, per_sec, burst);
+            if bucket.take() {
+                ready.extend(bucket.pending.take().map(|sample| (address.clone(), sample)));
+            }
+        }
+        ready
+    }
+
+    /// Every held-back sample regardless of tokens, e.g. before disconnecting
+    pub fn drain(&mut self) -> Vec<(String, T)> {
+        self.buckets
+            .iter_mut()
+            .filter_map(|(address, bucket)| bucket.pending.take().map(|sample| (address.clone(), sample)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_burst_is_capped_and_keeps_latest() {
+        let mut throttle = IngestThrottle::new(60, 5);
+        let start = Instant::now();
+        let mut stored = Vec::new();
+
+        // 100 readings per second for a minute
+        for i in 0..6000u32 {
+            let now = start + Duration::from_millis(i as u64 * 10);
+            stored.extend(throttle.offer_at("AA", i, now));
+            stored.extend(throttle.flush_at(now).into_iter().map(|(_, sample)| sample));
+        }
+        // One per second plus the initial burst
+        assert!(stored.len() <= 60 + 5, "stored {}", stored.len());
+        assert!(stored.len() >= 60, "stored {}", stored.len());
+        assert!(stored.windows(2).all(|w| w[0] < w[1]), "stored in order");
+
+        // The newest sample of the burst is never lost
+        let end = start + Duration::from_secs(60);
+        let last = throttle
+            .offer_at("AA", 9999, end)
+            .or_else(|| throttle.flush_at(end + Duration::from_secs(2)).pop().map(|(_, sample)| sample));
+        assert_eq!(last, Some(9999));
+    }
+
+    #[test]
+    fn test_devices_are_throttled_separately() {
+        let mut throttle = IngestThrottle::new(6, 1);
+        let now = Instant::now();
+        assert_eq!(throttle.offer_at("AA", 1, now), Some(1));
+        assert_eq!(throttle.offer_at("AA", 2, now), None);
+        assert_eq!(throttle.offer_at("BB", 3, now), Some(3));
+
+        assert_eq!(throttle.offer_at("AA", 4, now + Duration::from_secs(1)), None);
+        assert!(throttle.flush_at(now + Duration::from_secs(5)).is_empty());
+        assert_eq!(throttle.flush_at(now + Duration::from_secs(10)), vec![("AA".to_string(), 4)]);
+        assert!(throttle.drain().is_empty());
+
+        let mut off = IngestThrottle::new(0, 1);
+        assert!((0..100).all(|i| off.offer_at("AA", i, now) == Some(i)));
+    }
+}
@@ -0,0 +1,295 @@
+// src/license_store.rs
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::{Database, Setting};
+use crate::premium::{License, LicenseValidator, PremiumTier};
+
+/// `settings` key holding a license activated without a writable config file
+pub const LICENSE_SETTING: &str = "premium.license_key";
+
+/// Where the license key in effect was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseSource {
+    Config,
+    Database,
+}
+
+impl LicenseSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::Database => "database",
+        }
+    }
+}
+
+/// A license key and where it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedLicenseKey {
+    pub key: String,
+    pub source: LicenseSource,
+}
+
+/// Pick the license key from config.toml and the database
+///
+/// Config is checked first and the database is the fallback. When both hold
+/// different keys the most recently activated one wins, with a warning; a
+/// config key of unknown age (e.g. from an environment variable) wins.
+pub fn resolve_license_key(
+    config_key: &str,
+    config_activated_at: Option<DateTime<Utc>>,
+    stored: Option<&Setting>,
+) -> Option<ResolvedLicenseKey> {
+    let config_key = config_key.trim();
+    let stored = stored.filter(|s| !s.value.trim().is_empty());
+    let resolved = |key: &str, source| Some(ResolvedLicenseKey { key: key.to_string(), source });
+
+    match stored {
+        None if config_key.is_empty() => None,
+        None => resolved(config_key, LicenseSource::Config),
+        Some(stored) if config_key.is_empty() => resolved(stored.value.trim(), LicenseSource::Database),
+        Some(stored) if stored.value.trim() == config_key => resolved(config_key, LicenseSource::Config),
+        Some(stored) => {
+            let database_newer = config_activated_at.is_some_and(|config_at| stored.updated_at > config_at);
+            let (key, source) = if database_newer {
+                (stored.value.trim(), LicenseSource::Database)
+            } else {
+                (config_key, LicenseSource::Config)
+            };
+            warn!(
+                "⚠️  config.toml and the database hold different license keys; using the more recently activated one from the {}",
+                source.as_str()
+            );
+            resolved(key, source)
+        }
+    }
+}
+
+/// When the config's key was activated: `premium.activated_at`, else the file's modification time
+fn config_activated_at(config: &Config, config_path: Option<&Path>) -> Option<DateTime<Utc>> {
+    config.premium.activated_at.or_else(|| {
+        let modified = config_path?.metadata().ok()?.modified().ok()?;
+        Some(DateTime::<Utc>::from(modified))
+    })
+}
+
+/// Resolve the license key from a loaded config and the database
+pub async fn load_license_key(
+    config: &Config,
+    config_path: Option<&Path>,
+    db: &Database,
+) -> Result<Option<ResolvedLicenseKey>> {
+    let stored = db.get_setting(LICENSE_SETTING).await?;
+    Ok(resolve_license_key(
+        &config.premium.license_key,
+        config_activated_at(config, config_path),
+        stored.as_ref(),
+    ))
+}
+
+/// Persist an activated key: in config.toml when the file exists and is writable, otherwise in the database
+pub async fn store_license_key(key: &str, config_path: Option<&Path>, db: &Database) -> Result<LicenseSource> {
+    let writable = config_path.filter(|path| path.metadata().is_ok_and(|m| !m.permissions().readonly()));
+    if let Some(path) = writable {
+        match Config::store_license_key(path, key) {
+            Ok(()) => return Ok(LicenseSource::Config),
+            Err(e) => warn!("⚠️  Could not write the license to {}: {:#}", path.display(), e),
+        }
+    }
+    db.set_setting(LICENSE_SETTING, key).await?;
+    Ok(LicenseSource::Database)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ActivationError {
+    #[error("License key does not grant Premium")]
+    InvalidKey,
+    #[error("Failed to store the license: {0:#}")]
+    Storage(anyhow::Error),
+}
+
+struct ActiveLicense {
+    license: Arc<License>,
+    source: Option<LicenseSource>,
+}
+
+/// The license in effect, swapped in place when a new key is activated
+///
+/// Shared by the web server and the monitor, so an activation takes effect
+/// without a restart.
+pub struct LicenseManager {
+    validator: LicenseValidator,
+    config_path: Option<PathBuf>,
+    db: Arc<Database>,
+    active: RwLock<ActiveLicense>,
+}
+
+impl LicenseManager {
+    /// Resolve and validate the key from config and database
+    pub async fn load(
+        validator: LicenseValidator,
+        config: &Config,
+        config_path: Option<PathBuf>,
+        db: Arc<Database>,
+    ) -> Result<Self> {
+        let resolved = load_license_key(config, config_path.as_deref(), &db).await?;
+        let (license, source) = match resolved {
+            Some(resolved) => {
+                let license = validator
+                    .validate(&resolved.key)
+                    .with_context(|| format!("Invalid license key in the {}", resolved.source.as_str()))?;
+                (license, Some(resolved.source))
+            }
+            None => (License::free(), None),
+        };
+        Ok(Self::new(validator, config_path, db, license, source))
+    }
+
+    fn new(
+        validator: LicenseValidator,
+        config_path: Option<PathBuf>,
+        db: Arc<Database>,
+        license: License,
+        source: Option<LicenseSource>,
+    ) -> Self {
+        Self {
+            validator,
+            config_path,
+            db,
+            active: RwLock::new(ActiveLicense { license: Arc::new(license), source }),
+        }
+    }
+
+    /// A manager starting from `license`, with no config file to write to
+    pub fn with_license(license: License, validator: LicenseValidator, db: Arc<Database>) -> Self {
+        Self::new(validator, None, db, license, None)
+    }
+
+    pub fn current(&self) -> Arc<License> {
+        self.active.read().unwrap().license.clone()
+    }
+
+    /// Where the current key came from (None on the free tier without a key)
+    pub fn source(&self) -> Option<LicenseSource> {
+        self.active.read().unwrap().source
+    }
+
+    /// Validate a Premium key, persist it and make it the license in effect
+    pub async fn activate(&self, key: &str) -> Result<(Arc<License>, LicenseSource), ActivationError> {
+        let key = key.trim();
+        let license = match self.validator.validate(key) {
+            Ok(license) if license.tier == PremiumTier::Premium => Arc::new(license),
+            _ => return Err(ActivationError::InvalidKey),
+        };
+        let source = store_license_key(key, self.config_path.as_deref(), &self.db)
+            .await
+            .map_err(ActivationError::Storage)?;
+
+        info!("🔑 Premium license activated (stored in the {})", source.as_str());
+        *self.active.write().unwrap() = ActiveLicense { license: license.clone(), source: Some(source) };
+        Ok((license, source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::premium::generate_license_key;
+    use ed25519_dalek::SigningKey;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn validator() -> LicenseValidator {
+        LicenseValidator::with_public_key(&signing_key().verifying_key().to_bytes()).unwrap()
+    }
+
+    fn premium_key() -> String {
+        generate_license_key(PremiumTier::Premium, None, &signing_key()).unwrap()
+    }
+
+    fn setting(value: &str, minutes_ago: i64) -> Setting {
+        Setting { value: value.to_string(), updated_at: Utc::now() - chrono::Duration::minutes(minutes_ago) }
+    }
+
+    #[test]
+    fn test_resolve_each_source_combination() {
+        let source = |config: &str, config_minutes_ago: Option<i64>, stored: Option<Setting>| {
+            let config_at = config_minutes_ago.map(|m| Utc::now() - chrono::Duration::minutes(m));
+            resolve_license_key(config, config_at, stored.as_ref()).map(|r| (r.key, r.source))
+        };
+        let key = |k: &str, source| Some((k.to_string(), source));
+
+        assert_eq!(source("", None, None), None);
+        assert_eq!(source("", Some(5), Some(setting("  ", 1))), None);
+        assert_eq!(source("A", Some(5), None), key("A", LicenseSource::Config));
+        assert_eq!(source("", Some(5), Some(setting("B", 10))), key("B", LicenseSource::Database));
+        assert_eq!(source("A", Some(5), Some(setting("A", 1))), key("A", LicenseSource::Config));
+
+        // Different keys: the most recently activated wins
+        assert_eq!(source("A", Some(5), Some(setting("B", 1))), key("B", LicenseSource::Database));
+        assert_eq!(source("A", Some(1), Some(setting("B", 5))), key("A", LicenseSource::Config));
+        assert_eq!(source("A", None, Some(setting("B", 1))), key("A", LicenseSource::Config));
+    }
+
+    #[tokio::test]
+    async fn test_activation_falls_back_to_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let key = premium_key();
+
+        // No config file at all: headless install
+        let manager = LicenseManager::load(validator(), &Config::default(), Some(path.clone()), db.clone()).await.unwrap();
+        assert_eq!(manager.current().tier, PremiumTier::Free);
+        assert!(matches!(manager.activate("not-a-key").await, Err(ActivationError::InvalidKey)));
+        let (license, source) = manager.activate(&key).await.unwrap();
+        assert_eq!((license.tier, source), (PremiumTier::Premium, LicenseSource::Database));
+        assert_eq!(manager.current().tier, PremiumTier::Premium, "swapped in place");
+        assert!(!path.exists(), "no config file created");
+
+        let reloaded = LicenseManager::load(validator(), &Config::default(), Some(path.clone()), db.clone()).await.unwrap();
+        assert_eq!(reloaded.current().tier, PremiumTier::Premium);
+        assert_eq!(reloaded.source(), Some(LicenseSource::Database));
+
+        // Read-only config file: still the database
+        Config::default().save_atomic(&path).unwrap();
+        let mut permissions = path.metadata().unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        assert_eq!(store_license_key(&key, Some(&path), &db).await.unwrap(), LicenseSource::Database);
+    }
+
+    #[tokio::test]
+    async fn test_activation_prefers_writable_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        Config::default().save_atomic(&path).unwrap();
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        db.set_setting(LICENSE_SETTING, "older-database-key").await.unwrap();
+
+        let manager = LicenseManager::with_license(License::free(), validator(), db.clone());
+        assert_eq!(manager.source(), None);
+        let manager = LicenseManager { config_path: Some(path.clone()), ..manager };
+        let key = premium_key();
+        let (_, source) = manager.activate(&key).await.unwrap();
+        assert_eq!(source, LicenseSource::Config);
+
+        let config = Config::load_from_path(path.to_str().unwrap()).unwrap();
+        assert_eq!(config.premium.license_key, key);
+        assert!(config.premium.activated_at.is_some());
+
+        // The config activation is newer than the stale database key
+        let reloaded = LicenseManager::load(validator(), &config, Some(path), db).await.unwrap();
+        assert_eq!(reloaded.source(), Some(LicenseSource::Config));
+        assert_eq!(reloaded.current().tier, PremiumTier::Premium);
+    }
+}
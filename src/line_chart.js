@@ -0,0 +1,111 @@
+// Minimal canvas line chart for the dashboard, served from the binary so
+// charts work without internet access.
+class LineChart {
+    // options: color, fill, maxTicks, beforeDraw(ctx, area, y) for background layers
+    constructor(canvas, options = {}) {
+        this.canvas = canvas;
+        this.ctx = canvas.getContext('2d');
+        this.labels = [];
+        this.values = [];
+        this.target = null;
+        this.color = options.color || '#dc2626';
+        this.fill = options.fill || 'rgba(220, 38, 38, 0.1)';
+        this.maxTicks = options.maxTicks || 8;
+        this.beforeDraw = options.beforeDraw || null;
+        window.addEventListener('resize', () => this.update());
+        this.update();
+    }
+
+    setData(labels, values) {
+        this.labels = labels;
+        this.values = values;
+        this.update();
+    }
+
+    // Dashed horizontal line at the target, kept in view; null removes it
+    setTarget(value) {
+        this.target = Number.isFinite(value) ? value : null;
+        this.update();
+    }
+
+    update() {
+        const { canvas, ctx } = this;
+        const ratio = window.devicePixelRatio || 1;
+        const width = canvas.parentElement.clientWidth;
+        const height = canvas.parentElement.clientHeight;
+        canvas.width = width * ratio;
+        canvas.height = height * ratio;
+        canvas.style.width = `${width}px`;
+        canvas.style.height = `${height}px`;
+        ctx.setTransform(ratio, 0, 0, ratio, 0, 0);
+        ctx.clearRect(0, 0, width, height);
+
+        const area = { left: 44, top: 8, right: width - 8, bottom: height - 22 };
+        if (area.right <= area.left || area.bottom <= area.top) return;
+
+        const points = this.values
+            .map((value, index) => [index, value])
+            .filter(([, value]) => value !== null && Number.isFinite(value));
+        const ranged = points.map(([, v]) => v).concat(this.target === null ? [] : [this.target]);
+        let min = ranged.length ? Math.min(...ranged) : 0;
+        let max = ranged.length ? Math.max(...ranged) : 1;
+        if (max - min < 1) {
+            min -= 0.5;
+            max += 0.5;
+        }
+        const padding = (max - min) * 0.1;
+        min -= padding;
+        max += padding;
+
+        const y = value => area.bottom - (value - min) / (max - min) * (area.bottom - area.top);
+        const last = Math.max(this.values.length - 1, 1);
+        const x = index => area.left + index / last * (area.right - area.left);
+
+        if (this.beforeDraw) {
+            ctx.save();
+            this.beforeDraw(ctx, area, y);
+            ctx.restore();
+        }
+
+        ctx.fillStyle = '#64748b';
+        ctx.font = '11px sans-serif';
+        ctx.textAlign = 'right';
+        ctx.textBaseline = 'middle';
+        for (let step = 0; step <= 4; step++) {
+            const value = min + (max - min) * step / 4;
+            ctx.fillText(value.toFixed(0), area.left - 6, y(value));
+        }
+        ctx.textAlign = 'center';
+        ctx.textBaseline = 'top';
+        const every = Math.max(1, Math.ceil(this.labels.length / this.maxTicks));
+        for (let index = 0; index < this.labels.length; index += every) {
+            ctx.fillText(this.labels[index], x(index), area.bottom + 6);
+        }
+
+        if (this.target !== null) {
+            ctx.save();
+            ctx.strokeStyle = '#2563eb';
+            ctx.setLineDash([6, 4]);
+            ctx.beginPath();
+            ctx.moveTo(area.left, y(this.target));
+            ctx.lineTo(area.right, y(this.target));
+            ctx.stroke();
+            ctx.restore();
+        }
+
+        if (!points.length) return;
+        ctx.beginPath();
+        points.forEach(([index, value], i) => {
+            if (i === 0) ctx.moveTo(x(index), y(value));
+            else ctx.lineTo(x(index), y(value));
+        });
+        ctx.strokeStyle = this.color;
+        ctx.lineWidth = 2;
+        ctx.stroke();
+        ctx.lineTo(x(points[points.length - 1][0]), area.bottom);
+        ctx.lineTo(x(points[0][0]), area.bottom);
+        ctx.closePath();
+        ctx.fillStyle = this.fill;
+        ctx.fill();
+    }
+}
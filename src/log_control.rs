@@ -0,0 +1,289 @@
+// src/log_control.rs
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn, Level};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Longest burst the API accepts
+pub const MAX_BURST_DURATION: Duration = Duration::from_secs(3600);
+
+/// Number of finished bursts kept for diagnostics
+const BURST_HISTORY: usize = 20;
+
+/// Reload handle for the filter layer installed by the binary's logging setup
+pub type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogControlError {
+    #[error("Unknown log level: {0}")]
+    InvalidLevel(String),
+    #[error("Invalid log target: {0:?}")]
+    InvalidTarget(String),
+    #[error("Burst duration must be between 1 and {} seconds", MAX_BURST_DURATION.as_secs())]
+    InvalidDuration,
+    #[error("Failed to update log filter: {0}")]
+    Reload(String),
+}
+
+/// A temporary raise of the log level
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogBurst {
+    pub id: u64,
+    pub level: String,
+    /// Targets raised (empty = everything)
+    pub targets: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// When the base filter was restored (None while the burst is active)
+    pub reverted_at: Option<DateTime<Utc>>,
+}
+
+/// Current log filter and burst windows, newest first
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLevelStatus {
+    pub base_filter: String,
+    pub current_filter: String,
+    pub active: Option<LogBurst>,
+    pub history: Vec<LogBurst>,
+}
+
+struct Bursts {
+    next_id: u64,
+    active: Option<LogBurst>,
+    history: VecDeque<LogBurst>,
+}
+
+impl Bursts {
+    fn finish(&mut self, mut burst: LogBurst) -> LogBurst {
+        burst.reverted_at = Some(Utc::now());
+        if self.history.len() == BURST_HISTORY {
+            self.history.pop_back();
+        }
+        self.history.push_front(burst.clone());
+        burst
+    }
+}
+
+/// Time-boxed changes to the tracing filter ("burst logging")
+///
+/// A burst raises the level for everything or for selected targets and is
+/// reverted to the startup filter automatically when its window ends.
+/// Starting a new burst replaces the active one.
+pub struct LogControl {
+    handle: FilterHandle,
+    base: String,
+    bursts: Mutex<Bursts>,
+}
+
+impl LogControl {
+    /// `base` is the filter installed at startup, restored after each burst
+    pub fn new(handle: FilterHandle, base: impl Into<String>) -> Self {
+        Self {
+            handle,
+            base: base.into(),
+            bursts: Mutex::new(Bursts {
+                next_id: 1,
+                active: None,
+                history: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Raise the level to `level` for `duration`, then revert on a background task
+    pub fn start_burst(
+        self: &Arc<Self>,
+        level: &str,
+        targets: &[String],
+        duration: Duration,
+    ) -> Result<LogBurst, LogControlError> {
+        let parsed = Level::from_str(level).map_err(|_| LogControlError::InvalidLevel(level.to_string()))?;
+        if duration.is_zero() || duration > MAX_BURST_DURATION {
+            return Err(LogControlError::InvalidDuration);
+        }
+        if let Some(target) = targets.iter().find(|t| !is_valid_target(t)) {
+            return Err(LogControlError::InvalidTarget(target.clone()));
+        }
+
+        let level = parsed.as_str().to_lowercase();
+        let mut bursts = self.bursts.lock().unwrap();
+        self.reload(&burst_filter(&self.base, &level, targets))?;
+
+        if let Some(previous) = bursts.active.take() {
+            bursts.finish(previous);
+        }
+        let started_at = Utc::now();
+        let burst = LogBurst {
+            id: bursts.next_id,
+            level,
+            targets: targets.to_vec(),
+            started_at,
+            ends_at: started_at + chrono::Duration::from_std(duration).unwrap_or_default(),
+            reverted_at: None,
+        };
+        bursts.next_id += 1;
+        bursts.active = Some(burst.clone());
+        drop(bursts);
+
+        info!(
+            "🔎 Log burst {}: {} for {:?} until {}",
+            burst.id,
+            burst.level,
+            burst.targets,
+            burst.ends_at.to_rfc3339()
+        );
+
+        let control = self.clone();
+        let id = burst.id;
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            control.end_burst(Some(id));
+        });
+
+        Ok(burst)
+    }
+
+    /// Restore the base filter; with `Some(id)`, only if that burst is still active
+    pub fn end_burst(&self, id: Option<u64>) -> Option<LogBurst> {
+        let mut bursts = self.bursts.lock().unwrap();
+        let active_id = bursts.active.as_ref()?.id;
+        if id.is_some_and(|id| id != active_id) {
+            return None;
+        }
+
+        if let Err(e) = self.reload(&self.base) {
+            warn!("Failed to end log burst {}: {}", active_id, e);
+            return None;
+        }
+        let burst = bursts.active.take()?;
+        let burst = bursts.finish(burst);
+        drop(bursts);
+
+        info!("🔎 Log burst {} ended, filter restored to {}", burst.id, self.base);
+        Some(burst)
+    }
+
+    pub fn status(&self) -> LogLevelStatus {
+        let bursts = self.bursts.lock().unwrap();
+        LogLevelStatus {
+            base_filter: self.base.clone(),
+            current_filter: self
+                .handle
+                .with_current(|filter| filter.to_string())
+                .unwrap_or_default(),
+            active: bursts.active.clone(),
+            history: bursts.history.iter().cloned().collect(),
+        }
+    }
+
+    fn reload(&self, directives: &str) -> Result<(), LogControlError> {
+        let filter = EnvFilter::try_new(directives).map_err(|e| LogControlError::Reload(e.to_string()))?;
+        self.handle
+            .reload(filter)
+            .map_err(|e| LogControlError::Reload(e.to_string()))
+    }
+}
+
+/// Module path such as `btleplug` or `bbq_monitor::database`
+fn is_valid_target(target: &str) -> bool {
+    !target.is_empty() && target.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Filter directives for a burst; targeted bursts keep the base filter for everything else
+fn burst_filter(base: &str, level: &str, targets: &[String]) -> String {
+    if targets.is_empty() {
+        return level.to_string();
+    }
+
+    let raised: Vec<String> = targets.iter().map(|t| format!("{}={}", t, level)).collect();
+    if base.is_empty() {
+        raised.join(",")
+    } else {
+        format!("{},{}", base, raised.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn control(base: &str) -> (Arc<LogControl>, impl tracing::Subscriber) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(base));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        (Arc::new(LogControl::new(handle, base)), subscriber)
+    }
+
+    #[tokio::test]
+    async fn test_burst_raises_level_and_reverts() {
+        let (control, subscriber) = control("info");
+        let _guard = tracing::subscriber::set_default(subscriber);
+        assert!(!tracing::enabled!(Level::DEBUG));
+
+        let burst = control.start_burst("debug", &[], Duration::from_millis(100)).unwrap();
+        assert!(tracing::enabled!(Level::DEBUG));
+        assert_eq!(control.status().current_filter, "debug");
+        assert_eq!(control.status().active, Some(burst.clone()));
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!tracing::enabled!(Level::DEBUG));
+
+        let status = control.status();
+        assert_eq!(status.current_filter, "info");
+        assert!(status.active.is_none());
+        assert_eq!(status.history.len(), 1);
+        assert_eq!(status.history[0].id, burst.id);
+        assert!(status.history[0].reverted_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_targeted_burst_leaves_other_targets_alone() {
+        let (control, subscriber) = control("info");
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let targets = vec!["btleplug".to_string()];
+        control.start_burst("trace", &targets, Duration::from_secs(60)).unwrap();
+        assert!(tracing::enabled!(target: "btleplug", Level::TRACE));
+        assert!(!tracing::enabled!(target: "sqlx", Level::DEBUG));
+
+        // Ending early restores the base filter and ignores the pending timer
+        assert!(control.end_burst(None).is_some());
+        assert!(!tracing::enabled!(target: "btleplug", Level::DEBUG));
+        assert!(control.end_burst(None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_new_burst_replaces_active_one() {
+        let (control, _subscriber) = control("info");
+        let first = control.start_burst("debug", &[], Duration::from_millis(100)).unwrap();
+        let second = control.start_burst("trace", &[], Duration::from_secs(60)).unwrap();
+
+        // The first burst's timer must not end the second
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let status = control.status();
+        assert_eq!(status.active.map(|b| b.id), Some(second.id));
+        assert_eq!(status.history[0].id, first.id);
+        assert_eq!(status.current_filter, "trace");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_burst_requests() {
+        let (control, _subscriber) = control("info");
+        assert!(matches!(
+            control.start_burst("verbose", &[], Duration::from_secs(60)),
+            Err(LogControlError::InvalidLevel(_))
+        ));
+        assert!(matches!(
+            control.start_burst("debug", &[], Duration::from_secs(2 * 3600)),
+            Err(LogControlError::InvalidDuration)
+        ));
+        assert!(matches!(
+            control.start_burst("debug", &["btleplug=trace".to_string()], Duration::from_secs(60)),
+            Err(LogControlError::InvalidTarget(_))
+        ));
+        assert!(control.status().active.is_none());
+    }
+}
@@ -1,41 +1,78 @@
 // src/main.rs
 use anyhow::{Context, Result};
+use bbq_monitor::ble::{self, BleEnvironmentError, BleHealth, BlePhase, Platform};
+use bbq_monitor::alerts::{self, FirstSeenDevice};
+use bbq_monitor::ble_debug::{self, DebugCommand};
+use bbq_monitor::oneshot::{self, ChannelCentral, OutputFormat, RawFrame, ReadArgs};
+use bbq_monitor::fixtures::{ExportFixtureArgs, SessionFixture, EXPORT_FIXTURE_USAGE};
+use bbq_monitor::reconnect::{self, Reconnect, RECONNECT_BASE_DELAY, SERVICE_DISCOVERY_TIMEOUT};
+use bbq_monitor::maintenance::{self, ProbeDamageWatch};
+use bbq_monitor::simulator;
+use bbq_monitor::telemetry::{self, ParseFailureTelemetry, TelemetryMode};
 use bbq_monitor::{
-    Config, Database, LicenseValidator, MeatStickProtocol, ProbeCapabilities, TemperatureUpdate,
-    COMBUSTION_UART_SERVICE, COMBUSTION_UART_RX_CHAR, COMBUSTION_UART_TX_CHAR,
-    MEATSTICK_SERVICE, MEATSTICK_CHAR,
+    BatteryCache, Config, Database, DeviceInfo, IngestThrottle, LicenseManager, LicenseValidator, Temperature, has_probe_vendor_id, MeatStickProtocol, NotificationDeduper, PredictionStatus,
+    InstantReadFilter, LogControl, MeaterProtocol, ProbeBrand, ProbeCapabilities, ProtocolError, ProtocolRegistry, ReadingMode, RetentionPolicy, RetentionSource, TemperatureUnit, TemperatureUpdate, ThermoProProtocol, VersionInfo, VirtualSensors, broadcast_update,
+    COMBUSTION_PROBE_STATUS_CHAR, COMBUSTION_PROBE_STATUS_SERVICE, COMBUSTION_VENDOR_ID, PROBE_STATUS_TEMPERATURE_OFFSET,
+    MEATER_SERVICE, MEATER_TEMPERATURE_CHAR, MEATSTICK_SERVICE, MEATSTICK_CHAR, MEATSTICK_FRAME_LEN,
+    THERMOPRO_SERVICE, THERMOPRO_NOTIFY_CHAR, UartSession, frame_uart_command, BATTERY_REFRESH_INTERVAL, read_device_info,
+    IGRILL_AUTH_SERVICE, IGRILL_PROBE_CHARS, IGrillSessions, IGRILL_TEMPERATURE_SERVICES, WEBER_PROBE_COUNT, WeberProtocol, authenticate_igrill,
 };
 #[cfg(feature = "aws")]
 use bbq_monitor::AwsClient;
-use btleplug::api::{Central, CentralEvent, Manager as _, Peripheral as _, ScanFilter, WriteType};
-use btleplug::platform::Manager;
+use btleplug::api::{Central, CentralEvent, Peripheral as _, ScanFilter};
 use chrono::Utc;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
 use tokio::time;
 use tokio_stream::StreamExt;
 use tracing::{debug, error, info, warn};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt};
+
+/// Config file `Config::load` reads; activated license keys are written back to it
+const CONFIG_FILE: &str = "config.toml";
+
+/// How long shutdown waits for the web server and cloud sync before exiting anyway
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    
+    if args.iter().any(|a| a == "--version" || a == "-V") {
+        let version = VersionInfo::current();
+        if args.iter().any(|a| a == "--json") {
+            println!("{}", serde_json::to_string_pretty(&version)?);
+        } else {
+            println!("{}", version.banner());
+        }
+        return Ok(());
+    }
+    
+    if args.first().map(String::as_str) == Some("doctor") {
+        return run_doctor().await;
+    }
+    
+    if args.first().map(String::as_str) == Some("read") {
+        return run_read(&args[1..]).await;
+    }
+    
+    if args.first().map(String::as_str) == Some("db") {
+        return run_db(&args[1..]).await;
+    }
+    
     // Load configuration
     let config = Config::load().context("Failed to load configuration")?;
     
     // Initialize logging
-    init_logging(&config)?;
+    let log_control = init_logging(&config)?;
     
-    info!("🔥 BBQ Monitor v0.1.0 - Starting");
+    info!("🔥 {} - Starting", VersionInfo::current().banner());
     info!("Configuration loaded from config.toml");
-    
-    // Validate premium license
-    let validator = LicenseValidator::new();
-    let license = validator.validate(&config.premium.license_key)?;
-    info!("📋 License: {} tier", license.tier);
-    
-    if !license.features.cloud_sync && config.aws.enabled {
-        warn!("⚠️  Cloud sync requires Premium license. Upgrade at https://bbqmonitor.example.com/premium");
+    for key in &config.unknown_keys {
+        warn!("⚠️  Unknown config.toml setting '{}' is ignored", key);
     }
     
     // Initialize database
@@ -43,15 +80,97 @@ async fn main() -> Result<()> {
         Database::new(&config.database.path)
             .await
             .context("Failed to initialize database")?
+            .with_timestamp_precision(config.database.timestamp_precision)
+    );
+    
+    // Validate premium license: config.toml, or a key activated into the database
+    let license_manager = Arc::new(
+        LicenseManager::load(LicenseValidator::new(), &config, Some(CONFIG_FILE.into()), db.clone()).await?
     );
+    let license = license_manager.current();
+    match license_manager.source() {
+        Some(source) => info!("📋 License: {} tier (from the {})", license.tier, source.as_str()),
+        None => info!("📋 License: {} tier", license.tier),
+    }
+    
+    if !license.features.cloud_sync && config.aws.enabled {
+        warn!("⚠️  Cloud sync requires Premium license. Upgrade at {}", bbq_monitor::PREMIUM_URL);
+    }
+    
+    // Create shutdown channel for cleanup
+    let (shutdown_tx, _shutdown_rx) = broadcast::channel::<()>(1);
     
-    // Cleanup old readings (respect license tier for retention)
-    let retention_days = if license.features.unlimited_history {
-        0 // Keep forever for premium
+    // Serve the dashboard before anything slow: cleanup, cloud sync and
+    // Bluetooth start behind it and report progress through /api/health
+    let ble_health = BleHealth::default();
+    let (debug_tx, debug_rx) = if config.web.as_ref().is_some_and(|w| w.developer_mode) {
+        warn!("🧪 Developer mode: raw BLE read/write endpoints are enabled");
+        let (tx, rx) = ble_debug::debug_channel();
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
+    let (simulator_tx, simulator_rx) = if config.simulator.enabled {
+        let (tx, rx) = simulator::simulator_channel();
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
+    let (first_seen_tx, first_seen_rx) = if config.alerts.bootstrap_defaults {
+        let (tx, rx) = alerts::first_seen_channel();
+        (Some(tx), Some(rx))
     } else {
-        7 // 7 days for free tier
+        (None, None)
     };
-    db.cleanup_old_readings(retention_days).await?;
+    #[cfg(not(feature = "pit-controller"))]
+    if config.pit_controller.enabled {
+        warn!("⚠️  Pit controller enabled but not compiled in. Rebuild with '--features pit-controller'");
+    }
+    let (tx, web_handle) = bbq_monitor::start_server(
+        db.clone(),
+        license_manager.clone(),
+        Arc::new(config.clone()),
+        ble_health.clone(),
+        Some(log_control),
+        debug_tx,
+        simulator_tx,
+        first_seen_rx,
+        shutdown_tx.subscribe(),
+    ).await?;
+    
+    // Cleanup old readings (database.retention_days, capped by the license tier) without holding up startup
+    let retention_days = config.database.retention_days;
+    let (retention, retention_source) = RetentionPolicy::from_config(retention_days, license.features.unlimited_history);
+    match retention_source {
+        RetentionSource::Configured => info!("🗄️  Retention: {} (database.retention_days = {})", retention, retention_days),
+        RetentionSource::FreeTierCap => info!(
+            "🗄️  Retention: {} (database.retention_days = {} is more than the free tier keeps; Premium lifts the cap)",
+            retention, retention_days
+        ),
+    }
+    let cleanup_db = db.clone();
+    let vacuum_after_cleanup = config.database.vacuum_after_cleanup;
+    tokio::spawn(async move {
+        match cleanup_db.cleanup_old_readings(&retention).await {
+            Ok(cleanup) if vacuum_after_cleanup && cleanup.decimated + cleanup.deleted > 0 => {
+                if let Err(e) = cleanup_db.vacuum().await {
+                    warn!("⚠️  Vacuum after cleanup failed: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("❌ Startup cleanup failed: {:#}", e),
+        }
+    });
+    if config.database.cleanup_interval_hours > 0 {
+        let period = Duration::from_secs(config.database.cleanup_interval_hours * 3600);
+        tokio::spawn(bbq_monitor::start_retention_task(
+            db.clone(),
+            license_manager.clone(),
+            retention_days,
+            period,
+            shutdown_tx.subscribe(),
+        ));
+    }
     
     // Initialize AWS client if enabled AND licensed
     #[cfg(feature = "aws")]
@@ -62,6 +181,8 @@ async fn main() -> Result<()> {
             thing_name: config.aws.thing_name.clone(),
             table_name: config.aws.table_name.clone(),
             sync_interval_secs: config.aws.sync_interval_secs,
+            initial_lookback_hours: config.aws.initial_lookback_hours,
+            max_pending_sync: config.aws.max_pending_sync,
         };
         
         match AwsClient::new(aws_config, db.clone()).await {
@@ -76,7 +197,7 @@ async fn main() -> Result<()> {
         }
     } else {
         if config.aws.enabled {
-            info!("AWS cloud sync disabled (Premium license required)");
+            info!("AWS cloud sync disabled (Premium license required; restart after activating)");
         } else {
             info!("AWS cloud sync disabled in configuration");
         }
@@ -91,74 +212,210 @@ async fn main() -> Result<()> {
         None
     };
     
-    // Create shutdown channel for cleanup
-    let (_shutdown_tx, _shutdown_rx) = broadcast::channel::<()>(1);
-    
     // Start AWS sync background task if available
     #[cfg(feature = "aws")]
-    if let Some(aws) = aws_client.clone() {
-        let aws_shutdown = _shutdown_tx.subscribe();
+    let aws_task = aws_client.clone().map(|aws| {
+        let aws_shutdown = shutdown_tx.subscribe();
         tokio::spawn(async move {
             aws.start_sync_task(aws_shutdown).await;
+        })
+    });
+    
+    // Pull older cloud history once, in the background so large backfills do not hold up monitoring
+    #[cfg(feature = "aws")]
+    if let Some(aws) = aws_client.clone().filter(|_| config.aws.backfill_hours > 0) {
+        let hours = config.aws.backfill_hours;
+        tokio::spawn(async move {
+            if let Err(e) = aws.backfill(hours).await {
+                warn!("⚠️  Cloud backfill failed: {:#}", e);
+            }
         });
     }
     
+    // Opt-in only; counts leave the machine only in upload mode with cloud sync running
+    let telemetry = Arc::new(ParseFailureTelemetry::new(config.telemetry.mode));
+    if config.telemetry.mode != TelemetryMode::Off {
+        tokio::spawn(telemetry::run_telemetry_reporter(
+            telemetry.clone(),
+            aws_client.clone(),
+            Duration::from_secs(config.telemetry.report_interval_secs),
+            shutdown_tx.subscribe(),
+        ));
+    }
+    
     // Suppress unused variable warning when aws feature is disabled
     #[cfg(not(feature = "aws"))]
     let _ = aws_client;
     
-    // Start web server
-    let web_host = config.web.as_ref().map(|w| w.host.as_str()).unwrap_or("127.0.0.1");
-    let web_port = config.web.as_ref().map(|w| w.port).unwrap_or(8080);
+    if config.database.vacuum_interval_hours > 0 {
+        let vacuum_db = db.clone();
+        let vacuum_shutdown = shutdown_tx.subscribe();
+        let period = Duration::from_secs(config.database.vacuum_interval_hours * 3600);
+        tokio::spawn(async move {
+            bbq_monitor::start_vacuum_task(vacuum_db, period, vacuum_shutdown).await;
+        });
+    }
     
-    let (tx, _web_handle) = bbq_monitor::start_server(db.clone(), Arc::new(license), web_host, web_port).await?;
+    if config.maintenance.calibration_reminder_days > 0 {
+        let reminder_db = db.clone();
+        let reminder_shutdown = shutdown_tx.subscribe();
+        let max_age_days = config.maintenance.calibration_reminder_days;
+        tokio::spawn(async move {
+            bbq_monitor::maintenance::start_reminder_task(reminder_db, max_age_days, reminder_shutdown).await;
+        });
+    }
     
-    // Initialize BLE manager
-    info!("Initializing Bluetooth adapter...");
-    let manager = Manager::new().await?;
-    let adapters = manager.adapters().await?;
+    // Start scheduled exports if configured
+    if let Some(export_config) = config.export_schedule.clone().filter(|e| e.enabled) {
+        let export_db = db.clone();
+        let export_shutdown = shutdown_tx.subscribe();
+        let export_unit = config.temperature.display_unit();
+        tokio::spawn(async move {
+            bbq_monitor::start_export_task(export_db, export_config, export_unit, export_shutdown).await;
+        });
+    }
     
-    if adapters.is_empty() {
-        error!("No Bluetooth adapters found");
-        return Ok(());
+    if let Some(commands) = simulator_rx {
+        tokio::spawn(simulator::run_simulator(
+            config.simulator.clone(),
+            config.temperature.display_unit(),
+            db.clone(),
+            tx.clone(),
+            commands,
+            shutdown_tx.subscribe(),
+        ));
     }
     
-    let adapter = &adapters[0];
+    // Monitoring stops at the next safe point instead of being dropped mid-write
+    let monitor_shutdown = shutdown_tx.subscribe();
+    let signal_tx = shutdown_tx.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("🛑 Shutting down…");
+        let _ = signal_tx.send(());
+    });
+    
+    // Runs whatever the tier: a probe pushed past its rating is a hardware problem, not an alert
+    let damage_watch = Arc::new(ProbeDamageWatch::new(db.clone()));
+    tokio::spawn(maintenance::run_damage_watch(damage_watch.clone(), tx.subscribe()));
+    
+    let result = run_monitoring(
+        &db,
+        &config,
+        &tx,
+        &ble_health,
+        monitor_shutdown,
+        debug_rx,
+        first_seen_tx,
+        &damage_watch,
+        &telemetry,
+    )
+    .await;
+    
+    // Let background tasks wind down; AWS sync uploads pending readings first
+    let _ = shutdown_tx.send(());
+    let background = async {
+        let _ = web_handle.await;
+        #[cfg(feature = "aws")]
+        if let Some(task) = aws_task {
+            let _ = task.await;
+        }
+    };
+    if time::timeout(SHUTDOWN_TIMEOUT, background).await.is_err() {
+        warn!("⚠️  Background tasks did not stop within {}s, exiting anyway", SHUTDOWN_TIMEOUT.as_secs());
+    }
+    info!("👋 Shutdown complete");
+    
+    result
+}
+
+/// Resolves on Ctrl+C, or SIGTERM on Unix (e.g. `systemctl stop`)
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("⚠️  Cannot listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Scan, connect and monitor BBQ devices until done or the adapter fails
+#[allow(clippy::too_many_arguments)]
+async fn run_monitoring(
+    db: &Arc<Database>,
+    config: &Config,
+    tx: &broadcast::Sender<TemperatureUpdate>,
+    ble_health: &BleHealth,
+    mut shutdown: broadcast::Receiver<()>,
+    mut debug_commands: Option<mpsc::Receiver<DebugCommand>>,
+    first_seen: Option<mpsc::Sender<FirstSeenDevice>>,
+    damage_watch: &ProbeDamageWatch,
+    telemetry: &Arc<ParseFailureTelemetry>,
+) -> Result<()> {
+    // Initialize BLE manager
+    info!("Initializing Bluetooth adapter...");
+    ble_health.set_phase(BlePhase::Starting);
+    let (_manager, adapter) = match ble::select_adapter(config.device.adapter_index, config.device.adapter_address.as_deref()).await {
+        Ok(found) => found,
+        Err(e) => return wait_after_ble_failure(e, ble_health, &mut shutdown).await,
+    };
+    let adapter = &adapter;
     info!("Using adapter: {}", adapter.adapter_info().await?);
     
     // Start scanning for devices
     info!("Scanning for BBQ devices for {} seconds...", config.device.scan_duration);
-    adapter.start_scan(ScanFilter::default()).await?;
-    time::sleep(Duration::from_secs(config.device.scan_duration)).await;
+    match ble::run_scan(adapter, ble_health, Duration::from_secs(config.device.scan_duration), &mut shutdown).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(()),
+        Err(e) => return wait_after_ble_failure(e, ble_health, &mut shutdown).await,
+    }
+    
+    // Find BBQ devices first, so progress can say how many connects are ahead
+    let mut candidates = Vec::new();
+    for peripheral in adapter.peripherals().await? {
+        let Some(properties) = peripheral.properties().await? else { continue };
+        let name = properties.local_name.as_deref().unwrap_or("Unknown");
+        let rssi = properties.rssi.unwrap_or(0);
+        if should_connect(name, &properties.address.to_string(), &properties.manufacturer_data, rssi, config) {
+            candidates.push((peripheral, properties));
+        }
+    }
+    ble_health.set_phase(BlePhase::Connecting { devices: candidates.len() });
     
-    let peripherals = adapter.peripherals().await?;
     let mut connected_devices = Vec::new();
+    let mut stopping = false;
+    let battery = Arc::new(BatteryCache::new(BATTERY_REFRESH_INTERVAL));
+    let igrill = Arc::new(IGrillSessions::new());
     
-    // Find and connect to BBQ devices
-    for peripheral in peripherals {
-        let properties = match peripheral.properties().await? {
-            Some(props) => props,
-            None => continue,
-        };
+    // Connect to them
+    for (peripheral, properties) in candidates {
+        if shutdown.try_recv().is_ok() {
+            stopping = true;
+            break;
+        }
         
         let device_address = properties.address.to_string();
+        let advertised_name = properties.local_name.clone().unwrap_or_default();
         let device_name = properties.local_name.unwrap_or_else(|| "Unknown".to_string());
         let rssi = properties.rssi.unwrap_or(0);
         
-        // Apply filters
-        if !should_connect(&device_name, &device_address, rssi, &config) {
-            continue;
-        }
-        
         info!("🍖 Found: {} ({}) - RSSI: {}dBm", device_name, device_address, rssi);
         
-        match peripheral.connect().await {
+        match reconnect::connect_with_timeout(peripheral.connect(), config.device.connect_timeout()).await {
             Ok(_) => {
                 info!("   ✅ Connected to {}", device_name);
                 
                 // Discover services
-                peripheral.discover_services().await?;
-                let services = peripheral.services();
+                let services = reconnect::wait_for_services(&peripheral, &device_name, SERVICE_DISCOVERY_TIMEOUT).await?;
                 
                 // Detect device capabilities
                 let service_uuids: Vec<String> = services.iter()
@@ -166,31 +423,76 @@ async fn main() -> Result<()> {
                     .collect();
                 
                 let capabilities = ProbeCapabilities::detect_from_device(
-                    &device_name,
+                    &advertised_name,
                     &device_address,
                     &service_uuids,
+                    &properties.manufacturer_data,
                 );
                 
                 info!("   📋 Detected: {:?} with {} sensors", 
                     capabilities.brand, capabilities.sensor_count);
                 
-                // Save device to database
-                db.upsert_device(
-                    &device_address,
-                    &device_name,
-                    &format!("{:?}", capabilities.brand),
-                    &capabilities.model,
-                    capabilities.sensor_count,
-                ).await?;
+                // Save device to database; Combustion probes are matched by serial
+                // number so a rotated MAC keeps storing under the original address
+                let identity = properties
+                    .manufacturer_data
+                    .get(&COMBUSTION_VENDOR_ID)
+                    .and_then(|data| MeatStickProtocol::parse_advertisement(data).ok());
+                let brand = format!("{:?}", capabilities.brand);
+                let (device_address, is_new) = match &identity {
+                    Some(identity) => {
+                        info!("   🏷️  {} (serial {})",
+                            identity.label(), identity.serial_number.as_deref().unwrap_or("unknown"));
+                        db.upsert_device_with_identity(
+                            &device_address,
+                            &device_name,
+                            &brand,
+                            &capabilities.model,
+                            capabilities.sensor_count,
+                            identity,
+                        ).await?
+                    }
+                    None => {
+                        let is_new = db.upsert_device(
+                            &device_address,
+                            &device_name,
+                            &brand,
+                            &capabilities.model,
+                            capabilities.sensor_count,
+                        ).await?;
+                        (device_address, is_new)
+                    }
+                };
+                // Devices first seen without Premium come back until their default rules exist
+                if let Some(first_seen) = &first_seen {
+                    if is_new || db.alert_rules_pending(&device_address).await? {
+                        let device = FirstSeenDevice { device_address: device_address.clone(), capabilities: capabilities.clone() };
+                        if first_seen.try_send(device).is_err() {
+                            warn!("⚠️  Could not queue default alerts for {}", device_address);
+                        }
+                    }
+                }
+                
+                // Firmware and hardware revisions, for matching parser bugs to firmware
+                let device_info = read_device_info(&peripheral).await;
+                if !device_info.is_empty() {
+                    info!("   ℹ️  {}", describe_device_info(&device_info));
+                    db.set_device_info(&device_address, &device_info).await?;
+                }
                 
                 // Subscribe to notifications
-                if setup_notifications(&peripheral, &device_name).await? {
+                let wake_commands = config.device.wake_sequence(&brand, &device_address, &capabilities.wake_commands);
+                if setup_notifications(&peripheral, &device_address, &wake_commands, &battery, &igrill, db).await? {
+                    damage_watch.watch(&device_address, &capabilities);
+                    ble_health.device_online(&device_address);
                     connected_devices.push((
                         peripheral.clone(),
                         device_name.clone(),
                         device_address.clone(),
                         capabilities,
                     ));
+                } else {
+                    warn!("   ⚠️  Nothing to subscribe to on {}; not monitoring it", device_name);
                 }
             }
             Err(e) => {
@@ -203,25 +505,35 @@ async fn main() -> Result<()> {
     
     if connected_devices.is_empty() {
         warn!("No devices connected for monitoring");
+        ble_health.set_phase(BlePhase::Monitoring { devices: 0 });
         return Ok(());
     }
     
-    info!("🔔 Monitoring {} devices for {} seconds...", 
-        connected_devices.len(), config.device.monitor_duration);
-    
-    // Monitor devices
-    let notification_count = monitor_devices(
-        adapter,
-        &connected_devices,
-        &db,
-        &config,
-        &tx,
-    ).await?;
-    
-    info!("📊 Monitoring complete. Processed {} readings", notification_count);
-    
-    // Print device summary
-    print_device_summary(&connected_devices).await?;
+    if !stopping {
+        ble_health.set_phase(BlePhase::Monitoring { devices: connected_devices.len() });
+        info!("🔔 Monitoring {} devices for {} seconds...", 
+            connected_devices.len(), config.device.monitor_duration);
+        
+        // Monitor devices
+        let notification_count = monitor_devices(
+            adapter,
+            &mut connected_devices,
+            db,
+            config,
+            tx,
+            &battery,
+            &igrill,
+            telemetry,
+            ble_health,
+            &mut shutdown,
+            &mut debug_commands,
+        ).await?;
+        
+        info!("📊 Monitoring complete. Processed {} readings", notification_count);
+        
+        // Print device summary
+        print_device_summary(&connected_devices).await?;
+    }
     
     // Disconnect all devices
     for (peripheral, name, _, _) in &connected_devices {
@@ -232,11 +544,14 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn init_logging(config: &Config) -> Result<()> {
+/// Install the subscriber; the returned handle lets the API raise the level temporarily
+fn init_logging(config: &Config) -> Result<Arc<LogControl>> {
     let filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| {
             format!("bbq_monitor={},info", config.logging.level).into()
         });
+    let base_filter = filter.to_string();
+    let (filter, filter_handle) = reload::Layer::new(filter);
     
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(false)
@@ -265,16 +580,290 @@ fn init_logging(config: &Config) -> Result<()> {
             .init();
     }
     
+    Ok(Arc::new(LogControl::new(filter_handle, base_filter)))
+}
+
+/// Log a classified BLE failure, publish it to /api/health and keep the dashboard up until Ctrl+C
+async fn wait_after_ble_failure(
+    error: BleEnvironmentError,
+    health: &BleHealth,
+    shutdown: &mut broadcast::Receiver<()>,
+) -> Result<()> {
+    error!("❌ {}", error);
+    error!("   👉 {}", error.remediation(Platform::current()));
+    health.set_error(error);
+    
+    info!("Dashboard stays available for history; press Ctrl+C to exit");
+    let _ = shutdown.recv().await;
+    Ok(())
+}
+
+/// `bbq-monitor doctor`: check configuration, database and Bluetooth, then exit
+///
+/// Exits non-zero on any ❌: 1 for the configuration, 2 for Bluetooth, 3 for the database.
+async fn run_doctor() -> Result<()> {
+    println!("🩺 BBQ Monitor doctor");
+    
+    let config = match Config::load() {
+        Ok(config) => {
+            println!("✅ Configuration loaded");
+            for key in &config.unknown_keys {
+                println!("⚠️  Unknown setting '{}' is ignored", key);
+            }
+            config
+        }
+        Err(e) => {
+            println!("❌ Configuration: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    // A broken database only fails the run after the Bluetooth check, so one run shows both
+    let database_ok = match Database::new(&config.database.path).await {
+        Ok(_) => {
+            println!("✅ Database {} opened", config.database.path);
+            true
+        }
+        Err(e) => {
+            println!("❌ Database {}: {:#}", config.database.path, e);
+            false
+        }
+    };
+    
+    let ble_result = match ble::select_adapter(config.device.adapter_index, config.device.adapter_address.as_deref()).await {
+        Ok((_manager, adapter)) => match adapter.start_scan(ScanFilter::default()).await {
+            Ok(()) => {
+                let _ = adapter.stop_scan().await;
+                Ok(adapter.adapter_info().await.unwrap_or_default())
+            }
+            Err(e) => Err(BleEnvironmentError::classify(&e)),
+        },
+        Err(e) => Err(e),
+    };
+    
+    match ble_result {
+        Ok(info) => println!("✅ Bluetooth adapter ready: {}", info),
+        Err(e) => {
+            println!("❌ {}", e);
+            println!("   👉 {}", e.remediation(Platform::current()));
+            std::process::exit(2);
+        }
+    }
+    if !database_ok {
+        std::process::exit(3);
+    }
+    
+    Ok(())
+}
+
+/// `bbq-monitor read`: print one reading per device and exit, for scripts and cron jobs
+///
+/// Stdout carries only the readings; progress and errors go to stderr.
+async fn run_read(args: &[String]) -> Result<()> {
+    let args = match ReadArgs::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let config = Config::load().context("Failed to load configuration")?;
+    let unit = TemperatureUnit::parse(&config.temperature.unit).unwrap_or_default();
+    
+    let (_manager, adapter) = match ble::select_adapter(config.device.adapter_index, config.device.adapter_address.as_deref()).await {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            eprintln!("   👉 {}", e.remediation(Platform::current()));
+            std::process::exit(oneshot::EXIT_NO_ADAPTER);
+        }
+    };
+    if let Err(e) = adapter.start_scan(ScanFilter::default()).await {
+        let e = BleEnvironmentError::classify(&e);
+        eprintln!("❌ {}", e);
+        eprintln!("   👉 {}", e.remediation(Platform::current()));
+        std::process::exit(oneshot::EXIT_NO_ADAPTER);
+    }
+    
+    let mut central = ChannelCentral::new(spawn_read_central(adapter.clone(), args.passive, args.devices.clone()).await?);
+    let outcome = oneshot::collect_readings(&mut central, &args.devices, args.timeout).await;
+    let _ = adapter.stop_scan().await;
+    
+    let output = match args.format {
+        OutputFormat::Plain => oneshot::format_plain(&outcome.readings, unit),
+        OutputFormat::Json => oneshot::format_json(&outcome.readings, unit),
+    };
+    print!("{}", output);
+    
+    if args.store && !outcome.readings.is_empty() {
+        let db = Database::new(&config.database.path).await.context("Failed to open database")?;
+        for reading in &outcome.readings {
+            let capabilities = ProbeCapabilities::detect_from_device(
+                &reading.device_name,
+                &reading.device_address,
+                &[],
+                &HashMap::new(),
+            );
+            db.upsert_device(
+                &reading.device_address,
+                &reading.device_name,
+                &format!("{:?}", capabilities.brand),
+                &capabilities.model,
+                capabilities.sensor_count,
+            ).await?;
+            for (sensor_index, temperature) in reading.temperatures.iter().enumerate() {
+                if let Some(temperature) = temperature {
+                    db.insert_reading(&reading.device_address, reading.timestamp, sensor_index, *temperature, None, None, 0).await?;
+                }
+            }
+        }
+    }
+    
+    if !outcome.missing.is_empty() {
+        eprintln!("⏱️  No reading within {}s from: {}", args.timeout.as_secs(), outcome.missing.join(", "));
+    }
+    match outcome.exit_code() {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
+}
+
+/// `bbq-monitor db <command>`: maintenance commands against the configured database
+async fn run_db(args: &[String]) -> Result<()> {
+    if args.first().map(String::as_str) != Some("export-fixture") {
+        eprintln!("{}", EXPORT_FIXTURE_USAGE);
+        std::process::exit(1);
+    }
+    let args = match ExportFixtureArgs::parse(&args[1..]) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let config = Config::load().context("Failed to load configuration")?;
+    let db = Database::new(&config.database.path).await.context("Failed to open database")?;
+    
+    let fixture = SessionFixture::export(&db, args.session_id).await?.anonymized();
+    fixture.write_gz(&args.out)?;
+    eprintln!(
+        "📦 Cook session {} exported to {}: {} readings, {} events",
+        args.session_id,
+        args.out.display(),
+        fixture.readings.len(),
+        fixture.events.len()
+    );
     Ok(())
 }
 
-fn should_connect(name: &str, address: &str, rssi: i16, config: &Config) -> bool {
+/// Feed raw frames from the adapter into a channel for `collect_readings`
+///
+/// Passive mode forwards advertisements only; otherwise each matching device is
+/// connected once and its temperature notifications are forwarded.
+async fn spawn_read_central(
+    adapter: btleplug::platform::Adapter,
+    passive: bool,
+    wanted: Vec<String>,
+) -> Result<mpsc::Receiver<RawFrame>> {
+    let (frames, receiver) = mpsc::channel(64);
+    let mut events = adapter.events().await?;
+    
+    tokio::spawn(async move {
+        let mut connecting = HashSet::new();
+        while let Some(event) = events.next().await {
+            let id = match event {
+                CentralEvent::DeviceDiscovered(id)
+                | CentralEvent::DeviceUpdated(id)
+                | CentralEvent::ManufacturerDataAdvertisement { id, .. } => id,
+                _ => continue,
+            };
+            let Ok(peripheral) = adapter.peripheral(&id).await else { continue };
+            let Ok(Some(properties)) = peripheral.properties().await else { continue };
+            let address = properties.address.to_string();
+            let name = properties.local_name.clone().unwrap_or_default();
+            let requested = wanted.is_empty() || wanted.iter().any(|w| w.eq_ignore_ascii_case(&address));
+            if !requested || !is_bbq_device(&name, &address, &properties.manufacturer_data) {
+                continue;
+            }
+            
+            if passive {
+                let frame = RawFrame::Advertisement { address, name, manufacturer_data: properties.manufacturer_data };
+                if frames.send(frame).await.is_err() {
+                    break;
+                }
+            } else if connecting.insert(address.clone()) {
+                eprintln!("🔗 Connecting to {} ({})", name, address);
+                tokio::spawn(forward_notifications(peripheral, address, name, frames.clone()));
+            }
+        }
+    });
+    
+    Ok(receiver)
+}
+
+async fn forward_notifications(
+    peripheral: btleplug::platform::Peripheral,
+    address: String,
+    name: String,
+    frames: mpsc::Sender<RawFrame>,
+) {
+    let result: Result<()> = async {
+        peripheral.connect().await?;
+        reconnect::wait_for_services(&peripheral, &name, SERVICE_DISCOVERY_TIMEOUT).await?;
+        let mut notifications = peripheral.notifications().await?;
+        // An unpaired iGrill's probe values mean nothing, so those wait for the handshake
+        let paired = match authenticate_igrill(&peripheral).await {
+            Ok(session) => session.is_authenticated(),
+            Err(e) => {
+                warn!("❌ iGrill pairing failed for {}: {}", name, e);
+                false
+            }
+        };
+        for characteristic in peripheral.characteristics() {
+            let needs_pairing = WeberProtocol::probe_index(characteristic.uuid).is_some();
+            if ProtocolRegistry::reads_characteristic(characteristic.uuid) && (paired || !needs_pairing) {
+                peripheral.subscribe(&characteristic).await?;
+            }
+        }
+        while let Some(notification) = notifications.next().await {
+            let frame = RawFrame::Notification {
+                address: address.clone(),
+                name: name.clone(),
+                characteristic: notification.uuid,
+                value: notification.value,
+            };
+            if frames.send(frame).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        eprintln!("⚠️  {} ({}): {:#}", name, address, e);
+    }
+    let _ = peripheral.disconnect().await;
+}
+
+fn should_connect(
+    name: &str,
+    address: &str,
+    manufacturer_data: &HashMap<u16, Vec<u8>>,
+    rssi: i16,
+    config: &Config,
+) -> bool {
     // Check RSSI threshold
     if rssi < config.filters.min_rssi {
         debug!("Skipping {} - RSSI {} below threshold {}", name, rssi, config.filters.min_rssi);
         return false;
     }
     
+    // Explicit blocklist/allowlist (blocklist wins)
+    if !config.filters.allows_address(address) {
+        debug!("Skipping {} ({}) - not allowed by allowlist/blocklist", name, address);
+        return false;
+    }
+    
     // Check MAC address filter
     if !config.filters.mac_filters.is_empty() && !config.filters.mac_filters.iter().any(|filter| address.contains(filter)) {
         return false;
@@ -286,12 +875,17 @@ fn should_connect(name: &str, address: &str, rssi: i16, config: &Config) -> bool
     }
     
     // General BBQ device detection
-    is_bbq_device(name, address)
+    is_bbq_device(name, address, manufacturer_data)
 }
 
-fn is_bbq_device(name: &str, address: &str) -> bool {
+fn is_bbq_device(name: &str, address: &str, manufacturer_data: &HashMap<u16, Vec<u8>>) -> bool {
     let name_lower = name.to_lowercase();
     
+    // Probes identified by company ID, whatever name they advertise
+    if has_probe_vendor_id(manufacturer_data) {
+        return true;
+    }
+    
     // MeatStick devices
     if name.starts_with("cA00") || name.starts_with("cA02") || name.starts_with("Y0C") {
         return true;
@@ -302,6 +896,11 @@ fn is_bbq_device(name: &str, address: &str) -> bool {
         return true;
     }
     
+    // ThermoPro devices
+    if name.starts_with("TP25") || name_lower.contains("thermopro") {
+        return true;
+    }
+    
     // Weber devices
     if name_lower.contains("igrill") || name_lower.contains("weber") {
         return true;
@@ -317,11 +916,30 @@ fn is_bbq_device(name: &str, address: &str) -> bool {
 
 async fn setup_notifications(
     peripheral: &btleplug::platform::Peripheral,
-    _device_name: &str,
+    device_address: &str,
+    wake_commands: &[(Vec<u8>, Duration)],
+    battery: &BatteryCache,
+    igrill: &IGrillSessions,
+    db: &Database,
 ) -> Result<bool> {
     let services = peripheral.services();
     let mut subscribed = false;
     
+    // A Weber iGrill reports nothing until the pairing handshake has passed
+    if services.iter().any(|service| service.uuid == IGRILL_AUTH_SERVICE) {
+        match authenticate_igrill(peripheral).await {
+            Ok(session) => {
+                info!("   🔐 iGrill paired");
+                igrill.set(device_address, session);
+                subscribed = true;
+            }
+            Err(e) => {
+                warn!("   ❌ iGrill pairing failed: {}", e);
+                igrill.forget(device_address);
+            }
+        }
+    }
+    
     // MeatStick temperature service
     for service in &services {
         if service.uuid == MEATSTICK_SERVICE {
@@ -342,61 +960,246 @@ async fn setup_notifications(
             }
         }
         
-        // Nordic UART service (for commands)
-        if service.uuid == COMBUSTION_UART_SERVICE {
-            debug!("   📡 Found Nordic UART service");
-            
+        // Combustion probe status (temperatures + prediction engine)
+        if service.uuid == COMBUSTION_PROBE_STATUS_SERVICE {
             for characteristic in &service.characteristics {
-                let char_uuid = characteristic.uuid;
-                
-                // TX characteristic (device sends to us)
-                if char_uuid == COMBUSTION_UART_RX_CHAR && peripheral.subscribe(characteristic).await.is_ok() {
-                    info!("   📡 Subscribed to Nordic UART notifications");
+                if characteristic.uuid == COMBUSTION_PROBE_STATUS_CHAR && peripheral.subscribe(characteristic).await.is_ok() {
+                    info!("   ✅ Subscribed to probe status notifications");
                     subscribed = true;
                 }
-                
-                // RX characteristic (we send to device)
-                if char_uuid == COMBUSTION_UART_TX_CHAR {
-                    debug!("   📤 Sending wake-up commands...");
-                    
-                    let commands: Vec<&[u8]> = vec![
-                        b"temp\r\n",
-                        b"status\r\n",
-                    ];
-                    
-                    for cmd in &commands {
-                        let _ = peripheral.write(characteristic, cmd, WriteType::WithoutResponse).await;
-                        time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+        
+        // MEATER Block: one characteristic relaying every docked probe
+        if service.uuid == MEATER_SERVICE {
+            for characteristic in &service.characteristics {
+                if characteristic.uuid == MEATER_TEMPERATURE_CHAR && peripheral.subscribe(characteristic).await.is_ok() {
+                    info!("   ✅ Subscribed to MEATER notifications");
+                    subscribed = true;
+                }
+            }
+        }
+        
+        // iGrill probe temperatures, one characteristic per jack
+        if subscribed && IGRILL_TEMPERATURE_SERVICES.contains(&service.uuid) {
+            for characteristic in &service.characteristics {
+                if IGRILL_PROBE_CHARS.contains(&characteristic.uuid) && peripheral.subscribe(characteristic).await.is_ok() {
+                    debug!("   ✅ Subscribed to iGrill probe {}", characteristic.uuid);
+                }
+            }
+        }
+        
+        // ThermoPro temperature notifications
+        if service.uuid == THERMOPRO_SERVICE {
+            for characteristic in &service.characteristics {
+                if characteristic.uuid == THERMOPRO_NOTIFY_CHAR && peripheral.subscribe(characteristic).await.is_ok() {
+                    info!("   ✅ Subscribed to ThermoPro notifications");
+                    subscribed = true;
+                }
+            }
+        }
+    }
+    
+    // Standard Battery Service; readings reuse the level until it is refreshed
+    if let Some(level) = battery.refresh(peripheral, device_address).await {
+        info!("   🔋 Battery {}%", level);
+    }
+    
+    // Send the device's wake-up sequence over the Nordic UART, if one is configured.
+    // The session lives only for these commands and each one times out, so
+    // setup cannot hang on it.
+    if wake_commands.is_empty() {
+        return Ok(subscribed);
+    }
+    match UartSession::open(peripheral).await {
+        Ok(Some(mut uart)) => {
+            info!("   📡 Subscribed to Nordic UART notifications");
+            subscribed = true;
+            
+            for (frame, delay) in wake_commands {
+                // Text commands wait for their answer; anything else is just written
+                let command = std::str::from_utf8(frame)
+                    .ok()
+                    .map(|text| text.trim_end_matches(['\r', '\n']))
+                    .filter(|text| frame_uart_command(text).is_ok_and(|framed| framed == *frame));
+                match command {
+                    Some("status") => match uart.command("status").await {
+                        Ok(status) => info!("   📡 Probe status: {}", status.text),
+                        Err(e) => debug!("   Probe status unavailable: {}", e),
+                    },
+                    Some("version") => match uart.command("version").await {
+                        Ok(version) => {
+                            let firmware = version.get("fw").unwrap_or(&version.text);
+                            if !firmware.is_empty() {
+                                info!("   🧩 Firmware {}", firmware);
+                                db.set_firmware_version(device_address, firmware).await?;
+                            }
+                        }
+                        Err(e) => debug!("   Firmware version unavailable: {}", e),
+                    },
+                    Some(command) => match uart.command(command).await {
+                        Ok(response) => debug!("   📡 {}: {}", command, response.text),
+                        Err(e) => debug!("   No answer to {}: {}", command, e),
+                    },
+                    None => {
+                        if let Err(e) = uart.send_raw(frame).await {
+                            debug!("   Wake-up command failed: {}", e);
+                        }
                     }
                 }
+                if !delay.is_zero() {
+                    time::sleep(*delay).await;
+                }
             }
         }
+        Ok(None) => {}
+        Err(e) => warn!("   ❌ UART unavailable: {}", e),
     }
     
     Ok(subscribed)
 }
 
+/// Storage-side filters between a parsed packet and the database
+struct Ingest {
+    deduper: NotificationDeduper,
+    throttle: IngestThrottle<ReadingFrame>,
+    battery: Arc<BatteryCache>,
+    igrill: Arc<IGrillSessions>,
+    telemetry: Arc<ParseFailureTelemetry>,
+}
+
+impl Ingest {
+    /// Store a frame now, or leave it with the throttle to store once the rate allows
+    async fn store(&mut self, db: &Database, address: &str, frame: ReadingFrame) -> Result<()> {
+        match self.throttle.offer(address, frame) {
+            Some(frame) => frame.store(db, address).await,
+            None => Ok(()),
+        }
+    }
+    
+    /// Store held-back frames that are due; `all` ignores the rate limit
+    async fn flush(&mut self, db: &Database, all: bool) -> Result<()> {
+        let ready = if all { self.throttle.drain() } else { self.throttle.flush() };
+        for (address, frame) in ready {
+            frame.store(db, &address).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Readings from one packet, stored together
+struct ReadingFrame {
+    timestamp: chrono::DateTime<Utc>,
+    /// Per sensor index; None for an unplugged probe
+    temperatures: Vec<Option<Temperature>>,
+    ambient_temp: Option<Temperature>,
+    battery_level: Option<u8>,
+    mode: ReadingMode,
+    prediction: Option<PredictionStatus>,
+}
+
+impl ReadingFrame {
+    async fn store(&self, db: &Database, address: &str) -> Result<()> {
+        if let Some(prediction) = &self.prediction {
+            db.insert_prediction(address, self.timestamp, prediction).await?;
+        }
+        for (i, temp) in self.temperatures.iter().enumerate() {
+            let Some(temp) = *temp else { continue };
+            db.insert_reading_with_mode(
+                address,
+                self.timestamp,
+                i,
+                temp,
+                self.ambient_temp,
+                self.battery_level,
+                0, // signal strength from properties
+                self.mode,
+            ).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A monitored device's peripheral, with what re-subscribing after a drop needs
+struct LiveDevice {
+    peripheral: btleplug::platform::Peripheral,
+    address: String,
+    wake_commands: Vec<(Vec<u8>, Duration)>,
+    connect_timeout: Duration,
+    battery: Arc<BatteryCache>,
+    igrill: Arc<IGrillSessions>,
+    db: Arc<Database>,
+}
+
+impl Reconnect for LiveDevice {
+    async fn connect(&self) -> Result<()> {
+        reconnect::connect_with_timeout(self.peripheral.connect(), self.connect_timeout).await
+    }
+    
+    async fn restore(&self) -> Result<bool> {
+        reconnect::wait_for_services(&self.peripheral, &self.address, SERVICE_DISCOVERY_TIMEOUT).await?;
+        setup_notifications(&self.peripheral, &self.address, &self.wake_commands, &self.battery, &self.igrill, &self.db).await
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn monitor_devices(
     adapter: &btleplug::platform::Adapter,
-    connected_devices: &[(btleplug::platform::Peripheral, String, String, ProbeCapabilities)],
-    db: &Database,
+    connected_devices: &mut Vec<(btleplug::platform::Peripheral, String, String, ProbeCapabilities)>,
+    db: &Arc<Database>,
     config: &Config,
     tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    battery: &Arc<BatteryCache>,
+    igrill: &Arc<IGrillSessions>,
+    telemetry: &Arc<ParseFailureTelemetry>,
+    ble_health: &BleHealth,
+    shutdown: &mut broadcast::Receiver<()>,
+    debug_commands: &mut Option<mpsc::Receiver<DebugCommand>>,
 ) -> Result<u32> {
     let mut events = adapter.events().await?;
     let start_time = std::time::Instant::now();
     let timeout = Duration::from_secs(config.device.monitor_duration);
     let mut notification_count = 0;
+    let instant_read = &config.instant_read;
+    let mut ingest = Ingest {
+        deduper: NotificationDeduper::new(
+            Duration::from_secs(config.database.min_store_interval_secs)
+        ).with_instant_read(
+            instant_read
+                .enabled
+                .then(|| InstantReadFilter::new(instant_read.stable_samples, instant_read.stable_tolerance)),
+        ),
+        throttle: IngestThrottle::new(config.ingest.max_readings_per_min, config.ingest.burst),
+        battery: battery.clone(),
+        igrill: igrill.clone(),
+        telemetry: telemetry.clone(),
+    };
+    // Reconnects run on their own tasks so the other devices keep streaming
+    let mut reconnects = JoinSet::new();
+    let mut reconnecting = HashSet::new();
     
     while start_time.elapsed() < timeout {
         tokio::select! {
+            _ = shutdown.recv() => {
+                info!("Stopping monitoring");
+                break;
+            }
+            
+            Some(command) = next_debug_command(debug_commands) => {
+                let peripheral = connected_devices
+                    .iter()
+                    .find(|(_, _, address, _)| address.eq_ignore_ascii_case(&command.device_address))
+                    .map(|(peripheral, ..)| peripheral.clone());
+                ble_debug::spawn_command(peripheral, command);
+            }
+            
             Some(event) = events.next() => {
                 match event {
                     CentralEvent::DeviceUpdated(id) => {
-                        for (peripheral, name, address, capabilities) in connected_devices {
+                        for (peripheral, name, address, capabilities) in connected_devices.iter() {
                             if peripheral.id() == id {
                                 if let Ok(reading_count) = process_device_update(
-                                    peripheral, name, address, capabilities, db, tx
+                                    peripheral, name, address, capabilities, db, tx, &mut ingest
                                 ).await {
                                     notification_count += reading_count;
                                 }
@@ -405,10 +1208,30 @@ async fn monitor_devices(
                     }
                     
                     CentralEvent::DeviceDisconnected(id) => {
-                        for (peripheral, name, _, _) in connected_devices {
-                            if peripheral.id() == id {
-                                warn!("🔌 Device {} disconnected", name);
+                        for (peripheral, name, address, capabilities) in connected_devices.iter() {
+                            if peripheral.id() != id || !reconnecting.insert(address.clone()) {
+                                continue;
                             }
+                            warn!("🔌 Device {} disconnected", name);
+                            let device = LiveDevice {
+                                peripheral: peripheral.clone(),
+                                address: address.clone(),
+                                wake_commands: config.device.wake_sequence(
+                                    &format!("{:?}", capabilities.brand),
+                                    address,
+                                    &capabilities.wake_commands,
+                                ),
+                                connect_timeout: config.device.connect_timeout(),
+                                battery: battery.clone(),
+                                igrill: igrill.clone(),
+                                db: db.clone(),
+                            };
+                            let name = name.clone();
+                            let attempts = config.device.reconnect_attempts;
+                            reconnects.spawn(async move {
+                                let result = reconnect::reconnect_with_backoff(&device, &name, attempts, RECONNECT_BASE_DELAY).await;
+                                (device.address, result)
+                            });
                         }
                     }
                     
@@ -418,12 +1241,36 @@ async fn monitor_devices(
                 }
             }
             
+            Some(Ok((address, result))) = reconnects.join_next() => {
+                reconnecting.remove(&address);
+                let Some(device) = connected_devices.iter_mut().find(|(_, _, a, _)| *a == address) else {
+                    continue;
+                };
+                match result {
+                    // Use the adapter's current handle for the device from here on
+                    Ok(_) => match adapter.peripheral(&device.0.id()).await {
+                        Ok(peripheral) => device.0 = peripheral,
+                        Err(e) => debug!("Keeping the old handle for {}: {}", device.1, e),
+                    },
+                    Err(e) => {
+                        warn!(
+                            "📴 {} is offline after {} reconnect attempts: {:#}",
+                            device.1, config.device.reconnect_attempts, e
+                        );
+                        reconnect::drop_offline_device(connected_devices, &address, |(_, _, a, _)| a, ble_health);
+                    }
+                }
+            }
+            
             _ = time::sleep(Duration::from_secs(5)) => {
+                ingest.flush(db, false).await?;
+                
                 // Periodic polling for devices that don't send notifications
-                for (peripheral, name, address, capabilities) in connected_devices {
+                for (peripheral, name, address, capabilities) in connected_devices.iter() {
                     if peripheral.is_connected().await.unwrap_or(false) {
+                        battery.refresh(peripheral, address).await;
                         if let Ok(count) = poll_device_readings(
-                            peripheral, name, address, capabilities, db, tx
+                            peripheral, name, address, capabilities, db, tx, &mut ingest
                         ).await {
                             notification_count += count;
                         }
@@ -433,9 +1280,20 @@ async fn monitor_devices(
         }
     }
     
+    // Whatever the throttle still holds is the newest reading of its device
+    ingest.flush(db, true).await?;
+    
     Ok(notification_count)
 }
 
+/// Next developer-mode debug command; never resolves when developer mode is off
+async fn next_debug_command(commands: &mut Option<mpsc::Receiver<DebugCommand>>) -> Option<DebugCommand> {
+    match commands {
+        Some(commands) => commands.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 async fn process_device_update(
     peripheral: &btleplug::platform::Peripheral,
     name: &str,
@@ -443,10 +1301,18 @@ async fn process_device_update(
     capabilities: &ProbeCapabilities,
     db: &Database,
     tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
 ) -> Result<u32> {
-    let mut count = 0;
-    
     peripheral.discover_services().await?;
+    
+    match capabilities.brand {
+        ProbeBrand::ThermoProTP25 => return read_thermopro(peripheral, name, address, db, tx, ingest).await,
+        ProbeBrand::MeaterBlock => return read_meater_block(peripheral, name, address, db, tx, ingest).await,
+        ProbeBrand::WeberIGrill => return read_igrill(peripheral, name, address, db, tx, ingest).await,
+        _ => {}
+    }
+    
+    let mut count = 0;
     let services = peripheral.services();
     
     for service in &services {
@@ -455,12 +1321,22 @@ async fn process_device_update(
                 if characteristic.uuid == MEATSTICK_CHAR {
                     if let Ok(data) = peripheral.read(characteristic).await {
                         if !data.is_empty() {
-                            count += process_temperature_data(&data, name, address, capabilities, db, tx).await?;
+                            count += process_temperature_data(&data, None, None, ReadingMode::Normal, name, address, capabilities, db, tx, ingest).await?;
                         }
                     }
                 }
             }
         }
+        
+        if service.uuid == COMBUSTION_PROBE_STATUS_SERVICE {
+            for characteristic in &service.characteristics {
+                if characteristic.uuid == COMBUSTION_PROBE_STATUS_CHAR {
+                    if let Ok(data) = peripheral.read(characteristic).await {
+                        count += process_probe_status(&data, name, address, capabilities, db, tx, ingest).await?;
+                    }
+                }
+            }
+        }
     }
     
     Ok(count)
@@ -473,7 +1349,15 @@ async fn poll_device_readings(
     capabilities: &ProbeCapabilities,
     db: &Database,
     tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
 ) -> Result<u32> {
+    match capabilities.brand {
+        ProbeBrand::ThermoProTP25 => return read_thermopro(peripheral, name, address, db, tx, ingest).await,
+        ProbeBrand::MeaterBlock => return read_meater_block(peripheral, name, address, db, tx, ingest).await,
+        ProbeBrand::WeberIGrill => return read_igrill(peripheral, name, address, db, tx, ingest).await,
+        _ => {}
+    }
+    
     let services = peripheral.services();
     let mut count = 0;
     
@@ -483,63 +1367,383 @@ async fn poll_device_readings(
                 if characteristic.uuid == MEATSTICK_CHAR {
                     if let Ok(data) = peripheral.read(characteristic).await {
                         if !data.is_empty() {
-                            count += process_temperature_data(&data, name, address, capabilities, db, tx).await?;
+                            count += process_temperature_data(&data, None, None, ReadingMode::Normal, name, address, capabilities, db, tx, ingest).await?;
                         }
                     }
                 }
             }
         }
+        
+        if service.uuid == COMBUSTION_PROBE_STATUS_SERVICE {
+            for characteristic in &service.characteristics {
+                if characteristic.uuid == COMBUSTION_PROBE_STATUS_CHAR {
+                    if let Ok(data) = peripheral.read(characteristic).await {
+                        count += process_probe_status(&data, name, address, capabilities, db, tx, ingest).await?;
+                    }
+                }
+            }
+        }
     }
     
     Ok(count)
 }
 
+/// Read the ThermoPro temperature characteristic and store every plugged-in probe
+async fn read_thermopro(
+    peripheral: &btleplug::platform::Peripheral,
+    name: &str,
+    address: &str,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    let mut count = 0;
+    
+    for service in &peripheral.services() {
+        if service.uuid != THERMOPRO_SERVICE {
+            continue;
+        }
+        for characteristic in &service.characteristics {
+            if characteristic.uuid == THERMOPRO_NOTIFY_CHAR {
+                if let Ok(data) = peripheral.read(characteristic).await {
+                    count += process_thermopro_data(&data, name, address, db, tx, ingest).await?;
+                }
+            }
+        }
+    }
+    
+    Ok(count)
+}
+
+async fn process_thermopro_data(
+    data: &[u8],
+    name: &str,
+    address: &str,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    match ThermoProProtocol::parse_temperature_data(data) {
+        Ok(temperatures) => store_probe_jacks(temperatures, data, name, address, db, tx, ingest).await,
+        Err(e) => {
+            record_rejected_frame(&e, data, "thermopro", name, address, db, ingest).await?;
+            Ok(0)
+        }
+    }
+}
+
+/// Read each iGrill probe characteristic and store every plugged-in probe
+async fn read_igrill(
+    peripheral: &btleplug::platform::Peripheral,
+    name: &str,
+    address: &str,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    let mut temperatures = vec![None; WEBER_PROBE_COUNT];
+    // Raw values of every jack, so the deduper sees the whole device at once
+    let mut raw = Vec::new();
+    
+    for service in &peripheral.services() {
+        if !IGRILL_TEMPERATURE_SERVICES.contains(&service.uuid) {
+            continue;
+        }
+        for characteristic in &service.characteristics {
+            let Some(probe) = WeberProtocol::probe_index(characteristic.uuid) else { continue };
+            let Ok(data) = peripheral.read(characteristic).await else { continue };
+            match ingest.igrill.parse_notification(address, characteristic.uuid, &data) {
+                Some(Ok(values)) => temperatures[probe] = values[probe],
+                Some(Err(e)) => record_rejected_frame(&e, &data, "igrill", name, address, db, ingest).await?,
+                None => {
+                    debug!("{} has not paired; ignoring its probe values", name);
+                    return Ok(0);
+                }
+            }
+            raw.extend_from_slice(&data);
+        }
+    }
+    
+    if raw.is_empty() {
+        return Ok(0);
+    }
+    store_probe_jacks(temperatures, &raw, name, address, db, tx, ingest).await
+}
+
+/// Store and broadcast the readings of a multi-jack thermometer; `None` jacks are unplugged
+async fn store_probe_jacks(
+    temperatures: Vec<Option<Temperature>>,
+    data: &[u8],
+    name: &str,
+    address: &str,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    let timestamp = Utc::now();
+    let battery_level = ingest.battery.level(address);
+    let probes: Vec<String> = temperatures
+        .iter()
+        .map(|t| t.map_or_else(|| "--".to_string(), |t| t.to_string()))
+        .collect();
+    info!("🌡️  {} - Probes: {}", name, probes.join(", "));
+    
+    if ingest.deduper.should_store(address, data, None) {
+        ingest.store(db, address, ReadingFrame {
+            timestamp,
+            temperatures: temperatures.clone(),
+            ambient_temp: None,
+            battery_level,
+            mode: ReadingMode::Normal,
+            prediction: None,
+        }).await?;
+    } else {
+        debug!("Skipping storage of duplicate packet from {}", name);
+    }
+    
+    let mut count = 0;
+    // Unplugged probes keep their index but produce no reading
+    for (i, temp) in temperatures.into_iter().enumerate() {
+        let Some(temp) = temp else { continue };
+        
+        broadcast_update(tx, TemperatureUpdate {
+            device_address: address.to_string(),
+            device_name: name.to_string(),
+            timestamp,
+            sensor_index: i,
+            temperature: temp.celsius(),
+            ambient_temp: None,
+            unit: TemperatureUnit::Celsius,
+            battery_level,
+            signal_strength: 0,
+            eta_seconds: None,
+            reading_mode: ReadingMode::Normal,
+            virtual_sensors: None,
+        });
+        
+        count += 1;
+    }
+    
+    Ok(count)
+}
+
+/// Read the MEATER Block's relay characteristic and store each docked probe
+async fn read_meater_block(
+    peripheral: &btleplug::platform::Peripheral,
+    name: &str,
+    address: &str,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    let mut count = 0;
+    
+    for service in &peripheral.services() {
+        if service.uuid != MEATER_SERVICE {
+            continue;
+        }
+        for characteristic in &service.characteristics {
+            if characteristic.uuid == MEATER_TEMPERATURE_CHAR {
+                if let Ok(data) = peripheral.read(characteristic).await {
+                    count += process_meater_block_data(&data, name, address, db, tx, ingest).await?;
+                }
+            }
+        }
+    }
+    
+    Ok(count)
+}
+
+/// Store and broadcast each probe relayed by a Block as a device of its own
+async fn process_meater_block_data(
+    data: &[u8],
+    name: &str,
+    address: &str,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    let probes = match MeaterProtocol::parse_block_data(data) {
+        Ok(probes) => probes,
+        Err(e) => {
+            record_rejected_frame(&e, data, "meater_block", name, address, db, ingest).await?;
+            return Ok(0);
+        }
+    };
+    
+    let timestamp = Utc::now();
+    let mut count = 0;
+    for probe in probes {
+        let probe_address = MeaterProtocol::block_probe_address(address, probe.slot);
+        let probe_name = format!("{} Probe {}", name, probe.slot);
+        let [tip, ambient] = [0, 1].map(|i| probe.temperatures.get(i).copied().flatten());
+        info!("🌡️  {} - Tip: {}, Ambient: {}",
+            probe_name,
+            tip.map_or_else(|| "--".to_string(), |t| t.to_string()),
+            ambient.map_or_else(|| "--".to_string(), |t| t.to_string())
+        );
+        
+        // Each slot is its own card; keeps last_seen current while the Block relays it
+        db.upsert_device(
+            &probe_address,
+            &probe_name,
+            &format!("{:?}", ProbeBrand::MeaterOriginal),
+            "MEATER",
+            probe.temperatures.len(),
+        ).await?;
+        
+        if ingest.deduper.should_store(&probe_address, &probe.payload, None) {
+            ingest.store(db, &probe_address, ReadingFrame {
+                timestamp,
+                temperatures: probe.temperatures.clone(),
+                ambient_temp: ambient,
+                // The Block's own battery, not the probe's
+                battery_level: None,
+                mode: ReadingMode::Normal,
+                prediction: None,
+            }).await?;
+        }
+        
+        for (i, temp) in probe.temperatures.iter().enumerate() {
+            let Some(temp) = temp else { continue };
+            broadcast_update(tx, TemperatureUpdate {
+                device_address: probe_address.clone(),
+                device_name: probe_name.clone(),
+                timestamp,
+                sensor_index: i,
+                temperature: temp.celsius(),
+                ambient_temp: ambient.map(|t| t.celsius()),
+                unit: TemperatureUnit::Celsius,
+                battery_level: None,
+                signal_strength: 0,
+                eta_seconds: None,
+                reading_mode: ReadingMode::Normal,
+                virtual_sensors: None,
+            });
+            count += 1;
+        }
+    }
+    
+    Ok(count)
+}
+
+/// Handle a Combustion probe status packet: raw temperatures plus prediction status
+async fn process_probe_status(
+    data: &[u8],
+    name: &str,
+    address: &str,
+    capabilities: &ProbeCapabilities,
+    db: &Database,
+    tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
+) -> Result<u32> {
+    let mode = match MeatStickProtocol::parse_mode(data) {
+        Ok(ReadingMode::Error) => {
+            warn!("⚠️  {} reports a probe error, skipping packet", name);
+            return Ok(0);
+        }
+        Ok(mode) => mode,
+        Err(e) => {
+            debug!("No mode byte from {}: {}", name, e);
+            ReadingMode::Normal
+        }
+    };
+    
+    let prediction = match MeatStickProtocol::parse_prediction(data) {
+        Ok(prediction) => Some(prediction),
+        Err(e) => {
+            debug!("No prediction status from {}: {}", name, e);
+            None
+        }
+    };
+    
+    let virtual_sensors = match MeatStickProtocol::virtual_sensors(data) {
+        Ok(sensors) => Some(sensors),
+        Err(e) => {
+            debug!("No virtual sensors from {}: {}", name, e);
+            None
+        }
+    };
+    
+    let temperatures = data
+        .get(PROBE_STATUS_TEMPERATURE_OFFSET..PROBE_STATUS_TEMPERATURE_OFFSET + MEATSTICK_FRAME_LEN)
+        .unwrap_or_default();
+    process_temperature_data(temperatures, prediction.as_ref(), virtual_sensors, mode, name, address, capabilities, db, tx, ingest).await
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn process_temperature_data(
     data: &[u8],
+    prediction: Option<&PredictionStatus>,
+    virtual_sensors: Option<VirtualSensors>,
+    mode: ReadingMode,
     name: &str,
     address: &str,
     _capabilities: &ProbeCapabilities,
     db: &Database,
     tx: &tokio::sync::broadcast::Sender<TemperatureUpdate>,
+    ingest: &mut Ingest,
 ) -> Result<u32> {
     match MeatStickProtocol::parse_temperature_data(data) {
         Ok(temperatures) => {
             let timestamp = Utc::now();
-            let ambient_temp = MeatStickProtocol::get_ambient_temp(&temperatures);
-            let internal_temp = MeatStickProtocol::get_internal_temp(&temperatures);
+            let battery_level = ingest.battery.level(address);
+            // Prefer the sensors the probe picked; guess when it did not say or they read nothing
+            let ambient_temp = virtual_sensors
+                .and_then(|v| v.ambient_temp(&temperatures))
+                .or_else(|| MeatStickProtocol::get_ambient_temp(&temperatures));
+            let internal_temp = virtual_sensors
+                .and_then(|v| v.core_temp(&temperatures))
+                .or_else(|| MeatStickProtocol::get_internal_temp(&temperatures));
             
-            info!("🌡️  {} - Internal: {:.1}°F, Ambient: {:.1}°F, Sensors: {}", 
+            info!("🌡️  {} - Internal: {}, Ambient: {}, Sensors: {}", 
                 name,
-                internal_temp.unwrap_or(0.0),
-                ambient_temp.unwrap_or(0.0),
-                temperatures.len()
+                internal_temp.map_or_else(|| "--".to_string(), |t| t.to_string()),
+                ambient_temp.map_or_else(|| "--".to_string(), |t| t.to_string()),
+                temperatures.iter().flatten().count()
             );
             
-            // Store each sensor reading
-            let mut count = 0;
-            for (i, &temp) in temperatures.iter().enumerate() {
-                db.insert_reading(
-                    address,
+            // Repeated packets are still broadcast so the live view stays fresh,
+            // but only stored once; instant-read samples only once they settle
+            let store = ingest.deduper.should_store_frame(address, data, mode, internal_temp);
+            
+            if let Some(prediction) = prediction {
+                debug!("🔮 {} - Prediction: {:?}, ETA: {:?}s", name, prediction.state, prediction.eta_seconds);
+            }
+            if store {
+                ingest.store(db, address, ReadingFrame {
                     timestamp,
-                    i,
-                    temp,
+                    temperatures: temperatures.clone(),
                     ambient_temp,
-                    None, // battery level not available yet
-                    0,    // signal strength from properties
-                ).await?;
-                
+                    battery_level,
+                    mode,
+                    prediction: prediction.cloned(),
+                }).await?;
+            } else {
+                debug!("Skipping storage of {} packet from {}", mode.as_str(), name);
+            }
+            let eta_seconds = prediction.and_then(|p| p.eta_seconds);
+            
+            // Broadcast each sensor reading; sensors with no valid value are skipped
+            let mut count = 0;
+            for (i, temp) in temperatures.iter().enumerate() {
+                let Some(temp) = temp else { continue };
                 // Broadcast update to web clients
                 let update = TemperatureUpdate {
                     device_address: address.to_string(),
                     device_name: name.to_string(),
                     timestamp,
                     sensor_index: i,
-                    temperature: temp,
-                    ambient_temp,
-                    battery_level: None,
+                    temperature: temp.celsius(),
+                    ambient_temp: ambient_temp.map(|t| t.celsius()),
+                    unit: TemperatureUnit::Celsius,
+                    battery_level,
                     signal_strength: 0,
+                    eta_seconds,
+                    reading_mode: mode,
+                    virtual_sensors,
                 };
-                let _ = tx.send(update);
+                broadcast_update(tx, update);
                 
                 count += 1;
             }
@@ -547,34 +1751,63 @@ async fn process_temperature_data(
             Ok(count)
         }
         Err(e) => {
-            debug!("Failed to parse temperature data from {}: {}", name, e);
-            debug!("Raw data: {:02X?}", data);
+            record_rejected_frame(&e, data, "meatstick", name, address, db, ingest).await?;
             Ok(0)
         }
     }
 }
 
+/// Log a frame the parser rejected, counting it on the device when it points at a flaky probe
+///
+/// `protocol` names the parser for the opt-in parse failure telemetry.
+async fn record_rejected_frame(
+    error: &ProtocolError,
+    data: &[u8],
+    protocol: &str,
+    name: &str,
+    address: &str,
+    db: &Database,
+    ingest: &Ingest,
+) -> Result<()> {
+    ingest.telemetry.record(name, protocol, error, data.len());
+    match error {
+        // Partial notifications and other characteristics' packets are routine: not stored
+        ProtocolError::TooShort { .. } | ProtocolError::UnsupportedFormat { .. } => {
+            debug!("Ignoring frame from {}: {}", name, error);
+        }
+        ProtocolError::OutOfRange { .. } => {
+            let rejected = db.record_rejected_frame(address, &error.to_string()).await?;
+            warn!("⚠️  Implausible reading from {} ({} total): {}", name, rejected, error);
+        }
+    }
+    debug!("Raw data: {:02X?}", data);
+    Ok(())
+}
+
+/// "S/N 1234 FW 1.4.2 HW B (Acme)" from whatever the device reported
+fn describe_device_info(info: &DeviceInfo) -> String {
+    let fields = [("S/N", &info.serial_number), ("FW", &info.firmware_revision), ("HW", &info.hardware_revision)];
+    let mut parts: Vec<String> = fields
+        .iter()
+        .filter_map(|(label, value)| value.as_ref().map(|v| format!("{} {}", label, v)))
+        .collect();
+    if let Some(manufacturer) = &info.manufacturer {
+        parts.push(format!("({})", manufacturer));
+    }
+    parts.join(" ")
+}
+
 async fn print_device_summary(
     devices: &[(btleplug::platform::Peripheral, String, String, ProbeCapabilities)],
 ) -> Result<()> {
     info!("🔍 DEVICE SUMMARY:");
     
     for (peripheral, name, address, capabilities) in devices {
-        let services = peripheral.services();
         let mut info_str = format!("  {} ({}) - {:?}", name, address, capabilities.brand);
         
-        // Try to read serial number
-        for service in &services {
-            if service.uuid.to_string() == "0000180a-0000-1000-8000-00805f9b34fb" {
-                for characteristic in &service.characteristics {
-                    if characteristic.uuid.to_string() == "00002a25-0000-1000-8000-00805f9b34fb" {
-                        if let Ok(data) = peripheral.read(characteristic).await {
-                            let serial = String::from_utf8_lossy(&data);
-                            info_str.push_str(&format!(" S/N: {}", serial));
-                        }
-                    }
-                }
-            }
+        let device_info = read_device_info(peripheral).await;
+        if !device_info.is_empty() {
+            info_str.push_str(&format!(" {}", describe_device_info(&device_info)));
         }
         
         info!("{}", info_str);
@@ -0,0 +1,378 @@
+// src/maintenance.rs
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+use crate::database::Database;
+use crate::device_capabilities::{sensor_layout, ProbeBrand, ProbeCapabilities, SensorRole};
+use crate::temperature::Temperature;
+use crate::web_server::TemperatureUpdate;
+
+/// How often calibration reminders are checked
+pub const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What a device note records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum NoteCategory {
+    /// Free-form note
+    Note,
+    /// Battery replaced or charged
+    Battery,
+    /// Probe checked against a reference (e.g. "calibrated, +1.5°F")
+    Calibration,
+    Repair,
+}
+
+impl NoteCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Battery => "battery",
+            Self::Calibration => "calibration",
+            Self::Repair => "repair",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "note" => Some(Self::Note),
+            "battery" => Some(Self::Battery),
+            "calibration" => Some(Self::Calibration),
+            "repair" => Some(Self::Repair),
+            _ => None,
+        }
+    }
+}
+
+/// A maintenance log entry for a device
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow, Serialize)]
+pub struct DeviceNote {
+    pub id: i64,
+    pub device_address: String,
+    /// When the work was done (defaults to when the note was written)
+    pub timestamp: DateTime<Utc>,
+    pub category: NoteCategory,
+    pub text: String,
+}
+
+/// A probe whose last calibration is older than the configured limit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalibrationReminder {
+    pub device_address: String,
+    pub display_name: String,
+    pub last_calibration: DateTime<Utc>,
+    /// First day the calibration counts as stale
+    pub due_on: NaiveDate,
+    pub days_since: i64,
+}
+
+/// Day a calibration done at `calibrated` becomes stale, counting calendar days (UTC)
+pub fn calibration_due_on(calibrated: DateTime<Utc>, max_age_days: u32) -> NaiveDate {
+    calibrated.date_naive() + Days::new(max_age_days as u64 + 1)
+}
+
+/// Whether a calibration done at `calibrated` is older than `max_age_days` on `now`
+pub fn calibration_stale(calibrated: DateTime<Utc>, max_age_days: u32, now: DateTime<Utc>) -> bool {
+    now.date_naive() >= calibration_due_on(calibrated, max_age_days)
+}
+
+/// Calibration reminders for every device that was calibrated more than `max_age_days` ago
+///
+/// Devices that were never calibrated get no reminder; most probes ship
+/// calibrated and only the ones somebody tracks are worth nagging about.
+pub async fn calibration_reminders(
+    db: &Database,
+    max_age_days: u32,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Vec<CalibrationReminder>> {
+    let mut reminders = Vec::new();
+    for device in db.get_all_devices().await? {
+        let Some(last_calibration) = db
+            .latest_note_time(&device.device_address, NoteCategory::Calibration)
+            .await?
+        else {
+            continue;
+        };
+        if calibration_stale(last_calibration, max_age_days, now) {
+            reminders.push(CalibrationReminder {
+                display_name: device.display_name(),
+                device_address: device.device_address,
+                last_calibration,
+                due_on: calibration_due_on(last_calibration, max_age_days),
+                days_since: (now.date_naive() - last_calibration.date_naive()).num_days(),
+            });
+        }
+    }
+    Ok(reminders)
+}
+
+/// Check calibration ages once a day until shutdown, warning about stale probes
+pub async fn start_reminder_task(db: Arc<Database>, max_age_days: u32, mut shutdown: broadcast::Receiver<()>) {
+    info!("🔧 Calibration reminders enabled ({} days)", max_age_days);
+    let mut interval = tokio::time::interval(REMINDER_CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match calibration_reminders(&db, max_age_days, Utc::now()).await {
+                    Ok(reminders) if reminders.is_empty() => debug!("All calibrations are current"),
+                    Ok(reminders) => {
+                        for reminder in reminders {
+                            warn!(
+                                "🔧 {} was last calibrated {} days ago ({}), recalibration due",
+                                reminder.display_name,
+                                reminder.days_since,
+                                reminder.last_calibration.date_naive()
+                            );
+                        }
+                    }
+                    Err(e) => error!("Calibration reminder check failed: {:#}", e),
+                }
+            }
+            _ = shutdown.recv() => {
+                debug!("Calibration reminders stopping");
+                break;
+            }
+        }
+    }
+}
+
+/// Flags probes whose internal sensors went above their rated maximum
+///
+/// Even a brief exceedance can damage a probe, so unlike an alert the
+/// warning is stored on the device and stays until somebody clears it.
+pub struct ProbeDamageWatch {
+    db: Arc<Database>,
+    /// Sensor count and rated internal maximum of each watched device
+    limits: Mutex<HashMap<String, (usize, Temperature)>>,
+}
+
+impl ProbeDamageWatch {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db, limits: Mutex::new(HashMap::new()) }
+    }
+
+    /// Watch a connected device; ones without an internal rating (base stations) are skipped
+    pub fn watch(&self, device_address: &str, capabilities: &ProbeCapabilities) {
+        if capabilities.max_internal_temp_f <= 0.0 || capabilities.sensor_count == 0 {
+            return;
+        }
+        let limit = Temperature::from_fahrenheit(capabilities.max_internal_temp_f);
+        self.limits.lock().unwrap().insert(device_address.to_string(), (capabilities.sensor_count, limit));
+    }
+
+    /// Check one reading; returns true when it newly flagged the device
+    pub async fn check(
+        &self,
+        device_address: &str,
+        sensor_index: usize,
+        temperature: Temperature,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let Some((sensor_count, limit)) = self.limits.lock().unwrap().get(device_address).copied() else {
+            return Ok(false);
+        };
+        if temperature <= limit || sensor_layout(sensor_count, sensor_index).0 != SensorRole::Internal {
+            return Ok(false);
+        }
+
+        let flagged = self.db.flag_probe_damage(device_address, temperature, at).await?;
+        if flagged {
+            warn!(
+                "🔥 {} sensor {} reached {}, above its rated {}: possible probe damage",
+                device_address, sensor_index, temperature, limit
+            );
+        }
+        Ok(flagged)
+    }
+}
+
+/// Check every live update for over-temperature until the channel closes
+pub async fn run_damage_watch(watch: Arc<ProbeDamageWatch>, mut rx: broadcast::Receiver<TemperatureUpdate>) {
+    loop {
+        match rx.recv().await {
+            Ok(update) => {
+                let temperature = Temperature::from_unit(update.temperature, update.unit);
+                if let Err(e) = watch.check(&update.device_address, update.sensor_index, temperature, update.timestamp).await {
+                    error!("Probe damage check failed for {}: {:#}", update.device_address, e);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Probe damage watch lagged, skipped {} updates", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// One derived field `recompute_device_fields` changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+/// A device whose stored fields were out of date
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceRecompute {
+    pub device_address: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// What `recompute_device_fields` looked at and changed
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecomputeSummary {
+    pub devices_checked: usize,
+    pub devices_changed: Vec<DeviceRecompute>,
+}
+
+/// Bring every device's derived fields in line with its readings and current detection rules
+///
+/// - `sensor_count` grows to cover every sensor readings were stored for, and
+///   never drops below what the brand's detection gives, so a TP25 that only
+///   ever used jack 1 keeps its 4 sensors
+/// - `last_seen` moves up to the newest reading if that is later
+/// - devices stored under an unrecognised brand get the brand (and, without
+///   readings, the sensor count) detection now gives their name
+///
+/// Known brands are left alone: some were detected from advertisement data
+/// that is not stored, so the name alone could get them wrong.
+pub async fn recompute_device_fields(db: &Database) -> anyhow::Result<RecomputeSummary> {
+    let observed: HashMap<String, _> = db
+        .observed_readings()
+        .await?
+        .into_iter()
+        .map(|o| (o.device_address.clone(), o))
+        .collect();
+    let devices = db.get_all_devices().await?;
+    let mut summary = RecomputeSummary { devices_checked: devices.len(), ..Default::default() };
+
+    for device in devices {
+        let mut brand = device.brand.clone();
+        let mut sensor_count = device.sensor_count;
+        let mut last_seen = device.last_seen;
+
+        let detected = ProbeCapabilities::detect_from_device(&device.device_name, &device.device_address, &[], &HashMap::new());
+        if device.brand.starts_with("Unknown") && !matches!(detected.brand, ProbeBrand::Unknown(_)) {
+            brand = format!("{:?}", detected.brand);
+            sensor_count = detected.sensor_count as i64;
+        }
+        if format!("{:?}", detected.brand) == brand {
+            sensor_count = sensor_count.max(detected.sensor_count as i64);
+        }
+        if let Some(observed) = observed.get(&device.device_address) {
+            sensor_count = sensor_count.max(observed.max_sensor_index + 1);
+            last_seen = last_seen.max(observed.last_reading);
+        }
+
+        let mut changes = Vec::new();
+        if brand != device.brand {
+            changes.push(FieldChange { field: "brand", from: device.brand.clone().into(), to: brand.clone().into() });
+        }
+        if sensor_count != device.sensor_count {
+            changes.push(FieldChange { field: "sensor_count", from: device.sensor_count.into(), to: sensor_count.into() });
+        }
+        if last_seen != device.last_seen {
+            changes.push(FieldChange {
+                field: "last_seen",
+                from: device.last_seen.to_rfc3339().into(),
+                to: last_seen.to_rfc3339().into(),
+            });
+        }
+        if changes.is_empty() {
+            continue;
+        }
+
+        db.set_derived_device_fields(&device.device_address, &brand, sensor_count, last_seen).await?;
+        let described: Vec<String> = changes.iter().map(|c| format!("{} {} → {}", c.field, c.from, c.to)).collect();
+        info!("🔧 Recomputed {}: {}", device.device_address, described.join(", "));
+        summary.devices_changed.push(DeviceRecompute { device_address: device.device_address, changes });
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_calibration_age_across_months() {
+        // 90 days after 2 November is 31 January
+        let calibrated = at(2024, 11, 2, 18);
+        assert_eq!(calibration_due_on(calibrated, 90), NaiveDate::from_ymd_opt(2025, 2, 1).unwrap());
+        assert!(!calibration_stale(calibrated, 90, at(2025, 1, 31, 23)));
+        assert!(calibration_stale(calibrated, 90, at(2025, 2, 1, 0)));
+
+        // Counts calendar days, so a leap day is one more day
+        let calibrated = at(2024, 1, 30, 9);
+        assert_eq!(calibration_due_on(calibrated, 30), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert!(!calibration_stale(calibrated, 30, at(2024, 2, 29, 12)));
+        assert!(calibration_stale(calibrated, 30, at(2024, 3, 1, 0)));
+        assert_eq!(calibration_due_on(at(2023, 1, 30, 9), 30), NaiveDate::from_ymd_opt(2023, 3, 2).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reminders_only_for_stale_calibrations() {
+        let db = Database::new(":memory:").await.unwrap();
+        for address in ["AA", "BB", "CC"] {
+            db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        }
+        let now = at(2025, 3, 15, 12);
+        db.insert_device_note("AA", at(2024, 12, 1, 12), NoteCategory::Calibration, "calibrated, +1.5°F")
+            .await
+            .unwrap();
+        db.insert_device_note("BB", at(2025, 3, 1, 12), NoteCategory::Calibration, "ice bath ok")
+            .await
+            .unwrap();
+        db.insert_device_note("CC", at(2024, 1, 1, 12), NoteCategory::Battery, "replaced battery")
+            .await
+            .unwrap();
+
+        let reminders = calibration_reminders(&db, 90, now).await.unwrap();
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].device_address, "AA");
+        assert_eq!(reminders[0].days_since, 104);
+    }
+
+    #[tokio::test]
+    async fn test_damage_flag_persists_until_cleared() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        db.upsert_device("AA", "MEATER", "MeaterOriginal", "MEATER", 2).await.unwrap();
+        let watch = ProbeDamageWatch::new(db.clone());
+        let capabilities = ProbeCapabilities::detect_from_device("MEATER", "AA", &[], &HashMap::new());
+        watch.watch("AA", &capabilities);
+        let f = Temperature::from_fahrenheit;
+        let first = at(2025, 7, 4, 12);
+
+        assert!(!watch.check("AA", 0, f(211.0), first).await.unwrap(), "within the 212°F rating");
+        assert!(!watch.check("AA", 1, f(400.0), first).await.unwrap(), "the ambient sensor has its own rating");
+        assert!(!watch.check("BB", 0, f(400.0), first).await.unwrap(), "unwatched device");
+        assert!(db.get_device("AA").await.unwrap().damage_risk_at.is_none());
+
+        assert!(watch.check("AA", 0, f(215.0), first).await.unwrap());
+        assert!(!watch.check("AA", 0, f(230.0), at(2025, 7, 4, 13)).await.unwrap(), "already flagged");
+        // Cooling down does not clear it
+        assert!(!watch.check("AA", 0, f(150.0), at(2025, 7, 4, 14)).await.unwrap());
+        let device = db.get_device("AA").await.unwrap();
+        assert_eq!(device.damage_risk_at, Some(first), "keeps the first exceedance");
+        assert_eq!(device.damage_risk_peak, Some(f(230.0)));
+
+        assert!(db.clear_probe_damage("AA").await.unwrap());
+        assert!(!db.clear_probe_damage("AA").await.unwrap(), "nothing left to clear");
+        let device = db.get_device("AA").await.unwrap();
+        assert!(device.damage_risk_at.is_none() && device.damage_risk_peak.is_none());
+        assert!(watch.check("AA", 0, f(213.0), at(2025, 7, 5, 12)).await.unwrap(), "flags again after clearing");
+    }
+}
@@ -0,0 +1,532 @@
+// src/migrations.rs
+//
+// Self-contained (sqlx runtime API only) so `cargo xtask prepare-sqlx` can
+// build the schema the query macros are checked against.
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use std::future::Future;
+use std::pin::Pin;
+use tracing::{info, warn};
+
+/// Version of the SQLite schema created by `initialize` (the newest migration)
+pub const DB_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// First `PRAGMA user_version` (the schema marker before migrations) that stored Celsius
+const CELSIUS_SCHEMA_VERSION: i64 = 3;
+
+/// Last `PRAGMA user_version` written before migrations
+const LEGACY_USER_VERSION: i64 = 5;
+
+pub(crate) type MigrationFuture<'c> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'c>>;
+
+/// One step in the schema history, run inside a transaction by `run_migrations`
+#[derive(Clone, Copy)]
+pub(crate) struct Migration {
+    pub(crate) description: &'static str,
+    pub(crate) apply: for<'c> fn(&'c mut SqliteConnection) -> MigrationFuture<'c>,
+}
+
+/// Schema history, oldest first; a migration's version is its position + 1
+///
+/// Append new migrations to the end. Never edit or reorder one that has shipped.
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration { description: "baseline schema", apply: |conn| Box::pin(baseline_schema(conn)) },
+    Migration { description: "reading source", apply: |conn| Box::pin(add_reading_source(conn)) },
+    Migration { description: "settings table", apply: |conn| Box::pin(create_settings(conn)) },
+    Migration { description: "pending sync queue", apply: |conn| Box::pin(create_pending_sync(conn)) },
+    Migration { description: "device information", apply: |conn| Box::pin(add_device_information(conn)) },
+    Migration { description: "device alias", apply: |conn| Box::pin(add_device_alias(conn)) },
+    Migration { description: "default alert bootstraps", apply: |conn| Box::pin(create_alert_bootstraps(conn)) },
+    Migration { description: "probe damage warning", apply: |conn| Box::pin(add_probe_damage_warning(conn)) },
+    Migration { description: "cook session clock anchors", apply: |conn| Box::pin(add_cook_clock_anchors(conn)) },
+    Migration { description: "device target", apply: |conn| Box::pin(add_device_target(conn)) },
+    Migration { description: "default alert rules created", apply: |conn| Box::pin(add_alert_rules_created(conn)) },
+    Migration { description: "cook pit setpoint", apply: |conn| Box::pin(add_cook_pit_setpoint(conn)) },
+];
+
+/// Migration 1: the schema as it stood before the runner existed
+///
+/// Also brings databases from before the runner up to date: missing tables
+/// and columns are added, and Fahrenheit data (user_version below 3) is
+/// converted to Celsius.
+async fn baseline_schema(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS devices (
+            device_address TEXT PRIMARY KEY,
+            device_name TEXT NOT NULL,
+            brand TEXT NOT NULL,
+            model TEXT NOT NULL,
+            sensor_count INTEGER NOT NULL,
+            first_seen DATETIME NOT NULL,
+            last_seen DATETIME NOT NULL,
+            serial_number TEXT,
+            probe_id INTEGER,
+            probe_color TEXT,
+            firmware_version TEXT
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create devices table")?;
+    
+    // Probe identity columns (user_version 4)
+    add_column_if_missing(&mut *conn, "devices", "serial_number", "TEXT").await?;
+    add_column_if_missing(&mut *conn, "devices", "probe_id", "INTEGER").await?;
+    add_column_if_missing(&mut *conn, "devices", "probe_color", "TEXT").await?;
+    add_column_if_missing(&mut *conn, "devices", "firmware_version", "TEXT").await?;
+    
+    // A probe keeps one row however often its MAC rotates
+    sqlx::query(
+        r#"
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_devices_serial
+        ON devices(serial_number) WHERE serial_number IS NOT NULL
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create device serial index")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS readings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device_address TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            sensor_index INTEGER NOT NULL,
+            temperature REAL NOT NULL,
+            ambient_temp REAL,
+            battery_level INTEGER,
+            signal_strength INTEGER NOT NULL,
+            reading_mode TEXT NOT NULL DEFAULT 'normal',
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create readings table")?;
+    
+    // Reading mode (user_version 2)
+    add_column_if_missing(&mut *conn, "readings", "reading_mode", "TEXT NOT NULL DEFAULT 'normal'")
+        .await?;
+    
+    // Create index for faster queries
+    sqlx::query(
+        r#"
+        CREATE INDEX IF NOT EXISTS idx_readings_timestamp 
+        ON readings(timestamp DESC)
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create timestamp index")?;
+    
+    sqlx::query(
+        r#"
+        CREATE INDEX IF NOT EXISTS idx_readings_device 
+        ON readings(device_address, timestamp DESC)
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create device index")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS sync_state (
+            direction TEXT PRIMARY KEY,
+            last_synced DATETIME NOT NULL
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create sync_state table")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS predictions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device_address TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            state TEXT NOT NULL,
+            set_point REAL NOT NULL,
+            estimated_core REAL NOT NULL,
+            eta_seconds INTEGER,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create predictions table")?;
+    
+    sqlx::query(
+        r#"
+        CREATE INDEX IF NOT EXISTS idx_predictions_device 
+        ON predictions(device_address, timestamp DESC)
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create predictions index")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS cook_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device_address TEXT NOT NULL,
+            name TEXT NOT NULL,
+            target_temp REAL,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create cook_sessions table")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS frame_stats (
+            device_address TEXT PRIMARY KEY,
+            rejected_count INTEGER NOT NULL DEFAULT 0,
+            last_rejected_at DATETIME,
+            last_reason TEXT,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create frame_stats table")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS device_settings (
+            device_address TEXT PRIMARY KEY,
+            primary_sensor_index INTEGER,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create device_settings table")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS smoker_profiles (
+            device_address TEXT PRIMARY KEY,
+            efficiency REAL NOT NULL DEFAULT 1.0,
+            notes TEXT,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create smoker_profiles table")?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alert_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device_address TEXT NOT NULL,
+            sensor_index INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            threshold REAL NOT NULL,
+            hysteresis REAL NOT NULL,
+            carryover REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create alert_rules table")?;
+    add_column_if_missing(&mut *conn, "alert_rules", "carryover", "REAL NOT NULL DEFAULT 0").await?;
+    
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS device_notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device_address TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            category TEXT NOT NULL,
+            text TEXT NOT NULL,
+            FOREIGN KEY (device_address) REFERENCES devices(device_address)
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create device_notes table")?;
+    
+    // At most one open cook per device
+    sqlx::query(
+        r#"
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_cook_sessions_open 
+        ON cook_sessions(device_address) WHERE ended_at IS NULL
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create open cook index")?;
+    
+    convert_legacy_fahrenheit(&mut *conn).await?;
+    
+    // Older builds still read PRAGMA user_version; the last value they know
+    // keeps them from converting the data a second time
+    sqlx::query(&format!("PRAGMA user_version = {}", LEGACY_USER_VERSION))
+        .execute(&mut *conn)
+        .await
+        .context("Failed to update legacy schema version")?;
+    
+    Ok(())
+}
+
+/// Migration 2: where each reading came from; everything stored before was live BLE
+async fn add_reading_source(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "readings", "source", "TEXT NOT NULL DEFAULT 'ble'").await
+}
+
+/// Migration 3: key/value settings for installs without a writable config file
+async fn create_settings(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at DATETIME NOT NULL
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create settings table")?;
+    Ok(())
+}
+
+/// Migration 4: readings whose cloud upload failed, retried by the sync task
+async fn create_pending_sync(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS pending_sync (
+            reading_id INTEGER PRIMARY KEY,
+            queued_at DATETIME NOT NULL
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create pending_sync table")?;
+    Ok(())
+}
+
+/// Migration 5: hardware revision and manufacturer from the Device Information Service
+async fn add_device_information(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "devices", "hardware_revision", "TEXT").await?;
+    add_column_if_missing(&mut *conn, "devices", "manufacturer", "TEXT").await
+}
+
+/// Migration 6: a name the user gave the device, shown instead of the BLE name
+async fn add_device_alias(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "devices", "alias", "TEXT").await
+}
+
+/// Migration 7: devices that were given default alerts, so they never get them twice
+async fn create_alert_bootstraps(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alert_bootstraps (
+            device_address TEXT PRIMARY KEY,
+            bootstrapped_at DATETIME NOT NULL
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to create alert_bootstraps table")?;
+    Ok(())
+}
+
+/// Migration 8: when a probe first went above its rated internal maximum, and how hot it got
+async fn add_probe_damage_warning(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "devices", "damage_risk_at", "DATETIME").await?;
+    add_column_if_missing(&mut *conn, "devices", "damage_risk_peak", "REAL").await
+}
+
+/// Migration 9: boot id and uptime next to cook start/end, so durations survive wall clock resets
+async fn add_cook_clock_anchors(conn: &mut SqliteConnection) -> Result<()> {
+    for (column, definition) in [
+        ("start_boot_id", "TEXT"),
+        ("start_uptime_ms", "INTEGER"),
+        ("end_boot_id", "TEXT"),
+        ("end_uptime_ms", "INTEGER"),
+    ] {
+        add_column_if_missing(&mut *conn, "cook_sessions", column, definition).await?;
+    }
+    Ok(())
+}
+
+/// Migration 10: a target temperature kept on the device, for probes used without a cook session
+async fn add_device_target(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "devices", "target_temp", "REAL").await?;
+    add_column_if_missing(&mut *conn, "devices", "target_sensor_index", "INTEGER").await
+}
+
+/// Migration 11: when a bootstrapped device's default rules were stored (NULL = waiting for Premium)
+///
+/// Devices bootstrapped before this column existed count as done when they
+/// have any alert rule; the others were seen without Premium and still wait.
+async fn add_alert_rules_created(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "alert_bootstraps", "rules_created_at", "DATETIME").await?;
+    sqlx::query(
+        r#"
+        UPDATE alert_bootstraps SET rules_created_at = bootstrapped_at
+        WHERE device_address IN (SELECT device_address FROM alert_rules)
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to mark existing default alert rules")?;
+    Ok(())
+}
+
+/// Migration 12: the pit temperature a cook was run at, for the fuel estimate
+async fn add_cook_pit_setpoint(conn: &mut SqliteConnection) -> Result<()> {
+    add_column_if_missing(&mut *conn, "cook_sessions", "pit_setpoint", "REAL").await
+}
+
+/// Convert temperatures written before user_version 3 from Fahrenheit to Celsius
+async fn convert_legacy_fahrenheit(conn: &mut SqliteConnection) -> Result<()> {
+    let version = sqlx::query_scalar::<_, i64>("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await
+        .context("Failed to read legacy schema version")?;
+    if version >= CELSIUS_SCHEMA_VERSION {
+        return Ok(());
+    }
+    
+    let converted = sqlx::query(
+        r#"
+        UPDATE readings SET
+            temperature = (temperature - 32) * 5.0 / 9.0,
+            ambient_temp = (ambient_temp - 32) * 5.0 / 9.0
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to convert readings to Celsius")?
+    .rows_affected();
+    
+    sqlx::query(
+        r#"
+        UPDATE predictions SET
+            set_point = (set_point - 32) * 5.0 / 9.0,
+            estimated_core = (estimated_core - 32) * 5.0 / 9.0
+        "#
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to convert predictions to Celsius")?;
+    
+    sqlx::query("UPDATE cook_sessions SET target_temp = (target_temp - 32) * 5.0 / 9.0")
+        .execute(&mut *conn)
+        .await
+        .context("Failed to convert cook targets to Celsius")?;
+    
+    if converted > 0 {
+        info!("Converted {} stored readings from Fahrenheit to Celsius", converted);
+    }
+    Ok(())
+}
+
+/// Add a column to a table created by an older schema
+async fn add_column_if_missing(conn: &mut SqliteConnection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?"
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_one(&mut *conn)
+    .await
+    .context("Failed to inspect table columns")?;
+    
+    if exists == 0 {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(&mut *conn)
+            .await
+            .with_context(|| format!("Failed to add {}.{} column", table, column))?;
+        info!("Added {} column to {} table", column, table);
+    }
+    
+    Ok(())
+}
+
+/// Apply every migration the database has not seen, each in its own transaction
+///
+/// Safe to run on every startup: applied versions are recorded in
+/// `schema_version` and skipped. A failing migration is rolled back and
+/// the error names its version.
+pub(crate) async fn run_migrations(pool: &SqlitePool, migrations: &[Migration]) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at DATETIME NOT NULL
+        )
+        "#
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create schema_version table")?;
+    
+    let current = schema_version(pool).await?;
+    if current > migrations.len() as u32 {
+        warn!("Database schema version {} is newer than this build knows ({})", current, migrations.len());
+    }
+    
+    for (index, migration) in migrations.iter().enumerate() {
+        let version = index as u32 + 1;
+        if version <= current {
+            continue;
+        }
+        
+        let mut tx = pool.begin().await.context("Failed to start migration")?;
+        (migration.apply)(&mut tx)
+            .await
+            .with_context(|| format!("Database migration {} ({}) failed", version, migration.description))?;
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)")
+            .bind(version)
+            .bind(migration.description)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to record database migration {}", version))?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Failed to commit database migration {}", version))?;
+        
+        info!("Applied database migration {}: {}", version, migration.description);
+    }
+    
+    Ok(())
+}
+
+/// Newest migration applied to a database
+pub(crate) async fn schema_version(pool: &SqlitePool) -> Result<u32> {
+    let version = sqlx::query_scalar::<_, i64>("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(pool)
+        .await
+        .context("Failed to read schema version")?;
+    
+    Ok(version as u32)
+}
@@ -0,0 +1,367 @@
+// src/oneshot.rs
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::protocol::ProtocolRegistry;
+use crate::temperature::{Temperature, TemperatureUnit};
+
+/// Exit status when some requested device sent nothing before the timeout
+pub const EXIT_TIMEOUT: i32 = 2;
+
+/// Exit status when no usable Bluetooth adapter was found
+pub const EXIT_NO_ADAPTER: i32 = 3;
+
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const READ_USAGE: &str =
+    "Usage: bbq-monitor read [--device <addr>]... [--timeout <secs>] [--json|--plain] [--passive] [--store]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Plain,
+    Json,
+}
+
+/// Options of `bbq-monitor read`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadArgs {
+    /// Devices to wait for; empty = the first device that reports
+    pub devices: Vec<String>,
+    pub timeout: Duration,
+    pub format: OutputFormat,
+    /// Decode advertisements instead of connecting
+    pub passive: bool,
+    /// Also write the readings to the database
+    pub store: bool,
+}
+
+impl ReadArgs {
+    /// Parse the arguments following `read`
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut parsed = Self {
+            devices: Vec::new(),
+            timeout: DEFAULT_READ_TIMEOUT,
+            format: OutputFormat::Plain,
+            passive: false,
+            store: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--device" => {
+                    let value = args.next().context("--device needs an address")?;
+                    parsed.devices.extend(value.split(',').map(str::trim).filter(|a| !a.is_empty()).map(String::from));
+                }
+                "--timeout" => {
+                    let value = args.next().context("--timeout needs a number of seconds")?;
+                    let secs: u64 = value.parse().with_context(|| format!("Invalid --timeout '{}'", value))?;
+                    parsed.timeout = Duration::from_secs(secs);
+                }
+                "--json" => parsed.format = OutputFormat::Json,
+                "--plain" => parsed.format = OutputFormat::Plain,
+                "--passive" => parsed.passive = true,
+                "--store" => parsed.store = true,
+                other => bail!("Unknown option '{}'\n{}", other, READ_USAGE),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Raw data from the radio, before any protocol decoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawFrame {
+    Advertisement {
+        address: String,
+        name: String,
+        manufacturer_data: HashMap<u16, Vec<u8>>,
+    },
+    Notification {
+        address: String,
+        name: String,
+        characteristic: Uuid,
+        value: Vec<u8>,
+    },
+}
+
+/// Where a one-shot read gets its frames; the adapter in the binary, a script in tests
+pub trait BleCentral {
+    /// Next frame, or `None` once nothing more can arrive
+    fn next_frame(&mut self) -> impl Future<Output = Option<RawFrame>> + Send;
+}
+
+/// Central fed by a task that owns the real adapter
+pub struct ChannelCentral(mpsc::Receiver<RawFrame>);
+
+impl ChannelCentral {
+    pub fn new(frames: mpsc::Receiver<RawFrame>) -> Self {
+        Self(frames)
+    }
+}
+
+impl BleCentral for ChannelCentral {
+    async fn next_frame(&mut self) -> Option<RawFrame> {
+        self.0.recv().await
+    }
+}
+
+/// One complete reading of one device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceReading {
+    pub device_address: String,
+    pub device_name: String,
+    pub timestamp: DateTime<Utc>,
+    /// Per sensor index; None for a sensor without a value
+    pub temperatures: Vec<Option<Temperature>>,
+}
+
+/// Decode a frame with the registered parsers; partial or unknown frames give nothing
+pub fn decode_frame(frame: &RawFrame, timestamp: DateTime<Utc>) -> Option<DeviceReading> {
+    let (address, name, parsed) = match frame {
+        RawFrame::Advertisement { address, name, manufacturer_data } => {
+            (address, name, ProtocolRegistry::parse_advertisement(manufacturer_data)?)
+        }
+        RawFrame::Notification { address, name, characteristic, value } => {
+            (address, name, ProtocolRegistry::parse_notification(*characteristic, value)?)
+        }
+    };
+    let temperatures = parsed.ok()?;
+    temperatures.iter().any(Option::is_some).then(|| DeviceReading {
+        device_address: address.clone(),
+        device_name: name.clone(),
+        timestamp,
+        temperatures,
+    })
+}
+
+/// What a one-shot read collected before finishing or timing out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOutcome {
+    pub readings: Vec<DeviceReading>,
+    /// Requested devices that sent nothing in time
+    pub missing: Vec<String>,
+}
+
+impl ReadOutcome {
+    pub fn exit_code(&self) -> i32 {
+        if self.missing.is_empty() && !self.readings.is_empty() {
+            0
+        } else {
+            EXIT_TIMEOUT
+        }
+    }
+}
+
+/// Wait for one reading from each of `wanted` (any one device when empty)
+pub async fn collect_readings<C: BleCentral>(central: &mut C, wanted: &[String], timeout: Duration) -> ReadOutcome {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut readings: Vec<DeviceReading> = Vec::new();
+    let is_wanted = |address: &str| wanted.is_empty() || wanted.iter().any(|w| w.eq_ignore_ascii_case(address));
+    let done = |readings: &[DeviceReading]| {
+        if wanted.is_empty() {
+            !readings.is_empty()
+        } else {
+            readings.len() == wanted.len()
+        }
+    };
+
+    while !done(&readings) {
+        let Ok(Some(frame)) = tokio::time::timeout_at(deadline, central.next_frame()).await else {
+            break;
+        };
+        let Some(reading) = decode_frame(&frame, Utc::now()) else {
+            continue;
+        };
+        let seen = readings.iter().any(|r| r.device_address.eq_ignore_ascii_case(&reading.device_address));
+        if is_wanted(&reading.device_address) && !seen {
+            readings.push(reading);
+        }
+    }
+
+    // Report in the order the devices were asked for
+    if !wanted.is_empty() {
+        readings.sort_by_key(|r| wanted.iter().position(|w| w.eq_ignore_ascii_case(&r.device_address)));
+    }
+    let missing = if wanted.is_empty() && readings.is_empty() {
+        vec!["any device".to_string()]
+    } else {
+        wanted
+            .iter()
+            .filter(|w| !readings.iter().any(|r| r.device_address.eq_ignore_ascii_case(w)))
+            .cloned()
+            .collect()
+    };
+    ReadOutcome { readings, missing }
+}
+
+/// One tab-separated line per device: address, name, unit, then each sensor ("-" when missing)
+pub fn format_plain(readings: &[DeviceReading], unit: TemperatureUnit) -> String {
+    readings
+        .iter()
+        .map(|reading| {
+            let mut fields = vec![
+                reading.device_address.clone(),
+                reading.device_name.clone(),
+                unit.symbol().trim_start_matches('°').to_string(),
+            ];
+            fields.extend(
+                reading
+                    .temperatures
+                    .iter()
+                    .map(|t| t.map_or_else(|| "-".to_string(), |t| format!("{:.1}", t.in_unit(unit)))),
+            );
+            fields.join("\t") + "\n"
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct JsonReading<'a> {
+    device_address: &'a str,
+    device_name: &'a str,
+    timestamp: DateTime<Utc>,
+    unit: TemperatureUnit,
+    temperatures: Vec<Option<f32>>,
+}
+
+/// A JSON array with one object per device
+pub fn format_json(readings: &[DeviceReading], unit: TemperatureUnit) -> String {
+    let readings: Vec<JsonReading> = readings
+        .iter()
+        .map(|reading| JsonReading {
+            device_address: &reading.device_address,
+            device_name: &reading.device_name,
+            timestamp: reading.timestamp,
+            unit,
+            // Rounded like the plain output, so 72.0 does not print as 71.99999
+            temperatures: reading.temperatures.iter().map(|t| t.map(|t| t.in_unit(unit))).collect(),
+        })
+        .collect();
+    serde_json::to_string(&readings).unwrap_or_else(|_| "[]".to_string()) + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{COMBUSTION_PRODUCT_PROBE, COMBUSTION_VENDOR_ID, MEATSTICK_CHAR, THERMOPRO_NOTIFY_CHAR};
+    use std::collections::VecDeque;
+
+    /// Plays back scripted frames, each after a delay, then stays silent
+    struct FakeCentral(VecDeque<(Duration, RawFrame)>);
+
+    impl BleCentral for FakeCentral {
+        async fn next_frame(&mut self) -> Option<RawFrame> {
+            match self.0.pop_front() {
+                Some((delay, frame)) => {
+                    tokio::time::sleep(delay).await;
+                    Some(frame)
+                }
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    fn thermopro(address: &str) -> RawFrame {
+        RawFrame::Notification {
+            address: address.to_string(),
+            name: "TP25".to_string(),
+            characteristic: THERMOPRO_NOTIFY_CHAR,
+            // 22.2°C, 241.0°C, unplugged, unplugged
+            value: vec![0xDE, 0x00, 0x6A, 0x09, 0xFF, 0xFF, 0xFF, 0xFF],
+        }
+    }
+
+    fn combustion_advertisement(address: &str) -> RawFrame {
+        // Product type, serial, then every sensor at raw 844 (22.2°C)
+        let mut data = vec![COMBUSTION_PRODUCT_PROBE, 0x78, 0x56, 0x34, 0x12];
+        let bits = (0..8).fold(0u128, |acc, i| acc | 844u128 << (i * 13));
+        data.extend_from_slice(&bits.to_le_bytes()[..13]);
+        data.extend_from_slice(&[0x00, 0x00]);
+        RawFrame::Advertisement {
+            address: address.to_string(),
+            name: "".to_string(),
+            manufacturer_data: HashMap::from([(COMBUSTION_VENDOR_ID, data)]),
+        }
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_read_args() {
+        let parsed = ReadArgs::parse(&args(&["--device", "AA,BB", "--device", "CC", "--timeout", "5", "--json", "--store"])).unwrap();
+        assert_eq!(parsed.devices, ["AA", "BB", "CC"]);
+        assert_eq!(parsed.timeout, Duration::from_secs(5));
+        assert_eq!(parsed.format, OutputFormat::Json);
+        assert!(parsed.store && !parsed.passive);
+
+        let defaults = ReadArgs::parse(&[]).unwrap();
+        assert_eq!(defaults.timeout, DEFAULT_READ_TIMEOUT);
+        assert_eq!(defaults.format, OutputFormat::Plain);
+        assert!(ReadArgs::parse(&args(&["--timeout", "soon"])).is_err());
+        assert!(ReadArgs::parse(&args(&["--verbose"])).unwrap_err().to_string().contains("Usage"));
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_every_requested_device() {
+        let partial = RawFrame::Notification {
+            address: "AA".to_string(),
+            name: "cA00probe".to_string(),
+            characteristic: MEATSTICK_CHAR,
+            value: vec![0x4C, 0x03],
+        };
+        let mut central = FakeCentral(VecDeque::from([
+            (Duration::ZERO, partial),
+            (Duration::ZERO, thermopro("CC")),
+            (Duration::from_millis(5), combustion_advertisement("AA")),
+            (Duration::ZERO, thermopro("BB")),
+        ]));
+        let wanted = args(&["BB", "aa"]);
+        let outcome = collect_readings(&mut central, &wanted, Duration::from_secs(5)).await;
+        assert_eq!(outcome.exit_code(), 0);
+        let addresses: Vec<&str> = outcome.readings.iter().map(|r| r.device_address.as_str()).collect();
+        assert_eq!(addresses, ["BB", "AA"], "in requested order, unrequested CC ignored");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_reports_missing_devices() {
+        let mut central = FakeCentral(VecDeque::from([(Duration::ZERO, thermopro("AA"))]));
+        let outcome = collect_readings(&mut central, &args(&["AA", "BB"]), Duration::from_millis(20)).await;
+        assert_eq!(outcome.exit_code(), EXIT_TIMEOUT);
+        assert_eq!(outcome.readings.len(), 1);
+        assert_eq!(outcome.missing, ["BB"]);
+
+        let mut silent = FakeCentral(VecDeque::new());
+        let outcome = collect_readings(&mut silent, &[], Duration::from_millis(20)).await;
+        assert_eq!(outcome.exit_code(), EXIT_TIMEOUT);
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let timestamp = DateTime::parse_from_rfc3339("2025-07-04T18:30:00Z").unwrap().with_timezone(&Utc);
+        let readings = vec![
+            decode_frame(&thermopro("AA:BB:CC:DD:EE:FF"), timestamp).unwrap(),
+            decode_frame(&combustion_advertisement("11:22:33:44:55:66"), timestamp).unwrap(),
+        ];
+
+        assert_eq!(
+            format_plain(&readings, TemperatureUnit::Fahrenheit),
+            "AA:BB:CC:DD:EE:FF\tTP25\tF\t72.0\t465.8\t-\t-\n\
+             11:22:33:44:55:66\t\tF\t72.0\t72.0\t72.0\t72.0\t72.0\t72.0\t72.0\t72.0\n"
+        );
+        assert_eq!(
+            format_json(&readings[..1], TemperatureUnit::Celsius),
+            "[{\"device_address\":\"AA:BB:CC:DD:EE:FF\",\"device_name\":\"TP25\",\
+             \"timestamp\":\"2025-07-04T18:30:00Z\",\"unit\":\"celsius\",\
+             \"temperatures\":[22.2,241.0,null,null]}]\n"
+        );
+        assert_eq!(format_json(&[], TemperatureUnit::Celsius), "[]\n");
+    }
+}
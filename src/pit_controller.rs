@@ -0,0 +1,347 @@
+// src/pit_controller.rs
+//! PID loop driving a fan or damper from the pit temperature
+//!
+//! Each reading of the configured pit sensor yields a 0–100% output, which is
+//! handed to an `OutputHook` (a configured command, or whatever an embedder
+//! registers) and kept for `/api/pit-controller`.
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::config::PitControllerConfig;
+use crate::temperature::{Temperature, TemperatureUnit};
+use crate::web_server::TemperatureUpdate;
+
+/// Longest gap between readings the integral and derivative terms trust, in seconds
+///
+/// After a dropout the loop restarts from the proportional term rather than
+/// integrating minutes of unseen error at once.
+const MAX_STEP_SECS: f32 = 60.0;
+
+/// PID gains, in percent output per degree of the configured unit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PidGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+/// Positional PID with its output clamped to 0–100%
+///
+/// The derivative acts on the measurement, so moving the setpoint does not
+/// kick the fan, and the integral is clamped to what the output can use so it
+/// does not wind up while the fan is already flat out (or off).
+#[derive(Debug, Clone)]
+pub struct Pid {
+    gains: PidGains,
+    integral: f32,
+    last: Option<(f32, DateTime<Utc>)>,
+}
+
+impl Pid {
+    pub fn new(gains: PidGains) -> Self {
+        Self { gains, integral: 0.0, last: None }
+    }
+
+    /// Output for a new measurement taken at `at`
+    pub fn update(&mut self, setpoint: f32, measurement: f32, at: DateTime<Utc>) -> f32 {
+        let error = setpoint - measurement;
+        let step = self.last.map(|(_, last_at)| (at - last_at).num_milliseconds() as f32 / 1000.0);
+        let mut derivative = 0.0;
+        if let (Some((last_measurement, _)), Some(dt)) = (self.last, step) {
+            if dt > 0.0 && dt <= MAX_STEP_SECS {
+                self.integral += error * dt;
+                derivative = -(measurement - last_measurement) / dt;
+            }
+        }
+        if self.gains.ki > 0.0 {
+            self.integral = self.integral.clamp(0.0, 100.0 / self.gains.ki);
+        } else {
+            self.integral = 0.0;
+        }
+        self.last = Some((measurement, at));
+
+        let output = self.gains.kp * error + self.gains.ki * self.integral + self.gains.kd * derivative;
+        output.clamp(0.0, 100.0)
+    }
+}
+
+/// Where each new output goes, behind a trait so embedders and tests can supply their own
+pub trait OutputHook {
+    fn set_output(&self, percent: f32) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Runs `pit_controller.output_command`, e.g. a GPIO or serial helper, with `{output}` filled in
+pub struct CommandHook {
+    command: Vec<String>,
+}
+
+impl CommandHook {
+    /// None when no command is configured
+    pub fn new(command: &[String]) -> Option<Self> {
+        (!command.is_empty()).then(|| Self { command: command.to_vec() })
+    }
+
+    /// Program and arguments for an output, rounded to one decimal
+    fn command_for(&self, percent: f32) -> Vec<String> {
+        let output = format!("{:.1}", percent);
+        self.command.iter().map(|part| part.replace("{output}", &output)).collect()
+    }
+}
+
+impl OutputHook for CommandHook {
+    async fn set_output(&self, percent: f32) -> Result<()> {
+        let command = self.command_for(percent);
+        let status = tokio::process::Command::new(&command[0])
+            .args(&command[1..])
+            .status()
+            .await
+            .with_context(|| format!("Failed to run {}", command[0]))?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", command[0], status);
+        }
+        Ok(())
+    }
+}
+
+/// Stands in for a hook when none is configured; the output is only reported
+impl OutputHook for () {
+    async fn set_output(&self, _percent: f32) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What `/api/pit-controller` reports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PitControllerStatus {
+    pub device_address: String,
+    pub sensor_index: usize,
+    pub setpoint: f32,
+    /// Unit of `setpoint` and `pit_temp`
+    pub unit: TemperatureUnit,
+    pub gains: PidGains,
+    /// Latest pit reading (None until the first one arrives)
+    pub pit_temp: Option<f32>,
+    /// Latest output, 0–100%
+    pub output_percent: Option<f32>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Why the hook last failed, cleared by the next success
+    pub hook_error: Option<String>,
+}
+
+/// The loop's PID state and the status it reports
+pub struct PitController {
+    pid: Mutex<Pid>,
+    status: Mutex<PitControllerStatus>,
+}
+
+impl PitController {
+    /// Controller for the configured pit sensor; `unit` is the one `setpoint` is in
+    pub fn new(config: &PitControllerConfig, unit: TemperatureUnit) -> Self {
+        let gains = PidGains { kp: config.kp, ki: config.ki, kd: config.kd };
+        Self {
+            pid: Mutex::new(Pid::new(gains)),
+            status: Mutex::new(PitControllerStatus {
+                device_address: config.device_address.clone(),
+                sensor_index: config.sensor_index,
+                setpoint: config.setpoint,
+                unit,
+                gains,
+                pit_temp: None,
+                output_percent: None,
+                updated_at: None,
+                hook_error: None,
+            }),
+        }
+    }
+
+    pub fn status(&self) -> PitControllerStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// New output for an update from the pit sensor; None for any other sensor
+    pub fn observe(&self, update: &TemperatureUpdate) -> Option<f32> {
+        let mut status = self.status.lock().unwrap();
+        if !update.device_address.eq_ignore_ascii_case(&status.device_address) || update.sensor_index != status.sensor_index {
+            return None;
+        }
+        let pit_temp = Temperature::from_unit(update.temperature, update.unit).in_unit(status.unit);
+        let output = self.pid.lock().unwrap().update(status.setpoint, pit_temp, update.timestamp);
+        status.pit_temp = Some(pit_temp);
+        status.output_percent = Some(output);
+        status.updated_at = Some(update.timestamp);
+        Some(output)
+    }
+
+    /// Pass an output to the hook, recording a failure for the status
+    pub async fn apply<H: OutputHook>(&self, hook: &H, output: f32) {
+        let result = hook.set_output(output).await;
+        if let Err(e) = &result {
+            warn!("⚠️  Pit controller output hook failed: {:#}", e);
+        }
+        self.status.lock().unwrap().hook_error = result.err().map(|e| format!("{:#}", e));
+    }
+}
+
+/// Feed pit readings from the live update channel through the controller until it closes
+pub async fn run_pit_controller<H: OutputHook>(
+    controller: Arc<PitController>,
+    hook: H,
+    mut rx: broadcast::Receiver<TemperatureUpdate>,
+) {
+    let status = controller.status();
+    info!(
+        "🌀 Pit controller holding {} sensor {} at {:.0}{}",
+        status.device_address,
+        status.sensor_index,
+        status.setpoint,
+        status.unit.symbol()
+    );
+    loop {
+        match rx.recv().await {
+            Ok(update) => {
+                if let Some(output) = controller.observe(&update) {
+                    debug!("Pit controller output {:.1}%", output);
+                    controller.apply(&hook, output).await;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Pit controller lagged, skipped {} updates", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Start the configured controller on `tx`, running `output_command` as its hook
+pub fn start(config: &PitControllerConfig, unit: TemperatureUnit, tx: &broadcast::Sender<TemperatureUpdate>) -> Arc<PitController> {
+    let controller = Arc::new(PitController::new(config, unit));
+    match CommandHook::new(&config.output_command) {
+        Some(hook) => tokio::spawn(run_pit_controller(controller.clone(), hook, tx.subscribe())),
+        None => tokio::spawn(run_pit_controller(controller.clone(), (), tx.subscribe())),
+    };
+    controller
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    /// Outputs kept in memory; fails while `offline` is set
+    #[derive(Default)]
+    struct StubHook {
+        outputs: Mutex<Vec<f32>>,
+        offline: std::sync::atomic::AtomicBool,
+    }
+
+    impl OutputHook for StubHook {
+        async fn set_output(&self, percent: f32) -> Result<()> {
+            if self.offline.load(std::sync::atomic::Ordering::SeqCst) {
+                anyhow::bail!("fan unplugged");
+            }
+            self.outputs.lock().unwrap().push(percent);
+            Ok(())
+        }
+    }
+
+    fn pit_update(sensor_index: usize, celsius: f32, timestamp: DateTime<Utc>) -> TemperatureUpdate {
+        TemperatureUpdate {
+            device_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            device_name: "pit".to_string(),
+            timestamp,
+            sensor_index,
+            temperature: celsius,
+            ambient_temp: None,
+            unit: TemperatureUnit::Celsius,
+            battery_level: None,
+            signal_strength: -60,
+            eta_seconds: None,
+            reading_mode: Default::default(),
+            virtual_sensors: None,
+        }
+    }
+
+    #[test]
+    fn test_pid_terms() {
+        let start = Utc::now();
+        let mut pid = Pid::new(PidGains { kp: 2.0, ki: 0.1, kd: 10.0 });
+        // First reading: proportional only, 2 × (225 − 215)
+        assert!((pid.update(225.0, 215.0, start) - 20.0).abs() < 1e-4);
+        // 10 s later at 217: P = 16, I = 0.1 × 8 × 10 = 8, D = 10 × −(2 / 10) = −2
+        assert!((pid.update(225.0, 217.0, start + Duration::seconds(10)) - 22.0).abs() < 1e-4);
+        // Far below the setpoint the output saturates and the integral stops at what 100% needs
+        assert_eq!(pid.update(225.0, 100.0, start + Duration::seconds(20)), 100.0);
+        assert!(pid.integral <= 100.0 / 0.1);
+        // Above the setpoint the fan is off, never negative
+        assert_eq!(pid.update(225.0, 300.0, start + Duration::seconds(30)), 0.0);
+        // A long dropout is not integrated
+        let before = pid.integral;
+        pid.update(225.0, 200.0, start + Duration::seconds(600));
+        assert_eq!(pid.integral, before);
+    }
+
+    #[test]
+    fn test_pid_settles_first_order_pit() {
+        // Pit modelled as first-order: heats towards 120 + 3.3 × output °C with a 300 s time constant
+        let mut pid = Pid::new(PidGains { kp: 4.0, ki: 0.02, kd: 5.0 });
+        let start = Utc::now();
+        let setpoint = 250.0;
+        let mut pit = 100.0;
+        let mut peak: f32 = 0.0;
+        for second in 0..3 * 3600 {
+            let output = pid.update(setpoint, pit, start + Duration::seconds(second));
+            pit += (120.0 + 3.3 * output - pit) / 300.0;
+            if second > 600 {
+                peak = peak.max(pit);
+            }
+        }
+        assert!((pit - setpoint).abs() < 0.5, "settled at {}", pit);
+        assert!(peak < setpoint + 15.0, "overshoot to {}", peak);
+        // Holding 250 needs (250 − 120) / 3.3 ≈ 39.4%, carried by the integral once the error is gone
+        let output = pid.update(setpoint, pit, start + Duration::seconds(3 * 3600));
+        assert!((output - 39.4).abs() < 1.0, "holding output {}", output);
+    }
+
+    #[tokio::test]
+    async fn test_controller_follows_pit_sensor_and_reports_hook_errors() {
+        let config = PitControllerConfig {
+            enabled: true,
+            device_address: "aa:bb:cc:dd:ee:ff".to_string(),
+            sensor_index: 1,
+            setpoint: 225.0,
+            kp: 2.0,
+            ki: 0.0,
+            kd: 0.0,
+            output_command: Vec::new(),
+        };
+        let controller = PitController::new(&config, TemperatureUnit::Fahrenheit);
+        let hook = StubHook::default();
+        let now = Utc::now();
+
+        assert_eq!(controller.observe(&pit_update(0, 60.0, now)), None, "meat sensor is ignored");
+        // 100°C is 212°F: 2 × 13
+        let output = controller.observe(&pit_update(1, 100.0, now)).unwrap();
+        assert!((output - 26.0).abs() < 0.01, "{}", output);
+        controller.apply(&hook, output).await;
+        assert_eq!(*hook.outputs.lock().unwrap(), [output]);
+
+        hook.offline.store(true, std::sync::atomic::Ordering::SeqCst);
+        controller.apply(&hook, output).await;
+        let status = controller.status();
+        assert!((status.pit_temp.unwrap() - 212.0).abs() < 0.01);
+        assert_eq!(status.output_percent, Some(output));
+        assert_eq!(status.hook_error.as_deref(), Some("fan unplugged"));
+    }
+
+    #[test]
+    fn test_command_hook_fills_in_output() {
+        assert!(CommandHook::new(&[]).is_none());
+        let hook = CommandHook::new(&["fanctl".to_string(), "--duty={output}".to_string()]).unwrap();
+        assert_eq!(hook.command_for(37.46), ["fanctl", "--duty=37.5"]);
+    }
+}
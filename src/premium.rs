@@ -1,9 +1,16 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey, SIGNATURE_LENGTH};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use tracing::{debug, info, warn};
 
+/// Where to buy Premium
+pub const PREMIUM_URL: &str = "https://bbqmonitor.example.com/premium";
+
+/// Where to renew an expiring license
+pub const RENEW_URL: &str = "https://bbqmonitor.example.com/renew";
+
 /// Premium tier levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PremiumTier {
@@ -100,22 +107,33 @@ impl License {
     }
 }
 
+/// Ed25519 public key used to verify license signatures.
+/// The matching private key is held by the license issuer and never shipped.
+const LICENSE_PUBLIC_KEY: [u8; 32] = [
+    0x51, 0x9b, 0x96, 0xbf, 0x7e, 0x5a, 0x4f, 0x12, 0x0d, 0xf5, 0x3f, 0x52, 0x0e, 0x7e, 0x37, 0x2a,
+    0x60, 0x63, 0xda, 0xbe, 0x29, 0x0f, 0xda, 0x18, 0x32, 0xd5, 0x9d, 0xd7, 0x69, 0x8f, 0x38, 0xdf,
+];
+
 /// License validator
 pub struct LicenseValidator {
-    #[allow(dead_code)]
-    public_key: Vec<u8>,
+    public_key: VerifyingKey,
 }
 
 impl LicenseValidator {
-    /// Create a new validator with the public key
+    /// Create a new validator with the embedded public key
     pub fn new() -> Self {
-        // In production, embed this at compile time or load from secure location
-        // For now, using a placeholder
         Self {
             public_key: Self::default_public_key(),
         }
     }
 
+    /// Create a validator that trusts a different Ed25519 public key
+    pub fn with_public_key(public_key: &[u8; 32]) -> Result<Self> {
+        let public_key = VerifyingKey::from_bytes(public_key)
+            .context("Invalid Ed25519 public key")?;
+        Ok(Self { public_key })
+    }
+
     /// Validate a license key
     pub fn validate(&self, license_key: &str) -> Result<License> {
         if license_key.is_empty() {
@@ -127,13 +145,16 @@ impl LicenseValidator {
         let decoded = Self::decode_license(license_key)?;
         
         // Verify signature
-        if !self.verify_signature(&decoded) {
-            warn!("Invalid license signature");
-            return Ok(License::free());
-        }
+        let payload = match self.verify_signature(&decoded) {
+            Some(payload) => payload,
+            None => {
+                warn!("Invalid license signature");
+                return Ok(License::free());
+            }
+        };
 
         // Parse license data
-        let license = Self::parse_license(&decoded)?;
+        let license = Self::parse_license(payload)?;
 
         // Check if expired
         if license.is_expired() {
@@ -166,15 +187,22 @@ impl LicenseValidator {
     }
 
     /// Verify the signature of a license
-    fn verify_signature(&self, _data: &[u8]) -> bool {
-        // In production, use RSA or Ed25519 signature verification
-        // For now, accept all non-empty keys for development
-        // 
-        // Real implementation would:
-        // 1. Split data into: signature (last 256 bytes) + payload
-        // 2. Verify signature against payload using public key
-        // 3. Return true only if signature is valid
-        true
+    /// 
+    /// Decoded keys are laid out as payload followed by a 64-byte Ed25519
+    /// signature over the payload. Returns the payload if the signature checks out.
+    fn verify_signature<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        if data.len() <= SIGNATURE_LENGTH {
+            debug!("License key too short to carry a signature");
+            return None;
+        }
+
+        let (payload, signature_bytes) = data.split_at(data.len() - SIGNATURE_LENGTH);
+        let signature = Signature::from_slice(signature_bytes).ok()?;
+
+        self.public_key
+            .verify(payload, &signature)
+            .ok()
+            .map(|_| payload)
     }
 
     /// Parse license data from decoded bytes
@@ -223,10 +251,9 @@ impl LicenseValidator {
     }
 
     /// Get the default public key
-    fn default_public_key() -> Vec<u8> {
-        // In production, this would be your actual RSA/Ed25519 public key
-        // Generated once and embedded in the binary
-        vec![0u8; 32]
+    fn default_public_key() -> VerifyingKey {
+        VerifyingKey::from_bytes(&LICENSE_PUBLIC_KEY)
+            .expect("embedded license public key is a valid Ed25519 point")
     }
 }
 
@@ -236,10 +263,23 @@ impl Default for LicenseValidator {
     }
 }
 
-/// Generate a license key (for license generation tool)
+/// Parse a base64-encoded 32-byte Ed25519 signing key (seed)
+pub fn parse_signing_key(encoded: &str) -> Result<SigningKey> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim().as_bytes())
+        .context("Signing key is not valid base64")?;
+    let seed: [u8; 32] = bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| anyhow!("Signing key must be 32 bytes, got {}", b.len()))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Generate a signed license key (for license generation tool)
 pub fn generate_license_key(
     tier: PremiumTier,
     expires_at: Option<DateTime<Utc>>,
+    signing_key: &SigningKey,
 ) -> Result<String> {
     let issued_at = Utc::now();
     
@@ -258,11 +298,13 @@ pub fn generate_license_key(
     // Format: TIER|EXPIRY|ISSUED
     let data = format!("{}|{}|{}", tier_str, expiry_str, issued_str);
     
-    // In production, sign the data with private key here
-    // For now, just encode it
+    // Append the Ed25519 signature over the payload
+    let signature = signing_key.sign(data.as_bytes());
+    let mut signed = data.into_bytes();
+    signed.extend_from_slice(&signature.to_bytes());
     
     use base64::Engine;
-    let encoded = base64::engine::general_purpose::STANDARD.encode(data.as_bytes());
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&signed);
     
     // Format as readable key with dashes
     let formatted = encoded
@@ -280,6 +322,23 @@ pub fn generate_license_key(
 mod tests {
     use super::*;
 
+    fn test_signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn test_validator() -> LicenseValidator {
+        LicenseValidator::with_public_key(&test_signing_key().verifying_key().to_bytes()).unwrap()
+    }
+
+    fn encode(bytes: &[u8]) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    fn decode(key: &str) -> Vec<u8> {
+        LicenseValidator::decode_license(key).unwrap()
+    }
+
     #[test]
     fn test_free_license() {
         let license = License::free();
@@ -290,10 +349,10 @@ mod tests {
 
     #[test]
     fn test_license_generation() {
-        let key = generate_license_key(PremiumTier::Premium, None).unwrap();
+        let key = generate_license_key(PremiumTier::Premium, None, &test_signing_key()).unwrap();
         assert!(!key.is_empty());
         
-        let validator = LicenseValidator::new();
+        let validator = test_validator();
         let license = validator.validate(&key).unwrap();
         assert_eq!(license.tier, PremiumTier::Premium);
         assert!(license.is_valid());
@@ -302,11 +361,54 @@ mod tests {
     #[test]
     fn test_expired_license() {
         let past = Utc::now() - chrono::Duration::days(30);
-        let key = generate_license_key(PremiumTier::Premium, Some(past)).unwrap();
+        let key = generate_license_key(PremiumTier::Premium, Some(past), &test_signing_key()).unwrap();
         
-        let validator = LicenseValidator::new();
+        let validator = test_validator();
         let license = validator.validate(&key).unwrap();
         // Should fall back to free tier when expired
         assert_eq!(license.tier, PremiumTier::Free);
     }
+
+    #[test]
+    fn test_tampered_payload_rejected() {
+        let key = generate_license_key(PremiumTier::Free, None, &test_signing_key()).unwrap();
+        let bytes = decode(&key);
+        let signature = &bytes[bytes.len() - SIGNATURE_LENGTH..];
+        
+        // Swap in a Premium payload while keeping the Free key's signature
+        let mut tampered = b"PREMIUM|NEVER|2026-01-20T00:00:00Z".to_vec();
+        tampered.extend_from_slice(signature);
+        
+        let license = test_validator().validate(&encode(&tampered)).unwrap();
+        assert_eq!(license.tier, PremiumTier::Free);
+        assert!(!license.features.cloud_sync);
+    }
+
+    #[test]
+    fn test_unsigned_key_rejected() {
+        let unsigned = encode(b"PREMIUM|NEVER|2026-01-20T00:00:00Z");
+        let license = test_validator().validate(&unsigned).unwrap();
+        assert_eq!(license.tier, PremiumTier::Free);
+    }
+
+    #[test]
+    fn test_wrong_signing_key_rejected() {
+        let other = SigningKey::from_bytes(&[9u8; 32]);
+        let key = generate_license_key(PremiumTier::Premium, None, &other).unwrap();
+        
+        let license = test_validator().validate(&key).unwrap();
+        assert_eq!(license.tier, PremiumTier::Free);
+        
+        // The embedded production key doesn't trust test keys either
+        let license = LicenseValidator::new().validate(&key).unwrap();
+        assert_eq!(license.tier, PremiumTier::Free);
+    }
+
+    #[test]
+    fn test_parse_signing_key() {
+        let encoded = encode(&[7u8; 32]);
+        let key = parse_signing_key(&encoded).unwrap();
+        assert_eq!(key.to_bytes(), test_signing_key().to_bytes());
+        assert!(parse_signing_key(&encode(&[1u8; 16])).is_err());
+    }
 }
@@ -1,10 +1,33 @@
 // src/protocol.rs
-use anyhow::{anyhow, Result};
+use btleplug::api::{Characteristic, Service, WriteType};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_stream::{Stream, StreamExt};
+use tracing::debug;
 use uuid::Uuid;
 
+use crate::temperature::Temperature;
+use bbq_protocols::{parse_igrill_temperature, ADVERTISEMENT_TEMPERATURE_OFFSET};
+
+pub use bbq_protocols::{
+    probe_label, FrameFormat, MeatStickProtocol, MeaterBlockProbe, MeaterProtocol, PredictionState,
+    PredictionStatus, ProbeColor, ProbeIdentity, ProtocolError, ReadingMode, ThermoProProtocol, VirtualSensors,
+    MEATER_BLOCK_SLOTS, MEATSTICK_FRAME_LEN, MEATSTICK_SENSOR_COUNT,
+    PROBE_STATUS_MODE_OFFSET, PROBE_STATUS_PREDICTION_OFFSET, PROBE_STATUS_TEMPERATURE_OFFSET,
+    PROBE_STATUS_VIRTUAL_SENSORS_OFFSET, THERMOPRO_PROBE_COUNT, WEBER_PROBE_COUNT,
+};
+
 // Combustion Inc (MeatStick) Service UUIDs
 pub const COMBUSTION_PROBE_STATUS_SERVICE: Uuid = 
     uuid::uuid!("00000100-CAAB-3792-3D44-97AE51C1407A");
+pub const COMBUSTION_PROBE_STATUS_CHAR: Uuid = 
+    uuid::uuid!("00000101-CAAB-3792-3D44-97AE51C1407A");
 pub const COMBUSTION_UART_SERVICE: Uuid = 
     uuid::uuid!("6E400001-B5A3-F393-E0A9-E50E24DCCA9E");
 pub const COMBUSTION_UART_RX_CHAR: Uuid = 
@@ -22,187 +45,845 @@ pub const MEATSTICK_CHAR: Uuid =
 // Note: MEATER uses standard BLE GATT characteristics
 pub const MEATER_SERVICE: Uuid = 
     uuid::uuid!("A75CC7FC-C956-488F-AC2A-2DBC08B63A04");
+pub const MEATER_TEMPERATURE_CHAR: Uuid = 
+    uuid::uuid!("7EDDA774-045E-4BBF-909B-45D1991A2876");
 
-/// MeatStick (Combustion Inc) protocol parser
-/// 
-/// Based on official Combustion Inc documentation:
-/// https://github.com/combustion-inc/combustion-documentation
-pub struct MeatStickProtocol;
+// ThermoPro TP25 Service UUIDs
+pub const THERMOPRO_SERVICE: Uuid = 
+    uuid::uuid!("1086FFF0-3343-4817-8BB2-B32206336CE8");
+pub const THERMOPRO_NOTIFY_CHAR: Uuid = 
+    uuid::uuid!("1086FFF2-3343-4817-8BB2-B32206336CE8");
 
-impl MeatStickProtocol {
-    /// Parse MeatStick temperature data
-    /// 
-    /// Format (13 bytes total - 104 bits):
-    /// - 8 temperature sensors (13 bits each)
-    /// - Little-endian packed bit fields
-    /// - Temperature = (raw_value * 0.05) - 20 (in Celsius)
-    /// - Range: -20°C to 369°C per sensor
-    /// 
-    /// Sensor layout (Combustion Predictive Probe):
-    /// - Sensors T1-T4: Core temperatures (internal)
-    /// - Sensors T5-T7: Mid-section temperatures
-    /// - Sensor T8: Ambient/surface temperature
-    pub fn parse_temperature_data(data: &[u8]) -> Result<Vec<f32>> {
-        if data.len() < 13 {
-            return Err(anyhow!("Insufficient data: need 13 bytes, got {}", data.len()));
-        }
-        
-        let mut temperatures = Vec::with_capacity(8);
-        
-        // Parse 8 sensors as 13-bit values packed into 13 bytes (104 bits total)
-        let mut bit_offset = 0;
-        
-        for _sensor_idx in 0..8 {
-            // Extract 13-bit value
-            let byte_offset = bit_offset / 8;
-            let bit_shift = bit_offset % 8;
-            
-            let raw_temp = if bit_shift == 0 {
-                // Aligned case: bits fit within 2 bytes
-                let low = data[byte_offset] as u16;
-                let high = (data[byte_offset + 1] as u16) & 0x1F; // 5 bits
-                (high << 8) | low
-            } else {
-                // Unaligned case: spans 2-3 bytes
-                let bits_in_first = 8 - bit_shift;
-                let low = (data[byte_offset] as u16) >> bit_shift;
-                let mid = (data[byte_offset + 1] as u16) << bits_in_first;
-                let high = if bits_in_first < 5 {
-                    ((data[byte_offset + 2] as u16) & ((1 << (13 - 8)) - 1)) << (bits_in_first + 8)
-                } else {
-                    0
-                };
-                (high | mid | low) & 0x1FFF // Mask to 13 bits
-            };
-            
-            // Convert to Celsius: Temperature = (raw_value * 0.05) - 20
-            let temp_celsius = (raw_temp as f32 * 0.05) - 20.0;
-            
-            // Convert to Fahrenheit
-            let temp_fahrenheit = temp_celsius * 9.0 / 5.0 + 32.0;
-            
-            // Sanity check: reasonable temperature range
-            if (-40.0..=1100.0).contains(&temp_fahrenheit) {
-                temperatures.push(temp_fahrenheit);
-            } else {
-                // Invalid reading - use 0 or skip
-                temperatures.push(0.0);
-            }
-            
-            bit_offset += 13;
+// Weber iGrill Service UUIDs (from reverse engineering)
+// The authentication service must be satisfied before probe values read back as anything
+pub const IGRILL_AUTH_SERVICE: Uuid = 
+    uuid::uuid!("64AC0000-4A4B-4B58-9F37-94D3C52FFDF7");
+pub const IGRILL_APP_CHALLENGE_CHAR: Uuid = 
+    uuid::uuid!("64AC0002-4A4B-4B58-9F37-94D3C52FFDF7");
+pub const IGRILL_DEVICE_CHALLENGE_CHAR: Uuid = 
+    uuid::uuid!("64AC0003-4A4B-4B58-9F37-94D3C52FFDF7");
+pub const IGRILL_DEVICE_RESPONSE_CHAR: Uuid = 
+    uuid::uuid!("64AC0004-4A4B-4B58-9F37-94D3C52FFDF7");
+pub const IGRILL_MINI_SERVICE: Uuid = 
+    uuid::uuid!("63C70000-4A82-4033-A3D3-E07B75EA41A9");
+pub const IGRILL_V2_SERVICE: Uuid = 
+    uuid::uuid!("A5C50000-F186-4BD6-97F2-7EBACBA0D708");
+pub const IGRILL_V3_SERVICE: Uuid = 
+    uuid::uuid!("6E910000-58DC-41C7-943F-518B278CEAAA");
+pub const IGRILL_PROBE1_CHAR: Uuid = 
+    uuid::uuid!("06EF0002-2E06-4B79-9E33-FCE2C42805EC");
+pub const IGRILL_PROBE2_CHAR: Uuid = 
+    uuid::uuid!("06EF0004-2E06-4B79-9E33-FCE2C42805EC");
+pub const IGRILL_PROBE3_CHAR: Uuid = 
+    uuid::uuid!("06EF0006-2E06-4B79-9E33-FCE2C42805EC");
+pub const IGRILL_PROBE4_CHAR: Uuid = 
+    uuid::uuid!("06EF0008-2E06-4B79-9E33-FCE2C42805EC");
+
+/// Probe temperature characteristics, by probe jack
+pub const IGRILL_PROBE_CHARS: [Uuid; 4] = [IGRILL_PROBE1_CHAR, IGRILL_PROBE2_CHAR, IGRILL_PROBE3_CHAR, IGRILL_PROBE4_CHAR];
+
+/// Services an iGrill's probe characteristics live under, one per model
+pub const IGRILL_TEMPERATURE_SERVICES: [Uuid; 3] = [IGRILL_MINI_SERVICE, IGRILL_V2_SERVICE, IGRILL_V3_SERVICE];
+
+// Standard GATT Battery Service, exposed by many probes whatever the brand
+pub const BATTERY_SERVICE: Uuid = 
+    uuid::uuid!("0000180F-0000-1000-8000-00805F9B34FB");
+pub const BATTERY_LEVEL_CHAR: Uuid = 
+    uuid::uuid!("00002A19-0000-1000-8000-00805F9B34FB");
+
+// Standard GATT Device Information Service
+pub const DEVICE_INFORMATION_SERVICE: Uuid = 
+    uuid::uuid!("0000180A-0000-1000-8000-00805F9B34FB");
+pub const SERIAL_NUMBER_CHAR: Uuid = 
+    uuid::uuid!("00002A25-0000-1000-8000-00805F9B34FB");
+pub const FIRMWARE_REVISION_CHAR: Uuid = 
+    uuid::uuid!("00002A26-0000-1000-8000-00805F9B34FB");
+pub const HARDWARE_REVISION_CHAR: Uuid = 
+    uuid::uuid!("00002A27-0000-1000-8000-00805F9B34FB");
+pub const MANUFACTURER_NAME_CHAR: Uuid = 
+    uuid::uuid!("00002A29-0000-1000-8000-00805F9B34FB");
+
+/// How long a battery level is reused before the characteristic is read again
+pub const BATTERY_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Bluetooth SIG company ID Combustion probes advertise their manufacturer data under
+pub const COMBUSTION_VENDOR_ID: u16 = 0x09C7;
+/// Product type byte in Combustion manufacturer data for a predictive probe
+/// (other values are MeatNet nodes: displays and repeaters)
+pub const COMBUSTION_PRODUCT_PROBE: u8 = 0x01;
+
+/// Bluetooth SIG company ID of Apption Labs, the maker of MEATER
+pub const MEATER_VENDOR_ID: u16 = 0x037B;
+
+/// A registered protocol parser and the probe brands it decodes
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolInfo {
+    pub name: &'static str,
+    pub brands: &'static [&'static str],
+}
+
+const PROTOCOLS: &[ProtocolInfo] = &[
+    ProtocolInfo {
+        name: "combustion",
+        brands: &["MeatStickV1", "MeatStickV2", "MeatStickV"],
+    },
+    ProtocolInfo {
+        name: "meater",
+        brands: &["MeaterOriginal", "MeaterPlus"],
+    },
+    ProtocolInfo {
+        name: "thermopro",
+        brands: &["ThermoProTP25"],
+    },
+    ProtocolInfo {
+        name: "weber",
+        brands: &["WeberIGrill"],
+    },
+];
+
+/// Registry of the protocol parsers compiled into this build
+pub struct ProtocolRegistry;
+
+impl ProtocolRegistry {
+    pub fn protocols() -> &'static [ProtocolInfo] {
+        PROTOCOLS
+    }
+    
+    /// Every brand some registered parser can decode
+    pub fn supported_brands() -> Vec<&'static str> {
+        PROTOCOLS.iter().flat_map(|p| p.brands.iter().copied()).collect()
+    }
+    
+    /// Characteristic a fixture's notifications were recorded from, by fixture name
+    ///
+    /// The fixtures live in `bbq-protocols/tests/fixtures`, replayed through
+    /// the `FrameFormat` of the same name.
+    pub fn fixture_characteristic(name: &str) -> Option<Uuid> {
+        match name {
+            "meatstick" => Some(MEATSTICK_CHAR),
+            "combustion" => Some(COMBUSTION_PROBE_STATUS_CHAR),
+            "meater" => Some(MEATER_TEMPERATURE_CHAR),
+            "thermopro" => Some(THERMOPRO_NOTIFY_CHAR),
+            "weber" => Some(IGRILL_PROBE1_CHAR),
+            _ => None,
         }
-        
-        if temperatures.is_empty() {
-            return Err(anyhow!("No valid temperatures parsed"));
+    }
+    
+    /// Layout of the notifications a characteristic sends, if some registered parser reads it
+    pub fn frame_format(characteristic: Uuid) -> Option<FrameFormat> {
+        match characteristic {
+            MEATSTICK_CHAR => Some(FrameFormat::MeatStick),
+            COMBUSTION_PROBE_STATUS_CHAR => Some(FrameFormat::CombustionStatus),
+            MEATER_TEMPERATURE_CHAR => Some(FrameFormat::Meater),
+            THERMOPRO_NOTIFY_CHAR => Some(FrameFormat::ThermoPro),
+            _ => WeberProtocol::probe_index(characteristic).map(FrameFormat::IGrillProbe),
         }
-        
-        Ok(temperatures)
     }
     
-    /// Get the internal (meat core) temperature
-    /// For Combustion probes, T1-T4 are core sensors
-    /// Returns the deepest valid core reading (typically T4)
-    pub fn get_internal_temp(temperatures: &[f32]) -> Option<f32> {
-        if temperatures.is_empty() {
+    /// Whether some registered parser decodes values of this characteristic
+    pub fn reads_characteristic(characteristic: Uuid) -> bool {
+        Self::frame_format(characteristic).is_some()
+    }
+    
+    /// Decode a notification by the characteristic it came from
+    /// 
+    /// Sensors keep their index, `None` where a sensor has no reading.
+    /// Returns `None` when no registered parser reads that characteristic.
+    /// iGrill probe values only mean something from a paired connection;
+    /// live reads go through `IGrillSessions` instead.
+    pub fn parse_notification(
+        characteristic: Uuid,
+        data: &[u8],
+    ) -> Option<Result<Vec<Option<Temperature>>, ProtocolError>> {
+        Some(Self::frame_format(characteristic)?.parse(data))
+    }
+    
+    /// Temperatures a Combustion probe broadcasts in its advertisement, readable without connecting
+    pub fn parse_advertisement(
+        manufacturer_data: &HashMap<u16, Vec<u8>>,
+    ) -> Option<Result<Vec<Option<Temperature>>, ProtocolError>> {
+        let data = manufacturer_data.get(&COMBUSTION_VENDOR_ID)?;
+        if data.first() != Some(&COMBUSTION_PRODUCT_PROBE) {
             return None;
         }
-        
-        // Try T4 (index 3) as the deepest core sensor
-        if temperatures.len() >= 4 && temperatures[3] > 0.0 {
-            return Some(temperatures[3]);
-        }
-        
-        // Fallback to other core sensors (T3, T2, T1)
-        for i in (0..temperatures.len().min(4)).rev() {
-            if temperatures[i] > 0.0 {
-                return Some(temperatures[i]);
-            }
+        let end = ADVERTISEMENT_TEMPERATURE_OFFSET + MEATSTICK_FRAME_LEN;
+        Some(match data.get(ADVERTISEMENT_TEMPERATURE_OFFSET..end) {
+            Some(frame) => MeatStickProtocol::parse_temperature_data(frame),
+            None => Err(ProtocolError::TooShort { needed: end, got: data.len() }),
+        })
+    }
+}
+
+/// Length of the challenges exchanged during iGrill pairing
+pub const IGRILL_CHALLENGE_LEN: usize = 16;
+
+/// Where an iGrill pairing handshake stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IGrillAuthState {
+    /// Nothing written yet
+    Idle,
+    /// App challenge written; the device challenge is next
+    ChallengeSent,
+    /// Device challenge answered; waiting for the device to accept the response
+    Responding,
+    Authenticated,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum IGrillAuthError {
+    #[error("Handshake step not valid in state {0:?}")]
+    OutOfOrder(IGrillAuthState),
+    #[error("Device challenge has {0} bytes, expected 16")]
+    InvalidChallenge(usize),
+    #[error("No characteristic {0}")]
+    CharacteristicNotFound(Uuid),
+    #[error("BLE error: {0}")]
+    Ble(String),
+}
+
+/// Weber iGrill protocol: the pairing handshake, then per-probe temperatures
+///
+/// An iGrill only reports temperatures once the app has passed its
+/// encrypted-challenge exchange over the auth service:
+/// 1. write a 16-byte app challenge to `IGRILL_APP_CHALLENGE_CHAR`
+/// 2. read the device's encrypted challenge from `IGRILL_DEVICE_CHALLENGE_CHAR`
+/// 3. write the encrypted answer to `IGRILL_DEVICE_RESPONSE_CHAR`
+///
+/// With an all-zero app challenge the device accepts its own encrypted
+/// challenge echoed back, so no device key is needed. The handshake is
+/// stateful, so a `WeberProtocol` is kept per connection.
+#[derive(Debug)]
+pub struct WeberProtocol {
+    state: IGrillAuthState,
+}
+
+impl Default for WeberProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeberProtocol {
+    pub fn new() -> Self {
+        Self { state: IGrillAuthState::Idle }
+    }
+    
+    /// Session for an iGrill without the auth service, which reports temperatures unpaired
+    pub fn without_pairing() -> Self {
+        Self { state: IGrillAuthState::Authenticated }
+    }
+    
+    pub fn state(&self) -> IGrillAuthState {
+        self.state
+    }
+    
+    pub fn is_authenticated(&self) -> bool {
+        self.state == IGrillAuthState::Authenticated
+    }
+    
+    /// Step 1: the challenge to write to the app-challenge characteristic
+    pub fn app_challenge(&mut self) -> Result<[u8; IGRILL_CHALLENGE_LEN], IGrillAuthError> {
+        self.expect(IGrillAuthState::Idle)?;
+        self.state = IGrillAuthState::ChallengeSent;
+        Ok([0; IGRILL_CHALLENGE_LEN])
+    }
+    
+    /// Step 2: the response to write back for the device challenge just read
+    pub fn answer_challenge(&mut self, device_challenge: &[u8]) -> Result<Vec<u8>, IGrillAuthError> {
+        self.expect(IGrillAuthState::ChallengeSent)?;
+        if device_challenge.len() != IGRILL_CHALLENGE_LEN {
+            self.state = IGrillAuthState::Failed;
+            return Err(IGrillAuthError::InvalidChallenge(device_challenge.len()));
         }
-        
-        None
+        self.state = IGrillAuthState::Responding;
+        Ok(device_challenge.to_vec())
     }
     
-    /// Get the ambient temperature
-    /// For Combustion probes, T8 (index 7) is the ambient sensor
-    pub fn get_ambient_temp(temperatures: &[f32]) -> Option<f32> {
-        if temperatures.len() >= 8 && temperatures[7] > 0.0 {
-            Some(temperatures[7])
-        } else if temperatures.len() >= 6 {
-            // Fallback to T6 or T7 if T8 not available
-            temperatures[temperatures.len() - 1..]
-                .iter()
-                .rev()
-                .find(|&&t| t > 0.0)
-                .copied()
+    /// Step 3: whether the device accepted the response write
+    pub fn response_written(&mut self, accepted: bool) -> Result<(), IGrillAuthError> {
+        self.expect(IGrillAuthState::Responding)?;
+        self.state = if accepted { IGrillAuthState::Authenticated } else { IGrillAuthState::Failed };
+        Ok(())
+    }
+    
+    /// Give up on the handshake, e.g. after a BLE error; a new one starts from `Idle`
+    pub fn fail(&mut self) {
+        self.state = IGrillAuthState::Failed;
+    }
+    
+    fn expect(&self, state: IGrillAuthState) -> Result<(), IGrillAuthError> {
+        if self.state == state {
+            Ok(())
         } else {
-            None
+            Err(IGrillAuthError::OutOfOrder(self.state))
         }
     }
+    
+    /// Decode a probe characteristic value; `None` until the handshake has completed,
+    /// since an unpaired iGrill reports nothing meaningful
+    pub fn parse_notification(
+        &self,
+        characteristic: Uuid,
+        data: &[u8],
+    ) -> Option<Result<Vec<Option<Temperature>>, ProtocolError>> {
+        if !self.is_authenticated() {
+            return None;
+        }
+        Self::parse_probe_notification(characteristic, data)
+    }
+    
+    /// Decode a probe characteristic value into one entry per jack, only that probe filled in
+    pub fn parse_probe_notification(
+        characteristic: Uuid,
+        data: &[u8],
+    ) -> Option<Result<Vec<Option<Temperature>>, ProtocolError>> {
+        let probe = Self::probe_index(characteristic)?;
+        Some(FrameFormat::IGrillProbe(probe).parse(data))
+    }
+    
+    /// Jack a probe temperature characteristic belongs to
+    pub fn probe_index(characteristic: Uuid) -> Option<usize> {
+        IGRILL_PROBE_CHARS.iter().position(|c| *c == characteristic)
+    }
+    
+    /// Parse one probe temperature value (see `parse_igrill_temperature`)
+    pub fn parse_probe_temperature(probe: usize, data: &[u8]) -> Result<Option<Temperature>, ProtocolError> {
+        parse_igrill_temperature(probe, data)
+    }
+}
+
+/// Run the iGrill pairing handshake on a connected peripheral, returning the paired session
+///
+/// Succeeds right away on peripherals without the iGrill auth service.
+pub async fn authenticate_igrill<P: btleplug::api::Peripheral>(peripheral: &P) -> Result<WeberProtocol, IGrillAuthError> {
+    let characteristics = peripheral.characteristics();
+    if !characteristics.iter().any(|c| c.service_uuid == IGRILL_AUTH_SERVICE) {
+        return Ok(WeberProtocol::without_pairing());
+    }
+    let find = |uuid: Uuid| {
+        characteristics
+            .iter()
+            .find(|c| c.service_uuid == IGRILL_AUTH_SERVICE && c.uuid == uuid)
+            .ok_or(IGrillAuthError::CharacteristicNotFound(uuid))
+    };
+    let ble = |e: btleplug::Error| IGrillAuthError::Ble(e.to_string());
+    
+    let mut protocol = WeberProtocol::new();
+    let result = async {
+        let challenge = protocol.app_challenge()?;
+        peripheral
+            .write(find(IGRILL_APP_CHALLENGE_CHAR)?, &challenge, WriteType::WithResponse)
+            .await
+            .map_err(ble)?;
+        let device_challenge = peripheral.read(find(IGRILL_DEVICE_CHALLENGE_CHAR)?).await.map_err(ble)?;
+        let response = protocol.answer_challenge(&device_challenge)?;
+        let written = peripheral
+            .write(find(IGRILL_DEVICE_RESPONSE_CHAR)?, &response, WriteType::WithResponse)
+            .await;
+        protocol.response_written(written.is_ok())?;
+        written.map_err(ble)
+    }
+    .await;
+    debug!("iGrill handshake ended in {:?}", protocol.state());
+    result.map(|()| protocol)
 }
 
-/// MEATER protocol parser
+/// Pairing session of each connected iGrill, by address
+///
+/// Set when notifications are set up (again after a reconnect) and consulted
+/// on every read, so probe values only count from a connection that paired.
+#[derive(Debug, Default)]
+pub struct IGrillSessions {
+    sessions: Mutex<HashMap<String, WeberProtocol>>,
+}
+
+impl IGrillSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Keep the outcome of `authenticate_igrill` for `address`
+    pub fn set(&self, address: &str, session: WeberProtocol) {
+        self.sessions.lock().unwrap().insert(address.to_string(), session);
+    }
+    
+    /// Drop the session of a device whose pairing failed
+    pub fn forget(&self, address: &str) {
+        self.sessions.lock().unwrap().remove(address);
+    }
+    
+    /// Decode a probe value through the device's session; `None` unless it paired
+    pub fn parse_notification(
+        &self,
+        address: &str,
+        characteristic: Uuid,
+        data: &[u8],
+    ) -> Option<Result<Vec<Option<Temperature>>, ProtocolError>> {
+        self.sessions.lock().unwrap().get(address)?.parse_notification(characteristic, data)
+    }
+}
+
+/// Suppresses duplicate probe notifications before they reach the database
 /// 
-/// Based on reverse engineering by Nathan Faber:
-/// https://github.com/nathanfaber/meaterble
-pub struct MeaterProtocol;
+/// Combustion probes resend the same status packet at 1Hz even when nothing
+/// changed. The deduper remembers the last stored packet per device (by
+/// sequence number when the format carries one, otherwise by payload hash)
+/// and enforces a minimum interval between stored readings.
+pub struct NotificationDeduper {
+    min_interval: Duration,
+    last_stored: HashMap<String, (u64, Instant)>,
+    instant_read: Option<InstantReadFilter>,
+}
 
-impl MeaterProtocol {
-    /// Parse MEATER temperature data
-    /// 
-    /// Format (8 bytes total):
-    /// - Bytes 0-1: Tip temperature (little-endian u16)
-    /// - Bytes 2-3: RA ambient reading (little-endian u16)
-    /// - Bytes 4-5: OA ambient reading (little-endian u16)
-    /// - Bytes 6-7: Unknown/reserved
-    /// 
-    /// Temperature conversion:
-    /// - Tip: direct value / 10.0 = Celsius
-    /// - Ambient: calculated from RA and OA using formula
-    pub fn parse_temperature_data(data: &[u8]) -> Result<Vec<f32>> {
-        if data.len() < 8 {
-            return Err(anyhow!("Insufficient data for MEATER format: need 8 bytes, got {}", data.len()));
+impl NotificationDeduper {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_stored: HashMap::new(),
+            instant_read: Some(InstantReadFilter::new(5, 1.0)),
         }
+    }
+    
+    /// Filter instant-read samples with `filter`, or store none of them with None
+    pub fn with_instant_read(mut self, filter: Option<InstantReadFilter>) -> Self {
+        self.instant_read = filter;
+        self
+    }
+    
+    /// Returns true if a sample from a thermometer in instant-read mode should be persisted
+    pub fn should_store_instant_read(&mut self, device_address: &str, temperature: Temperature) -> bool {
+        self.instant_read
+            .as_mut()
+            .is_some_and(|filter| filter.should_store(device_address, temperature))
+    }
+    
+    /// Returns true if a temperature frame received in `mode` should be persisted
+    ///
+    /// Instant-read frames are judged by their internal temperature, all others by payload.
+    pub fn should_store_frame(
+        &mut self,
+        device_address: &str,
+        payload: &[u8],
+        mode: ReadingMode,
+        internal_temp: Option<Temperature>,
+    ) -> bool {
+        match mode {
+            ReadingMode::InstantRead => internal_temp
+                .is_some_and(|temp| self.should_store_instant_read(device_address, temp)),
+            _ => self.should_store(device_address, payload, None),
+        }
+    }
+    
+    /// Returns true if this notification should be persisted
+    pub fn should_store(&mut self, device_address: &str, payload: &[u8], sequence: Option<u32>) -> bool {
+        self.should_store_at(device_address, payload, sequence, Instant::now())
+    }
+    
+    fn should_store_at(
+        &mut self,
+        device_address: &str,
+        payload: &[u8],
+        sequence: Option<u32>,
+        now: Instant,
+    ) -> bool {
+        let key = match sequence {
+            Some(seq) => seq as u64,
+            None => {
+                let mut hasher = DefaultHasher::new();
+                payload.hash(&mut hasher);
+                hasher.finish()
+            }
+        };
         
-        let mut temperatures = Vec::new();
-        
-        // Parse tip temperature (bytes 0-1)
-        let tip_raw = u16::from_le_bytes([data[0], data[1]]);
-        let tip_celsius = tip_raw as f32 / 10.0;
-        let tip_fahrenheit = tip_celsius * 9.0 / 5.0 + 32.0;
+        if let Some(&(last_key, stored_at)) = self.last_stored.get(device_address) {
+            if last_key == key {
+                return false;
+            }
+            if now.duration_since(stored_at) < self.min_interval {
+                return false;
+            }
+        }
         
-        if (-40.0..=600.0).contains(&tip_fahrenheit) {
-            temperatures.push(tip_fahrenheit);
+        self.last_stored.insert(device_address.to_string(), (key, now));
+        true
+    }
+}
+
+/// Decides which instant-read samples are worth storing
+/// 
+/// An instant-read thermometer reports every second, even while it lies on
+/// the counter. A sample is stored once the last `stable_samples` readings
+/// agree within `tolerance` °F; nothing more is stored until the reading
+/// moves and settles again.
+pub struct InstantReadFilter {
+    stable_samples: usize,
+    tolerance: f32,
+    devices: HashMap<String, InstantReadState>,
+}
+
+#[derive(Default)]
+struct InstantReadState {
+    recent: VecDeque<f32>,
+    stored: bool,
+}
+
+impl InstantReadFilter {
+    pub fn new(stable_samples: usize, tolerance: f32) -> Self {
+        Self {
+            stable_samples: stable_samples.max(1),
+            tolerance,
+            devices: HashMap::new(),
+        }
+    }
+    
+    /// Returns true if this instant-read sample should be persisted
+    pub fn should_store(&mut self, device_address: &str, temperature: Temperature) -> bool {
+        let state = self.devices.entry(device_address.to_string()).or_default();
+        state.recent.push_back(temperature.fahrenheit());
+        if state.recent.len() > self.stable_samples {
+            state.recent.pop_front();
         }
         
-        // Parse ambient temperature components
-        let ra_raw = u16::from_le_bytes([data[2], data[3]]);
-        let oa_raw = u16::from_le_bytes([data[4], data[5]]);
+        let min = state.recent.iter().copied().fold(f32::MAX, f32::min);
+        let max = state.recent.iter().copied().fold(f32::MIN, f32::max);
+        let stable = state.recent.len() == self.stable_samples && max - min <= self.tolerance;
         
-        // Calculate ambient using MEATER formula (from Nathan Faber's work)
-        // ambient = tip + max(0, ((ra - min(48, oa)) * 16 * 589) / 1487)
-        let ambient_raw = tip_raw as i32 + 
-            ((((ra_raw as i32 - oa_raw.min(48) as i32) * 16 * 589) / 1487).max(0));
+        if !stable {
+            state.stored = false;
+            return false;
+        }
+        !std::mem::replace(&mut state.stored, true)
+    }
+}
+
+/// How long a UART command waits for its response
+pub const UART_RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest partial UART line kept while waiting for its line ending
+const UART_MAX_LINE_LEN: usize = 256;
+
+/// Errors from a UART command exchange
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum UartError {
+    #[error("Invalid UART command '{0}'")]
+    InvalidCommand(String),
+    /// Subscribing, writing or reading notifications failed
+    #[error("UART transport error: {0}")]
+    Transport(String),
+    #[error("No response to '{command}' within {timeout:?}")]
+    Timeout { command: String, timeout: Duration },
+    #[error("UART notifications ended")]
+    Closed,
+}
+
+/// A response line from the probe UART, e.g. `version: fw=1.4.2 hw=3`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UartResponse {
+    /// Command the line answers
+    pub command: String,
+    /// Everything after `command:`
+    pub text: String,
+    /// `key=value` pairs found in `text`
+    pub fields: HashMap<String, String>,
+}
+
+impl UartResponse {
+    /// Parse a `command: key=value ...` line (None for anything else)
+    pub fn parse(line: &str) -> Option<Self> {
+        let (command, text) = line.trim().split_once(':')?;
+        let command = command.trim();
+        if command.is_empty() || !command.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+        let text = text.trim();
+        let fields = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Some(Self { command: command.to_string(), text: text.to_string(), fields })
+    }
+    
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+}
+
+/// Frame a UART command: the command word followed by CRLF
+pub fn frame_uart_command(command: &str) -> Result<Vec<u8>, UartError> {
+    if command.is_empty() || !command.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(UartError::InvalidCommand(command.to_string()));
+    }
+    Ok(format!("{}\r\n", command).into_bytes())
+}
+
+/// Write half of a UART link
+pub trait UartWriter {
+    fn write(&self, frame: &[u8]) -> impl Future<Output = Result<(), UartError>> + Send;
+}
+
+/// Writes UART frames to a peripheral's Nordic UART RX characteristic
+pub struct PeripheralUart<P> {
+    peripheral: P,
+    rx: Characteristic,
+}
+
+impl<P: btleplug::api::Peripheral> UartWriter for PeripheralUart<P> {
+    async fn write(&self, frame: &[u8]) -> Result<(), UartError> {
+        self.peripheral
+            .write(&self.rx, frame, WriteType::WithoutResponse)
+            .await
+            .map_err(|e| UartError::Transport(e.to_string()))
+    }
+}
+
+/// Sends commands over the probe UART and waits for the matching response
+///
+/// Responses are text lines (`status: ...`) that may arrive split across
+/// notifications. Lines for other commands and binary notifications are
+/// counted as unsolicited and skipped, and every command gives up after the
+/// timeout, so a silent probe cannot stall the caller.
+pub struct UartSession<W, S> {
+    writer: W,
+    notifications: S,
+    timeout: Duration,
+    buffer: Vec<u8>,
+    unsolicited: usize,
+}
+
+impl<P: btleplug::api::Peripheral> UartSession<PeripheralUart<P>, Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>> {
+    /// Subscribe to a connected peripheral's UART (None if it has no Nordic UART service)
+    pub async fn open(peripheral: &P) -> Result<Option<Self>, UartError> {
+        let characteristics = peripheral.characteristics();
+        let find = |uuid: Uuid| {
+            characteristics
+                .iter()
+                .find(|c| c.service_uuid == COMBUSTION_UART_SERVICE && c.uuid == uuid)
+                .cloned()
+        };
+        let (Some(rx), Some(tx)) = (find(COMBUSTION_UART_RX_CHAR), find(COMBUSTION_UART_TX_CHAR)) else {
+            return Ok(None);
+        };
         
-        let ambient_celsius = ambient_raw as f32 / 10.0;
-        let ambient_fahrenheit = ambient_celsius * 9.0 / 5.0 + 32.0;
+        peripheral.subscribe(&tx).await.map_err(|e| UartError::Transport(e.to_string()))?;
+        let notifications = peripheral
+            .notifications()
+            .await
+            .map_err(|e| UartError::Transport(e.to_string()))?
+            .filter(|n| n.uuid == COMBUSTION_UART_TX_CHAR)
+            .map(|n| n.value);
         
-        if (-40.0..=600.0).contains(&ambient_fahrenheit) {
-            temperatures.push(ambient_fahrenheit);
+        let writer = PeripheralUart { peripheral: peripheral.clone(), rx };
+        Ok(Some(Self::new(writer, Box::pin(notifications))))
+    }
+}
+
+impl<W: UartWriter, S: Stream<Item = Vec<u8>> + Unpin> UartSession<W, S> {
+    pub fn new(writer: W, notifications: S) -> Self {
+        Self {
+            writer,
+            notifications,
+            timeout: UART_RESPONSE_TIMEOUT,
+            buffer: Vec::new(),
+            unsolicited: 0,
         }
+    }
+    
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Lines and notifications skipped while waiting for responses
+    pub fn unsolicited(&self) -> usize {
+        self.unsolicited
+    }
+    
+    /// Write a frame without waiting for an answer
+    pub async fn send_raw(&mut self, frame: &[u8]) -> Result<(), UartError> {
+        self.writer.write(frame).await
+    }
+    
+    /// Send `command` and wait for the line that answers it
+    pub async fn command(&mut self, command: &str) -> Result<UartResponse, UartError> {
+        let frame = frame_uart_command(command)?;
+        self.writer.write(&frame).await?;
         
-        Ok(temperatures)
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            while let Some(line) = self.next_line() {
+                match UartResponse::parse(&line) {
+                    Some(response) if response.command == command => return Ok(response),
+                    _ => {
+                        debug!("Skipping unsolicited UART line {:?}", line);
+                        self.unsolicited += 1;
+                    }
+                }
+            }
+            
+            match tokio::time::timeout_at(deadline, self.notifications.next()).await {
+                Ok(Some(data)) => self.accept(&data),
+                Ok(None) => return Err(UartError::Closed),
+                Err(_) => {
+                    return Err(UartError::Timeout { command: command.to_string(), timeout: self.timeout });
+                }
+            }
+        }
     }
     
-    /// Get internal/tip temperature (first sensor)
-    pub fn get_internal_temp(temperatures: &[f32]) -> Option<f32> {
-        temperatures.first().copied()
+    /// Buffer a text notification; binary ones (temperature frames) are skipped
+    fn accept(&mut self, data: &[u8]) {
+        let is_text = data.iter().all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+        if !is_text {
+            self.unsolicited += 1;
+            return;
+        }
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() > UART_MAX_LINE_LEN && !self.buffer.contains(&b'\n') {
+            debug!("Dropping {} bytes of unterminated UART output", self.buffer.len());
+            self.buffer.clear();
+            self.unsolicited += 1;
+        }
     }
     
-    /// Get ambient temperature (second sensor)
-    pub fn get_ambient_temp(temperatures: &[f32]) -> Option<f32> {
-        if temperatures.len() >= 2 {
-            Some(temperatures[1])
-        } else {
+    /// Take the next complete line from the buffer
+    fn next_line(&mut self) -> Option<String> {
+        loop {
+            let end = self.buffer.iter().position(|&b| b == b'\n')?;
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                return Some(line);
+            }
+        }
+    }
+}
+
+/// Battery Level (0x2A19) value: one byte, 0-100 percent
+pub fn parse_battery_level(data: &[u8]) -> Option<u8> {
+    data.first().copied().filter(|level| *level <= 100)
+}
+
+/// The Battery Level characteristic among a peripheral's services, if it has a Battery Service
+pub fn find_battery_level_characteristic(services: &BTreeSet<Service>) -> Option<&Characteristic> {
+    services
+        .iter()
+        .filter(|service| service.uuid == BATTERY_SERVICE)
+        .flat_map(|service| &service.characteristics)
+        .find(|characteristic| characteristic.uuid == BATTERY_LEVEL_CHAR)
+}
+
+/// What a probe reports about itself in the Device Information Service
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub serial_number: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub hardware_revision: Option<String>,
+    pub manufacturer: Option<String>,
+}
+
+impl DeviceInfo {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    /// Fill in the field `characteristic` holds from its raw value; other characteristics are ignored
+    pub fn set(&mut self, characteristic: Uuid, data: &[u8]) {
+        let field = match characteristic {
+            SERIAL_NUMBER_CHAR => &mut self.serial_number,
+            FIRMWARE_REVISION_CHAR => &mut self.firmware_revision,
+            HARDWARE_REVISION_CHAR => &mut self.hardware_revision,
+            MANUFACTURER_NAME_CHAR => &mut self.manufacturer,
+            _ => return,
+        };
+        *field = parse_dis_string(data);
+    }
+}
+
+/// Device Information string: UTF-8, often padded with NULs or spaces; None when blank
+pub fn parse_dis_string(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Read every Device Information characteristic a connected peripheral exposes
+pub async fn read_device_info<P: btleplug::api::Peripheral>(peripheral: &P) -> DeviceInfo {
+    let mut info = DeviceInfo::default();
+    let services = peripheral.services();
+    let characteristics = services
+        .iter()
+        .filter(|service| service.uuid == DEVICE_INFORMATION_SERVICE)
+        .flat_map(|service| &service.characteristics);
+    for characteristic in characteristics {
+        match peripheral.read(characteristic).await {
+            Ok(data) => info.set(characteristic.uuid, &data),
+            Err(e) => debug!("Device information read of {} failed: {}", characteristic.uuid, e),
+        }
+    }
+    info
+}
+
+/// Battery levels read from the standard Battery Service, per device
+///
+/// A read costs a round trip, so each level is reused until the refresh
+/// interval passes. Devices without the service are remembered the same way
+/// and only looked at again once the interval is up.
+pub struct BatteryCache {
+    refresh: Duration,
+    levels: Mutex<HashMap<String, CachedBattery>>,
+}
+
+struct CachedBattery {
+    level: Option<u8>,
+    read_at: Instant,
+}
+
+impl BatteryCache {
+    pub fn new(refresh: Duration) -> Self {
+        Self { refresh, levels: Mutex::new(HashMap::new()) }
+    }
+    
+    /// Last level read from a device
+    pub fn level(&self, device_address: &str) -> Option<u8> {
+        self.levels.lock().unwrap().get(device_address).and_then(|cached| cached.level)
+    }
+    
+    fn is_due_at(&self, device_address: &str, now: Instant) -> bool {
+        self.levels
+            .lock()
+            .unwrap()
+            .get(device_address)
+            .is_none_or(|cached| now.saturating_duration_since(cached.read_at) >= self.refresh)
+    }
+    
+    /// Remember a read; a failed one keeps the previous level until the next try
+    fn record_at(&self, device_address: &str, level: Option<u8>, now: Instant) {
+        let mut levels = self.levels.lock().unwrap();
+        let previous = levels.get(device_address).and_then(|cached| cached.level);
+        levels.insert(device_address.to_string(), CachedBattery { level: level.or(previous), read_at: now });
+    }
+    
+    /// Read the level again if the cached one is stale, returning the current level
+    pub async fn refresh<P: btleplug::api::Peripheral>(&self, peripheral: &P, device_address: &str) -> Option<u8> {
+        let now = Instant::now();
+        if self.is_due_at(device_address, now) {
+            let level = read_battery_level(peripheral).await;
+            self.record_at(device_address, level, now);
+        }
+        self.level(device_address)
+    }
+}
+
+/// Read a connected peripheral's battery percentage (None without a Battery Service)
+pub async fn read_battery_level<P: btleplug::api::Peripheral>(peripheral: &P) -> Option<u8> {
+    let services = peripheral.services();
+    let characteristic = find_battery_level_characteristic(&services)?;
+    match peripheral.read(characteristic).await {
+        Ok(data) => parse_battery_level(&data),
+        Err(e) => {
+            debug!("Battery level read failed: {}", e);
             None
         }
     }
@@ -213,41 +894,283 @@ mod tests {
     use super::*;
     
     #[test]
-    fn test_meatstick_parsing() {
-        // Simulate room temperature readings (72°F = 22.2°C)
-        // Using Combustion format: (temp_c + 20) / 0.05 = raw
-        // 22.2°C: (22.2 + 20) / 0.05 = 844
-        let raw_value = 844u16;
+    fn test_every_protocol_ships_a_fixture() {
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("bbq-protocols/tests/fixtures");
+        for protocol in ProtocolRegistry::protocols() {
+            let characteristic = ProtocolRegistry::fixture_characteristic(protocol.name)
+                .unwrap_or_else(|| panic!("protocol {} has no fixture", protocol.name));
+            // Recorded on the characteristic whose notifications the replay test parses
+            assert_eq!(ProtocolRegistry::frame_format(characteristic), FrameFormat::for_fixture(protocol.name));
+            assert!(fixtures.join(format!("{}.txt", protocol.name)).exists(), "protocol {} has no fixture", protocol.name);
+        }
+    }
+    
+    #[test]
+    fn test_instant_read_stores_once_per_plateau() {
+        let mut filter = InstantReadFilter::new(3, 0.5);
+        let stored: Vec<bool> = [72.0, 72.1, 72.0, 72.2, 72.1, 140.0, 160.0, 165.0, 165.2, 165.1, 165.0]
+            .iter()
+            .map(|&t| filter.should_store("AA", Temperature::from_fahrenheit(t)))
+            .collect();
+        assert_eq!(
+            stored,
+            // Counter temperature once, the climb is skipped, the settled reading once
+            [false, false, true, false, false, false, false, false, false, true, false]
+        );
+        
+        // Other devices are tracked separately
+        assert!(!filter.should_store("BB", Temperature::from_fahrenheit(72.0)));
+    }
+    
+    #[test]
+    fn test_igrill_post_auth_decode() {
+        let mut protocol = WeberProtocol::new();
+        // Hand-made probe 3 value (not a hardware capture): 0x0122 = 29.0°C
+        let notification = [0x22, 0x01];
+        assert_eq!(protocol.parse_notification(IGRILL_PROBE3_CHAR, &notification), None, "not paired yet");
+        
+        let challenge = protocol.app_challenge().unwrap();
+        assert_eq!(challenge, [0; IGRILL_CHALLENGE_LEN]);
+        let device_challenge: Vec<u8> = (0..16).collect();
+        assert_eq!(protocol.answer_challenge(&device_challenge).unwrap(), device_challenge);
+        protocol.response_written(true).unwrap();
+        assert!(protocol.is_authenticated());
+        
+        let temps = protocol.parse_notification(IGRILL_PROBE3_CHAR, &notification).unwrap().unwrap();
+        assert_eq!(temps.len(), WEBER_PROBE_COUNT);
+        assert!((temps[2].unwrap().celsius() - 29.0).abs() < 0.01, "{:?}", temps[2]);
+        assert!(temps[0].is_none() && temps[1].is_none() && temps[3].is_none());
+        assert_eq!(protocol.parse_notification(THERMOPRO_NOTIFY_CHAR, &notification), None);
+    }
+    
+    #[test]
+    fn test_igrill_sessions_gate_reads() {
+        let sessions = IGrillSessions::new();
+        let value = [0x22, 0x01];
+        assert_eq!(sessions.parse_notification("AA", IGRILL_PROBE3_CHAR, &value), None, "never paired");
+        
+        sessions.set("AA", WeberProtocol::new());
+        assert_eq!(sessions.parse_notification("AA", IGRILL_PROBE3_CHAR, &value), None, "handshake not done");
+        
+        sessions.set("AA", WeberProtocol::without_pairing());
+        let temps = sessions.parse_notification("AA", IGRILL_PROBE3_CHAR, &value).unwrap().unwrap();
+        assert!((temps[2].unwrap().celsius() - 29.0).abs() < 0.01);
+        assert_eq!(sessions.parse_notification("BB", IGRILL_PROBE3_CHAR, &value), None, "sessions are per device");
+        
+        sessions.forget("AA");
+        assert_eq!(sessions.parse_notification("AA", IGRILL_PROBE3_CHAR, &value), None);
+    }
+    
+    #[test]
+    fn test_igrill_handshake_order() {
+        let mut protocol = WeberProtocol::new();
+        assert_eq!(protocol.answer_challenge(&[0; 16]), Err(IGrillAuthError::OutOfOrder(IGrillAuthState::Idle)));
+        protocol.app_challenge().unwrap();
+        assert!(protocol.app_challenge().is_err(), "challenge sent only once");
+        assert_eq!(protocol.answer_challenge(&[0; 8]), Err(IGrillAuthError::InvalidChallenge(8)));
+        assert_eq!(protocol.state(), IGrillAuthState::Failed);
+        
+        let mut protocol = WeberProtocol::new();
+        protocol.app_challenge().unwrap();
+        protocol.answer_challenge(&[0xAB; 16]).unwrap();
+        assert_eq!(protocol.state(), IGrillAuthState::Responding);
+        protocol.response_written(false).unwrap();
+        assert_eq!(protocol.state(), IGrillAuthState::Failed);
+        assert!(!protocol.is_authenticated());
+    }
+    
+    #[test]
+    fn test_meater_short_frame_converts_to_anyhow() {
+        fn parse(data: &[u8]) -> anyhow::Result<Vec<Temperature>> {
+            Ok(MeaterProtocol::parse_temperature_data(data)?)
+        }
+        
+        let err = parse(&[0xDE, 0x00]).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::TooShort { needed: 8, got: 2 })
+        );
+    }
+    
+    #[test]
+    fn test_deduper_skips_identical_payloads() {
+        let mut deduper = NotificationDeduper::new(Duration::ZERO);
+        let payload = [0x4C, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        
+        assert!(deduper.should_store("AA", &payload, None));
+        assert!(!deduper.should_store("AA", &payload, None));
+        
+        // Other devices are tracked independently
+        assert!(deduper.should_store("BB", &payload, None));
+        
+        let mut changed = payload;
+        changed[0] = 0x4D;
+        assert!(deduper.should_store("AA", &changed, None));
+    }
+    
+    #[test]
+    fn test_deduper_prefers_sequence_numbers() {
+        let mut deduper = NotificationDeduper::new(Duration::ZERO);
+        
+        assert!(deduper.should_store("AA", &[1, 2, 3], Some(7)));
+        // Same sequence with a different payload is still a resend
+        assert!(!deduper.should_store("AA", &[9, 9, 9], Some(7)));
+        assert!(deduper.should_store("AA", &[1, 2, 3], Some(8)));
+    }
+    
+    #[test]
+    fn test_deduper_min_interval() {
+        let mut deduper = NotificationDeduper::new(Duration::from_secs(10));
+        let start = Instant::now();
+        
+        assert!(deduper.should_store_at("AA", &[1], None, start));
+        assert!(!deduper.should_store_at("AA", &[2], None, start + Duration::from_secs(5)));
+        assert!(deduper.should_store_at("AA", &[3], None, start + Duration::from_secs(11)));
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_notifications_store_one_row() {
+        let db = crate::Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        let mut deduper = NotificationDeduper::new(Duration::ZERO);
         
-        // Create 13-byte packed data for 8 sensors (13 bits each)
-        // Simplified: just putting same value in first few sensors
-        let mut data = vec![0u8; 13];
-        data[0] = (raw_value & 0xFF) as u8;
-        data[1] = ((raw_value >> 8) & 0x1F) as u8;
+        let mut warmer = valid_frame();
+        warmer[0] = 0x50;
         
-        let temps = MeatStickProtocol::parse_temperature_data(&data).unwrap();
-        assert!(!temps.is_empty());
+        for data in [valid_frame(), valid_frame(), valid_frame(), warmer] {
+            let temps = MeatStickProtocol::parse_temperature_data(&data).unwrap();
+            if deduper.should_store_frame("AA", &data, ReadingMode::Normal, temps[0]) {
+                db.insert_reading("AA", chrono::Utc::now(), 0, temps[0].unwrap(), None, None, 0)
+                    .await
+                    .unwrap();
+            }
+        }
         
-        // Should be close to 72°F
-        let temp_f = temps[0];
-        assert!((temp_f - 72.0).abs() < 1.0, "Expected ~72°F, got {}", temp_f);
+        let rows = db.get_device_readings("AA", 0).await.unwrap();
+        assert_eq!(rows.len(), 2, "one row for the repeats, one for the new value");
+    }
+    
+    /// Records written frames
+    #[derive(Clone, Default)]
+    struct RecordingWriter(std::sync::Arc<std::sync::Mutex<Vec<Vec<u8>>>>);
+    
+    impl UartWriter for RecordingWriter {
+        fn write(&self, frame: &[u8]) -> impl Future<Output = Result<(), UartError>> + Send {
+            self.0.lock().unwrap().push(frame.to_vec());
+            async { Ok(()) }
+        }
+    }
+    
+    /// A MeatStick temperature frame, which arrives between UART responses
+    fn valid_frame() -> Vec<u8> {
+        let mut data = vec![0u8; MEATSTICK_FRAME_LEN];
+        data[0] = 0x4C;
+        data[1] = 0x03;
+        data
+    }
+    
+    #[tokio::test]
+    async fn test_uart_session_matches_split_response() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let writer = RecordingWriter::default();
+        let mut session = UartSession::new(writer.clone(), tokio_stream::wrappers::ReceiverStream::new(rx));
+        
+        // A temperature frame and another command's output arrive before the answer,
+        // which itself is split across notifications
+        tx.send(valid_frame()).await.unwrap();
+        tx.send(b"temp: t1=21.5\r\n".to_vec()).await.unwrap();
+        tx.send(b"version: fw=1.4".to_vec()).await.unwrap();
+        tx.send(b".2 hw=3\r\nstatus: battery=ok\r\n".to_vec()).await.unwrap();
+        
+        let version = session.command("version").await.unwrap();
+        assert_eq!(version.get("fw"), Some("1.4.2"));
+        assert_eq!(version.get("hw"), Some("3"));
+        assert_eq!(session.unsolicited(), 2);
+        
+        // The buffered status line answers the next command without waiting
+        let status = session.command("status").await.unwrap();
+        assert_eq!(status.get("battery"), Some("ok"));
+        assert_eq!(*writer.0.lock().unwrap(), vec![b"version\r\n".to_vec(), b"status\r\n".to_vec()]);
+    }
+    
+    #[tokio::test]
+    async fn test_uart_session_times_out() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let mut session = UartSession::new(RecordingWriter::default(), tokio_stream::wrappers::ReceiverStream::new(rx))
+            .with_timeout(Duration::from_millis(20));
+        
+        tx.send(b"temp: t1=21.5\r\n".to_vec()).await.unwrap();
+        let err = session.command("version").await.unwrap_err();
+        assert!(matches!(err, UartError::Timeout { ref command, .. } if command == "version"), "{}", err);
+        
+        drop(tx);
+        assert_eq!(session.command("version").await.unwrap_err(), UartError::Closed);
+        assert!(matches!(session.command("bad cmd").await, Err(UartError::InvalidCommand(_))));
     }
     
     #[test]
-    fn test_meater_parsing() {
-        // Simulate MEATER data: tip at 72°F (22.2°C = 222 raw)
-        // ambient at 80°F (26.7°C)
-        let data = vec![
-            0xDE, 0x00, // Tip: 222 (22.2°C = 72°F)
-            0x00, 0x01, // RA: 256
-            0x00, 0x01, // OA: 256
-            0x00, 0x00, // Reserved
-        ];
-        
-        let temps = MeaterProtocol::parse_temperature_data(&data).unwrap();
-        assert_eq!(temps.len(), 2);
-        
-        // Check tip temperature
-        assert!((temps[0] - 72.0).abs() < 1.0);
+    fn test_device_info_strings() {
+        let mut info = DeviceInfo::default();
+        assert!(info.is_empty());
+        info.set(FIRMWARE_REVISION_CHAR, b"v1.4.2\0\0\0");
+        info.set(HARDWARE_REVISION_CHAR, b" rev B ");
+        info.set(MANUFACTURER_NAME_CHAR, b"Combustion Inc.");
+        info.set(SERIAL_NUMBER_CHAR, b"\0\0\0\0");
+        info.set(BATTERY_LEVEL_CHAR, b"ignored");
+        assert_eq!(info, DeviceInfo {
+            serial_number: None,
+            firmware_revision: Some("v1.4.2".to_string()),
+            hardware_revision: Some("rev B".to_string()),
+            manufacturer: Some("Combustion Inc.".to_string()),
+        });
+        assert_eq!(parse_dis_string(&[0xff, b'1']).as_deref(), Some("\u{fffd}1"));
+    }
+    
+    #[test]
+    fn test_battery_service_lookup_and_cache() {
+        let service = |uuid: Uuid, characteristic: Uuid| Service {
+            uuid,
+            primary: true,
+            characteristics: BTreeSet::from([Characteristic {
+                uuid: characteristic,
+                service_uuid: uuid,
+                properties: btleplug::api::CharPropFlags::READ,
+                descriptors: BTreeSet::new(),
+            }]),
+        };
+        let services = BTreeSet::from([
+            service(THERMOPRO_SERVICE, THERMOPRO_NOTIFY_CHAR),
+            service(BATTERY_SERVICE, BATTERY_LEVEL_CHAR),
+        ]);
+        assert_eq!(find_battery_level_characteristic(&services).unwrap().uuid, BATTERY_LEVEL_CHAR);
+        assert!(find_battery_level_characteristic(&BTreeSet::from([service(THERMOPRO_SERVICE, BATTERY_LEVEL_CHAR)])).is_none());
+        
+        assert_eq!(parse_battery_level(&[87]), Some(87));
+        assert_eq!(parse_battery_level(&[101]), None);
+        assert_eq!(parse_battery_level(&[]), None);
+        
+        let cache = BatteryCache::new(Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(cache.is_due_at("AA", now));
+        cache.record_at("AA", Some(80), now);
+        assert!(!cache.is_due_at("AA", now + Duration::from_secs(59)));
+        assert!(cache.is_due_at("AA", now + Duration::from_secs(60)));
+        cache.record_at("AA", None, now + Duration::from_secs(60));
+        assert_eq!(cache.level("AA"), Some(80), "a failed read keeps the last level");
+        assert!(!cache.is_due_at("AA", now + Duration::from_secs(61)));
+        assert_eq!(cache.level("BB"), None);
+    }
+    
+    #[test]
+    fn test_uart_response_parse() {
+        let response = UartResponse::parse("version: 1.4.2").unwrap();
+        assert_eq!(response.command, "version");
+        assert_eq!(response.text, "1.4.2");
+        assert!(response.fields.is_empty());
+        
+        let response = UartResponse::parse("status: battery=ok, mode=normal").unwrap();
+        assert_eq!(response.get("mode"), Some("normal"));
+        assert!(UartResponse::parse("no separator").is_none());
+        assert!(UartResponse::parse("two words: x").is_none());
     }
 }
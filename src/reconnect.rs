@@ -0,0 +1,287 @@
+// src/reconnect.rs
+use anyhow::{anyhow, Result};
+use btleplug::api::Service;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::ble::BleHealth;
+
+/// Wait before the first reconnect attempt; doubles with every failure
+pub const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between two reconnect attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// How long `wait_for_services` keeps discovering before accepting a device with no services
+pub const SERVICE_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause between service discovery attempts
+const SERVICE_DISCOVERY_RETRY: Duration = Duration::from_millis(250);
+
+/// GATT service discovery, behind a trait so tests can mock the peripheral
+pub trait ServiceDiscovery {
+    fn discover_services(&self) -> impl Future<Output = Result<()>> + Send;
+    fn services(&self) -> BTreeSet<Service>;
+}
+
+impl ServiceDiscovery for btleplug::platform::Peripheral {
+    async fn discover_services(&self) -> Result<()> {
+        Ok(btleplug::api::Peripheral::discover_services(self).await?)
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        btleplug::api::Peripheral::services(self)
+    }
+}
+
+/// A connection that can be brought back after the device dropped it
+pub trait Reconnect {
+    fn connect(&self) -> impl Future<Output = Result<()>> + Send;
+    /// Re-discover services and re-subscribe; false when nothing could be subscribed
+    fn restore(&self) -> impl Future<Output = Result<bool>> + Send;
+}
+
+/// Connect, treating an attempt that outlasts `timeout` as failed
+///
+/// `peripheral.connect()` can hang on a flaky device; this keeps one device
+/// from stalling everything waiting behind it.
+pub async fn connect_with_timeout<E>(connect: impl Future<Output = Result<(), E>>, timeout: Duration) -> Result<()>
+where
+    E: Into<anyhow::Error>,
+{
+    match tokio::time::timeout(timeout, connect).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => Err(anyhow!("connect timed out after {}s", timeout.as_secs_f32())),
+    }
+}
+
+/// Discover a connected device's services, repeating until some show up or `timeout` passes
+///
+/// Some adapters report no services right after `discover_services` returns;
+/// subscribing then would find nothing and the device would be dropped.
+/// Returns an empty set (after logging it) when none appear in time.
+pub async fn wait_for_services<P: ServiceDiscovery>(peripheral: &P, name: &str, timeout: Duration) -> Result<BTreeSet<Service>> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut attempts = 1;
+    loop {
+        peripheral.discover_services().await?;
+        let services = peripheral.services();
+        if !services.is_empty() {
+            if attempts > 1 {
+                info!("   🔍 {} services of {} found on discovery attempt {}", services.len(), name, attempts);
+            }
+            return Ok(services);
+        }
+        if tokio::time::Instant::now() + SERVICE_DISCOVERY_RETRY > deadline {
+            warn!("⚠️  {} is connected but exposes no services after {} discovery attempts", name, attempts);
+            return Ok(services);
+        }
+        debug!("No services on {} yet, discovering again", name);
+        tokio::time::sleep(SERVICE_DISCOVERY_RETRY).await;
+        attempts += 1;
+    }
+}
+
+/// Delay before attempt `attempt` (1-based)
+pub fn backoff_delay(attempt: u32, base: Duration) -> Duration {
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(MAX_RECONNECT_DELAY)
+}
+
+/// Try up to `attempts` times to reconnect and restore notifications
+///
+/// Returns the attempt that succeeded, or the last error once all have failed.
+pub async fn reconnect_with_backoff<P: Reconnect>(
+    peripheral: &P,
+    name: &str,
+    attempts: u32,
+    base_delay: Duration,
+) -> Result<u32> {
+    let mut last_error = anyhow!("no reconnect attempts configured");
+    for attempt in 1..=attempts {
+        let delay = backoff_delay(attempt, base_delay);
+        info!("🔄 Reconnecting to {} in {:?} (attempt {}/{})", name, delay, attempt, attempts);
+        tokio::time::sleep(delay).await;
+
+        let result = match peripheral.connect().await {
+            Ok(()) => peripheral.restore().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(true) => {
+                info!("✅ Reconnected to {} on attempt {}", name, attempt);
+                return Ok(attempt);
+            }
+            Ok(false) => last_error = anyhow!("no notifications to subscribe to"),
+            Err(e) => last_error = e,
+        }
+        warn!("   ❌ Attempt {}/{} for {} failed: {:#}", attempt, attempts, name, last_error);
+    }
+    Err(last_error)
+}
+
+/// Stop polling a device whose reconnect attempts ran out and report it offline
+///
+/// Returns the removed entry; None when `address` was not being monitored.
+pub fn drop_offline_device<T>(
+    devices: &mut Vec<T>,
+    address: &str,
+    address_of: impl Fn(&T) -> &str,
+    health: &BleHealth,
+) -> Option<T> {
+    let position = devices.iter().position(|device| address_of(device) == address)?;
+    let device = devices.remove(position);
+    health.device_offline(address, devices.len());
+    Some(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails to connect until `connect_after` attempts have been made
+    struct FlakyPeripheral {
+        connect_after: u32,
+        connects: AtomicU32,
+        restores: AtomicU32,
+    }
+
+    impl FlakyPeripheral {
+        fn new(connect_after: u32) -> Self {
+            Self { connect_after, connects: AtomicU32::new(0), restores: AtomicU32::new(0) }
+        }
+    }
+
+    impl Reconnect for FlakyPeripheral {
+        async fn connect(&self) -> Result<()> {
+            let attempt = self.connects.fetch_add(1, Ordering::SeqCst) + 1;
+            if attempt >= self.connect_after {
+                Ok(())
+            } else {
+                Err(anyhow!("le-connection-abort-by-local"))
+            }
+        }
+
+        async fn restore(&self) -> Result<bool> {
+            self.restores.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_on_second_try() {
+        let peripheral = FlakyPeripheral::new(2);
+        let attempt = reconnect_with_backoff(&peripheral, "probe", 3, Duration::from_millis(1)).await.unwrap();
+        assert_eq!(attempt, 2);
+        assert_eq!(peripheral.connects.load(Ordering::SeqCst), 2);
+        assert_eq!(peripheral.restores.load(Ordering::SeqCst), 1, "services restored once connected");
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_configured_attempts() {
+        let peripheral = FlakyPeripheral::new(u32::MAX);
+        let err = reconnect_with_backoff(&peripheral, "probe", 3, Duration::from_millis(1)).await.unwrap_err();
+        assert!(err.to_string().contains("abort"), "{}", err);
+        assert_eq!(peripheral.connects.load(Ordering::SeqCst), 3);
+        assert_eq!(peripheral.restores.load(Ordering::SeqCst), 0);
+
+        assert!(reconnect_with_backoff(&peripheral, "probe", 0, Duration::ZERO).await.is_err());
+
+        // The monitor stops polling it and the dashboard hears it is offline
+        let health = BleHealth::default();
+        let mut changes = health.subscribe();
+        let mut devices = vec![("probe", "AA"), ("other", "BB")];
+        let dropped = drop_offline_device(&mut devices, "AA", |(_, address)| address, &health);
+        assert_eq!(dropped, Some(("probe", "AA")));
+        assert_eq!(devices, [("other", "BB")]);
+        let status = changes.try_recv().unwrap();
+        assert_eq!(status.phase, crate::ble::BlePhase::Monitoring { devices: 1 });
+        assert_eq!(status.offline_devices, ["AA"]);
+        assert_eq!(status.message, "monitoring 1 device, 1 offline");
+        assert_eq!(health.status(), status);
+        assert_eq!(drop_offline_device(&mut devices, "AA", |(_, address)| address, &health), None);
+
+        health.device_online("AA");
+        assert!(health.status().offline_devices.is_empty());
+    }
+
+    /// Connects that never complete, like a device that stopped answering mid-handshake
+    struct HungPeripheral;
+
+    impl Reconnect for HungPeripheral {
+        async fn connect(&self) -> Result<()> {
+            connect_with_timeout(std::future::pending::<Result<()>>(), Duration::from_millis(20)).await
+        }
+
+        async fn restore(&self) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hung_connect_times_out() {
+        let err = connect_with_timeout(std::future::pending::<Result<()>>(), Duration::from_millis(20)).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(connect_with_timeout(async { Ok::<_, anyhow::Error>(()) }, Duration::from_secs(10)).await.is_ok());
+
+        // Every attempt fails in bounded time instead of hanging the loop
+        let err = reconnect_with_backoff(&HungPeripheral, "probe", 2, Duration::from_millis(1)).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+    }
+
+    /// Reports no services until discovery has run `ready_after` times
+    struct SlowDiscovery {
+        ready_after: u32,
+        discoveries: AtomicU32,
+    }
+
+    impl SlowDiscovery {
+        fn new(ready_after: u32) -> Self {
+            Self { ready_after, discoveries: AtomicU32::new(0) }
+        }
+    }
+
+    impl ServiceDiscovery for SlowDiscovery {
+        async fn discover_services(&self) -> Result<()> {
+            self.discoveries.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn services(&self) -> BTreeSet<Service> {
+            if self.discoveries.load(Ordering::SeqCst) < self.ready_after {
+                return BTreeSet::new();
+            }
+            BTreeSet::from([Service {
+                uuid: crate::protocol::MEATSTICK_SERVICE,
+                primary: true,
+                characteristics: BTreeSet::new(),
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_services_to_appear() {
+        let peripheral = SlowDiscovery::new(2);
+        let services = wait_for_services(&peripheral, "probe", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(services.len(), 1);
+        assert_eq!(peripheral.discoveries.load(Ordering::SeqCst), 2, "discovered again after an empty result");
+
+        let ready = SlowDiscovery::new(1);
+        wait_for_services(&ready, "probe", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(ready.discoveries.load(Ordering::SeqCst), 1);
+
+        // A device with no services is given up on at the deadline, not waited on forever
+        let empty = SlowDiscovery::new(u32::MAX);
+        let services = wait_for_services(&empty, "probe", Duration::from_millis(600)).await.unwrap();
+        assert!(services.is_empty());
+        assert!(empty.discoveries.load(Ordering::SeqCst) >= 2, "retried before giving up");
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let delays: Vec<u64> = (1..=7).map(|a| backoff_delay(a, RECONNECT_BASE_DELAY).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+    }
+}
@@ -0,0 +1,16 @@
+// src/schema_fingerprint.rs
+// Shared by build.rs and the xtask (via include!); keep it std-only.
+
+/// Records which migrations the sqlx query cache in `.sqlx` was prepared against
+pub const SCHEMA_FINGERPRINT_FILE: &str = ".sqlx/migrations.fingerprint";
+
+/// FNV-1a hash of the migrations source as hex, ignoring carriage returns
+///
+/// Unlike `DefaultHasher`, stable across Rust releases and platforms.
+pub fn schema_fingerprint(source: &[u8]) -> String {
+    let hash = source
+        .iter()
+        .filter(|byte| **byte != b'\r')
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", hash)
+}
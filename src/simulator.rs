@@ -0,0 +1,343 @@
+// src/simulator.rs
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{info, warn};
+
+use crate::config::SimulatorConfig;
+use crate::temperature::{Temperature, TemperatureUnit};
+use crate::web_server::{broadcast_update, TemperatureUpdate};
+use crate::database::{Database, ReadingSource};
+
+/// Brand simulated probes are stored under
+pub const SIMULATOR_BRAND: &str = "Simulator";
+
+/// Sensor indices of a simulated probe
+pub const SIM_MEAT_SENSOR: usize = 0;
+pub const SIM_PIT_SENSOR: usize = 1;
+
+/// Pending scenario commands queued for the simulator
+const SIMULATOR_CHANNEL_CAPACITY: usize = 8;
+
+/// Where the pit settles when nothing says otherwise (225°F)
+const DEFAULT_PIT_CELSIUS: f32 = 107.2;
+/// Room temperature, where a dead fire ends up
+const ROOM_CELSIUS: f32 = 21.0;
+/// Meat straight from the fridge
+const START_MEAT_CELSIUS: f32 = 4.0;
+/// Time constants (simulated seconds) for the pit following its target and the meat following the pit
+const PIT_TIME_CONSTANT_SECS: f32 = 180.0;
+const MEAT_TIME_CONSTANT_SECS: f32 = 7200.0;
+
+/// A change to a running simulated probe
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ScenarioCommand {
+    /// Drive the pit toward `temperature` (in the request's unit), relighting a dead fire
+    SetAmbient { temperature: f32 },
+    /// Hold the meat where it is for a while
+    Stall { minutes: f32 },
+    /// The fire goes out and the pit cools toward room temperature
+    Flameout,
+    /// Stop reporting, as if out of range
+    Disconnect { seconds: f32 },
+    /// Battery percent lost per simulated hour
+    BatteryDrain { percent_per_hour: f32 },
+    /// Back to a steady cook at the default pit temperature
+    Reset,
+}
+
+/// Effects currently applied to a simulated probe
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Scenario {
+    pub flameout: bool,
+    /// Simulated seconds the meat stays stalled
+    pub stall_secs_left: Option<f32>,
+    /// Simulated seconds until the probe reports again
+    pub offline_secs_left: Option<f32>,
+    pub battery_drain_per_hour: f32,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            flameout: false,
+            stall_secs_left: None,
+            offline_secs_left: None,
+            battery_drain_per_hour: 0.0,
+        }
+    }
+}
+
+/// A simulated probe as listed by `GET /api/admin/simulator`
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedDevice {
+    pub device_address: String,
+    pub device_name: String,
+    pub meat: f32,
+    pub pit: f32,
+    pub pit_target: f32,
+    /// Unit of `meat`, `pit` and `pit_target`
+    pub unit: TemperatureUnit,
+    pub battery_level: u8,
+    pub connected: bool,
+    pub scenario: Scenario,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SimulatorError {
+    #[error("No simulated device {0}")]
+    UnknownDevice(String),
+    #[error("Simulator is not running")]
+    Unavailable,
+}
+
+/// A request for the simulator task, answered on `reply`
+#[derive(Debug)]
+pub enum SimulatorCommand {
+    List {
+        reply: oneshot::Sender<Vec<SimulatedDevice>>,
+    },
+    Scenario {
+        device_address: String,
+        command: ScenarioCommand,
+        /// Unit temperatures in `command` are given in
+        unit: TemperatureUnit,
+        reply: oneshot::Sender<Result<SimulatedDevice, SimulatorError>>,
+    },
+}
+
+/// Channel the web server sends scenario commands on; the simulator owns the receiver
+pub fn simulator_channel() -> (mpsc::Sender<SimulatorCommand>, mpsc::Receiver<SimulatorCommand>) {
+    mpsc::channel(SIMULATOR_CHANNEL_CAPACITY)
+}
+
+/// One simulated probe: meat on sensor 0, pit on sensor 1
+#[derive(Debug, Clone)]
+pub struct SimulatedProbe {
+    pub address: String,
+    pub name: String,
+    meat: Temperature,
+    pit: Temperature,
+    pit_target: Temperature,
+    battery: f32,
+    scenario: Scenario,
+}
+
+impl SimulatedProbe {
+    pub fn new(index: usize) -> Self {
+        Self {
+            address: format!("sim-{}", index),
+            name: format!("Simulated Probe {}", index),
+            meat: Temperature::from_celsius(START_MEAT_CELSIUS),
+            pit: Temperature::from_celsius(DEFAULT_PIT_CELSIUS),
+            pit_target: Temperature::from_celsius(DEFAULT_PIT_CELSIUS),
+            battery: 100.0,
+            scenario: Scenario::default(),
+        }
+    }
+
+    pub fn apply(&mut self, command: &ScenarioCommand, unit: TemperatureUnit) {
+        match *command {
+            ScenarioCommand::SetAmbient { temperature } => {
+                self.pit_target = Temperature::from_unit(temperature, unit);
+                self.scenario.flameout = false;
+            }
+            ScenarioCommand::Stall { minutes } => self.scenario.stall_secs_left = Some(minutes.max(0.0) * 60.0),
+            ScenarioCommand::Flameout => {
+                self.pit_target = Temperature::from_celsius(ROOM_CELSIUS);
+                self.scenario.flameout = true;
+            }
+            ScenarioCommand::Disconnect { seconds } => self.scenario.offline_secs_left = Some(seconds.max(0.0)),
+            ScenarioCommand::BatteryDrain { percent_per_hour } => {
+                self.scenario.battery_drain_per_hour = percent_per_hour.max(0.0);
+            }
+            ScenarioCommand::Reset => {
+                self.pit_target = Temperature::from_celsius(DEFAULT_PIT_CELSIUS);
+                self.scenario = Scenario::default();
+            }
+        }
+    }
+
+    /// Advance `dt` of simulated time; returns (meat, pit) unless the probe is offline
+    pub fn step(&mut self, dt: Duration) -> Option<(Temperature, Temperature)> {
+        let secs = dt.as_secs_f32();
+        let approach = |from: Temperature, to: Temperature, tau: f32| {
+            let k = 1.0 - (-secs / tau).exp();
+            Temperature::from_celsius(from.celsius() + (to.celsius() - from.celsius()) * k)
+        };
+        self.pit = approach(self.pit, self.pit_target, PIT_TIME_CONSTANT_SECS);
+
+        match &mut self.scenario.stall_secs_left {
+            Some(left) if *left > secs => *left -= secs,
+            stall => {
+                *stall = None;
+                self.meat = approach(self.meat, self.pit, MEAT_TIME_CONSTANT_SECS);
+            }
+        }
+        self.battery = (self.battery - self.scenario.battery_drain_per_hour * secs / 3600.0).max(0.0);
+
+        match &mut self.scenario.offline_secs_left {
+            Some(left) if *left > secs => {
+                *left -= secs;
+                None
+            }
+            offline => {
+                *offline = None;
+                Some((self.meat, self.pit))
+            }
+        }
+    }
+
+    pub fn battery_level(&self) -> u8 {
+        self.battery.round() as u8
+    }
+
+    pub fn status(&self, unit: TemperatureUnit) -> SimulatedDevice {
+        SimulatedDevice {
+            device_address: self.address.clone(),
+            device_name: self.name.clone(),
+            meat: self.meat.in_unit(unit),
+            pit: self.pit.in_unit(unit),
+            pit_target: self.pit_target.in_unit(unit),
+            unit,
+            battery_level: self.battery_level(),
+            connected: self.scenario.offline_secs_left.is_none(),
+            scenario: self.scenario.clone(),
+        }
+    }
+}
+
+/// Run the simulated probes until shutdown: store and broadcast a reading per tick, apply commands
+pub async fn run_simulator(
+    config: SimulatorConfig,
+    unit: TemperatureUnit,
+    db: Arc<Database>,
+    tx: broadcast::Sender<TemperatureUpdate>,
+    mut commands: mpsc::Receiver<SimulatorCommand>,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    let mut probes: Vec<SimulatedProbe> = (1..=config.devices.max(1)).map(SimulatedProbe::new).collect();
+    for probe in &probes {
+        if let Err(e) = db.upsert_device(&probe.address, &probe.name, SIMULATOR_BRAND, "Simulated probe", 2).await {
+            warn!("⚠️  Simulator: {:#}", e);
+        }
+    }
+    info!("🧪 Simulating {} probe(s) at {}x speed", probes.len(), config.speed);
+
+    let tick = Duration::from_millis(config.tick_ms.max(1));
+    let simulated_tick = tick.mul_f32(config.speed.max(0.0));
+    let mut interval = tokio::time::interval(tick);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                for probe in &mut probes {
+                    let Some((meat, pit)) = probe.step(simulated_tick) else { continue };
+                    publish_reading(&db, &tx, probe, meat, pit).await;
+                }
+            }
+            Some(command) = commands.recv() => match command {
+                SimulatorCommand::List { reply } => {
+                    let _ = reply.send(probes.iter().map(|p| p.status(unit)).collect());
+                }
+                SimulatorCommand::Scenario { device_address, command, unit: command_unit, reply } => {
+                    let result = match probes.iter_mut().find(|p| p.address == device_address) {
+                        Some(probe) => {
+                            info!("🧪 {} scenario: {:?}", probe.address, command);
+                            probe.apply(&command, command_unit);
+                            Ok(probe.status(unit))
+                        }
+                        None => Err(SimulatorError::UnknownDevice(device_address)),
+                    };
+                    let _ = reply.send(result);
+                }
+            },
+            _ = shutdown.recv() => break,
+        }
+    }
+}
+
+async fn publish_reading(
+    db: &Database,
+    tx: &broadcast::Sender<TemperatureUpdate>,
+    probe: &SimulatedProbe,
+    meat: Temperature,
+    pit: Temperature,
+) {
+    let timestamp = Utc::now();
+    let battery = Some(probe.battery_level());
+    for (sensor_index, temperature) in [(SIM_MEAT_SENSOR, meat), (SIM_PIT_SENSOR, pit)] {
+        if let Err(e) = db.insert_reading_from(ReadingSource::Simulator, &probe.address, timestamp, sensor_index, temperature, Some(pit), battery, 0).await {
+            warn!("⚠️  Simulator: {:#}", e);
+        }
+        broadcast_update(tx, TemperatureUpdate {
+            device_address: probe.address.clone(),
+            device_name: probe.name.clone(),
+            timestamp,
+            sensor_index,
+            temperature: temperature.celsius(),
+            ambient_temp: Some(pit.celsius()),
+            unit: TemperatureUnit::Celsius,
+            battery_level: battery,
+            signal_strength: 0,
+            eta_seconds: None,
+            reading_mode: Default::default(),
+            virtual_sensors: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Step a probe for `minutes` of simulated time in 10 s ticks, returning the readings
+    fn run(probe: &mut SimulatedProbe, minutes: u64) -> Vec<Option<(Temperature, Temperature)>> {
+        (0..minutes * 6).map(|_| probe.step(Duration::from_secs(10))).collect()
+    }
+
+    #[test]
+    fn test_flameout_cools_the_pit() {
+        let mut probe = SimulatedProbe::new(1);
+        run(&mut probe, 10);
+        assert!((probe.pit.fahrenheit() - 225.0).abs() < 1.0);
+
+        probe.apply(&ScenarioCommand::Flameout, TemperatureUnit::Fahrenheit);
+        let readings = run(&mut probe, 10);
+        let pits: Vec<f32> = readings.iter().map(|r| r.unwrap().1.fahrenheit()).collect();
+        assert!(pits.windows(2).all(|w| w[1] < w[0]), "pit only falls");
+        assert!(pits[pits.len() - 1] < 150.0, "dead fire after 10 min: {}", pits[pits.len() - 1]);
+
+        // Relighting clears the flameout
+        probe.apply(&ScenarioCommand::SetAmbient { temperature: 250.0 }, TemperatureUnit::Fahrenheit);
+        assert!(!probe.scenario.flameout);
+        run(&mut probe, 30);
+        assert!((probe.pit.fahrenheit() - 250.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_stall_disconnect_and_battery() {
+        let mut probe = SimulatedProbe::new(1);
+        run(&mut probe, 5);
+        probe.apply(&ScenarioCommand::Stall { minutes: 2.0 }, TemperatureUnit::Celsius);
+        let held = probe.meat;
+        run(&mut probe, 1);
+        assert_eq!(probe.meat, held);
+        run(&mut probe, 2);
+        assert!(probe.meat > held, "stall ends");
+
+        probe.apply(&ScenarioCommand::Disconnect { seconds: 30.0 }, TemperatureUnit::Celsius);
+        assert!(!probe.status(TemperatureUnit::Celsius).connected);
+        let readings = run(&mut probe, 1);
+        assert_eq!(readings.iter().filter(|r| r.is_none()).count(), 2, "silent for 30 s");
+        assert!(probe.status(TemperatureUnit::Celsius).connected);
+
+        probe.apply(&ScenarioCommand::BatteryDrain { percent_per_hour: 60.0 }, TemperatureUnit::Celsius);
+        run(&mut probe, 30);
+        assert_eq!(probe.battery_level(), 70);
+        probe.apply(&ScenarioCommand::Reset, TemperatureUnit::Celsius);
+        assert_eq!(probe.status(TemperatureUnit::Celsius).scenario, Scenario::default());
+    }
+}
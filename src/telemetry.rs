@@ -0,0 +1,278 @@
+// src/telemetry.rs
+//! Opt-in counts of frames the parsers rejected, to find unsupported formats
+//!
+//! Only what identifies a format is kept: the probe's advertised name with
+//! its digits masked, the parser, the kind of failure and the frame length.
+//! No addresses, payload bytes, temperatures or timestamps.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::protocol::ProtocolError;
+
+/// Longest device name kept in a report
+const MAX_NAME_CHARS: usize = 32;
+
+/// What happens to parse failure counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetryMode {
+    /// Nothing is counted
+    #[default]
+    Off,
+    /// Counts are logged on this machine and never leave it
+    Local,
+    /// Counts are also uploaded through the cloud backend
+    Upload,
+}
+
+/// Everything one count is keyed by
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct ParseFailureKey {
+    /// Advertised name with digits masked (see `anonymize_device_name`)
+    pub device_name: String,
+    /// Parser that rejected the frame, e.g. `meatstick` or `igrill`
+    pub protocol: String,
+    /// `too_short`, `out_of_range` or `unsupported_format`
+    pub failure: &'static str,
+    pub frame_len: usize,
+}
+
+/// Rejected frames sharing a key since the last report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseFailureCount {
+    #[serde(flatten)]
+    pub key: ParseFailureKey,
+    pub count: u64,
+}
+
+/// One upload: the counts and the app version that produced them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TelemetryReport {
+    pub app_version: &'static str,
+    pub failures: Vec<ParseFailureCount>,
+}
+
+/// Where uploaded reports go, behind a trait so tests can stub it
+pub trait TelemetryUpload {
+    fn upload_telemetry(&self, report: &TelemetryReport) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Stands in for the cloud backend when it is not compiled in
+impl TelemetryUpload for () {
+    async fn upload_telemetry(&self, _report: &TelemetryReport) -> Result<()> {
+        anyhow::bail!("cloud sync is not compiled in")
+    }
+}
+
+/// "iGrill_mini##" from "iGrill_mini42": model names survive, serials and user numbering do not
+pub fn anonymize_device_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .take(MAX_NAME_CHARS)
+        .map(|c| if c.is_ascii_digit() { '#' } else { c })
+        .collect()
+}
+
+/// Stable label for the kind of failure
+pub fn failure_kind(error: &ProtocolError) -> &'static str {
+    match error {
+        ProtocolError::TooShort { .. } => "too_short",
+        ProtocolError::OutOfRange { .. } => "out_of_range",
+        ProtocolError::UnsupportedFormat { .. } => "unsupported_format",
+    }
+}
+
+/// Parse failure counts, held until the next report
+pub struct ParseFailureTelemetry {
+    mode: TelemetryMode,
+    counts: Mutex<HashMap<ParseFailureKey, u64>>,
+}
+
+impl ParseFailureTelemetry {
+    pub fn new(mode: TelemetryMode) -> Self {
+        Self { mode, counts: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn mode(&self) -> TelemetryMode {
+        self.mode
+    }
+
+    /// Count a rejected frame; does nothing unless telemetry was opted into
+    pub fn record(&self, device_name: &str, protocol: &str, error: &ProtocolError, frame_len: usize) {
+        if self.mode == TelemetryMode::Off {
+            return;
+        }
+        let key = ParseFailureKey {
+            device_name: anonymize_device_name(device_name),
+            protocol: protocol.to_string(),
+            failure: failure_kind(error),
+            frame_len,
+        };
+        *self.counts.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    /// Take every count so far, ordered by key
+    pub fn take(&self) -> Vec<ParseFailureCount> {
+        let mut failures: Vec<ParseFailureCount> = self
+            .counts
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(key, count)| ParseFailureCount { key, count })
+            .collect();
+        failures.sort_by(|a, b| a.key.cmp(&b.key));
+        failures
+    }
+
+    /// Put counts back after a failed upload, adding to anything counted since
+    fn restore(&self, failures: Vec<ParseFailureCount>) {
+        let mut counts = self.counts.lock().unwrap();
+        for failure in failures {
+            *counts.entry(failure.key).or_insert(0) += failure.count;
+        }
+    }
+
+    /// Report the counts so far: log them, and upload them in upload mode
+    ///
+    /// Without an uploader (no cloud backend running) upload mode only logs.
+    /// A failed upload keeps the counts for the next report.
+    pub async fn report<U: TelemetryUpload>(&self, uploader: Option<&U>) {
+        let failures = self.take();
+        if failures.is_empty() {
+            return;
+        }
+        for failure in &failures {
+            let key = &failure.key;
+            info!(
+                "📈 Parse failures: {} × {} {} frames of {} bytes from '{}'",
+                failure.count, key.protocol, key.failure, key.frame_len, key.device_name
+            );
+        }
+
+        match (self.mode, uploader) {
+            (TelemetryMode::Upload, Some(uploader)) => {
+                let report = TelemetryReport { app_version: env!("CARGO_PKG_VERSION"), failures };
+                match uploader.upload_telemetry(&report).await {
+                    Ok(()) => debug!("Uploaded {} parse failure counts", report.failures.len()),
+                    Err(e) => {
+                        warn!("⚠️  Telemetry upload failed, keeping the counts for next time: {:#}", e);
+                        self.restore(report.failures);
+                    }
+                }
+            }
+            (TelemetryMode::Upload, None) => debug!("No cloud backend running; parse failure counts stay local"),
+            _ => {}
+        }
+    }
+}
+
+/// Report parse failure counts every `interval`, and once more on shutdown
+pub async fn run_telemetry_reporter<U: TelemetryUpload>(
+    telemetry: Arc<ParseFailureTelemetry>,
+    uploader: Option<Arc<U>>,
+    interval: Duration,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    info!("📈 Parse failure telemetry on ({:?}), reporting every {}s", telemetry.mode(), interval.as_secs());
+    let mut ticks = tokio::time::interval(interval);
+    // The first tick completes at once, with nothing counted yet
+    ticks.tick().await;
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => telemetry.report(uploader.as_deref()).await,
+            _ = shutdown.recv() => {
+                telemetry.report(uploader.as_deref()).await;
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uploads kept in memory; fails while `offline` is set
+    #[derive(Default)]
+    struct StubUpload {
+        reports: Mutex<Vec<TelemetryReport>>,
+        offline: std::sync::atomic::AtomicBool,
+    }
+
+    impl TelemetryUpload for StubUpload {
+        async fn upload_telemetry(&self, report: &TelemetryReport) -> Result<()> {
+            if self.offline.load(std::sync::atomic::Ordering::SeqCst) {
+                anyhow::bail!("offline");
+            }
+            self.reports.lock().unwrap().push(report.clone());
+            Ok(())
+        }
+    }
+
+    fn too_short() -> ProtocolError {
+        ProtocolError::TooShort { needed: 13, got: 6 }
+    }
+
+    #[test]
+    fn test_failures_aggregate_by_format() {
+        let telemetry = ParseFailureTelemetry::new(TelemetryMode::Local);
+        telemetry.record("cA00probe1234", "meatstick", &too_short(), 6);
+        telemetry.record("cA00probe9876", "meatstick", &too_short(), 6);
+        telemetry.record("cA00probe9876", "meatstick", &too_short(), 7);
+        telemetry.record("TP25", "thermopro", &ProtocolError::UnsupportedFormat { got: 9 }, 9);
+
+        let failures = telemetry.take();
+        let summary: Vec<(&str, &str, usize, u64)> = failures
+            .iter()
+            .map(|f| (f.key.device_name.as_str(), f.key.failure, f.key.frame_len, f.count))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("TP##", "unsupported_format", 9, 1),
+                ("cA##probe####", "too_short", 6, 2),
+                ("cA##probe####", "too_short", 7, 1),
+            ]
+        );
+        assert!(telemetry.take().is_empty(), "taking empties the counts");
+
+        let json = serde_json::to_value(&failures[0]).unwrap();
+        let mut fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort();
+        assert_eq!(fields, ["count", "device_name", "failure", "frame_len", "protocol"], "nothing else leaves the machine");
+    }
+
+    #[tokio::test]
+    async fn test_nothing_collected_or_uploaded_without_opt_in() {
+        let uploader = StubUpload::default();
+
+        let off = ParseFailureTelemetry::new(TelemetryMode::default());
+        off.record("MEATER", "meater_block", &too_short(), 2);
+        assert!(off.take().is_empty());
+
+        let local = ParseFailureTelemetry::new(TelemetryMode::Local);
+        local.record("MEATER", "meater_block", &too_short(), 2);
+        local.report(Some(&uploader)).await;
+        assert!(uploader.reports.lock().unwrap().is_empty(), "local mode only logs");
+        assert!(local.take().is_empty());
+
+        let upload = ParseFailureTelemetry::new(TelemetryMode::Upload);
+        upload.record("MEATER", "meater_block", &too_short(), 2);
+        uploader.offline.store(true, std::sync::atomic::Ordering::SeqCst);
+        upload.report(Some(&uploader)).await;
+        upload.record("MEATER", "meater_block", &too_short(), 2);
+        uploader.offline.store(false, std::sync::atomic::Ordering::SeqCst);
+        upload.report(Some(&uploader)).await;
+
+        let reports = uploader.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].failures[0].count, 2, "a failed upload is retried with later counts");
+        assert_eq!(reports[0].app_version, env!("CARGO_PKG_VERSION"));
+    }
+}
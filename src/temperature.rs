@@ -0,0 +1,3 @@
+// src/temperature.rs
+//! Temperature types live in `bbq-protocols`, so the parsers there can use them without std
+pub use bbq_protocols::temperature::*;
@@ -0,0 +1,26 @@
+// src/test_support.rs
+//! Helpers shared by the unit tests of several modules
+
+/// Cook sessions recorded with `bbq-monitor db export-fixture`, in `tests/fixtures`
+pub mod fixtures {
+    use std::path::Path;
+
+    use crate::database::{CookSession, Database, ReadingRecord};
+    use crate::fixtures::SessionFixture;
+
+    /// A fresh in-memory database holding the fixture `<name>.json.gz`, and its session
+    pub async fn load(name: &str) -> (Database, CookSession) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("{}.json.gz", name));
+        let fixture = SessionFixture::read_gz(&path).unwrap();
+        let db = Database::new(":memory:").await.unwrap();
+        let session = fixture.load_into(&db).await.unwrap();
+        (db, session)
+    }
+
+    /// One sensor's readings over the whole session, oldest first
+    pub async fn sensor_readings(db: &Database, session: &CookSession, sensor_index: i64) -> Vec<ReadingRecord> {
+        let end = session.ended_at.expect("fixture sessions are finished");
+        let readings = db.get_readings_in_range(&session.device_address, session.started_at, end).await.unwrap();
+        readings.into_iter().filter(|r| r.sensor_index == sensor_index).collect()
+    }
+}
@@ -0,0 +1,131 @@
+// src/version.rs
+use serde::Serialize;
+
+use crate::config::CONFIG_SCHEMA_VERSION;
+use crate::database::DB_SCHEMA_VERSION;
+use crate::fanout::WS_PROTOCOL_VERSION;
+use crate::protocol::{ProtocolInfo, ProtocolRegistry};
+
+/// Commit the binary was built from ("unknown" outside a git checkout)
+pub const GIT_HASH: &str = env!("BBQ_GIT_HASH");
+
+/// Schema versions the binary reads and writes
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaVersions {
+    pub database: u32,
+    pub config: u32,
+    pub ws_protocol: u32,
+}
+
+/// Build information for `--version --json` and `/api/version`
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub features: Vec<&'static str>,
+    pub protocols: &'static [ProtocolInfo],
+    pub schema: SchemaVersions,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        let mut features = Vec::new();
+        if cfg!(feature = "aws") {
+            features.push("aws");
+        }
+        if cfg!(feature = "pit-controller") {
+            features.push("pit-controller");
+        }
+
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: GIT_HASH,
+            features,
+            protocols: ProtocolRegistry::protocols(),
+            schema: SchemaVersions {
+                database: DB_SCHEMA_VERSION,
+                config: CONFIG_SCHEMA_VERSION,
+                ws_protocol: WS_PROTOCOL_VERSION,
+            },
+        }
+    }
+
+    /// One-line summary for logs and plain `--version`
+    pub fn banner(&self) -> String {
+        let features = if self.features.is_empty() {
+            "none".to_string()
+        } else {
+            self.features.join(",")
+        };
+        let protocols: Vec<&str> = self.protocols.iter().map(|p| p.name).collect();
+
+        format!(
+            "BBQ Monitor v{} ({}) features: {} | protocols: {} | schema db={} config={} ws={}",
+            self.version,
+            self.git_hash,
+            features,
+            protocols.join(","),
+            self.schema.database,
+            self.schema.config,
+            self.schema.ws_protocol,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    include!("git_hash.rs");
+
+    /// First frame of a protocol's fixture and the temperatures it should decode to
+    fn first_fixture_frame(name: &str) -> (Vec<u8>, Vec<Option<f32>>) {
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("bbq-protocols/tests/fixtures");
+        let frames = std::fs::read_to_string(fixtures.join(format!("{}.txt", name))).unwrap();
+        let hex = frames.lines().find(|l| !l.is_empty() && !l.starts_with('#')).unwrap();
+        let frame = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        let expected = std::fs::read_to_string(fixtures.join(format!("{}.csv", name))).unwrap();
+        let row = expected.lines().nth(1).unwrap();
+        let celsius = row.split(',').skip(1).map(|v| v.parse().ok()).collect();
+        (frame, celsius)
+    }
+
+    #[test]
+    fn test_json_lists_every_registered_protocol() {
+        let json = serde_json::to_value(VersionInfo::current()).unwrap();
+        let listed: Vec<&str> = json["protocols"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert!(!listed.is_empty());
+
+        // Every listed protocol really decodes a frame of its own through the registry
+        for name in listed {
+            let characteristic = ProtocolRegistry::fixture_characteristic(name).unwrap();
+            let (frame, expected) = first_fixture_frame(name);
+            let decoded: Vec<Option<f32>> = ProtocolRegistry::parse_notification(characteristic, &frame)
+                .unwrap_or_else(|| panic!("no parser for {}", name))
+                .unwrap_or_else(|e| panic!("{} frame rejected: {}", name, e))
+                .iter()
+                .map(|t| t.map(|t| (t.celsius() * 10.0).round() / 10.0))
+                .collect();
+            assert_eq!(decoded, expected, "{}", name);
+        }
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["schema"]["ws_protocol"], WS_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_git_hash_fallback_outside_checkout() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(git_hash(dir.path()), "unknown");
+        assert!(!GIT_HASH.is_empty());
+    }
+}
@@ -1,30 +1,79 @@
 // src/web_server.rs
 use anyhow::Result;
 use axum::{
-    extract::{Path, State, ws::{Message, WebSocket, WebSocketUpgrade}},
-    http::StatusCode,
+    body::Body,
+    error_handling::HandleErrorLayer,
+    extract::{FromRequestParts, Path, State, ws::{Message, WebSocket, WebSocketUpgrade}},
+    http::{header, request::Parts, StatusCode},
+    middleware::Next,
     response::{Html, IntoResponse, Response},
-    routing::{get, get_service},
-    Json, Router,
+    routing::{delete, get, get_service, post, put},
+    BoxError, Json, Router,
 };
 use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::StreamExt;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::ServiceBuilder;
 use tower_http::services::ServeDir;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, trace};
+use uuid::Uuid;
 
-use crate::{Database, License};
+use crate::access_log::{log_access, AccessLog};
+use crate::alerts::{AlertEngine, AlertEvent, AlertKind, AlertKindName, AlertRule, FirstSeenDevice, MAX_TARGET_OFFSET_F};
+use crate::analytics;
+use crate::ble::BleHealth;
+use crate::ble_debug::{self, DebugCommand, DebugError, DebugOperation};
+use crate::log_control::LogControl;
+use crate::simulator::{ScenarioCommand, SimulatorCommand, SimulatorError};
+use crate::maintenance::{self, DeviceNote, NoteCategory};
+use crate::fanout::{ClientCursor, ClientMessage, ControlMessage, DeviceHistory, Fanout, Replay, REPLAY_BUFFER_CAPACITY};
+use crate::device_capabilities::{default_primary_sensor, sensor_layout, SensorRole};
+use crate::{
+    export, food_safety, ActivationError, Config, CookSession, CookStats, Database, DeviceRecord, DeviceSort, DeviceStats, ExportFormat, FuelEstimate, LicenseManager, ReadingMode,
+    ProbeColor, ReadingRecord, ReadingSource, SmokerProfile, Temperature, TemperatureUnit,
+    VirtualSensors,
+};
 
 /// Web server state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
     pub db: Arc<Database>,
     pub tx: broadcast::Sender<TemperatureUpdate>,
-    pub license: Arc<License>,
+    pub license: Arc<LicenseManager>,
+    pub config: Arc<Config>,
+    pub ble_health: BleHealth,
+    /// Sequenced view of `tx` with a replay buffer for reconnecting clients
+    pub fanout: Arc<Fanout>,
+    /// Runtime log level control (None when the host did not install a reload layer)
+    pub log_control: Option<Arc<LogControl>>,
+    /// Alert rules and events; only served while the license has `alerts` (see `licensed_alerts`)
+    pub alerts: Option<Arc<AlertEngine>>,
+    /// Request log for `web.access_log_path` (None when not configured)
+    pub access_log: Option<Arc<AccessLog>>,
+    /// Raw BLE operations for the device monitor (None outside developer mode)
+    pub debug_commands: Option<mpsc::Sender<DebugCommand>>,
+    /// Scenario control for simulated probes (None unless the simulator runs)
+    pub simulator: Option<mpsc::Sender<SimulatorCommand>>,
+    /// Pit fan/damper loop (None unless `pit_controller.enabled`)
+    #[cfg(feature = "pit-controller")]
+    pub pit_controller: Option<Arc<crate::pit_controller::PitController>>,
+}
+
+impl AppState {
+    /// The alert engine, if the license currently includes alerts
+    fn licensed_alerts(&self) -> Option<&Arc<AlertEngine>> {
+        self.alerts.as_ref().filter(|_| self.license.current().features.alerts)
+    }
 }
 
 /// Real-time temperature update message
+///
+/// Producers send Celsius; the WebSocket converts to the display unit with `in_unit`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemperatureUpdate {
     pub device_address: String,
@@ -33,8 +82,144 @@ pub struct TemperatureUpdate {
     pub sensor_index: usize,
     pub temperature: f32,
     pub ambient_temp: Option<f32>,
+    /// Unit of `temperature` and `ambient_temp`
+    #[serde(default)]
+    pub unit: TemperatureUnit,
     pub battery_level: Option<u8>,
     pub signal_strength: i16,
+    /// Seconds until the predicted set point is reached (predictive probes only)
+    #[serde(default)]
+    pub eta_seconds: Option<u32>,
+    /// Probe mode the reading was taken in
+    #[serde(default)]
+    pub reading_mode: ReadingMode,
+    /// Sensors the probe reports as core/surface/ambient (Combustion probes only)
+    #[serde(default)]
+    pub virtual_sensors: Option<VirtualSensors>,
+}
+
+impl TemperatureUpdate {
+    /// The same update with temperatures converted to `unit`
+    pub fn in_unit(mut self, unit: TemperatureUnit) -> Self {
+        let from = self.unit;
+        self.temperature = Temperature::from_unit(self.temperature, from).in_unit(unit);
+        self.ambient_temp = self.ambient_temp.map(|t| Temperature::from_unit(t, from).in_unit(unit));
+        self.unit = unit;
+        self
+    }
+}
+
+/// Publish a live update to every current subscriber, returning how many got it
+///
+/// Updates are dropped when nobody is listening (no clients and no background
+/// consumers yet); that is routine, so it is only traced rather than treated as an error.
+pub fn broadcast_update(tx: &broadcast::Sender<TemperatureUpdate>, update: TemperatureUpdate) -> usize {
+    match tx.send(update) {
+        Ok(receivers) => receivers,
+        Err(broadcast::error::SendError(update)) => {
+            trace!(
+                "No subscribers, dropped update for {} sensor {}",
+                update.device_address,
+                update.sensor_index
+            );
+            0
+        }
+    }
+}
+
+/// Unit a request wants temperatures in: `?unit=c|f` (or `celsius`/`fahrenheit`), else the configured unit
+///
+/// The only place the API picks a unit. Handlers convert the stored Celsius
+/// values with `Temperature::in_unit` on the way out and `Temperature::from_unit`
+/// on the way in, so nothing is converted twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestUnit(pub TemperatureUnit);
+
+/// Query parameter read by `RequestUnit`
+#[derive(Debug, Deserialize)]
+struct UnitQuery {
+    unit: Option<String>,
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for RequestUnit {
+    type Rejection = Response;
+    
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let query = axum::extract::Query::<UnitQuery>::try_from_uri(&parts.uri)
+            .map_err(IntoResponse::into_response)?;
+        match query.0.unit {
+            None => Ok(Self(state.config.temperature.display_unit())),
+            Some(unit) => TemperatureUnit::parse(&unit).map(Self).ok_or_else(|| {
+                let message = format!("Unknown unit '{}', expected c or f", unit);
+                (StatusCode::BAD_REQUEST, message).into_response()
+            }),
+        }
+    }
+}
+
+/// Proof that the request carries the configured `web.admin_token`
+pub struct AdminAuth;
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = Response;
+    
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let Some(expected) = state.config.web.as_ref().and_then(|w| w.admin_token.as_deref()).filter(|t| !t.is_empty()) else {
+            return Err((StatusCode::FORBIDDEN, "Set web.admin_token to use admin endpoints").into_response());
+        };
+        let provided = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match provided {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(Self),
+            _ => Err((StatusCode::UNAUTHORIZED, "Admin token required").into_response()),
+        }
+    }
+}
+
+/// Middleware requiring `web.api_key` on `/api/*` and `/ws` when one is configured
+///
+/// The key comes as `Authorization: Bearer`, `X-API-Key`, or (for `/ws`, since
+/// browsers cannot set headers on a WebSocket) an `api_key` query parameter.
+/// The admin token is accepted as well, so admin calls need only one header.
+async fn require_api_key(State(state): State<AppState>, request: axum::extract::Request, next: Next) -> Response {
+    let web = state.config.web.as_ref();
+    let Some(expected) = web.and_then(|w| w.api_key.as_deref()).filter(|k| !k.is_empty()) else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if !(path.starts_with("/api/") || path == "/ws") {
+        return next.run(request).await;
+    }
+    
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let api_key_header = headers.get("x-api-key").and_then(|value| value.to_str().ok());
+    let query = axum::extract::Query::<std::collections::HashMap<String, String>>::try_from_uri(request.uri());
+    let query_key = query.as_ref().ok().filter(|_| path == "/ws").and_then(|q| q.get("api_key")).map(String::as_str);
+    let admin_token = web.and_then(|w| w.admin_token.as_deref()).filter(|t| !t.is_empty());
+    
+    let authorized = [bearer, api_key_header, query_key].into_iter().flatten().any(|provided| {
+        constant_time_eq(provided.as_bytes(), expected.as_bytes())
+            || admin_token.is_some_and(|token| constant_time_eq(provided.as_bytes(), token.as_bytes()))
+    });
+    if authorized {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], "API key required").into_response()
+    }
+}
+
+/// Compare secrets without leaking where they differ through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
 }
 
 /// Device summary for API
@@ -42,11 +227,60 @@ pub struct TemperatureUpdate {
 pub struct DeviceSummary {
     pub device_address: String,
     pub device_name: String,
+    /// Name to show on cards: "Probe 2 (Red)" for multi-probe sets, otherwise the BLE name
+    pub display_name: String,
+    pub serial_number: Option<String>,
+    pub probe_id: Option<u8>,
+    pub probe_color: Option<ProbeColor>,
+    pub firmware_version: Option<String>,
+    /// From the Device Information Service, when the probe has one
+    pub hardware_revision: Option<String>,
+    pub manufacturer: Option<String>,
     pub brand: String,
     pub model: String,
     pub sensor_count: i64,
     pub last_seen: DateTime<Utc>,
     pub latest_reading: Option<ReadingSummary>,
+    /// Sensor whose value is the headline number in compact views
+    pub primary_sensor_index: usize,
+    pub primary_temperature: Option<f32>,
+    /// Latest value of every sensor, one entry per sensor index
+    pub sensors: Vec<SensorSummary>,
+    /// Frames from this probe with impossible readings (a rising count means a flaky probe); short or unknown packets are not counted
+    pub rejected_frames: i64,
+    /// Unit of every temperature in this summary
+    pub unit: TemperatureUnit,
+    /// Date of the newest battery note
+    pub last_battery_change: Option<DateTime<Utc>>,
+    /// Date of the newest calibration note
+    pub last_calibration: Option<DateTime<Utc>>,
+    /// Last calibration is older than `maintenance.calibration_reminder_days`
+    pub calibration_due: bool,
+    /// A sensor went above the probe's rated internal maximum; stays until cleared
+    pub damage_warning: Option<DamageWarning>,
+    /// Target of the running cook, else the device's own target
+    pub target_temp: Option<f32>,
+    /// Sensor `target_temp` applies to (a cook's target is on the primary sensor)
+    pub target_sensor_index: Option<usize>,
+}
+
+/// Possible probe damage from over-temperature
+#[derive(Debug, Serialize)]
+pub struct DamageWarning {
+    /// First reading above the rating
+    pub since: DateTime<Utc>,
+    /// Hottest reading since, in the summary's unit
+    pub peak: f32,
+}
+
+/// Latest value of one sensor on a device
+#[derive(Debug, Serialize)]
+pub struct SensorSummary {
+    pub index: usize,
+    pub role: SensorRole,
+    pub label: String,
+    pub temperature: Option<f32>,
+    pub timestamp: Option<DateTime<Utc>>,
 }
 
 /// Reading summary for API
@@ -57,6 +291,40 @@ pub struct ReadingSummary {
     pub ambient_temp: Option<f32>,
     pub battery_level: Option<u8>,
     pub signal_strength: i16,
+    pub source: ReadingSource,
+    pub unit: TemperatureUnit,
+}
+
+impl ReadingSummary {
+    fn new(reading: &ReadingRecord, unit: TemperatureUnit) -> Self {
+        Self {
+            timestamp: reading.timestamp,
+            temperature: reading.temperature.in_unit(unit),
+            ambient_temp: reading.ambient_temp.map(|t| t.in_unit(unit)),
+            battery_level: reading.battery_level,
+            signal_strength: reading.signal_strength,
+            source: reading.source,
+            unit,
+        }
+    }
+}
+
+/// Parse a comma separated `source` filter; None keeps readings from every source
+fn parse_source_filter(filter: Option<&str>) -> Result<Option<Vec<ReadingSource>>, String> {
+    let Some(filter) = filter else {
+        return Ok(None);
+    };
+    filter
+        .split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(|name| {
+            ReadingSource::parse(name).ok_or_else(|| {
+                let expected: Vec<&str> = ReadingSource::ALL.iter().map(ReadingSource::as_str).collect();
+                format!("Unknown source '{}' (expected {})", name.trim(), expected.join(", "))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
 }
 
 /// Historical data query parameters
@@ -64,37 +332,567 @@ pub struct ReadingSummary {
 pub struct HistoryQuery {
     #[serde(default = "default_hours")]
     pub hours: u32,
+    /// Extra sections to embed, comma separated: `target`, `annotations`, `bands`
+    pub include: Option<String>,
+    /// Meat type whose USDA minimum `bands` marks
+    pub meat: Option<String>,
+    /// Only readings from these sources, comma separated (e.g. `ble,import`)
+    pub source: Option<String>,
+    /// Interpolate each sensor onto a grid this many seconds apart (history only)
+    pub resample: Option<u32>,
+    /// Longest gap between readings, in seconds, that `resample` interpolates across
+    pub max_gap: Option<u32>,
+}
+
+/// Reading export query parameters
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// One device; every device when omitted
+    pub device: Option<String>,
+    /// RFC 3339; 24 hours before `end` when omitted
+    pub start: Option<DateTime<Utc>>,
+    /// RFC 3339; now when omitted
+    pub end: Option<DateTime<Utc>>,
+    /// `csv` (default), `json` or `ndjson`
+    pub format: Option<String>,
+}
+
+/// Device list query parameters
+#[derive(Debug, Deserialize)]
+pub struct DeviceListQuery {
+    /// Only devices of this brand family: a brand prefix, any case (e.g. `meater`, `meatstickv`)
+    pub brand: Option<String>,
+    /// `name`, `last_seen` (default) or `first_seen`
+    #[serde(default)]
+    pub sort: DeviceSort,
+}
+
+/// A sensor's temperature interpolated onto the `resample` grid
+#[derive(Debug, Serialize)]
+pub struct ResampledSummary {
+    pub timestamp: DateTime<Utc>,
+    pub sensor_index: i64,
+    pub temperature: f32,
+    pub ambient_temp: Option<f32>,
+    pub unit: TemperatureUnit,
+}
+
+/// Stored readings, or readings resampled onto a grid
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum HistoryReadings {
+    Stored(Vec<ReadingSummary>),
+    Resampled(Vec<ResampledSummary>),
+}
+
+/// History with the sections requested via `include`
+#[derive(Debug, Serialize)]
+pub struct HistoryResponse {
+    pub readings: HistoryReadings,
+    /// Present when requested; null if no cook in the window has a target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<Option<TargetLine>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bands: Option<Vec<food_safety::ChartBand>>,
+}
+
+/// Horizontal target line for charts, from the latest cook in the window
+#[derive(Debug, Serialize)]
+pub struct TargetLine {
+    pub temperature: f32,
+    pub unit: TemperatureUnit,
+    pub cook_id: i64,
+    pub cook_name: String,
+}
+
+/// Point-in-time chart marker
+#[derive(Debug, Serialize)]
+pub struct Annotation {
+    pub timestamp: DateTime<Utc>,
+    pub kind: AnnotationKind,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationKind {
+    CookStarted,
+    CookEnded,
 }
 
 fn default_hours() -> u32 {
     24
 }
 
-/// Start the web server
+/// Most devices one comparison may overlay
+const MAX_COMPARE_DEVICES: usize = 8;
+
+/// Most points per device in the history sent on WebSocket connect
+const CONNECT_HISTORY_POINTS: u32 = 120;
+
+/// Most grid points per sensor a resampled history may have
+const MAX_RESAMPLE_POINTS: u64 = 10_000;
+
+/// Cross-device comparison query parameters
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    /// Device addresses, comma separated
+    pub devices: String,
+    #[serde(default = "default_hours")]
+    pub hours: u32,
+    /// Sensor to compare; each device's primary sensor when omitted
+    pub sensor: Option<usize>,
+    /// Average each series into buckets of this many seconds, on a grid shared by all devices
+    pub step_secs: Option<u32>,
+}
+
+/// One device's curve in a comparison
+#[derive(Debug, Serialize)]
+pub struct CompareSeries {
+    pub device_address: String,
+    pub device_name: String,
+    pub sensor_index: usize,
+    pub points: Vec<ComparePoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ComparePoint {
+    pub timestamp: DateTime<Utc>,
+    pub temperature: f32,
+}
+
+/// Series of several devices over the same window, for overlaying
+///
+/// Without `step_secs` every series keeps its own sample times; with it,
+/// points fall on `start + n * step_secs` for all devices and empty buckets are omitted.
+#[derive(Debug, Serialize)]
+pub struct CompareResponse {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub step_secs: Option<u32>,
+    pub unit: TemperatureUnit,
+    pub series: Vec<CompareSeries>,
+}
+
+/// Readings considered for stall detection; long enough for a brisket stall
+const ANALYTICS_LOOKBACK_HOURS: i64 = 12;
+
+/// Device analytics query parameters
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    /// Minutes of readings the rate of change is fitted over
+    #[serde(default = "default_rate_window_mins")]
+    pub window_mins: u32,
+    /// Only readings from these sources, comma separated, e.g. `ble` to leave out cloud rows
+    pub source: Option<String>,
+}
+
+fn default_rate_window_mins() -> u32 {
+    30
+}
+
+/// Rate of change and stall status of a device's primary sensor
+#[derive(Debug, Serialize)]
+pub struct DeviceAnalytics {
+    pub sensor_index: usize,
+    /// Degrees (display unit) per hour over the last `window_mins`
+    pub rate_per_hour: Option<f32>,
+    pub window_mins: u32,
+    pub stalled: bool,
+    pub stall_started_at: Option<DateTime<Utc>>,
+    pub stall_duration_mins: Option<i64>,
+    pub unit: TemperatureUnit,
+}
+
+/// Device stats query parameters
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    #[serde(default = "default_hours")]
+    pub hours: u32,
+    /// Threshold (display unit) to report time at or above; needs advanced analytics
+    pub above: Option<f32>,
+}
+
+/// Device stats over the last `hours`, temperatures in the display unit
+#[derive(Debug, Serialize)]
+pub struct DeviceStatsResponse {
+    pub device_address: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub reading_count: i64,
+    pub min_temp: Option<f32>,
+    pub max_temp: Option<f32>,
+    pub avg_temp: Option<f32>,
+    pub min_ambient: Option<f32>,
+    pub max_ambient: Option<f32>,
+    pub first_reading_at: Option<DateTime<Utc>>,
+    pub last_reading_at: Option<DateTime<Utc>>,
+    pub above: Option<f32>,
+    pub secs_above: Option<i64>,
+    pub unit: TemperatureUnit,
+}
+
+impl DeviceStatsResponse {
+    fn new(stats: DeviceStats, above: Option<f32>, unit: TemperatureUnit) -> Self {
+        Self {
+            device_address: stats.device_address,
+            start: stats.start,
+            end: stats.end,
+            reading_count: stats.reading_count,
+            min_temp: stats.min_temp.map(|t| t.in_unit(unit)),
+            max_temp: stats.max_temp.map(|t| t.in_unit(unit)),
+            avg_temp: stats.avg_temp.map(|t| t.in_unit(unit)),
+            min_ambient: stats.min_ambient.map(|t| t.in_unit(unit)),
+            max_ambient: stats.max_ambient.map(|t| t.in_unit(unit)),
+            first_reading_at: stats.first_reading_at,
+            last_reading_at: stats.last_reading_at,
+            above,
+            secs_above: stats.secs_above,
+            unit,
+        }
+    }
+}
+
+/// Time-to-target query parameters
+#[derive(Debug, Deserialize)]
+pub struct EtaQuery {
+    /// Target internal temperature in the configured display unit
+    pub target: f32,
+    /// Meat being cooked, to estimate carryover
+    pub meat: Option<String>,
+    /// Carryover degrees (display unit), overriding the meat's estimate
+    pub carryover: Option<f32>,
+}
+
+/// When a device's primary sensor is expected to reach a target
+#[derive(Debug, Serialize)]
+pub struct DeviceEta {
+    pub sensor_index: usize,
+    pub target: f32,
+    /// Expected rise after pulling the meat (0 without a meat or carryover)
+    pub carryover: f32,
+    /// Temperature to pull the meat at, `target - carryover`; the estimate counts down to this
+    pub remove_at: f32,
+    pub status: analytics::EtaStatus,
+    /// Expected time the removal temperature is reached (the last reading's time once reached)
+    pub eta: Option<DateTime<Utc>>,
+    pub seconds_remaining: Option<i64>,
+    /// Smoothed climb in degrees (display unit) per hour
+    pub rate_per_hour: Option<f32>,
+    /// 0-1, from how much the readings scatter around the fitted slope
+    pub confidence: f32,
+    /// Why no estimate could be made
+    pub reason: Option<&'static str>,
+    pub unit: TemperatureUnit,
+}
+
+/// Request body for starting a cook session
+#[derive(Debug, Deserialize)]
+pub struct StartCookRequest {
+    pub name: String,
+    /// Target in the configured display unit
+    pub target_temp: Option<f32>,
+    /// Pit temperature the smoker is run at, in the display unit; the fuel estimate measures against it
+    pub pit_setpoint: Option<f32>,
+}
+
+/// Request body for `PUT /api/devices/:address/cook/target`
+#[derive(Debug, Deserialize)]
+pub struct CookTargetRequest {
+    /// New target in the configured display unit; null clears it
+    pub target_temp: Option<f32>,
+}
+
+/// Cook session with temperatures in the display unit
+#[derive(Debug, Serialize)]
+pub struct CookSessionResponse {
+    pub id: i64,
+    pub device_address: String,
+    pub name: String,
+    pub target_temp: Option<f32>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub unit: TemperatureUnit,
+}
+
+impl CookSessionResponse {
+    fn new(session: CookSession, unit: TemperatureUnit) -> Self {
+        Self {
+            id: session.id,
+            device_address: session.device_address,
+            name: session.name,
+            target_temp: session.target_temp.map(|t| t.in_unit(unit)),
+            started_at: session.started_at,
+            ended_at: session.ended_at,
+            unit,
+        }
+    }
+}
+
+/// Cook stats with temperatures in the display unit
+#[derive(Debug, Serialize)]
+pub struct CookStatsResponse {
+    #[serde(flatten)]
+    pub session: CookSessionResponse,
+    pub reading_count: i64,
+    pub min_temp: Option<f32>,
+    pub max_temp: Option<f32>,
+    pub avg_temp: Option<f32>,
+    pub duration_secs: i64,
+    /// `duration_secs` comes from the monotonic clock because the wall clock was reset mid-session
+    pub duration_estimated: bool,
+    pub time_to_target_secs: Option<i64>,
+    pub danger_zone_secs: i64,
+    pub fuel: Option<FuelEstimate>,
+}
+
+impl CookStatsResponse {
+    fn new(stats: CookStats, unit: TemperatureUnit) -> Self {
+        Self {
+            session: CookSessionResponse::new(stats.session, unit),
+            reading_count: stats.reading_count,
+            min_temp: stats.min_temp.map(|t| t.in_unit(unit)),
+            max_temp: stats.max_temp.map(|t| t.in_unit(unit)),
+            avg_temp: stats.avg_temp.map(|t| t.in_unit(unit)),
+            duration_secs: stats.duration_secs,
+            duration_estimated: stats.duration_estimated,
+            time_to_target_secs: stats.time_to_target_secs,
+            danger_zone_secs: stats.danger_zone_secs,
+            fuel: stats.fuel,
+        }
+    }
+}
+
+/// Request body for choosing a device's headline sensor
+#[derive(Debug, Deserialize)]
+pub struct PrimarySensorRequest {
+    /// Sensor index, or null to go back to the brand's internal sensor
+    pub sensor_index: Option<usize>,
+}
+
+/// Request body for `PUT /api/devices/:address/target`
+#[derive(Debug, Deserialize)]
+pub struct DeviceTargetRequest {
+    /// Sensor the target applies to; defaults to the primary sensor
+    pub sensor_index: Option<usize>,
+    /// Target in the configured unit, or null to clear it
+    pub target: Option<f32>,
+}
+
+/// Longest name `PUT /api/devices/:address/name` accepts
+const MAX_DEVICE_NAME_CHARS: usize = 64;
+
+/// Request body for `PUT /api/devices/:address/name`
+#[derive(Debug, Deserialize)]
+pub struct RenameDeviceRequest {
+    /// New name; null or blank goes back to the detected name
+    pub name: Option<String>,
+}
+
+/// Result of `DELETE /api/devices/:address`
+#[derive(Debug, Serialize)]
+pub struct DeletedDevice {
+    pub device_address: String,
+    pub deleted_readings: u64,
+}
+
+/// Everything the dashboard's settings drawer shows for one device
+#[derive(Debug, Serialize)]
+pub struct DeviceSettings {
+    pub device_address: String,
+    pub display_name: String,
+    /// Name the user gave the device, if any
+    pub alias: Option<String>,
+    pub sensor_count: usize,
+    pub primary_sensor_index: usize,
+    /// Running cook; its target is the device's target
+    pub cook: Option<CookSessionResponse>,
+    /// The device's alert rules; null without the alerts license feature
+    pub alerts: Option<Vec<AlertRuleResponse>>,
+    pub unit: TemperatureUnit,
+}
+
+/// Longest snooze `POST /api/alerts/:id/snooze` accepts
+const MAX_SNOOZE_MINUTES: u32 = 24 * 60;
+
+/// Request body for `POST /api/alerts/:id/snooze`
+#[derive(Debug, Deserialize)]
+pub struct SnoozeRequest {
+    /// How long to hold the rule quiet; 0 ends a snooze
+    pub minutes: u32,
+}
+
+/// Device notes query parameters
+#[derive(Debug, Deserialize)]
+pub struct NotesQuery {
+    /// Only notes of this category
+    pub category: Option<NoteCategory>,
+}
+
+/// Request body for adding or editing a device note
+#[derive(Debug, Deserialize)]
+pub struct DeviceNoteRequest {
+    pub category: NoteCategory,
+    pub text: String,
+    /// When the work was done; defaults to now for new notes and is kept on edits
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Request body for `POST /api/alerts`
+#[derive(Debug, Deserialize)]
+pub struct AlertRuleRequest {
+    pub device_address: String,
+    #[serde(default)]
+    pub sensor_index: usize,
+    pub kind: AlertKindName,
+    /// Threshold in the configured display unit (absolute kinds)
+    #[serde(default)]
+    pub threshold: Option<f32>,
+    /// Degrees (display unit) below the cook target, for approaching_target
+    #[serde(default)]
+    pub offset: Option<f32>,
+    /// Degrees (display unit) the value must move back before the rule fires again
+    #[serde(default)]
+    pub hysteresis: f32,
+    /// Meat being cooked; an above-target rule fires early by its carryover estimate
+    #[serde(default)]
+    pub meat: Option<String>,
+    /// Carryover degrees (display unit), overriding the meat's estimate
+    #[serde(default)]
+    pub carryover: Option<f32>,
+}
+
+/// Alert rule with temperatures in the display unit
+#[derive(Debug, Serialize)]
+pub struct AlertRuleResponse {
+    pub id: i64,
+    pub device_address: String,
+    pub sensor_index: usize,
+    pub kind: AlertKindName,
+    /// Fixed threshold (a percentage for battery_below); null for kinds relative to the cook target
+    pub threshold: Option<f32>,
+    /// Degrees below the cook target an approaching_target rule fires
+    pub offset: Option<f32>,
+    pub hysteresis: f32,
+    /// Degrees before the threshold the rule fires
+    pub carryover: f32,
+    /// When a snooze ends; null when the rule is not snoozed
+    pub snoozed_until: Option<DateTime<Utc>>,
+    pub unit: TemperatureUnit,
+}
+
+impl AlertRuleResponse {
+    fn new(rule: AlertRule, unit: TemperatureUnit, engine: &AlertEngine) -> Self {
+        Self {
+            snoozed_until: engine.snoozed_until(rule.id, Utc::now()),
+            id: rule.id,
+            device_address: rule.device_address,
+            sensor_index: rule.sensor_index,
+            kind: rule.kind.name(),
+            threshold: match rule.kind {
+                AlertKind::BatteryBelow { percent } => Some(percent as f32),
+                kind => kind.threshold().map(|t| t.in_unit(unit)),
+            },
+            offset: rule.kind.offset().map(|o| unit.delta_from_celsius(o)),
+            hysteresis: unit.delta_from_celsius(rule.hysteresis),
+            carryover: unit.delta_from_celsius(rule.carryover),
+            unit,
+        }
+    }
+}
+
+/// Alert pushed to WebSocket clients, tagged to tell it apart from temperature updates
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AlertMessage {
+    Alert(AlertEvent),
+}
+
+/// Chart band query parameters
+#[derive(Debug, Deserialize)]
+pub struct BandsQuery {
+    pub meat: Option<String>,
+}
+
+/// Chart band response
+#[derive(Debug, Serialize)]
+pub struct BandsResponse {
+    pub unit: String,
+    pub bands: Vec<food_safety::ChartBand>,
+}
+
+/// Start the web server; it stops accepting connections once `shutdown` fires
+#[allow(clippy::too_many_arguments)]
 pub async fn start_server(
     db: Arc<Database>,
-    license: Arc<License>,
-    host: &str,
-    port: u16,
+    license: Arc<LicenseManager>,
+    config: Arc<Config>,
+    ble_health: BleHealth,
+    log_control: Option<Arc<LogControl>>,
+    debug_commands: Option<mpsc::Sender<DebugCommand>>,
+    simulator: Option<mpsc::Sender<SimulatorCommand>>,
+    first_seen: Option<mpsc::Receiver<FirstSeenDevice>>,
+    mut shutdown: broadcast::Receiver<()>,
 ) -> Result<(broadcast::Sender<TemperatureUpdate>, tokio::task::JoinHandle<()>)> {
     let (tx, _rx) = broadcast::channel(100);
     
+    let host = config.web.as_ref().map(|w| w.host.clone()).unwrap_or_else(|| "127.0.0.1".to_string());
+    let port = config.web.as_ref().map(|w| w.port).unwrap_or(8080);
+    
+    // Built on every tier and gated on the license where it is used, so a
+    // Premium key activated while running turns alerts on
+    let engine = Arc::new(AlertEngine::new(db.get_alert_rules().await?));
+    for device in db.get_all_devices().await? {
+        if let Some(target) = db.active_target(&device.device_address).await? {
+            engine.set_target(&device.device_address, Some(target));
+        }
+    }
+    tokio::spawn(crate::alerts::run_alert_engine(engine.clone(), license.clone(), tx.subscribe()));
+    let alerts = Some(engine);
+    
+    let access_log = match config.web.as_ref().and_then(|w| w.access_log_path.as_deref()) {
+        Some(path) => {
+            info!("📝 Writing API access log to {}", path);
+            Some(Arc::new(AccessLog::open(path)?))
+        }
+        None => None,
+    };
+    
     let state = AppState {
         db: db.clone(),
         tx: tx.clone(),
         license: license.clone(),
+        config: config.clone(),
+        ble_health,
+        fanout: Arc::new(Fanout::new(REPLAY_BUFFER_CAPACITY)),
+        log_control,
+        alerts,
+        access_log,
+        debug_commands,
+        simulator,
+        #[cfg(feature = "pit-controller")]
+        pit_controller: config
+            .pit_controller
+            .enabled
+            .then(|| crate::pit_controller::start(&config.pit_controller, config.temperature.display_unit(), &tx)),
     };
+    tokio::spawn(crate::fanout::run_sequencer(state.fanout.clone(), tx.subscribe()));
+    if let Some(first_seen) = first_seen {
+        let default_meat = config.alerts.default_meat.clone();
+        tokio::spawn(crate::alerts::run_bootstrap(
+            db.clone(),
+            state.alerts.clone(),
+            state.license.clone(),
+            state.fanout.clone(),
+            default_meat,
+            first_seen,
+        ));
+    }
     
-    // Build router
-    let app = Router::new()
-        .route("/", get(index_handler))
-        .route("/api/devices", get(list_devices))
-        .route("/api/devices/:address", get(device_details))
-        .route("/api/devices/:address/history", get(device_history))
-        .route("/api/premium/status", get(premium_status))
-        .route("/ws", get(websocket_handler))
-        .nest_service("/static", get_service(ServeDir::new("static")))
-        .with_state(state);
+    let app = router(state);
     
     let addr = format!("{}:{}", host, port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -102,7 +900,11 @@ pub async fn start_server(
     info!("🌐 Web dashboard starting at http://{}", addr);
     
     let handle = tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
+        let serve = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move {
+                let _ = shutdown.recv().await;
+            });
+        if let Err(e) = serve.await {
             error!("Web server error: {}", e);
         }
     });
@@ -110,34 +912,210 @@ pub async fn start_server(
     Ok((tx, handle))
 }
 
+/// Build the application router
+pub fn router(state: AppState) -> Router {
+    let access_log = state.access_log.clone();
+    let developer_mode = state.config.web.as_ref().is_some_and(|w| w.developer_mode);
+    let simulator = state.simulator.is_some();
+    #[cfg(feature = "pit-controller")]
+    let pit_controller = state.pit_controller.is_some();
+    let mut app = Router::new()
+        .route("/", get(index_handler))
+        .route("/assets/line-chart.js", get(line_chart_js))
+        .route("/api/devices", get(list_devices))
+        .route("/api/devices/:address", get(device_details).delete(delete_device))
+        .route("/api/devices/:address/settings", get(device_settings))
+        .route("/api/devices/:address/name", put(rename_device))
+        .route("/api/devices/:address/damage-warning", delete(clear_damage_warning))
+        .route("/api/devices/:address/suggestion", delete(dismiss_suggestion))
+        .route("/api/devices/:address/history", get(device_history))
+        .route("/api/devices/:address/export.csv", get(export_csv))
+        .route("/api/export", get(export_readings))
+        .route("/api/compare", get(compare_devices))
+        .route("/api/devices/:address/cook", post(start_cook).delete(end_cook))
+        .route("/api/devices/:address/cook/target", put(set_cook_target))
+        .route("/api/devices/:address/primary-sensor", put(set_primary_sensor))
+        .route("/api/devices/:address/target", put(set_device_target))
+        .route("/api/devices/:address/smoker", get(get_smoker).put(set_smoker))
+        .route("/api/devices/:address/analytics", get(device_analytics))
+        .route("/api/devices/:address/stats", get(device_stats))
+        .route("/api/devices/:address/eta", get(device_eta))
+        .route("/api/devices/:address/notes", get(list_notes).post(create_note))
+        .route("/api/devices/:address/notes/:id", put(update_note).delete(delete_note))
+        .route("/api/alerts", get(list_alerts).post(create_alert))
+        .route("/api/alerts/:id", delete(delete_alert))
+        .route("/api/alerts/:id/snooze", post(snooze_alert))
+        .route("/api/chart/bands", get(chart_bands))
+        .route("/api/premium/status", get(premium_status))
+        .route("/api/premium/activate", post(activate_premium))
+        .route("/api/health", get(health))
+        .route("/api/version", get(version))
+        .route("/api/admin/log-level", get(log_level_status).post(start_log_burst).delete(end_log_burst))
+        .route("/api/maintenance/recompute", post(recompute_devices))
+        .route("/ws", get(websocket_handler))
+        .nest_service("/static", get_service(ServeDir::new("static")));
+    // Raw BLE access only exists in developer mode; otherwise the paths are plain 404s
+    if developer_mode {
+        app = app
+            .route("/api/devices/:address/debug/write", post(debug_write))
+            .route("/api/devices/:address/debug/read", post(debug_read));
+    }
+    // Likewise the simulator controls only exist while it runs
+    if simulator {
+        app = app
+            .route("/api/admin/simulator", get(list_simulated_devices))
+            .route("/api/admin/simulator/devices/:id/scenario", post(set_simulator_scenario));
+    }
+    #[cfg(feature = "pit-controller")]
+    if pit_controller {
+        app = app.route("/api/pit-controller", get(pit_controller_status));
+    }
+    let max_concurrent = state.config.web.as_ref().map(|w| w.max_concurrent_requests);
+    let mut app = app
+        .layer(axum::middleware::from_fn_with_state(state.clone(), require_api_key))
+        .with_state(state);
+    if let Some(max) = max_concurrent {
+        app = limit_concurrency(app, max);
+    }
+    
+    match access_log {
+        Some(log) => app.layer(axum::middleware::from_fn_with_state(log, log_access)),
+        None => app,
+    }
+}
+
+/// Refuse requests beyond `max` in flight with 503 rather than queueing them
+///
+/// A request holds its slot until the response is ready, so an upgraded
+/// WebSocket stops counting once the handshake is answered.
+fn limit_concurrency(app: Router, max: usize) -> Router {
+    app.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(|_: BoxError| async {
+                (StatusCode::SERVICE_UNAVAILABLE, [(header::RETRY_AFTER, "1")], "Server busy, retry shortly")
+            }))
+            .load_shed()
+            .layer(GlobalConcurrencyLimitLayer::new(max)),
+    )
+}
+
+/// Cache-busting query for embedded assets, so an upgrade never runs a stale script
+const ASSET_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Dashboard HTML with asset URLs versioned
+static INDEX_PAGE: Lazy<String> = Lazy::new(|| INDEX_HTML.replace("{{ASSET_VERSION}}", ASSET_VERSION));
+
 /// Serve the main dashboard HTML
 async fn index_handler() -> Html<&'static str> {
-    Html(INDEX_HTML)
+    Html(INDEX_PAGE.as_str())
+}
+
+/// Serve the dashboard's chart script (versioned URL, so it can be cached for good)
+async fn line_chart_js() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/javascript; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        LINE_CHART_JS,
+    )
+}
+
+/// Build the API summary for a device, including per-sensor latest values
+///
+/// `calibration_reminder_days` of 0 never marks a calibration as due.
+async fn device_summary(
+    db: &Database,
+    device: DeviceRecord,
+    unit: TemperatureUnit,
+    calibration_reminder_days: u32,
+) -> Result<DeviceSummary> {
+    let latest = db.get_latest_reading(&device.device_address).await.ok();
+    let per_sensor = db.get_latest_per_sensor(&device.device_address).await?;
+    
+    let sensor_count = device.sensor_count.max(0) as usize;
+    let highest_seen = per_sensor.iter().map(|r| r.sensor_index as usize + 1).max().unwrap_or(0);
+    let sensors: Vec<SensorSummary> = (0..sensor_count.max(highest_seen))
+        .map(|index| {
+            let (role, label) = sensor_layout(sensor_count, index);
+            let reading = per_sensor.iter().find(|r| r.sensor_index as usize == index);
+            SensorSummary {
+                index,
+                role,
+                label,
+                temperature: reading.map(|r| r.temperature.in_unit(unit)),
+                timestamp: reading.map(|r| r.timestamp),
+            }
+        })
+        .collect();
+    
+    let rejected_frames = db.get_rejected_frames(&device.device_address).await?;
+    let primary_sensor_index = db
+        .get_primary_sensor(&device.device_address)
+        .await?
+        .unwrap_or_else(|| default_primary_sensor(sensor_count));
+    let primary_temperature = sensors
+        .get(primary_sensor_index)
+        .and_then(|s| s.temperature);
+    
+    let last_battery_change = db.latest_note_time(&device.device_address, NoteCategory::Battery).await?;
+    let last_calibration = db.latest_note_time(&device.device_address, NoteCategory::Calibration).await?;
+    let calibration_due = calibration_reminder_days > 0
+        && last_calibration
+            .is_some_and(|at| maintenance::calibration_stale(at, calibration_reminder_days, Utc::now()));
+    
+    let cook_target = db.get_open_cook(&device.device_address).await?.and_then(|cook| cook.target_temp);
+    let target = match (cook_target, device.target_temp) {
+        (Some(temperature), _) => Some((primary_sensor_index, temperature)),
+        (None, Some(temperature)) => Some((device.target_sensor_index.unwrap_or(0).max(0) as usize, temperature)),
+        (None, None) => None,
+    };
+    
+    Ok(DeviceSummary {
+        display_name: device.display_name(),
+        probe_id: device.probe_id.map(|id| id as u8),
+        probe_color: device.probe_color.as_deref().and_then(ProbeColor::parse),
+        serial_number: device.serial_number,
+        firmware_version: device.firmware_version,
+        hardware_revision: device.hardware_revision,
+        manufacturer: device.manufacturer,
+        device_address: device.device_address,
+        device_name: device.device_name,
+        brand: device.brand,
+        model: device.model,
+        sensor_count: device.sensor_count,
+        last_seen: device.last_seen,
+        latest_reading: latest.map(|r| ReadingSummary::new(&r, unit)),
+        primary_sensor_index,
+        primary_temperature,
+        sensors,
+        rejected_frames,
+        unit,
+        last_battery_change,
+        last_calibration,
+        calibration_due,
+        damage_warning: device
+            .damage_risk_at
+            .zip(device.damage_risk_peak)
+            .map(|(since, peak)| DamageWarning { since, peak: peak.in_unit(unit) }),
+        target_temp: target.map(|(_, temperature)| temperature.in_unit(unit)),
+        target_sensor_index: target.map(|(index, _)| index),
+    })
 }
 
 /// List all devices
-async fn list_devices(State(state): State<AppState>) -> Result<Json<Vec<DeviceSummary>>, AppError> {
-    let devices = state.db.get_all_devices().await?;
+async fn list_devices(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    axum::extract::Query(query): axum::extract::Query<DeviceListQuery>,
+) -> Result<Json<Vec<DeviceSummary>>, AppError> {
+    let brand = query.brand.as_deref().filter(|b| !b.trim().is_empty());
+    let devices = state.db.get_devices(brand, query.sort).await?;
+    let reminder_days = state.config.maintenance.calibration_reminder_days;
     
     let mut summaries = Vec::new();
     for device in devices {
-        let latest = state.db.get_latest_reading(&device.device_address).await.ok();
-        
-        summaries.push(DeviceSummary {
-            device_address: device.device_address.clone(),
-            device_name: device.device_name,
-            brand: device.brand,
-            model: device.model,
-            sensor_count: device.sensor_count,
-            last_seen: device.last_seen,
-            latest_reading: latest.map(|r| ReadingSummary {
-                timestamp: r.timestamp,
-                temperature: r.temperature,
-                ambient_temp: r.ambient_temp,
-                battery_level: r.battery_level,
-                signal_strength: r.signal_strength,
-            }),
-        });
+        summaries.push(device_summary(&state.db, device, unit, reminder_days).await?);
     }
     
     Ok(Json(summaries))
@@ -146,574 +1124,4317 @@ async fn list_devices(State(state): State<AppState>) -> Result<Json<Vec<DeviceSu
 /// Get details for a specific device
 async fn device_details(
     State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
     Path(address): Path<String>,
 ) -> Result<Json<DeviceSummary>, AppError> {
     let device = state.db.get_device(&address).await?;
-    let latest = state.db.get_latest_reading(&address).await.ok();
+    let reminder_days = state.config.maintenance.calibration_reminder_days;
     
-    Ok(Json(DeviceSummary {
-        device_address: device.device_address.clone(),
-        device_name: device.device_name,
-        brand: device.brand,
-        model: device.model,
-        sensor_count: device.sensor_count,
-        last_seen: device.last_seen,
-        latest_reading: latest.map(|r| ReadingSummary {
-            timestamp: r.timestamp,
-            temperature: r.temperature,
-            ambient_temp: r.ambient_temp,
-            battery_level: r.battery_level,
-            signal_strength: r.signal_strength,
-        }),
-    }))
+    Ok(Json(device_summary(&state.db, device, unit, reminder_days).await?))
 }
 
 /// Get historical readings for a device
+///
+/// Returns a plain array of readings, or a `HistoryResponse` object when
+/// `include` asks for the target line and/or annotation markers. With
+/// `resample` the readings are interpolated per sensor onto a fixed grid.
 async fn device_history(
     State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
     Path(address): Path<String>,
     axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
-) -> Result<Json<Vec<ReadingSummary>>, AppError> {
-    let cutoff = Utc::now() - chrono::Duration::hours(query.hours as i64);
-    let readings = state.db.get_readings_since(&address, cutoff).await?;
+) -> Result<Response, AppError> {
+    let (mut want_target, mut want_annotations, mut want_bands) = (false, false, false);
+    for section in query.include.iter().flat_map(|i| i.split(',')).map(str::trim) {
+        match section {
+            "target" => want_target = true,
+            "annotations" => want_annotations = true,
+            "bands" => want_bands = true,
+            "" => {}
+            other => {
+                let message = format!("Unknown include '{}' (expected target, annotations, bands)", other);
+                return Ok((StatusCode::BAD_REQUEST, message).into_response());
+            }
+        }
+    }
+    
+    let sources = match parse_source_filter(query.source.as_deref()) {
+        Ok(sources) => sources,
+        Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+    };
+    
+    match query.resample {
+        Some(0) => return Ok((StatusCode::BAD_REQUEST, "resample must be above 0").into_response()),
+        Some(step) if query.hours as u64 * 3600 / step as u64 > MAX_RESAMPLE_POINTS => {
+            let message = format!("resample would give more than {} points per sensor; use a larger step", MAX_RESAMPLE_POINTS);
+            return Ok((StatusCode::BAD_REQUEST, message).into_response());
+        }
+        None if query.max_gap.is_some() => {
+            return Ok((StatusCode::BAD_REQUEST, "max_gap only applies with resample").into_response());
+        }
+        _ => {}
+    }
+    
+    let now = Utc::now();
+    let cutoff = now - chrono::Duration::hours(query.hours as i64);
+    let mut readings = state.db.get_readings_since(&address, cutoff).await?;
+    readings.retain(|r| sources.as_ref().is_none_or(|sources| sources.contains(&r.source)));
+    
+    let readings = match query.resample {
+        Some(step) => {
+            let max_gap = query.max_gap.map_or(analytics::DEFAULT_MAX_INTERPOLATION_GAP_SECS, i64::from);
+            HistoryReadings::Resampled(resample_history(&readings, step, max_gap, unit))
+        }
+        None => HistoryReadings::Stored(readings.iter().map(|r| ReadingSummary::new(r, unit)).collect()),
+    };
+    
+    if !want_target && !want_annotations && !want_bands {
+        return Ok(Json(readings).into_response());
+    }
+    
+    let cooks = state.db.get_cooks_in_range(&address, cutoff, now).await?;
+    
+    let target = want_target.then(|| {
+        cooks.iter().rev().find_map(|cook| {
+            cook.target_temp.map(|temperature| TargetLine {
+                temperature: temperature.in_unit(unit),
+                unit,
+                cook_id: cook.id,
+                cook_name: cook.name.clone(),
+            })
+        })
+    });
+    
+    let annotations = want_annotations.then(|| {
+        let mut markers = Vec::new();
+        for cook in &cooks {
+            if cook.started_at >= cutoff {
+                markers.push(Annotation {
+                    timestamp: cook.started_at,
+                    kind: AnnotationKind::CookStarted,
+                    label: format!("Started {}", cook.name),
+                });
+            }
+            if let Some(ended_at) = cook.ended_at {
+                markers.push(Annotation {
+                    timestamp: ended_at,
+                    kind: AnnotationKind::CookEnded,
+                    label: format!("Ended {}", cook.name),
+                });
+            }
+        }
+        markers.sort_by_key(|m| m.timestamp);
+        markers
+    });
+    let bands = want_bands.then(|| food_safety::chart_bands(unit.as_str(), query.meat.as_deref()));
     
-    let summaries: Vec<ReadingSummary> = readings
+    Ok(Json(HistoryResponse {
+        readings,
+        target,
+        annotations,
+        bands,
+    })
+    .into_response())
+}
+
+/// Interpolate each sensor's readings onto a `step_secs` grid, ordered by time then sensor
+fn resample_history(readings: &[ReadingRecord], step_secs: u32, max_gap_secs: i64, unit: TemperatureUnit) -> Vec<ResampledSummary> {
+    let mut by_sensor: std::collections::BTreeMap<i64, Vec<ReadingRecord>> = Default::default();
+    for reading in readings {
+        by_sensor.entry(reading.sensor_index).or_default().push(reading.clone());
+    }
+    let step = chrono::Duration::seconds(step_secs as i64);
+    let max_gap = chrono::Duration::seconds(max_gap_secs);
+    let mut points: Vec<ResampledSummary> = by_sensor
         .into_iter()
-        .map(|r| ReadingSummary {
-            timestamp: r.timestamp,
-            temperature: r.temperature,
-            ambient_temp: r.ambient_temp,
-            battery_level: r.battery_level,
-            signal_strength: r.signal_strength,
+        .flat_map(|(sensor_index, readings)| {
+            analytics::interpolate_readings(&readings, step, max_gap)
+                .into_iter()
+                .map(move |point| ResampledSummary {
+                    timestamp: point.timestamp,
+                    sensor_index,
+                    temperature: point.temperature.in_unit(unit),
+                    ambient_temp: point.ambient_temp.map(|t| t.in_unit(unit)),
+                    unit,
+                })
         })
         .collect();
+    points.sort_by_key(|p| (p.timestamp, p.sensor_index));
+    points
+}
+
+/// Overlay the history of several devices
+async fn compare_devices(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    axum::extract::Query(query): axum::extract::Query<CompareQuery>,
+) -> Result<Response, AppError> {
+    let addresses: Vec<&str> = query.devices.split(',').map(str::trim).filter(|a| !a.is_empty()).collect();
+    if addresses.is_empty() || addresses.len() > MAX_COMPARE_DEVICES {
+        let message = format!("devices must list 1 to {} device addresses", MAX_COMPARE_DEVICES);
+        return Ok((StatusCode::BAD_REQUEST, message).into_response());
+    }
+    if query.step_secs == Some(0) {
+        return Ok((StatusCode::BAD_REQUEST, "step_secs must be above 0").into_response());
+    }
     
-    Ok(Json(summaries))
+    let devices = state.db.get_all_devices().await?;
+    let end = Utc::now();
+    let start = end - chrono::Duration::hours(query.hours as i64);
+    
+    let mut series = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        let Some(device) = devices.iter().find(|d| d.device_address == address) else {
+            return Ok((StatusCode::NOT_FOUND, format!("Unknown device {}", address)).into_response());
+        };
+        let sensor_index = match query.sensor {
+            Some(sensor) => sensor,
+            None => state
+                .db
+                .get_primary_sensor(address)
+                .await?
+                .unwrap_or_else(|| default_primary_sensor(device.sensor_count.max(0) as usize)),
+        };
+        let points = state
+            .db
+            .get_readings_in_range(address, start, end)
+            .await?
+            .into_iter()
+            .filter(|r| r.sensor_index as usize == sensor_index)
+            .map(|r| ComparePoint { timestamp: r.timestamp, temperature: r.temperature.in_unit(unit) })
+            .collect();
+        series.push(CompareSeries {
+            device_address: device.device_address.clone(),
+            device_name: device.device_name.clone(),
+            sensor_index,
+            points: match query.step_secs {
+                Some(step) => resample(points, start, step),
+                None => points,
+            },
+        });
+    }
+    
+    Ok(Json(CompareResponse { start, end, step_secs: query.step_secs, unit, series }).into_response())
 }
 
-/// WebSocket handler for real-time updates
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
+/// Average points into `step_secs` buckets starting at `start`, stamped with each bucket's start
+fn resample(points: Vec<ComparePoint>, start: DateTime<Utc>, step_secs: u32) -> Vec<ComparePoint> {
+    let step = chrono::Duration::seconds(step_secs as i64);
+    let mut buckets: Vec<(i64, f32, u32)> = Vec::new();
+    for point in points {
+        let bucket = (point.timestamp - start).num_seconds().max(0) / step_secs as i64;
+        match buckets.last_mut() {
+            Some((last, sum, count)) if *last == bucket => {
+                *sum += point.temperature;
+                *count += 1;
+            }
+            _ => buckets.push((bucket, point.temperature, 1)),
+        }
+    }
+    buckets
+        .into_iter()
+        .map(|(bucket, sum, count)| ComparePoint {
+            timestamp: start + step * bucket as i32,
+            temperature: sum / count as f32,
+        })
+        .collect()
+}
+
+/// Download a device's readings as CSV, streamed row by row
+async fn export_csv(
     State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    let sources = match parse_source_filter(query.source.as_deref()) {
+        Ok(sources) => sources,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    let cutoff = Utc::now() - chrono::Duration::hours(query.hours as i64);
+    
+    let header_row = tokio_stream::once(Ok(format!("{}\n", export::CSV_HEADER)));
+    let rows = state
+        .db
+        .stream_readings_since(&address, cutoff)
+        .filter(move |row| match (row, &sources) {
+            (Ok(reading), Some(sources)) => sources.contains(&reading.source),
+            _ => true,
+        })
+        .map(move |row| row.map(|reading| format!("{}\n", export::csv_row(&reading, unit))));
+    let body = Body::from_stream(header_row.chain(rows));
+    
+    let disposition = format!(
+        "attachment; filename=\"bbq-{}.csv\"",
+        address.replace(':', "")
+    );
+    
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
 }
 
-/// Handle WebSocket connection
-async fn handle_socket(mut socket: WebSocket, state: AppState) {
-    let mut rx = state.tx.subscribe();
+/// Download readings in a time range as CSV, JSON or NDJSON, streamed as they are read
+async fn export_readings(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    axum::extract::Query(query): axum::extract::Query<ExportQuery>,
+) -> Result<Response, AppError> {
+    let format = match ExportFormat::parse(query.format.as_deref().unwrap_or("csv")) {
+        Ok(format) => format,
+        Err(e) => return Ok((StatusCode::BAD_REQUEST, e.to_string()).into_response()),
+    };
+    let end = query.end.unwrap_or_else(Utc::now);
+    let start = query.start.unwrap_or(end - chrono::Duration::hours(default_hours() as i64));
+    if start > end {
+        return Ok((StatusCode::BAD_REQUEST, "start must not be after end").into_response());
+    }
+    if let Some(address) = &query.device {
+        if state.db.find_device(address).await?.is_none() {
+            return Ok((StatusCode::NOT_FOUND, format!("Unknown device {}", address)).into_response());
+        }
+    }
     
-    debug!("WebSocket client connected");
+    let body = Body::from_stream(state.db.export_readings(query.device.as_deref(), start, end, format, unit));
+    let disposition = format!(
+        "attachment; filename=\"bbq-{}-{}.{}\"",
+        query.device.as_deref().map_or("all".to_string(), |address| address.replace(':', "")),
+        start.format("%Y%m%d"),
+        format.extension()
+    );
     
-    // Send initial device list
-    if let Ok(devices) = state.db.get_all_devices().await {
-        for device in devices {
-            if let Ok(latest) = state.db.get_latest_reading(&device.device_address).await {
-                let update = TemperatureUpdate {
-                    device_address: device.device_address.clone(),
-                    device_name: device.device_name,
-                    timestamp: latest.timestamp,
-                    sensor_index: latest.sensor_index as usize,
-                    temperature: latest.temperature,
-                    ambient_temp: latest.ambient_temp,
-                    battery_level: latest.battery_level,
-                    signal_strength: latest.signal_strength,
-                };
-                
-                if let Ok(json) = serde_json::to_string(&update) {
-                    let _ = socket.send(Message::Text(json)).await;
-                }
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Start a cook session on a device
+async fn start_cook(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    Json(request): Json<StartCookRequest>,
+) -> Result<Response, AppError> {
+    state.db.get_device(&address).await?;
+    
+    if let Some(open) = state.db.get_open_cook(&address).await? {
+        let message = format!("Cook '{}' is already running on {}", open.name, address);
+        return Ok((StatusCode::CONFLICT, message).into_response());
+    }
+    
+    if request.pit_setpoint.is_some_and(|t| !t.is_finite()) {
+        return Ok((StatusCode::BAD_REQUEST, "pit_setpoint must be a number").into_response());
+    }
+    let target = request.target_temp.map(|t| Temperature::from_unit(t, unit));
+    let session = state.db.start_cook(&address, &request.name, target).await?;
+    if let Some(setpoint) = request.pit_setpoint {
+        state.db.set_cook_pit_setpoint(&address, Some(Temperature::from_unit(setpoint, unit))).await?;
+    }
+    info!("🍖 Started cook '{}' on {}", session.name, address);
+    state.fanout.clear_suggestion(&address);
+    if let Some(engine) = &state.alerts {
+        engine.set_target(&address, state.db.active_target(&address).await?);
+    }
+    state.fanout.settings_changed(&address);
+    
+    Ok((StatusCode::CREATED, Json(CookSessionResponse::new(session, unit))).into_response())
+}
+
+/// End the open cook session on a device and return its stats
+async fn end_cook(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+) -> Result<Response, AppError> {
+    match state.db.end_cook(&address).await? {
+        Some(stats) => {
+            info!("🏁 Ended cook '{}' on {}", stats.session.name, address);
+            if let Some(engine) = &state.alerts {
+                engine.set_target(&address, state.db.active_target(&address).await?);
             }
+            state.fanout.settings_changed(&address);
+            Ok(Json(CookStatsResponse::new(stats, unit)).into_response())
         }
+        None => Ok((StatusCode::NOT_FOUND, format!("No cook running on {}", address)).into_response()),
+    }
+}
+
+/// Change the target of the running cook; relative alert rules follow it immediately
+async fn set_cook_target(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    Json(request): Json<CookTargetRequest>,
+) -> Result<Response, AppError> {
+    if request.target_temp.is_some_and(|t| !t.is_finite()) {
+        return Ok((StatusCode::BAD_REQUEST, "target_temp must be a number").into_response());
+    }
+    let target = request.target_temp.map(|t| Temperature::from_unit(t, unit));
+    let Some(session) = state.db.set_cook_target(&address, target).await? else {
+        return Ok((StatusCode::NOT_FOUND, format!("No cook running on {}", address)).into_response());
+    };
+    info!("🎯 Cook '{}' on {} target set to {:?}", session.name, address, session.target_temp);
+    if let Some(engine) = &state.alerts {
+        engine.set_target(&address, state.db.active_target(&address).await?);
     }
+    state.fanout.settings_changed(&address);
     
-    // Stream real-time updates
-    while let Ok(update) = rx.recv().await {
-        if let Ok(json) = serde_json::to_string(&update) {
-            if socket.send(Message::Text(json)).await.is_err() {
-                break;
-            }
+    Ok(Json(CookSessionResponse::new(session, unit)).into_response())
+}
+
+/// Choose which sensor drives the headline temperature and cook targets
+async fn set_primary_sensor(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    Json(request): Json<PrimarySensorRequest>,
+) -> Result<Response, AppError> {
+    let device = state.db.get_device(&address).await?;
+    
+    if let Some(index) = request.sensor_index {
+        let sensor_count = device.sensor_count.max(0) as usize;
+        if index >= sensor_count {
+            let message = format!("{} has {} sensors, no sensor {}", address, sensor_count, index);
+            return Ok((StatusCode::BAD_REQUEST, message).into_response());
         }
     }
     
-    debug!("WebSocket client disconnected");
+    state.db.set_primary_sensor(&address, request.sensor_index).await?;
+    info!("🎯 Primary sensor for {} set to {:?}", address, request.sensor_index);
+    
+    let reminder_days = state.config.maintenance.calibration_reminder_days;
+    Ok(Json(device_summary(&state.db, device, unit, reminder_days).await?).into_response())
 }
 
-/// Premium status endpoint
-async fn premium_status(State(state): State<AppState>) -> Result<Json<serde_json::Value>, AppError> {
-    let license = &state.license;
+/// Keep a target on the device itself, for probes used without a cook session
+///
+/// A running cook's target takes precedence for alerts; the device target
+/// applies again once the cook ends.
+async fn set_device_target(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    Json(request): Json<DeviceTargetRequest>,
+) -> Result<Response, AppError> {
+    let Some(device) = state.db.find_device(&address).await? else {
+        return Ok((StatusCode::NOT_FOUND, format!("Unknown device {}", address)).into_response());
+    };
+    if request.target.is_some_and(|t| !t.is_finite()) {
+        return Ok((StatusCode::BAD_REQUEST, "target must be a number").into_response());
+    }
+    let sensor_count = device.sensor_count.max(0) as usize;
+    let sensor_index = match request.sensor_index {
+        Some(index) => index,
+        None => state
+            .db
+            .get_primary_sensor(&address)
+            .await?
+            .unwrap_or_else(|| default_primary_sensor(sensor_count)),
+    };
+    if sensor_index >= sensor_count {
+        let message = format!("{} has {} sensors, no sensor {}", address, sensor_count, sensor_index);
+        return Ok((StatusCode::BAD_REQUEST, message).into_response());
+    }
     
-    let response = serde_json::json!({
-        "tier": license.tier,
-        "features": {
-            "cloud_sync": license.features.cloud_sync,
-            "unlimited_history": license.features.unlimited_history,
-            "cook_profiles": license.features.cook_profiles,
-            "remote_access": license.features.remote_access,
-            "advanced_analytics": license.features.advanced_analytics,
-            "alerts": license.features.alerts,
-        },
-        "is_valid": license.is_valid(),
-        "expires_at": license.expires_at,
-        "days_until_expiry": license.days_until_expiry(),
+    let target = request.target.map(|t| Temperature::from_unit(t, unit));
+    state.db.set_target(&address, sensor_index, target).await?;
+    info!("🎯 Target for {} set to {:?} on sensor {}", address, target, sensor_index);
+    if let Some(engine) = &state.alerts {
+        engine.set_target(&address, state.db.active_target(&address).await?);
+    }
+    state.fanout.settings_changed(&address);
+    
+    let device = state.db.get_device(&address).await?;
+    let reminder_days = state.config.maintenance.calibration_reminder_days;
+    Ok(Json(device_summary(&state.db, device, unit, reminder_days).await?).into_response())
+}
+
+/// Give a device a name of its own, or go back to the detected one
+async fn rename_device(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    Json(request): Json<RenameDeviceRequest>,
+) -> Result<Response, AppError> {
+    if request.name.as_deref().is_some_and(|name| name.trim().chars().count() > MAX_DEVICE_NAME_CHARS) {
+        let message = format!("name must be at most {} characters", MAX_DEVICE_NAME_CHARS);
+        return Ok((StatusCode::BAD_REQUEST, message).into_response());
+    }
+    if !state.db.set_device_alias(&address, request.name.as_deref()).await? {
+        return Ok((StatusCode::NOT_FOUND, format!("Unknown device {}", address)).into_response());
+    }
+    let device = state.db.get_device(&address).await?;
+    info!("✏️  {} is now shown as '{}'", address, device.display_name());
+    state.fanout.settings_changed(&address);
+    
+    let reminder_days = state.config.maintenance.calibration_reminder_days;
+    Ok(Json(device_summary(&state.db, device, unit, reminder_days).await?).into_response())
+}
+
+/// Forget a device and everything stored for it; refused while a cook is running on it
+async fn delete_device(State(state): State<AppState>, Path(address): Path<String>) -> Result<Response, AppError> {
+    if let Some(open) = state.db.get_open_cook(&address).await? {
+        let message = format!("End cook '{}' on {} before deleting the device", open.name, address);
+        return Ok((StatusCode::CONFLICT, message).into_response());
+    }
+    let Some(deleted_readings) = state.db.delete_device(&address).await? else {
+        return Ok((StatusCode::NOT_FOUND, format!("Unknown device {}", address)).into_response());
+    };
+    if let Some(engine) = &state.alerts {
+        for rule in engine.rules().into_iter().filter(|rule| rule.device_address == address) {
+            engine.remove_rule(rule.id);
+        }
+        engine.set_target(&address, None);
+    }
+    info!("🗑️  Deleted {} and its {} readings", address, deleted_readings);
+    state.fanout.forget(&address);
+    state.fanout.settings_changed(&address);
+    
+    Ok(Json(DeletedDevice { device_address: address, deleted_readings }).into_response())
+}
+
+/// Clear a device's possible-damage warning, e.g. after checking the probe
+async fn clear_damage_warning(State(state): State<AppState>, Path(address): Path<String>) -> Result<Response, AppError> {
+    if !state.db.clear_probe_damage(&address).await? {
+        return Ok((StatusCode::NOT_FOUND, format!("{} has no damage warning", address)).into_response());
+    }
+    info!("🔥 Damage warning for {} cleared", address);
+    state.fanout.settings_changed(&address);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Dismiss the cook target suggested for a new device, so dashboards connecting later skip it
+async fn dismiss_suggestion(State(state): State<AppState>, Path(address): Path<String>) -> Result<Response, AppError> {
+    if !state.fanout.clear_suggestion(&address) {
+        return Ok((StatusCode::NOT_FOUND, format!("{} has no suggested target", address)).into_response());
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Name, cook target and alert rules of one device, for the dashboard's settings drawer
+async fn device_settings(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+) -> Result<Json<DeviceSettings>, AppError> {
+    let device = state.db.get_device(&address).await?;
+    let sensor_count = device.sensor_count.max(0) as usize;
+    let primary_sensor_index = state
+        .db
+        .get_primary_sensor(&address)
+        .await?
+        .unwrap_or_else(|| default_primary_sensor(sensor_count));
+    let cook = state.db.get_open_cook(&address).await?;
+    let alerts = state.licensed_alerts().map(|engine| {
+        engine
+            .rules()
+            .into_iter()
+            .filter(|rule| rule.device_address == address)
+            .map(|rule| AlertRuleResponse::new(rule, unit, engine))
+            .collect()
     });
     
-    Ok(Json(response))
+    Ok(Json(DeviceSettings {
+        display_name: device.display_name(),
+        alias: device.alias,
+        device_address: device.device_address,
+        sensor_count,
+        primary_sensor_index,
+        cook: cook.map(|session| CookSessionResponse::new(session, unit)),
+        alerts,
+        unit,
+    }))
 }
 
-/// Error type for API handlers
-struct AppError(anyhow::Error);
+/// Smoker characteristics used for fuel estimates
+async fn get_smoker(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<Json<SmokerProfile>, AppError> {
+    Ok(Json(state.db.get_smoker_profile(&address).await?))
+}
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        error!("API error: {}", self.0);
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("{}", self.0)).into_response()
+async fn set_smoker(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(profile): Json<SmokerProfile>,
+) -> Result<Response, AppError> {
+    state.db.get_device(&address).await?;
+    
+    if !(profile.efficiency.is_finite() && profile.efficiency > 0.0) {
+        return Ok((StatusCode::BAD_REQUEST, "efficiency must be a positive number").into_response());
     }
+    
+    state.db.set_smoker_profile(&address, &profile).await?;
+    Ok(Json(profile).into_response())
 }
 
-impl<E> From<E> for AppError
-where
-    E: Into<anyhow::Error>,
-{
-    fn from(err: E) -> Self {
-        Self(err.into())
+/// Temperature slope and stall detection for the primary sensor
+async fn device_analytics(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<AnalyticsQuery>,
+) -> Result<Response, AppError> {
+    if !state.license.current().features.advanced_analytics {
+        return Ok(premium_required("Advanced analytics"));
     }
+    
+    let sources = match parse_source_filter(query.source.as_deref()) {
+        Ok(sources) => sources,
+        Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+    };
+    
+    let (sensor_index, mut readings) = primary_sensor_readings(&state.db, &address).await?;
+    if let Some(sources) = sources {
+        readings.retain(|r| sources.contains(&r.source));
+    }
+    
+    let rate = analytics::rate_of_change(&readings, chrono::Duration::minutes(query.window_mins as i64));
+    let stall = analytics::detect_stall(&readings);
+    
+    Ok(Json(DeviceAnalytics {
+        sensor_index,
+        // Analytics slopes are °F per hour
+        rate_per_hour: rate.map(|r| unit.delta_from_celsius(TemperatureUnit::Fahrenheit.delta_to_celsius(r))),
+        window_mins: query.window_mins,
+        stalled: stall.is_some(),
+        stall_started_at: stall.as_ref().map(|s| s.started_at),
+        stall_duration_mins: stall.map(|s| s.duration_mins),
+        unit,
+    })
+    .into_response())
 }
 
-/// Embedded HTML for the dashboard
-const INDEX_HTML: &str = r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>BBQ Monitor Dashboard</title>
-    <script src="https://cdn.jsdelivr.net/npm/chart.js@4.4.1/dist/chart.umd.min.js"></script>
-    <style>
-        * { margin: 0; padding: 0; box-sizing: border-box; }
-        body {
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            background: linear-gradient(135deg, #1e3c72 0%, #2a5298 100%);
-            color: #fff;
-            padding: 20px;
-        }
-        .container { max-width: 1400px; margin: 0 auto; }
-        h1 {
-            text-align: center;
-            margin-bottom: 30px;
-            font-size: 2.5em;
-            text-shadow: 2px 2px 4px rgba(0,0,0,0.3);
-        }
-        .premium-badge {
-            display: inline-block;
-            background: linear-gradient(135deg, #f59e0b 0%, #d97706 100%);
-            color: white;
-            padding: 4px 12px;
-            border-radius: 12px;
-            font-size: 0.7em;
-            font-weight: bold;
-            margin-left: 10px;
-            box-shadow: 0 2px 4px rgba(0,0,0,0.2);
-        }
-        .premium-banner {
-            background: linear-gradient(135deg, #1e40af 0%, #3b82f6 100%);
-            padding: 15px 20px;
-            border-radius: 8px;
-            margin-bottom: 20px;
-            text-align: center;
-            box-shadow: 0 4px 8px rgba(0,0,0,0.2);
-        }
-        .premium-banner h3 {
-            margin-bottom: 8px;
-            font-size: 1.2em;
-        }
-        .premium-banner p {
-            opacity: 0.9;
-            font-size: 0.9em;
-            margin-bottom: 12px;
-        }
-        .premium-banner a {
-            display: inline-block;
-            background: white;
-            color: #1e40af;
-            padding: 10px 24px;
-            border-radius: 6px;
-            text-decoration: none;
-            font-weight: bold;
-            transition: transform 0.2s;
-        }
-        .premium-banner a:hover {
-            transform: translateY(-2px);
-        }
-        .status {
-            text-align: center;
-            margin-bottom: 20px;
-            padding: 10px;
+/// Min/max/avg of the primary sensor and ambient over the last `?hours=`
+///
+/// Time spent at or above `?above=` is an advanced analytics feature.
+async fn device_stats(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<StatsQuery>,
+) -> Result<Response, AppError> {
+    if query.above.is_some() && !state.license.current().features.advanced_analytics {
+        return Ok(premium_required("Advanced analytics"));
+    }
+    if query.above.is_some_and(|above| !above.is_finite()) {
+        return Ok((StatusCode::BAD_REQUEST, "above must be a number").into_response());
+    }
+    state.db.get_device(&address).await?;
+    
+    let end = Utc::now();
+    let start = end - chrono::Duration::hours(query.hours as i64);
+    let above = query.above.map(|above| Temperature::from_unit(above, unit));
+    let stats = state.db.get_device_stats(&address, start, end, above).await?;
+    Ok(Json(DeviceStatsResponse::new(stats, query.above, unit)).into_response())
+}
+
+/// Maintenance log of a device, newest first
+async fn list_notes(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<NotesQuery>,
+) -> Result<Json<Vec<DeviceNote>>, AppError> {
+    Ok(Json(state.db.get_device_notes(&address, query.category).await?))
+}
+
+async fn create_note(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(request): Json<DeviceNoteRequest>,
+) -> Result<Response, AppError> {
+    state.db.get_device(&address).await?;
+    
+    let timestamp = request.timestamp.unwrap_or_else(Utc::now);
+    let note = state.db
+        .insert_device_note(&address, timestamp, request.category, request.text.trim())
+        .await?;
+    info!("📝 Added {} note {} on {}", note.category.as_str(), note.id, address);
+    
+    Ok((StatusCode::CREATED, Json(note)).into_response())
+}
+
+async fn update_note(
+    State(state): State<AppState>,
+    Path((address, id)): Path<(String, i64)>,
+    Json(request): Json<DeviceNoteRequest>,
+) -> Result<Response, AppError> {
+    match state.db
+        .update_device_note(&address, id, request.timestamp, request.category, request.text.trim())
+        .await?
+    {
+        Some(note) => Ok(Json(note).into_response()),
+        None => Ok((StatusCode::NOT_FOUND, format!("{} has no note {}", address, id)).into_response()),
+    }
+}
+
+async fn delete_note(
+    State(state): State<AppState>,
+    Path((address, id)): Path<(String, i64)>,
+) -> Result<Response, AppError> {
+    if state.db.delete_device_note(&address, id).await? {
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        Ok((StatusCode::NOT_FOUND, format!("{} has no note {}", address, id)).into_response())
+    }
+}
+
+/// Estimated time until the primary sensor reaches `?target=`
+async fn device_eta(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(address): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<EtaQuery>,
+) -> Result<Response, AppError> {
+    if !state.license.current().features.advanced_analytics {
+        return Ok(premium_required("Advanced analytics"));
+    }
+    if !query.target.is_finite() || query.carryover.is_some_and(|c| !(c.is_finite() && c >= 0.0)) {
+        return Ok((StatusCode::BAD_REQUEST, "target and carryover must be numbers, carryover not negative").into_response());
+    }
+    
+    let (sensor_index, readings) = primary_sensor_readings(&state.db, &address).await?;
+    let carryover = carryover_celsius(&state, query.meat.as_deref(), query.carryover, unit);
+    let target_f = Temperature::from_unit(query.target, unit).fahrenheit();
+    let remove_at_f = analytics::removal_temp_f(target_f, TemperatureUnit::Fahrenheit.delta_from_celsius(carryover));
+    let estimate = analytics::eta_estimate(&readings, remove_at_f);
+    let eta = estimate
+        .duration
+        .zip(readings.last())
+        .map(|(duration, last)| last.timestamp + duration);
+    
+    Ok(Json(DeviceEta {
+        sensor_index,
+        target: query.target,
+        carryover: unit.delta_from_celsius(carryover),
+        remove_at: Temperature::from_fahrenheit(remove_at_f).in_unit(unit),
+        status: estimate.status,
+        eta,
+        seconds_remaining: eta.map(|eta| (eta - Utc::now()).num_seconds().max(0)),
+        rate_per_hour: estimate
+            .slope_per_hour
+            .map(|r| unit.delta_from_celsius(TemperatureUnit::Fahrenheit.delta_to_celsius(r))),
+        confidence: estimate.confidence,
+        reason: estimate.reason,
+        unit,
+    })
+    .into_response())
+}
+
+/// Primary sensor index of a device and its last `ANALYTICS_LOOKBACK_HOURS` of readings
+async fn primary_sensor_readings(db: &Database, address: &str) -> Result<(usize, Vec<ReadingRecord>)> {
+    let device = db.get_device(address).await?;
+    let sensor_index = db
+        .get_primary_sensor(address)
+        .await?
+        .unwrap_or_else(|| default_primary_sensor(device.sensor_count.max(0) as usize));
+    let now = Utc::now();
+    let readings = db
+        .get_readings_in_range(address, now - chrono::Duration::hours(ANALYTICS_LOOKBACK_HOURS), now)
+        .await?
+        .into_iter()
+        .filter(|r| r.sensor_index as usize == sensor_index)
+        .collect();
+    Ok((sensor_index, readings))
+}
+
+/// Carryover in Celsius degrees: an explicit value in `unit`, else the meat's estimate, else none
+fn carryover_celsius(state: &AppState, meat: Option<&str>, explicit: Option<f32>, unit: TemperatureUnit) -> f32 {
+    match explicit {
+        Some(degrees) => unit.delta_to_celsius(degrees),
+        None => meat
+            .and_then(|meat| state.config.analytics.carryover_f(meat))
+            .map(|f| TemperatureUnit::Fahrenheit.delta_to_celsius(f))
+            .unwrap_or(0.0),
+    }
+}
+
+fn premium_required(feature: &str) -> Response {
+    (StatusCode::FORBIDDEN, format!("{} require a Premium license", feature)).into_response()
+}
+
+/// Alert rules in the configured unit
+async fn list_alerts(State(state): State<AppState>, RequestUnit(unit): RequestUnit) -> Response {
+    let Some(engine) = state.licensed_alerts() else {
+        return premium_required("Alerts");
+    };
+    
+    let rules: Vec<AlertRuleResponse> = engine
+        .rules()
+        .into_iter()
+        .map(|rule| AlertRuleResponse::new(rule, unit, engine))
+        .collect();
+    Json(rules).into_response()
+}
+
+/// Add an alert rule; it applies to live readings immediately
+async fn create_alert(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Json(request): Json<AlertRuleRequest>,
+) -> Result<Response, AppError> {
+    let Some(engine) = state.licensed_alerts() else {
+        return Ok(premium_required("Alerts"));
+    };
+    
+    let device = state.db.get_device(&request.device_address).await?;
+    let sensor_count = device.sensor_count.max(0) as usize;
+    if request.sensor_index >= sensor_count {
+        let message = format!("{} has {} sensors, no sensor {}", device.device_address, sensor_count, request.sensor_index);
+        return Ok((StatusCode::BAD_REQUEST, message).into_response());
+    }
+    if !(request.hysteresis.is_finite() && request.hysteresis >= 0.0) {
+        return Ok((StatusCode::BAD_REQUEST, "hysteresis must be a number, not negative").into_response());
+    }
+    let kind = match (request.kind, request.threshold) {
+        (AlertKindName::ApproachingTarget, _) => {
+            let max_offset = unit.delta_from_celsius(TemperatureUnit::Fahrenheit.delta_to_celsius(MAX_TARGET_OFFSET_F));
+            match request.offset {
+                Some(offset) if offset > 0.0 && offset <= max_offset + 1e-3 => {
+                    AlertKind::ApproachingTarget { offset: unit.delta_to_celsius(offset) }
+                }
+                _ => {
+                    let message = format!("offset must be above 0 and at most {:.1}{}", max_offset, unit.symbol());
+                    return Ok((StatusCode::BAD_REQUEST, message).into_response());
+                }
+            }
+        }
+        (AlertKindName::TargetReached, _) => AlertKind::TargetReached,
+        (AlertKindName::BatteryBelow, Some(percent)) if (1.0..=100.0).contains(&percent) => {
+            AlertKind::BatteryBelow { percent: percent.round() as u8 }
+        }
+        (AlertKindName::BatteryBelow, _) => {
+            return Ok((StatusCode::BAD_REQUEST, "threshold must be a battery percentage from 1 to 100").into_response());
+        }
+        (name, Some(threshold)) if threshold.is_finite() => {
+            AlertKind::from_stored(name, Temperature::from_unit(threshold, unit).celsius())
+        }
+        _ => return Ok((StatusCode::BAD_REQUEST, "threshold must be a number").into_response()),
+    };
+    
+    if request.carryover.is_some_and(|c| !(c.is_finite() && c >= 0.0)) {
+        return Ok((StatusCode::BAD_REQUEST, "carryover must be a number, not negative").into_response());
+    }
+    let carryover = carryover_celsius(&state, request.meat.as_deref(), request.carryover, unit);
+    if carryover > 0.0 && request.kind != AlertKindName::AboveTarget {
+        return Ok((StatusCode::BAD_REQUEST, "carryover only applies to above_target rules").into_response());
+    }
+    
+    let rule = state.db
+        .insert_alert_rule(
+            &device.device_address,
+            request.sensor_index,
+            kind,
+            unit.delta_to_celsius(request.hysteresis),
+            carryover,
+        )
+        .await?;
+    engine.add_rule(rule.clone());
+    if rule.kind.is_relative() {
+        // Pick up the current target, and fire now if the probe is already past it
+        engine.set_target(&rule.device_address, engine.target(&rule.device_address));
+    }
+    info!("🚨 Added alert rule {} ({}) on {} sensor {}", rule.id, request.kind.as_str(), rule.device_address, rule.sensor_index);
+    state.fanout.settings_changed(&rule.device_address);
+    
+    Ok((StatusCode::CREATED, Json(AlertRuleResponse::new(rule, unit, engine))).into_response())
+}
+
+/// Remove an alert rule
+async fn delete_alert(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Response, AppError> {
+    let Some(engine) = state.licensed_alerts() else {
+        return Ok(premium_required("Alerts"));
+    };
+    
+    if !state.db.delete_alert_rule(id).await? {
+        return Ok((StatusCode::NOT_FOUND, format!("No alert rule {}", id)).into_response());
+    }
+    if let Some(rule) = engine.remove_rule(id) {
+        info!("🗑️  Removed alert rule {} on {}", id, rule.device_address);
+        state.fanout.settings_changed(&rule.device_address);
+    }
+    
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Hold an alert rule quiet for a while; it fires again afterwards if still past its threshold
+async fn snooze_alert(
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    Path(id): Path<i64>,
+    Json(request): Json<SnoozeRequest>,
+) -> Response {
+    let Some(engine) = state.licensed_alerts() else {
+        return premium_required("Alerts");
+    };
+    if request.minutes > MAX_SNOOZE_MINUTES {
+        let message = format!("minutes must be at most {}", MAX_SNOOZE_MINUTES);
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    
+    let until = (request.minutes > 0).then(|| Utc::now() + chrono::Duration::minutes(request.minutes as i64));
+    if !engine.snooze(id, until) {
+        return (StatusCode::NOT_FOUND, format!("No alert rule {}", id)).into_response();
+    }
+    let Some(rule) = engine.rules().into_iter().find(|r| r.id == id) else {
+        return (StatusCode::NOT_FOUND, format!("No alert rule {}", id)).into_response();
+    };
+    info!("😴 Alert rule {} snoozed for {} min", id, request.minutes);
+    state.fanout.settings_changed(&rule.device_address);
+    
+    Json(AlertRuleResponse::new(rule, unit, engine)).into_response()
+}
+
+/// Food-safety chart bands in the configured unit
+async fn chart_bands(
+    RequestUnit(unit): RequestUnit,
+    axum::extract::Query(query): axum::extract::Query<BandsQuery>,
+) -> Json<BandsResponse> {
+    let unit = unit.as_str().to_string();
+    let bands = food_safety::chart_bands(&unit, query.meat.as_deref());
+    
+    Json(BandsResponse { unit, bands })
+}
+
+#[derive(Debug, Deserialize)]
+struct WebSocketQuery {
+    /// Last seq a reconnecting client saw; missed updates are replayed before live ones
+    last_seq: Option<u64>,
+}
+
+/// WebSocket handler for real-time updates
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    RequestUnit(unit): RequestUnit,
+    axum::extract::Query(query): axum::extract::Query<WebSocketQuery>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state, unit, query.last_seq))
+}
+
+/// A device's primary sensor over the last `minutes`, downsampled to at most `CONNECT_HISTORY_POINTS`
+///
+/// None when the device has no readings in that window.
+async fn connect_history(
+    db: &Database,
+    device: &DeviceRecord,
+    minutes: u32,
+    unit: TemperatureUnit,
+) -> Result<Option<DeviceHistory>> {
+    let sensor_index = db
+        .get_primary_sensor(&device.device_address)
+        .await?
+        .unwrap_or_else(|| default_primary_sensor(device.sensor_count.max(0) as usize));
+    let start = Utc::now() - chrono::Duration::minutes(minutes as i64);
+    let points: Vec<ComparePoint> = db
+        .get_readings_since(&device.device_address, start)
+        .await?
+        .into_iter()
+        .filter(|r| r.sensor_index as usize == sensor_index)
+        .map(|r| ComparePoint { timestamp: r.timestamp, temperature: r.temperature.in_unit(unit) })
+        .collect();
+    if points.is_empty() {
+        return Ok(None);
+    }
+    
+    let step_secs = (minutes * 60).div_ceil(CONNECT_HISTORY_POINTS);
+    Ok(Some(DeviceHistory {
+        device_address: device.device_address.clone(),
+        device_name: device.device_name.clone(),
+        sensor_index,
+        unit,
+        minutes,
+        step_secs,
+        points: resample(points, start, step_secs),
+    }))
+}
+
+/// Handle WebSocket connection; every update is sent in `unit`
+async fn handle_socket(mut socket: WebSocket, state: AppState, unit: TemperatureUnit, resume_from: Option<u64>) {
+    let mut live = state.fanout.subscribe();
+    let mut alerts = state.alerts.as_ref().map(|engine| engine.subscribe());
+    let mut ble_changes = state.ble_health.subscribe();
+    let mut settings_changes = state.fanout.subscribe_settings();
+    let mut suggestions = state.fanout.subscribe_suggestions();
+    let mut cursor = ClientCursor::default();
+    let heartbeat_every = state
+        .config
+        .web
+        .as_ref()
+        .map_or(0, |w| w.heartbeat_interval_secs);
+    let mut heartbeat = (heartbeat_every > 0).then(|| {
+        let every = std::time::Duration::from_secs(heartbeat_every);
+        tokio::time::interval_at(tokio::time::Instant::now() + every, every)
+    });
+    
+    debug!("WebSocket client connected");
+    
+    // Where Bluetooth startup is, so a client connecting mid-scan is not left guessing
+    let system = ControlMessage::System(state.ble_health.status());
+    if let Ok(json) = serde_json::to_string(&system) {
+        let _ = socket.send(Message::Text(json)).await;
+    }
+    
+    // Send initial device list (unsequenced; resuming clients ignore it)
+    let devices = state.db.get_all_devices().await.unwrap_or_default();
+    for device in &devices {
+        if let Ok(latest) = state.db.get_latest_reading(&device.device_address).await {
+            let update = TemperatureUpdate {
+                device_address: device.device_address.clone(),
+                device_name: device.device_name.clone(),
+                timestamp: latest.timestamp,
+                sensor_index: latest.sensor_index as usize,
+                temperature: latest.temperature.in_unit(unit),
+                ambient_temp: latest.ambient_temp.map(|t| t.in_unit(unit)),
+                unit,
+                battery_level: latest.battery_level,
+                signal_strength: latest.signal_strength,
+                eta_seconds: None,
+                reading_mode: latest.reading_mode,
+                virtual_sensors: None,
+            };
+            
+            if let Ok(json) = serde_json::to_string(&update) {
+                let _ = socket.send(Message::Text(json)).await;
+            }
+        }
+    }
+    
+    // Then recent history, so a fresh dashboard starts with full charts
+    let history_mins = state.config.web.as_ref().map_or(0, |w| w.history_on_connect_mins);
+    if history_mins > 0 {
+        for device in &devices {
+            match connect_history(&state.db, device, history_mins, unit).await {
+                Ok(Some(history)) => {
+                    if let Ok(json) = serde_json::to_string(&ControlMessage::History(history)) {
+                        let _ = socket.send(Message::Text(json)).await;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => debug!("No connect history for {}: {:#}", device.device_address, e),
+            }
+        }
+    }
+    
+    // Target suggestions still waiting for an answer, made before this client connected
+    for suggestion in state.fanout.pending_suggestions() {
+        if let Ok(json) = serde_json::to_string(&ControlMessage::TargetSuggestion(suggestion.in_unit(unit))) {
+            let _ = socket.send(Message::Text(json)).await;
+        }
+    }
+    
+    // Missed updates go out before live ones, which wait in `live` meanwhile
+    if let Some(last_seq) = resume_from {
+        for json in replay_messages(&state, &mut cursor, last_seq, unit) {
+            if socket.send(Message::Text(json)).await.is_err() {
+                return;
+            }
+        }
+    }
+    
+    // Stream real-time updates, answering resume requests from the client
+    loop {
+        tokio::select! {
+            received = live.recv() => {
+                let json = match received {
+                    Ok(update) if cursor.admit_live(update.seq) => serde_json::to_string(&update.in_unit(unit)),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("WebSocket client lagged by {} updates, asking it to resync", skipped);
+                        serde_json::to_string(&ControlMessage::Resync { latest_seq: state.fanout.latest_seq() })
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                
+                if let Ok(json) = json {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            _ = next_heartbeat(&mut heartbeat) => {
+                let quiet_for = std::time::Duration::from_secs(heartbeat_every);
+                for beat in state.fanout.heartbeats(quiet_for, unit) {
+                    let Ok(json) = serde_json::to_string(&ControlMessage::Heartbeat(beat)) else { continue };
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            change = ble_changes.recv() => {
+                let status = match change {
+                    Ok(status) => status,
+                    // Only the latest phase matters
+                    Err(broadcast::error::RecvError::Lagged(_)) => state.ble_health.status(),
+                    Err(broadcast::error::RecvError::Closed) => continue,
+                };
+                if let Ok(json) = serde_json::to_string(&ControlMessage::System(status)) {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            change = settings_changes.recv() => {
+                let device_address = match change {
+                    Ok(device_address) => device_address,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("WebSocket client missed {} settings changes", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => continue,
+                };
+                if let Ok(json) = serde_json::to_string(&ControlMessage::Settings { device_address }) {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            suggestion = suggestions.recv() => {
+                let suggestion = match suggestion {
+                    Ok(suggestion) => suggestion.in_unit(unit),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("WebSocket client missed {} target suggestions", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => continue,
+                };
+                if let Ok(json) = serde_json::to_string(&ControlMessage::TargetSuggestion(suggestion)) {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            alert = next_alert(&mut alerts) => {
+                let event = match alert {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("WebSocket client missed {} alerts", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        alerts = None;
+                        continue;
+                    }
+                };
+                
+                if let Ok(json) = serde_json::to_string(&AlertMessage::Alert(event.in_unit(unit))) {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    _ => break,
+                };
+                
+                let Ok(ClientMessage::Resume { last_seq }) = serde_json::from_str(&text) else {
+                    debug!("Ignoring unknown WebSocket message: {}", text);
+                    continue;
+                };
+                
+                for json in replay_messages(&state, &mut cursor, last_seq, unit) {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    
+    debug!("WebSocket client disconnected");
+}
+
+/// What a client resuming after `last_seq` is sent: the updates it missed, or a resync
+fn replay_messages(state: &AppState, cursor: &mut ClientCursor, last_seq: u64, unit: TemperatureUnit) -> Vec<String> {
+    let messages: Vec<String> = match cursor.admit_replay(state.fanout.replay_since(last_seq)) {
+        Replay::Messages(updates) => updates
+            .into_iter()
+            .filter_map(|update| serde_json::to_string(&update.in_unit(unit)).ok())
+            .collect(),
+        Replay::Resync => serde_json::to_string(&ControlMessage::Resync { latest_seq: state.fanout.latest_seq() })
+            .into_iter()
+            .collect(),
+    };
+    debug!("Resuming WebSocket client from seq {} ({} messages)", last_seq, messages.len());
+    messages
+}
+
+/// Next heartbeat tick; never resolves when heartbeats are off
+async fn next_heartbeat(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Next alert for a WebSocket client; never resolves when alerts are off
+async fn next_alert(
+    alerts: &mut Option<broadcast::Receiver<AlertEvent>>,
+) -> Result<AlertEvent, broadcast::error::RecvError> {
+    match alerts {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Premium status endpoint
+async fn premium_status(State(state): State<AppState>) -> Result<Json<serde_json::Value>, AppError> {
+    let license = state.license.current();
+    
+    let response = serde_json::json!({
+        "tier": license.tier,
+        "source": state.license.source(),
+        "features": {
+            "cloud_sync": license.features.cloud_sync,
+            "unlimited_history": license.features.unlimited_history,
+            "cook_profiles": license.features.cook_profiles,
+            "remote_access": license.features.remote_access,
+            "advanced_analytics": license.features.advanced_analytics,
+            "alerts": license.features.alerts,
+        },
+        "is_valid": license.is_valid(),
+        "expires_at": license.expires_at,
+        "days_until_expiry": license.days_until_expiry(),
+        "upgrade_url": crate::premium::PREMIUM_URL,
+        "renew_url": crate::premium::RENEW_URL,
+    });
+    
+    Ok(Json(response))
+}
+
+/// License activation request
+#[derive(Debug, Deserialize)]
+pub struct ActivateRequest {
+    pub license_key: String,
+}
+
+/// Activate a Premium key without editing config.toml
+///
+/// Stored in config.toml when it is writable, otherwise in the database, and
+/// in effect immediately. Alerts still need a restart if they were off.
+async fn activate_premium(_admin: AdminAuth, State(state): State<AppState>, Json(request): Json<ActivateRequest>) -> Response {
+    match state.license.activate(&request.license_key).await {
+        Ok((license, source)) => Json(serde_json::json!({
+            "tier": license.tier,
+            "source": source,
+            "expires_at": license.expires_at,
+        }))
+        .into_response(),
+        Err(e @ ActivationError::InvalidKey) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) => {
+            error!("❌ {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Service health, including the Bluetooth environment
+///
+/// Served from launch on; while Bluetooth is still starting, `ble.phase`
+/// and `ble.message` report how far it got.
+async fn health(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let (status, mut ble) = match state.ble_health.error() {
+        None => ("ok", serde_json::json!({ "status": "ok" })),
+        Some(err) => ("degraded", err.to_json()),
+    };
+    if let (Some(ble), Ok(serde_json::Value::Object(progress))) =
+        (ble.as_object_mut(), serde_json::to_value(state.ble_health.status()))
+    {
+        ble.extend(progress);
+    }
+
+    Json(serde_json::json!({ "status": status, "ble": ble }))
+}
+
+/// Build, feature and schema information (same document as `--version --json`)
+async fn version() -> Json<crate::version::VersionInfo> {
+    Json(crate::version::VersionInfo::current())
+}
+
+/// Request body for `POST /api/admin/log-level`
+#[derive(Debug, Deserialize)]
+pub struct LogBurstRequest {
+    /// "trace", "debug", "info", "warn" or "error"
+    pub level: String,
+    /// How long to keep the raised level (at most one hour)
+    pub duration_secs: u64,
+    /// Only raise these targets, e.g. `bbq_monitor::database` or `btleplug` (empty = everything)
+    #[serde(default)]
+    pub targets: Vec<String>,
+}
+
+fn log_control_unavailable() -> Response {
+    (StatusCode::SERVICE_UNAVAILABLE, "Runtime log level control is not available").into_response()
+}
+
+/// Current log filter, active burst and recent burst windows
+async fn log_level_status(_admin: AdminAuth, State(state): State<AppState>) -> Response {
+    match &state.log_control {
+        Some(control) => Json(control.status()).into_response(),
+        None => log_control_unavailable(),
+    }
+}
+
+/// Temporarily raise the log level; reverts automatically after `duration_secs`
+async fn start_log_burst(
+    _admin: AdminAuth,
+    State(state): State<AppState>,
+    Json(request): Json<LogBurstRequest>,
+) -> Response {
+    let Some(control) = &state.log_control else {
+        return log_control_unavailable();
+    };
+
+    let duration = std::time::Duration::from_secs(request.duration_secs);
+    match control.start_burst(&request.level, &request.targets, duration) {
+        Ok(burst) => Json(burst).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// End the active burst early
+async fn end_log_burst(_admin: AdminAuth, State(state): State<AppState>) -> Response {
+    let Some(control) = &state.log_control else {
+        return log_control_unavailable();
+    };
+
+    match control.end_burst(None) {
+        Some(burst) => Json(burst).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Recompute sensor counts, last-seen times and brands from stored readings (admin)
+async fn recompute_devices(_admin: AdminAuth, State(state): State<AppState>) -> Result<Json<maintenance::RecomputeSummary>, AppError> {
+    Ok(Json(maintenance::recompute_device_fields(&state.db).await?))
+}
+
+/// Extra wait on top of a debug operation's timeout for the monitor to pick it up
+const DEBUG_QUEUE_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Request body for `POST /api/devices/:address/debug/write`
+#[derive(Debug, Deserialize)]
+pub struct DebugWriteRequest {
+    pub service: String,
+    pub characteristic: String,
+    /// Bytes to write as hex ("01 ff" and "01:ff" also accepted)
+    pub payload: String,
+    #[serde(default)]
+    pub with_response: bool,
+    /// How long to collect notifications (default 2s, at most 10s)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Request body for `POST /api/devices/:address/debug/read`
+#[derive(Debug, Deserialize)]
+pub struct DebugReadRequest {
+    pub service: String,
+    pub characteristic: String,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Write raw bytes to a characteristic of a connected device (developer mode)
+async fn debug_write(
+    _admin: AdminAuth,
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(request): Json<DebugWriteRequest>,
+) -> Response {
+    let Some(payload) = ble_debug::parse_hex(&request.payload) else {
+        return (StatusCode::BAD_REQUEST, "payload must be hex bytes").into_response();
+    };
+    let operation = DebugOperation::Write { payload, with_response: request.with_response };
+    run_debug_command(&state, address, &request.service, &request.characteristic, operation, request.timeout_ms).await
+}
+
+/// Read a characteristic of a connected device (developer mode)
+async fn debug_read(
+    _admin: AdminAuth,
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(request): Json<DebugReadRequest>,
+) -> Response {
+    run_debug_command(&state, address, &request.service, &request.characteristic, DebugOperation::Read, request.timeout_ms).await
+}
+
+/// Hand a debug operation to the monitor, which owns the connection, and wait for its result
+async fn run_debug_command(
+    state: &AppState,
+    device_address: String,
+    service: &str,
+    characteristic: &str,
+    operation: DebugOperation,
+    timeout_ms: Option<u64>,
+) -> Response {
+    let (Ok(service), Ok(characteristic)) = (Uuid::parse_str(service), Uuid::parse_str(characteristic)) else {
+        return (StatusCode::BAD_REQUEST, "service and characteristic must be UUIDs").into_response();
+    };
+    let timeout = timeout_ms
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(ble_debug::DEFAULT_DEBUG_TIMEOUT)
+        .min(ble_debug::MAX_DEBUG_TIMEOUT);
+    
+    let (reply, response) = tokio::sync::oneshot::channel();
+    let command = DebugCommand { device_address, service, characteristic, operation, timeout, reply };
+    let sent = match &state.debug_commands {
+        Some(commands) => commands.send(command).await.is_ok(),
+        None => false,
+    };
+    // The monitor only takes commands while it is monitoring, not while scanning
+    let result = if sent {
+        tokio::time::timeout(timeout + DEBUG_QUEUE_GRACE, response)
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or(Err(DebugError::MonitorUnavailable))
+    } else {
+        Err(DebugError::MonitorUnavailable)
+    };
+    
+    match result {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => {
+            let status = match e {
+                DebugError::NotConnected(_) => StatusCode::CONFLICT,
+                DebugError::CharacteristicNotFound { .. } => StatusCode::BAD_REQUEST,
+                DebugError::Ble(_) => StatusCode::BAD_GATEWAY,
+                DebugError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+                DebugError::MonitorUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            (status, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Simulated probes with their current readings and scenario
+async fn list_simulated_devices(_admin: AdminAuth, State(state): State<AppState>) -> Response {
+    let (reply, response) = tokio::sync::oneshot::channel();
+    match send_simulator_command(&state, SimulatorCommand::List { reply }).await {
+        Ok(()) => match response.await {
+            Ok(devices) => Json(devices).into_response(),
+            Err(_) => simulator_unavailable(),
+        },
+        Err(response) => response,
+    }
+}
+
+/// Apply a scenario command (flameout, stall, ...) to a running simulated probe
+async fn set_simulator_scenario(
+    _admin: AdminAuth,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    RequestUnit(unit): RequestUnit,
+    Json(command): Json<ScenarioCommand>,
+) -> Response {
+    let (reply, response) = tokio::sync::oneshot::channel();
+    let command = SimulatorCommand::Scenario { device_address: id, command, unit, reply };
+    if let Err(response) = send_simulator_command(&state, command).await {
+        return response;
+    }
+    match response.await {
+        Ok(Ok(device)) => Json(device).into_response(),
+        Ok(Err(e @ SimulatorError::UnknownDevice(_))) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+        Ok(Err(SimulatorError::Unavailable)) | Err(_) => simulator_unavailable(),
+    }
+}
+
+async fn send_simulator_command(state: &AppState, command: SimulatorCommand) -> Result<(), Response> {
+    match &state.simulator {
+        Some(commands) if commands.send(command).await.is_ok() => Ok(()),
+        _ => Err(simulator_unavailable()),
+    }
+}
+
+fn simulator_unavailable() -> Response {
+    (StatusCode::SERVICE_UNAVAILABLE, SimulatorError::Unavailable.to_string()).into_response()
+}
+
+/// Pit controller setpoint, latest pit reading and output
+#[cfg(feature = "pit-controller")]
+async fn pit_controller_status(State(state): State<AppState>) -> Response {
+    match &state.pit_controller {
+        Some(controller) => Json(controller.status()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Error type for API handlers
+struct AppError(anyhow::Error);
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        error!("API error: {}", self.0);
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("{}", self.0)).into_response()
+    }
+}
+
+impl<E> From<E> for AppError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+/// Embedded chart script for the dashboard (no CDN, so charts work offline)
+const LINE_CHART_JS: &str = include_str!("line_chart.js");
+
+/// Embedded HTML for the dashboard; everything it loads is served by this binary
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>BBQ Monitor Dashboard</title>
+    <script src="/assets/line-chart.js?v={{ASSET_VERSION}}"></script>
+    <style>
+        * { margin: 0; padding: 0; box-sizing: border-box; }
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+            background: linear-gradient(135deg, #1e3c72 0%, #2a5298 100%);
+            color: #fff;
+            padding: 20px;
+        }
+        .container { max-width: 1400px; margin: 0 auto; }
+        h1 {
+            text-align: center;
+            margin-bottom: 30px;
+            font-size: 2.5em;
+            text-shadow: 2px 2px 4px rgba(0,0,0,0.3);
+        }
+        .premium-badge {
+            display: inline-block;
+            background: linear-gradient(135deg, #f59e0b 0%, #d97706 100%);
+            color: white;
+            padding: 4px 12px;
+            border-radius: 12px;
+            font-size: 0.7em;
+            font-weight: bold;
+            margin-left: 10px;
+            box-shadow: 0 2px 4px rgba(0,0,0,0.2);
+        }
+        .premium-banner {
+            background: linear-gradient(135deg, #1e40af 0%, #3b82f6 100%);
+            padding: 15px 20px;
+            border-radius: 8px;
+            margin-bottom: 20px;
+            text-align: center;
+            box-shadow: 0 4px 8px rgba(0,0,0,0.2);
+        }
+        .premium-banner h3 {
+            margin-bottom: 8px;
+            font-size: 1.2em;
+        }
+        .premium-banner p {
+            opacity: 0.9;
+            font-size: 0.9em;
+            margin-bottom: 12px;
+        }
+        .premium-banner a {
+            display: inline-block;
+            background: white;
+            color: #1e40af;
+            padding: 10px 24px;
+            border-radius: 6px;
+            text-decoration: none;
+            font-weight: bold;
+            transition: transform 0.2s;
+        }
+        .premium-banner a:hover {
+            transform: translateY(-2px);
+        }
+        .status {
+            text-align: center;
+            margin-bottom: 20px;
+            padding: 10px;
             background: rgba(255,255,255,0.1);
             border-radius: 8px;
             font-size: 0.9em;
         }
-        .status.connected { color: #4ade80; }
-        .status.disconnected { color: #f87171; }
-        .devices-grid {
-            display: grid;
-            grid-template-columns: repeat(auto-fit, minmax(400px, 1fr));
-            gap: 20px;
-            margin-bottom: 30px;
+        .status.connected { color: #4ade80; }
+        .status.disconnected { color: #f87171; }
+        .devices-grid {
+            display: grid;
+            grid-template-columns: repeat(auto-fit, minmax(400px, 1fr));
+            gap: 20px;
+            margin-bottom: 30px;
+        }
+        .device-card {
+            background: rgba(255, 255, 255, 0.95);
+            color: #1e293b;
+            border-radius: 12px;
+            padding: 20px;
+            box-shadow: 0 8px 16px rgba(0,0,0,0.2);
+        }
+        .device-header {
+            display: flex;
+            justify-content: space-between;
+            align-items: center;
+            margin-bottom: 15px;
+            padding-bottom: 15px;
+            border-bottom: 2px solid #e2e8f0;
+        }
+        .device-name {
+            font-size: 1.3em;
+            font-weight: bold;
+            color: #1e40af;
+        }
+        .device-brand {
+            font-size: 0.85em;
+            color: #64748b;
+            text-transform: uppercase;
+            letter-spacing: 1px;
+        }
+        .temperature-display {
+            text-align: center;
+            margin: 20px 0;
+        }
+        .temp-value {
+            font-size: 3em;
+            font-weight: bold;
+            color: #dc2626;
+        }
+        .temp-label {
+            font-size: 0.9em;
+            color: #64748b;
+            margin-top: 5px;
+        }
+        .timestamp {
+            text-align: center;
+            font-size: 0.75em;
+            color: #94a3b8;
+            margin-top: 8px;
+        }
+        .timestamp.aged {
+            font-style: italic;
+            color: #f59e0b;
+        }
+        .timestamp.stale {
+            font-style: italic;
+            color: #ef4444;
+        }
+        .eta {
+            text-align: center;
+            font-size: 0.95em;
+            font-weight: bold;
+            color: #16a34a;
+            margin-top: 6px;
+        }
+        .alert-line {
+            text-align: center;
+            font-weight: bold;
+            color: #dc2626;
+            margin-top: 4px;
+        }
+        .suggestion-line {
+            display: flex;
+            justify-content: center;
+            align-items: center;
+            gap: 6px;
+            font-size: 0.9em;
+            margin-top: 4px;
+        }
+        .suggestion-line:empty { display: none; }
+        .damage-line { color: #dc2626; }
+        .maintenance-badge {
+            text-align: center;
+            font-size: 0.85em;
+            color: #d97706;
+            margin-top: 4px;
+        }
+        .metrics {
+            display: grid;
+            grid-template-columns: repeat(3, 1fr);
+            gap: 10px;
+            margin-top: 15px;
+        }
+        .metric {
+            text-align: center;
+            padding: 10px;
+            background: #f1f5f9;
+            border-radius: 6px;
+        }
+        .metric-value {
+            font-size: 1.2em;
+            font-weight: bold;
+            color: #1e40af;
+        }
+        .metric-label {
+            font-size: 0.75em;
+            color: #64748b;
+            text-transform: uppercase;
+            margin-top: 3px;
+        }
+        .sensor-rows {
+            margin-top: 15px;
+            border-top: 1px solid #e2e8f0;
+        }
+        .sensor-row {
+            display: flex;
+            justify-content: space-between;
+            padding: 6px 4px;
+            border-bottom: 1px solid #f1f5f9;
+            font-size: 0.9em;
+            cursor: pointer;
+        }
+        .sensor-row.primary {
+            font-weight: bold;
+        }
+        .sensor-label {
+            color: #64748b;
+        }
+        .sensor-value {
+            color: #dc2626;
+            font-weight: bold;
+        }
+        .chart-container {
+            margin-top: 20px;
+            height: 200px;
+        }
+        .settings-button {
+            background: none;
+            border: none;
+            font-size: 1.3em;
+            cursor: pointer;
+        }
+        .drawer {
+            position: fixed;
+            top: 0;
+            right: 0;
+            height: 100%;
+            width: 360px;
+            max-width: 100%;
+            background: #fff;
+            color: #1e293b;
+            padding: 20px;
+            overflow-y: auto;
+            box-shadow: -8px 0 16px rgba(0,0,0,0.3);
+            transform: translateX(100%);
+            transition: transform 0.2s;
+            z-index: 10;
+        }
+        .drawer.open { transform: translateX(0); }
+        .drawer h2 {
+            font-size: 1.2em;
+            color: #1e40af;
+            margin-bottom: 10px;
+        }
+        .drawer h3 {
+            font-size: 0.8em;
+            color: #64748b;
+            text-transform: uppercase;
+            margin: 18px 0 6px;
+        }
+        .drawer input, .drawer select {
+            padding: 6px;
+            border: 1px solid #cbd5e1;
+            border-radius: 4px;
+            min-width: 0;
+        }
+        .drawer button {
+            padding: 6px 10px;
+            border: none;
+            border-radius: 4px;
+            background: #1e40af;
+            color: #fff;
+            cursor: pointer;
+        }
+        .drawer button.secondary {
+            background: #e2e8f0;
+            color: #1e293b;
+        }
+        .drawer-row {
+            display: flex;
+            gap: 6px;
+            align-items: center;
+            margin-bottom: 6px;
+        }
+        .drawer-row .grow { flex: 1; }
+        .drawer-note {
+            font-size: 0.85em;
+            color: #64748b;
+        }
+        .drawer-error {
+            color: #dc2626;
+            font-size: 0.85em;
+            margin-top: 12px;
+        }
+        @media (max-width: 768px) {
+            .devices-grid {
+                grid-template-columns: 1fr;
+            }
+            h1 { font-size: 1.8em; }
+            .temp-value { font-size: 2.5em; }
+        }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>🔥 BBQ Monitor Dashboard<span class="premium-badge" id="tier-badge" style="display: none;">FREE</span></h1>
+        <div id="premium-banner" style="display: none;"></div>
+        <div id="status" class="status disconnected">
+            ⚠️ Connecting to server...
+        </div>
+        <div id="devices" class="devices-grid"></div>
+    </div>
+    <aside id="settings-drawer" class="drawer">
+        <div class="drawer-row">
+            <h2 class="grow" id="settings-title">Settings</h2>
+            <button class="secondary" onclick="closeSettings()">✕</button>
+        </div>
+        <div id="settings-body"></div>
+        <div class="drawer-note" id="settings-status"></div>
+        <div class="drawer-error" id="settings-error"></div>
+    </aside>
+
+    <script>
+        let ws = null;
+        let bluetoothStatus = null;
+        let lastSeq = null;
+        let charts = {};
+        let deviceData = {};
+        // Charts keep readings this far back (from the connect history), and at least the last 50
+        let historyWindowMs = 0;
+        // Display unit reported by the server; updated from every response
+        let unitSymbol = '°F';
+
+        function setUnit(unit) {
+            unitSymbol = unit === 'celsius' ? '°C' : '°F';
+        }
+
+        function formatTemp(value) {
+            return `${value.toFixed(1)}${unitSymbol}`;
+        }
+
+        // Key for servers with web.api_key set, asked for on the first 401
+        let apiKey = localStorage.getItem('apiKey');
+
+        async function apiFetch(url, options = {}) {
+            const headers = { ...(options.headers || {}) };
+            if (apiKey) headers['X-API-Key'] = apiKey;
+            const response = await fetch(url, { ...options, headers });
+            if (response.status === 401) {
+                const entered = window.prompt('API key for this monitor');
+                if (entered) {
+                    apiKey = entered;
+                    localStorage.setItem('apiKey', entered);
+                    return apiFetch(url, options);
+                }
+            }
+            return response;
+        }
+        let chartBands = [];
+
+        const BAND_COLORS = {
+            danger: 'rgba(220, 38, 38, 0.12)',
+            safe: 'rgba(22, 163, 74, 0.9)'
+        };
+
+        // Shade food-safety bands provided by the server
+        function drawBands(ctx, area, y) {
+            for (const band of chartBands) {
+                const color = BAND_COLORS[band.color] || 'rgba(100, 116, 139, 0.2)';
+                const top = Math.max(y(band.to), area.top);
+                const bottom = Math.min(y(band.from), area.bottom);
+                if (band.from === band.to) {
+                    if (top < area.top || top > area.bottom) continue;
+                    ctx.strokeStyle = color;
+                    ctx.setLineDash([6, 4]);
+                    ctx.beginPath();
+                    ctx.moveTo(area.left, top);
+                    ctx.lineTo(area.right, top);
+                    ctx.stroke();
+                    ctx.setLineDash([]);
+                } else if (bottom > top) {
+                    ctx.fillStyle = color;
+                    ctx.fillRect(area.left, top, area.right - area.left, bottom - top);
+                }
+            }
+        }
+
+        // Load food-safety bands (meat type from ?meat= on the page URL)
+        async function loadChartBands() {
+            try {
+                const meat = new URLSearchParams(window.location.search).get('meat');
+                const url = meat ? `/api/chart/bands?meat=${encodeURIComponent(meat)}` : '/api/chart/bands';
+                const response = await apiFetch(url);
+                const data = await response.json();
+                chartBands = data.bands;
+                Object.values(charts).forEach(chart => chart.update());
+            } catch (error) {
+                console.error('Failed to load chart bands:', error);
+            }
+        }
+
+        // Load premium status
+        async function loadPremiumStatus() {
+            try {
+                const response = await apiFetch('/api/premium/status');
+                const status = await response.json();
+                
+                const badge = document.getElementById('tier-badge');
+                badge.style.display = 'inline-block';
+                badge.textContent = status.tier.toUpperCase();
+                
+                if (status.tier === 'Free') {
+                    badge.style.background = 'linear-gradient(135deg, #64748b 0%, #475569 100%)';
+                    
+                    // Show premium banner for free users
+                    const banner = document.getElementById('premium-banner');
+                    banner.style.display = 'block';
+                    banner.className = 'premium-banner';
+                    banner.innerHTML = `
+                        <h3>🌟 Upgrade to Premium</h3>
+                        <p>Unlock cloud sync, unlimited history, cook profiles, and more!</p>
+                        <a href="${status.upgrade_url}" target="_blank">View Premium Features →</a>
+                    `;
+                } else if (status.tier === 'Premium') {
+                    badge.style.background = 'linear-gradient(135deg, #f59e0b 0%, #d97706 100%)';
+                    
+                    // Show expiry warning if needed
+                    if (status.days_until_expiry !== null && status.days_until_expiry < 30) {
+                        const banner = document.getElementById('premium-banner');
+                        banner.style.display = 'block';
+                        banner.className = 'premium-banner';
+                        banner.style.background = 'linear-gradient(135deg, #dc2626 0%, #b91c1c 100%)';
+                        banner.innerHTML = `
+                            <h3>⚠️ License Expiring Soon</h3>
+                            <p>Your Premium license expires in ${status.days_until_expiry} days</p>
+                            <a href="${status.renew_url}" target="_blank">Renew License →</a>
+                        `;
+                    }
+                }
+            } catch (error) {
+                console.error('Failed to load premium status:', error);
+            }
+        }
+
+        function connect() {
+            const params = new URLSearchParams();
+            if (apiKey) params.set('api_key', apiKey);
+            // Ask for anything broadcast while we were disconnected, ahead of live updates
+            if (lastSeq !== null) params.set('last_seq', lastSeq);
+            const query = params.toString() ? `?${params}` : '';
+            const wsUrl = `ws://${window.location.host}/ws${query}`;
+            ws = new WebSocket(wsUrl);
+            
+            ws.onopen = () => {
+                console.log('WebSocket connected');
+                updateStatus(true);
+            };
+            
+            ws.onmessage = (event) => {
+                const message = JSON.parse(event.data);
+                
+                if (message.type === 'resync') {
+                    lastSeq = message.latest_seq;
+                    resync();
+                    return;
+                }
+                
+                if (message.type === 'alert') {
+                    showAlert(message);
+                    return;
+                }
+                
+                if (message.type === 'heartbeat') {
+                    handleHeartbeat(message);
+                    return;
+                }
+                
+                if (message.type === 'system') {
+                    bluetoothStatus = message;
+                    updateStatus(true);
+                    return;
+                }
+                
+                if (message.type === 'history') {
+                    // Backfill for a fresh page; a resuming client already has these points
+                    if (lastSeq === null) seedHistory(message);
+                    return;
+                }
+                
+                if (message.type === 'target_suggestion') {
+                    suggestions[message.device_address] = message;
+                    showSuggestion(message.device_address);
+                    return;
+                }
+                
+                if (message.type === 'settings') {
+                    // Confirms an edit, made here or on another page
+                    if (drawerSettings && drawerSettings.device_address === message.device_address) {
+                        loadSettings(message.device_address);
+                    }
+                    loadDevices();
+                    return;
+                }
+                
+                if (message.seq === undefined) {
+                    // Initial snapshot; already seen when resuming
+                    if (lastSeq !== null) return;
+                } else {
+                    lastSeq = Math.max(lastSeq || 0, message.seq);
+                }
+                
+                handleUpdate(message);
+            };
+            
+            ws.onerror = (error) => {
+                console.error('WebSocket error:', error);
+                updateStatus(false);
+            };
+            
+            ws.onclose = () => {
+                console.log('WebSocket disconnected');
+                updateStatus(false);
+                setTimeout(connect, 3000);
+            };
+        }
+
+        // Missed updates are gone from the server buffer: start the charts over
+        function resync() {
+            for (const addr in deviceData) {
+                deviceData[addr].readings = [];
+                deviceData[addr].timestamps = [];
+                updateChart(addr);
+            }
+            loadDevices();
+        }
+
+        const alertText = {
+            above_target: 'reached',
+            below_target: 'dropped to',
+            ambient_above: 'ambient reached',
+            battery_below: 'battery at',
+        };
+
+        function showAlert(alert) {
+            const line = document.getElementById(`alert-${alert.device_address}`);
+            if (!line) return;
+            setUnit(alert.unit);
+            const value = alert.kind === 'battery_below' ? `${alert.temperature}%` : formatTemp(alert.temperature);
+            line.textContent = `🚨 Sensor ${alert.sensor_index + 1} ${alertText[alert.kind]} ${value}`;
+        }
+
+        // Cook targets offered for newly seen devices, kept until applied or dismissed
+        const suggestions = {};
+
+        function showSuggestion(addr) {
+            const line = document.getElementById(`suggestion-${addr}`);
+            const suggestion = suggestions[addr];
+            if (!line) return;
+            if (!suggestion) {
+                line.innerHTML = '';
+                return;
+            }
+            setUnit(suggestion.unit);
+            line.innerHTML = `
+                <span></span>
+                <button data-apply>Apply</button>
+                <button class="secondary" data-dismiss>Dismiss</button>`;
+            line.querySelector('span').textContent =
+                `Suggested target for ${suggestion.meat.replace(/_/g, ' ')}: ${formatTemp(suggestion.target)}`;
+            line.querySelector('[data-apply]').addEventListener('click', () => applySuggestion(addr));
+            line.querySelector('[data-dismiss]').addEventListener('click', async () => {
+                delete suggestions[addr];
+                showSuggestion(addr);
+                await apiFetch(`/api/devices/${encodeURIComponent(addr)}/suggestion`, { method: 'DELETE' });
+            });
+        }
+
+        async function applySuggestion(addr) {
+            const suggestion = suggestions[addr];
+            const name = `${suggestion.meat.replace(/_/g, ' ')} cook`;
+            const response = await apiFetch(`/api/devices/${encodeURIComponent(addr)}/cook`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ name, target_temp: suggestion.target })
+            });
+            if (response.ok) {
+                delete suggestions[addr];
+                showSuggestion(addr);
+            } else {
+                document.getElementById(`alert-${addr}`).textContent = await response.text();
+            }
+        }
+
+        // Persistent over-temperature warning; only the user clears it
+        function showDamageWarning(addr, warning) {
+            const line = document.getElementById(`damage-${addr}`);
+            if (!warning) {
+                line.innerHTML = '';
+                return;
+            }
+            line.innerHTML = `<span></span><button class="secondary">Clear</button>`;
+            line.querySelector('span').textContent =
+                `🔥 Possible probe damage: reached ${formatTemp(warning.peak)} on ${new Date(warning.since).toLocaleString()}`;
+            line.querySelector('button').addEventListener('click', async () => {
+                const response = await apiFetch(`/api/devices/${encodeURIComponent(addr)}/damage-warning`, { method: 'DELETE' });
+                if (response.ok) showDamageWarning(addr, null);
+            });
+        }
+
+        function updateStatus(connected) {
+            const status = document.getElementById('status');
+            if (connected && bluetoothStatus && bluetoothStatus.phase === 'failed') {
+                status.className = 'status disconnected';
+                status.textContent = '⚠️ Connected - Bluetooth unavailable, showing history';
+            } else if (connected && bluetoothStatus && bluetoothStatus.phase !== 'monitoring') {
+                status.className = 'status connected';
+                status.textContent = `✅ Connected - Bluetooth ${bluetoothStatus.message}`;
+            } else if (connected) {
+                status.className = 'status connected';
+                status.textContent = '✅ Connected - Live Updates Active';
+            } else {
+                status.className = 'status disconnected';
+                status.textContent = '⚠️ Disconnected - Reconnecting...';
+            }
+        }
+
+        function handleUpdate(update) {
+            const addr = update.device_address;
+            
+            if (!deviceData[addr]) {
+                deviceData[addr] = {
+                    name: update.device_name,
+                    address: addr,
+                    primaryIndex: 0,
+                    readings: [],
+                    timestamps: []
+                };
+                createDeviceCard(addr);
+                // Picks up the probe label and sensor layout
+                loadDevices();
+            }
+            
+            const data = deviceData[addr];
+            setSensorValue(addr, update.sensor_index, null, update.temperature);
+            updateEta(addr, update.eta_seconds);
+            
+            // The headline number and chart follow the primary sensor only
+            if (update.sensor_index !== data.primaryIndex) {
+                return;
+            }
+            
+            data.readings.push(update.temperature);
+            data.timestamps.push(new Date(update.timestamp));
+            
+            // Keep the last 50 readings, or more while inside the history window
+            const cutoff = Date.now() - historyWindowMs;
+            while (data.readings.length > 50 && data.timestamps[0] < cutoff) {
+                data.readings.shift();
+                data.timestamps.shift();
+            }
+            
+            updateDeviceCard(addr, update);
+            updateChart(addr);
+        }
+
+        // Replace a chart's points with the history sent on connect
+        function seedHistory(history) {
+            const data = deviceData[history.device_address];
+            if (!data) return;
+            historyWindowMs = Math.max(historyWindowMs, history.minutes * 60 * 1000);
+            data.primaryIndex = history.sensor_index;
+            data.readings = history.points.map(point => point.temperature);
+            data.timestamps = history.points.map(point => new Date(point.timestamp));
+            updateChart(history.device_address);
+        }
+
+        function createDeviceCard(addr) {
+            const data = deviceData[addr];
+            const container = document.getElementById('devices');
+            
+            const card = document.createElement('div');
+            card.className = 'device-card';
+            card.id = `device-${addr}`;
+            card.innerHTML = `
+                <div class="device-header">
+                    <div>
+                        <div class="device-name" id="name-${addr}"></div>
+                        <div class="device-brand">Thermometer</div>
+                    </div>
+                    <button class="settings-button" title="Name, target and alerts">⚙️</button>
+                </div>
+                <div class="temperature-display">
+                    <div class="temp-value" id="temp-${addr}">--${unitSymbol}</div>
+                    <div class="temp-label">Internal Temperature</div>
+                    <div class="timestamp" id="timestamp-${addr}">No data</div>
+                    <div class="eta" id="eta-${addr}"></div>
+                    <div class="alert-line" id="alert-${addr}"></div>
+                    <div class="suggestion-line" id="suggestion-${addr}"></div>
+                    <div class="maintenance-badge" id="maintenance-${addr}"></div>
+                    <div class="suggestion-line damage-line" id="damage-${addr}"></div>
+                </div>
+                <div class="metrics">
+                    <div class="metric">
+                        <div class="metric-value" id="ambient-${addr}">--</div>
+                        <div class="metric-label">Ambient</div>
+                    </div>
+                    <div class="metric">
+                        <div class="metric-value" id="battery-${addr}">--</div>
+                        <div class="metric-label">Battery</div>
+                    </div>
+                    <div class="metric">
+                        <div class="metric-value" id="rssi-${addr}">--</div>
+                        <div class="metric-label">Signal</div>
+                    </div>
+                </div>
+                <div class="sensor-rows" id="sensors-${addr}"></div>
+                <div class="chart-container">
+                    <canvas id="chart-${addr}"></canvas>
+                </div>
+            `;
+            
+            container.appendChild(card);
+            // Names can be typed by the user, so never parsed as HTML
+            document.getElementById(`name-${addr}`).textContent = data.name;
+            card.querySelector('.settings-button').addEventListener('click', () => openSettings(addr));
+            showSuggestion(addr);
+            
+            // Create chart
+            charts[addr] = new LineChart(document.getElementById(`chart-${addr}`), {
+                color: '#dc2626',
+                fill: 'rgba(220, 38, 38, 0.1)',
+                beforeDraw: drawBands
+            });
+        }
+
+        // One row per sensor; rows for unseen sensors are added on first update
+        function setSensorValue(addr, index, label, temperature) {
+            const list = document.getElementById(`sensors-${addr}`);
+            let row = document.getElementById(`sensor-${addr}-${index}`);
+            if (!row) {
+                row = document.createElement('div');
+                row.className = 'sensor-row';
+                row.id = `sensor-${addr}-${index}`;
+                row.innerHTML = `
+                    <span class="sensor-label">${label || `Sensor ${index + 1}`}</span>
+                    <span class="sensor-value">--${unitSymbol}</span>
+                `;
+                row.title = 'Click to show this sensor as the main temperature';
+                row.addEventListener('click', () => setPrimarySensor(addr, index));
+                list.appendChild(row);
+            }
+            row.classList.toggle('primary', index === deviceData[addr].primaryIndex);
+            if (temperature !== null && temperature !== undefined) {
+                row.querySelector('.sensor-value').textContent = formatTemp(temperature);
+            }
+        }
+
+        async function setPrimarySensor(addr, index) {
+            try {
+                const response = await apiFetch(`/api/devices/${encodeURIComponent(addr)}/primary-sensor`, {
+                    method: 'PUT',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({ sensor_index: index })
+                });
+                if (!response.ok) {
+                    console.error('Failed to set primary sensor:', await response.text());
+                    return;
+                }
+                // The chart only holds the old sensor's history
+                deviceData[addr].readings = [];
+                deviceData[addr].timestamps = [];
+                await loadDevices();
+            } catch (error) {
+                console.error('Failed to set primary sensor:', error);
+            }
+        }
+
+        // Load known devices with their sensor layout
+        async function loadDevices() {
+            try {
+                const response = await apiFetch('/api/devices');
+                const devices = await response.json();
+                
+                // Devices deleted here or on another page
+                const listed = new Set(devices.map(device => device.device_address));
+                for (const addr of Object.keys(deviceData)) {
+                    if (!listed.has(addr)) removeDeviceCard(addr);
+                }
+                
+                for (const device of devices) {
+                    const addr = device.device_address;
+                    setUnit(device.unit);
+                    if (!deviceData[addr]) {
+                        deviceData[addr] = {
+                            name: device.display_name,
+                            address: addr,
+                            primaryIndex: device.primary_sensor_index,
+                            readings: [],
+                            timestamps: []
+                        };
+                        createDeviceCard(addr);
+                    } else {
+                        deviceData[addr].primaryIndex = device.primary_sensor_index;
+                        deviceData[addr].name = device.display_name;
+                        document.getElementById(`name-${addr}`).textContent = device.display_name;
+                    }
+                    
+                    for (const sensor of device.sensors) {
+                        setSensorValue(addr, sensor.index, sensor.label, sensor.temperature);
+                    }
+                    document.getElementById(`maintenance-${addr}`).textContent =
+                        device.calibration_due ? '🔧 Calibration due' : '';
+                    showDamageWarning(addr, device.damage_warning);
+                    // The chart plots the primary sensor only
+                    charts[addr].setTarget(
+                        device.target_sensor_index === device.primary_sensor_index ? device.target_temp : null
+                    );
+                    if (device.primary_temperature !== null) {
+                        document.getElementById(`temp-${addr}`).textContent =
+                            formatTemp(device.primary_temperature);
+                    }
+                }
+            } catch (error) {
+                console.error('Failed to load devices:', error);
+            }
+        }
+
+        function removeDeviceCard(addr) {
+            const card = document.getElementById(`device-${addr}`);
+            if (card) card.remove();
+            delete deviceData[addr];
+            delete charts[addr];
+            if (drawerSettings && drawerSettings.device_address === addr) closeSettings();
+        }
+
+        // Settings drawer: an edit shows at once, is sent over REST, and is
+        // confirmed by the `settings` WebSocket message that reloads the drawer
+        let drawerSettings = null;
+
+        const ruleText = {
+            above_target: 'Above',
+            below_target: 'Below',
+            ambient_above: 'Ambient above',
+            approaching_target: 'Near target',
+            target_reached: 'Target reached',
+            battery_below: 'Battery below',
+        };
+
+        // For text between tags only; attributes get their values through DOM properties
+        function escapeHtml(text) {
+            return String(text).replace(/[&<>"']/g, c =>
+                ({ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#39;' })[c]);
+        }
+
+        async function openSettings(addr) {
+            drawerSettings = null;
+            document.getElementById('settings-body').innerHTML = '';
+            document.getElementById('settings-error').textContent = '';
+            document.getElementById('settings-drawer').classList.add('open');
+            await loadSettings(addr);
+        }
+
+        function closeSettings() {
+            document.getElementById('settings-drawer').classList.remove('open');
+            drawerSettings = null;
+        }
+
+        async function loadSettings(addr) {
+            try {
+                const response = await apiFetch(`/api/devices/${encodeURIComponent(addr)}/settings`);
+                if (!response.ok) throw new Error(await response.text());
+                drawerSettings = await response.json();
+                setUnit(drawerSettings.unit);
+                renderSettings();
+            } catch (error) {
+                document.getElementById('settings-error').textContent = `Could not load settings: ${error.message}`;
+            }
+        }
+
+        // Apply `change` to the drawer now and send the request; undo the change if it fails
+        async function optimistic(change, url, options) {
+            const addr = drawerSettings.device_address;
+            const previous = JSON.parse(JSON.stringify(drawerSettings));
+            change(drawerSettings);
+            drawerSettings.pending = true;
+            document.getElementById('settings-error').textContent = '';
+            renderSettings();
+            try {
+                const response = await apiFetch(url, {
+                    ...options,
+                    headers: { 'Content-Type': 'application/json' }
+                });
+                if (!response.ok) throw new Error(await response.text());
+                // Without the WebSocket no confirmation comes; ask directly
+                if (!ws || ws.readyState !== WebSocket.OPEN) loadSettings(addr);
+            } catch (error) {
+                if (drawerSettings && drawerSettings.device_address === addr) {
+                    drawerSettings = previous;
+                    renderSettings();
+                    document.getElementById('settings-error').textContent = error.message;
+                }
+            }
+        }
+
+        function renderSettings() {
+            const s = drawerSettings;
+            if (!s) return;
+            document.getElementById('settings-title').textContent = s.display_name;
+            document.getElementById('settings-status').textContent = s.pending ? 'Saving…' : '';
+            const target = s.cook && s.cook.target_temp !== null ? s.cook.target_temp : '';
+            const cookNote = s.cook
+                ? `Cook: ${escapeHtml(s.cook.name)}`
+                : 'No cook running; setting a target starts one.';
+            const sensors = Array.from({ length: Math.max(s.sensor_count, 1) }, (_, i) =>
+                `<option value="${i}" ${i === s.primary_sensor_index ? 'selected' : ''}>Sensor ${i + 1}</option>`
+            ).join('');
+
+            let alerts;
+            if (s.alerts === null) {
+                alerts = '<p class="drawer-note">Alerts need a Premium license.</p>';
+            } else {
+                const rules = s.alerts.map(rule => {
+                    const threshold = rule.threshold === null || rule.threshold === undefined ? ''
+                        : rule.kind === 'battery_below' ? `${rule.threshold}%` : formatTemp(rule.threshold);
+                    const snoozed = rule.snoozed_until
+                        ? `😴 until ${new Date(rule.snoozed_until).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })}`
+                        : '';
+                    const saved = rule.id !== null;
+                    return `
+                        <div class="drawer-row">
+                            <span class="grow">Sensor ${rule.sensor_index + 1}: ${ruleText[rule.kind]} ${threshold} ${snoozed}</span>
+                            ${saved ? `<button class="secondary" data-snooze="${rule.id}" data-minutes="${rule.snoozed_until ? 0 : 15}">
+                                ${rule.snoozed_until ? 'Wake' : 'Snooze 15m'}</button>
+                            <button class="secondary" data-delete="${rule.id}">✕</button>` : ''}
+                        </div>`;
+                }).join('');
+                alerts = `
+                    ${rules || '<p class="drawer-note">No alerts yet.</p>'}
+                    <div class="drawer-row">
+                        <select id="settings-alert-kind">
+                            <option value="above_target">Above</option>
+                            <option value="below_target">Below</option>
+                        </select>
+                        <select id="settings-alert-sensor">${sensors}</select>
+                        <input id="settings-alert-threshold" class="grow" type="number" step="0.1" placeholder="${unitSymbol}">
+                        <button id="settings-alert-add">Add</button>
+                    </div>`;
+            }
+
+            document.getElementById('settings-body').innerHTML = `
+                <h3>Name</h3>
+                <div class="drawer-row">
+                    <input id="settings-name" class="grow" maxlength="64">
+                    <button id="settings-name-save">Save</button>
+                </div>
+                <h3>Target temperature</h3>
+                <div class="drawer-row">
+                    <input id="settings-target" class="grow" type="number" step="0.1" placeholder="${unitSymbol}" value="${target}">
+                    <button id="settings-target-save">Set</button>
+                    <button id="settings-target-clear" class="secondary" ${s.cook ? '' : 'disabled'}>Clear</button>
+                </div>
+                <p class="drawer-note">${cookNote}</p>
+                <h3>Alerts</h3>
+                ${alerts}
+                <h3>Remove</h3>
+                <div class="drawer-row">
+                    <span class="grow drawer-note">Deletes the device and all its readings.</span>
+                    <button id="settings-delete" class="secondary" ${s.cook ? 'disabled' : ''}>Delete device</button>
+                </div>
+            `;
+
+            // Names come from BLE advertisements and users, so never through innerHTML
+            const nameInput = document.getElementById('settings-name');
+            nameInput.placeholder = s.display_name;
+            nameInput.value = s.alias || '';
+            document.getElementById('settings-name-save').addEventListener('click', saveName);
+            document.getElementById('settings-target-save').addEventListener('click', () => saveTarget(false));
+            document.getElementById('settings-target-clear').addEventListener('click', () => saveTarget(true));
+            document.getElementById('settings-delete').addEventListener('click', deleteDevice);
+            const add = document.getElementById('settings-alert-add');
+            if (add) add.addEventListener('click', addAlert);
+            document.querySelectorAll('#settings-body [data-delete]').forEach(button =>
+                button.addEventListener('click', () => deleteAlert(Number(button.dataset.delete))));
+            document.querySelectorAll('#settings-body [data-snooze]').forEach(button =>
+                button.addEventListener('click', () =>
+                    snoozeAlert(Number(button.dataset.snooze), Number(button.dataset.minutes))));
+        }
+
+        function saveName() {
+            const name = document.getElementById('settings-name').value.trim();
+            optimistic(
+                s => { s.alias = name || null; if (name) s.display_name = name; },
+                `/api/devices/${encodeURIComponent(drawerSettings.device_address)}/name`,
+                { method: 'PUT', body: JSON.stringify({ name: name || null }) }
+            );
+        }
+
+        function saveTarget(clear) {
+            const addr = drawerSettings.device_address;
+            const target = clear ? null : parseFloat(document.getElementById('settings-target').value);
+            if (!clear && !Number.isFinite(target)) {
+                document.getElementById('settings-error').textContent = 'Enter a target temperature';
+                return;
+            }
+            if (drawerSettings.cook) {
+                optimistic(
+                    s => { s.cook.target_temp = target; },
+                    `/api/devices/${encodeURIComponent(addr)}/cook/target`,
+                    { method: 'PUT', body: JSON.stringify({ target_temp: target }) }
+                );
+            } else if (!clear) {
+                // Targets belong to a cook, so start one
+                const name = `${drawerSettings.display_name} cook`;
+                optimistic(
+                    s => { s.cook = { name, target_temp: target }; },
+                    `/api/devices/${encodeURIComponent(addr)}/cook`,
+                    { method: 'POST', body: JSON.stringify({ name, target_temp: target }) }
+                );
+            }
+        }
+
+        async function deleteDevice() {
+            const addr = drawerSettings.device_address;
+            if (!confirm(`Delete ${drawerSettings.display_name} and all its readings?`)) return;
+            try {
+                const response = await apiFetch(`/api/devices/${encodeURIComponent(addr)}`, { method: 'DELETE' });
+                if (!response.ok) throw new Error(await response.text());
+                removeDeviceCard(addr);
+            } catch (error) {
+                document.getElementById('settings-error').textContent = error.message;
+            }
+        }
+
+        function addAlert() {
+            const kind = document.getElementById('settings-alert-kind').value;
+            const sensor = Number(document.getElementById('settings-alert-sensor').value);
+            const threshold = parseFloat(document.getElementById('settings-alert-threshold').value);
+            if (!Number.isFinite(threshold)) {
+                document.getElementById('settings-error').textContent = 'Enter an alert temperature';
+                return;
+            }
+            const rule = { id: null, kind, sensor_index: sensor, threshold, snoozed_until: null };
+            optimistic(
+                s => { s.alerts.push(rule); },
+                '/api/alerts',
+                {
+                    method: 'POST',
+                    body: JSON.stringify({ device_address: drawerSettings.device_address, sensor_index: sensor, kind, threshold })
+                }
+            );
+        }
+
+        function deleteAlert(id) {
+            optimistic(s => { s.alerts = s.alerts.filter(rule => rule.id !== id); }, `/api/alerts/${id}`, { method: 'DELETE' });
+        }
+
+        function snoozeAlert(id, minutes) {
+            optimistic(
+                s => {
+                    const rule = s.alerts.find(rule => rule.id === id);
+                    if (rule) rule.snoozed_until = minutes ? new Date(Date.now() + minutes * 60000).toISOString() : null;
+                },
+                `/api/alerts/${id}/snooze`,
+                { method: 'POST', body: JSON.stringify({ minutes }) }
+            );
+        }
+
+        function updateEta(addr, etaSeconds) {
+            const etaEl = document.getElementById(`eta-${addr}`);
+            if (etaSeconds === null || etaSeconds === undefined) {
+                etaEl.textContent = '';
+            } else if (etaSeconds === 0) {
+                etaEl.textContent = 'Ready to remove';
+            } else {
+                etaEl.textContent = `Done in ${Math.round(etaSeconds / 60)} min`;
+            }
+        }
+
+        function updateDeviceCard(addr, update) {
+            setUnit(update.unit);
+            document.getElementById(`temp-${addr}`).textContent = 
+                formatTemp(update.temperature);
+            
+            document.getElementById(`ambient-${addr}`).textContent = 
+                update.ambient_temp ? formatTemp(update.ambient_temp) : '--';
+            
+            document.getElementById(`battery-${addr}`).textContent = 
+                update.battery_level ? `${update.battery_level}%` : '--';
+            
+            document.getElementById(`rssi-${addr}`).textContent = 
+                `${update.signal_strength} dBm`;
+            
+            // Update timestamp
+            const timestampEl = document.getElementById(`timestamp-${addr}`);
+            const now = new Date(update.timestamp);
+            timestampEl.textContent = `Last: ${now.toLocaleTimeString()}`;
+            // Live updates age from arrival on the monotonic clock, so a system clock step never
+            // ages cards; the unsequenced connect snapshot is a stored row, aged by its timestamp
+            const storedAgeMs = update.seq === undefined ? Math.max(0, Date.now() - now.getTime()) : 0;
+            timestampEl.dataset.receivedAt = performance.now() - storedAgeMs;
+            updateTimestampAging(addr);
+        }
+
+        function updateChart(addr) {
+            const chart = charts[addr];
+            const data = deviceData[addr];
+            
+            chart.setData(
+                data.timestamps.map(t => t.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })),
+                data.readings
+            );
+        }
+
+        function updateTimestampAging(addr) {
+            const timestampEl = document.getElementById(`timestamp-${addr}`);
+            if (!timestampEl || !timestampEl.dataset.receivedAt) return;
+            
+            const ageSeconds = (performance.now() - Number(timestampEl.dataset.receivedAt)) / 1000;
+            
+            // Remove all aging classes
+            timestampEl.classList.remove('aged', 'stale');
+            
+            // Add appropriate class based on age
+            if (ageSeconds > 60) {
+                timestampEl.classList.add('stale');
+            } else if (ageSeconds > 30) {
+                timestampEl.classList.add('aged');
+            }
+        }
+
+        // No new reading, but the server says how old the last one is
+        function handleHeartbeat(beat) {
+            // One per sensor; the card shows the primary one
+            const data = deviceData[beat.device_address];
+            if (!data || beat.sensor_index !== data.primaryIndex) return;
+            const timestampEl = document.getElementById(`timestamp-${beat.device_address}`);
+            if (!timestampEl) return;
+            timestampEl.dataset.receivedAt = performance.now() - beat.age_secs * 1000;
+            timestampEl.title = `Quiet for ${beat.age_secs}s, last ${formatTemp(beat.temperature)}`;
+            updateTimestampAging(beat.device_address);
+        }
+
+        function updateAllTimestamps() {
+            for (const addr in deviceData) {
+                updateTimestampAging(addr);
+            }
+        }
+
+        // Update aging indicators every second
+        setInterval(updateAllTimestamps, 1000);
+
+        // Load premium status on page load
+        loadPremiumStatus();
+        loadChartBands();
+        loadDevices();
+
+        // Start connection
+        connect();
+    </script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble::{BleEnvironmentError, BlePhase};
+    use crate::{License, LicenseValidator, MeaterProtocol};
+    use axum::extract::ConnectInfo;
+    use axum::http::Request;
+    use tower::Service;
+
+    async fn test_state() -> AppState {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let (tx, _rx) = broadcast::channel(16);
+        AppState {
+            db: db.clone(),
+            tx,
+            license: Arc::new(LicenseManager::with_license(License::free(), LicenseValidator::new(), db.clone())),
+            config: Arc::new(Config::default()),
+            ble_health: BleHealth::default(),
+            fanout: Arc::new(Fanout::new(16)),
+            log_control: None,
+            alerts: None,
+            access_log: None,
+            debug_commands: None,
+            simulator: None,
+            #[cfg(feature = "pit-controller")]
+            pit_controller: None,
+        }
+    }
+
+    fn premium_license(db: &Arc<Database>) -> Arc<LicenseManager> {
+        let premium = License {
+            tier: crate::PremiumTier::Premium,
+            features: crate::PremiumFeatures::premium(),
+            ..License::free()
+        };
+        Arc::new(LicenseManager::with_license(premium, LicenseValidator::new(), db.clone()))
+    }
+
+    /// Log sink for asserting on what a call logged
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
         }
-        .device-card {
-            background: rgba(255, 255, 255, 0.95);
-            color: #1e293b;
-            border-radius: 12px;
-            padding: 20px;
-            box-shadow: 0 8px 16px rgba(0,0,0,0.2);
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
         }
-        .device-header {
-            display: flex;
-            justify-content: space-between;
-            align-items: center;
-            margin-bottom: 15px;
-            padding-bottom: 15px;
-            border-bottom: 2px solid #e2e8f0;
+    }
+
+    fn sample_update() -> TemperatureUpdate {
+        TemperatureUpdate {
+            device_address: "AA:BB".to_string(),
+            device_name: "probe".to_string(),
+            timestamp: Utc::now(),
+            sensor_index: 0,
+            temperature: 60.0,
+            ambient_temp: None,
+            unit: TemperatureUnit::Celsius,
+            battery_level: None,
+            signal_strength: 0,
+            eta_seconds: None,
+            reading_mode: ReadingMode::Normal,
+            virtual_sensors: None,
         }
-        .device-name {
-            font-size: 1.3em;
-            font-weight: bold;
-            color: #1e40af;
+    }
+
+    #[test]
+    fn test_broadcast_without_subscribers_is_quiet() {
+        let (tx, rx) = broadcast::channel(4);
+        drop(rx);
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let delivered = tracing::subscriber::with_default(subscriber, || broadcast_update(&tx, sample_update()));
+        assert_eq!(delivered, 0);
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("No subscribers"), "{logs}");
+        assert!(!logs.contains("WARN") && !logs.contains("ERROR"), "{logs}");
+
+        let mut rx = tx.subscribe();
+        assert_eq!(broadcast_update(&tx, sample_update()), 1);
+        assert_eq!(rx.try_recv().unwrap().sensor_index, 0);
+    }
+
+    #[tokio::test]
+    async fn test_device_summary_lists_each_sensor() {
+        let state = test_state().await;
+        state.db.upsert_device("11:22:33:44:55:66", "Tri", "Unknown", "Tri", 3)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        for (index, temperature) in [(0, 150.0), (1, 160.0), (2, 170.0)] {
+            // An older reading per sensor that must not be reported
+            state.db
+                .insert_reading("11:22:33:44:55:66", now - chrono::Duration::minutes(5), index, Temperature::from_celsius(100.0), None, None, -60)
+                .await
+                .unwrap();
+            state.db
+                .insert_reading("11:22:33:44:55:66", now, index, Temperature::from_celsius(temperature), None, None, -60)
+                .await
+                .unwrap();
         }
-        .device-brand {
-            font-size: 0.85em;
-            color: #64748b;
-            text-transform: uppercase;
-            letter-spacing: 1px;
+
+        let device = state.db.get_device("11:22:33:44:55:66").await.unwrap();
+        let summary = device_summary(&state.db, device, TemperatureUnit::Celsius, 0).await.unwrap();
+
+        assert_eq!(summary.sensors.len(), 3);
+        let values: Vec<Option<f32>> = summary.sensors.iter().map(|s| s.temperature).collect();
+        assert_eq!(values, vec![Some(150.0), Some(160.0), Some(170.0)]);
+        assert_eq!(summary.sensors[2].index, 2);
+        assert_eq!(summary.sensors[2].label, "Probe 3");
+        assert_eq!(summary.primary_sensor_index, 0);
+        assert_eq!(summary.primary_temperature, Some(150.0));
+    }
+
+    #[tokio::test]
+    async fn test_api_reports_configured_unit() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1)
+            .await
+            .unwrap();
+        state.db
+            .insert_reading(address, Utc::now(), 0, Temperature::from_celsius(100.0), None, None, -60)
+            .await
+            .unwrap();
+
+        let devices = get_json(&state, "/api/devices").await;
+        assert_eq!(devices[0]["unit"], "fahrenheit");
+        assert_eq!(devices[0]["primary_temperature"], 212.0);
+
+        let mut config = Config::default();
+        config.temperature.unit = "celsius".to_string();
+        state.config = Arc::new(config);
+        let devices = get_json(&state, "/api/devices").await;
+        assert_eq!(devices[0]["unit"], "celsius");
+        assert_eq!(devices[0]["primary_temperature"], 100.0);
+        let history = get_json(&state, &format!("/api/devices/{}/history?hours=1", address)).await;
+        assert_eq!(history[0]["temperature"], 100.0);
+        assert_eq!(history[0]["unit"], "celsius");
+    }
+
+    #[tokio::test]
+    async fn test_device_summary_labels_probes() {
+        let state = test_state().await;
+        state.db.upsert_device("11:22:33:44:55:66", "MEATER", "MeaterPlus", "MEATER", 2)
+            .await
+            .unwrap();
+        let identity = crate::ProbeIdentity {
+            serial_number: Some("10002A3B".to_string()),
+            probe_id: 2,
+            color: ProbeColor::Red,
+        };
+        state.db
+            .upsert_device_with_identity("AA:BB:CC:DD:EE:FF", "cA00probe", "MeatStickV", "cA00probe", 8, &identity)
+            .await
+            .unwrap();
+
+        let probe = get_json(&state, "/api/devices/AA:BB:CC:DD:EE:FF").await;
+        assert_eq!(probe["display_name"], "Probe 2 (Red)");
+        assert_eq!(probe["serial_number"], "10002A3B");
+        assert_eq!(probe["probe_id"], 2);
+        assert_eq!(probe["probe_color"], "red");
+
+        let meater = get_json(&state, "/api/devices/11:22:33:44:55:66").await;
+        assert_eq!(meater["display_name"], "MEATER");
+        assert!(meater["probe_color"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_device_list_filters_by_brand() {
+        let state = test_state().await;
+        state.db.upsert_device("AA", "MEATER", "MeaterPlus", "MEATER", 2).await.unwrap();
+        state.db.upsert_device("BB", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        state.db.upsert_device("CC", "TP25", "ThermoProTP25", "TP25", 4).await.unwrap();
+        state.db.upsert_device("DD", "MEATER", "MeaterPlus", "MEATER", 2).await.unwrap();
+        state.db.upsert_device("EE", "MEATER Block", "MeaterBlock", "MEATER Block", 2).await.unwrap();
+
+        let addresses = |devices: serde_json::Value| -> Vec<String> {
+            let mut addresses: Vec<String> = devices
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|d| d["device_address"].as_str().unwrap().to_string())
+                .collect();
+            addresses.sort();
+            addresses
+        };
+        assert_eq!(addresses(get_json(&state, "/api/devices?brand=meaterplus").await), ["AA", "DD"]);
+        assert_eq!(addresses(get_json(&state, "/api/devices?brand=meater").await), ["AA", "DD", "EE"], "brand family");
+        assert_eq!(addresses(get_json(&state, "/api/devices?brand=MEATSTICKV").await), ["BB"]);
+        assert!(addresses(get_json(&state, "/api/devices?brand=Weber").await).is_empty());
+        assert!(addresses(get_json(&state, "/api/devices?brand=%25").await).is_empty(), "wildcards are literal");
+        assert!(addresses(get_json(&state, "/api/devices?brand=Meater_lus").await).is_empty());
+        assert_eq!(addresses(get_json(&state, "/api/devices?brand=").await).len(), 5, "empty brand lists all");
+        assert_eq!(addresses(get_json(&state, "/api/devices").await).len(), 5);
+
+        let names: Vec<String> = get_json(&state, "/api/devices?sort=name&brand=meaterplus")
+            .await
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["device_address"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["AA", "DD"], "same name, tie broken by address");
+        let request = Request::builder().uri("/api/devices?sort=color").body(Body::empty()).unwrap();
+        assert_eq!(router(state.clone()).call(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_unit_query_overrides_config() {
+        let state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        state.db
+            .insert_reading(address, Utc::now(), 0, Temperature::from_celsius(100.0), Some(Temperature::from_celsius(0.0)), None, -60)
+            .await
+            .unwrap();
+
+        let history = get_json(&state, &format!("/api/devices/{}/history?hours=1&unit=c", address)).await;
+        assert_eq!(history[0]["temperature"], 100.0);
+        assert_eq!(history[0]["ambient_temp"], 0.0);
+        assert_eq!(history[0]["unit"], "celsius");
+        let devices = get_json(&state, "/api/devices?unit=celsius").await;
+        assert_eq!(devices[0]["primary_temperature"], 100.0);
+        // Config default (Fahrenheit) when not asked
+        let device = get_json(&state, &format!("/api/devices/{}", address)).await;
+        assert_eq!(device["latest_reading"]["temperature"], 212.0);
+        assert_eq!(device["latest_reading"]["unit"], "fahrenheit");
+        let device = get_json(&state, &format!("/api/devices/{}?unit=F", address)).await;
+        assert_eq!(device["latest_reading"]["ambient_temp"], 32.0);
+
+        let request = Request::builder().uri("/api/devices?unit=kelvin").body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_primary_sensor_drives_headline_temperature() {
+        let state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "cA00probe", "MeatStickV", "cA00probe", 8)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        for index in 0..8 {
+            state.db
+                .insert_reading(address, now, index, Temperature::from_celsius(100.0 + index as f32), None, None, -60)
+                .await
+                .unwrap();
         }
-        .temperature-display {
-            text-align: center;
-            margin: 20px 0;
+
+        let device = state.db.get_device(address).await.unwrap();
+        let summary = device_summary(&state.db, device, TemperatureUnit::Celsius, 0).await.unwrap();
+        assert_eq!(summary.primary_sensor_index, 3);
+        assert_eq!(summary.primary_temperature, Some(103.0));
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri(format!("/api/devices/{}/primary-sensor", address))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"sensor_index":7}"#))
+            .unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let device = state.db.get_device(address).await.unwrap();
+        let summary = device_summary(&state.db, device, TemperatureUnit::Celsius, 0).await.unwrap();
+        assert_eq!(summary.primary_sensor_index, 7);
+        assert_eq!(summary.primary_temperature, Some(107.0));
+
+        // Out-of-range sensors are rejected and leave the choice unchanged
+        let request = Request::builder()
+            .method("PUT")
+            .uri(format!("/api/devices/{}/primary-sensor", address))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"sensor_index":8}"#))
+            .unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(state.db.get_primary_sensor(address).await.unwrap(), Some(7));
+    }
+
+    async fn get_json(state: &AppState, uri: &str) -> serde_json::Value {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_health_is_served_while_bluetooth_starts() {
+        /// A radio whose scan window only closes when the test says so
+        struct FakeCentral(tokio::sync::watch::Receiver<bool>);
+
+        impl crate::ble::ScanCentral for FakeCentral {
+            async fn start_scan(&self) -> Result<(), BleEnvironmentError> {
+                Ok(())
+            }
+
+            async fn stop_scan(&self) -> Result<(), BleEnvironmentError> {
+                Ok(())
+            }
+
+            async fn scan_window(&self, _duration: std::time::Duration) {
+                let _ = self.0.clone().wait_for(|done| *done).await;
+            }
         }
-        .temp-value {
-            font-size: 3em;
-            font-weight: bold;
-            color: #dc2626;
+
+        let state = test_state().await;
+        assert_eq!(get_json(&state, "/api/health").await["ble"]["phase"], "starting");
+
+        let (finish_scan, scan_window) = tokio::sync::watch::channel(false);
+        let health = state.ble_health.clone();
+        let mut changes = health.subscribe();
+        let (_stop, mut shutdown) = broadcast::channel(1);
+        let scan = tokio::spawn(async move {
+            let central = FakeCentral(scan_window);
+            crate::ble::run_scan(&central, &health, std::time::Duration::from_secs(30), &mut shutdown).await
+        });
+        assert_eq!(changes.recv().await.unwrap().message, "scanning...");
+
+        // Served while the scan is still running
+        let health = get_json(&state, "/api/health").await;
+        assert_eq!(health["status"], "ok");
+        assert_eq!(health["ble"]["phase"], "scanning");
+        assert_eq!(health["ble"]["message"], "scanning...");
+        assert!(!scan.is_finished());
+
+        finish_scan.send(true).unwrap();
+        assert_eq!(scan.await.unwrap(), Ok(true));
+        state.ble_health.set_phase(BlePhase::Connecting { devices: 2 });
+        let status = changes.recv().await.unwrap();
+        let message = serde_json::to_value(ControlMessage::System(status)).unwrap();
+        assert_eq!(message["type"], "system");
+        assert_eq!(message["message"], "connecting to 2 devices...");
+        assert_eq!(get_json(&state, "/api/health").await["ble"]["devices"], 2);
+
+        state.ble_health.set_error(BleEnvironmentError::AdapterOff);
+        let health = get_json(&state, "/api/health").await;
+        assert_eq!((health["status"].as_str(), health["ble"]["kind"].as_str()), (Some("degraded"), Some("adapter_off")));
+        assert_eq!(health["ble"]["phase"], "failed");
+    }
+
+    #[tokio::test]
+    async fn test_device_details_show_every_sensor() {
+        use chrono::TimeZone;
+        let state = test_state().await;
+        state.db.upsert_device("AA", "six-probe", "Unknown", "six-probe", 6).await.unwrap();
+        let start = Utc.with_ymd_and_hms(2026, 1, 20, 12, 0, 0).unwrap();
+        let celsius = |index: usize| Temperature::from_celsius(60.0 + index as f32);
+
+        // Each sensor reports in its own packet, the last one inserted being sensor 5
+        db_reading(&state, start, 0, Temperature::from_celsius(20.0)).await;
+        for index in 0..6 {
+            db_reading(&state, start + chrono::Duration::seconds(index as i64 + 1), index, celsius(index)).await;
         }
-        .temp-label {
-            font-size: 0.9em;
-            color: #64748b;
-            margin-top: 5px;
+
+        let details = get_json(&state, "/api/devices/AA?unit=celsius").await;
+        let sensors = details["sensors"].as_array().unwrap();
+        assert_eq!(sensors.len(), 6);
+        for (index, sensor) in sensors.iter().enumerate() {
+            assert_eq!(sensor["index"], index);
+            assert!((sensor["temperature"].as_f64().unwrap() - celsius(index).celsius() as f64).abs() < 1e-3);
+            let timestamp: DateTime<Utc> = serde_json::from_value(sensor["timestamp"].clone()).unwrap();
+            assert_eq!(timestamp, start + chrono::Duration::seconds(index as i64 + 1));
         }
-        .timestamp {
-            text-align: center;
-            font-size: 0.75em;
-            color: #94a3b8;
-            margin-top: 8px;
+    }
+
+    #[tokio::test]
+    async fn test_damage_warning_shown_until_cleared() {
+        let state = test_state().await;
+        state.db.upsert_device("AA", "MEATER", "MeaterOriginal", "MEATER", 2).await.unwrap();
+        assert!(get_json(&state, "/api/devices/AA").await["damage_warning"].is_null());
+
+        let at = Utc::now();
+        state.db.flag_probe_damage("AA", Temperature::from_fahrenheit(220.0), at).await.unwrap();
+        let warning = &get_json(&state, "/api/devices/AA?unit=fahrenheit").await["damage_warning"];
+        assert!((warning["peak"].as_f64().unwrap() - 220.0).abs() < 0.1);
+        assert!(get_json(&state, "/api/devices").await[0]["damage_warning"].is_object());
+
+        let clear = || Request::builder().method("DELETE").uri("/api/devices/AA/damage-warning").body(Body::empty()).unwrap();
+        assert_eq!(router(state.clone()).call(clear()).await.unwrap().status(), StatusCode::NO_CONTENT);
+        assert_eq!(router(state.clone()).call(clear()).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert!(get_json(&state, "/api/devices/AA").await["damage_warning"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_suggestion_kept_until_applied_or_dismissed() {
+        let state = test_state().await;
+        state.db.upsert_device("AA", "MEATER", "MeaterOriginal", "MEATER", 2).await.unwrap();
+        let suggest = |address: &str| {
+            state.fanout.suggest_target(crate::fanout::TargetSuggestion {
+                device_address: address.to_string(),
+                meat: "brisket".to_string(),
+                target: 203.0,
+                unit: TemperatureUnit::Fahrenheit,
+            })
+        };
+
+        // Dismissed on one dashboard: gone for the next one too
+        suggest("AA");
+        let dismiss = || Request::builder().method("DELETE").uri("/api/devices/AA/suggestion").body(Body::empty()).unwrap();
+        assert_eq!(router(state.clone()).call(dismiss()).await.unwrap().status(), StatusCode::NO_CONTENT);
+        assert_eq!(router(state.clone()).call(dismiss()).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert!(state.fanout.pending_suggestions().is_empty());
+
+        // Applied: starting the cook answers it
+        suggest("AA");
+        let start = Request::builder()
+            .method("POST")
+            .uri("/api/devices/AA/cook")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name":"brisket cook","target_temp":95.0}"#))
+            .unwrap();
+        assert_eq!(router(state.clone()).call(start).await.unwrap().status(), StatusCode::CREATED);
+        assert!(state.fanout.pending_suggestions().is_empty());
+    }
+
+    async fn db_reading(state: &AppState, timestamp: DateTime<Utc>, sensor_index: usize, temperature: Temperature) {
+        state.db.insert_reading("AA", timestamp, sensor_index, temperature, None, None, -60).await.unwrap();
+    }
+
+    fn post_log_level(body: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/log-level")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn log_level_request(method: &str, token: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().method(method).uri("/api/admin/log-level");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
         }
-        .timestamp.aged {
-            font-style: italic;
-            color: #f59e0b;
+        request.body(Body::empty()).unwrap()
+    }
+
+    async fn log_level_status_json(state: &AppState) -> serde_json::Value {
+        let response = router(state.clone()).call(log_level_request("GET", Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_log_level_burst_endpoints() {
+        use tracing_subscriber::{reload, EnvFilter};
+
+        let mut state = test_state().await;
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().admin_token = Some("secret".to_string());
+        state.config = Arc::new(config);
+        let response = router(state.clone()).call(post_log_level(r#"{"level":"debug","duration_secs":60}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let (_layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        state.log_control = Some(Arc::new(LogControl::new(handle, "info")));
+
+        let response = router(state.clone())
+            .call(post_log_level(r#"{"level":"debug","duration_secs":7200}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = router(state.clone())
+            .call(post_log_level(r#"{"level":"debug","duration_secs":60,"targets":["btleplug"]}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let status = log_level_status_json(&state).await;
+        assert_eq!(status["current_filter"], "btleplug=debug,info");
+        assert_eq!(status["active"]["targets"][0], "btleplug");
+
+        // Admin only
+        for method in ["GET", "DELETE"] {
+            let response = router(state.clone()).call(log_level_request(method, None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", method);
+            let response = router(state.clone()).call(log_level_request(method, Some("wrong"))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", method);
         }
-        .timestamp.stale {
-            font-style: italic;
-            color: #ef4444;
+        assert!(log_level_status_json(&state).await["active"].is_object(), "burst still running");
+
+        let response = router(state.clone()).call(log_level_request("DELETE", Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let status = log_level_status_json(&state).await;
+        assert_eq!(status["current_filter"], "info");
+        assert!(status["active"].is_null());
+        assert!(status["history"][0]["reverted_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_recompute_corrects_stale_sensor_count() {
+        let mut state = test_state().await;
+        let stale = "AA:BB:CC:DD:EE:01";
+        let current = "AA:BB:CC:DD:EE:02";
+        state.db.upsert_device(stale, "cA00probe", "MeatStickV", "cA00probe", 1).await.unwrap();
+        state.db.upsert_device(current, "TP25", "ThermoProTP25", "TP25", 4).await.unwrap();
+        state.db.upsert_device("AA:BB:CC:DD:EE:03", "iGrill_V2", "Unknown(\"iGrill_V2\")", "iGrill_V2", 1).await.unwrap();
+        // Only ever used jack 1; an earlier recompute had cut it down to one sensor
+        let one_jack = "AA:BB:CC:DD:EE:04";
+        state.db.upsert_device(one_jack, "TP25", "ThermoProTP25", "TP25", 1).await.unwrap();
+        state.db.insert_reading(one_jack, Utc::now(), 0, Temperature::from_celsius(60.0), None, None, -60).await.unwrap();
+        let newest = Utc::now() + chrono::Duration::minutes(5);
+        for index in 0..8 {
+            state.db.insert_reading(stale, newest, index, Temperature::from_celsius(60.0), None, None, -60).await.unwrap();
         }
-        .metrics {
-            display: grid;
-            grid-template-columns: repeat(3, 1fr);
-            gap: 10px;
-            margin-top: 15px;
+        state.db.insert_reading(current, Utc::now() - chrono::Duration::hours(1), 3, Temperature::from_celsius(60.0), None, None, -60)
+            .await
+            .unwrap();
+
+        let recompute = |token: Option<&str>| {
+            let mut request = Request::builder().method("POST").uri("/api/maintenance/recompute");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let response = router(state.clone()).call(recompute(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().admin_token = Some("secret".to_string());
+        state.config = Arc::new(config);
+        let response = router(state.clone()).call(recompute(Some("wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router(state.clone()).call(recompute(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(summary["devices_checked"], 4);
+        let changed = summary["devices_changed"].as_array().unwrap();
+        assert_eq!(changed.len(), 3, "{}", summary);
+        let stale_changes = &changed.iter().find(|d| d["device_address"] == stale).unwrap()["changes"];
+        assert_eq!(stale_changes[0], serde_json::json!({"field": "sensor_count", "from": 1, "to": 8}));
+        assert_eq!(stale_changes[1]["field"], "last_seen");
+
+        let device = state.db.get_device(stale).await.unwrap();
+        assert_eq!(device.sensor_count, 8);
+        assert_eq!(device.last_seen.timestamp_millis(), newest.timestamp_millis());
+        // An older reading does not move last_seen back
+        assert_eq!(state.db.get_device(current).await.unwrap().sensor_count, 4);
+        assert_eq!(state.db.get_device(one_jack).await.unwrap().sensor_count, 4, "never below the brand's sensors");
+        let igrill = state.db.get_device("AA:BB:CC:DD:EE:03").await.unwrap();
+        assert_eq!((igrill.brand.as_str(), igrill.sensor_count), ("WeberIGrill", 4));
+
+        // Nothing left to fix
+        let response = router(state.clone()).call(recompute(Some("secret"))).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(summary["devices_changed"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_includes_target_and_annotations() {
+        let state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "cA00probe", "MeatStickV", "cA00probe", 8)
+            .await
+            .unwrap();
+        state.db.start_cook(address, "Pork butt", Some(Temperature::from_fahrenheit(203.0))).await.unwrap();
+        state.db
+            .insert_reading(address, Utc::now(), 3, Temperature::from_celsius(150.0), Some(Temperature::from_celsius(250.0)), None, -60)
+            .await
+            .unwrap();
+
+        let plain = get_json(&state, &format!("/api/devices/{}/history?hours=1", address)).await;
+        assert_eq!(plain.as_array().unwrap().len(), 1);
+
+        let combined = get_json(
+            &state,
+            &format!("/api/devices/{}/history?hours=1&include=target,annotations", address),
+        )
+        .await;
+        assert_eq!(combined["readings"].as_array().unwrap().len(), 1);
+        assert_eq!(combined["target"]["temperature"], 203.0);
+        assert_eq!(combined["target"]["cook_name"], "Pork butt");
+        let annotations = combined["annotations"].as_array().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0]["kind"], "cook_started");
+
+        // Only the requested sections are embedded
+        let target_only = get_json(&state, &format!("/api/devices/{}/history?include=target", address)).await;
+        assert!(target_only.get("annotations").is_none());
+        assert!(target_only.get("target").is_some());
+        assert!(target_only.get("bands").is_none());
+
+        let banded = get_json(&state, &format!("/api/devices/{}/history?include=bands&meat=chicken", address)).await;
+        let bands: Vec<(f64, f64)> = banded["bands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| (b["from"].as_f64().unwrap(), b["to"].as_f64().unwrap()))
+            .collect();
+        assert_eq!(bands, [(40.0, 140.0), (165.0, 165.0)], "danger zone, then the USDA minimum");
+    }
+
+    #[tokio::test]
+    async fn test_history_resample() {
+        let state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        let start = DateTime::from_timestamp(Utc::now().timestamp() / 60 * 60 - 30 * 60, 0).unwrap();
+        let minutes = |m: i64| start + chrono::Duration::minutes(m);
+        // Sensor 0 every two minutes, sensor 1 with a 24 minute gap
+        for (sensor, minute, celsius) in [(0, 0, 100.0), (0, 2, 120.0), (1, 0, 50.0), (1, 1, 60.0), (1, 25, 70.0)] {
+            state.db
+                .insert_reading(address, minutes(minute), sensor, Temperature::from_celsius(celsius), None, None, -60)
+                .await
+                .unwrap();
         }
-        .metric {
-            text-align: center;
-            padding: 10px;
-            background: #f1f5f9;
-            border-radius: 6px;
+
+        let uri = format!("/api/devices/{}/history?hours=1&unit=c&resample=60", address);
+        let history = get_json(&state, &uri).await;
+        let points: Vec<(i64, i64, f64)> = history
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                let timestamp: DateTime<Utc> = p["timestamp"].as_str().unwrap().parse().unwrap();
+                let temperature = p["temperature"].as_f64().unwrap().round();
+                ((timestamp - start).num_minutes(), p["sensor_index"].as_i64().unwrap(), temperature)
+            })
+            .collect();
+        assert_eq!(
+            points,
+            [(0, 0, 100.0), (0, 1, 50.0), (1, 0, 110.0), (1, 1, 60.0), (2, 0, 120.0), (25, 1, 70.0)],
+            "interpolated per sensor, nothing inside the gap"
+        );
+
+        let bridged = get_json(&state, &format!("{}&max_gap=3600&include=target", uri)).await;
+        assert_eq!(bridged["readings"].as_array().unwrap().len(), 3 + 26);
+
+        for bad in ["resample=0", "max_gap=60", "hours=48&resample=1"] {
+            let request = Request::builder()
+                .uri(format!("/api/devices/{}/history?{}", address, bad))
+                .body(Body::empty())
+                .unwrap();
+            let response = router(state.clone()).call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", bad);
         }
-        .metric-value {
-            font-size: 1.2em;
-            font-weight: bold;
-            color: #1e40af;
+    }
+
+    #[tokio::test]
+    async fn test_compare_overlays_devices() {
+        let state = test_state().await;
+        let now = Utc::now();
+        let minutes = |m: i64| now - chrono::Duration::minutes(m);
+        for (address, name) in [("AA", "Left brisket"), ("BB", "Right brisket")] {
+            state.db.upsert_device(address, name, "MeatStickV", name, 8).await.unwrap();
         }
-        .metric-label {
-            font-size: 0.75em;
-            color: #64748b;
-            text-transform: uppercase;
-            margin-top: 3px;
+        // AA ran for the last 3 hours, BB started 90 minutes ago, sampling at other times
+        for (address, samples) in [
+            ("AA", [(170, 20.0), (100, 40.0), (58, 60.0), (5, 70.0)]),
+            ("BB", [(85, 25.0), (61, 45.0), (30, 55.0), (2, 65.0)]),
+        ] {
+            for (ago, celsius) in samples {
+                let temp = Temperature::from_celsius(celsius);
+                state.db.insert_reading(address, minutes(ago), 0, temp, None, None, 0).await.unwrap();
+                state.db.insert_reading(address, minutes(ago), 7, Temperature::from_celsius(110.0), None, None, 0).await.unwrap();
+            }
         }
-        .chart-container {
-            margin-top: 20px;
-            height: 200px;
+        // Older than the window
+        state.db.insert_reading("AA", minutes(400), 0, Temperature::from_celsius(5.0), None, None, 0).await.unwrap();
+
+        let compared = get_json(&state, "/api/compare?devices=AA,BB&hours=2&sensor=0&unit=celsius").await;
+        let series = compared["series"].as_array().unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0]["device_name"], "Left brisket");
+        let temps = |i: usize| -> Vec<f64> {
+            series[i]["points"].as_array().unwrap().iter().map(|p| p["temperature"].as_f64().unwrap()).collect()
+        };
+        assert_eq!(temps(0), [40.0, 60.0, 70.0]);
+        assert_eq!(temps(1), [25.0, 45.0, 55.0, 65.0]);
+        assert_ne!(series[0]["points"][0]["timestamp"], series[1]["points"][0]["timestamp"]);
+
+        // On a shared hourly grid: BB's two samples in the first hour are averaged
+        let gridded = get_json(&state, "/api/compare?devices=AA,BB&hours=2&sensor=0&step_secs=3600&unit=celsius").await;
+        let points = |i: usize| gridded["series"][i]["points"].as_array().unwrap().clone();
+        assert_eq!(points(1)[0]["temperature"], 35.0);
+        assert_eq!(points(0)[0]["timestamp"], points(1)[0]["timestamp"]);
+        assert_eq!(points(0)[0]["timestamp"], gridded["start"]);
+
+        for uri in ["/api/compare?devices=", "/api/compare?devices=AA&step_secs=0"] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = router(state.clone()).call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
         }
-        @media (max-width: 768px) {
-            .devices-grid {
-                grid-template-columns: 1fr;
+        let request = Request::builder().uri("/api/compare?devices=AA,CC").body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_export_csv_endpoint() {
+        let state = test_state().await;
+        state.db.upsert_device("AA:BB:CC:DD:EE:FF", "cA00probe", "MeatStickV", "cA00probe", 8)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        for i in 0..5 {
+            state.db
+                .insert_reading("AA:BB:CC:DD:EE:FF", now - chrono::Duration::minutes(i), 0, Temperature::from_celsius(150.0), None, Some(90), -60)
+                .await
+                .unwrap();
+        }
+        // Outside the requested window
+        state.db
+            .insert_reading("AA:BB:CC:DD:EE:FF", now - chrono::Duration::hours(3), 0, Temperature::from_celsius(90.0), None, None, -60)
+            .await
+            .unwrap();
+
+        // Router is always ready, so it can be called without poll_ready
+        let response = router(state)
+            .call(
+                Request::get("/api/devices/AA:BB:CC:DD:EE:FF/export.csv?hours=2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"bbq-AABBCCDDEEFF.csv\""
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], export::CSV_HEADER);
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_connect_history_is_downsampled_primary_sensor() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.upsert_device("AA:BB", "cA00probe", "MeatStickV", "cA00probe", 8).await.unwrap();
+        db.upsert_device("CC:DD", "MEATER", "Meater", "MEATER", 2).await.unwrap();
+        let now = Utc::now();
+        // One reading every 5 seconds for 40 minutes, on the tip and the default primary (sensor 3)
+        for step in 0..480 {
+            let timestamp = now - chrono::Duration::seconds(5 * step);
+            for (sensor, celsius) in [(0, 20.0), (3, 60.0)] {
+                db.insert_reading("AA:BB", timestamp, sensor, Temperature::from_celsius(celsius), None, None, -60)
+                    .await
+                    .unwrap();
             }
-            h1 { font-size: 1.8em; }
-            .temp-value { font-size: 2.5em; }
         }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>🔥 BBQ Monitor Dashboard<span class="premium-badge" id="tier-badge" style="display: none;">FREE</span></h1>
-        <div id="premium-banner" style="display: none;"></div>
-        <div id="status" class="status disconnected">
-            ⚠️ Connecting to server...
-        </div>
-        <div id="devices" class="devices-grid"></div>
-    </div>
+        let devices = db.get_all_devices().await.unwrap();
+        let device = |address: &str| devices.iter().find(|d| d.device_address == address).unwrap();
+
+        let history = connect_history(&db, device("AA:BB"), 30, TemperatureUnit::Fahrenheit).await.unwrap().unwrap();
+        assert_eq!(history.sensor_index, 3);
+        assert_eq!(history.step_secs, 15);
+        assert!((119..=121).contains(&history.points.len()), "{} points", history.points.len());
+        assert!(history.points.iter().all(|p| (p.temperature - 140.0).abs() < 0.01));
+        assert!(history.points[0].timestamp >= now - chrono::Duration::minutes(30) - chrono::Duration::seconds(1));
+
+        let json = serde_json::to_value(ControlMessage::History(history)).unwrap();
+        assert_eq!(json["type"], "history");
+        assert_eq!(json["unit"], "fahrenheit");
+        assert_eq!(json["minutes"], 30);
+
+        assert!(connect_history(&db, device("CC:DD"), 30, TemperatureUnit::Celsius).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_export_endpoint_downloads_a_range() {
+        use chrono::TimeZone;
+        let state = test_state().await;
+        let start = Utc.with_ymd_and_hms(2026, 6, 1, 12, 0, 0).unwrap();
+        for address in ["AA:BB", "CC:DD"] {
+            state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+            for minute in 0..3 {
+                state.db.insert_reading(address, start + chrono::Duration::minutes(minute), 0, Temperature::from_celsius(60.0), None, None, -60)
+                    .await
+                    .unwrap();
+            }
+        }
+        let get = |uri: &str| {
+            let state = state.clone();
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            async move { router(state).call(request).await.unwrap() }
+        };
+
+        let response = get("/api/export?device=AA:BB&start=2026-06-01T12:00:00Z&end=2026-06-01T12:01:00Z&format=csv").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_DISPOSITION], "attachment; filename=\"bbq-AABB-20260601.csv\"");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], export::DEVICES_CSV_HEADER);
+        assert_eq!(lines.len(), 3, "two readings in the range");
+        assert!(lines[1].starts_with("AA:BB,2026-06-01T12:00:00+00:00,0,"), "{}", lines[1]);
+
+        let response = get("/api/export?start=2026-06-01T00:00:00Z&end=2026-06-02T00:00:00Z&format=ndjson&unit=fahrenheit").await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let readings: Vec<serde_json::Value> = body
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(readings.len(), 6, "every device");
+        assert_eq!(readings[0]["temperature"], 140.0);
+
+        assert_eq!(get("/api/export?format=xml").await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get("/api/export?start=2026-06-02T00:00:00Z&end=2026-06-01T00:00:00Z").await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get("/api/export?device=EE:FF").await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_device_notes_crud_and_calibration_due() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        let send = |method: &str, uri: String, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let notes_uri = format!("/api/devices/{}/notes", address);
+
+        let response = router(state.clone())
+            .call(send("POST", notes_uri.clone(), r#"{"category":"battery","text":"replaced battery","timestamp":"2024-11-02T12:00:00Z"}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = router(state.clone())
+            .call(send("POST", notes_uri.clone(), r#"{"category":"calibration","text":"calibrated, +1.5F","timestamp":"2024-11-03T12:00:00Z"}"#))
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let calibration: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(calibration["category"], "calibration");
+
+        let notes = get_json(&state, &notes_uri).await;
+        assert_eq!(notes.as_array().unwrap().len(), 2);
+        assert_eq!(notes[0]["category"], "calibration");
+        let batteries = get_json(&state, &format!("{}?category=battery", notes_uri)).await;
+        assert_eq!(batteries.as_array().unwrap().len(), 1);
+
+        // Reminders off: dates are reported but nothing is due
+        let summary = get_json(&state, &format!("/api/devices/{}", address)).await;
+        assert_eq!(summary["last_battery_change"], "2024-11-02T12:00:00Z");
+        assert_eq!(summary["calibration_due"], false);
+        let mut config = Config::default();
+        config.maintenance.calibration_reminder_days = 90;
+        state.config = Arc::new(config);
+        let summary = get_json(&state, &format!("/api/devices/{}", address)).await;
+        assert_eq!(summary["calibration_due"], true);
+
+        let note_uri = format!("{}/{}", notes_uri, calibration["id"]);
+        let response = router(state.clone())
+            .call(send("PUT", note_uri.clone(), &format!(r#"{{"category":"calibration","text":"ice bath ok","timestamp":"{}"}}"#, Utc::now().to_rfc3339())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let summary = get_json(&state, &format!("/api/devices/{}", address)).await;
+        assert_eq!(summary["calibration_due"], false);
+
+        let response = router(state.clone()).call(send("DELETE", note_uri.clone(), "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = router(state.clone()).call(send("DELETE", note_uri, "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(get_json(&state, &notes_uri).await.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_loads_nothing_cross_origin() {
+        let state = test_state().await;
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(!html.contains("http://") && !html.contains("https://"), "dashboard references another origin");
+        let script = format!("/assets/line-chart.js?v={}", env!("CARGO_PKG_VERSION"));
+        assert!(html.contains(&script));
+
+        let request = Request::builder().uri(&script).body(Body::empty()).unwrap();
+        let response = router(state).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/javascript"));
+    }
+
+    #[test]
+    fn test_dashboard_encodes_device_addresses() {
+        // Block probe addresses contain '#', which would end the path unencoded
+        for (i, _) in INDEX_HTML.match_indices("/api/devices/${") {
+            let rest = &INDEX_HTML[i + "/api/devices/${".len()..];
+            assert!(rest.starts_with("encodeURIComponent("), "unencoded address: {}", &rest[..40.min(rest.len())]);
+        }
+    }
+
+    #[test]
+    fn test_connect_snapshot_ages_from_its_timestamp() {
+        // After a restart no heartbeat corrects a stale stored row, so it must not look fresh
+        let card = &INDEX_HTML[INDEX_HTML.find("function updateDeviceCard").unwrap()..];
+        let card = &card[..card.find("function updateChart").unwrap()];
+        assert!(card.contains("update.seq === undefined ? Math.max(0, Date.now() - now.getTime()) : 0"));
+        assert!(card.contains("dataset.receivedAt = performance.now() - storedAgeMs"));
+    }
+
+    #[tokio::test]
+    async fn test_quoted_device_name_stays_out_of_attributes() {
+        let state = test_state().await;
+        let name = r#"meater" autofocus onfocus="alert(1)"#;
+        state.db.upsert_device("AA", name, "MeaterPlus", "MEATER", 2).await.unwrap();
+        state.db.set_device_alias("AA", Some("it's \"mine\"")).await.unwrap();
+        let settings = get_json(&state, "/api/devices/AA/settings").await;
+        assert_eq!(settings["alias"], "it's \"mine\"");
+
+        // The drawer sets names as properties; escapeHtml covers both quote kinds for text
+        assert!(INDEX_HTML.contains("nameInput.placeholder = s.display_name;"));
+        assert!(INDEX_HTML.contains("nameInput.value = s.alias || '';"));
+        assert!(!INDEX_HTML.contains("=\"${escapeHtml("), "escaped text used as an attribute value");
+        assert!(INDEX_HTML.contains("'\"': '&quot;', \"'\": '&#39;'"));
+    }
+
+    #[tokio::test]
+    async fn test_alert_rules_are_gated_and_converted() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 2).await.unwrap();
+        let post = |body: String| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/alerts")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let body = format!(r#"{{"device_address":"{}","kind":"above_target","threshold":203,"hysteresis":9}}"#, address);
+
+        let engine = Arc::new(AlertEngine::new(Vec::new()));
+        state.alerts = Some(engine.clone());
+        let response = router(state.clone()).call(post(body.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Activating Premium turns the running engine on
+        state.license = premium_license(&state.db);
+        let response = router(state.clone()).call(post(body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let bad_sensor = format!(r#"{{"device_address":"{}","sensor_index":2,"kind":"below_target","threshold":225}}"#, address);
+        let response = router(state.clone()).call(post(bad_sensor)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Stored in Celsius, listed in the display unit
+        let rules = engine.rules();
+        assert_eq!(rules.len(), 1);
+        assert!((rules[0].kind.threshold().unwrap().celsius() - 95.0).abs() < 1e-3);
+        assert!((rules[0].hysteresis - 5.0).abs() < 1e-3);
+        assert_eq!(state.db.get_alert_rules().await.unwrap(), rules);
+        let listed = get_json(&state, "/api/alerts").await;
+        assert_eq!(listed[0]["kind"], "above_target");
+        assert_eq!(listed[0]["unit"], "fahrenheit");
+
+        let events = engine.process(address, 0, Temperature::from_fahrenheit(204.0), None, Utc::now());
+        let message = serde_json::to_value(AlertMessage::Alert(events[0].clone().in_unit(TemperatureUnit::Fahrenheit))).unwrap();
+        assert_eq!(message["type"], "alert");
+        assert_eq!(message["rule_id"], rules[0].id);
+        // A meat's carryover moves an above-target rule's trigger down
+        let roast = format!(r#"{{"device_address":"{}","kind":"above_target","threshold":135,"meat":"prime_rib"}}"#, address);
+        let response = router(state.clone()).call(post(roast)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let listed = get_json(&state, "/api/alerts").await;
+        assert!((listed[1]["carryover"].as_f64().unwrap() - 10.0).abs() < 1e-3, "{}", listed);
+        let bad_kind = format!(r#"{{"device_address":"{}","kind":"below_target","threshold":225,"carryover":5}}"#, address);
+        let response = router(state.clone()).call(post(bad_kind)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_relative_alerts_follow_cook_target() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 2).await.unwrap();
+        let engine = Arc::new(AlertEngine::new(Vec::new()));
+        state.alerts = Some(engine.clone());
+        state.license = premium_license(&state.db);
+        let send = |method: &str, uri: &str, body: String| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let rule = |extra: &str| format!(r#"{{"device_address":"{}","kind":"approaching_target"{}}}"#, address, extra);
+
+        for bad in ["", r#","offset":0"#, r#","offset":-5"#, r#","offset":51"#] {
+            let response = router(state.clone()).call(send("POST", "/api/alerts", rule(bad))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", bad);
+        }
+        let response = router(state.clone()).call(send("POST", "/api/alerts", rule(r#","offset":50"#))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = router(state.clone()).call(send("POST", "/api/alerts", rule(r#","offset":5"#))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let listed = get_json(&state, "/api/alerts").await;
+        assert!(listed[1]["threshold"].is_null());
+        assert!((listed[1]["offset"].as_f64().unwrap() - 5.0).abs() < 1e-3);
+        assert_eq!(state.db.get_alert_rules().await.unwrap(), engine.rules());
+
+        // No cook target yet: the rules stay quiet
+        let mut events = engine.subscribe();
+        assert!(engine.process(address, 0, Temperature::from_fahrenheit(199.0), None, Utc::now()).is_empty());
+
+        let uri = format!("/api/devices/{}/cook", address);
+        let target_uri = format!("/api/devices/{}/cook/target", address);
+        let response = router(state.clone()).call(send("PUT", &target_uri, r#"{"target_temp":210}"#.to_string())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = router(state.clone())
+            .call(send("POST", &uri, r#"{"name":"Brisket","target_temp":260}"#.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        // 260 - 50 = 210 is still ahead of 199
+        assert!(events.try_recv().is_err());
+
+        // Lowering the target re-checks the last reading at once
+        let response = router(state.clone()).call(send("PUT", &target_uri, r#"{"target_temp":203}"#.to_string())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let fired: Vec<i64> = std::iter::from_fn(|| events.try_recv().ok()).map(|e| e.rule_id).collect();
+        assert_eq!(fired, engine.rules().iter().map(|r| r.id).collect::<Vec<_>>());
+        let session = state.db.get_open_cook(address).await.unwrap().unwrap();
+        assert!((session.target_temp.unwrap().fahrenheit() - 203.0).abs() < 1e-3);
+    }
+
+    #[tokio::test]
+    async fn test_device_target_persists_and_feeds_alerts() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 2).await.unwrap();
+        let engine = Arc::new(AlertEngine::new(Vec::new()));
+        state.alerts = Some(engine.clone());
+        let put = |uri: &str, body: &str| {
+            Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let uri = format!("/api/devices/{}/target", address);
+        let call = |request: Request<Body>| {
+            let state = state.clone();
+            async move { router(state).call(request).await.unwrap().status() }
+        };
 
-    <script>
-        let ws = null;
-        let charts = {};
-        let deviceData = {};
+        assert_eq!(call(put("/api/devices/11:22/target", r#"{"target":203}"#)).await, StatusCode::NOT_FOUND);
+        assert!(state.db.find_device("11:22").await.unwrap().is_none(), "no row created");
+        assert_eq!(call(put(&uri, r#"{"sensor_index":2,"target":203}"#)).await, StatusCode::BAD_REQUEST);
 
-        // Load premium status
-        async function loadPremiumStatus() {
-            try {
-                const response = await fetch('/api/premium/status');
-                const status = await response.json();
-                
-                const badge = document.getElementById('tier-badge');
-                badge.style.display = 'inline-block';
-                badge.textContent = status.tier.toUpperCase();
-                
-                if (status.tier === 'Free') {
-                    badge.style.background = 'linear-gradient(135deg, #64748b 0%, #475569 100%)';
-                    
-                    // Show premium banner for free users
-                    const banner = document.getElementById('premium-banner');
-                    banner.style.display = 'block';
-                    banner.className = 'premium-banner';
-                    banner.innerHTML = `
-                        <h3>🌟 Upgrade to Premium</h3>
-                        <p>Unlock cloud sync, unlimited history, cook profiles, and more!</p>
-                        <a href="https://bbqmonitor.example.com/premium" target="_blank">View Premium Features →</a>
-                    `;
-                } else if (status.tier === 'Premium') {
-                    badge.style.background = 'linear-gradient(135deg, #f59e0b 0%, #d97706 100%)';
-                    
-                    // Show expiry warning if needed
-                    if (status.days_until_expiry !== null && status.days_until_expiry < 30) {
-                        const banner = document.getElementById('premium-banner');
-                        banner.style.display = 'block';
-                        banner.className = 'premium-banner';
-                        banner.style.background = 'linear-gradient(135deg, #dc2626 0%, #b91c1c 100%)';
-                        banner.innerHTML = `
-                            <h3>⚠️ License Expiring Soon</h3>
-                            <p>Your Premium license expires in ${status.days_until_expiry} days</p>
-                            <a href="https://bbqmonitor.example.com/renew" target="_blank">Renew License →</a>
-                        `;
+        assert_eq!(call(put(&uri, r#"{"sensor_index":1,"target":203}"#)).await, StatusCode::OK);
+        let target = state.db.get_target(address).await.unwrap().unwrap();
+        assert_eq!(target.sensor_index, 1);
+        assert!((target.temperature.fahrenheit() - 203.0).abs() < 1e-3);
+        assert!((engine.target(address).unwrap().fahrenheit() - 203.0).abs() < 1e-3);
+        let summary = get_json(&state, &format!("/api/devices/{}", address)).await;
+        assert!((summary["target_temp"].as_f64().unwrap() - 203.0).abs() < 1e-3, "{}", summary);
+        assert_eq!(summary["target_sensor_index"], 1);
+
+        // A cook's target wins while it runs, then the device target is back
+        state.db.start_cook(address, "Ribs", Some(Temperature::from_fahrenheit(195.0))).await.unwrap();
+        let summary = get_json(&state, &format!("/api/devices/{}", address)).await;
+        assert!((summary["target_temp"].as_f64().unwrap() - 195.0).abs() < 1e-3, "{}", summary);
+        let request = Request::builder()
+            .method("DELETE")
+            .uri(format!("/api/devices/{}/cook", address))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(call(request).await, StatusCode::OK);
+        assert!((engine.target(address).unwrap().fahrenheit() - 203.0).abs() < 1e-3);
+
+        assert_eq!(call(put(&uri, r#"{"target":null}"#)).await, StatusCode::OK);
+        assert!(state.db.get_target(address).await.unwrap().is_none());
+        assert!(engine.target(address).is_none());
+        assert!(get_json(&state, &format!("/api/devices/{}", address)).await["target_temp"].is_null());
+    }
+
+    /// Replace values that change from run to run, for comparing against a golden file
+    fn redact_times(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, field) in map.iter_mut() {
+                    if matches!(key.as_str(), "started_at" | "snoozed_until") && !field.is_null() {
+                        *field = serde_json::Value::from("<time>");
+                    } else {
+                        redact_times(field);
                     }
                 }
-            } catch (error) {
-                console.error('Failed to load premium status:', error);
             }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(redact_times),
+            _ => {}
         }
+    }
 
-        function connect() {
-            const wsUrl = `ws://${window.location.host}/ws`;
-            ws = new WebSocket(wsUrl);
-            
-            ws.onopen = () => {
-                console.log('WebSocket connected');
-                updateStatus(true);
-            };
-            
-            ws.onmessage = (event) => {
-                const update = JSON.parse(event.data);
-                handleUpdate(update);
-            };
-            
-            ws.onerror = (error) => {
-                console.error('WebSocket error:', error);
-                updateStatus(false);
-            };
-            
-            ws.onclose = () => {
-                console.log('WebSocket disconnected');
-                updateStatus(false);
-                setTimeout(connect, 3000);
-            };
+    #[tokio::test]
+    async fn test_device_settings_golden() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "cA00probe", "MeatStickV", "cA00probe", 2).await.unwrap();
+        let settings_uri = format!("/api/devices/{}/settings", address);
+
+        // Free tier: no alerts section, no cook
+        let free = get_json(&state, &settings_uri).await;
+        assert!(free["alerts"].is_null() && free["cook"].is_null());
+        assert_eq!(free["display_name"], "cA00probe");
+
+        let engine = Arc::new(AlertEngine::new(Vec::new()));
+        state.alerts = Some(engine.clone());
+        state.license = premium_license(&state.db);
+        let mut confirmations = state.fanout.subscribe_settings();
+        let send = |method: &str, uri: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let call = |request: Request<Body>| {
+            let state = state.clone();
+            async move { router(state).call(request).await.unwrap().status() }
+        };
+
+        let name_uri = format!("/api/devices/{}/name", address);
+        assert_eq!(call(send("PUT", &name_uri, r#"{"name":"  Brisket point "}"#)).await, StatusCode::OK);
+        let too_long = format!(r#"{{"name":"{}"}}"#, "x".repeat(65));
+        assert_eq!(call(send("PUT", &name_uri, &too_long)).await, StatusCode::BAD_REQUEST);
+        assert_eq!(call(send("PUT", "/api/devices/11:22/name", r#"{"name":"x"}"#)).await, StatusCode::NOT_FOUND);
+        let cook = format!("/api/devices/{}/cook", address);
+        assert_eq!(call(send("POST", &cook, r#"{"name":"Brisket","target_temp":203}"#)).await, StatusCode::CREATED);
+        let above = format!(r#"{{"device_address":"{}","kind":"above_target","threshold":195}}"#, address);
+        let below = format!(r#"{{"device_address":"{}","sensor_index":1,"kind":"below_target","threshold":225}}"#, address);
+        let extra = format!(r#"{{"device_address":"{}","kind":"above_target","threshold":100}}"#, address);
+        for rule in [&above, &below, &extra] {
+            assert_eq!(call(send("POST", "/api/alerts", rule)).await, StatusCode::CREATED);
         }
+        let [first, second, third] = [0, 1, 2].map(|i| engine.rules()[i].id);
+        assert_eq!(call(send("DELETE", &format!("/api/alerts/{}", third), "")).await, StatusCode::NO_CONTENT);
+        assert_eq!(call(send("DELETE", &format!("/api/alerts/{}", third), "")).await, StatusCode::NOT_FOUND);
+        let snooze = format!("/api/alerts/{}/snooze", second);
+        assert_eq!(call(send("POST", &snooze, r#"{"minutes":1441}"#)).await, StatusCode::BAD_REQUEST);
+        assert_eq!(call(send("POST", &snooze, r#"{"minutes":15}"#)).await, StatusCode::OK);
+        assert!(engine.snoozed_until(second, Utc::now()).is_some());
+        assert!(engine.snoozed_until(first, Utc::now()).is_none());
 
-        function updateStatus(connected) {
-            const status = document.getElementById('status');
-            if (connected) {
-                status.className = 'status connected';
-                status.textContent = '✅ Connected - Live Updates Active';
-            } else {
-                status.className = 'status disconnected';
-                status.textContent = '⚠️ Disconnected - Reconnecting...';
+        // Every successful edit is confirmed to WebSocket clients
+        let confirmed = std::iter::from_fn(|| confirmations.try_recv().ok()).count();
+        assert_eq!(confirmed, 7);
+
+        let mut settings = get_json(&state, &settings_uri).await;
+        redact_times(&mut settings);
+        let golden_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/device_settings.json");
+        let golden: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(golden_path).unwrap()).unwrap();
+        assert_eq!(settings, golden, "{}", serde_json::to_string_pretty(&settings).unwrap());
+
+        // Clearing the name goes back to the detected one
+        assert_eq!(call(send("PUT", &name_uri, r#"{"name":null}"#)).await, StatusCode::OK);
+        assert_eq!(get_json(&state, &settings_uri).await["display_name"], "cA00probe");
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_written_to_access_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        let mut state = test_state().await;
+        state.access_log = Some(Arc::new(AccessLog::open(&path).unwrap()));
+
+        let mut request = Request::builder().uri("/api/devices/AA:BB/history?hours=2").body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([192, 168, 1, 20], 51000))));
+        let response = router(state).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let entry: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(entry["ip"], "192.168.1.20");
+        assert_eq!(entry["method"], "GET");
+        assert_eq!(entry["path"], "/api/devices/AA:BB/history");
+        assert_eq!(entry["status"], 200);
+        assert!(entry["timestamp"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_guards_api_and_websocket() {
+        let mut state = test_state().await;
+        let mut config = Config::default();
+        let web = config.web.as_mut().unwrap();
+        web.api_key = Some("s3cret".to_string());
+        web.admin_token = Some("admin".to_string());
+        state.config = Arc::new(config);
+        let call = |uri: &str, header: Option<(&str, &str)>| {
+            let mut request = Request::builder().uri(uri);
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
             }
+            router(state.clone()).call(request.body(Body::empty()).unwrap())
+        };
+
+        let missing = call("/api/devices", None).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(missing.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        for wrong in [("authorization", "Bearer nope"), ("x-api-key", "nope"), ("authorization", "s3cret")] {
+            assert_eq!(call("/api/devices", Some(wrong)).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        }
+        for right in [("authorization", "Bearer s3cret"), ("x-api-key", "s3cret"), ("authorization", "Bearer admin")] {
+            assert_eq!(call("/api/devices", Some(right)).await.unwrap().status(), StatusCode::OK, "{:?}", right);
         }
 
-        function handleUpdate(update) {
-            const addr = update.device_address;
-            
-            if (!deviceData[addr]) {
-                deviceData[addr] = {
-                    name: update.device_name,
-                    address: addr,
-                    readings: [],
-                    timestamps: []
-                };
-                createDeviceCard(addr);
-            }
-            
-            const data = deviceData[addr];
-            data.readings.push(update.temperature);
-            data.timestamps.push(new Date(update.timestamp));
-            
-            // Keep last 50 readings
-            if (data.readings.length > 50) {
-                data.readings.shift();
-                data.timestamps.shift();
+        // The upgrade is refused before it happens; the query key only counts on /ws
+        assert_eq!(call("/ws", None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_ne!(call("/ws?api_key=s3cret", None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(call("/api/devices?api_key=s3cret", None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // The dashboard page itself stays reachable so it can ask for the key
+        assert_eq!(call("/", None).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_requests_beyond_the_limit_get_503() {
+        let (release, released) = tokio::sync::watch::channel(false);
+        let (entered_tx, mut entered) = tokio::sync::mpsc::unbounded_channel();
+        let slow = move || {
+            let mut released = released.clone();
+            let entered_tx = entered_tx.clone();
+            async move {
+                let _ = entered_tx.send(());
+                let _ = released.wait_for(|done| *done).await;
+                "done"
             }
-            
-            updateDeviceCard(addr, update);
-            updateChart(addr);
+        };
+        let app = limit_concurrency(Router::new().route("/slow", get(slow)).route("/fast", get(|| async { "ok" })), 2);
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let busy: Vec<_> = (0..2).map(|_| tokio::spawn(app.clone().call(get("/slow")))).collect();
+        // Both slots are taken once both handlers are running
+        for _ in 0..2 {
+            entered.recv().await.unwrap();
         }
 
-        function createDeviceCard(addr) {
-            const data = deviceData[addr];
-            const container = document.getElementById('devices');
-            
-            const card = document.createElement('div');
-            card.className = 'device-card';
-            card.id = `device-${addr}`;
-            card.innerHTML = `
-                <div class="device-header">
-                    <div>
-                        <div class="device-name">${data.name}</div>
-                        <div class="device-brand">Thermometer</div>
-                    </div>
-                </div>
-                <div class="temperature-display">
-                    <div class="temp-value" id="temp-${addr}">--°F</div>
-                    <div class="temp-label">Internal Temperature</div>
-                    <div class="timestamp" id="timestamp-${addr}">No data</div>
-                </div>
-                <div class="metrics">
-                    <div class="metric">
-                        <div class="metric-value" id="ambient-${addr}">--</div>
-                        <div class="metric-label">Ambient</div>
-                    </div>
-                    <div class="metric">
-                        <div class="metric-value" id="battery-${addr}">--</div>
-                        <div class="metric-label">Battery</div>
-                    </div>
-                    <div class="metric">
-                        <div class="metric-value" id="rssi-${addr}">--</div>
-                        <div class="metric-label">Signal</div>
-                    </div>
-                </div>
-                <div class="chart-container">
-                    <canvas id="chart-${addr}"></canvas>
-                </div>
-            `;
-            
-            container.appendChild(card);
-            
-            // Create chart
-            const ctx = document.getElementById(`chart-${addr}`).getContext('2d');
-            charts[addr] = new Chart(ctx, {
-                type: 'line',
-                data: {
-                    labels: [],
-                    datasets: [{
-                        label: 'Temperature',
-                        data: [],
-                        borderColor: '#dc2626',
-                        backgroundColor: 'rgba(220, 38, 38, 0.1)',
-                        tension: 0.4,
-                        fill: true
-                    }]
-                },
-                options: {
-                    responsive: true,
-                    maintainAspectRatio: false,
-                    plugins: {
-                        legend: { display: false }
-                    },
-                    scales: {
-                        y: {
-                            beginAtZero: false,
-                            ticks: { color: '#64748b' }
-                        },
-                        x: {
-                            ticks: { 
-                                color: '#64748b',
-                                maxTicksLimit: 8
-                            }
-                        }
-                    }
-                }
-            });
+        let shed = app.clone().call(get("/fast")).await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE, "the limit covers every route");
+        assert_eq!(shed.headers()[header::RETRY_AFTER], "1");
+
+        release.send(true).unwrap();
+        for request in busy {
+            assert_eq!(request.await.unwrap().unwrap().status(), StatusCode::OK);
         }
+        assert_eq!(app.clone().call(get("/fast")).await.unwrap().status(), StatusCode::OK, "slots are given back");
 
-        function updateDeviceCard(addr, update) {
-            document.getElementById(`temp-${addr}`).textContent = 
-                `${update.temperature.toFixed(1)}°F`;
-            
-            document.getElementById(`ambient-${addr}`).textContent = 
-                update.ambient_temp ? `${update.ambient_temp.toFixed(1)}°F` : '--';
-            
-            document.getElementById(`battery-${addr}`).textContent = 
-                update.battery_level ? `${update.battery_level}%` : '--';
-            
-            document.getElementById(`rssi-${addr}`).textContent = 
-                `${update.signal_strength} dBm`;
-            
-            // Update timestamp
-            const timestampEl = document.getElementById(`timestamp-${addr}`);
-            const now = new Date(update.timestamp);
-            timestampEl.textContent = `Last: ${now.toLocaleTimeString()}`;
-            timestampEl.dataset.timestamp = update.timestamp;
-            updateTimestampAging(addr);
+        // The configured limit is applied to the real router
+        let mut state = test_state().await;
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().max_concurrent_requests = 1;
+        state.config = Arc::new(config);
+        assert_eq!(router(state).call(get("/api/health")).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_simulator_flameout_fires_low_pit_alert() {
+        use crate::simulator::{run_simulator, simulator_channel, SIM_PIT_SENSOR};
+
+        let mut state = test_state().await;
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().admin_token = Some("secret".to_string());
+        state.config = Arc::new(config);
+        let get = |uri: &str| {
+            Request::builder().uri(uri).header(header::AUTHORIZATION, "Bearer secret").body(Body::empty()).unwrap()
+        };
+        let scenario = |uri: &str, body: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, "Bearer secret")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let response = router(state.clone()).call(get("/api/admin/simulator")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "hidden without the simulator");
+
+        let low_pit = AlertRule {
+            id: 1,
+            device_address: "sim-1".to_string(),
+            sensor_index: SIM_PIT_SENSOR,
+            kind: AlertKind::BelowTarget(Temperature::from_fahrenheit(150.0)),
+            hysteresis: 2.0,
+            carryover: 0.0,
+        };
+        let engine = Arc::new(AlertEngine::new(vec![low_pit]));
+        let mut alerts = engine.subscribe();
+        state.license = premium_license(&state.db);
+        tokio::spawn(crate::alerts::run_alert_engine(engine.clone(), state.license.clone(), state.tx.subscribe()));
+        state.alerts = Some(engine);
+
+        // A minute of cook per 100 ms
+        let (commands, receiver) = simulator_channel();
+        state.simulator = Some(commands);
+        let (_stop, shutdown) = broadcast::channel(1);
+        let config = crate::SimulatorConfig { enabled: true, devices: 1, tick_ms: 5, speed: 600.0 };
+        tokio::spawn(run_simulator(config, TemperatureUnit::Fahrenheit, state.db.clone(), state.tx.clone(), receiver, shutdown));
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(alerts.try_recv().is_err(), "steady pit stays quiet");
+
+        let uri = "/api/admin/simulator/devices/sim-1/scenario";
+        // Driving the simulator is an admin operation
+        let mut anonymous = scenario(uri, r#"{"command":"flameout"}"#);
+        anonymous.headers_mut().remove(header::AUTHORIZATION);
+        assert_eq!(router(state.clone()).call(anonymous).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let mut anonymous = get("/api/admin/simulator");
+        anonymous.headers_mut().remove(header::AUTHORIZATION);
+        assert_eq!(router(state.clone()).call(anonymous).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert!(alerts.try_recv().is_err());
+
+        let response = router(state.clone()).call(scenario(uri, r#"{"command":"flameout"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let unknown = "/api/admin/simulator/devices/sim-9/scenario";
+        let response = router(state.clone()).call(scenario(unknown, r#"{"command":"flameout"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = router(state.clone()).call(scenario(uri, r#"{"command":"explode"}"#)).await.unwrap();
+        assert!(response.status().is_client_error());
+
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), alerts.recv()).await.unwrap().unwrap();
+        assert_eq!((event.device_address.as_str(), event.sensor_index), ("sim-1", SIM_PIT_SENSOR));
+        assert_eq!(event.kind, AlertKindName::BelowTarget);
+
+        let response = router(state.clone()).call(get("/api/admin/simulator")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let devices: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(devices[0]["device_address"], "sim-1");
+        assert_eq!(devices[0]["scenario"]["flameout"], true);
+        assert!(devices[0]["pit"].as_f64().unwrap() <= 150.0);
+        let stored = state.db.get_latest_reading("sim-1").await.unwrap();
+        assert_eq!(stored.source, ReadingSource::Simulator);
+    }
+
+    #[cfg(feature = "pit-controller")]
+    #[tokio::test]
+    async fn test_pit_controller_output_in_api() {
+        let mut state = test_state().await;
+        let request = || Request::builder().uri("/api/pit-controller").body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "hidden unless enabled");
+
+        let config = crate::PitControllerConfig {
+            enabled: true,
+            device_address: "AA:BB".to_string(),
+            sensor_index: 1,
+            setpoint: 225.0,
+            kp: 4.0,
+            ki: 0.0,
+            kd: 0.0,
+            output_command: Vec::new(),
+        };
+        state.pit_controller = Some(crate::pit_controller::start(&config, TemperatureUnit::Fahrenheit, &state.tx));
+        // 100°C is 212°F, 13°F under the setpoint
+        broadcast_update(&state.tx, TemperatureUpdate { sensor_index: 1, temperature: 100.0, ..sample_update() });
+        for _ in 0..100 {
+            if state.pit_controller.as_ref().unwrap().status().output_percent.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         }
 
-        function updateChart(addr) {
-            const chart = charts[addr];
-            const data = deviceData[addr];
-            
-            chart.data.labels = data.timestamps.map(t => 
-                t.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })
-            );
-            chart.data.datasets[0].data = data.readings;
-            chart.update('none');
+        let status = get_json(&state, "/api/pit-controller").await;
+        assert_eq!(status["setpoint"], 225.0);
+        assert_eq!(status["unit"], "fahrenheit");
+        assert_eq!(status["pit_temp"], 212.0);
+        assert_eq!(status["output_percent"], 52.0);
+        assert_eq!(status["hook_error"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_delete_device() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        for minute in 0..3 {
+            let timestamp = Utc::now() - chrono::Duration::minutes(minute);
+            state.db.insert_reading(address, timestamp, 0, Temperature::from_celsius(60.0), None, None, -60).await.unwrap();
         }
+        let rule = state.db.insert_alert_rule(address, 0, AlertKind::BatteryBelow { percent: 20 }, 0.0, 0.0).await.unwrap();
+        let engine = Arc::new(AlertEngine::new(vec![rule]));
+        state.alerts = Some(engine.clone());
+        let delete = |uri: &str| Request::builder().method("DELETE").uri(uri).body(Body::empty()).unwrap();
+        let uri = format!("/api/devices/{}", address);
 
-        function updateTimestampAging(addr) {
-            const timestampEl = document.getElementById(`timestamp-${addr}`);
-            if (!timestampEl || !timestampEl.dataset.timestamp) return;
-            
-            const lastUpdate = new Date(timestampEl.dataset.timestamp);
-            const ageSeconds = (Date.now() - lastUpdate.getTime()) / 1000;
-            
-            // Remove all aging classes
-            timestampEl.classList.remove('aged', 'stale');
-            
-            // Add appropriate class based on age
-            if (ageSeconds > 60) {
-                timestampEl.classList.add('stale');
-            } else if (ageSeconds > 30) {
-                timestampEl.classList.add('aged');
+        let response = router(state.clone()).call(delete("/api/devices/11:22:33:44:55:66")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        state.db.start_cook(address, "Brisket", None).await.unwrap();
+        let response = router(state.clone()).call(delete(&uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT, "not while cooking");
+        state.db.end_cook(address).await.unwrap();
+
+        state.fanout.publish(TemperatureUpdate { device_address: address.to_string(), ..sample_update() });
+        let mut settings = state.fanout.subscribe_settings();
+        let response = router(state.clone()).call(delete(&uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["device_address"], address);
+        assert_eq!(body["deleted_readings"], 3);
+        assert_eq!(settings.try_recv().unwrap(), address, "dashboards reload their device list");
+
+        assert!(engine.rules().is_empty());
+        assert!(state.fanout.heartbeats(std::time::Duration::ZERO, TemperatureUnit::Celsius).is_empty(), "no heartbeats for it");
+        assert_eq!(get_json(&state, "/api/devices").await, serde_json::json!([]));
+        let response = router(state.clone()).call(Request::builder().uri(&uri).body(Body::empty()).unwrap()).await.unwrap();
+        assert!(!response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_delete_block_probe_keeps_block() {
+        let state = test_state().await;
+        let block = "AA:BB:CC:DD:EE:FF";
+        let probe = MeaterProtocol::block_probe_address(block, 2);
+        state.db.upsert_device(block, "MEATER Block", "MeaterBlock", "MEATER Block", 0).await.unwrap();
+        state.db.upsert_device(&probe, "MEATER Probe 2", "MeaterBlock", "MEATER Probe 2", 2).await.unwrap();
+
+        // The dashboard percent-encodes the address, so the `#` is not taken as a fragment
+        let uri = format!("/api/devices/{}", probe.replace(':', "%3A").replace('#', "%23"));
+        let request = Request::builder().method("DELETE").uri(uri).body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let devices = get_json(&state, "/api/devices").await;
+        let addresses: Vec<&str> = devices.as_array().unwrap().iter().map(|d| d["device_address"].as_str().unwrap()).collect();
+        assert_eq!(addresses, [block], "only the probe is removed");
+    }
+
+    #[tokio::test]
+    async fn test_history_and_export_filter_by_source() {
+        let state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        let now = Utc::now();
+        let temperature = Temperature::from_celsius(100.0);
+        state.db.insert_reading(address, now - chrono::Duration::minutes(2), 0, temperature, None, None, -60).await.unwrap();
+        state
+            .db
+            .insert_reading_from(ReadingSource::Cloud, address, now - chrono::Duration::minutes(1), 0, temperature, None, None, -60)
+            .await
+            .unwrap();
+        
+        let all = get_json(&state, &format!("/api/devices/{}/history", address)).await;
+        assert_eq!(all[0]["source"], "ble");
+        assert_eq!(all[1]["source"], "cloud");
+        let local = get_json(&state, &format!("/api/devices/{}/history?source=ble", address)).await;
+        assert_eq!(local.as_array().unwrap().len(), 1);
+        assert_eq!(local[0]["source"], "ble");
+        
+        let request = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request(format!("/api/devices/{}/history?source=usb", address))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let response = router(state.clone()).call(request(format!("/api/devices/{}/export.csv?source=cloud", address))).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", text);
+        assert!(lines[1].ends_with(",cloud"), "{}", lines[1]);
+    }
+
+    #[tokio::test]
+    async fn test_debug_endpoints_only_exist_in_developer_mode() {
+        let address = "AA:BB:CC:DD:EE:FF";
+        let uri = format!("/api/devices/{}/debug/write", address);
+        let body = format!(
+            r#"{{"service":"{}","characteristic":"{}","payload":"53 54 0a","timeout_ms":50}}"#,
+            crate::COMBUSTION_UART_SERVICE,
+            crate::COMBUSTION_UART_RX_CHAR
+        );
+        let post = |uri: &str, body: &str, token: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
             }
+            request.body(Body::from(body.to_string())).unwrap()
+        };
+
+        // Off: the routes do not exist, whatever the credentials
+        let mut state = test_state().await;
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().admin_token = Some("secret".to_string());
+        state.config = Arc::new(config.clone());
+        let (commands, mut monitor) = crate::ble_debug::debug_channel();
+        state.debug_commands = Some(commands);
+        for path in [uri.clone(), format!("/api/devices/{}/debug/read", address)] {
+            let response = router(state.clone()).call(post(&path, &body, Some("secret"))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
         }
 
-        function updateAllTimestamps() {
-            for (const addr in deviceData) {
-                updateTimestampAging(addr);
+        config.web.as_mut().unwrap().developer_mode = true;
+        state.config = Arc::new(config);
+        for token in [None, Some("wrong")] {
+            let response = router(state.clone()).call(post(&uri, &body, token)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let bad = body.replace("53 54 0a", "xyz");
+        let response = router(state.clone()).call(post(&uri, &bad, Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Stand-in for the monitor loop that owns the connection
+        tokio::spawn(async move {
+            while let Some(command) = monitor.recv().await {
+                assert_eq!(command.operation, DebugOperation::Write { payload: vec![0x53, 0x54, 0x0a], with_response: false });
+                let response = ble_debug::DebugResponse {
+                    value: None,
+                    notifications: vec![ble_debug::CapturedNotification {
+                        characteristic: crate::COMBUSTION_UART_TX_CHAR.to_string(),
+                        value: "6f6b0a".to_string(),
+                    }],
+                };
+                let _ = command.reply.send(Ok(response));
+            }
+        });
+        let response = router(state.clone()).call(post(&uri, &body, Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["notifications"][0]["value"], "6f6b0a");
+
+        state.debug_commands = None;
+        let response = router(state.clone()).call(post(&uri, &body, Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_premium_activation_swaps_license() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let validator = LicenseValidator::with_public_key(&signing_key.verifying_key().to_bytes()).unwrap();
+        let key = crate::generate_license_key(crate::PremiumTier::Premium, None, &signing_key).unwrap();
+        let mut state = test_state().await;
+        state.license = Arc::new(LicenseManager::with_license(License::free(), validator, state.db.clone()));
+        let activate_as = |key: &str, token: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/api/premium/activate")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
             }
+            request.body(Body::from(serde_json::json!({ "license_key": key }).to_string())).unwrap()
+        };
+        let activate = |key: &str| activate_as(key, Some("secret"));
+
+        // Admin only: off without a token, refused with a wrong one
+        let response = router(state.clone()).call(activate_as(&key, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let mut config = Config::default();
+        config.web.as_mut().unwrap().admin_token = Some("secret".to_string());
+        state.config = Arc::new(config);
+        let response = router(state.clone()).call(activate_as(&key, Some("wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(get_json(&state, "/api/premium/status").await["tier"], "Free");
+        
+        let response = router(state.clone()).call(activate("AAAA-BBBB")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get_json(&state, "/api/premium/status").await["tier"], "Free");
+        
+        let response = router(state.clone()).call(activate(&key)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let status = get_json(&state, "/api/premium/status").await;
+        assert_eq!(status["tier"], "Premium", "in effect without a restart");
+        assert_eq!(status["source"], "database");
+        let stored = state.db.get_setting(crate::license_store::LICENSE_SETTING).await.unwrap().unwrap();
+        assert_eq!(stored.value, key);
+    }
+    
+    #[tokio::test]
+    async fn test_device_analytics_requires_license() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        let now = Utc::now();
+        // Climbing 0.5°F per minute
+        for minute in 0..40 {
+            let timestamp = now - chrono::Duration::minutes(40 - minute);
+            let temperature = Temperature::from_fahrenheit(100.0 + minute as f32 * 0.5);
+            state.db.insert_reading(address, timestamp, 0, temperature, None, None, -60).await.unwrap();
         }
+        let uri = format!("/api/devices/{}/analytics", address);
 
-        // Update aging indicators every second
-        setInterval(updateAllTimestamps, 1000);
+        let request = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
-        // Load premium status on page load
-        loadPremiumStatus();
+        let premium = License {
+            tier: crate::PremiumTier::Premium,
+            features: crate::PremiumFeatures::premium(),
+            ..License::free()
+        };
+        state.license = Arc::new(LicenseManager::with_license(premium, LicenseValidator::new(), state.db.clone()));
+        let analytics = get_json(&state, &uri).await;
+        assert!((analytics["rate_per_hour"].as_f64().unwrap() - 30.0).abs() < 0.05, "{}", analytics);
+        assert_eq!(analytics["stalled"], false);
+        assert!(analytics["stall_started_at"].is_null());
+        assert_eq!(analytics["unit"], "fahrenheit");
 
-        // Start connection
-        connect();
-    </script>
-</body>
-</html>
-"#;
+        let eta = get_json(&state, &format!("/api/devices/{}/eta?target=203", address)).await;
+        assert_eq!(eta["status"], "estimated");
+        // 203 - 119.5 = 83.5°F at 30°F/h
+        let remaining = eta["seconds_remaining"].as_i64().unwrap();
+        assert!((remaining - 10_020).abs() < 120, "{}", eta);
+        assert!(eta["eta"].is_string());
+        assert!(eta["confidence"].as_f64().unwrap() > 0.9);
+
+        let eta = get_json(&state, &format!("/api/devices/{}/eta?target=110", address)).await;
+        assert_eq!(eta["status"], "reached");
+        assert_eq!(eta["seconds_remaining"], 0);
+        // Brisket coasts 5°F, so the countdown is to 198°F: 78.5°F at 30°F/h
+        let eta = get_json(&state, &format!("/api/devices/{}/eta?target=203&meat=brisket", address)).await;
+        assert!((eta["carryover"].as_f64().unwrap() - 5.0).abs() < 1e-3, "{}", eta);
+        assert!((eta["remove_at"].as_f64().unwrap() - 198.0).abs() < 1e-3, "{}", eta);
+        let remaining = eta["seconds_remaining"].as_i64().unwrap();
+        assert!((remaining - 9_420).abs() < 120, "{}", eta);
+        let eta = get_json(&state, &format!("/api/devices/{}/eta?target=203&meat=brisket&carryover=0", address)).await;
+        assert!((eta["remove_at"].as_f64().unwrap() - 203.0).abs() < 1e-3, "{}", eta);
+    }
+    
+    #[tokio::test]
+    async fn test_device_stats_gate_time_above_threshold() {
+        let mut state = test_state().await;
+        let address = "AA:BB:CC:DD:EE:FF";
+        state.db.upsert_device(address, "probe", "Unknown", "probe", 1).await.unwrap();
+        let now = Utc::now();
+        for (minutes_ago, internal, pit) in [(90, 150.0, 250.0), (60, 160.0, 275.0), (30, 170.0, 240.0)] {
+            let timestamp = now - chrono::Duration::minutes(minutes_ago);
+            let ambient = Some(Temperature::from_fahrenheit(pit));
+            state.db.insert_reading(address, timestamp, 0, Temperature::from_fahrenheit(internal), ambient, None, -60).await.unwrap();
+        }
+        state.db.insert_reading(address, now - chrono::Duration::hours(5), 0, Temperature::from_fahrenheit(40.0), None, None, -60).await.unwrap();
+
+        let stats = get_json(&state, &format!("/api/devices/{}/stats?hours=2", address)).await;
+        assert_eq!(stats["reading_count"], 3);
+        assert!((stats["min_temp"].as_f64().unwrap() - 150.0).abs() < 0.01, "{}", stats);
+        assert!((stats["max_ambient"].as_f64().unwrap() - 275.0).abs() < 0.01, "{}", stats);
+        assert!(stats["secs_above"].is_null());
+        assert_eq!(stats["unit"], "fahrenheit");
+
+        let uri = format!("/api/devices/{}/stats?hours=2&above=160", address);
+        let request = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+        let response = router(state.clone()).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let premium = License {
+            tier: crate::PremiumTier::Premium,
+            features: crate::PremiumFeatures::premium(),
+            ..License::free()
+        };
+        state.license = Arc::new(LicenseManager::with_license(premium, LicenseValidator::new(), state.db.clone()));
+        let stats = get_json(&state, &uri).await;
+        assert_eq!(stats["secs_above"], 1800, "{}", stats);
+        assert_eq!(stats["above"], 160.0);
+
+        let request = Request::builder().uri("/api/devices/00:00/stats").body(Body::empty()).unwrap();
+        assert!(!router(state).call(request).await.unwrap().status().is_success());
+    }
+}
@@ -0,0 +1,106 @@
+// xtask/src/main.rs
+//! Development tasks, run as `cargo xtask <task>`
+use anyhow::{bail, Context, Result};
+use sqlx::sqlite::SqlitePoolOptions;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[path = "../../src/migrations.rs"]
+#[allow(dead_code)]
+mod migrations;
+
+include!("../../src/schema_fingerprint.rs");
+
+const USAGE: &str = "Usage: cargo xtask prepare-sqlx
+
+  prepare-sqlx   Rebuild the sqlx query cache (.sqlx) against a database
+                 created by the current migrations";
+
+fn main() -> Result<()> {
+    match std::env::args().nth(1).as_deref() {
+        Some("prepare-sqlx") => prepare_sqlx(),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Repository root: the xtask crate lives one level below it
+fn project_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}
+
+/// Create a database with every migration applied, as `Database::new` would
+async fn create_schema(path: &Path) -> Result<()> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite:{}?mode=rwc", path.display()))
+        .await
+        .context("Failed to create schema database")?;
+    migrations::run_migrations(&pool, migrations::MIGRATIONS).await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// Bump the modification time of every `.rs` file under `dir`, so cargo recompiles them
+fn touch_sources(dir: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            touch_sources(&path)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            std::fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(std::time::SystemTime::now())
+                .with_context(|| format!("Failed to touch {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Regenerate `.sqlx` by compiling the crate with the query macros online
+///
+/// The macros write their metadata to `SQLX_OFFLINE_DIR` while checking each
+/// query against the fresh schema, so a query naming a column no migration
+/// creates fails here with the compiler error pointing at it.
+fn prepare_sqlx() -> Result<()> {
+    let root = project_root();
+    let cache = root.join(".sqlx");
+    let schema_dir = std::env::temp_dir().join(format!("bbq-monitor-sqlx-{}", std::process::id()));
+    std::fs::create_dir_all(&schema_dir)?;
+    let schema = schema_dir.join("schema.db");
+
+    tokio::runtime::Runtime::new()?.block_on(create_schema(&schema))?;
+
+    // Start over so queries that no longer exist leave no stale entries
+    std::fs::create_dir_all(&cache)?;
+    for entry in std::fs::read_dir(&cache)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    // Macros only expand when the crate is compiled again
+    touch_sources(&root.join("src"))?;
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let checked = Command::new(&cargo)
+        .current_dir(&root)
+        .args(["check", "--package", "bbq-monitor", "--all-targets"])
+        .env("DATABASE_URL", format!("sqlite:{}", schema.display()))
+        .env("SQLX_OFFLINE", "false")
+        .env("SQLX_OFFLINE_DIR", &cache)
+        .status()?;
+    std::fs::remove_dir_all(&schema_dir)?;
+    if !checked.success() {
+        bail!("Queries do not match the schema the migrations create");
+    }
+
+    let migrations = std::fs::read(root.join("src/migrations.rs"))?;
+    std::fs::write(root.join(SCHEMA_FINGERPRINT_FILE), format!("{}\n", schema_fingerprint(&migrations)))?;
+    let queries = std::fs::read_dir(&cache)?.count() - 1;
+    println!("Prepared {} queries in {}", queries, cache.display());
+    Ok(())
+}